@@ -0,0 +1,140 @@
+//! A jar with a [`SourceFile`] input for each file read from the file system,
+//! and the glue to keep those inputs up to date when the files change.
+//!
+//! Add [`Jar`] to your database, implement [`Db`] by returning a [`Files`]
+//! stored in the database, read files with [`source_file`] and, when the
+//! watcher reports changes, call [`Db::apply_fs_changes`].
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use dashmap::{mapref::entry::Entry, DashMap};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use salsa::Durability;
+
+pub use notify;
+
+#[salsa::jar(db = Db)]
+pub struct Jar(SourceFile);
+
+/// The database of a program reading its files through this jar.
+pub trait Db: salsa::DbWithJar<Jar> {
+    /// The files read so far.
+    fn files(&self) -> &Files;
+
+    /// Updates the contents of the files affected by `events` (as reported by the
+    /// watcher) by reading them again. Events for files that were never read with
+    /// [`source_file`] are ignored, and so are the files whose contents are unchanged,
+    /// so that no new revision is created for them.
+    fn apply_fs_changes(&mut self, events: impl IntoIterator<Item = Event>)
+    where
+        Self: Sized,
+    {
+        let changes: Vec<_> = events
+            .into_iter()
+            .filter(|event| !matches!(event.kind, EventKind::Access(_)))
+            .flat_map(|event| event.paths)
+            .filter_map(|path| {
+                let files = self.files();
+                let file = *files.files.get(&path)?;
+                Some((file, read(&path), (files.durability)(&path)))
+            })
+            .collect();
+
+        let db: &mut dyn Db = self;
+        for (file, contents, durability) in changes {
+            file.set_contents(db)
+                .with_durability(durability)
+                .to_if_changed(contents);
+        }
+    }
+}
+
+/// A file read from the file system.
+#[salsa::input]
+pub struct SourceFile {
+    #[id]
+    #[return_ref]
+    pub path: PathBuf,
+
+    /// `None` if the file does not exist or could not be read.
+    #[return_ref]
+    pub contents: Option<String>,
+}
+
+/// The [`SourceFile`] inputs created so far, indexed by path, along with the
+/// watcher to notify of new files.
+pub struct Files {
+    files: DashMap<PathBuf, SourceFile>,
+    watcher: Option<Mutex<Box<dyn Watcher + Send>>>,
+    durability: Box<dyn Fn(&Path) -> Durability + Send + Sync>,
+}
+
+impl Default for Files {
+    fn default() -> Self {
+        Self {
+            files: DashMap::new(),
+            watcher: None,
+            durability: Box::new(|_| Durability::LOW),
+        }
+    }
+}
+
+impl Files {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Watches each file with `watcher` when it is first read. The events it reports
+    /// are to be given to [`Db::apply_fs_changes`].
+    pub fn with_watcher(mut self, watcher: impl Watcher + Send + 'static) -> Self {
+        self.watcher = Some(Mutex::new(Box::new(watcher)));
+        self
+    }
+
+    /// Sets the durability of the contents of each file, e.g. `Durability::HIGH`
+    /// for the files of libraries, which rarely change. By default, it is
+    /// `Durability::LOW` for all files.
+    pub fn with_durability(
+        mut self,
+        durability: impl Fn(&Path) -> Durability + Send + Sync + 'static,
+    ) -> Self {
+        self.durability = Box::new(durability);
+        self
+    }
+}
+
+/// Returns the input for the file at `path`, reading it (and starting to watch it)
+/// the first time it is requested.
+///
+/// The path is used as given to match the paths of the watcher events, so it
+/// should be absolute (and canonical, if the watcher reports canonical paths).
+pub fn source_file(db: &dyn Db, path: impl Into<PathBuf>) -> SourceFile {
+    let files = db.files();
+    let path = path.into();
+    match files.files.entry(path.clone()) {
+        Entry::Occupied(entry) => *entry.get(),
+        Entry::Vacant(entry) => {
+            // Start watching before reading the contents, so that no change
+            // is missed in between. The file may not exist, in which case
+            // it can't be watched: it is then read as `None`.
+            if let Some(watcher) = &files.watcher {
+                let _ = watcher
+                    .lock()
+                    .unwrap()
+                    .watch(&path, RecursiveMode::NonRecursive);
+            }
+            let contents = read(&path);
+            let durability = (files.durability)(&path);
+            *entry.insert(
+                SourceFile::builder(path, contents)
+                    .durability(durability)
+                    .new(db),
+            )
+        }
+    }
+}
+
+fn read(path: &Path) -> Option<String> {
+    std::fs::read_to_string(path).ok()
+}
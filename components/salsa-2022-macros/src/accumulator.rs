@@ -2,6 +2,9 @@ use syn::ItemStruct;
 
 // #[salsa::accumulator(jar = Jar0)]
 // struct Accumulator(DataType);
+//
+// #[salsa::accumulator(jar = Jar0, extends = Accumulator)]
+// struct ChildAccumulator(ChildDataType);
 
 pub(crate) fn accumulator(
     args: proc_macro::TokenStream,
@@ -21,10 +24,14 @@ struct Accumulator;
 impl crate::options::AllowedOptions for Accumulator {
     const RETURN_REF: bool = false;
 
+    const RETURN_DEREF: bool = false;
+
     const SPECIFY: bool = false;
 
     const NO_EQ: bool = false;
 
+    const COMPARE_WITH: bool = false;
+
     const SINGLETON: bool = false;
 
     const JAR: bool = true;
@@ -38,6 +45,44 @@ impl crate::options::AllowedOptions for Accumulator {
     const LRU: bool = false;
 
     const CONSTRUCTOR_NAME: bool = false;
+
+    const INCLUDES: bool = false;
+
+    const CYCLE_ERR: bool = false;
+
+    const RETRY_ERR: bool = false;
+
+    const UPDATE: bool = false;
+
+    const STORAGE: bool = false;
+
+    const VOLATILE: bool = false;
+
+    const HEAP_SIZE: bool = false;
+
+    const INSTANCES: bool = false;
+
+    const HISTORY: bool = false;
+
+    const GC: bool = false;
+
+    const WEAK: bool = false;
+
+    const FIREWALL: bool = false;
+
+    const CAPACITY: bool = false;
+
+    const EXTENDS: bool = true;
+
+    const COLLECT: bool = false;
+
+    const REMOTE_CACHE: bool = false;
+
+    const ELEMENTS: bool = false;
+
+    const CYCLE_INITIAL: bool = false;
+
+    const NO_CLONE: bool = false;
 }
 
 fn accumulator_contents(
@@ -94,16 +139,39 @@ fn struct_item_out(
 
 fn inherent_impl(args: &Args, struct_ty: &syn::Type, data_ty: &syn::Type) -> syn::ItemImpl {
     let jar_ty = args.jar_ty();
+
+    // The values pushed to an accumulator are also pushed to its parent, converted with `Into`.
+    let (parent_bound, push_to_parent) = match &args.extends {
+        Some(parent) => (
+            quote! { + salsa::storage::HasJar<<#parent as salsa::accumulator::Accumulator>::Jar> },
+            quote! { <#parent>::push(db, <#data_ty as Into<_>>::into(data.clone())); },
+        ),
+        None => (quote! {}, quote! {}),
+    };
+
     parse_quote! {
         impl #struct_ty {
             pub fn push<DB: ?Sized>(db: &DB, data: #data_ty)
             where
-                DB: salsa::storage::HasJar<#jar_ty>,
+                DB: salsa::storage::HasJar<#jar_ty> #parent_bound,
             {
+                #push_to_parent
                 let (jar, runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(db);
                 let ingredients = <#jar_ty as salsa::storage::HasIngredientsFor< #struct_ty >>::ingredient(jar);
                 ingredients.push(runtime, data)
             }
+
+            /// Returns the values accumulated while computing the tracked functions
+            /// that were called for `entity`, and everything they depend on.
+            pub fn accumulated_by<DB: ?Sized, E>(db: &DB, entity: E) -> Vec<#data_ty>
+            where
+                DB: salsa::storage::HasJar<#jar_ty> + salsa::storage::HasJarsDyn,
+                E: salsa::AsId + 'static,
+            {
+                let (jar, _) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(db);
+                let ingredients = <#jar_ty as salsa::storage::HasIngredientsFor< #struct_ty >>::ingredient(jar);
+                ingredients.accumulated_by(db, entity)
+            }
         }
     }
 }
@@ -136,6 +204,10 @@ fn ingredients_for_impl(
                     );
                     salsa::accumulator::AccumulatorIngredient::new(index, #debug_name)
             }
+
+            fn fork_ingredients(ingredients: &Self::Ingredients) -> Option<Self::Ingredients> {
+                ingredients.fork()
+            }
         }
     }
 }
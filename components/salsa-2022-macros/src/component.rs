@@ -0,0 +1,306 @@
+use proc_macro2::TokenStream;
+use syn::spanned::Spanned;
+
+use crate::tracked_fn::{self, FnArgs};
+
+// #[salsa::component(jar = Jar0)]
+// trait Analysis {
+//     #[salsa::tracked]
+//     fn score(self, db: &dyn Db, weight: u32) -> u32 {
+//         ...
+//     }
+// }
+//
+// Each tracked method becomes a tracked function named `Analysis_score`, to be listed
+// in the jar, whose memos are keyed by the type implementing the trait, `self` and the
+// other arguments. The default body moves into a hidden method, `__salsa_score`, which
+// the tracked function calls for the right type through a function pointer.
+
+pub(crate) fn component(
+    args: proc_macro::TokenStream,
+    input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let args = syn::parse_macro_input!(args as Args);
+    let item_trait = syn::parse_macro_input!(input as syn::ItemTrait);
+    component_contents(&args, item_trait)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+type Args = crate::options::Options<Component>;
+
+struct Component;
+
+impl crate::options::AllowedOptions for Component {
+    const RETURN_REF: bool = false;
+
+    const RETURN_DEREF: bool = false;
+
+    const SPECIFY: bool = false;
+
+    const NO_EQ: bool = false;
+
+    const COMPARE_WITH: bool = false;
+
+    const SINGLETON: bool = false;
+
+    const JAR: bool = true;
+
+    const DATA: bool = false;
+
+    const DB: bool = false;
+
+    const RECOVERY_FN: bool = false;
+
+    const LRU: bool = false;
+
+    const CONSTRUCTOR_NAME: bool = false;
+
+    const INCLUDES: bool = false;
+
+    const CYCLE_ERR: bool = false;
+
+    const RETRY_ERR: bool = false;
+
+    const UPDATE: bool = false;
+
+    const STORAGE: bool = false;
+
+    const VOLATILE: bool = false;
+
+    const HEAP_SIZE: bool = false;
+
+    const INSTANCES: bool = false;
+
+    const HISTORY: bool = false;
+
+    const GC: bool = false;
+
+    const WEAK: bool = false;
+
+    const FIREWALL: bool = false;
+
+    const CAPACITY: bool = false;
+
+    const EXTENDS: bool = false;
+
+    const COLLECT: bool = false;
+
+    const REMOTE_CACHE: bool = false;
+
+    const ELEMENTS: bool = false;
+
+    const CYCLE_INITIAL: bool = false;
+
+    const NO_CLONE: bool = false;
+}
+
+fn component_contents(args: &Args, mut item_trait: syn::ItemTrait) -> syn::Result<TokenStream> {
+    let mut hidden_methods = vec![];
+    let mut fn_items = vec![];
+    for item in &mut item_trait.items {
+        let item_method = match item {
+            syn::TraitItem::Method(item_method) => item_method,
+            _ => continue,
+        };
+        let Some(position) = item_method
+            .attrs
+            .iter()
+            .position(tracked_fn::is_salsa_tracked)
+        else {
+            continue;
+        };
+        let salsa_tracked_attr = item_method.attrs.remove(position);
+        let mut method_args = if salsa_tracked_attr.tokens.is_empty() {
+            FnArgs::default()
+        } else {
+            salsa_tracked_attr.parse_args()?
+        };
+        method_args.jar_ty = method_args.jar_ty.or_else(|| args.jar_ty.clone());
+
+        let (hidden_method, fn_item) = component_method(
+            &method_args,
+            &item_trait.ident,
+            &item_trait.vis,
+            item_method,
+        )?;
+        hidden_methods.push(syn::TraitItem::Method(hidden_method));
+        fn_items.push(fn_item);
+    }
+    item_trait.items.extend(hidden_methods);
+
+    Ok(quote! {
+        #item_trait
+
+        #(#fn_items)*
+    })
+}
+
+/// Turns the tracked method `item_method` of the trait `trait_name` into a method
+/// calling the tracked function generated for it. Returns the hidden method holding
+/// the original body and the tracked function.
+fn component_method(
+    args: &FnArgs,
+    trait_name: &syn::Ident,
+    vis: &syn::Visibility,
+    item_method: &mut syn::TraitItemMethod,
+) -> syn::Result<(syn::TraitItemMethod, TokenStream)> {
+    if let Some(specify) = &args.specify {
+        return Err(syn::Error::new(
+            specify.span(),
+            "the `specify` option is not supported on the methods of components",
+        ));
+    }
+    if let Some(instances) = &args.instances {
+        return Err(syn::Error::new(
+            instances.span(),
+            "the `for` option is not supported on the methods of components",
+        ));
+    }
+    if let Some(collect) = &args.collect {
+        return Err(syn::Error::new(
+            collect.span(),
+            "the `collect` option is not supported on the methods of components",
+        ));
+    }
+
+    let sig = &item_method.sig;
+    let method_name = &sig.ident;
+    if !sig.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            sig.generics.span(),
+            "the tracked methods of components cannot be generic",
+        ));
+    }
+    let Some(block) = item_method.default.take() else {
+        return Err(syn::Error::new(
+            method_name.span(),
+            "the tracked methods of components must have a default body",
+        ));
+    };
+
+    let mut inputs = sig.inputs.iter();
+    match inputs.next() {
+        Some(syn::FnArg::Receiver(receiver)) if receiver.reference.is_none() => {}
+        _ => {
+            return Err(syn::Error::new(
+                sig.span(),
+                "the tracked methods of components must have self and a database argument",
+            ))
+        }
+    }
+    let mut args_names = vec![];
+    let mut args_tys = vec![];
+    for input in inputs {
+        let syn::FnArg::Typed(pat_ty) = input else {
+            unreachable!("only the first argument can be a receiver")
+        };
+        match &*pat_ty.pat {
+            syn::Pat::Ident(ident) => args_names.push(ident.ident.clone()),
+            _ => {
+                return Err(syn::Error::new(
+                    pat_ty.pat.span(),
+                    "all arguments must be given names",
+                ))
+            }
+        }
+        args_tys.push((*pat_ty.ty).clone());
+    }
+    let (db_var, db_ty) = match (args_names.first(), args_tys.first()) {
+        (Some(db_var), Some(syn::Type::Reference(db_ty))) if db_ty.lifetime.is_none() => {
+            (db_var.clone(), db_ty.elem.clone())
+        }
+        _ => {
+            return Err(syn::Error::new(
+                sig.span(),
+                "the second argument of the tracked methods of components must be `&dyn Db`",
+            ))
+        }
+    };
+    let arg_names = &args_names[1..];
+    let arg_tys = &args_tys[1..];
+    let value_ty = crate::configuration::value_ty(sig);
+
+    // The original body, which the tracked function calls for the implementing type.
+    let hidden_name = syn::Ident::new(&format!("__salsa_{}", method_name), method_name.span());
+    let mut hidden_sig = sig.clone();
+    hidden_sig.ident = hidden_name.clone();
+    hidden_sig
+        .generics
+        .make_where_clause()
+        .predicates
+        .push(parse_quote! { Self: Sized + salsa::AsId + 'static });
+    let hidden_method: syn::TraitItemMethod = parse_quote! {
+        #[doc(hidden)]
+        #hidden_sig #block
+    };
+
+    // The tracked function, whose first two arguments identify `self`.
+    let fn_name = syn::Ident::new(
+        &format!("{}_{}", trait_name, method_name),
+        method_name.span(),
+    );
+    let execute_ty: syn::Type = parse_quote! {
+        for<'__execute_db> fn(&'__execute_db #db_ty, salsa::Id, #(#arg_tys),*) -> #value_ty
+    };
+    let mut item_fn: syn::ItemFn = parse_quote! {
+        #[allow(non_snake_case)]
+        #vis fn #fn_name(
+            #db_var: &#db_ty,
+            __salsa_method: salsa::component::Method<#execute_ty>,
+            __salsa_self: salsa::Id,
+            #(#arg_names: #arg_tys,)*
+        ) -> #value_ty {
+            (__salsa_method.execute())(#db_var, __salsa_self, #(#arg_names,)*)
+        }
+    };
+    let (config_ty, fn_struct) = tracked_fn::fn_struct(args, &item_fn)?;
+    *item_fn.block =
+        tracked_fn::getter_fn(args, &mut item_fn.sig, item_fn.block.span(), &config_ty)?;
+
+    // The method itself calls the tracked function, and returns what it returns.
+    let method = &mut item_method.sig;
+    tracked_fn::make_getter_sig(args, method)?;
+    for input in &mut method.inputs {
+        if let syn::FnArg::Typed(pat_ty) = input {
+            if let syn::Pat::Ident(ident) = &mut *pat_ty.pat {
+                ident.mutability = None;
+            }
+        }
+    }
+    method
+        .generics
+        .make_where_clause()
+        .predicates
+        .push(parse_quote! { Self: Sized + salsa::AsId + 'static });
+    item_method.default = Some(parse_quote! {
+        {
+            #fn_name(
+                #db_var,
+                salsa::component::Method::<#execute_ty>::of::<Self>(
+                    |#db_var: &#db_ty, __salsa_self: salsa::Id, #(#arg_names: #arg_tys,)*| {
+                        <Self as #trait_name>::#hidden_name(
+                            salsa::AsId::from_id(__salsa_self),
+                            #db_var,
+                            #(#arg_names,)*
+                        )
+                    },
+                ),
+                salsa::AsId::as_id(self),
+                #(#arg_names,)*
+            )
+        }
+    });
+
+    Ok((
+        hidden_method,
+        quote! {
+            #fn_struct
+
+            // we generate a `'db` lifetime that clippy
+            // sometimes doesn't like
+            #[allow(clippy::needless_lifetimes)]
+            #item_fn
+        },
+    ))
+}
@@ -1,12 +1,22 @@
+use syn::spanned::Spanned;
+
 pub(crate) struct Configuration {
     pub(crate) jar_ty: syn::Type,
     pub(crate) salsa_struct_ty: syn::Type,
     pub(crate) key_ty: syn::Type,
     pub(crate) value_ty: syn::Type,
+    pub(crate) storage_ty: syn::Type,
     pub(crate) cycle_strategy: CycleRecoveryStrategy,
     pub(crate) backdate_fn: syn::ImplItemMethod,
     pub(crate) execute_fn: syn::ImplItemMethod,
     pub(crate) recover_fn: syn::ImplItemMethod,
+    pub(crate) cycle_initial_fn: Option<syn::ImplItemMethod>,
+    pub(crate) update_fn: Option<syn::ImplItemMethod>,
+    pub(crate) heap_size_fn: Option<syn::ImplItemMethod>,
+    pub(crate) value_to_any_fn: Option<syn::ImplItemMethod>,
+    pub(crate) weak: bool,
+    pub(crate) firewall: bool,
+    pub(crate) no_eq: bool,
 }
 
 impl Configuration {
@@ -16,21 +26,54 @@ impl Configuration {
             salsa_struct_ty,
             key_ty,
             value_ty,
+            storage_ty,
             cycle_strategy,
             backdate_fn,
             execute_fn,
             recover_fn,
+            cycle_initial_fn,
+            update_fn,
+            heap_size_fn,
+            value_to_any_fn,
+            weak,
+            firewall,
+            no_eq,
         } = self;
+        let update_const = update_fn
+            .as_ref()
+            .map(|_| quote! { const UPDATE: bool = true; });
+        let heap_size_const = heap_size_fn
+            .as_ref()
+            .map(|_| quote! { const HEAP_SIZE: bool = true; });
+        let fetch_dyn_const = value_to_any_fn
+            .as_ref()
+            .map(|_| quote! { const FETCH_DYN: bool = true; });
+        let weak_const = weak.then(|| quote! { const WEAK: bool = true; });
+        let firewall_const = firewall.then(|| quote! { const FIREWALL: bool = true; });
+        let no_eq_const = no_eq.then(|| quote! { const NO_EQ: bool = true; });
+        let clone_value_fn = clone_value_fn(value_ty);
         parse_quote! {
             impl salsa::function::Configuration for #self_ty {
                 type Jar = #jar_ty;
                 type SalsaStruct = #salsa_struct_ty;
                 type Key = #key_ty;
                 type Value = #value_ty;
+                type Storage = #storage_ty;
                 const CYCLE_STRATEGY: salsa::cycle::CycleRecoveryStrategy = #cycle_strategy;
                 #backdate_fn
                 #execute_fn
                 #recover_fn
+                #cycle_initial_fn
+                #update_const
+                #update_fn
+                #heap_size_const
+                #heap_size_fn
+                #fetch_dyn_const
+                #value_to_any_fn
+                #clone_value_fn
+                #weak_const
+                #firewall_const
+                #no_eq_const
             }
         }
     }
@@ -39,6 +82,7 @@ impl Configuration {
 pub(crate) enum CycleRecoveryStrategy {
     Panic,
     Fallback,
+    FixedPoint,
 }
 
 impl quote::ToTokens for CycleRecoveryStrategy {
@@ -50,17 +94,38 @@ impl quote::ToTokens for CycleRecoveryStrategy {
             CycleRecoveryStrategy::Fallback => {
                 tokens.extend(quote! {salsa::cycle::CycleRecoveryStrategy::Fallback})
             }
+            CycleRecoveryStrategy::FixedPoint => {
+                tokens.extend(quote! {salsa::cycle::CycleRecoveryStrategy::FixedPoint})
+            }
+        }
+    }
+}
+
+/// Returns the memo storage backend selected by the `storage` option
+/// (`hash` if it is not given).
+pub(crate) fn storage_ty(storage: Option<&syn::Ident>) -> syn::Type {
+    match storage {
+        Some(ident) if ident == "dense" => {
+            parse_quote!(salsa::function::DenseMemoStorage<Self::Key, Self::Value>)
         }
+        _ => parse_quote!(salsa::function::HashMemoStorage<Self::Key, Self::Value>),
     }
 }
 
 /// Returns an appropriate definition for `should_backdate_value` depending on
 /// whether this value is memoized or not.
-pub(crate) fn should_backdate_value_fn(should_backdate: bool) -> syn::ImplItemMethod {
+pub(crate) fn should_backdate_value_fn(
+    should_backdate: bool,
+    value_ty: &syn::Type,
+) -> syn::ImplItemMethod {
     if should_backdate {
+        // Spanned so that a value type that is not `Eq` is reported on the type itself.
+        let should_backdate_value = quote_spanned! {value_ty.span()=>
+            salsa::function::should_backdate_value
+        };
         parse_quote! {
             fn should_backdate_value(v1: &Self::Value, v2: &Self::Value) -> bool {
-                salsa::function::should_backdate_value(v1, v2)
+                #should_backdate_value(v1, v2)
             }
         }
     } else {
@@ -72,6 +137,85 @@ pub(crate) fn should_backdate_value_fn(should_backdate: bool) -> syn::ImplItemMe
     }
 }
 
+/// Returns a definition for `should_backdate_value` that defers to the
+/// user-supplied `compare_with` function.
+pub(crate) fn compare_with_value_fn(compare_with: &syn::Path) -> syn::ImplItemMethod {
+    parse_quote! {
+        fn should_backdate_value(v1: &Self::Value, v2: &Self::Value) -> bool {
+            #compare_with(v1, v2)
+        }
+    }
+}
+
+/// Wraps a definition of `should_backdate_value` so that `Err` values are
+/// never backdated, for tracked functions with the `retry_err` option.
+pub(crate) fn never_backdate_err_fn(backdate_fn: syn::ImplItemMethod) -> syn::ImplItemMethod {
+    let block = &backdate_fn.block;
+    parse_quote! {
+        fn should_backdate_value(v1: &Self::Value, v2: &Self::Value) -> bool {
+            let _ = v1;
+            v2.is_ok() && #block
+        }
+    }
+}
+
+/// Returns a definition for `update_value` that defers to the value's
+/// `salsa::Update` impl, for tracked functions with the `update` option.
+pub(crate) fn update_value_fn() -> syn::ImplItemMethod {
+    parse_quote! {
+        fn update_value(old_value: &mut Self::Value, new_value: Self::Value) -> bool {
+            salsa::update::Update::maybe_update(old_value, new_value)
+        }
+    }
+}
+
+/// Returns a definition for `heap_size` that defers to the value's
+/// `salsa::HeapSize` impl, for tracked functions with the `heap_size` option.
+pub(crate) fn heap_size_fn() -> syn::ImplItemMethod {
+    parse_quote! {
+        fn heap_size(value: &Self::Value) -> usize {
+            salsa::memory::HeapSize::heap_size(value)
+        }
+    }
+}
+
+/// Returns a definition for `value_to_any` that clones the value, for tracked
+/// functions whose values are returned by cloning them.
+pub(crate) fn value_to_any_fn(value_ty: &syn::Type) -> syn::ImplItemMethod {
+    let clone = quote_spanned! {value_ty.span()=> <Self::Value as Clone>::clone };
+    parse_quote! {
+        fn value_to_any(value: &Self::Value) -> Box<dyn std::any::Any> {
+            Box::new(#clone(value))
+        }
+    }
+}
+
+/// Returns a definition for `clone_value` that clones the value if its type
+/// implements `Clone` (see `salsa::input_field::helper::CloneValue`).
+pub(crate) fn clone_value_fn(value_ty: &syn::Type) -> syn::ImplItemMethod {
+    parse_quote! {
+        fn clone_value(value: &Self::Value) -> Option<Self::Value> {
+            #[allow(unused_imports)]
+            use salsa::input_field::helper::NoCloneValue;
+            salsa::input_field::helper::CloneValue::<#value_ty>::clone_value().map(|clone| clone(value))
+        }
+    }
+}
+
+/// Returns an appropriate definition for `recover_from_cycle` for cases where
+/// the cycle is returned as an error, for tracked functions with the `cycle_err` option.
+pub(crate) fn err_cycle_recovery_fn() -> syn::ImplItemMethod {
+    parse_quote! {
+        fn recover_from_cycle(
+            _db: &salsa::function::DynDb<Self>,
+            cycle: &salsa::Cycle,
+            _key: Self::Key,
+        ) -> Self::Value {
+            Err(std::convert::From::from(cycle.clone()))
+        }
+    }
+}
+
 /// Returns an appropriate definition for `recover_from_cycle` for cases where
 /// the cycle recovery is panic.
 pub(crate) fn panic_cycle_recovery_fn() -> syn::ImplItemMethod {
@@ -1,12 +1,17 @@
-use proc_macro2::Literal;
+use proc_macro2::{Literal, TokenStream};
 use syn::{spanned::Spanned, Token};
 
+use crate::jar_chain::{self, Target};
+
 // Source:
 //
 // #[salsa::db(Jar0, Jar1, Jar2)]
 // pub struct Database {
 //    storage: salsa::Storage<Self>,
 // }
+//
+// The `parallel` and `default` flags can be listed along with the jars
+// to generate the `ParallelDatabase` and `Default` impls.
 
 pub(crate) fn db(
     args: proc_macro::TokenStream,
@@ -30,27 +35,61 @@ pub(crate) fn db(
     let as_salsa_database_impl = as_salsa_database_impl(&input);
     let has_jars_impl = has_jars_impl(&args, &input, &storage);
     let has_jars_dyn_impl = has_jars_dyn_impl(&input, &storage);
-    let per_jar_impls = per_jar_impls(&args, &input, &storage);
+    let has_jar_impls = has_jar_impls(&args, &input, &storage);
+    let parallel_database_impl = args
+        .parallel
+        .is_some()
+        .then(|| parallel_database_impl(&input, &storage));
+    let default_impl = args.default.is_some().then(|| default_impl(&input));
 
     quote! {
         #input
         #as_salsa_database_impl
         #has_jars_impl
         #has_jars_dyn_impl
-        #(#per_jar_impls)*
+        #has_jar_impls
+        #parallel_database_impl
+        #default_impl
     }
     .into()
 }
 
 pub struct Args {
     jar_paths: syn::punctuated::Punctuated<syn::Path, Token![,]>,
+
+    /// The `parallel` flag, to generate the `ParallelDatabase` impl.
+    parallel: Option<syn::Ident>,
+
+    /// The `default` flag, to generate the `Default` impl.
+    default: Option<syn::Ident>,
 }
 
 impl syn::parse::Parse for Args {
     fn parse(input: syn::parse::ParseStream<'_>) -> syn::Result<Self> {
-        Ok(Self {
-            jar_paths: syn::punctuated::Punctuated::parse_terminated(input)?,
-        })
+        let paths = syn::punctuated::Punctuated::<syn::Path, Token![,]>::parse_terminated(input)?;
+        let mut args = Self {
+            jar_paths: Default::default(),
+            parallel: None,
+            default: None,
+        };
+        for path in paths {
+            let flag = if path.is_ident("parallel") {
+                &mut args.parallel
+            } else if path.is_ident("default") {
+                &mut args.default
+            } else {
+                args.jar_paths.push(path);
+                continue;
+            };
+            let ident = path.get_ident().unwrap().clone();
+            if let Some(old) = flag.replace(ident) {
+                return Err(syn::Error::new(
+                    old.span(),
+                    format!("option `{}` provided twice", old),
+                ));
+            }
+        }
+        Ok(args)
     }
 }
 
@@ -71,6 +110,48 @@ fn find_storage_field(input: &syn::ItemStruct) -> Result<syn::Ident, &'static st
     Err("database has no field named `storage`")
 }
 
+/// Generates a `ParallelDatabase` impl whose snapshots clone all the fields but the storage.
+fn parallel_database_impl(input: &syn::ItemStruct, storage: &syn::Ident) -> syn::ItemImpl {
+    let db = &input.ident;
+    // Spanned at the field type, so that a field that does not implement `Clone` is reported there.
+    let other_fields = input.fields.iter().filter_map(|field| {
+        let ident = field.ident.as_ref().filter(|ident| *ident != storage)?;
+        Some(quote_spanned! {field.ty.span()=>
+            #ident: std::clone::Clone::clone(&self.#ident),
+        })
+    });
+    parse_quote! {
+        impl salsa::ParallelDatabase for #db {
+            fn snapshot(&self) -> salsa::Snapshot<Self> {
+                salsa::Snapshot::new(#db {
+                    #storage: self.#storage.snapshot(),
+                    #(#other_fields)*
+                })
+            }
+        }
+    }
+}
+
+/// Generates a `Default` impl that creates each field with its `Default` impl.
+fn default_impl(input: &syn::ItemStruct) -> syn::ItemImpl {
+    let db = &input.ident;
+    let fields = input.fields.iter().filter_map(|field| {
+        let ident = field.ident.as_ref()?;
+        Some(quote_spanned! {field.ty.span()=>
+            #ident: std::default::Default::default(),
+        })
+    });
+    parse_quote! {
+        impl std::default::Default for #db {
+            fn default() -> Self {
+                #db {
+                    #(#fields)*
+                }
+            }
+        }
+    }
+}
+
 fn as_salsa_database_impl(input: &syn::ItemStruct) -> syn::ItemImpl {
     let db = &input.ident;
     parse_quote! {
@@ -112,13 +193,21 @@ fn has_jars_impl(args: &Args, input: &syn::ItemStruct, storage: &syn::Ident) ->
                         #(
                             unsafe {
                                 let place = std::ptr::addr_of_mut!((*jars).#jar_field_names);
-                                <#jar_paths as salsa::jar::Jar>::init_jar(place, routes);
+                                salsa::jar::init_jar::<Self, #jar_paths>(place, routes);
                             }
                         )*
                     })
                 }
             }
             // ANCHOR_END: create_jars
+
+            fn fork_jars(jars: &Self::Jars) -> Option<Box<Self::Jars>> {
+                Some(Box::new((
+                    #(
+                        <#jar_paths as salsa::jar::JarInDb<Self>>::fork_jar(&jars.#jar_field_names)?,
+                    )*
+                )))
+            }
         }
     }
 }
@@ -179,55 +268,98 @@ fn has_jars_dyn_impl(input: &syn::ItemStruct, storage: &syn::Ident) -> syn::Item
                 let ingredient = self.#storage.ingredient(index.ingredient_index());
                 ingredient.fmt_index(index.key_index(), fmt)
             }
+
+            fn ingredient_debug_name(&self, ingredient: salsa::IngredientIndex) -> &'static str {
+                self.#storage.ingredient(ingredient).debug_name()
+            }
+
+            fn ingredient_metrics(&self) -> Vec<salsa::metrics::QueryMetrics> {
+                self.#storage.query_metrics()
+            }
+
+            fn ingredient_memory(&self) -> salsa::memory::MemoryReport {
+                self.#storage.memory_report()
+            }
+
+            fn dump_jar(&self, jar: &'static str) -> salsa::dump::JarDump {
+                self.#storage.dump_jar(jar)
+            }
+
+            fn dyn_query_names(&self) -> Vec<String> {
+                self.#storage.query_names()
+            }
+
+            fn dyn_fetch(&self, name: &str, key: salsa::Id) -> Option<Box<dyn std::any::Any>> {
+                self.#storage.fetch_dyn(self, name, key)
+            }
+
+            fn dyn_stable_key(&self, index: salsa::key::DependencyIndex) -> salsa::key::StableKey {
+                self.#storage.stable_key(index)
+            }
+
+            fn dyn_resolve_stable_key(&self, key: &salsa::key::StableKey) -> Option<salsa::key::DependencyIndex> {
+                self.#storage.resolve_stable_key(key)
+            }
+
+            fn revert_inputs(&mut self, revision: salsa::Revision) -> Result<(), salsa::history::RevertError> {
+                self.#storage.revert_to(revision)
+            }
+
+            fn memos_changed_since(&self, revision: salsa::Revision) -> Vec<salsa::DatabaseKeyIndex> {
+                self.#storage.changed_since(revision)
+            }
+
+            fn pipeline_writes(&mut self, enabled: bool) {
+                self.#storage.set_pipelined_writes(enabled)
+            }
+
+            fn queries_keyed_by(&self, key_type: std::any::TypeId, key: salsa::Id) -> Vec<salsa::DatabaseKeyIndex> {
+                self.#storage.queries_keyed_by(key_type, key)
+            }
+
+            fn entity_creator(&self, entity: salsa::DatabaseKeyIndex) -> Option<salsa::DatabaseKeyIndex> {
+                self.#storage.creator_of(entity)
+            }
+
+            fn created_entities(&self, query: salsa::DatabaseKeyIndex) -> Vec<salsa::DatabaseKeyIndex> {
+                self.#storage.entities_created_by(query)
+            }
+
+            fn add_subscription(
+                &self,
+                refresh: Box<dyn FnMut(&Self) + Send>,
+            ) -> salsa::subscription::SubscriptionId {
+                self.#storage.add_subscription(refresh)
+            }
+
+            fn remove_subscription(&self, id: salsa::subscription::SubscriptionId) {
+                self.#storage.remove_subscription(id)
+            }
+
+            fn run_subscriptions(&self, only_stale: bool) {
+                if only_stale {
+                    self.#storage.refresh_stale_subscriptions(self)
+                } else {
+                    self.#storage.refresh_subscriptions(self)
+                }
+            }
         }
     }
 }
 
-fn per_jar_impls(args: &Args, input: &syn::ItemStruct, storage: &syn::Ident) -> Vec<syn::ItemImpl> {
-    let db = &input.ident;
-    args.jar_paths
-        .iter()
-        .zip(0..)
-        .flat_map(|(jar_path, jar_index)| {
-            let jar_index = Literal::u32_unsuffixed(jar_index);
-            vec![
-                parse_quote! {
-                    impl salsa::storage::DbWithJar<#jar_path> for #db {
-                        fn as_jar_db<'db>(&'db self) -> &'db <#jar_path as salsa::jar::Jar<'db>>::DynDb
-                        where
-                            'db: 'db,
-                        {
-                            self as &'db <#jar_path as salsa::jar::Jar<'db>>::DynDb
-                        }
-                    }
-                },
-
-                parse_quote! {
-                    impl salsa::storage::HasJar<#jar_path> for #db {
-                        fn jar(&self) -> (&#jar_path, &salsa::Runtime) {
-                            let (__jars, __runtime) = self.#storage.jars();
-                            (&__jars.#jar_index, __runtime)
-                        }
-
-                        fn jar_mut(&mut self) -> (&mut #jar_path, &mut salsa::Runtime) {
-                            let (__jars, __runtime) = self.#storage.jars_mut();
-                            (&mut __jars.#jar_index, __runtime)
-                        }
-                    }
-                },
-
-                parse_quote! {
-                    impl salsa::storage::JarFromJars<#jar_path> for #db {
-                        fn jar_from_jars<'db>(jars: &Self::Jars) -> &#jar_path {
-                            &jars.#jar_index
-                        }
-
-                        fn jar_from_jars_mut<'db>(jars: &mut Self::Jars) -> &mut #jar_path {
-                            &mut jars.#jar_index
-                        }
-                    }
-                }
-            ]
-        })
-        .collect()
+/// Implements the traits that give access to each jar of the database: the jars that
+/// it lists and, recursively, the jars that they include. The latter can't be named
+/// here, so this invokes the hidden macros of the listed jars, which list them
+/// (see `jar_chain`).
+fn has_jar_impls(args: &Args, input: &syn::ItemStruct, storage: &syn::Ident) -> TokenStream {
+    let jar_paths: Vec<syn::Path> = args.jar_paths.iter().cloned().collect();
+    if jar_paths.is_empty() {
+        return TokenStream::new();
+    }
+    let target = Target::Db {
+        db: input.ident.clone(),
+        storage: storage.clone(),
+        jar_paths: jar_paths.clone(),
+    };
+    jar_chain::start(&target, &jar_paths)
 }
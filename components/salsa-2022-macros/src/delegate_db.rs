@@ -0,0 +1,291 @@
+use proc_macro2::{Literal, TokenStream};
+use syn::{parse::Parser, punctuated::Punctuated, spanned::Spanned, Token};
+
+// Source:
+//
+// #[salsa::delegate_db(crate::Db, other::Db)]
+// pub struct RequestDb<'a> {
+//    db: &'a Database,
+//    request: RequestId,
+// }
+//
+// The first field is the wrapped database: the database itself, a reference
+// to it, or a `Snapshot` of it. The listed traits are the `Db` traits of the
+// jars, which are implemented without any method.
+
+pub(crate) fn delegate_db(
+    args: proc_macro::TokenStream,
+    input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let db_traits = match Punctuated::<syn::Path, Token![,]>::parse_terminated.parse(args) {
+        Ok(db_traits) => db_traits,
+        Err(err) => return err.into_compile_error().into(),
+    };
+    let input = syn::parse_macro_input!(input as syn::ItemStruct);
+    match delegate_db_contents(&db_traits, &input) {
+        Ok(impls) => quote! {
+            #input
+            #impls
+        },
+        Err(err) => {
+            let err = err.into_compile_error();
+            quote! {
+                #input
+                #err
+            }
+        }
+    }
+    .into()
+}
+
+/// How the wrapper holds the database.
+struct Wrapped {
+    /// The type of the database.
+    db_ty: syn::Type,
+
+    /// An expression giving `&db_ty`.
+    db: TokenStream,
+
+    /// An expression giving `&mut db_ty`, unless the database is shared.
+    db_mut: Option<TokenStream>,
+}
+
+fn wrapped(input: &syn::ItemStruct) -> syn::Result<Wrapped> {
+    let Some(field) = input.fields.iter().next() else {
+        return Err(syn::Error::new(
+            input.ident.span(),
+            "the first field of a `delegate_db` struct must be the wrapped database",
+        ));
+    };
+    let member = match &field.ident {
+        Some(ident) => quote!(#ident),
+        None => {
+            let index = syn::Index::from(0);
+            quote!(#index)
+        }
+    };
+    let wrapped = match &field.ty {
+        syn::Type::Reference(reference) => Wrapped {
+            db_ty: (*reference.elem).clone(),
+            db: quote!(&*self.#member),
+            db_mut: reference.mutability.map(|_| quote!(&mut *self.#member)),
+        },
+        syn::Type::Path(path) if path.qself.is_none() => {
+            let last = path.path.segments.last().unwrap();
+            match &last.arguments {
+                syn::PathArguments::AngleBracketed(args) if last.ident == "Snapshot" => {
+                    let Some(syn::GenericArgument::Type(db_ty)) = args.args.first() else {
+                        return Err(syn::Error::new(
+                            field.ty.span(),
+                            "expected a snapshot of a database",
+                        ));
+                    };
+                    Wrapped {
+                        db_ty: db_ty.clone(),
+                        db: quote!(&*self.#member),
+                        db_mut: None,
+                    }
+                }
+                _ => Wrapped {
+                    db_ty: field.ty.clone(),
+                    db: quote!(&self.#member),
+                    db_mut: Some(quote!(&mut self.#member)),
+                },
+            }
+        }
+        _ => {
+            return Err(syn::Error::new(
+                field.ty.span(),
+                "expected a database, a reference to a database, or a snapshot of a database",
+            ))
+        }
+    };
+    Ok(wrapped)
+}
+
+fn delegate_db_contents(
+    db_traits: &Punctuated<syn::Path, Token![,]>,
+    input: &syn::ItemStruct,
+) -> syn::Result<TokenStream> {
+    let wrapped = wrapped(input)?;
+    let Wrapped { db_ty, db, db_mut } = &wrapped;
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    // A shared database cannot be written through the wrapper.
+    let shared_panic = Literal::string(&format!(
+        "`{ident}` wraps a shared database, which cannot be written through it"
+    ));
+    let (runtime_mut, revert_inputs, pipeline_writes, jar_mut) = match db_mut {
+        Some(db_mut) => (
+            quote! { salsa::storage::HasJarsDyn::runtime_mut(#db_mut) },
+            quote! { salsa::storage::HasJarsDyn::revert_inputs(#db_mut, revision) },
+            quote! { salsa::storage::HasJarsDyn::pipeline_writes(#db_mut, enabled) },
+            quote! { <#db_ty as salsa::storage::HasJar<J>>::jar_mut(#db_mut) },
+        ),
+        None => (
+            quote! { panic!(#shared_panic) },
+            quote! { let _ = revision; panic!(#shared_panic) },
+            quote! { let _ = enabled; panic!(#shared_panic) },
+            quote! { panic!(#shared_panic) },
+        ),
+    };
+    let subscription_panic = Literal::string(&format!(
+        "cannot subscribe through `{ident}`: subscribe with the wrapped database"
+    ));
+
+    let mut jar_generics = input.generics.clone();
+    jar_generics.params.push(parse_quote!(J: 'static));
+    let (jar_impl_generics, _, _) = jar_generics.split_for_impl();
+    let mut jar_where_clause = input.generics.clone().make_where_clause().clone();
+    jar_where_clause
+        .predicates
+        .push(parse_quote!(#db_ty: salsa::storage::HasJar<J>));
+
+    let db_trait_impls = db_traits.iter().map(|db_trait| {
+        quote_spanned! {db_trait.span()=>
+            impl #impl_generics #db_trait for #ident #ty_generics #where_clause {}
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics salsa::database::AsSalsaDatabase for #ident #ty_generics #where_clause {
+            fn as_salsa_database(&self) -> &dyn salsa::Database {
+                self
+            }
+        }
+
+        impl #impl_generics salsa::storage::HasJarsDyn for #ident #ty_generics #where_clause {
+            fn runtime(&self) -> &salsa::Runtime {
+                salsa::storage::HasJarsDyn::runtime(#db)
+            }
+
+            fn runtime_mut(&mut self) -> &mut salsa::Runtime {
+                #runtime_mut
+            }
+
+            fn maybe_changed_after(
+                &self,
+                input: salsa::key::DependencyIndex,
+                revision: salsa::Revision,
+            ) -> bool {
+                salsa::storage::HasJarsDyn::maybe_changed_after(#db, input, revision)
+            }
+
+            fn cycle_recovery_strategy(
+                &self,
+                ingredient_index: salsa::IngredientIndex,
+            ) -> salsa::cycle::CycleRecoveryStrategy {
+                salsa::storage::HasJarsDyn::cycle_recovery_strategy(#db, ingredient_index)
+            }
+
+            fn origin(
+                &self,
+                index: salsa::DatabaseKeyIndex,
+            ) -> Option<salsa::runtime::local_state::QueryOrigin> {
+                salsa::storage::HasJarsDyn::origin(#db, index)
+            }
+
+            fn mark_validated_output(&self, executor: salsa::DatabaseKeyIndex, output: salsa::key::DependencyIndex) {
+                salsa::storage::HasJarsDyn::mark_validated_output(#db, executor, output)
+            }
+
+            fn remove_stale_output(&self, executor: salsa::DatabaseKeyIndex, stale_output: salsa::key::DependencyIndex) {
+                salsa::storage::HasJarsDyn::remove_stale_output(#db, executor, stale_output)
+            }
+
+            fn salsa_struct_deleted(&self, ingredient: salsa::IngredientIndex, id: salsa::Id) {
+                salsa::storage::HasJarsDyn::salsa_struct_deleted(#db, ingredient, id)
+            }
+
+            fn fmt_index(&self, index: salsa::key::DependencyIndex, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                salsa::storage::HasJarsDyn::fmt_index(#db, index, fmt)
+            }
+
+            fn ingredient_debug_name(&self, ingredient: salsa::IngredientIndex) -> &'static str {
+                salsa::storage::HasJarsDyn::ingredient_debug_name(#db, ingredient)
+            }
+
+            fn ingredient_metrics(&self) -> Vec<salsa::metrics::QueryMetrics> {
+                salsa::storage::HasJarsDyn::ingredient_metrics(#db)
+            }
+
+            fn ingredient_memory(&self) -> salsa::memory::MemoryReport {
+                salsa::storage::HasJarsDyn::ingredient_memory(#db)
+            }
+
+            fn dump_jar(&self, jar: &'static str) -> salsa::dump::JarDump {
+                salsa::storage::HasJarsDyn::dump_jar(#db, jar)
+            }
+
+            fn dyn_query_names(&self) -> Vec<String> {
+                salsa::storage::HasJarsDyn::dyn_query_names(#db)
+            }
+
+            fn dyn_fetch(&self, name: &str, key: salsa::Id) -> Option<Box<dyn std::any::Any>> {
+                salsa::storage::HasJarsDyn::dyn_fetch(#db, name, key)
+            }
+
+            fn dyn_stable_key(&self, index: salsa::key::DependencyIndex) -> salsa::key::StableKey {
+                salsa::storage::HasJarsDyn::dyn_stable_key(#db, index)
+            }
+
+            fn dyn_resolve_stable_key(&self, key: &salsa::key::StableKey) -> Option<salsa::key::DependencyIndex> {
+                salsa::storage::HasJarsDyn::dyn_resolve_stable_key(#db, key)
+            }
+
+            fn revert_inputs(&mut self, revision: salsa::Revision) -> Result<(), salsa::history::RevertError> {
+                #revert_inputs
+            }
+
+            fn memos_changed_since(&self, revision: salsa::Revision) -> Vec<salsa::DatabaseKeyIndex> {
+                salsa::storage::HasJarsDyn::memos_changed_since(#db, revision)
+            }
+
+            fn pipeline_writes(&mut self, enabled: bool) {
+                #pipeline_writes
+            }
+
+            fn queries_keyed_by(&self, key_type: std::any::TypeId, key: salsa::Id) -> Vec<salsa::DatabaseKeyIndex> {
+                salsa::storage::HasJarsDyn::queries_keyed_by(#db, key_type, key)
+            }
+
+            fn entity_creator(&self, entity: salsa::DatabaseKeyIndex) -> Option<salsa::DatabaseKeyIndex> {
+                salsa::storage::HasJarsDyn::entity_creator(#db, entity)
+            }
+
+            fn created_entities(&self, query: salsa::DatabaseKeyIndex) -> Vec<salsa::DatabaseKeyIndex> {
+                salsa::storage::HasJarsDyn::created_entities(#db, query)
+            }
+
+            fn add_subscription(
+                &self,
+                _refresh: Box<dyn FnMut(&Self) + Send>,
+            ) -> salsa::subscription::SubscriptionId {
+                panic!(#subscription_panic)
+            }
+
+            fn remove_subscription(&self, id: salsa::subscription::SubscriptionId) {
+                salsa::storage::HasJarsDyn::remove_subscription(#db, id)
+            }
+
+            fn run_subscriptions(&self, only_stale: bool) {
+                salsa::storage::HasJarsDyn::run_subscriptions(#db, only_stale)
+            }
+        }
+
+        impl #jar_impl_generics salsa::storage::HasJar<J> for #ident #ty_generics #jar_where_clause {
+            fn jar(&self) -> (&J, &salsa::Runtime) {
+                <#db_ty as salsa::storage::HasJar<J>>::jar(#db)
+            }
+
+            fn jar_mut(&mut self) -> (&mut J, &mut salsa::Runtime) {
+                #jar_mut
+            }
+        }
+
+        impl #jar_impl_generics salsa::storage::DbWithJar<J> for #ident #ty_generics #jar_where_clause {}
+
+        #(#db_trait_impls)*
+    })
+}
@@ -0,0 +1,99 @@
+//! Implementation of `#[derive(salsa::HeapSize)]`, which sums the heap memory
+//! of each field (or, for enums, of each field of the current variant).
+
+use proc_macro2::TokenStream;
+use syn::spanned::Spanned;
+
+pub(crate) fn heap_size(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+    match heap_size_impl(input) {
+        Ok(s) => s.into(),
+        Err(err) => err.into_compile_error().into(),
+    }
+}
+
+fn heap_size_impl(input: syn::DeriveInput) -> syn::Result<TokenStream> {
+    let ident = &input.ident;
+    let body = match &input.data {
+        syn::Data::Struct(data) => {
+            let (pattern, sizes) = fields_heap_size(&data.fields);
+            quote! {
+                let #ident #pattern = self;
+                0 #(+ #sizes)*
+            }
+        }
+        syn::Data::Enum(data) => {
+            let arms = data.variants.iter().map(|variant| {
+                let variant_ident = &variant.ident;
+                let (pattern, sizes) = fields_heap_size(&variant.fields);
+                quote! {
+                    #ident::#variant_ident #pattern => 0 #(+ #sizes)*,
+                }
+            });
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+        syn::Data::Union(data) => {
+            return Err(syn::Error::new(
+                data.union_token.span(),
+                "`HeapSize` cannot be derived for unions",
+            ))
+        }
+    };
+
+    let field_tys = match &input.data {
+        syn::Data::Struct(data) => data.fields.iter().map(|f| &f.ty).collect(),
+        syn::Data::Enum(data) => data
+            .variants
+            .iter()
+            .flat_map(|v| v.fields.iter().map(|f| &f.ty))
+            .collect(),
+        syn::Data::Union(_) => vec![],
+    };
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let mut where_clause = where_clause
+        .cloned()
+        .unwrap_or_else(|| parse_quote! { where });
+    for ty in field_tys {
+        where_clause
+            .predicates
+            .push(parse_quote! { #ty: salsa::memory::HeapSize });
+    }
+
+    Ok(quote! {
+        impl #impl_generics salsa::memory::HeapSize for #ident #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn heap_size(&self) -> usize {
+                #body
+            }
+        }
+    })
+}
+
+/// Returns the pattern binding the fields, along with
+/// the expressions computing the heap size of each field.
+fn fields_heap_size(fields: &syn::Fields) -> (TokenStream, Vec<TokenStream>) {
+    let names: Vec<_> = (0..fields.len())
+        .map(|i| syn::Ident::new(&format!("__field{}", i), proc_macro2::Span::call_site()))
+        .collect();
+    let sizes = names
+        .iter()
+        .zip(fields)
+        .map(|(name, field)| {
+            let ty = &field.ty;
+            quote! { <#ty as salsa::memory::HeapSize>::heap_size(#name) }
+        })
+        .collect();
+    let pattern = match fields {
+        syn::Fields::Named(_) => {
+            let field_names: Vec<_> = fields.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+            quote! { { #(#field_names: #names),* } }
+        }
+        syn::Fields::Unnamed(_) => quote! { ( #(#names),* ) },
+        syn::Fields::Unit => quote! {},
+    };
+    (pattern, sizes)
+}
@@ -31,22 +31,64 @@ impl std::ops::Deref for InputStruct {
 impl crate::options::AllowedOptions for InputStruct {
     const RETURN_REF: bool = false;
 
+    const RETURN_DEREF: bool = false;
+
     const SPECIFY: bool = false;
 
     const NO_EQ: bool = false;
+
+    const COMPARE_WITH: bool = false;
     const SINGLETON: bool = true;
 
     const JAR: bool = true;
 
     const DATA: bool = true;
 
-    const DB: bool = false;
+    const DB: bool = true;
 
     const RECOVERY_FN: bool = false;
 
     const LRU: bool = false;
 
     const CONSTRUCTOR_NAME: bool = true;
+
+    const INCLUDES: bool = false;
+
+    const CYCLE_ERR: bool = false;
+
+    const RETRY_ERR: bool = false;
+
+    const UPDATE: bool = false;
+
+    const STORAGE: bool = false;
+
+    const VOLATILE: bool = false;
+
+    const HEAP_SIZE: bool = false;
+
+    const INSTANCES: bool = false;
+
+    const HISTORY: bool = true;
+
+    const GC: bool = false;
+
+    const WEAK: bool = false;
+
+    const FIREWALL: bool = false;
+
+    const CAPACITY: bool = false;
+
+    const EXTENDS: bool = false;
+
+    const COLLECT: bool = false;
+
+    const REMOTE_CACHE: bool = false;
+
+    const ELEMENTS: bool = false;
+
+    const CYCLE_INITIAL: bool = false;
+
+    const NO_CLONE: bool = false;
 }
 
 impl InputStruct {
@@ -55,14 +97,21 @@ impl InputStruct {
         let inherent_impl = self.input_inherent_impl();
         let ingredients_for_impl = self.input_ingredients();
         let as_id_impl = self.as_id_impl();
+        let update_impl = self.update_impl();
+        let heap_size_impl = self.heap_size_impl();
         let salsa_struct_in_db_impl = self.salsa_struct_in_db_impl();
         let as_debug_with_db_impl = self.as_debug_with_db_impl();
+        let (builder_struct, builder_impl) = self.input_builder();
 
         Ok(quote! {
             #id_struct
             #inherent_impl
+            #builder_struct
+            #builder_impl
             #ingredients_for_impl
             #as_id_impl
+            #update_impl
+            #heap_size_impl
             #as_debug_with_db_impl
             #salsa_struct_in_db_impl
         })
@@ -105,6 +154,7 @@ impl InputStruct {
         .collect();
 
         // setters
+        let db_dyn_ty_db = self.db_dyn_ty_with_lifetime(&parse_quote!('db));
         let set_field_names = self.all_set_field_names();
         let field_setters: Vec<syn::ImplItemMethod> = field_indices.iter()
             .zip(&set_field_names)
@@ -113,11 +163,17 @@ impl InputStruct {
             .filter_map(|(((field_index, &set_field_name), field_vis), field_ty)| {
                 let set_field_name = set_field_name?;
                 Some(parse_quote! {
-                    #field_vis fn #set_field_name<'db>(self, __db: &'db mut #db_dyn_ty) -> salsa::setter::Setter<'db, #ident, #field_ty>
+                    #field_vis fn #set_field_name<'db>(self, __db: &'db mut #db_dyn_ty_db) -> salsa::setter::Setter<'db, #db_dyn_ty_db, #ident, #field_ty>
                     {
-                        let (__jar, __runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar_mut(__db);
-                        let __ingredients = <#jar_ty as salsa::storage::HasIngredientsFor< #ident >>::ingredient_mut(__jar);
-                        salsa::setter::Setter::new(__runtime, self, &mut __ingredients.#field_index)
+                        salsa::setter::Setter::new(__db, self, |__db| {
+                            let (__jar, _) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(__db);
+                            let __ingredients = <#jar_ty as salsa::storage::HasIngredientsFor< #ident >>::ingredient(__jar);
+                            &__ingredients.#field_index
+                        }, |__db| {
+                            let (__jar, __runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar_mut(__db);
+                            let __ingredients = <#jar_ty as salsa::storage::HasIngredientsFor< #ident >>::ingredient_mut(__jar);
+                            (&mut __ingredients.#field_index, __runtime)
+                        })
                     }
                 })
         })
@@ -126,6 +182,30 @@ impl InputStruct {
         let constructor_name = self.constructor_name();
         let singleton = self.0.is_isingleton();
 
+        let builder_ident = self.builder_ident();
+        let (required_names, required_tys): (Vec<_>, Vec<_>) = self
+            .all_fields()
+            .filter(|field| !field.has_default_attr)
+            .map(|field| (field.name(), field.ty()))
+            .unzip();
+        let default_names: Vec<_> = self
+            .all_fields()
+            .filter(|field| field.has_default_attr)
+            .map(SalsaField::name)
+            .collect();
+        let builder: syn::ImplItemMethod = parse_quote! {
+            /// Creates a builder for a new input; fields marked `#[default]`
+            /// can be given with its methods, the others are required here.
+            pub fn builder(#(#required_names: #required_tys,)*) -> #builder_ident
+            {
+                #builder_ident {
+                    #(#required_names,)*
+                    #(#default_names: Default::default(),)*
+                    __durability: salsa::Durability::LOW,
+                }
+            }
+        };
+
         let constructor: syn::ImplItemMethod = if singleton {
             parse_quote! {
                 /// Creates a new singleton input
@@ -182,6 +262,8 @@ impl InputStruct {
                 impl #ident {
                     #constructor
 
+                    #builder
+
                     #get
 
                     #try_get
@@ -192,10 +274,87 @@ impl InputStruct {
                 }
             }
         } else {
+            let all: syn::ImplItemMethod = parse_quote! {
+                /// Returns all the inputs, in the order they were created. The calling
+                /// query depends on the set of inputs, so it is executed again after an
+                /// input is created.
+                pub fn all(__db: &#db_dyn_ty) -> Vec<Self> {
+                    let (__jar, __runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(__db);
+                    let __ingredients = <#jar_ty as salsa::storage::HasIngredientsFor< #ident >>::ingredient(__jar);
+                    __ingredients.#input_index.entries(__runtime).collect()
+                }
+            };
+
+            let len: syn::ImplItemMethod = parse_quote! {
+                /// Returns how many inputs were created, with the same dependency as `all`.
+                pub fn len(__db: &#db_dyn_ty) -> usize {
+                    let (__jar, __runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(__db);
+                    let __ingredients = <#jar_ty as salsa::storage::HasIngredientsFor< #ident >>::ingredient(__jar);
+                    __ingredients.#input_index.len(__runtime)
+                }
+            };
+
+            let remove: syn::ImplItemMethod = parse_quote! {
+                /// Removes this input, in a new revision: the queries that read its fields are
+                /// executed again, the memos of the tracked functions keyed by it are deleted,
+                /// and reading its fields panics. Its id may be reused, with another generation.
+                ///
+                /// Panics if the input was already removed.
+                pub fn remove(self, __db: &mut #db_dyn_ty) {
+                    let (__jar, __runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar_mut(__db);
+                    let __ingredients = <#jar_ty as salsa::storage::HasIngredientsFor< #ident >>::ingredient_mut(__jar);
+                    #(
+                        __ingredients.#field_indices.remove(__runtime, self);
+                    )*
+
+                    let (__jar, _) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(__db);
+                    let __ingredients = <#jar_ty as salsa::storage::HasIngredientsFor< #ident >>::ingredient(__jar);
+                    __ingredients.#input_index.remove(salsa::database::AsSalsaDatabase::as_salsa_database(__db), self);
+                }
+            };
+
+            let retain: syn::ImplItemMethod = parse_quote! {
+                /// Removes (as `remove` does) all the inputs for which `keep` returns false,
+                /// in a single new revision.
+                pub fn retain(__db: &mut #db_dyn_ty, mut keep: impl FnMut(&#db_dyn_ty, Self) -> bool) {
+                    let __removed: Vec<Self> = Self::all(__db)
+                        .into_iter()
+                        .filter(|&__input| !keep(__db, __input))
+                        .collect();
+                    if __removed.is_empty() {
+                        return;
+                    }
+
+                    let (__jar, __runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar_mut(__db);
+                    let __ingredients = <#jar_ty as salsa::storage::HasIngredientsFor< #ident >>::ingredient_mut(__jar);
+                    for &__input in &__removed {
+                        #(
+                            __ingredients.#field_indices.remove(__runtime, __input);
+                        )*
+                    }
+
+                    let (__jar, _) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(__db);
+                    let __ingredients = <#jar_ty as salsa::storage::HasIngredientsFor< #ident >>::ingredient(__jar);
+                    for __input in __removed {
+                        __ingredients.#input_index.remove(salsa::database::AsSalsaDatabase::as_salsa_database(__db), __input);
+                    }
+                }
+            };
+
             parse_quote! {
                 impl #ident {
                     #constructor
 
+                    #builder
+
+                    #all
+
+                    #len
+
+                    #remove
+
+                    #retain
+
                     #(#field_getters)*
 
                     #(#field_setters)*
@@ -206,6 +365,84 @@ impl InputStruct {
         // }
     }
 
+    /// The name of the builder struct for this input, e.g. `FooBuilder`.
+    fn builder_ident(&self) -> syn::Ident {
+        let ident = self.id_ident();
+        syn::Ident::new(&format!("{}Builder", ident), ident.span())
+    }
+
+    /// Generate the builder struct returned by `Foo::builder`, which stores the
+    /// field values (and the durability) until the input is created by its `new` method.
+    fn input_builder(&self) -> (syn::ItemStruct, syn::ItemImpl) {
+        let ident = self.id_ident();
+        let vis = self.visibility();
+        let jar_ty = self.jar_ty();
+        let db_dyn_ty = self.db_dyn_ty();
+        let input_index = self.input_index();
+        let builder_ident = self.builder_ident();
+
+        let field_indices = self.all_field_indices();
+        let field_names = self.all_field_names();
+        let field_tys = self.all_field_tys();
+
+        let default_field_methods: Vec<syn::ImplItemMethod> = self
+            .all_fields()
+            .filter(|field| field.has_default_attr)
+            .map(|field| {
+                let field_vis = field.vis();
+                let field_name = field.name();
+                let field_ty = field.ty();
+                parse_quote! {
+                    #field_vis fn #field_name(mut self, #field_name: #field_ty) -> Self {
+                        self.#field_name = #field_name;
+                        self
+                    }
+                }
+            })
+            .collect();
+
+        let new_input: syn::Expr = if self.0.is_isingleton() {
+            parse_quote!(__ingredients.#input_index.new_singleton_input(__runtime))
+        } else {
+            parse_quote!(__ingredients.#input_index.new_input(__runtime))
+        };
+
+        let builder_struct = parse_quote! {
+            #[must_use]
+            #vis struct #builder_ident {
+                #(#field_names: #field_tys,)*
+                __durability: salsa::Durability,
+            }
+        };
+
+        let builder_impl = parse_quote! {
+            impl #builder_ident {
+                #(#default_field_methods)*
+
+                /// Sets the durability of all the fields.
+                pub fn durability(mut self, durability: salsa::Durability) -> Self {
+                    self.__durability = durability;
+                    self
+                }
+
+                /// Creates the input.
+                #[allow(clippy::new_ret_no_self)]
+                pub fn new(self, __db: &#db_dyn_ty) -> #ident
+                {
+                    let (__jar, __runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(__db);
+                    let __ingredients = <#jar_ty as salsa::storage::HasIngredientsFor< #ident >>::ingredient(__jar);
+                    let __id = #new_input;
+                    #(
+                        __ingredients.#field_indices.store_new(__runtime, __id, self.#field_names, self.__durability);
+                    )*
+                    __id
+                }
+            }
+        };
+
+        (builder_struct, builder_impl)
+    }
+
     /// Generate the `IngredientsFor` impl for this entity.
     ///
     /// The entity's ingredients include both the main entity ingredient along with a
@@ -219,6 +456,10 @@ impl InputStruct {
         let input_index: Literal = self.input_index();
         let debug_name_struct = literal(self.id_ident());
         let debug_name_fields: Vec<_> = self.all_field_names().into_iter().map(literal).collect();
+        let keep_history: TokenStream = self
+            .history()
+            .map(|revisions| quote! { ingredient.keep_history(#revisions); })
+            .unwrap_or_default();
 
         parse_quote! {
             impl salsa::storage::IngredientsFor for #ident {
@@ -239,7 +480,8 @@ impl InputStruct {
                     (
                         #(
                             {
-                                let index = routes.push(
+                                let index = routes.push_field(
+                                    #debug_name_struct,
                                     |jars| {
                                         let jar = <DB as salsa::storage::JarFromJars<Self::Jar>>::jar_from_jars(jars);
                                         let ingredients = <_ as salsa::storage::HasIngredientsFor<Self>>::ingredient(jar);
@@ -251,7 +493,19 @@ impl InputStruct {
                                         &mut ingredients.#all_field_indices
                                     },
                                 );
-                                salsa::input_field::InputFieldIngredient::new(index, #debug_name_fields)
+                                let mut ingredient = salsa::input_field::InputFieldIngredient::new(index, #debug_name_fields);
+                                ingredient.set_clone_value({
+                                    #[allow(unused_imports)]
+                                    use salsa::input_field::helper::NoCloneValue;
+                                    salsa::input_field::helper::CloneValue::<#field_ty>::clone_value()
+                                });
+                                ingredient.set_debug_value({
+                                    #[allow(unused_imports)]
+                                    use salsa::input_field::helper::NoDebugValue;
+                                    salsa::input_field::helper::DebugValue::<#field_ty>::debug_value()
+                                });
+                                #keep_history
+                                ingredient
                             },
                         )*
                         {
@@ -271,6 +525,15 @@ impl InputStruct {
                         },
                     )
                 }
+
+                fn fork_ingredients(ingredients: &Self::Ingredients) -> Option<Self::Ingredients> {
+                    Some((
+                        #(
+                            ingredients.#all_field_indices.fork()?,
+                        )*
+                        ingredients.#input_index.fork()?,
+                    ))
+                }
             }
         }
     }
@@ -306,14 +569,16 @@ impl InputStruct {
     fn salsa_struct_in_db_impl(&self) -> syn::ItemImpl {
         let ident = self.id_ident();
         let jar_ty = self.jar_ty();
+        let input_index = self.input_index();
         parse_quote! {
             impl<DB> salsa::salsa_struct::SalsaStructInDb<DB> for #ident
             where
                 DB: ?Sized + salsa::DbWithJar<#jar_ty>,
             {
-                fn register_dependent_fn(_db: &DB, _index: salsa::routes::IngredientIndex) {
-                    // Do nothing here, at least for now.
-                    // If/when we add ability to delete inputs, this would become relevant.
+                fn register_dependent_fn(db: &DB, index: salsa::routes::IngredientIndex) {
+                    let (jar, _) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(db);
+                    let ingredients = <#jar_ty as salsa::storage::HasIngredientsFor<#ident>>::ingredient(jar);
+                    ingredients.#input_index.register_dependent_fn(index)
                 }
             }
         }
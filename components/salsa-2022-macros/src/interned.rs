@@ -1,5 +1,6 @@
 use crate::salsa_struct::{SalsaStruct, SalsaStructKind};
-use proc_macro2::TokenStream;
+use heck::ToSnakeCase;
+use proc_macro2::{Ident, TokenStream};
 
 // #[salsa::interned(jar = Jar0, data = TyData0)]
 // #[derive(Eq, PartialEq, Hash, Debug, Clone)]
@@ -8,14 +9,24 @@ use proc_macro2::TokenStream;
 //    #[id(ref)] field2: Type2,
 //    ...
 // }
+//
+// #[salsa::interned(jar = Jar0, data = TyData0)]
+// enum Ty0 {
+//    Variant1,
+//    Variant2(Ty0, Type2),
+//    Variant3 { field1: Vec<Ty0> },
+// }
 
 pub(crate) fn interned(
     args: proc_macro::TokenStream,
     input: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
-    match SalsaStruct::new(SalsaStructKind::Interned, args, input)
-        .and_then(|el| InternedStruct(el).generate_interned())
-    {
+    let result = match syn::parse::<syn::ItemEnum>(input.clone()) {
+        Ok(enum_item) => InternedEnum::new(args, enum_item).and_then(|e| e.generate_interned()),
+        Err(_) => SalsaStruct::new(SalsaStructKind::Interned, args, input)
+            .and_then(|el| InternedStruct(el).generate_interned()),
+    };
+    match result {
         Ok(s) => s.into(),
         Err(err) => err.into_compile_error().into(),
     }
@@ -34,23 +45,65 @@ impl std::ops::Deref for InternedStruct {
 impl crate::options::AllowedOptions for InternedStruct {
     const RETURN_REF: bool = false;
 
+    const RETURN_DEREF: bool = false;
+
     const SPECIFY: bool = false;
 
     const NO_EQ: bool = false;
 
+    const COMPARE_WITH: bool = false;
+
     const SINGLETON: bool = false;
 
     const JAR: bool = true;
 
     const DATA: bool = true;
 
-    const DB: bool = false;
+    const DB: bool = true;
 
     const RECOVERY_FN: bool = false;
 
     const LRU: bool = false;
 
     const CONSTRUCTOR_NAME: bool = true;
+
+    const INCLUDES: bool = false;
+
+    const CYCLE_ERR: bool = false;
+
+    const RETRY_ERR: bool = false;
+
+    const UPDATE: bool = false;
+
+    const STORAGE: bool = false;
+
+    const VOLATILE: bool = false;
+
+    const HEAP_SIZE: bool = false;
+
+    const INSTANCES: bool = false;
+
+    const HISTORY: bool = false;
+
+    const GC: bool = true;
+
+    const WEAK: bool = false;
+
+    const FIREWALL: bool = false;
+
+    const CAPACITY: bool = false;
+
+    const EXTENDS: bool = false;
+
+    const COLLECT: bool = false;
+
+    const REMOTE_CACHE: bool = false;
+
+    const ELEMENTS: bool = false;
+
+    const CYCLE_INITIAL: bool = false;
+
+    const NO_CLONE: bool = false;
 }
 
 impl InternedStruct {
@@ -58,8 +111,10 @@ impl InternedStruct {
         self.validate_interned()?;
         let id_struct = self.id_struct();
         let data_struct = self.data_struct();
-        let ingredients_for_impl = self.ingredients_for_impl();
+        let ingredients_for_impl = self.ingredients_for_impl(&self.data_ident());
         let as_id_impl = self.as_id_impl();
+        let update_impl = self.update_impl();
+        let heap_size_impl = self.heap_size_impl();
         let named_fields_impl = self.inherent_impl_for_named_fields();
         let salsa_struct_in_db_impl = self.salsa_struct_in_db_impl();
         let as_debug_with_db_impl = self.as_debug_with_db_impl();
@@ -69,6 +124,8 @@ impl InternedStruct {
             #data_struct
             #ingredients_for_impl
             #as_id_impl
+            #update_impl
+            #heap_size_impl
             #named_fields_impl
             #salsa_struct_in_db_impl
             #as_debug_with_db_impl
@@ -77,11 +134,12 @@ impl InternedStruct {
 
     fn validate_interned(&self) -> syn::Result<()> {
         self.disallow_id_fields("interned")?;
+        self.disallow_default_fields("interned")?;
         Ok(())
     }
 
     /// If this is an interned struct, then generate methods to access each field,
-    /// as well as a `new` method.
+    /// as well as a `new` and a `lookup` method.
     fn inherent_impl_for_named_fields(&self) -> syn::ItemImpl {
         let vis = self.visibility();
         let id_ident = self.id_ident();
@@ -132,11 +190,28 @@ impl InternedStruct {
             }
         };
 
+        let lookup_method: syn::ImplItemMethod = parse_quote! {
+            /// Returns the existing interned value with the given fields, if any,
+            /// without interning a new one.
+            #vis fn lookup(
+                db: &#db_dyn_ty,
+                #(#field_names: #field_tys,)*
+            ) -> Option<Self> {
+                let (jar, runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(db);
+                let ingredients = <#jar_ty as salsa::storage::HasIngredientsFor< #id_ident >>::ingredient(jar);
+                ingredients.lookup(runtime, &#data_ident {
+                    #(#field_names,)*
+                })
+            }
+        };
+
         parse_quote! {
             impl #id_ident {
                 #(#field_getters)*
 
                 #new_method
+
+                #lookup_method
             }
         }
     }
@@ -144,11 +219,19 @@ impl InternedStruct {
     /// Generates an impl of `salsa::storage::IngredientsFor`.
     ///
     /// For a memoized type, the only ingredient is an `InternedIngredient`.
-    fn ingredients_for_impl(&self) -> syn::ItemImpl {
+    fn ingredients_for_impl(&self, data_ident: &syn::Ident) -> syn::ItemImpl {
         let id_ident = self.id_ident();
         let debug_name = crate::literal(id_ident);
         let jar_ty = self.jar_ty();
-        let data_ident = self.data_ident();
+        let ingredient: TokenStream = if self.is_gc() {
+            quote! {
+                let mut ingredient = salsa::interned::InternedIngredient::new(index, #debug_name);
+                ingredient.enable_gc();
+                ingredient
+            }
+        } else {
+            quote! { salsa::interned::InternedIngredient::new(index, #debug_name) }
+        };
         parse_quote! {
             impl salsa::storage::IngredientsFor for #id_ident {
                 type Jar = #jar_ty;
@@ -158,7 +241,7 @@ impl InternedStruct {
                     routes: &mut salsa::routes::Routes<DB>,
                 ) -> Self::Ingredients
                 where
-                    DB: salsa::storage::JarFromJars<Self::Jar>,
+                    DB: salsa::DbWithJar<Self::Jar> + salsa::storage::JarFromJars<Self::Jar>,
                 {
                     let index = routes.push(
                         |jars| {
@@ -170,7 +253,11 @@ impl InternedStruct {
                             <_ as salsa::storage::HasIngredientsFor<Self>>::ingredient_mut(jar)
                         },
                     );
-                    salsa::interned::InternedIngredient::new(index, #debug_name)
+                    #ingredient
+                }
+
+                fn fork_ingredients(ingredients: &Self::Ingredients) -> Option<Self::Ingredients> {
+                    ingredients.fork()
                 }
             }
         }
@@ -193,3 +280,241 @@ impl InternedStruct {
         }
     }
 }
+
+/// An interned enum, typically recursive (e.g., the types of a type system), whose
+/// variants refer to other values by their interned id: `Ty0` is the id, and the
+/// variants move to the data enum `TyData0`. Equal values are interned once,
+/// so values sharing a part share its id.
+struct InternedEnum {
+    /// The id struct, which has no fields.
+    interned: InternedStruct,
+    enum_item: syn::ItemEnum,
+}
+
+impl InternedEnum {
+    fn new(args: proc_macro::TokenStream, enum_item: syn::ItemEnum) -> syn::Result<Self> {
+        if !enum_item.generics.params.is_empty() {
+            return Err(syn::Error::new_spanned(
+                &enum_item.generics,
+                "interned enums cannot be generic",
+            ));
+        }
+        for variant in &enum_item.variants {
+            if let Some((_, discriminant)) = &variant.discriminant {
+                return Err(syn::Error::new_spanned(
+                    discriminant,
+                    "the variants of interned enums cannot have discriminants",
+                ));
+            }
+        }
+
+        let attrs = &enum_item.attrs;
+        let vis = &enum_item.vis;
+        let ident = &enum_item.ident;
+        let struct_item: syn::ItemStruct = parse_quote! {
+            #(#attrs)*
+            #vis struct #ident {}
+        };
+        let interned = InternedStruct(SalsaStruct::with_struct(
+            SalsaStructKind::Interned,
+            args,
+            struct_item,
+        )?);
+        Ok(Self {
+            interned,
+            enum_item,
+        })
+    }
+
+    fn generate_interned(&self) -> syn::Result<TokenStream> {
+        let interned = &self.interned;
+        let id_struct = interned.id_struct();
+        let data_enum = self.data_enum();
+        let ingredients_for_impl = interned.ingredients_for_impl(&interned.public_data_ident());
+        let as_id_impl = interned.as_id_impl();
+        let update_impl = interned.update_impl();
+        let heap_size_impl = interned.heap_size_impl();
+        let inherent_impl = self.inherent_impl();
+        let salsa_struct_in_db_impl = interned.salsa_struct_in_db_impl();
+        let as_debug_with_db_impl = self.as_debug_with_db_impl();
+
+        Ok(quote! {
+            #id_struct
+            #data_enum
+            #ingredients_for_impl
+            #as_id_impl
+            #update_impl
+            #heap_size_impl
+            #inherent_impl
+            #salsa_struct_in_db_impl
+            #as_debug_with_db_impl
+        })
+    }
+
+    /// Generates the data enum, with the variants written by the user.
+    fn data_enum(&self) -> syn::ItemEnum {
+        let data_ident = self.interned.public_data_ident();
+        let vis = &self.enum_item.vis;
+        let variants = &self.enum_item.variants;
+        let doc = format!("The data of an interned [`{}`].", self.enum_item.ident);
+        parse_quote! {
+            #[doc = #doc]
+            #[derive(Eq, PartialEq, Hash, Clone)]
+            #vis enum #data_ident {
+                #variants
+            }
+        }
+    }
+
+    /// The arguments of the constructor of `variant`, and the expression
+    /// building the variant from them.
+    fn variant_args(&self, variant: &syn::Variant) -> (Vec<Ident>, Vec<syn::Type>, syn::Expr) {
+        let data_ident = self.interned.public_data_ident();
+        let variant_ident = &variant.ident;
+        let tys = variant
+            .fields
+            .iter()
+            .map(|field| field.ty.clone())
+            .collect();
+        match &variant.fields {
+            syn::Fields::Named(fields) => {
+                let names: Vec<Ident> = fields
+                    .named
+                    .iter()
+                    .map(|field| field.ident.clone().unwrap())
+                    .collect();
+                let expr = parse_quote!(#data_ident::#variant_ident { #(#names),* });
+                (names, tys, expr)
+            }
+            syn::Fields::Unnamed(fields) => {
+                let names: Vec<Ident> = (0..fields.unnamed.len())
+                    .map(|i| Ident::new(&format!("field{i}"), variant_ident.span()))
+                    .collect();
+                let expr = parse_quote!(#data_ident::#variant_ident(#(#names),*));
+                (names, tys, expr)
+            }
+            syn::Fields::Unit => (vec![], tys, parse_quote!(#data_ident::#variant_ident)),
+        }
+    }
+
+    /// Generates `new`, `data` and `lookup`, and a constructor for each variant,
+    /// named after it in snake case (e.g. `fn_type(db, param, ret)` for `FnType`).
+    fn inherent_impl(&self) -> syn::ItemImpl {
+        let interned = &self.interned;
+        let vis = interned.visibility();
+        let id_ident = interned.id_ident();
+        let db_dyn_ty = interned.db_dyn_ty();
+        let jar_ty = interned.jar_ty();
+        let data_ident = interned.public_data_ident();
+        let constructor_name = interned.constructor_name();
+
+        let variant_constructors: Vec<syn::ImplItemMethod> = self
+            .enum_item
+            .variants
+            .iter()
+            .map(|variant| {
+                let name = variant.ident.to_string().to_snake_case();
+                let name = syn::parse_str::<Ident>(&name)
+                    .unwrap_or_else(|_| Ident::new_raw(&name, variant.ident.span()));
+                let (arg_names, arg_tys, expr) = self.variant_args(variant);
+                let doc = format!("Interns `{}::{}`.", data_ident, variant.ident);
+                parse_quote! {
+                    #[doc = #doc]
+                    #vis fn #name(db: &#db_dyn_ty, #(#arg_names: #arg_tys),*) -> Self {
+                        Self::#constructor_name(db, #expr)
+                    }
+                }
+            })
+            .collect();
+
+        parse_quote! {
+            impl #id_ident {
+                #vis fn #constructor_name(db: &#db_dyn_ty, data: #data_ident) -> Self {
+                    let (jar, runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(db);
+                    let ingredients = <#jar_ty as salsa::storage::HasIngredientsFor< #id_ident >>::ingredient(jar);
+                    ingredients.intern(runtime, data)
+                }
+
+                /// Returns the variant this id was interned for, whose fields
+                /// refer to other interned values by id.
+                #vis fn data(self, db: &#db_dyn_ty) -> #data_ident {
+                    let (jar, runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(db);
+                    let ingredients = <#jar_ty as salsa::storage::HasIngredientsFor< #id_ident >>::ingredient(jar);
+                    std::clone::Clone::clone(ingredients.data(runtime, self))
+                }
+
+                /// Returns the existing interned value for `data`, if any,
+                /// without interning a new one.
+                #vis fn lookup(db: &#db_dyn_ty, data: #data_ident) -> Option<Self> {
+                    let (jar, runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(db);
+                    let ingredients = <#jar_ty as salsa::storage::HasIngredientsFor< #id_ident >>::ingredient(jar);
+                    ingredients.lookup(runtime, &data)
+                }
+
+                #(#variant_constructors)*
+            }
+        }
+    }
+
+    /// Generates `impl salsa::DebugWithDb for Ty0`, which formats the variant
+    /// and, recursively, the interned values its fields refer to.
+    fn as_debug_with_db_impl(&self) -> syn::ItemImpl {
+        let interned = &self.interned;
+        let id_ident = interned.id_ident();
+        let db_type = interned.db_dyn_ty();
+        let data_ident = interned.public_data_ident();
+
+        let arms: Vec<syn::Arm> = self
+            .enum_item
+            .variants
+            .iter()
+            .map(|variant| {
+                let variant_ident = &variant.ident;
+                let variant_string = variant_ident.to_string();
+                let (names, tys, _) = self.variant_args(variant);
+                let field_debugs = names.iter().zip(&tys).map(|(name, ty)| {
+                    quote! {
+                        &::salsa::debug::helper::SalsaDebug::<#ty, #db_type>::salsa_debug(
+                            #name,
+                            _db,
+                            _include_all_fields,
+                        )
+                    }
+                });
+                match &variant.fields {
+                    syn::Fields::Named(_) => {
+                        let name_strings = names.iter().map(|name| name.to_string());
+                        parse_quote! {
+                            #data_ident::#variant_ident { #(#names),* } => f
+                                .debug_struct(#variant_string)
+                                #(.field(#name_strings, #field_debugs))*
+                                .finish(),
+                        }
+                    }
+                    syn::Fields::Unnamed(_) => parse_quote! {
+                        #data_ident::#variant_ident(#(#names),*) => f
+                            .debug_tuple(#variant_string)
+                            #(.field(#field_debugs))*
+                            .finish(),
+                    },
+                    syn::Fields::Unit => parse_quote! {
+                        #data_ident::#variant_ident => f.write_str(#variant_string),
+                    },
+                }
+            })
+            .collect();
+
+        // `use ::salsa::debug::helper::Fallback` is needed for the fallback to `Debug` impl
+        parse_quote_spanned! {id_ident.span()=>
+            impl ::salsa::DebugWithDb<#db_type> for #id_ident {
+                fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>, _db: &#db_type, _include_all_fields: bool) -> ::std::fmt::Result {
+                    #[allow(unused_imports)]
+                    use ::salsa::debug::helper::Fallback;
+                    match &self.data(_db) {
+                        #(#arms)*
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,14 +1,22 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use proc_macro2::Literal;
 use syn::punctuated::Punctuated;
 use syn::spanned::Spanned;
 use syn::{Field, FieldsUnnamed, Ident, ItemStruct, Path, Token};
 
+use crate::jar_chain::{self, Target};
 use crate::options::Options;
 
 // Source:
 //
 // #[salsa::jar(db = Jar0Db)]
 // pub struct Jar0(Entity0, Ty0, EntityComponent0, my_func);
+//
+// #[salsa::jar(db = Jar1Db, includes(Jar0))]
+// pub struct Jar1(Entity1, my_other_func);
 
 pub(crate) fn jar(
     args: proc_macro::TokenStream,
@@ -19,8 +27,26 @@ pub(crate) fn jar(
         Some(v) => v,
         None => panic!("no `db` specified"),
     };
+    let includes: Vec<Path> = options.includes.into_iter().flatten().collect();
     let input = syn::parse_macro_input!(input as ItemStruct);
-    jar_struct_and_friends(&db_path, &input).into()
+    if let Err(err) = check_fields(&input) {
+        return err.into_compile_error().into();
+    }
+    jar_struct_and_friends(&db_path, &includes, &input).into()
+}
+
+/// Salsa items are named by a path, e.g. `MyInput` or `my_fn`.
+/// Whether they really are salsa items of this jar is checked by [`item_assertion`].
+fn check_fields(input: &ItemStruct) -> syn::Result<()> {
+    for field in input.fields.iter() {
+        if !matches!(field.ty, syn::Type::Path(_)) {
+            return Err(syn::Error::new_spanned(
+                &field.ty,
+                "jars can only list salsa structs, tracked functions and accumulators",
+            ));
+        }
+    }
+    Ok(())
 }
 
 type Args = Options<Jar>;
@@ -30,10 +56,14 @@ struct Jar;
 impl crate::options::AllowedOptions for Jar {
     const RETURN_REF: bool = false;
 
+    const RETURN_DEREF: bool = false;
+
     const SPECIFY: bool = false;
 
     const NO_EQ: bool = false;
 
+    const COMPARE_WITH: bool = false;
+
     const SINGLETON: bool = false;
 
     const JAR: bool = false;
@@ -47,16 +77,61 @@ impl crate::options::AllowedOptions for Jar {
     const LRU: bool = false;
 
     const CONSTRUCTOR_NAME: bool = false;
+
+    const INCLUDES: bool = true;
+
+    const CYCLE_ERR: bool = false;
+
+    const RETRY_ERR: bool = false;
+
+    const UPDATE: bool = false;
+
+    const STORAGE: bool = false;
+
+    const VOLATILE: bool = false;
+
+    const HEAP_SIZE: bool = false;
+
+    const INSTANCES: bool = false;
+
+    const HISTORY: bool = false;
+
+    const GC: bool = false;
+
+    const WEAK: bool = false;
+
+    const FIREWALL: bool = false;
+
+    const CAPACITY: bool = false;
+
+    const EXTENDS: bool = false;
+
+    const COLLECT: bool = false;
+
+    const REMOTE_CACHE: bool = false;
+
+    const ELEMENTS: bool = false;
+
+    const CYCLE_INITIAL: bool = false;
+
+    const NO_CLONE: bool = false;
 }
 
 pub(crate) fn jar_struct_and_friends(
     jar_trait: &Path,
+    includes: &[Path],
     input: &ItemStruct,
 ) -> proc_macro2::TokenStream {
-    let output_struct = jar_struct(input);
+    let output_struct = jar_struct(input, includes);
 
     let jar_struct = &input.ident;
 
+    let item_assertions: Vec<_> = input
+        .fields
+        .iter()
+        .map(|field| item_assertion(jar_struct, field))
+        .collect();
+
     // for each field, we need to generate an impl of `HasIngredientsFor`
     let has_ingredients_for_impls: Vec<_> = input
         .fields
@@ -65,14 +140,114 @@ pub(crate) fn jar_struct_and_friends(
         .map(|(field, index)| has_ingredients_for_impl(jar_struct, field, index))
         .collect();
 
-    let jar_impl = jar_impl(jar_struct, jar_trait, input);
+    let jar_impl = jar_impl(jar_struct, jar_trait, includes, input);
+
+    let included_jar_impls = included_jar_impls(jar_struct, includes, input);
+
+    let debug_all_impl = debug_all_impl(jar_struct);
+
+    let jar_macro = jar_macro(jar_struct, includes);
 
     quote! {
+        #(#item_assertions)*
+
         #output_struct
 
         #(#has_ingredients_for_impls)*
 
         #jar_impl
+
+        #(#included_jar_impls)*
+
+        #debug_all_impl
+
+        #jar_macro
+    }
+}
+
+/// Generates the hidden macro that lists the jars contained in this jar, so that
+/// `#[salsa::db]` can implement `HasJar` for each of them (see `jar_chain`).
+/// If the jar includes other jars, their macros are invoked first.
+fn jar_macro(jar_struct: &Ident, includes: &[Path]) -> proc_macro2::TokenStream {
+    let uid = jar_uid(jar_struct);
+    if includes.is_empty() {
+        jar_chain::jar_macro(&uid, jar_struct, vec![])
+    } else {
+        let target = Target::Jar {
+            uid,
+            jar: jar_struct.clone(),
+        };
+        jar_chain::start(&target, includes)
+    }
+}
+
+/// Names the hidden macro of the jar. Macros exported by a crate share one namespace,
+/// and `#[salsa::db]` tells apart the jars of several crates by this name.
+fn jar_uid(jar_struct: &Ident) -> Ident {
+    static JARS: AtomicUsize = AtomicUsize::new(0);
+    let mut hasher = DefaultHasher::new();
+    std::env::var("CARGO_CRATE_NAME").ok().hash(&mut hasher);
+    std::env::var("CARGO_PKG_VERSION").ok().hash(&mut hasher);
+    JARS.fetch_add(1, Ordering::Relaxed).hash(&mut hasher);
+    format_ident!("__salsa_jar_{}_{:016x}", jar_struct, hasher.finish())
+}
+
+/// Implements `IncludedJar<K>` for the `K`-th jar included by this jar.
+fn included_jar_impls(
+    jar_struct: &Ident,
+    includes: &[Path],
+    input: &ItemStruct,
+) -> Vec<proc_macro2::TokenStream> {
+    includes
+        .iter()
+        .zip(input.fields.len()..)
+        .enumerate()
+        .map(|(k, (include, field))| {
+            let k = Literal::usize_unsuffixed(k);
+            let field = Literal::usize_unsuffixed(field);
+            quote! {
+                impl salsa::jar::IncludedJar<#k> for #jar_struct {
+                    type Jar = #include;
+
+                    fn included(&self) -> &#include {
+                        &self.#field
+                    }
+
+                    fn included_mut(&mut self) -> &mut #include {
+                        &mut self.#field
+                    }
+                }
+            }
+        })
+        .collect()
+}
+
+/// Generates `Jar::debug_all`, which dumps what the ingredients of the jar store.
+fn debug_all_impl(jar_struct: &Ident) -> proc_macro2::TokenStream {
+    quote! {
+        impl #jar_struct {
+            /// Returns what the ingredients of this jar store: the values of the fields
+            /// of inputs (if their type implements `Debug`), and the number of instances
+            /// of salsa structs, of memos of tracked functions, and of queries that
+            /// accumulated values.
+            #[allow(dead_code)]
+            pub fn debug_all<DB>(db: &DB) -> salsa::dump::JarDump
+            where
+                DB: ?Sized + salsa::storage::HasJar<Self> + salsa::storage::HasJarsDyn,
+            {
+                db.dump_jar(std::any::type_name::<Self>())
+            }
+        }
+    }
+}
+
+/// Checks that the item listed in the jar is a salsa item declared for this jar.
+/// The check is spanned at the item, so that the error points there
+/// rather than at the code generated for the jar.
+fn item_assertion(jar_struct: &Ident, field: &Field) -> proc_macro2::TokenStream {
+    let field_ty = &field.ty;
+    quote_spanned! {field_ty.span()=>
+        const _: fn() = salsa::jar::assert_in_jar::<#field_ty, #jar_struct>;
     }
 }
 
@@ -83,7 +258,7 @@ pub(crate) fn has_ingredients_for_impl(
 ) -> proc_macro2::TokenStream {
     let field_ty = &field.ty;
     let index = Literal::u32_unsuffixed(index);
-    quote! {
+    quote_spanned! {field_ty.span()=>
         impl salsa::storage::HasIngredientsFor<#field_ty> for #jar_struct {
             fn ingredient(&self) -> &<#field_ty as salsa::storage::IngredientsFor>::Ingredients {
                 &self.#index
@@ -99,6 +274,7 @@ pub(crate) fn has_ingredients_for_impl(
 pub(crate) fn jar_impl(
     jar_struct: &Ident,
     jar_trait: &Path,
+    includes: &[Path],
     input: &ItemStruct,
 ) -> proc_macro2::TokenStream {
     let field_tys: Vec<_> = input.fields.iter().map(|f| &f.ty).collect();
@@ -108,37 +284,70 @@ pub(crate) fn jar_impl(
         .zip(0..)
         .map(|(f, i)| syn::LitInt::new(&format!("{}", i), f.ty.span()))
         .collect();
+    // The included jars are stored after the ingredients.
+    let include_var_names: &Vec<_> = &includes
+        .iter()
+        .zip(input.fields.len()..)
+        .map(|(p, i)| syn::LitInt::new(&format!("{}", i), p.span()))
+        .collect();
     // ANCHOR: init_jar
     quote! {
-        unsafe impl<'salsa_db> salsa::jar::Jar<'salsa_db> for #jar_struct {
+        impl<'salsa_db> salsa::jar::Jar<'salsa_db> for #jar_struct {
             type DynDb = dyn #jar_trait + 'salsa_db;
+        }
 
-            unsafe fn init_jar<DB>(place: *mut Self, routes: &mut salsa::routes::Routes<DB>)
+        unsafe impl<DB> salsa::jar::JarInDb<DB> for #jar_struct
+        where
+            DB: #jar_trait + salsa::storage::JarFromJars<Self>,
+            #(#includes: salsa::jar::JarInDb<DB>,)*
+        {
+            fn as_jar_db<'db>(db: &'db DB) -> &'db <Self as salsa::jar::Jar<'db>>::DynDb
             where
-                DB: salsa::storage::JarFromJars<Self> + salsa::storage::DbWithJar<Self>,
+                'db: 'db,
             {
+                db
+            }
+
+            unsafe fn init_jar(place: *mut Self, routes: &mut salsa::routes::Routes<DB>) {
                 #(
                     unsafe {
                         std::ptr::addr_of_mut!((*place).#field_var_names)
                             .write(<#field_tys as salsa::storage::IngredientsFor>::create_ingredients(routes));
                     }
                 )*
+                #(
+                    unsafe {
+                        let place = std::ptr::addr_of_mut!((*place).#include_var_names);
+                        salsa::jar::init_jar::<DB, #includes>(place, routes);
+                    }
+                )*
+            }
+
+            fn fork_jar(&self) -> Option<Self> {
+                Some(Self(
+                    #(
+                        <#field_tys as salsa::storage::IngredientsFor>::fork_ingredients(&self.#field_var_names)?,
+                    )*
+                    #(
+                        <#includes as salsa::jar::JarInDb<DB>>::fork_jar(&self.#include_var_names)?,
+                    )*
+                ))
             }
         }
     }
     // ANCHOR_END: init_jar
 }
 
-pub(crate) fn jar_struct(input: &ItemStruct) -> ItemStruct {
+pub(crate) fn jar_struct(input: &ItemStruct, includes: &[Path]) -> ItemStruct {
     let mut output_struct = input.clone();
-    output_struct.fields = generate_fields(input).into();
+    output_struct.fields = generate_fields(input, includes).into();
     if output_struct.semi_token.is_none() {
         output_struct.semi_token = Some(Token![;](input.struct_token.span));
     }
     output_struct
 }
 
-fn generate_fields(input: &ItemStruct) -> FieldsUnnamed {
+fn generate_fields(input: &ItemStruct, includes: &[Path]) -> FieldsUnnamed {
     // Generate the
     let mut output_fields = Punctuated::new();
     for field in input.fields.iter() {
@@ -148,13 +357,24 @@ fn generate_fields(input: &ItemStruct) -> FieldsUnnamed {
         field.ident = None;
 
         let field_ty = &field.ty;
-        field.ty =
-            syn::parse2(quote!(< #field_ty as salsa::storage::IngredientsFor >::Ingredients))
-                .unwrap();
+        field.ty = parse_quote_spanned!(field_ty.span()=>
+            < #field_ty as salsa::storage::IngredientsFor >::Ingredients
+        );
 
         output_fields.push(field);
     }
 
+    // Followed by the included jars
+    for include in includes {
+        output_fields.push(Field {
+            attrs: vec![],
+            vis: syn::Visibility::Inherited,
+            ident: None,
+            colon_token: None,
+            ty: parse_quote!(#include),
+        });
+    }
+
     let paren_token = match &input.fields {
         syn::Fields::Named(f) => syn::token::Paren {
             span: f.brace_token.span,
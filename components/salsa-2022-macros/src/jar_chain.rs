@@ -0,0 +1,311 @@
+use proc_macro2::{Literal, Punct, Spacing, TokenStream};
+use syn::parse::{Parse, ParseStream};
+use syn::{bracketed, parenthesized};
+
+// The database has to implement `HasJar<J>` for the jars it lists and for the jars
+// they include, recursively, but `#[salsa::db]` only sees the paths of the jars it
+// lists. So each jar defines a hidden macro, re-exported next to the jar under the
+// same name, which expands to the jars it contains:
+//
+// #[salsa::jar(db = Jar1Db, includes(Jar0))]
+// pub struct Jar1(...);
+//
+// // Generated:
+// #[macro_export]
+// macro_rules! __salsa_jar_Jar1_1a2b3c {
+//     ($($state:tt)*) => {
+//         salsa::plumbing::jar_chain! { $($state)* [(__salsa_jar_Jar1_1a2b3c) (__salsa_jar_Jar0_4d5e6f 0)] }
+//     };
+// }
+// pub use __salsa_jar_Jar1_1a2b3c as Jar1;
+//
+// Each entry names a jar by the identifier of its macro, followed by the indices
+// of the `IncludedJar` impls that lead to it. A jar that includes other jars learns
+// what they contain by invoking their macros one after the other, which in turn
+// invoke `jar_chain!`:
+//
+// Jar0! { jar { __salsa_jar_Jar1_1a2b3c Jar1 } 0 [(1 Jar2)] [] }
+//
+// where `jar { .. }` says what to generate once all the jars are known, `0` is
+// the index of the jar being invoked, `[(1 Jar2)]` are the jars that come next and
+// `[]` are the entries collected so far. `#[salsa::db]` does the same with
+// `db { Database storage [(Jar0) (Jar1)] }` to generate the `HasJar` impls.
+
+pub(crate) fn jar_chain(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let chain = syn::parse_macro_input!(input as Chain);
+    chain.expand().into()
+}
+
+/// A jar reached from a list of jars: the identifier of its macro, then the index
+/// of the jar in the list followed by the indices of the `IncludedJar` impls.
+#[derive(Clone)]
+pub(crate) struct Entry {
+    uid: syn::Ident,
+    indices: Vec<usize>,
+}
+
+impl Parse for Entry {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let content;
+        parenthesized!(content in input);
+        let uid = content.parse()?;
+        let mut indices = vec![];
+        while !content.is_empty() {
+            indices.push(content.parse::<syn::LitInt>()?.base10_parse()?);
+        }
+        Ok(Entry { uid, indices })
+    }
+}
+
+impl quote::ToTokens for Entry {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let uid = &self.uid;
+        let indices = self.indices.iter().map(|&i| Literal::usize_unsuffixed(i));
+        tokens.extend(quote! { (#uid #(#indices)*) });
+    }
+}
+
+fn parse_entries(input: ParseStream) -> syn::Result<Vec<Entry>> {
+    let content;
+    bracketed!(content in input);
+    let mut entries = vec![];
+    while !content.is_empty() {
+        entries.push(content.parse()?);
+    }
+    Ok(entries)
+}
+
+/// Keeps the first entry for each jar: that is the copy of the jar that
+/// `salsa::jar::init_jar` registers, as it initializes the jars depth-first.
+fn dedup(entries: Vec<Entry>) -> Vec<Entry> {
+    let mut seen = std::collections::HashSet::new();
+    entries
+        .into_iter()
+        .filter(|entry| seen.insert(entry.uid.clone()))
+        .collect()
+}
+
+pub(crate) enum Target {
+    /// Defines the macro of a jar, see [`jar_macro`].
+    Jar { uid: syn::Ident, jar: syn::Ident },
+
+    /// Implements the traits that give access to the jars of a database.
+    Db {
+        db: syn::Ident,
+        storage: syn::Ident,
+        jar_paths: Vec<syn::Path>,
+    },
+}
+
+impl Parse for Target {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let kind: syn::Ident = input.parse()?;
+        let content;
+        syn::braced!(content in input);
+        if kind == "jar" {
+            Ok(Target::Jar {
+                uid: content.parse()?,
+                jar: content.parse()?,
+            })
+        } else if kind == "db" {
+            let db = content.parse()?;
+            let storage = content.parse()?;
+            let paths;
+            bracketed!(paths in content);
+            let mut jar_paths = vec![];
+            while !paths.is_empty() {
+                let path;
+                parenthesized!(path in paths);
+                jar_paths.push(path.parse()?);
+            }
+            Ok(Target::Db {
+                db,
+                storage,
+                jar_paths,
+            })
+        } else {
+            Err(syn::Error::new(kind.span(), "expected `jar` or `db`"))
+        }
+    }
+}
+
+impl quote::ToTokens for Target {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        tokens.extend(match self {
+            Target::Jar { uid, jar } => quote! { jar { #uid #jar } },
+            Target::Db {
+                db,
+                storage,
+                jar_paths,
+            } => quote! { db { #db #storage [#((#jar_paths))*] } },
+        })
+    }
+}
+
+struct Chain {
+    target: Target,
+    /// The index of the jar whose entries were appended by its macro.
+    current: usize,
+    /// The jars left to invoke, with their index.
+    todo: Vec<(usize, syn::Path)>,
+    /// The entries collected from the jars before `current`.
+    done: Vec<Entry>,
+    /// The entries of the jar at `current`.
+    appended: Vec<Entry>,
+}
+
+impl Parse for Chain {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let target = input.parse()?;
+        let current = input.parse::<syn::LitInt>()?.base10_parse()?;
+        let content;
+        bracketed!(content in input);
+        let mut todo = vec![];
+        while !content.is_empty() {
+            let item;
+            parenthesized!(item in content);
+            let index = item.parse::<syn::LitInt>()?.base10_parse()?;
+            todo.push((index, item.parse()?));
+        }
+        let done = parse_entries(input)?;
+        let appended = parse_entries(input)?;
+        Ok(Chain {
+            target,
+            current,
+            todo,
+            done,
+            appended,
+        })
+    }
+}
+
+impl Chain {
+    fn expand(self) -> TokenStream {
+        let Chain {
+            target,
+            current,
+            mut todo,
+            mut done,
+            appended,
+        } = self;
+        done.extend(appended.into_iter().map(|mut entry| {
+            entry.indices.insert(0, current);
+            entry
+        }));
+        let done = dedup(done);
+        if todo.is_empty() {
+            match target {
+                Target::Jar { uid, jar } => jar_macro(&uid, &jar, done),
+                Target::Db {
+                    db,
+                    storage,
+                    jar_paths,
+                } => has_jar_impls(&db, &storage, &jar_paths, &done),
+            }
+        } else {
+            let (next, path) = todo.remove(0);
+            invoke(&path, &target, next, &todo, &done)
+        }
+    }
+}
+
+/// Invokes the macro of the jar at `path`, which continues the chain.
+fn invoke(
+    path: &syn::Path,
+    target: &Target,
+    index: usize,
+    todo: &[(usize, syn::Path)],
+    done: &[Entry],
+) -> TokenStream {
+    let index = Literal::usize_unsuffixed(index);
+    let todo = todo.iter().map(|(i, path)| {
+        let i = Literal::usize_unsuffixed(*i);
+        quote! { (#i #path) }
+    });
+    quote! {
+        #path! { #target #index [#(#todo)*] [#(#done)*] }
+    }
+}
+
+/// Starts a chain over `paths`, the jars listed in a database or included by a jar,
+/// which must not be empty.
+pub(crate) fn start(target: &Target, paths: &[syn::Path]) -> TokenStream {
+    let todo: Vec<_> = paths.iter().cloned().enumerate().collect();
+    invoke(&todo[0].1, target, 0, &todo[1..], &[])
+}
+
+/// Defines the hidden macro of the jar `jar` that lists `entries`, the jars it
+/// contains, and re-exports it under the name of the jar.
+pub(crate) fn jar_macro(uid: &syn::Ident, jar: &syn::Ident, entries: Vec<Entry>) -> TokenStream {
+    let entries = std::iter::once(Entry {
+        uid: uid.clone(),
+        indices: vec![],
+    })
+    .chain(entries);
+    let entries = dedup(entries.collect());
+    let dollar = Punct::new('$', Spacing::Alone);
+    quote! {
+        #[doc(hidden)]
+        #[macro_export]
+        macro_rules! #uid {
+            (#dollar(#dollar state:tt)*) => {
+                salsa::plumbing::jar_chain! { #dollar(#dollar state)* [#(#entries)*] }
+            };
+        }
+
+        #[doc(hidden)]
+        #[allow(unused_imports)]
+        pub use #uid as #jar;
+    }
+}
+
+/// Implements the traits that give access to each jar of the database `db`,
+/// i.e. to the jars that it lists, `jar_paths`, and to the jars they include.
+fn has_jar_impls(
+    db: &syn::Ident,
+    storage: &syn::Ident,
+    jar_paths: &[syn::Path],
+    entries: &[Entry],
+) -> TokenStream {
+    let impls = entries.iter().map(|entry| {
+        let (first, rest) = entry.indices.split_first().unwrap();
+        let field = Literal::usize_unsuffixed(*first);
+        let jar_path = &jar_paths[*first];
+        let mut jar_ty = quote! { #jar_path };
+        let mut jar = quote! { &__jars.#field };
+        let mut jar_mut = quote! { &mut __jars.#field };
+        for &index in rest {
+            let index = Literal::usize_unsuffixed(index);
+            let trait_path = quote! { <#jar_ty as salsa::jar::IncludedJar<#index>> };
+            jar = quote! { #trait_path::included(#jar) };
+            jar_mut = quote! { #trait_path::included_mut(#jar_mut) };
+            jar_ty = quote! { #trait_path::Jar };
+        }
+        quote! {
+            impl salsa::storage::DbWithJar<#jar_ty> for #db {}
+
+            impl salsa::storage::HasJar<#jar_ty> for #db {
+                fn jar(&self) -> (&#jar_ty, &salsa::Runtime) {
+                    let (__jars, __runtime) = self.#storage.jars();
+                    (<Self as salsa::storage::JarFromJars<#jar_ty>>::jar_from_jars(__jars), __runtime)
+                }
+
+                fn jar_mut(&mut self) -> (&mut #jar_ty, &mut salsa::Runtime) {
+                    let (__jars, __runtime) = self.#storage.jars_mut();
+                    (<Self as salsa::storage::JarFromJars<#jar_ty>>::jar_from_jars_mut(__jars), __runtime)
+                }
+            }
+
+            impl salsa::storage::JarFromJars<#jar_ty> for #db {
+                fn jar_from_jars<'db>(__jars: &Self::Jars) -> &#jar_ty {
+                    #jar
+                }
+
+                fn jar_from_jars_mut<'db>(__jars: &mut Self::Jars) -> &mut #jar_ty {
+                    #jar_mut
+                }
+            }
+        }
+    });
+    quote! { #(#impls)* }
+}
@@ -31,32 +31,55 @@ pub(crate) fn literal(ident: &proc_macro2::Ident) -> proc_macro2::Literal {
 }
 
 mod accumulator;
+mod component;
 mod configuration;
 mod db;
+mod delegate_db;
+mod heap_size;
 mod input;
 mod interned;
 mod jar;
+mod jar_chain;
 mod options;
+mod progress;
+mod requires;
 mod salsa_struct;
 mod tracked;
 mod tracked_fn;
 mod tracked_struct;
+mod update;
 
 #[proc_macro_attribute]
 pub fn accumulator(args: TokenStream, input: TokenStream) -> TokenStream {
     accumulator::accumulator(args, input)
 }
 
+#[proc_macro_attribute]
+pub fn component(args: TokenStream, input: TokenStream) -> TokenStream {
+    component::component(args, input)
+}
+
 #[proc_macro_attribute]
 pub fn jar(args: TokenStream, input: TokenStream) -> TokenStream {
     jar::jar(args, input)
 }
 
+#[doc(hidden)]
+#[proc_macro]
+pub fn jar_chain(input: TokenStream) -> TokenStream {
+    jar_chain::jar_chain(input)
+}
+
 #[proc_macro_attribute]
 pub fn db(args: TokenStream, input: TokenStream) -> TokenStream {
     db::db(args, input)
 }
 
+#[proc_macro_attribute]
+pub fn delegate_db(args: TokenStream, input: TokenStream) -> TokenStream {
+    delegate_db::delegate_db(args, input)
+}
+
 #[proc_macro_attribute]
 pub fn interned(args: TokenStream, input: TokenStream) -> TokenStream {
     interned::interned(args, input)
@@ -67,7 +90,27 @@ pub fn input(args: TokenStream, input: TokenStream) -> TokenStream {
     input::input(args, input)
 }
 
+#[proc_macro_attribute]
+pub fn progress(args: TokenStream, input: TokenStream) -> TokenStream {
+    progress::progress(args, input)
+}
+
+#[proc_macro_attribute]
+pub fn requires(args: TokenStream, input: TokenStream) -> TokenStream {
+    requires::requires(args, input)
+}
+
 #[proc_macro_attribute]
 pub fn tracked(args: TokenStream, input: TokenStream) -> TokenStream {
     tracked::tracked(args, input)
 }
+
+#[proc_macro_derive(Update)]
+pub fn update(input: TokenStream) -> TokenStream {
+    update::update(input)
+}
+
+#[proc_macro_derive(HeapSize)]
+pub fn heap_size(input: TokenStream) -> TokenStream {
+    heap_size::heap_size(input)
+}
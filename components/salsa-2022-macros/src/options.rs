@@ -13,12 +13,30 @@ pub(crate) struct Options<A: AllowedOptions> {
     /// If this is `Some`, the value is the `ref` identifier.
     pub return_ref: Option<syn::Ident>,
 
+    /// The `return_deref` option is like `return_ref`, but the reference returned
+    /// is to the `Deref::Target` of the value (e.g., `&[T]` for a `Vec<T>`).
+    ///
+    /// If this is `Some`, the value is the `return_deref` identifier.
+    pub return_deref: Option<syn::Ident>,
+
+    /// The target of `return_deref = Target`, for the values whose target
+    /// can't be named from their type alone (e.g., `str` for a `Box<String>`).
+    pub return_deref_target: Option<syn::Type>,
+
     ///  The `no_eq` option is used to signal that a given field does not implement
     /// the `Eq` trait and cannot be compared for equality.
     ///
     /// If this is `Some`, the value is the `no_eq` identifier.
     pub no_eq: Option<syn::Ident>,
 
+    /// The `compare_with = <path>` option is used to supply the function used
+    /// to decide whether a new value is equal to the old one (and hence whether
+    /// the value can be backdated). It takes precedence over `Eq`, so it can be
+    /// combined with `no_eq` for types that do not implement `Eq`.
+    ///
+    /// If this is `Some`, the value is the `<path>`.
+    pub compare_with: Option<syn::Path>,
+
     /// The `singleton` option is used on input with only one field
     /// It allows the creation of convenient methods
     pub singleton: Option<syn::Ident>,
@@ -34,7 +52,9 @@ pub(crate) struct Options<A: AllowedOptions> {
     /// If this is `Some`, the value is the `<type>`.
     pub jar_ty: Option<syn::Type>,
 
-    /// The `db = <path>` option is used to indicate the db.
+    /// The `db = <path>` option is used to indicate the db. On salsa structs, it
+    /// names the database trait used in the signatures of the generated methods;
+    /// it can also be written `db = dyn <path>` there.
     ///
     /// If this is `Some`, the value is the `<path>`.
     pub db_path: Option<syn::Path>,
@@ -61,6 +81,128 @@ pub(crate) struct Options<A: AllowedOptions> {
     /// If this is `Some`, the value is the `<ident>`.
     pub constructor_name: Option<syn::Ident>,
 
+    /// The `includes(<path>, ...)` option lists other jars that are included
+    /// by a jar, so that listing the jar in a database brings them along.
+    ///
+    /// If this is `Some`, the value is the list of `<path>`s.
+    pub includes: Option<syn::punctuated::Punctuated<syn::Path, Comma>>,
+
+    /// The `cycle_err` option is used on tracked functions returning a `Result`
+    /// to recover from cycles by returning `Err(salsa::Cycle.into())`.
+    ///
+    /// If this is `Some`, the value is the `cycle_err` identifier.
+    pub cycle_err: Option<syn::Ident>,
+
+    /// The `retry_err` option is used on tracked functions returning a `Result`
+    /// so that `Err` values are never backdated and are recomputed in the next revision.
+    ///
+    /// If this is `Some`, the value is the `retry_err` identifier.
+    pub retry_err: Option<syn::Ident>,
+
+    /// The `update` option is used on tracked functions to merge new values
+    /// into the old ones with `salsa::Update` rather than replacing them.
+    ///
+    /// If this is `Some`, the value is the `update` identifier.
+    pub update: Option<syn::Ident>,
+
+    /// The `storage = <ident>` option is used on tracked functions to choose where
+    /// their memos are stored: `hash` (the default) or `dense`.
+    ///
+    /// If this is `Some`, the value is the `<ident>`.
+    pub storage: Option<syn::Ident>,
+
+    /// The `volatile` option is used on tracked functions that read state unknown
+    /// to salsa, so that they are executed again in every new revision.
+    ///
+    /// If this is `Some`, the value is the `volatile` identifier.
+    pub volatile: Option<syn::Ident>,
+
+    /// The `heap_size` option is used on tracked functions to report the heap memory
+    /// of their values in `Database::memory_report`, using their `salsa::HeapSize` impl.
+    ///
+    /// If this is `Some`, the value is the `heap_size` identifier.
+    pub heap_size: Option<syn::Ident>,
+
+    /// The `for(<type>, ...)` option lists the types for which a generic tracked
+    /// function is instantiated, each instance being a separate ingredient.
+    ///
+    /// If this is `Some`, the value is the list of `<type>`s.
+    pub instances: Option<syn::punctuated::Punctuated<syn::Type, Comma>>,
+
+    /// The `history = <usize>` option is used on inputs to keep the old values of
+    /// their fields set in the last `<usize>` revisions, for `Database::revert_to`.
+    ///
+    /// If this is `Some`, the value is the `<usize>`.
+    pub history: Option<usize>,
+
+    /// The `gc` option is used on interned structs so that the values not used in
+    /// the last revisions are reclaimed by `Database::sweep`.
+    ///
+    /// If this is `Some`, the value is the `gc` identifier.
+    pub gc: Option<syn::Ident>,
+
+    /// The `weak` option is used on tracked functions whose values are expensive
+    /// but can be recomputed, so that they are dropped by `Storage::release_weak_memos`.
+    ///
+    /// If this is `Some`, the value is the `weak` identifier.
+    pub weak: Option<syn::Ident>,
+
+    /// The `firewall` option is used on tracked functions that project a small value
+    /// out of a large one, so that the functions depending on them only see a change
+    /// when the projected value changes. Their values are always kept to compare with,
+    /// and they must not read untracked state.
+    ///
+    /// If this is `Some`, the value is the `firewall` identifier.
+    pub firewall: Option<syn::Ident>,
+
+    /// The `capacity = <usize>` option is used on tracked functions to reserve room
+    /// for the memos of that many keys when the database is created.
+    ///
+    /// If this is `Some`, the value is the `<usize>`.
+    pub capacity: Option<usize>,
+
+    /// The `extends = <path>` option is used on accumulators to declare a parent
+    /// accumulator, which also gets the values pushed to this one.
+    ///
+    /// If this is `Some`, the value is the `<path>`.
+    pub extends: Option<syn::Path>,
+
+    /// The `collect = <type>` option is used on tracked functions returning an
+    /// iterator, to collect it into a container of type `<type>` (e.g. `Vec<Item>`),
+    /// which is then the memoized value.
+    ///
+    /// If this is `Some`, the value is the `<type>`.
+    pub collect: Option<syn::Type>,
+
+    /// The `remote_cache = <path>` option is used on tracked functions to look up
+    /// their values in the `RemoteCache` of the database before executing them.
+    /// `<path>` names a function taking the same arguments, which returns a checksum
+    /// of everything the value depends on.
+    ///
+    /// If this is `Some`, the value is the `<path>`.
+    pub remote_cache: Option<syn::Path>,
+
+    /// The `elements = <ident>` option is used on tracked functions returning a
+    /// `salsa::List`, to generate a tracked function named `<ident>` returning
+    /// the element at an index.
+    ///
+    /// If this is `Some`, the value is the `<ident>`.
+    pub elements: Option<syn::Ident>,
+
+    /// The `cycle_initial = <path>` option is used on tracked functions to iterate
+    /// the cycles they are part of to a fixed point, starting from the value returned
+    /// by the function `<path>`, which takes the same arguments.
+    ///
+    /// If this is `Some`, the value is the `<path>`.
+    pub cycle_initial: Option<syn::Path>,
+
+    /// The `no_clone` option is used on tracked functions whose values are not `Clone`:
+    /// the function itself, which returns a clone of the value, is not generated,
+    /// and the value is read by reference with `<fn>::get`.
+    ///
+    /// If this is `Some`, the value is the `no_clone` identifier.
+    pub no_clone: Option<syn::Ident>,
+
     /// Remember the `A` parameter, which plays no role after parsing.
     phantom: PhantomData<A>,
 }
@@ -69,13 +211,35 @@ impl<A: AllowedOptions> Default for Options<A> {
     fn default() -> Self {
         Self {
             return_ref: Default::default(),
+            return_deref: Default::default(),
+            return_deref_target: Default::default(),
             specify: Default::default(),
             no_eq: Default::default(),
+            compare_with: Default::default(),
             jar_ty: Default::default(),
             db_path: Default::default(),
             recovery_fn: Default::default(),
             data: Default::default(),
             constructor_name: Default::default(),
+            includes: Default::default(),
+            cycle_err: Default::default(),
+            retry_err: Default::default(),
+            update: Default::default(),
+            storage: Default::default(),
+            volatile: Default::default(),
+            heap_size: Default::default(),
+            instances: Default::default(),
+            history: Default::default(),
+            gc: Default::default(),
+            weak: Default::default(),
+            firewall: Default::default(),
+            capacity: Default::default(),
+            extends: Default::default(),
+            collect: Default::default(),
+            remote_cache: Default::default(),
+            elements: Default::default(),
+            cycle_initial: Default::default(),
+            no_clone: Default::default(),
             phantom: Default::default(),
             lru: Default::default(),
             singleton: Default::default(),
@@ -86,8 +250,10 @@ impl<A: AllowedOptions> Default for Options<A> {
 /// These flags determine which options are allowed in a given context
 pub(crate) trait AllowedOptions {
     const RETURN_REF: bool;
+    const RETURN_DEREF: bool;
     const SPECIFY: bool;
     const NO_EQ: bool;
+    const COMPARE_WITH: bool;
     const SINGLETON: bool;
     const JAR: bool;
     const DATA: bool;
@@ -95,6 +261,25 @@ pub(crate) trait AllowedOptions {
     const RECOVERY_FN: bool;
     const LRU: bool;
     const CONSTRUCTOR_NAME: bool;
+    const INCLUDES: bool;
+    const CYCLE_ERR: bool;
+    const RETRY_ERR: bool;
+    const UPDATE: bool;
+    const STORAGE: bool;
+    const VOLATILE: bool;
+    const HEAP_SIZE: bool;
+    const INSTANCES: bool;
+    const HISTORY: bool;
+    const GC: bool;
+    const WEAK: bool;
+    const FIREWALL: bool;
+    const CAPACITY: bool;
+    const EXTENDS: bool;
+    const COLLECT: bool;
+    const REMOTE_CACHE: bool;
+    const ELEMENTS: bool;
+    const CYCLE_INITIAL: bool;
+    const NO_CLONE: bool;
 }
 
 type Equals = syn::Token![=];
@@ -124,7 +309,7 @@ impl<A: AllowedOptions> syn::parse::Parse for Options<A> {
             let ident: syn::Ident = syn::Ident::parse_any(input)?;
             if ident == "return_ref" {
                 if A::RETURN_REF {
-                    if let Some(old) = std::mem::replace(&mut options.return_ref, Some(ident)) {
+                    if let Some(old) = options.return_ref.replace(ident) {
                         return Err(syn::Error::new(
                             old.span(),
                             "option `return_ref` provided twice",
@@ -136,9 +321,27 @@ impl<A: AllowedOptions> syn::parse::Parse for Options<A> {
                         "`return_ref` option not allowed here",
                     ));
                 }
+            } else if ident == "return_deref" {
+                if A::RETURN_DEREF {
+                    if let Some(old) = options.return_deref.replace(ident) {
+                        return Err(syn::Error::new(
+                            old.span(),
+                            "option `return_deref` provided twice",
+                        ));
+                    }
+                    if input.peek(syn::Token![=]) {
+                        let _eq = Equals::parse(input)?;
+                        options.return_deref_target = Some(syn::Type::parse(input)?);
+                    }
+                } else {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "`return_deref` option not allowed here",
+                    ));
+                }
             } else if ident == "no_eq" {
                 if A::NO_EQ {
-                    if let Some(old) = std::mem::replace(&mut options.no_eq, Some(ident)) {
+                    if let Some(old) = options.no_eq.replace(ident) {
                         return Err(syn::Error::new(old.span(), "option `no_eq` provided twice"));
                     }
                 } else {
@@ -147,9 +350,25 @@ impl<A: AllowedOptions> syn::parse::Parse for Options<A> {
                         "`no_eq` option not allowed here",
                     ));
                 }
+            } else if ident == "compare_with" {
+                if A::COMPARE_WITH {
+                    let _eq = Equals::parse(input)?;
+                    let path = syn::Path::parse(input)?;
+                    if let Some(old) = options.compare_with.replace(path) {
+                        return Err(syn::Error::new(
+                            old.span(),
+                            "option `compare_with` provided twice",
+                        ));
+                    }
+                } else {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "`compare_with` option not allowed here",
+                    ));
+                }
             } else if ident == "singleton" {
                 if A::SINGLETON {
-                    if let Some(old) = std::mem::replace(&mut options.singleton, Some(ident)) {
+                    if let Some(old) = options.singleton.replace(ident) {
                         return Err(syn::Error::new(
                             old.span(),
                             "option `singleton` provided twice",
@@ -163,7 +382,7 @@ impl<A: AllowedOptions> syn::parse::Parse for Options<A> {
                 }
             } else if ident == "specify" {
                 if A::SPECIFY {
-                    if let Some(old) = std::mem::replace(&mut options.specify, Some(ident)) {
+                    if let Some(old) = options.specify.replace(ident) {
                         return Err(syn::Error::new(
                             old.span(),
                             "option `specify` provided twice",
@@ -179,7 +398,7 @@ impl<A: AllowedOptions> syn::parse::Parse for Options<A> {
                 if A::JAR {
                     let _eq = Equals::parse(input)?;
                     let ty = syn::Type::parse(input)?;
-                    if let Some(old) = std::mem::replace(&mut options.jar_ty, Some(ty)) {
+                    if let Some(old) = options.jar_ty.replace(ty) {
                         return Err(syn::Error::new(old.span(), "option `jar` provided twice"));
                     }
                 } else {
@@ -191,8 +410,9 @@ impl<A: AllowedOptions> syn::parse::Parse for Options<A> {
             } else if ident == "db" {
                 if A::DB {
                     let _eq = Equals::parse(input)?;
+                    let _dyn = Option::<syn::Token![dyn]>::parse(input)?;
                     let path = syn::Path::parse(input)?;
-                    if let Some(old) = std::mem::replace(&mut options.db_path, Some(path)) {
+                    if let Some(old) = options.db_path.replace(path) {
                         return Err(syn::Error::new(old.span(), "option `db` provided twice"));
                     }
                 } else {
@@ -205,7 +425,7 @@ impl<A: AllowedOptions> syn::parse::Parse for Options<A> {
                 if A::RECOVERY_FN {
                     let _eq = Equals::parse(input)?;
                     let path = syn::Path::parse(input)?;
-                    if let Some(old) = std::mem::replace(&mut options.recovery_fn, Some(path)) {
+                    if let Some(old) = options.recovery_fn.replace(path) {
                         return Err(syn::Error::new(
                             old.span(),
                             "option `recovery_fn` provided twice",
@@ -221,7 +441,7 @@ impl<A: AllowedOptions> syn::parse::Parse for Options<A> {
                 if A::DATA {
                     let _eq = Equals::parse(input)?;
                     let ident = syn::Ident::parse(input)?;
-                    if let Some(old) = std::mem::replace(&mut options.data, Some(ident)) {
+                    if let Some(old) = options.data.replace(ident) {
                         return Err(syn::Error::new(old.span(), "option `data` provided twice"));
                     }
                 } else {
@@ -235,7 +455,7 @@ impl<A: AllowedOptions> syn::parse::Parse for Options<A> {
                     let _eq = Equals::parse(input)?;
                     let lit = syn::LitInt::parse(input)?;
                     let value = lit.base10_parse::<usize>()?;
-                    if let Some(old) = std::mem::replace(&mut options.lru, Some(value)) {
+                    if let Some(old) = options.lru.replace(value) {
                         return Err(syn::Error::new(old.span(), "option `lru` provided twice"));
                     }
                 } else {
@@ -248,8 +468,7 @@ impl<A: AllowedOptions> syn::parse::Parse for Options<A> {
                 if A::CONSTRUCTOR_NAME {
                     let _eq = Equals::parse(input)?;
                     let ident = syn::Ident::parse(input)?;
-                    if let Some(old) = std::mem::replace(&mut options.constructor_name, Some(ident))
-                    {
+                    if let Some(old) = options.constructor_name.replace(ident) {
                         return Err(syn::Error::new(
                             old.span(),
                             "option `constructor` provided twice",
@@ -261,11 +480,298 @@ impl<A: AllowedOptions> syn::parse::Parse for Options<A> {
                         "`constructor` option not allowed here",
                     ));
                 }
+            } else if ident == "includes" {
+                if A::INCLUDES {
+                    let content;
+                    syn::parenthesized!(content in input);
+                    let paths = syn::punctuated::Punctuated::parse_terminated(&content)?;
+                    if let Some(old) = options.includes.replace(paths) {
+                        return Err(syn::Error::new(
+                            old.span(),
+                            "option `includes` provided twice",
+                        ));
+                    }
+                } else {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "`includes` option not allowed here",
+                    ));
+                }
+            } else if ident == "cycle_err" {
+                if A::CYCLE_ERR {
+                    if let Some(old) = options.cycle_err.replace(ident) {
+                        return Err(syn::Error::new(
+                            old.span(),
+                            "option `cycle_err` provided twice",
+                        ));
+                    }
+                } else {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "`cycle_err` option not allowed here",
+                    ));
+                }
+            } else if ident == "retry_err" {
+                if A::RETRY_ERR {
+                    if let Some(old) = options.retry_err.replace(ident) {
+                        return Err(syn::Error::new(
+                            old.span(),
+                            "option `retry_err` provided twice",
+                        ));
+                    }
+                } else {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "`retry_err` option not allowed here",
+                    ));
+                }
+            } else if ident == "update" {
+                if A::UPDATE {
+                    if let Some(old) = options.update.replace(ident) {
+                        return Err(syn::Error::new(
+                            old.span(),
+                            "option `update` provided twice",
+                        ));
+                    }
+                } else {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "`update` option not allowed here",
+                    ));
+                }
+            } else if ident == "storage" {
+                if A::STORAGE {
+                    let _eq = Equals::parse(input)?;
+                    let ident = syn::Ident::parse(input)?;
+                    if ident != "hash" && ident != "dense" {
+                        return Err(syn::Error::new(ident.span(), "expected `hash` or `dense`"));
+                    }
+                    if let Some(old) = options.storage.replace(ident) {
+                        return Err(syn::Error::new(
+                            old.span(),
+                            "option `storage` provided twice",
+                        ));
+                    }
+                } else {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "`storage` option not allowed here",
+                    ));
+                }
+            } else if ident == "volatile" {
+                if A::VOLATILE {
+                    if let Some(old) = options.volatile.replace(ident) {
+                        return Err(syn::Error::new(
+                            old.span(),
+                            "option `volatile` provided twice",
+                        ));
+                    }
+                } else {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "`volatile` option not allowed here",
+                    ));
+                }
+            } else if ident == "heap_size" {
+                if A::HEAP_SIZE {
+                    if let Some(old) = options.heap_size.replace(ident) {
+                        return Err(syn::Error::new(
+                            old.span(),
+                            "option `heap_size` provided twice",
+                        ));
+                    }
+                } else {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "`heap_size` option not allowed here",
+                    ));
+                }
+            } else if ident == "for" {
+                if A::INSTANCES {
+                    let content;
+                    syn::parenthesized!(content in input);
+                    let types = syn::punctuated::Punctuated::parse_terminated(&content)?;
+                    if let Some(old) = options.instances.replace(types) {
+                        return Err(syn::Error::new(old.span(), "option `for` provided twice"));
+                    }
+                } else {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "`for` option not allowed here",
+                    ));
+                }
+            } else if ident == "history" {
+                if A::HISTORY {
+                    let _eq = Equals::parse(input)?;
+                    let lit = syn::LitInt::parse(input)?;
+                    let value = lit.base10_parse::<usize>()?;
+                    if let Some(old) = options.history.replace(value) {
+                        return Err(syn::Error::new(
+                            old.span(),
+                            "option `history` provided twice",
+                        ));
+                    }
+                } else {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "`history` option not allowed here",
+                    ));
+                }
+            } else if ident == "gc" {
+                if A::GC {
+                    if let Some(old) = options.gc.replace(ident) {
+                        return Err(syn::Error::new(old.span(), "option `gc` provided twice"));
+                    }
+                } else {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "`gc` option not allowed here",
+                    ));
+                }
+            } else if ident == "weak" {
+                if A::WEAK {
+                    if let Some(old) = options.weak.replace(ident) {
+                        return Err(syn::Error::new(old.span(), "option `weak` provided twice"));
+                    }
+                } else {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "`weak` option not allowed here",
+                    ));
+                }
+            } else if ident == "firewall" {
+                if A::FIREWALL {
+                    if let Some(old) = options.firewall.replace(ident) {
+                        return Err(syn::Error::new(
+                            old.span(),
+                            "option `firewall` provided twice",
+                        ));
+                    }
+                } else {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "`firewall` option not allowed here",
+                    ));
+                }
+            } else if ident == "capacity" {
+                if A::CAPACITY {
+                    let _eq = Equals::parse(input)?;
+                    let lit = syn::LitInt::parse(input)?;
+                    let value = lit.base10_parse::<usize>()?;
+                    if let Some(old) = options.capacity.replace(value) {
+                        return Err(syn::Error::new(
+                            old.span(),
+                            "option `capacity` provided twice",
+                        ));
+                    }
+                } else {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "`capacity` option not allowed here",
+                    ));
+                }
+            } else if ident == "extends" {
+                if A::EXTENDS {
+                    let _eq = Equals::parse(input)?;
+                    let path = syn::Path::parse(input)?;
+                    if let Some(old) = options.extends.replace(path) {
+                        return Err(syn::Error::new(
+                            old.span(),
+                            "option `extends` provided twice",
+                        ));
+                    }
+                } else {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "`extends` option not allowed here",
+                    ));
+                }
+            } else if ident == "collect" {
+                if A::COLLECT {
+                    let _eq = Equals::parse(input)?;
+                    let ty = syn::Type::parse(input)?;
+                    if let Some(old) = options.collect.replace(ty) {
+                        return Err(syn::Error::new(
+                            old.span(),
+                            "option `collect` provided twice",
+                        ));
+                    }
+                } else {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "`collect` option not allowed here",
+                    ));
+                }
+            } else if ident == "remote_cache" {
+                if A::REMOTE_CACHE {
+                    let _eq = Equals::parse(input)?;
+                    let path = syn::Path::parse(input)?;
+                    if let Some(old) = options.remote_cache.replace(path) {
+                        return Err(syn::Error::new(
+                            old.span(),
+                            "option `remote_cache` provided twice",
+                        ));
+                    }
+                } else {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "`remote_cache` option not allowed here",
+                    ));
+                }
+            } else if ident == "elements" {
+                if A::ELEMENTS {
+                    let _eq = Equals::parse(input)?;
+                    let name = syn::Ident::parse(input)?;
+                    if let Some(old) = options.elements.replace(name) {
+                        return Err(syn::Error::new(
+                            old.span(),
+                            "option `elements` provided twice",
+                        ));
+                    }
+                } else {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "`elements` option not allowed here",
+                    ));
+                }
+            } else if ident == "cycle_initial" {
+                if A::CYCLE_INITIAL {
+                    let _eq = Equals::parse(input)?;
+                    let path = syn::Path::parse(input)?;
+                    if let Some(old) = options.cycle_initial.replace(path) {
+                        return Err(syn::Error::new(
+                            old.span(),
+                            "option `cycle_initial` provided twice",
+                        ));
+                    }
+                } else {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "`cycle_initial` option not allowed here",
+                    ));
+                }
+            } else if ident == "no_clone" {
+                if A::NO_CLONE {
+                    if let Some(old) = options.no_clone.replace(ident) {
+                        return Err(syn::Error::new(
+                            old.span(),
+                            "option `no_clone` provided twice",
+                        ));
+                    }
+                } else {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "`no_clone` option not allowed here",
+                    ));
+                }
             } else {
-                return Err(syn::Error::new(
-                    ident.span(),
-                    format!("unrecognized option `{}`", ident),
-                ));
+                let message = match suggestion::<A>(&ident.to_string()) {
+                    Some(name) => {
+                        format!("unrecognized option `{}`, did you mean `{}`?", ident, name)
+                    }
+                    None => format!("unrecognized option `{}`", ident),
+                };
+                return Err(syn::Error::new(ident.span(), message));
             }
 
             if input.is_empty() {
@@ -278,3 +784,67 @@ impl<A: AllowedOptions> syn::parse::Parse for Options<A> {
         Ok(options)
     }
 }
+
+/// The names of the options, and whether `A` allows them.
+fn option_names<A: AllowedOptions>() -> [(&'static str, bool); 31] {
+    [
+        ("return_ref", A::RETURN_REF),
+        ("return_deref", A::RETURN_DEREF),
+        ("specify", A::SPECIFY),
+        ("no_eq", A::NO_EQ),
+        ("compare_with", A::COMPARE_WITH),
+        ("singleton", A::SINGLETON),
+        ("jar", A::JAR),
+        ("data", A::DATA),
+        ("db", A::DB),
+        ("recovery_fn", A::RECOVERY_FN),
+        ("lru", A::LRU),
+        ("constructor", A::CONSTRUCTOR_NAME),
+        ("includes", A::INCLUDES),
+        ("cycle_err", A::CYCLE_ERR),
+        ("retry_err", A::RETRY_ERR),
+        ("update", A::UPDATE),
+        ("storage", A::STORAGE),
+        ("volatile", A::VOLATILE),
+        ("heap_size", A::HEAP_SIZE),
+        ("for", A::INSTANCES),
+        ("history", A::HISTORY),
+        ("gc", A::GC),
+        ("weak", A::WEAK),
+        ("firewall", A::FIREWALL),
+        ("capacity", A::CAPACITY),
+        ("extends", A::EXTENDS),
+        ("collect", A::COLLECT),
+        ("remote_cache", A::REMOTE_CACHE),
+        ("elements", A::ELEMENTS),
+        ("cycle_initial", A::CYCLE_INITIAL),
+        ("no_clone", A::NO_CLONE),
+    ]
+}
+
+/// Returns the option allowed by `A` whose name is the closest to `unknown`,
+/// if it is close enough to be a likely typo.
+fn suggestion<A: AllowedOptions>(unknown: &str) -> Option<&'static str> {
+    option_names::<A>()
+        .into_iter()
+        .filter(|&(_, allowed)| allowed)
+        .map(|(name, _)| (edit_distance(unknown, name), name))
+        .filter(|&(distance, name)| distance <= std::cmp::max(name.len(), 3) / 3)
+        .min()
+        .map(|(_, name)| name)
+}
+
+/// The Levenshtein distance between `a` and `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, &b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
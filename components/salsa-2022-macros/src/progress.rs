@@ -0,0 +1,194 @@
+use syn::ItemStruct;
+
+// #[salsa::progress(jar = Jar0)]
+// struct Progress(DataType);
+
+pub(crate) fn progress(
+    args: proc_macro::TokenStream,
+    input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let args = syn::parse_macro_input!(args as Args);
+    let struct_impl = syn::parse_macro_input!(input as ItemStruct);
+    progress_contents(&args, &struct_impl)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+type Args = crate::options::Options<Progress>;
+
+struct Progress;
+
+impl crate::options::AllowedOptions for Progress {
+    const RETURN_REF: bool = false;
+
+    const RETURN_DEREF: bool = false;
+
+    const SPECIFY: bool = false;
+
+    const NO_EQ: bool = false;
+
+    const COMPARE_WITH: bool = false;
+
+    const SINGLETON: bool = false;
+
+    const JAR: bool = true;
+
+    const DATA: bool = false;
+
+    const DB: bool = false;
+
+    const RECOVERY_FN: bool = false;
+
+    const LRU: bool = false;
+
+    const CONSTRUCTOR_NAME: bool = false;
+
+    const INCLUDES: bool = false;
+
+    const CYCLE_ERR: bool = false;
+
+    const RETRY_ERR: bool = false;
+
+    const UPDATE: bool = false;
+
+    const STORAGE: bool = false;
+
+    const VOLATILE: bool = false;
+
+    const HEAP_SIZE: bool = false;
+
+    const INSTANCES: bool = false;
+
+    const HISTORY: bool = false;
+
+    const GC: bool = false;
+
+    const WEAK: bool = false;
+
+    const FIREWALL: bool = false;
+
+    const CAPACITY: bool = false;
+
+    const EXTENDS: bool = false;
+
+    const COLLECT: bool = false;
+
+    const REMOTE_CACHE: bool = false;
+
+    const ELEMENTS: bool = false;
+
+    const CYCLE_INITIAL: bool = false;
+
+    const NO_CLONE: bool = false;
+}
+
+fn progress_contents(
+    args: &Args,
+    struct_item: &syn::ItemStruct,
+) -> syn::Result<proc_macro2::TokenStream> {
+    // We expect a single anonymous field.
+    let data_ty = data_ty(struct_item)?;
+    let struct_name = &struct_item.ident;
+    let struct_ty = &parse_quote! {#struct_name};
+
+    let inherent_impl = inherent_impl(args, struct_ty, data_ty);
+    let ingredients_for_impl = ingredients_for_impl(args, struct_name, data_ty);
+    let struct_item_out = struct_item_out(args, struct_item, data_ty);
+
+    Ok(quote! {
+        #inherent_impl
+        #ingredients_for_impl
+        #struct_item_out
+    })
+}
+
+fn data_ty(struct_item: &syn::ItemStruct) -> syn::Result<&syn::Type> {
+    if let syn::Fields::Unnamed(fields) = &struct_item.fields {
+        if fields.unnamed.len() != 1 {
+            Err(syn::Error::new(
+                struct_item.ident.span(),
+                "progress structs should have only one anonymous field",
+            ))
+        } else {
+            Ok(&fields.unnamed[0].ty)
+        }
+    } else {
+        Err(syn::Error::new(
+            struct_item.ident.span(),
+            "progress structs should have only one anonymous field",
+        ))
+    }
+}
+
+fn struct_item_out(
+    _args: &Args,
+    struct_item: &syn::ItemStruct,
+    data_ty: &syn::Type,
+) -> syn::ItemStruct {
+    let mut struct_item_out = struct_item.clone();
+    struct_item_out.fields = syn::Fields::Unnamed(parse_quote! {
+            (std::marker::PhantomData<#data_ty>)
+    });
+    struct_item_out
+}
+
+fn inherent_impl(args: &Args, struct_ty: &syn::Type, data_ty: &syn::Type) -> syn::ItemImpl {
+    let jar_ty = args.jar_ty();
+    parse_quote! {
+        impl #struct_ty {
+            pub fn report<DB: ?Sized>(db: &DB, data: #data_ty)
+            where
+                DB: salsa::storage::HasJar<#jar_ty>,
+            {
+                let (jar, runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(db);
+                let ingredients = <#jar_ty as salsa::storage::HasIngredientsFor< #struct_ty >>::ingredient(jar);
+                ingredients.report(runtime, data)
+            }
+
+            pub fn subscribe<DB: ?Sized>(db: &DB) -> salsa::progress::Receiver<salsa::progress::Progress<#data_ty>>
+            where
+                DB: salsa::storage::HasJar<#jar_ty>,
+            {
+                let (jar, _) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(db);
+                let ingredients = <#jar_ty as salsa::storage::HasIngredientsFor< #struct_ty >>::ingredient(jar);
+                ingredients.subscribe()
+            }
+        }
+    }
+}
+
+fn ingredients_for_impl(
+    args: &Args,
+    struct_name: &syn::Ident,
+    data_ty: &syn::Type,
+) -> syn::ItemImpl {
+    let jar_ty = args.jar_ty();
+    let debug_name = crate::literal(struct_name);
+    parse_quote! {
+        impl salsa::storage::IngredientsFor for #struct_name {
+            type Ingredients = salsa::progress::ProgressIngredient<#data_ty>;
+            type Jar = #jar_ty;
+
+            fn create_ingredients<DB>(routes: &mut salsa::routes::Routes<DB>) -> Self::Ingredients
+            where
+                DB: salsa::DbWithJar<Self::Jar> + salsa::storage::JarFromJars<Self::Jar>,
+            {
+                    let index = routes.push(
+                        |jars| {
+                            let jar = <DB as salsa::storage::JarFromJars<Self::Jar>>::jar_from_jars(jars);
+                            <_ as salsa::storage::HasIngredientsFor<Self>>::ingredient(jar)
+                        },
+                        |jars| {
+                            let jar = <DB as salsa::storage::JarFromJars<Self::Jar>>::jar_from_jars_mut(jars);
+                            <_ as salsa::storage::HasIngredientsFor<Self>>::ingredient_mut(jar)
+                        },
+                    );
+                    salsa::progress::ProgressIngredient::new(index, #debug_name)
+            }
+
+            fn fork_ingredients(ingredients: &Self::Ingredients) -> Option<Self::Ingredients> {
+                ingredients.fork()
+            }
+        }
+    }
+}
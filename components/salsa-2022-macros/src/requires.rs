@@ -0,0 +1,84 @@
+use syn::{parse::Parser, punctuated::Punctuated, spanned::Spanned, Token};
+
+// #[salsa::requires(JarB, JarC)]
+// fn helper<DB: ?Sized>(db: &DB, ...) { ... }
+
+pub(crate) fn requires(
+    args: proc_macro::TokenStream,
+    input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let jars = match Punctuated::<syn::Path, Token![,]>::parse_terminated.parse(args) {
+        Ok(jars) => jars,
+        Err(err) => return err.into_compile_error().into(),
+    };
+    let item_fn = syn::parse_macro_input!(input as syn::ItemFn);
+    requires_contents(jars.into_iter().collect(), item_fn)
+        .map(|item_fn| quote! { #item_fn })
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Adds the bounds needed for `item_fn` to use the jars `jars` through its database,
+/// which is its first argument:
+///
+/// * if the database type is a type parameter `DB` of the function, each jar gets a
+///   `Jar: salsa::jar::JarInDb<DB>` bound, so that `salsa::jar::db_for_jar` can be
+///   used to call its functions;
+/// * otherwise (typically, for `&dyn Db`), the database type is checked to include
+///   each jar, so that a missing jar is reported on the function itself rather than
+///   where its functions are called.
+fn requires_contents(jars: Vec<syn::Path>, mut item_fn: syn::ItemFn) -> syn::Result<syn::ItemFn> {
+    let (db_ident, db_ty) = db_arg(&item_fn)?;
+
+    let type_param = match &db_ty {
+        syn::Type::Path(path) if path.qself.is_none() => {
+            path.path.get_ident().cloned().filter(|ident| {
+                item_fn
+                    .sig
+                    .generics
+                    .type_params()
+                    .any(|param| param.ident == *ident)
+            })
+        }
+        _ => None,
+    };
+
+    match type_param {
+        Some(db_param) => {
+            let where_clause = item_fn.sig.generics.make_where_clause();
+            for jar in &jars {
+                where_clause
+                    .predicates
+                    .push(parse_quote_spanned!(jar.span() => #jar: salsa::jar::JarInDb<#db_param>));
+            }
+        }
+        None => {
+            let checks: Vec<syn::Stmt> = jars
+                .iter()
+                .map(|jar| {
+                    parse_quote_spanned! {jar.span() =>
+                        salsa::storage::require_jar::<#jar, _>(&*#db_ident);
+                    }
+                })
+                .collect();
+            item_fn.block.stmts.splice(0..0, checks);
+        }
+    }
+
+    Ok(item_fn)
+}
+
+/// Returns the name and the (referenced) type of the database argument.
+fn db_arg(item_fn: &syn::ItemFn) -> syn::Result<(syn::Ident, syn::Type)> {
+    if let Some(syn::FnArg::Typed(pat_ty)) = item_fn.sig.inputs.first() {
+        if let (syn::Pat::Ident(pat_ident), syn::Type::Reference(reference)) =
+            (&*pat_ty.pat, &*pat_ty.ty)
+        {
+            return Ok((pat_ident.ident.clone(), (*reference.elem).clone()));
+        }
+    }
+    Err(syn::Error::new(
+        item_fn.sig.span(),
+        "`requires` expects the first argument to be a reference to the database, like `db: &dyn Db`",
+    ))
+}
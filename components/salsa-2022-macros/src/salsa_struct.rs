@@ -138,15 +138,40 @@ impl<A: AllowedOptions> SalsaStruct<A> {
         self.args.jar_ty()
     }
 
+    /// The number of revisions for which the old values of the fields are kept,
+    /// as given by the `history` option
+    pub(crate) fn history(&self) -> Option<usize> {
+        self.args.history
+    }
+
+    /// checks if the "gc" flag was set
+    pub(crate) fn is_gc(&self) -> bool {
+        self.args.gc.is_some()
+    }
+
     /// checks if the "singleton" flag was set
     pub(crate) fn is_isingleton(&self) -> bool {
         self.args.singleton.is_some()
     }
 
+    /// The database type taken by the generated methods: `dyn Db` for the trait
+    /// given with the `db = Db` option, or else the jar's `DynDb`.
     pub(crate) fn db_dyn_ty(&self) -> syn::Type {
-        let jar_ty = self.jar_ty();
-        parse_quote! {
-            <#jar_ty as salsa::jar::Jar<'_>>::DynDb
+        self.db_dyn_ty_with_lifetime(&parse_quote!('_))
+    }
+
+    /// Like [`Self::db_dyn_ty`], with an explicit lifetime for the database.
+    pub(crate) fn db_dyn_ty_with_lifetime(&self, lifetime: &syn::Lifetime) -> syn::Type {
+        match &self.args.db_path {
+            Some(db_path) => parse_quote! {
+                (dyn #db_path + #lifetime)
+            },
+            None => {
+                let jar_ty = self.jar_ty();
+                parse_quote! {
+                    <#jar_ty as salsa::jar::Jar<#lifetime>>::DynDb
+                }
+            }
         }
     }
 
@@ -162,6 +187,16 @@ impl<A: AllowedOptions> SalsaStruct<A> {
         }
     }
 
+    /// The name of the data enum of an interned enum, which users match on
+    /// (this comes from the `data = Foo` option or, if that is not provided,
+    /// by concatenating `Data` to the name of the enum).
+    pub(crate) fn public_data_ident(&self) -> syn::Ident {
+        match &self.args.data {
+            Some(d) => d.clone(),
+            None => syn::Ident::new(&format!("{}Data", self.id_ident()), self.id_ident().span()),
+        }
+    }
+
     /// Generate `struct Foo(Id)`
     pub(crate) fn id_struct(&self) -> syn::ItemStruct {
         let ident = self.id_ident();
@@ -254,17 +289,22 @@ impl<A: AllowedOptions> SalsaStruct<A> {
                     ef.name(),
                 ));
 
-                let should_backdate_value_fn = configuration::should_backdate_value_fn(value_field_backdate);
+                let should_backdate_value_fn = configuration::should_backdate_value_fn(value_field_backdate, value_field_ty);
+                let storage_ty = configuration::storage_ty(None);
+                let clone_value_fn = configuration::clone_value_fn(value_field_ty);
                 let item_impl: syn::ItemImpl = parse_quote! {
                     impl salsa::function::Configuration for #config_name {
                         type Jar = #jar_ty;
                         type SalsaStruct = #ident;
                         type Key = #ident;
                         type Value = #value_field_ty;
+                        type Storage = #storage_ty;
                         const CYCLE_STRATEGY: salsa::cycle::CycleRecoveryStrategy = salsa::cycle::CycleRecoveryStrategy::Panic;
 
                         #should_backdate_value_fn
 
+                        #clone_value_fn
+
                         fn execute(db: &salsa::function::DynDb<Self>, key: Self::Key) -> Self::Value {
                             panic!(#execute_string)
                         }
@@ -297,6 +337,30 @@ impl<A: AllowedOptions> SalsaStruct<A> {
         }
     }
 
+    /// Generate `impl salsa::Update for Foo`
+    pub(crate) fn update_impl(&self) -> syn::ItemImpl {
+        let ident = self.id_ident();
+        parse_quote! {
+            impl salsa::update::Update for #ident {
+                fn maybe_update(old_value: &mut Self, new_value: Self) -> bool {
+                    salsa::update::update_fallback(old_value, new_value)
+                }
+            }
+        }
+    }
+
+    /// Generate `impl salsa::HeapSize for Foo`
+    pub(crate) fn heap_size_impl(&self) -> syn::ItemImpl {
+        let ident = self.id_ident();
+        parse_quote! {
+            impl salsa::memory::HeapSize for #ident {
+                fn heap_size(&self) -> usize {
+                    0
+                }
+            }
+        }
+    }
+
     /// Generate `impl salsa::DebugWithDb for Foo`
     pub(crate) fn as_debug_with_db_impl(&self) -> syn::ItemImpl {
         let ident = self.id_ident();
@@ -373,6 +437,24 @@ impl<A: AllowedOptions> SalsaStruct<A> {
 
         Ok(())
     }
+
+    /// Disallows `#[default]` fields, which only make sense for inputs.
+    ///
+    /// # Parameters
+    ///
+    /// * `kind`, the attribute name (e.g., `tracked` or `interned`)
+    pub(crate) fn disallow_default_fields(&self, kind: &str) -> syn::Result<()> {
+        for ef in self.all_fields() {
+            if ef.has_default_attr {
+                return Err(syn::Error::new(
+                    ef.name().span(),
+                    format!("`#[default]` cannot be used with `#[salsa::{kind}]`"),
+                ));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[allow(clippy::type_complexity)]
@@ -380,6 +462,7 @@ pub(crate) const FIELD_OPTION_ATTRIBUTES: &[(&str, fn(&syn::Attribute, &mut Sals
     ("id", |_, ef| ef.has_id_attr = true),
     ("return_ref", |_, ef| ef.has_ref_attr = true),
     ("no_eq", |_, ef| ef.has_no_eq_attr = true),
+    ("default", |_, ef| ef.has_default_attr = true),
     ("get", |attr, ef| {
         ef.get_name = attr.parse_args().unwrap();
     }),
@@ -394,6 +477,7 @@ pub(crate) struct SalsaField {
     pub(crate) has_id_attr: bool,
     pub(crate) has_ref_attr: bool,
     pub(crate) has_no_eq_attr: bool,
+    pub(crate) has_default_attr: bool,
     get_name: syn::Ident,
     set_name: syn::Ident,
 }
@@ -419,6 +503,7 @@ impl SalsaField {
             has_id_attr: false,
             has_ref_attr: false,
             has_no_eq_attr: false,
+            has_default_attr: false,
             get_name,
             set_name,
         };
@@ -25,28 +25,53 @@ pub(crate) fn tracked_fn(
         ));
     }
 
-    if let Some(s) = &args.specify {
-        if function_type(&item_fn) == FunctionType::RequiresInterning {
+    if let Some(collect_ty) = &args.collect {
+        collect_into(&mut item_fn, collect_ty)?;
+    }
+
+    if let Some(instances) = &args.instances {
+        if let Some(no_clone) = &args.no_clone {
             return Err(syn::Error::new(
-                s.span(),
-                "tracked function takes too many arguments to have its value set with `specify`",
+                no_clone.span(),
+                "`no_clone` and `for` cannot be used together",
             ));
         }
-
-        if args.lru.is_some() {
+        if let Some(elements) = &args.elements {
             return Err(syn::Error::new(
-                s.span(),
-                "`specify` and `lru` cannot be used together",
+                elements.span(),
+                "`elements` and `for` cannot be used together",
             ));
         }
+        return generic_tracked_fn(&args, item_fn, instances);
     }
+    if let Some(type_param) = item_fn.sig.generics.type_params().next() {
+        return Err(syn::Error::new(
+            type_param.span(),
+            "generic tracked functions must list the types they are used with in `for(...)`",
+        ));
+    }
+
+    let elements_fn = match &args.elements {
+        Some(elements) => Some(elements_fn(&args, &item_fn, elements)?),
+        None => None,
+    };
 
     let (config_ty, fn_struct) = fn_struct(&args, &item_fn)?;
+
+    // With `no_clone`, the value is only read by reference, with `<fn>::get`.
+    if args.no_clone.is_some() {
+        return Ok(quote! {
+            #fn_struct
+        });
+    }
+
     *item_fn.block = getter_fn(&args, &mut item_fn.sig, item_fn.block.span(), &config_ty)?;
 
     Ok(quote! {
         #fn_struct
 
+        #elements_fn
+
         // we generate a `'db` lifetime that clippy
         // sometimes doesn't like
         #[allow(clippy::needless_lifetimes)]
@@ -54,17 +79,198 @@ pub(crate) fn tracked_fn(
     })
 }
 
-type FnArgs = Options<TrackedFn>;
+/// Expands a generic tracked function with the `for(...)` option: each type
+/// it lists gets its own copy of the function, with the type parameter replaced
+/// by that type, named `<fn>_<type>` (e.g. `lower_Wasm`). The copies are ordinary
+/// tracked functions, to be listed in the jar. The generic function itself becomes
+/// a shim that calls the copy for its type parameter, which it finds by `TypeId`.
+fn generic_tracked_fn(
+    args: &FnArgs,
+    mut item_fn: syn::ItemFn,
+    instances: &syn::punctuated::Punctuated<syn::Type, Token![,]>,
+) -> syn::Result<TokenStream> {
+    let mut type_params = item_fn.sig.generics.type_params();
+    let type_param = match (type_params.next(), type_params.next()) {
+        (Some(type_param), None) => type_param.ident.clone(),
+        _ => {
+            return Err(syn::Error::new(
+                item_fn.sig.generics.span(),
+                "tracked functions with the `for` option must have exactly one type parameter",
+            ))
+        }
+    };
+
+    let value_ty = configuration::value_ty(&item_fn.sig);
+    let (db_var, arg_names) = fn_args(&item_fn)?;
+    let mut shim_sig = item_fn.sig.clone();
+    let return_value = make_getter_sig(args, &mut shim_sig)?.value(quote! { __value });
+    let mut fn_structs = vec![];
+    let mut instance_calls = vec![];
+    for ty in instances {
+        let ty_name = match ty {
+            syn::Type::Path(type_path) if type_path.qself.is_none() => {
+                &type_path.path.segments.last().unwrap().ident
+            }
+            _ => {
+                return Err(syn::Error::new(
+                    ty.span(),
+                    "the types listed in `for` must be named types",
+                ))
+            }
+        };
+
+        let mut instance_fn = item_fn.clone();
+        instance_fn.sig.ident = syn::Ident::new(
+            &format!("{}_{}", item_fn.sig.ident, ty_name),
+            item_fn.sig.ident.span(),
+        );
+        instance_fn.sig.generics.params = instance_fn
+            .sig
+            .generics
+            .params
+            .into_iter()
+            .filter(|param| !matches!(param, syn::GenericParam::Type(_)))
+            .collect();
+        instance_fn.sig.generics.where_clause = None;
+        SubstituteTypeParam {
+            param: &type_param,
+            ty,
+        }
+        .visit_item_fn_mut(&mut instance_fn);
+
+        let (config_ty, fn_struct) = fn_struct(args, &instance_fn)?;
+        fn_structs.push(fn_struct);
+        instance_calls.push(quote! {
+            if __type_id == std::any::TypeId::of::<#ty>() {
+                let __value = #config_ty::get(#db_var, #(#arg_names,)*);
+                let __value = <dyn std::any::Any>::downcast_ref::<#value_ty>(__value).unwrap();
+                return #return_value;
+            }
+        });
+    }
+
+    item_fn.sig = shim_sig;
+    // The instance is found by the `TypeId` of the type parameter.
+    let param = item_fn.sig.generics.type_params_mut().next().unwrap();
+    param.colon_token.get_or_insert_with(Default::default);
+    param.bounds.push(parse_quote! { 'static });
+    let fn_name = item_fn.sig.ident.to_string();
+    item_fn.block = parse_quote_spanned! {
+        item_fn.block.span() => {
+            let __type_id = std::any::TypeId::of::<#type_param>();
+            #(#instance_calls)*
+            panic!(
+                "`{}` is not instantiated for `{}`: add it to the `for` option",
+                #fn_name,
+                std::any::type_name::<#type_param>(),
+            )
+        }
+    };
+
+    Ok(quote! {
+        #(#fn_structs)*
+
+        #[allow(clippy::needless_lifetimes)]
+        #item_fn
+    })
+}
+
+/// Replaces a type parameter with a type, in the copy of a generic tracked
+/// function made for that type. Paths starting with the type parameter, like
+/// `T::new`, become qualified paths, like `<Wasm>::new`.
+struct SubstituteTypeParam<'a> {
+    param: &'a syn::Ident,
+    ty: &'a syn::Type,
+}
+
+impl SubstituteTypeParam<'_> {
+    fn qualify(&self, qself: &mut Option<syn::QSelf>, path: &mut syn::Path) {
+        let starts_with_param = qself.is_none()
+            && path.leading_colon.is_none()
+            && path.segments.len() > 1
+            && path.segments[0].ident == *self.param
+            && path.segments[0].arguments.is_empty();
+        if starts_with_param {
+            path.segments = path.segments.iter().skip(1).cloned().collect();
+            path.leading_colon = Some(Default::default());
+            *qself = Some(syn::QSelf {
+                lt_token: Default::default(),
+                ty: Box::new(self.ty.clone()),
+                position: 0,
+                as_token: None,
+                gt_token: Default::default(),
+            });
+        }
+    }
+
+    fn substitute_in_tokens(&self, tokens: TokenStream) -> TokenStream {
+        tokens
+            .into_iter()
+            .flat_map(|token| -> TokenStream {
+                match token {
+                    proc_macro2::TokenTree::Ident(i) if i == *self.param => {
+                        let ty = self.ty;
+                        quote! { #ty }
+                    }
+                    proc_macro2::TokenTree::Group(group) => {
+                        let mut substituted = proc_macro2::Group::new(
+                            group.delimiter(),
+                            self.substitute_in_tokens(group.stream()),
+                        );
+                        substituted.set_span(group.span());
+                        proc_macro2::TokenTree::Group(substituted).into()
+                    }
+                    token => token.into(),
+                }
+            })
+            .collect()
+    }
+}
+
+impl VisitMut for SubstituteTypeParam<'_> {
+    fn visit_type_mut(&mut self, ty: &mut syn::Type) {
+        if let syn::Type::Path(type_path) = ty {
+            if type_path.qself.is_none() && type_path.path.is_ident(self.param) {
+                *ty = self.ty.clone();
+                return;
+            }
+        }
+        syn::visit_mut::visit_type_mut(self, ty);
+    }
+
+    fn visit_type_path_mut(&mut self, type_path: &mut syn::TypePath) {
+        self.qualify(&mut type_path.qself, &mut type_path.path);
+        syn::visit_mut::visit_type_path_mut(self, type_path);
+    }
+
+    fn visit_expr_path_mut(&mut self, expr_path: &mut syn::ExprPath) {
+        self.qualify(&mut expr_path.qself, &mut expr_path.path);
+        syn::visit_mut::visit_expr_path_mut(self, expr_path);
+    }
+
+    // The arguments of macros like `format!` are not parsed, so we
+    // have to replace the type parameter in their tokens.
+    fn visit_macro_mut(&mut self, mac: &mut syn::Macro) {
+        syn::visit_mut::visit_macro_mut(self, mac);
+        mac.tokens = self.substitute_in_tokens(std::mem::take(&mut mac.tokens));
+    }
+}
+
+pub(crate) type FnArgs = Options<TrackedFn>;
 
-struct TrackedFn;
+pub(crate) struct TrackedFn;
 
 impl crate::options::AllowedOptions for TrackedFn {
     const RETURN_REF: bool = true;
 
+    const RETURN_DEREF: bool = true;
+
     const SPECIFY: bool = true;
 
     const NO_EQ: bool = true;
 
+    const COMPARE_WITH: bool = true;
+
     const SINGLETON: bool = false;
 
     const JAR: bool = true;
@@ -78,6 +284,44 @@ impl crate::options::AllowedOptions for TrackedFn {
     const LRU: bool = true;
 
     const CONSTRUCTOR_NAME: bool = false;
+
+    const INCLUDES: bool = false;
+
+    const CYCLE_ERR: bool = true;
+
+    const RETRY_ERR: bool = true;
+
+    const UPDATE: bool = true;
+
+    const STORAGE: bool = true;
+
+    const VOLATILE: bool = true;
+
+    const HEAP_SIZE: bool = true;
+
+    const INSTANCES: bool = true;
+
+    const HISTORY: bool = false;
+
+    const GC: bool = false;
+
+    const WEAK: bool = true;
+
+    const FIREWALL: bool = true;
+
+    const CAPACITY: bool = true;
+
+    const EXTENDS: bool = false;
+
+    const COLLECT: bool = true;
+
+    const REMOTE_CACHE: bool = true;
+
+    const ELEMENTS: bool = true;
+
+    const CYCLE_INITIAL: bool = true;
+
+    const NO_CLONE: bool = true;
 }
 
 type ImplArgs = Options<TrackedImpl>;
@@ -113,14 +357,7 @@ pub(crate) fn tracked_impl(
                 syn::ImplItem::Method(item_method) => item_method,
                 _ => return None,
             };
-            let salsa_tracked_attr = item_method.attrs.iter().position(|attr| {
-                let path = &attr.path.segments;
-                path.len() == 2
-                    && path[0].arguments == syn::PathArguments::None
-                    && path[0].ident == "salsa"
-                    && path[1].arguments == syn::PathArguments::None
-                    && path[1].ident == "tracked"
-            })?;
+            let salsa_tracked_attr = item_method.attrs.iter().position(is_salsa_tracked)?;
             let salsa_tracked_attr = item_method.attrs.remove(salsa_tracked_attr);
             let inner_args = if !salsa_tracked_attr.tokens.is_empty() {
                 salsa_tracked_attr.parse_args()
@@ -158,15 +395,29 @@ pub(crate) fn tracked_impl(
     })
 }
 
+/// True if `attr` is `#[salsa::tracked]`, with or without options.
+pub(crate) fn is_salsa_tracked(attr: &syn::Attribute) -> bool {
+    let path = &attr.path.segments;
+    path.len() == 2
+        && path[0].arguments == syn::PathArguments::None
+        && path[0].ident == "salsa"
+        && path[1].arguments == syn::PathArguments::None
+        && path[1].ident == "tracked"
+}
+
 struct TrackedImpl;
 
 impl crate::options::AllowedOptions for TrackedImpl {
     const RETURN_REF: bool = false;
 
+    const RETURN_DEREF: bool = false;
+
     const SPECIFY: bool = false;
 
     const NO_EQ: bool = false;
 
+    const COMPARE_WITH: bool = false;
+
     const JAR: bool = true;
 
     const DATA: bool = false;
@@ -179,6 +430,44 @@ impl crate::options::AllowedOptions for TrackedImpl {
 
     const CONSTRUCTOR_NAME: bool = false;
 
+    const INCLUDES: bool = false;
+
+    const CYCLE_ERR: bool = false;
+
+    const RETRY_ERR: bool = false;
+
+    const UPDATE: bool = false;
+
+    const STORAGE: bool = false;
+
+    const VOLATILE: bool = false;
+
+    const HEAP_SIZE: bool = false;
+
+    const INSTANCES: bool = false;
+
+    const HISTORY: bool = false;
+
+    const GC: bool = false;
+
+    const WEAK: bool = false;
+
+    const FIREWALL: bool = false;
+
+    const CAPACITY: bool = false;
+
+    const EXTENDS: bool = false;
+
+    const COLLECT: bool = false;
+
+    const REMOTE_CACHE: bool = false;
+
+    const ELEMENTS: bool = false;
+
+    const CYCLE_INITIAL: bool = false;
+
+    const NO_CLONE: bool = false;
+
     const SINGLETON: bool = false;
 }
 
@@ -191,6 +480,13 @@ fn tracked_method(
 ) -> syn::Result<TokenStream> {
     args.jar_ty = args.jar_ty.or_else(|| outer_args.jar_ty.clone());
 
+    if let Some(instances) = &args.instances {
+        return Err(syn::Error::new(
+            instances.span(),
+            "the `for` option is not supported on tracked methods",
+        ));
+    }
+
     if item_method.sig.inputs.len() <= 1 {
         return Err(syn::Error::new(
             item_method.sig.ident.span(),
@@ -232,6 +528,11 @@ fn tracked_method(
     inputs.extend(original_inputs);
     item_fn.sig.inputs = inputs;
 
+    if let Some(collect_ty) = &args.collect {
+        collect_into(&mut item_fn, collect_ty)?;
+        item_method.sig.output = item_fn.sig.output.clone();
+    }
+
     let (config_ty, fn_struct) = crate::tracked_fn::fn_struct(&args, &item_fn)?;
 
     // we generate a `'db` lifetime that clippy
@@ -250,6 +551,98 @@ fn tracked_method(
     Ok(fn_struct)
 }
 
+/// With the `elements = <name>` option, the function returns a `salsa::List`, and
+/// `<name>` is a tracked function taking the same arguments followed by an index,
+/// which returns a clone of the element at that index, if any. It is listed in the
+/// jar like the function itself.
+fn elements_fn(
+    args: &FnArgs,
+    item_fn: &syn::ItemFn,
+    elements: &syn::Ident,
+) -> syn::Result<syn::ItemFn> {
+    let element_ty = match &item_fn.sig.output {
+        ReturnType::Type(_, ty) => list_element_ty(ty),
+        ReturnType::Default => None,
+    };
+    let Some(element_ty) = element_ty else {
+        return Err(syn::Error::new(
+            elements.span(),
+            "`elements` requires the function to return a `salsa::List`",
+        ));
+    };
+
+    let jar_ty = args.jar_ty();
+    let fn_name = &item_fn.sig.ident;
+    let (db_var, arg_names) = fn_args(item_fn)?;
+    let inputs = item_fn.sig.inputs.iter();
+    let visibility = &item_fn.vis;
+    Ok(parse_quote! {
+        #[salsa::tracked(jar = #jar_ty)]
+        #visibility fn #elements(#(#inputs,)* index: usize) -> Option<#element_ty> {
+            #fn_name(#db_var, #(#arg_names),*).get(index).cloned()
+        }
+    })
+}
+
+/// Returns `T` if `ty` is `List<T>` (or `salsa::List<T>`).
+fn list_element_ty(ty: &syn::Type) -> Option<&syn::Type> {
+    let syn::Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != "List" {
+        return None;
+    }
+    let syn::PathArguments::AngleBracketed(generics) = &segment.arguments else {
+        return None;
+    };
+    match generics.args.first()? {
+        syn::GenericArgument::Type(element_ty) if generics.args.len() == 1 => Some(element_ty),
+        _ => None,
+    }
+}
+
+/// With the `collect` option, the function returns an iterator, which is collected
+/// into the container `collect_ty`. The body moves into a nested function returning
+/// the iterator, and the tracked function returns (and memoizes) the container.
+fn collect_into(item_fn: &mut syn::ItemFn, collect_ty: &syn::Type) -> syn::Result<()> {
+    if let ReturnType::Default = item_fn.sig.output {
+        return Err(syn::Error::new(
+            collect_ty.span(),
+            "`collect` requires the function to return an iterator",
+        ));
+    }
+
+    let (db_var, arg_names) = fn_args(item_fn)?;
+    let iter_fn_name = syn::Ident::new("__iter", item_fn.sig.ident.span());
+    let iter_fn = syn::ItemFn {
+        attrs: vec![],
+        vis: syn::Visibility::Inherited,
+        sig: syn::Signature {
+            ident: iter_fn_name.clone(),
+            ..item_fn.sig.clone()
+        },
+        block: item_fn.block.clone(),
+    };
+
+    // The arguments are only moved into the nested function.
+    for input in &mut item_fn.sig.inputs {
+        if let syn::FnArg::Typed(pat_ty) = input {
+            if let syn::Pat::Ident(ident) = &mut *pat_ty.pat {
+                ident.mutability = None;
+            }
+        }
+    }
+    item_fn.sig.output = parse_quote! { -> #collect_ty };
+    *item_fn.block = parse_quote! {
+        {
+            #iter_fn
+            std::iter::FromIterator::from_iter(#iter_fn_name(#db_var, #(#arg_names,)*))
+        }
+    };
+    Ok(())
+}
+
 /// Rename all occurrences of `self` to `__salsa_self` in a block
 /// so that it can be used in a free function.
 fn rename_self_in_block(mut block: syn::Block) -> syn::Result<syn::Block> {
@@ -271,6 +664,36 @@ fn rename_self_in_block(mut block: syn::Block) -> syn::Result<syn::Block> {
                 *i = syn::Ident::new("__salsa_self", i.span());
             }
         }
+
+        // The arguments of macros like `format!` are not parsed, so we
+        // have to rename `self` in their tokens.
+        fn visit_macro_mut(&mut self, mac: &mut syn::Macro) {
+            syn::visit_mut::visit_macro_mut(self, mac);
+            mac.tokens = self.rename_in_tokens(std::mem::take(&mut mac.tokens));
+        }
+    }
+
+    impl RenameIdent {
+        fn rename_in_tokens(&mut self, tokens: TokenStream) -> TokenStream {
+            tokens
+                .into_iter()
+                .map(|token| match token {
+                    proc_macro2::TokenTree::Ident(mut i) => {
+                        self.visit_ident_mut(&mut i);
+                        proc_macro2::TokenTree::Ident(i)
+                    }
+                    proc_macro2::TokenTree::Group(group) => {
+                        let mut renamed = proc_macro2::Group::new(
+                            group.delimiter(),
+                            self.rename_in_tokens(group.stream()),
+                        );
+                        renamed.set_span(group.span());
+                        proc_macro2::TokenTree::Group(renamed)
+                    }
+                    token => token,
+                })
+                .collect()
+        }
     }
 
     let mut rename = RenameIdent(Ok(()));
@@ -281,7 +704,101 @@ fn rename_self_in_block(mut block: syn::Block) -> syn::Result<syn::Block> {
 /// Create the struct representing the function and all of its impls.
 ///
 /// This returns the name of the constructed type and the code defining everything.
-fn fn_struct(args: &FnArgs, item_fn: &syn::ItemFn) -> syn::Result<(syn::Type, TokenStream)> {
+pub(crate) fn fn_struct(
+    args: &FnArgs,
+    item_fn: &syn::ItemFn,
+) -> syn::Result<(syn::Type, TokenStream)> {
+    if let Some(s) = &args.specify {
+        if item_fn.sig.inputs.len() > 2 {
+            return Err(syn::Error::new(
+                s.span(),
+                "tracked function takes too many arguments to have its value set with `specify`",
+            ));
+        }
+
+        if function_type(item_fn) == FunctionType::RequiresInterning {
+            return Err(syn::Error::new(
+                s.span(),
+                "tracked function must take a salsa struct to have its value set with `specify`",
+            ));
+        }
+
+        if args.lru.is_some() {
+            return Err(syn::Error::new(
+                s.span(),
+                "`specify` and `lru` cannot be used together",
+            ));
+        }
+    }
+
+    if let Some(update) = &args.update {
+        if args.no_eq.is_some() || args.compare_with.is_some() || args.retry_err.is_some() {
+            return Err(syn::Error::new(
+                update.span(),
+                "`update` cannot be used together with `no_eq`, `compare_with` or `retry_err`",
+            ));
+        }
+    }
+
+    if let Some(firewall) = &args.firewall {
+        if args.no_eq.is_some()
+            || args.volatile.is_some()
+            || args.retry_err.is_some()
+            || args.lru.is_some()
+            || args.weak.is_some()
+        {
+            return Err(syn::Error::new(
+                firewall.span(),
+                "`firewall` cannot be used together with `no_eq`, `volatile`, `retry_err`, `lru` or `weak`",
+            ));
+        }
+    }
+
+    if let (Some(_), Some(cycle_err)) = (&args.recovery_fn, &args.cycle_err) {
+        return Err(syn::Error::new(
+            cycle_err.span(),
+            "`recovery_fn` and `cycle_err` cannot be used together",
+        ));
+    }
+
+    if let Some(cycle_initial) = &args.cycle_initial {
+        if args.recovery_fn.is_some() || args.cycle_err.is_some() {
+            return Err(syn::Error::new(
+                cycle_initial.span(),
+                "`cycle_initial` cannot be used together with `recovery_fn` or `cycle_err`",
+            ));
+        }
+        if args.no_eq.is_some() {
+            return Err(syn::Error::new(
+                cycle_initial.span(),
+                "`cycle_initial` compares the values of successive iterations, so it cannot be used together with `no_eq`",
+            ));
+        }
+    }
+
+    if let (Some(_), Some(return_deref)) = (&args.return_ref, &args.return_deref) {
+        return Err(syn::Error::new(
+            return_deref.span(),
+            "`return_ref` and `return_deref` cannot be used together",
+        ));
+    }
+
+    if let Some(no_clone) = &args.no_clone {
+        if args.return_ref.is_some() || args.return_deref.is_some() {
+            return Err(syn::Error::new(
+                no_clone.span(),
+                "`no_clone` is not needed with `return_ref` or `return_deref`, \
+                 which already return a reference rather than a clone",
+            ));
+        }
+        if args.elements.is_some() {
+            return Err(syn::Error::new(
+                no_clone.span(),
+                "`no_clone` cannot be used together with `elements`: a `salsa::List` is always `Clone`",
+            ));
+        }
+    }
+
     let struct_item = configuration_struct(item_fn);
     let configuration = fn_configuration(args, item_fn);
     let struct_item_ident = &struct_item.ident;
@@ -289,6 +806,7 @@ fn fn_struct(args: &FnArgs, item_fn: &syn::ItemFn) -> syn::Result<(syn::Type, To
     let configuration_impl = configuration.to_impl(&config_ty);
     let ingredients_for_impl = ingredients_for_impl(args, item_fn, &config_ty);
     let item_impl = setter_impl(args, item_fn, &config_ty)?;
+    let stub_query_impl = stub_query_impl(item_fn, &config_ty)?;
 
     Ok((
         config_ty,
@@ -297,10 +815,68 @@ fn fn_struct(args: &FnArgs, item_fn: &syn::ItemFn) -> syn::Result<(syn::Type, To
             #configuration_impl
             #ingredients_for_impl
             #item_impl
+            #stub_query_impl
         },
     ))
 }
 
+/// Implements `salsa::testing::StubQuery` with the `set` function, for any database
+/// implementing the traits of the database argument, if that is a trait object.
+fn stub_query_impl(
+    item_fn: &syn::ItemFn,
+    config_ty: &syn::Type,
+) -> syn::Result<Option<syn::ItemImpl>> {
+    if !item_fn.sig.generics.params.is_empty() {
+        return Ok(None);
+    }
+    let db_bounds: Vec<&syn::TraitBound> = match item_fn.sig.inputs.first() {
+        Some(syn::FnArg::Typed(pat_ty)) => match &*pat_ty.ty {
+            syn::Type::Reference(reference) => match &*reference.elem {
+                syn::Type::TraitObject(object) => object
+                    .bounds
+                    .iter()
+                    .filter_map(|bound| match bound {
+                        syn::TypeParamBound::Trait(bound) => Some(bound),
+                        syn::TypeParamBound::Lifetime(_) => None,
+                    })
+                    .collect(),
+                _ => return Ok(None),
+            },
+            _ => return Ok(None),
+        },
+        _ => return Ok(None),
+    };
+
+    let (_, arg_names) = fn_args(item_fn)?;
+    let arg_tys: Vec<&syn::Type> = item_fn
+        .sig
+        .inputs
+        .iter()
+        .skip(1)
+        .map(|arg| match arg {
+            syn::FnArg::Receiver(_) => unreachable!(),
+            syn::FnArg::Typed(pat_ty) => &*pat_ty.ty,
+        })
+        .collect();
+    let (key_ty, key_pat) = match (&arg_tys[..], &arg_names[..]) {
+        ([arg_ty], [arg_name]) => (quote!(#arg_ty), quote!(#arg_name)),
+        _ => (quote!((#(#arg_tys,)*)), quote!((#(#arg_names,)*))),
+    };
+    let value_ty = configuration::value_ty(&item_fn.sig);
+
+    Ok(Some(parse_quote! {
+        impl<__DB: #(#db_bounds)+*> salsa::testing::StubQuery<__DB> for #config_ty {
+            type Key = #key_ty;
+            type Value = #value_ty;
+
+            fn stub(__db: &mut __DB, __key: Self::Key, __value: Self::Value) {
+                let #key_pat = __key;
+                Self::set(__db, #(#arg_names,)* __value)
+            }
+        }
+    }))
+}
+
 /// Returns the key type for this tracked function.
 /// This is a tuple of all the argument types (apart from the database).
 fn key_tuple_ty(item_fn: &syn::ItemFn) -> syn::Type {
@@ -354,23 +930,68 @@ fn function_type(item_fn: &syn::ItemFn) -> FunctionType {
             "functions have been checked to have at least a database argument by this point"
         ),
         1 => FunctionType::Constant,
-        2 => FunctionType::SalsaStruct,
+        2 if !is_plain_data(&first_arg_ty(item_fn)) => FunctionType::SalsaStruct,
         _ => FunctionType::RequiresInterning,
     }
 }
 
-/// Every tracked fn takes a salsa struct as its second argument.
-/// This fn returns the type of that second argument.
+/// Tracked fns take a salsa struct as their second argument, unless they
+/// only take the database, or their second argument is plain data.
+/// This fn returns the type of that salsa struct.
 fn salsa_struct_ty(item_fn: &syn::ItemFn) -> syn::Type {
     if item_fn.sig.inputs.len() == 1 {
         return parse_quote! { salsa::salsa_struct::Singleton };
     }
+    let ty = first_arg_ty(item_fn);
+    if is_plain_data(&ty) {
+        return parse_quote! { salsa::salsa_struct::Singleton };
+    }
+    ty
+}
+
+fn first_arg_ty(item_fn: &syn::ItemFn) -> syn::Type {
     match &item_fn.sig.inputs[1] {
         syn::FnArg::Receiver(_) => panic!("receiver not expected"),
         syn::FnArg::Typed(pat_ty) => (*pat_ty.ty).clone(),
     }
 }
 
+/// Returns true if `ty` is certainly not a salsa struct: a tuple, an array,
+/// a primitive or string type, or a type with generic arguments
+/// (e.g. `(u32, SmolStr)` or `Vec<String>`). The tracked fns taking it as
+/// their only key are keyed by an id interned from it, like those taking
+/// several arguments. Other types of plain data can be given as a 1-tuple.
+fn is_plain_data(ty: &syn::Type) -> bool {
+    const PLAIN_TYPES: &[&str] = &[
+        "bool", "char", "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64",
+        "u128", "usize", "String", "SmolStr", "PathBuf", "OsString",
+    ];
+
+    match ty {
+        syn::Type::Tuple(_) | syn::Type::Array(_) => true,
+        syn::Type::Paren(ty) => is_plain_data(&ty.elem),
+        syn::Type::Group(ty) => is_plain_data(&ty.elem),
+        syn::Type::Path(ty) if ty.qself.is_none() => match ty.path.segments.last() {
+            Some(segment) => {
+                !segment.arguments.is_empty()
+                    || PLAIN_TYPES.iter().any(|plain| segment.ident == plain)
+            }
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+/// Returns the expression given to `intern_map.intern` for the arguments
+/// `arg_names`: a tuple of them if the fn requires interning, or the argument
+/// itself otherwise.
+fn interned_key(item_fn: &syn::ItemFn, arg_names: &[syn::Ident]) -> TokenStream {
+    match function_type(item_fn) {
+        FunctionType::Constant | FunctionType::SalsaStruct => quote! { (#(#arg_names),*) },
+        FunctionType::RequiresInterning => quote! { (#(#arg_names,)*) },
+    }
+}
+
 fn fn_configuration(args: &FnArgs, item_fn: &syn::ItemFn) -> Configuration {
     let jar_ty = args.jar_ty();
     let salsa_struct_ty = salsa_struct_ty(item_fn);
@@ -399,6 +1020,16 @@ fn fn_configuration(args: &FnArgs, item_fn: &syn::ItemFn) -> Configuration {
             }
         };
         (cycle_strategy, cycle_fullback)
+    } else if args.cycle_initial.is_some() {
+        // The cycles are iterated from the value of `cycle_initial`, see below.
+        let cycle_strategy = CycleRecoveryStrategy::FixedPoint;
+        let cycle_panic = configuration::panic_cycle_recovery_fn();
+        (cycle_strategy, cycle_panic)
+    } else if args.cycle_err.is_some() {
+        // With `cycle_err`, the participants of a cycle return it as an error.
+        let cycle_strategy = CycleRecoveryStrategy::Fallback;
+        let cycle_err = configuration::err_cycle_recovery_fn();
+        (cycle_strategy, cycle_err)
     } else {
         // When the `recovery_fn` attribute is not set, set `cycle_strategy` to `Panic`
         let cycle_strategy = CycleRecoveryStrategy::Panic;
@@ -406,7 +1037,52 @@ fn fn_configuration(args: &FnArgs, item_fn: &syn::ItemFn) -> Configuration {
         (cycle_strategy, cycle_panic)
     };
 
-    let backdate_fn = configuration::should_backdate_value_fn(args.should_backdate());
+    // Create the `cycle_initial` function, which maps from the interned id to the actual
+    // keys, like `recover_from_cycle` above.
+    let indices = (0..item_fn.sig.inputs.len() - 1).map(Literal::usize_unsuffixed);
+    let cycle_initial_fn = args.cycle_initial.as_ref().map(|cycle_initial| {
+        parse_quote! {
+            fn cycle_initial(__db: &salsa::function::DynDb<Self>, __id: Self::Key) -> Self::Value {
+                let (__jar, __runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(__db);
+                let __ingredients =
+                    <_ as salsa::storage::HasIngredientsFor<#fn_ty>>::ingredient(__jar);
+                let __key = __ingredients.intern_map.data(__runtime, __id).clone();
+                #cycle_initial(__db, #(__key.#indices),*)
+            }
+        }
+    });
+
+    let mut backdate_fn = match &args.compare_with {
+        Some(compare_with) => configuration::compare_with_value_fn(compare_with),
+        None => configuration::should_backdate_value_fn(
+            args.should_backdate(),
+            &configuration::value_ty(&item_fn.sig),
+        ),
+    };
+    if args.retry_err.is_some() {
+        backdate_fn = configuration::never_backdate_err_fn(backdate_fn);
+    }
+
+    let update_fn = args
+        .update
+        .as_ref()
+        .map(|_| configuration::update_value_fn());
+
+    let heap_size_fn = args
+        .heap_size
+        .as_ref()
+        .map(|_| configuration::heap_size_fn());
+
+    // Values returned by reference may not be `Clone`.
+    let value_to_any_fn =
+        (args.return_ref.is_none() && args.return_deref.is_none() && args.no_clone.is_none())
+            .then(|| configuration::value_to_any_fn(&configuration::value_ty(&item_fn.sig)));
+
+    let storage_ty = configuration::storage_ty(args.storage.as_ref());
+
+    let weak = args.weak.is_some();
+    let firewall = args.firewall.is_some();
+    let no_eq = !args.should_backdate();
 
     // The type of the configuration struct; this has the same name as the fn itself.
 
@@ -420,7 +1096,26 @@ fn fn_configuration(args: &FnArgs, item_fn: &syn::ItemFn) -> Configuration {
     // Create the `execute` function, which (a) maps from the interned id to the actual
     // keys and then (b) invokes the function itself (which we embed within).
     let indices = (0..item_fn.sig.inputs.len() - 1).map(Literal::usize_unsuffixed);
-    let execute_fn = parse_quote! {
+    let mut call: syn::Expr = parse_quote! {
+        #inner_fn_name(__db, #(__key.#indices),*)
+    };
+    if let Some(checksum_fn) = &args.remote_cache {
+        // The value is looked up in the remote cache by the checksum of its
+        // dependencies, whose reads become the dependencies of the query.
+        // The function is named by its path rather than by `type_name`,
+        // which may change from one compiler version to the next.
+        let fn_name = &item_fn.sig.ident;
+        let indices = (0..item_fn.sig.inputs.len() - 1).map(Literal::usize_unsuffixed);
+        call = parse_quote! {
+            salsa::remote_cache::fetch_or_execute(
+                __db,
+                concat!(module_path!(), "::", stringify!(#fn_name)),
+                #checksum_fn(__db, #(std::clone::Clone::clone(&__key.#indices)),*),
+                || #call,
+            )
+        };
+    }
+    let mut execute_fn: syn::ImplItemMethod = parse_quote! {
         fn execute(__db: &salsa::function::DynDb<Self>, __id: Self::Key) -> Self::Value {
             #inner_fn
 
@@ -428,19 +1123,52 @@ fn fn_configuration(args: &FnArgs, item_fn: &syn::ItemFn) -> Configuration {
             let __ingredients =
                 <_ as salsa::storage::HasIngredientsFor<#fn_ty>>::ingredient(__jar);
             let __key = __ingredients.intern_map.data(__runtime, __id).clone();
-            #inner_fn_name(__db, #(__key.#indices),*)
+            #call
         }
     };
+    if args.retry_err.is_some() {
+        // Errors are treated as if they depended on some state unknown to salsa,
+        // so that the function is executed again in the next revision.
+        let block = &execute_fn.block;
+        execute_fn.block = parse_quote! {
+            {
+                let __value = #block;
+                if __value.is_err() {
+                    <_ as salsa::storage::HasJarsDyn>::runtime(__db).report_untracked_read();
+                }
+                __value
+            }
+        };
+    }
+    if args.volatile.is_some() {
+        // The value depends on some state unknown to salsa, so the function is
+        // executed again in every new revision (its value can still be backdated).
+        let block = &execute_fn.block;
+        execute_fn.block = parse_quote! {
+            {
+                <_ as salsa::storage::HasJarsDyn>::runtime(__db).report_untracked_read();
+                #block
+            }
+        };
+    }
 
     Configuration {
         jar_ty,
         salsa_struct_ty,
         key_ty,
         value_ty,
+        storage_ty,
         cycle_strategy,
         backdate_fn,
         execute_fn,
         recover_fn,
+        cycle_initial_fn,
+        update_fn,
+        heap_size_fn,
+        value_to_any_fn,
+        weak,
+        firewall,
+        no_eq,
     }
 }
 
@@ -483,6 +1211,9 @@ fn ingredients_for_impl(
 
     // set 0 as default to disable LRU
     let lru = args.lru.unwrap_or(0);
+    let reserve = args
+        .capacity
+        .map(|capacity| quote! { ingredient.reserve(#capacity); });
 
     // get the name of the function as a string literal
     let debug_name = crate::literal(&item_fn.sig.ident);
@@ -495,6 +1226,7 @@ fn ingredients_for_impl(
             fn create_ingredients<DB>(routes: &mut salsa::routes::Routes<DB>) -> Self::Ingredients
             where
                 DB: salsa::DbWithJar<Self::Jar> + salsa::storage::JarFromJars<Self::Jar>,
+                Self::Jar: salsa::jar::JarInDb<DB>,
             {
                 Self {
                     intern_map: #intern_map,
@@ -515,10 +1247,18 @@ fn ingredients_for_impl(
                             });
                         let ingredient = salsa::function::FunctionIngredient::new(index, #debug_name);
                         ingredient.set_capacity(#lru);
+                        #reserve
                         ingredient
                     }
                 }
             }
+
+            fn fork_ingredients(ingredients: &Self::Ingredients) -> Option<Self::Ingredients> {
+                Some(Self {
+                    intern_map: ingredients.intern_map.fork()?,
+                    function: ingredients.function.fork()?,
+                })
+            }
         }
     }
 }
@@ -529,24 +1269,61 @@ fn setter_impl(
     config_ty: &syn::Type,
 ) -> syn::Result<syn::ItemImpl> {
     let ref_getter_fn = ref_getter_fn(args, item_fn, config_ty)?;
+    let peek_fn = peek_fn(args, item_fn, config_ty)?;
+    let iter_range_fn = iter_range_fn(args, item_fn, config_ty)?;
     let accumulated_fn = accumulated_fn(args, item_fn, config_ty)?;
+    let accumulated_with_queries_fn = accumulated_with_queries_fn(args, item_fn, config_ty)?;
     let setter_fn = setter_fn(args, item_fn, config_ty)?;
+    let invalidate_fn = invalidate_fn(args, item_fn, config_ty)?;
+    let last_changed_fn = last_changed_fn(args, item_fn, config_ty)?;
+    let database_key_index_fn = database_key_index_fn(args, item_fn, config_ty)?;
+    let fetch_checksum_fn = fetch_checksum_fn(args, item_fn, config_ty)?
+        .map(|f| quote! { #[allow(dead_code, clippy::needless_lifetimes)] #f });
     let specify_fn = specify_fn(args, item_fn, config_ty)?.map(|f| quote! { #f });
     let set_lru_fn = set_lru_capacity_fn(args, config_ty)?.map(|f| quote! { #f });
+    let pin_fns = pin_fns(args, item_fn, config_ty)?;
+    let reserve_fn = reserve_fn(args, config_ty);
 
     let setter_impl: syn::ItemImpl = parse_quote! {
         impl #config_ty {
-            #[allow(dead_code, clippy::needless_lifetimes)]
+            #[allow(dead_code, clippy::needless_lifetimes, clippy::borrowed_box)]
             #ref_getter_fn
 
+            #[allow(dead_code, clippy::needless_lifetimes, clippy::borrowed_box)]
+            #peek_fn
+
+            #[allow(dead_code, clippy::needless_lifetimes, clippy::borrowed_box)]
+            #iter_range_fn
+
             #[allow(dead_code, clippy::needless_lifetimes)]
             #setter_fn
 
+            #[allow(dead_code, clippy::needless_lifetimes)]
+            #invalidate_fn
+
+            #[allow(dead_code, clippy::needless_lifetimes)]
+            #last_changed_fn
+
+            #[allow(dead_code, clippy::needless_lifetimes)]
+            #database_key_index_fn
+
+            #fetch_checksum_fn
+
             #[allow(dead_code, clippy::needless_lifetimes)]
             #accumulated_fn
 
+            #[allow(dead_code, clippy::needless_lifetimes)]
+            #accumulated_with_queries_fn
+
             #set_lru_fn
 
+            #(
+                #[allow(dead_code, clippy::needless_lifetimes)]
+                #pin_fns
+            )*
+
+            #reserve_fn
+
             #specify_fn
         }
     };
@@ -556,7 +1333,7 @@ fn setter_impl(
 
 /// Creates the shim function that looks like the original function but calls
 /// into the machinery we've just generated rather than executing the code.
-fn getter_fn(
+pub(crate) fn getter_fn(
     args: &FnArgs,
     fn_sig: &mut syn::Signature,
     block_span: proc_macro2::Span,
@@ -584,20 +1361,93 @@ fn getter_fn(
     if is_method {
         arg_idents.swap(0, 1);
     }
-    Ok(if args.return_ref.is_some() {
-        make_fn_return_ref(fn_sig)?;
-        parse_quote_spanned! {
-            block_span => {
-                #config_ty::get(#(#arg_idents,)*)
+    let value = make_getter_sig(args, fn_sig)?.value(quote! {
+        #config_ty::get(#(#arg_idents,)*)
+    });
+    Ok(parse_quote_spanned! {
+        block_span => {
+            #value
+        }
+    })
+}
+
+/// How a getter of a tracked function turns the `&Value` it fetches into what it returns.
+pub(crate) enum Getter {
+    /// Returns a clone of the value, whose type is the return type.
+    Clone(Box<syn::Type>),
+
+    /// Returns the reference (`return_ref`).
+    Ref,
+
+    /// Returns an `Option<&T>` for an `Option<T>` value (`return_ref`).
+    OptionRef,
+
+    /// Returns a reference to the target of the value (`return_deref`).
+    Deref,
+}
+
+impl Getter {
+    /// Returns the expression returned for the `&Value` `value`.
+    pub(crate) fn value(&self, value: TokenStream) -> TokenStream {
+        match self {
+            Getter::Clone(value_ty) => {
+                // Spanned so that a value that is not `Clone` is reported on the return type.
+                let clone = quote_spanned! {value_ty.span()=> <#value_ty as Clone>::clone };
+                quote! { #clone(#value) }
             }
+            Getter::Ref => value,
+            Getter::OptionRef => quote! { Option::as_ref(#value) },
+            Getter::Deref => quote! { std::ops::Deref::deref(#value) },
         }
-    } else {
-        parse_quote_spanned! {
-            block_span => {
-                Clone::clone(#config_ty::get(#(#arg_idents,)*))
+    }
+}
+
+/// Modifies `fn_sig`, the signature of a getter of a tracked function (the function
+/// itself, or a method calling it), to return what the `return_ref` or `return_deref`
+/// option asks for, if any.
+pub(crate) fn make_getter_sig(args: &FnArgs, fn_sig: &mut syn::Signature) -> syn::Result<Getter> {
+    if args.return_ref.is_some() {
+        let option_ty = match &fn_sig.output {
+            ReturnType::Type(_, ty) => option_inner_ty(ty).cloned(),
+            ReturnType::Default => None,
+        };
+        match option_ty {
+            Some(inner_ty) => {
+                let (db_lifetime, _) = db_lifetime_and_ty(fn_sig)?;
+                if let ReturnType::Type(_, ty) = &mut fn_sig.output {
+                    *ty = parse_quote!(Option<&#db_lifetime #inner_ty>);
+                }
+                Ok(Getter::OptionRef)
+            }
+            None => {
+                make_fn_return_ref(fn_sig)?;
+                Ok(Getter::Ref)
             }
         }
-    })
+    } else if args.return_deref.is_some() {
+        make_fn_return_deref(fn_sig, args.return_deref_target.as_ref())?;
+        Ok(Getter::Deref)
+    } else {
+        Ok(Getter::Clone(Box::new(configuration::value_ty(fn_sig))))
+    }
+}
+
+/// Returns `T` if `ty` is `Option<T>`.
+fn option_inner_ty(ty: &syn::Type) -> Option<&syn::Type> {
+    let syn::Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    if type_path.qself.is_some() || segment.ident != "Option" {
+        return None;
+    }
+    let syn::PathArguments::AngleBracketed(generics) = &segment.arguments else {
+        return None;
+    };
+    match generics.args.first()? {
+        syn::GenericArgument::Type(inner_ty) if generics.args.len() == 1 => Some(inner_ty),
+        _ => None,
+    }
 }
 
 /// Creates a `get` associated function that returns `&Value`
@@ -615,11 +1465,12 @@ fn ref_getter_fn(
     make_fn_return_ref(&mut ref_getter_fn.sig)?;
 
     let (db_var, arg_names) = fn_args(item_fn)?;
+    let key = interned_key(item_fn, &arg_names);
     ref_getter_fn.block = parse_quote! {
         {
             let (__jar, __runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(#db_var);
             let __ingredients = <_ as salsa::storage::HasIngredientsFor<#config_ty>>::ingredient(__jar);
-            let __key = __ingredients.intern_map.intern(__runtime, (#(#arg_names),*));
+            let __key = __ingredients.intern_map.intern(__runtime, #key);
             __ingredients.function.fetch(#db_var, __key)
         }
     };
@@ -627,6 +1478,95 @@ fn ref_getter_fn(
     Ok(ref_getter_fn)
 }
 
+/// Creates a `peek` associated function that returns `Some(&Value)` if the value is
+/// already memoized and verified in the current revision, without executing the function.
+fn peek_fn(
+    args: &FnArgs,
+    item_fn: &syn::ItemFn,
+    config_ty: &syn::Type,
+) -> syn::Result<syn::ItemFn> {
+    let jar_ty = args.jar_ty();
+    let mut peek_fn = item_fn.clone();
+    peek_fn.sig.ident = syn::Ident::new("peek", item_fn.sig.ident.span());
+    make_fn_return_ref(&mut peek_fn.sig)?;
+    if let ReturnType::Type(_, ty) = &mut peek_fn.sig.output {
+        *ty = parse_quote!(Option<#ty>);
+    }
+
+    let (db_var, arg_names) = fn_args(item_fn)?;
+    let key = interned_key(item_fn, &arg_names);
+    peek_fn.block = parse_quote! {
+        {
+            let (__jar, __runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(#db_var);
+            let __ingredients = <_ as salsa::storage::HasIngredientsFor<#config_ty>>::ingredient(__jar);
+            let __key = __ingredients.intern_map.intern(__runtime, #key);
+            __ingredients.function.peek(#db_var, __key)
+        }
+    };
+
+    Ok(peek_fn)
+}
+
+/// Creates an `iter_range` associated function that returns the memoized values whose
+/// arguments are in a range, sorted by arguments. The range is over the argument if there
+/// is a single one, and over the tuple of arguments otherwise.
+fn iter_range_fn(
+    args: &FnArgs,
+    item_fn: &syn::ItemFn,
+    config_ty: &syn::Type,
+) -> syn::Result<syn::ItemFn> {
+    let jar_ty = args.jar_ty();
+    let (db_var, arg_names) = fn_args(item_fn)?;
+    let arg_tys: Vec<&syn::Type> = item_fn
+        .sig
+        .inputs
+        .iter()
+        .skip(1)
+        .map(|arg| match arg {
+            syn::FnArg::Receiver(_) => unreachable!(),
+            syn::FnArg::Typed(pat_ty) => &*pat_ty.ty,
+        })
+        .collect();
+    let key_ty: syn::Type = match &arg_tys[..] {
+        [arg_ty] => (*arg_ty).clone(),
+        _ => parse_quote!((#(#arg_tys,)*)),
+    };
+    let key_of = match function_type(item_fn) {
+        FunctionType::Constant | FunctionType::SalsaStruct => quote! { __key },
+        FunctionType::RequiresInterning if arg_names.len() == 1 => {
+            quote! { __ingredients.intern_map.data(__runtime, __key).0.clone() }
+        }
+        FunctionType::RequiresInterning => {
+            quote! { __ingredients.intern_map.data(__runtime, __key).clone() }
+        }
+    };
+
+    let mut iter_range_fn = item_fn.clone();
+    iter_range_fn.sig.ident = syn::Ident::new("iter_range", item_fn.sig.ident.span());
+    make_fn_return_ref(&mut iter_range_fn.sig)?;
+    if let ReturnType::Type(_, ty) = &mut iter_range_fn.sig.output {
+        *ty = parse_quote!(Vec<(#key_ty, #ty)>);
+    }
+    let db_arg = iter_range_fn.sig.inputs[0].clone();
+    iter_range_fn.sig.inputs = [db_arg, parse_quote!(__range: __R)].into_iter().collect();
+    iter_range_fn.sig.generics.params.push(parse_quote!(__R));
+    iter_range_fn
+        .sig
+        .generics
+        .make_where_clause()
+        .predicates
+        .push(parse_quote!(__R: salsa::function::KeyRange<#key_ty>));
+    iter_range_fn.block = parse_quote! {
+        {
+            let (__jar, __runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(#db_var);
+            let __ingredients = <_ as salsa::storage::HasIngredientsFor<#config_ty>>::ingredient(__jar);
+            __ingredients.function.fetch_range(#db_var, &__range, |__key| #key_of)
+        }
+    };
+
+    Ok(iter_range_fn)
+}
+
 /// Creates a `set` associated function that can be used to set (given an `&mut db`)
 /// the value for this function for some inputs.
 fn setter_fn(
@@ -638,6 +1578,7 @@ fn setter_fn(
     // but it takes a value arg and has no return type.
     let jar_ty = args.jar_ty();
     let (db_var, arg_names) = fn_args(item_fn)?;
+    let key = interned_key(item_fn, &arg_names);
     let mut setter_sig = item_fn.sig.clone();
     let value_ty = configuration::value_ty(&item_fn.sig);
     setter_sig.ident = syn::Ident::new("set", item_fn.sig.ident.span());
@@ -663,13 +1604,145 @@ fn setter_fn(
             {
                 let (__jar, __runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar_mut(#db_var);
                 let __ingredients = <_ as salsa::storage::HasIngredientsFor<#config_ty>>::ingredient_mut(__jar);
-                let __key = __ingredients.intern_map.intern(__runtime, (#(#arg_names),*));
+                let __key = __ingredients.intern_map.intern(__runtime, #key);
                 __ingredients.function.store(__runtime, __key, #value_arg, salsa::Durability::LOW)
             }
         },
     })
 }
 
+/// Creates an `invalidate` associated function that forces the function to be executed
+/// again (given an `&mut db`) for some inputs, the next time its value is needed.
+fn invalidate_fn(
+    args: &FnArgs,
+    item_fn: &syn::ItemFn,
+    config_ty: &syn::Type,
+) -> syn::Result<syn::ImplItemMethod> {
+    // `invalidate` has the same signature as the original,
+    // but it takes an `&mut db` and has no return type.
+    let jar_ty = args.jar_ty();
+    let (db_var, arg_names) = fn_args(item_fn)?;
+    let key = interned_key(item_fn, &arg_names);
+    let mut invalidate_sig = item_fn.sig.clone();
+    invalidate_sig.ident = syn::Ident::new("invalidate", item_fn.sig.ident.span());
+    match &mut invalidate_sig.inputs[0] {
+        // change from `&dyn ...` to `&mut dyn...`
+        syn::FnArg::Receiver(_) => unreachable!(), // early fns should have detected
+        syn::FnArg::Typed(pat_ty) => match &mut *pat_ty.ty {
+            syn::Type::Reference(ty) => {
+                ty.mutability = Some(Token![mut](ty.and_token.span()));
+            }
+            _ => unreachable!(), // early fns should have detected
+        },
+    }
+    invalidate_sig.output = ReturnType::Default;
+    Ok(syn::ImplItemMethod {
+        attrs: vec![],
+        vis: item_fn.vis.clone(),
+        defaultness: None,
+        sig: invalidate_sig,
+        block: parse_quote! {
+            {
+                let (__jar, __runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar_mut(#db_var);
+                let __ingredients = <_ as salsa::storage::HasIngredientsFor<#config_ty>>::ingredient_mut(__jar);
+                let __key = __ingredients.intern_map.intern(__runtime, #key);
+                __ingredients.function.invalidate(__runtime, __key)
+            }
+        },
+    })
+}
+
+/// Creates a `last_changed` associated function that returns the revision in which
+/// the value of the function for some inputs last changed, if it is memoized.
+fn last_changed_fn(
+    args: &FnArgs,
+    item_fn: &syn::ItemFn,
+    config_ty: &syn::Type,
+) -> syn::Result<syn::ImplItemMethod> {
+    let jar_ty = args.jar_ty();
+    let (db_var, arg_names) = fn_args(item_fn)?;
+    let key = interned_key(item_fn, &arg_names);
+    let mut last_changed_sig = item_fn.sig.clone();
+    last_changed_sig.ident = syn::Ident::new("last_changed", item_fn.sig.ident.span());
+    last_changed_sig.output = parse_quote! { -> Option<salsa::Revision> };
+    Ok(syn::ImplItemMethod {
+        attrs: vec![],
+        vis: item_fn.vis.clone(),
+        defaultness: None,
+        sig: last_changed_sig,
+        block: parse_quote! {
+            {
+                let (__jar, __runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(#db_var);
+                let __ingredients = <_ as salsa::storage::HasIngredientsFor<#config_ty>>::ingredient(__jar);
+                let __key = __ingredients.intern_map.intern(__runtime, #key);
+                __ingredients.function.last_changed(__key)
+            }
+        },
+    })
+}
+
+/// Creates a `database_key_index` associated function that returns the index identifying
+/// the call of the function for the given arguments, as in the events of the runtime.
+fn database_key_index_fn(
+    args: &FnArgs,
+    item_fn: &syn::ItemFn,
+    config_ty: &syn::Type,
+) -> syn::Result<syn::ImplItemMethod> {
+    let jar_ty = args.jar_ty();
+    let (db_var, arg_names) = fn_args(item_fn)?;
+    let key = interned_key(item_fn, &arg_names);
+    let mut database_key_index_sig = item_fn.sig.clone();
+    database_key_index_sig.ident = syn::Ident::new("database_key_index", item_fn.sig.ident.span());
+    database_key_index_sig.output = parse_quote! { -> salsa::DatabaseKeyIndex };
+    Ok(syn::ImplItemMethod {
+        attrs: vec![],
+        vis: item_fn.vis.clone(),
+        defaultness: None,
+        sig: database_key_index_sig,
+        block: parse_quote! {
+            {
+                let (__jar, __runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(#db_var);
+                let __ingredients = <_ as salsa::storage::HasIngredientsFor<#config_ty>>::ingredient(__jar);
+                let __key = __ingredients.intern_map.intern(__runtime, #key);
+                __ingredients.function.database_key_index(__key)
+            }
+        },
+    })
+}
+
+/// Creates a `fetch_checksum` associated function, for tracked functions with
+/// the `remote_cache` option, that returns the checksum of the value of the
+/// function, e.g. to compute the checksum of the functions that use it.
+fn fetch_checksum_fn(
+    args: &FnArgs,
+    item_fn: &syn::ItemFn,
+    config_ty: &syn::Type,
+) -> syn::Result<Option<syn::ImplItemMethod>> {
+    if args.remote_cache.is_none() {
+        return Ok(None);
+    }
+    let jar_ty = args.jar_ty();
+    let (db_var, arg_names) = fn_args(item_fn)?;
+    let key = interned_key(item_fn, &arg_names);
+    let mut fetch_checksum_sig = item_fn.sig.clone();
+    fetch_checksum_sig.ident = syn::Ident::new("fetch_checksum", item_fn.sig.ident.span());
+    fetch_checksum_sig.output = parse_quote! { -> u64 };
+    Ok(Some(syn::ImplItemMethod {
+        attrs: vec![],
+        vis: item_fn.vis.clone(),
+        defaultness: None,
+        sig: fetch_checksum_sig,
+        block: parse_quote! {
+            {
+                let (__jar, __runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(#db_var);
+                let __ingredients = <_ as salsa::storage::HasIngredientsFor<#config_ty>>::ingredient(__jar);
+                let __key = __ingredients.intern_map.intern(__runtime, #key);
+                salsa::remote_cache::value_checksum(__ingredients.function.fetch(#db_var, __key))
+            }
+        },
+    }))
+}
+
 /// Create a `set_lru_capacity` associated function that can be used to change LRU
 /// capacity at runtime.
 /// Note that this function is only generated if the tracked function has the lru option set.
@@ -703,6 +1776,77 @@ fn set_lru_capacity_fn(
     Ok(Some(lru_fn))
 }
 
+/// Create `pin` and `unpin` associated functions that prevent the LRU from evicting
+/// the value for some inputs, and allow it again.
+/// Note that these functions are only generated if the tracked function has the lru option set.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// #[salsa::tracked(lru = 32)]
+/// fn my_tracked_fn(db: &dyn crate::Db, file: File) { }
+///
+/// my_tracked_fn::pin(db, open_file)
+/// ```
+fn pin_fns(
+    args: &FnArgs,
+    item_fn: &syn::ItemFn,
+    config_ty: &syn::Type,
+) -> syn::Result<Vec<syn::ImplItemMethod>> {
+    if args.lru.is_none() {
+        return Ok(vec![]);
+    }
+
+    let jar_ty = args.jar_ty();
+    let (db_var, arg_names) = fn_args(item_fn)?;
+    let key = interned_key(item_fn, &arg_names);
+    let pin_fn = |name: &str| {
+        let fn_name = syn::Ident::new(name, item_fn.sig.ident.span());
+        let mut sig = item_fn.sig.clone();
+        sig.ident = fn_name.clone();
+        sig.output = ReturnType::Default;
+        syn::ImplItemMethod {
+            attrs: vec![],
+            vis: item_fn.vis.clone(),
+            defaultness: None,
+            sig,
+            block: parse_quote! {
+                {
+                    let (__jar, __runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(#db_var);
+                    let __ingredients = <_ as salsa::storage::HasIngredientsFor<#config_ty>>::ingredient(__jar);
+                    let __key = __ingredients.intern_map.intern(__runtime, #key);
+                    __ingredients.function.#fn_name(__key)
+                }
+            },
+        }
+    };
+    Ok(vec![pin_fn("pin"), pin_fn("unpin")])
+}
+
+/// Create a `reserve` associated function that makes room for the memos of
+/// more keys, like the `capacity` option does when the database is created.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// #[salsa::tracked]
+/// fn my_tracked_fn(db: &dyn crate::Db, ...) { }
+///
+/// my_tracked_fn::reserve(db, files.len())
+/// ```
+fn reserve_fn(args: &FnArgs, config_ty: &syn::Type) -> syn::ImplItemMethod {
+    let jar_ty = args.jar_ty();
+    parse_quote! {
+        #[allow(dead_code, clippy::needless_lifetimes)]
+        fn reserve(__db: &salsa::function::DynDb<Self>, __additional: usize) {
+            let (__jar, __runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(__db);
+            let __ingredients =
+                <_ as salsa::storage::HasIngredientsFor<#config_ty>>::ingredient(__jar);
+            __ingredients.function.reserve(__additional);
+        }
+    }
+}
+
 fn specify_fn(
     args: &FnArgs,
     item_fn: &syn::ItemFn,
@@ -762,6 +1906,72 @@ fn make_fn_return_ref(fn_sig: &mut syn::Signature) -> syn::Result<()> {
     Ok(())
 }
 
+/// Given a function def tagged with `#[return_deref]`, modifies `fn_sig` so that
+/// it returns an `&'db Target` instead of a `Value`, naming the target of the
+/// value concretely (e.g., `&'db [T]` for a `Vec<T>`), so that the signature does
+/// not mention the type of the value. `target` is given by `return_deref = Target`.
+fn make_fn_return_deref(
+    fn_sig: &mut syn::Signature,
+    target: Option<&syn::Type>,
+) -> syn::Result<()> {
+    let target = match (target, &fn_sig.output) {
+        (Some(target), _) => target.clone(),
+        (None, ReturnType::Type(_, ty)) => deref_target(ty).ok_or_else(|| {
+            syn::Error::new(
+                ty.span(),
+                "cannot name the `Deref::Target` of this type, give it with `return_deref = Target`",
+            )
+        })?,
+        (None, ReturnType::Default) => {
+            return Err(syn::Error::new(
+                fn_sig.ident.span(),
+                "`return_deref` requires a return type",
+            ))
+        }
+    };
+    // `&dyn A + B` is ambiguous: it must be written `&(dyn A + B)`.
+    let target = match target {
+        syn::Type::TraitObject(_) => parse_quote!((#target)),
+        target => target,
+    };
+    let right_arrow = match &fn_sig.output {
+        ReturnType::Default => syn::Token![->](fn_sig.paren_token.span),
+        ReturnType::Type(rarrow, _) => *rarrow,
+    };
+    fn_sig.output = ReturnType::Type(right_arrow, Box::new(target));
+    make_fn_return_ref(fn_sig)
+}
+
+/// Returns the `Deref::Target` of the standard containers: `[T]` for `Vec<T>`,
+/// `str` for `String`, `T` for `Box<T>`, `Rc<T>` and `Arc<T>`, and so on.
+fn deref_target(ty: &syn::Type) -> Option<syn::Type> {
+    let syn::Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    if type_path.qself.is_some() {
+        return None;
+    }
+    let element_ty = match &segment.arguments {
+        syn::PathArguments::AngleBracketed(generics) if generics.args.len() == 1 => {
+            match &generics.args[0] {
+                syn::GenericArgument::Type(element_ty) => Some(element_ty),
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+    match (segment.ident.to_string().as_str(), element_ty) {
+        ("Vec", Some(element_ty)) => Some(parse_quote!([#element_ty])),
+        ("Box" | "Rc" | "Arc", Some(element_ty)) => Some(element_ty.clone()),
+        ("String", None) => Some(parse_quote!(str)),
+        ("PathBuf", None) => Some(parse_quote!(std::path::Path)),
+        ("OsString", None) => Some(parse_quote!(std::ffi::OsStr)),
+        ("CString", None) => Some(parse_quote!(std::ffi::CStr)),
+        _ => None,
+    }
+}
+
 /// Given a function signature, identifies the name given to the `&dyn Db` reference
 /// and returns it, along with the type of the database.
 /// If the database lifetime did not have a name, then modifies the item function
@@ -839,11 +2049,12 @@ fn accumulated_fn(
     }
 
     let (db_var, arg_names) = fn_args(item_fn)?;
+    let key = interned_key(item_fn, &arg_names);
     accumulated_fn.block = parse_quote! {
         {
             let (__jar, __runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(#db_var);
             let __ingredients = <_ as salsa::storage::HasIngredientsFor<#config_ty>>::ingredient(__jar);
-            let __key = __ingredients.intern_map.intern(__runtime, (#(#arg_names),*));
+            let __key = __ingredients.intern_map.intern(__runtime, #key);
             __ingredients.function.accumulated::<__A>(#db_var, __key)
         }
     };
@@ -851,6 +2062,36 @@ fn accumulated_fn(
     Ok(accumulated_fn)
 }
 
+/// Generates the `accumulated_with_queries` function, which is like `accumulated`
+/// but also returns the query that accumulated each value.
+fn accumulated_with_queries_fn(
+    args: &FnArgs,
+    item_fn: &syn::ItemFn,
+    config_ty: &syn::Type,
+) -> syn::Result<syn::ItemFn> {
+    let jar_ty = args.jar_ty();
+
+    let mut accumulated_fn = accumulated_fn(args, item_fn, config_ty)?;
+    accumulated_fn.sig.ident =
+        syn::Ident::new("accumulated_with_queries", item_fn.sig.ident.span());
+    accumulated_fn.sig.output = parse_quote! {
+        -> Vec<salsa::accumulator::Accumulated<<__A as salsa::accumulator::Accumulator>::Data>>
+    };
+
+    let (db_var, arg_names) = fn_args(item_fn)?;
+    let key = interned_key(item_fn, &arg_names);
+    accumulated_fn.block = parse_quote! {
+        {
+            let (__jar, __runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(#db_var);
+            let __ingredients = <_ as salsa::storage::HasIngredientsFor<#config_ty>>::ingredient(__jar);
+            let __key = __ingredients.intern_map.intern(__runtime, #key);
+            __ingredients.function.accumulated_with_queries::<__A>(#db_var, __key)
+        }
+    };
+
+    Ok(accumulated_fn)
+}
+
 /// Examines the function arguments and returns a tuple of:
 ///
 /// * the name of the database argument
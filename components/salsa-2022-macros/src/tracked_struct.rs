@@ -28,23 +28,65 @@ impl std::ops::Deref for TrackedStruct {
 impl crate::options::AllowedOptions for TrackedStruct {
     const RETURN_REF: bool = false;
 
+    const RETURN_DEREF: bool = false;
+
     const SPECIFY: bool = false;
 
     const NO_EQ: bool = false;
 
+    const COMPARE_WITH: bool = false;
+
     const SINGLETON: bool = false;
 
     const JAR: bool = true;
 
     const DATA: bool = true;
 
-    const DB: bool = false;
+    const DB: bool = true;
 
     const RECOVERY_FN: bool = false;
 
     const LRU: bool = false;
 
     const CONSTRUCTOR_NAME: bool = true;
+
+    const INCLUDES: bool = false;
+
+    const CYCLE_ERR: bool = false;
+
+    const RETRY_ERR: bool = false;
+
+    const UPDATE: bool = false;
+
+    const STORAGE: bool = false;
+
+    const VOLATILE: bool = false;
+
+    const HEAP_SIZE: bool = false;
+
+    const INSTANCES: bool = false;
+
+    const HISTORY: bool = false;
+
+    const GC: bool = false;
+
+    const WEAK: bool = false;
+
+    const FIREWALL: bool = false;
+
+    const CAPACITY: bool = false;
+
+    const EXTENDS: bool = false;
+
+    const COLLECT: bool = false;
+
+    const REMOTE_CACHE: bool = false;
+
+    const ELEMENTS: bool = false;
+
+    const CYCLE_INITIAL: bool = false;
+
+    const NO_CLONE: bool = false;
 }
 
 impl TrackedStruct {
@@ -60,6 +102,8 @@ impl TrackedStruct {
         let salsa_struct_in_db_impl = self.salsa_struct_in_db_impl();
         let tracked_struct_in_db_impl = self.tracked_struct_in_db_impl();
         let as_id_impl = self.as_id_impl();
+        let update_impl = self.update_impl();
+        let heap_size_impl = self.heap_size_impl();
         let as_debug_with_db_impl = self.as_debug_with_db_impl();
         Ok(quote! {
             #(#config_structs)*
@@ -69,12 +113,15 @@ impl TrackedStruct {
             #salsa_struct_in_db_impl
             #tracked_struct_in_db_impl
             #as_id_impl
+            #update_impl
+            #heap_size_impl
             #as_debug_with_db_impl
             #(#config_impls)*
         })
     }
 
     fn validate_tracked(&self) -> syn::Result<()> {
+        self.disallow_default_fields("tracked")?;
         Ok(())
     }
 
@@ -98,6 +145,7 @@ impl TrackedStruct {
                     {
                         let (__jar, __runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(__db);
                         let __ingredients = <#jar_ty as salsa::storage::HasIngredientsFor< #ident >>::ingredient(__jar);
+                        __ingredients.#struct_index.assert_not_deleted(__db, self);
                         &__ingredients.#struct_index.tracked_struct_data(__runtime, self).#field_index
                     }
                 }
@@ -107,6 +155,7 @@ impl TrackedStruct {
                     {
                         let (__jar, __runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(__db);
                         let __ingredients = <#jar_ty as salsa::storage::HasIngredientsFor< #ident >>::ingredient(__jar);
+                        __ingredients.#struct_index.assert_not_deleted(__db, self);
                         __ingredients.#struct_index.tracked_struct_data(__runtime, self).#field_index.clone()
                     }
                 }
@@ -130,6 +179,7 @@ impl TrackedStruct {
                     {
                         let (__jar, __runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(__db);
                         let __ingredients = <#jar_ty as salsa::storage::HasIngredientsFor< #ident >>::ingredient(__jar);
+                        __ingredients.#struct_index.assert_not_deleted(__db, self);
                         __ingredients.#field_index.fetch(__db, self)
                     }
                 }
@@ -139,6 +189,7 @@ impl TrackedStruct {
                     {
                         let (__jar, __runtime) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(__db);
                         let __ingredients = <#jar_ty as salsa::storage::HasIngredientsFor< #ident >>::ingredient(__jar);
+                        __ingredients.#struct_index.assert_not_deleted(__db, self);
                         __ingredients.#field_index.fetch(__db, self).clone()
                     }
                 }
@@ -200,11 +251,13 @@ impl TrackedStruct {
                 ) -> Self::Ingredients
                 where
                     DB: salsa::DbWithJar<Self::Jar> + salsa::storage::JarFromJars<Self::Jar>,
+                    Self::Jar: salsa::jar::JarInDb<DB>,
                 {
                     (
                         #(
                             {
-                                let index = routes.push(
+                                let index = routes.push_field(
+                                    #debug_name_struct,
                                     |jars| {
                                         let jar = <DB as salsa::storage::JarFromJars<Self::Jar>>::jar_from_jars(jars);
                                         let ingredients = <_ as salsa::storage::HasIngredientsFor<Self>>::ingredient(jar);
@@ -236,6 +289,15 @@ impl TrackedStruct {
                         },
                     )
                 }
+
+                fn fork_ingredients(ingredients: &Self::Ingredients) -> Option<Self::Ingredients> {
+                    Some((
+                        #(
+                            ingredients.#value_field_indices.fork()?,
+                        )*
+                        ingredients.#tracked_struct_index.fork()?,
+                    ))
+                }
             }
         }
     }
@@ -255,6 +317,12 @@ impl TrackedStruct {
                     let ingredients = <#jar_ty as salsa::storage::HasIngredientsFor<#ident>>::ingredient(jar);
                     ingredients.#tracked_struct_index.register_dependent_fn(index)
                 }
+
+                fn instances_ingredient(db: &DB) -> Option<salsa::routes::IngredientIndex> {
+                    let (jar, _) = <_ as salsa::storage::HasJar<#jar_ty>>::jar(db);
+                    let ingredients = <#jar_ty as salsa::storage::HasIngredientsFor<#ident>>::ingredient(jar);
+                    Some(ingredients.#tracked_struct_index.ingredient_index())
+                }
             }
         }
     }
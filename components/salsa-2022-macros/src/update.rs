@@ -0,0 +1,116 @@
+//! Implementation of `#[derive(salsa::Update)]`, which merges each field
+//! (or, for enums, each field of the matching variant) separately.
+
+use proc_macro2::TokenStream;
+use syn::spanned::Spanned;
+
+pub(crate) fn update(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+    match update_impl(input) {
+        Ok(s) => s.into(),
+        Err(err) => err.into_compile_error().into(),
+    }
+}
+
+fn update_impl(input: syn::DeriveInput) -> syn::Result<TokenStream> {
+    let ident = &input.ident;
+    let body = match &input.data {
+        syn::Data::Struct(data) => {
+            let (old_pattern, new_pattern, updates) = fields_update(&data.fields);
+            quote! {
+                let #ident #new_pattern = new_value;
+                let #ident #old_pattern = old_value;
+                false #(| #updates)*
+            }
+        }
+        syn::Data::Enum(data) => {
+            let arms = data.variants.iter().map(|variant| {
+                let variant_ident = &variant.ident;
+                let (old_pattern, new_pattern, updates) = fields_update(&variant.fields);
+                quote! {
+                    #ident::#variant_ident #new_pattern => {
+                        if let #ident::#variant_ident #old_pattern = old_value {
+                            return false #(| #updates)*;
+                        }
+                        *old_value = #ident::#variant_ident #new_pattern;
+                        true
+                    }
+                }
+            });
+            quote! {
+                match new_value {
+                    #(#arms)*
+                }
+            }
+        }
+        syn::Data::Union(data) => {
+            return Err(syn::Error::new(
+                data.union_token.span(),
+                "`Update` cannot be derived for unions",
+            ))
+        }
+    };
+
+    let field_tys = match &input.data {
+        syn::Data::Struct(data) => data.fields.iter().map(|f| &f.ty).collect(),
+        syn::Data::Enum(data) => data
+            .variants
+            .iter()
+            .flat_map(|v| v.fields.iter().map(|f| &f.ty))
+            .collect(),
+        syn::Data::Union(_) => vec![],
+    };
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let mut where_clause = where_clause
+        .cloned()
+        .unwrap_or_else(|| parse_quote! { where });
+    for ty in field_tys {
+        where_clause
+            .predicates
+            .push(parse_quote! { #ty: salsa::update::Update });
+    }
+
+    Ok(quote! {
+        impl #impl_generics salsa::update::Update for #ident #ty_generics #where_clause {
+            #[allow(unused_variables, clippy::nonminimal_bool)]
+            fn maybe_update(old_value: &mut Self, new_value: Self) -> bool {
+                #body
+            }
+        }
+    })
+}
+
+/// Returns the patterns binding the fields of the old and new values, along
+/// with the expressions updating each old field from the new one.
+fn fields_update(fields: &syn::Fields) -> (TokenStream, TokenStream, Vec<TokenStream>) {
+    let old_names: Vec<_> = (0..fields.len())
+        .map(|i| syn::Ident::new(&format!("__old{}", i), proc_macro2::Span::call_site()))
+        .collect();
+    let new_names: Vec<_> = (0..fields.len())
+        .map(|i| syn::Ident::new(&format!("__new{}", i), proc_macro2::Span::call_site()))
+        .collect();
+    let updates = old_names
+        .iter()
+        .zip(&new_names)
+        .zip(fields)
+        .map(|((old_name, new_name), field)| {
+            let ty = &field.ty;
+            quote! { <#ty as salsa::update::Update>::maybe_update(#old_name, #new_name) }
+        })
+        .collect();
+    let (old_pattern, new_pattern) = match fields {
+        syn::Fields::Named(_) => {
+            let field_names: Vec<_> = fields.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+            (
+                quote! { { #(#field_names: #old_names),* } },
+                quote! { { #(#field_names: #new_names),* } },
+            )
+        }
+        syn::Fields::Unnamed(_) => (
+            quote! { ( #(#old_names),* ) },
+            quote! { ( #(#new_names),* ) },
+        ),
+        syn::Fields::Unit => (quote! {}, quote! {}),
+    };
+    (old_pattern, new_pattern, updates)
+}
@@ -1,15 +1,19 @@
 //! Basic test of accumulator functionality.
 
+use std::any::{Any, TypeId};
 use std::fmt;
 
 use crate::{
     cycle::CycleRecoveryStrategy,
-    hash::FxDashMap,
+    dump::IngredientDump,
+    hash::{FxDashMap, FxHashSet},
     ingredient::{fmt_index, Ingredient, IngredientRequiresReset},
     key::DependencyIndex,
-    runtime::local_state::QueryOrigin,
-    storage::HasJar,
-    DatabaseKeyIndex, Event, EventKind, IngredientIndex, Revision, Runtime,
+    memory::IngredientMemory,
+    metrics::QueryMetrics,
+    runtime::local_state::{EdgeKind, QueryOrigin},
+    storage::{HasJar, HasJarsDyn},
+    AsId, DatabaseKeyIndex, Event, EventKind, IngredientIndex, Revision, Runtime,
 };
 
 pub trait Accumulator {
@@ -20,15 +24,35 @@ pub trait Accumulator {
     where
         Db: ?Sized + HasJar<Self::Jar>;
 }
+
+/// A value accumulated by some query, as returned by `accumulated_with_queries`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Accumulated<Data> {
+    /// The query that accumulated the value.
+    pub query: DatabaseKeyIndex,
+
+    /// The number of dependencies (inputs and outputs) the query had recorded
+    /// when it accumulated the value. Together with `query`, this is a key
+    /// that is stable across runs.
+    pub position: usize,
+
+    /// The accumulated value.
+    pub value: Data,
+}
+
 pub struct AccumulatorIngredient<Data: Clone> {
     index: IngredientIndex,
     map: FxDashMap<DatabaseKeyIndex, AccumulatedValues<Data>>,
     debug_name: &'static str,
 }
 
+#[derive(Clone)]
 struct AccumulatedValues<Data> {
     produced_at: Revision,
-    values: Vec<Data>,
+
+    /// The values, along with the number of dependencies the query had
+    /// recorded when each of them was pushed.
+    values: Vec<(usize, Data)>,
 }
 
 impl<Data: Clone> AccumulatorIngredient<Data> {
@@ -40,6 +64,15 @@ impl<Data: Clone> AccumulatorIngredient<Data> {
         }
     }
 
+    /// Copies the accumulated values for the jars of a pipelined write.
+    pub fn fork(&self) -> Option<Self> {
+        Some(Self {
+            index: self.index,
+            map: self.map.clone(),
+            debug_name: self.debug_name,
+        })
+    }
+
     fn dependency_index(&self) -> DependencyIndex {
         DependencyIndex {
             ingredient_index: self.index,
@@ -49,6 +82,7 @@ impl<Data: Clone> AccumulatorIngredient<Data> {
 
     pub fn push(&self, runtime: &Runtime, value: Data) {
         let current_revision = runtime.current_revision();
+        let position = runtime.active_query_edge_count();
         let (active_query, _) = match runtime.active_query() {
             Some(pair) => pair,
             None => {
@@ -70,14 +104,64 @@ impl<Data: Clone> AccumulatorIngredient<Data> {
         }
 
         runtime.add_output(self.dependency_index());
-        accumulated_values.values.push(value);
+        accumulated_values.values.push((position, value));
     }
 
-    pub(crate) fn produced_by(
+    /// Returns the values accumulated by the memoized queries that take `entity`
+    /// (i.e., by the tracked functions whose key is this salsa struct) and by their
+    /// transitive inputs, in the order in which they were accumulated.
+    ///
+    /// The queries are brought up to date first, but only those that were already
+    /// called for `entity`: a function that has never been called for it is not.
+    /// A query reachable from several of them only contributes its values once.
+    pub fn accumulated_by<DB, E>(&self, db: &DB, entity: E) -> Vec<Data>
+    where
+        DB: ?Sized + HasJarsDyn,
+        E: AsId + 'static,
+    {
+        let queries = db.queries_keyed_by(TypeId::of::<E>(), entity.as_id());
+        let current_revision = db.runtime().current_revision();
+        for &query in &queries {
+            // Re-executes the query if its inputs changed.
+            db.maybe_changed_after(query.into(), current_revision);
+        }
+        self.accumulated_from(db, queries)
+            .into_iter()
+            .map(|accumulated| accumulated.value)
+            .collect()
+    }
+
+    /// Walks over the (up to date) queries in `start` and everything they depended on,
+    /// and returns the values they accumulated, in execution order.
+    pub(crate) fn accumulated_from<DB>(
+        &self,
+        db: &DB,
+        start: impl IntoIterator<Item = DatabaseKeyIndex>,
+    ) -> Vec<Accumulated<Data>>
+    where
+        DB: ?Sized + HasJarsDyn,
+    {
+        let runtime = db.runtime();
+        let mut result = vec![];
+        let mut stack = Stack::new(start);
+        while let Some(item) = stack.pop() {
+            match item {
+                Item::Value(accumulated) => result.push(accumulated),
+                Item::Query(query) => {
+                    let mut values = vec![];
+                    self.produced_by(runtime, query, &mut values);
+                    stack.extend(query, values, db.origin(query));
+                }
+            }
+        }
+        result
+    }
+
+    fn produced_by(
         &self,
         runtime: &Runtime,
         query: DatabaseKeyIndex,
-        output: &mut Vec<Data>,
+        output: &mut Vec<(usize, Data)>,
     ) {
         let current_revision = runtime.current_revision();
         if let Some(v) = self.map.get(&query) {
@@ -98,6 +182,97 @@ impl<Data: Clone> AccumulatorIngredient<Data> {
     }
 }
 
+/// An item left to visit in the DFS.
+enum Item<Data> {
+    /// A query whose accumulated values and dependencies are to be visited.
+    Query(DatabaseKeyIndex),
+
+    /// A value accumulated by some query.
+    Value(Accumulated<Data>),
+}
+
+/// The stack is used to execute a DFS across all the queries
+/// that were transitively executed by some given start query.
+/// When we visit a query Q0, we look at its dependencies Q1...Qn,
+/// and if they have not already been visited, we push them on the stack,
+/// interleaved with the values Q0 accumulated in between reading them.
+struct Stack<Data> {
+    /// Stack of items left to visit.
+    v: Vec<Item<Data>>,
+
+    /// Set of all queries we've seen.
+    s: FxHashSet<DatabaseKeyIndex>,
+}
+
+impl<Data> Stack<Data> {
+    /// Starts the DFS from `start`, visiting the queries in order.
+    fn new(start: impl IntoIterator<Item = DatabaseKeyIndex>) -> Self {
+        let mut v: Vec<_> = start.into_iter().map(Item::Query).collect();
+        v.reverse();
+        Self {
+            v,
+            s: FxHashSet::default(),
+        }
+    }
+
+    fn pop(&mut self) -> Option<Item<Data>> {
+        loop {
+            match self.v.pop()? {
+                Item::Query(query) if !self.s.insert(query) => continue,
+                item => return Some(item),
+            }
+        }
+    }
+
+    /// Extend the stack with the `values` accumulated by `query` and
+    /// the dependencies from its `origin`, in execution order.
+    fn extend(
+        &mut self,
+        query: DatabaseKeyIndex,
+        values: Vec<(usize, Data)>,
+        origin: Option<QueryOrigin>,
+    ) {
+        let edges = match origin {
+            None | Some(QueryOrigin::Assigned(_)) | Some(QueryOrigin::BaseInput) => None,
+            Some(QueryOrigin::Derived(edges)) | Some(QueryOrigin::DerivedUntracked(edges)) => {
+                Some(edges)
+            }
+        };
+
+        // A value pushed at position `p` was accumulated after the first `p`
+        // edges were recorded, so it comes before the edge with index `p`.
+        let mut items = vec![];
+        let mut values = values.into_iter().peekable();
+        for (index, &(kind, dependency_index)) in edges
+            .iter()
+            .flat_map(|edges| edges.input_outputs.iter())
+            .enumerate()
+        {
+            while let Some((position, value)) = values.next_if(|(position, _)| *position <= index) {
+                items.push(Item::Value(Accumulated {
+                    query,
+                    position,
+                    value,
+                }));
+            }
+            if kind == EdgeKind::Input {
+                if let Ok(i) = DatabaseKeyIndex::try_from(dependency_index) {
+                    items.push(Item::Query(i));
+                }
+            }
+        }
+        items.extend(values.map(|(position, value)| {
+            Item::Value(Accumulated {
+                query,
+                position,
+                value,
+            })
+        }));
+
+        self.v.extend(items.into_iter().rev());
+    }
+}
+
 impl<DB: ?Sized, Data> Ingredient<DB> for AccumulatorIngredient<Data>
 where
     DB: crate::Database,
@@ -141,13 +316,16 @@ where
     ) {
         assert!(stale_output_key.is_none());
         if self.map.remove(&executor).is_some() {
-            db.salsa_event(Event {
-                runtime_id: db.runtime().id(),
-                kind: EventKind::DidDiscardAccumulated {
-                    executor_key: executor,
-                    accumulator: self.dependency_index(),
+            crate::event::report(
+                db,
+                Event {
+                    runtime_id: db.runtime().id(),
+                    kind: EventKind::DidDiscardAccumulated {
+                        executor_key: executor,
+                        accumulator: self.dependency_index(),
+                    },
                 },
-            })
+            )
         }
     }
 
@@ -155,10 +333,78 @@ where
         panic!("unexpected reset on accumulator")
     }
 
+    fn sweep_edges(
+        &self,
+        _revision: Revision,
+        _op: &mut dyn FnMut(crate::Id, bool, &mut dyn Iterator<Item = DependencyIndex>),
+    ) {
+    }
+
+    fn sweep(&mut self, _revision: Revision, _reachable: &FxHashSet<DatabaseKeyIndex>) {}
+
+    fn release_weak_memos(&mut self) {}
+
+    fn unshare(&mut self) -> bool {
+        true
+    }
+
+    fn memoized_keys(&self) -> Vec<crate::Id> {
+        vec![]
+    }
+
+    fn query_metrics(&self) -> Option<QueryMetrics> {
+        None
+    }
+
+    fn memory_usage(&self) -> Option<IngredientMemory> {
+        None
+    }
+
+    fn memo_count(&self) -> Option<usize> {
+        None
+    }
+
+    fn dump(&self) -> IngredientDump {
+        IngredientDump {
+            entries: self.map.len(),
+            ..Default::default()
+        }
+    }
+
+    fn can_fetch_dyn(&self) -> bool {
+        false
+    }
+
+    fn fetch_dyn(&self, _db: &DB, _key: crate::Id) -> Option<Box<dyn Any>> {
+        None
+    }
+
+    fn history_start(&self) -> Option<Revision> {
+        None
+    }
+
+    fn revert_to(&mut self, _runtime: &mut Runtime, _revision: Revision) {}
+
+    fn changed_since(&self, _revision: Revision) -> Vec<crate::Id> {
+        vec![]
+    }
+
+    fn key_type(&self) -> Option<TypeId> {
+        None
+    }
+
+    fn creator(&self, _key_index: crate::Id) -> Option<DatabaseKeyIndex> {
+        None
+    }
+
     fn salsa_struct_deleted(&self, _db: &DB, _id: crate::Id) {
         panic!("unexpected call: accumulator is not registered as a dependent fn");
     }
 
+    fn debug_name(&self) -> &'static str {
+        self.debug_name
+    }
+
     fn fmt_index(&self, index: Option<crate::Id>, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt_index(self.debug_name, index, fmt)
     }
@@ -0,0 +1,210 @@
+//! Computes queries on background threads, e.g. to warm the memoized values
+//! after startup without delaying the queries called to answer the user.
+//!
+//! The threads run the queries given to [`Background::enqueue`] on snapshots of the
+//! database, which are only taken while queries remain to be computed, since a write
+//! waits for the snapshots to be dropped. A write cancels the queries running in the
+//! background: they are queued again, and computed on the new revision once
+//! [`Background::resume`] is called.
+//!
+//! A query called by a handle of the database while a background thread executes it
+//! waits for its value rather than executing it a second time.
+//!
+//! This requires the `parallel` feature, which is enabled by default.
+
+use std::any::Any;
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use crate::sync::{Condvar, Mutex};
+use crate::{Cancelled, ParallelDatabase, Snapshot};
+
+/// A pool of threads computing queries in the background.
+/// Dropping it waits for the queries being computed to complete.
+pub struct Background<DB: ParallelDatabase> {
+    shared: Arc<Shared<DB>>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+struct Shared<DB: ParallelDatabase> {
+    state: Mutex<State<DB>>,
+
+    /// Notified when a snapshot is handed to the threads or dropped by one,
+    /// or the threads are stopped.
+    wake: Condvar,
+}
+
+struct State<DB: ParallelDatabase> {
+    /// The queries to compute, in order.
+    jobs: VecDeque<Job<DB>>,
+
+    /// The snapshots taken by `resume`, which idle threads pick up.
+    snapshots: Vec<Snapshot<DB>>,
+
+    /// The number of threads holding a snapshot.
+    busy: usize,
+
+    stopped: bool,
+}
+
+/// A call of `query(db, key)`.
+struct Job<DB> {
+    /// The address of the query function and the key, to recognize duplicates.
+    query: usize,
+    key: Box<dyn Any + Send>,
+
+    run: Box<dyn Fn(&DB) + Send>,
+}
+
+impl<DB: ParallelDatabase + 'static> Background<DB> {
+    /// Starts `threads` threads, which wait for queries to compute.
+    pub fn new(threads: usize) -> Self {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                jobs: VecDeque::new(),
+                snapshots: Vec::new(),
+                busy: 0,
+                stopped: false,
+            }),
+            wake: Condvar::new(),
+        });
+        let threads = (0..threads)
+            .map(|_| {
+                let shared = shared.clone();
+                std::thread::spawn(move || shared.work())
+            })
+            .collect();
+        Background { shared, threads }
+    }
+
+    /// Queues the call of `query(db, key)`, typically a tracked function
+    /// (e.g. `|db, file| parse(db, file)`), unless it is already queued, and
+    /// starts computing it if a thread is idle.
+    ///
+    /// # Panics
+    ///
+    /// As [`ParallelDatabase::snapshot`], if called from inside of a query.
+    pub fn enqueue<K, V>(&self, db: &DB, query: fn(&DB, K) -> V, key: K)
+    where
+        K: Clone + PartialEq + Send + 'static,
+        V: 'static,
+    {
+        {
+            let mut state = self.shared.state.lock();
+            let address = query as usize;
+            let queued = state
+                .jobs
+                .iter()
+                .any(|job| job.query == address && job.key.downcast_ref::<K>() == Some(&key));
+            if !queued {
+                state.jobs.push_back(Job {
+                    query: address,
+                    key: Box::new(key.clone()),
+                    run: Box::new(move |db| {
+                        query(db, key.clone());
+                    }),
+                });
+            }
+        }
+        self.resume(db);
+    }
+
+    /// Hands snapshots of `db` to the idle threads, if queries remain to be computed.
+    /// Call this after writing to the database, to compute the queries whose
+    /// execution the write cancelled.
+    ///
+    /// # Panics
+    ///
+    /// As [`ParallelDatabase::snapshot`], if called from inside of a query.
+    pub fn resume(&self, db: &DB) {
+        let mut state = self.shared.state.lock();
+        let idle = self.threads.len() - state.busy - state.snapshots.len();
+        let missing = state.jobs.len().saturating_sub(state.snapshots.len());
+        for _ in 0..idle.min(missing) {
+            state.snapshots.push(db.snapshot());
+        }
+        self.shared.wake.notify_all();
+    }
+
+    /// Returns the number of queries that remain to be computed,
+    /// not counting those being computed.
+    pub fn pending(&self) -> usize {
+        self.shared.state.lock().jobs.len()
+    }
+
+    /// Waits until the threads no longer hold snapshots: the queries are computed,
+    /// or those that remain were cancelled and wait for [`Background::resume`].
+    pub fn wait(&self) {
+        let mut state = self.shared.state.lock();
+        while state.busy > 0 || !state.snapshots.is_empty() {
+            self.shared.wake.wait(&mut state);
+        }
+    }
+}
+
+impl<DB: ParallelDatabase> Shared<DB> {
+    /// The loop of each thread: waits for a snapshot, then computes queries
+    /// with it until none remain or they are cancelled, and drops it.
+    fn work(&self) {
+        loop {
+            let snapshot = {
+                let mut state = self.state.lock();
+                loop {
+                    if state.stopped {
+                        return;
+                    }
+                    if let Some(snapshot) = state.snapshots.pop() {
+                        state.busy += 1;
+                        break snapshot;
+                    }
+                    self.wake.wait(&mut state);
+                }
+            };
+
+            while let Some(job) = self.next_job() {
+                match panic::catch_unwind(AssertUnwindSafe(|| (job.run)(&snapshot))) {
+                    Ok(()) => {}
+                    Err(payload) if payload.is::<Cancelled>() => {
+                        // Computed again with the snapshot of the next revision.
+                        self.state.lock().jobs.push_front(job);
+                        break;
+                    }
+                    Err(_) => {
+                        // The panic was reported by the panic hook; the query
+                        // would panic again if computed in the same revision.
+                        log::warn!("a query computed in the background panicked");
+                    }
+                }
+            }
+
+            // Lets writes proceed, before another snapshot may be handed out.
+            drop(snapshot);
+            self.state.lock().busy -= 1;
+            self.wake.notify_all();
+        }
+    }
+
+    fn next_job(&self) -> Option<Job<DB>> {
+        let mut state = self.state.lock();
+        if state.stopped {
+            return None;
+        }
+        state.jobs.pop_front()
+    }
+}
+
+impl<DB: ParallelDatabase> Drop for Background<DB> {
+    fn drop(&mut self) {
+        {
+            let mut state = self.shared.state.lock();
+            state.stopped = true;
+            state.snapshots.clear();
+        }
+        self.shared.wake.notify_all();
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
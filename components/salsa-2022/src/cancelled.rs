@@ -1,34 +1,78 @@
 use std::{
     fmt,
     panic::{self, UnwindSafe},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
 
+use crate::runtime::QueryFrame;
+
 /// A panic payload indicating that execution of a salsa query was cancelled.
 ///
-/// This can occur for a few reasons:
-/// *
-/// *
-/// *
+/// Salsa unwinds with it using [`std::panic::resume_unwind`], so the panic hook is
+/// not invoked. [`Cancelled::catch`] tells it from the panics of the queries,
+/// which are bugs. This can occur for a few reasons:
+/// * another handle of the database is waiting to write to it,
+/// * the query was blocked on another thread, which panicked,
+/// * the cancellation token of the snapshot was cancelled,
+/// * the timeout given to [`Database::with_timeout`](`crate::Database::with_timeout`) elapsed.
 #[derive(Debug)]
 #[non_exhaustive]
 pub enum Cancelled {
     /// The query was operating on revision R, but there is a pending write to move to revision R+1.
     #[non_exhaustive]
-    PendingWrite,
+    PendingWrite {
+        /// The queries that were executing on the cancelled thread, outermost first.
+        query_stack: Vec<QueryFrame>,
+    },
 
     /// The query was blocked on another thread, and that thread panicked.
     #[non_exhaustive]
-    PropagatedPanic,
+    PropagatedPanic {
+        /// The queries that were executing on the cancelled thread, outermost first.
+        query_stack: Vec<QueryFrame>,
+    },
+
+    /// The query was running on a snapshot created with
+    /// [`ParallelDatabase::snapshot_with_token`](`crate::ParallelDatabase::snapshot_with_token`),
+    /// and that token was cancelled.
+    #[non_exhaustive]
+    Requested {
+        /// The queries that were executing on the cancelled thread, outermost first.
+        query_stack: Vec<QueryFrame>,
+    },
+
+    /// The query was called from [`Database::with_timeout`](`crate::Database::with_timeout`),
+    /// and the timeout elapsed.
+    #[non_exhaustive]
+    TimedOut {
+        /// The queries that were executing on the cancelled thread, outermost first.
+        query_stack: Vec<QueryFrame>,
+    },
 }
 
 impl Cancelled {
     pub(crate) fn throw(self) -> ! {
+        crate::trace::cancelled(&self);
         // We use resume and not panic here to avoid running the panic
         // hook (that is, to avoid collecting and printing backtrace).
         std::panic::resume_unwind(Box::new(self));
     }
 
+    /// Returns the queries that were executing on the cancelled thread, outermost first.
+    pub fn query_stack(&self) -> &[QueryFrame] {
+        match self {
+            Cancelled::PendingWrite { query_stack }
+            | Cancelled::PropagatedPanic { query_stack }
+            | Cancelled::Requested { query_stack }
+            | Cancelled::TimedOut { query_stack } => query_stack,
+        }
+    }
+
     /// Runs `f`, and catches any salsa cancellation.
+    /// Other panics keep unwinding.
     pub fn catch<F, T>(f: F) -> Result<T, Cancelled>
     where
         F: FnOnce() -> T + UnwindSafe,
@@ -46,8 +90,10 @@ impl Cancelled {
 impl std::fmt::Display for Cancelled {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let why = match self {
-            Cancelled::PendingWrite => "pending write",
-            Cancelled::PropagatedPanic => "propagated panic",
+            Cancelled::PendingWrite { .. } => "pending write",
+            Cancelled::PropagatedPanic { .. } => "propagated panic",
+            Cancelled::Requested { .. } => "cancellation request",
+            Cancelled::TimedOut { .. } => "timeout",
         };
         f.write_str("cancelled because of ")?;
         f.write_str(why)
@@ -55,3 +101,29 @@ impl std::fmt::Display for Cancelled {
 }
 
 impl std::error::Error for Cancelled {}
+
+/// Cancels the queries running on the snapshots created with it by
+/// [`ParallelDatabase::snapshot_with_token`](`crate::ParallelDatabase::snapshot_with_token`),
+/// e.g. to abandon an outdated request while the other ones keep running.
+///
+/// Clones share the same state, so the token can be cancelled from another thread.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the snapshots created with this token: their queries unwind
+    /// with [`Cancelled::Requested`] the next time they call a query.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,66 @@
+//! Support for the tracked methods of traits annotated with `#[salsa::component]`.
+//!
+//! The tracked function generated for such a method is shared by all the types
+//! implementing the trait, so its memos are keyed by a [`Method`], which identifies
+//! the implementing type and executes the method for it, along with the id of `self`.
+
+use std::any::TypeId;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use crate::salsa_struct::SalsaStructInDb;
+use crate::{Database, IngredientIndex};
+
+/// The tracked method of a component, as implemented by a particular type:
+/// `execute` is a function pointer running the method for that type.
+///
+/// Two `Method`s are equal if they are for the same type.
+#[derive(Copy, Clone)]
+pub struct Method<F> {
+    type_id: TypeId,
+    type_name: &'static str,
+    execute: F,
+}
+
+impl<F: Copy> Method<F> {
+    /// Returns the method as implemented by `T`.
+    pub fn of<T: 'static>(execute: F) -> Self {
+        Method {
+            type_id: TypeId::of::<T>(),
+            type_name: std::any::type_name::<T>(),
+            execute,
+        }
+    }
+
+    /// Returns the function executing the method.
+    pub fn execute(&self) -> F {
+        self.execute
+    }
+}
+
+impl<F> PartialEq for Method<F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.type_id == other.type_id
+    }
+}
+
+impl<F> Eq for Method<F> {}
+
+impl<F> Hash for Method<F> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.type_id.hash(state);
+    }
+}
+
+impl<F> fmt::Debug for Method<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Method").field(&self.type_name).finish()
+    }
+}
+
+/// The memos are keyed by the id of `self` rather than by a salsa struct, so
+/// like the memos of functions with several arguments, they are not removed
+/// when a tracked struct is deleted.
+impl<DB: ?Sized + Database, F> SalsaStructInDb<DB> for Method<F> {
+    fn register_dependent_fn(_db: &DB, _index: IngredientIndex) {}
+}
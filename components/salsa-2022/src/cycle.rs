@@ -1,5 +1,5 @@
 use crate::debug::DebugWithDb;
-use crate::{key::DatabaseKeyIndex, Database};
+use crate::{key::DatabaseKeyIndex, runtime::QueryFrame, Database};
 use std::{panic::AssertUnwindSafe, sync::Arc};
 
 /// Captures the participants of a cycle that occurred when executing a query.
@@ -13,20 +13,30 @@ use std::{panic::AssertUnwindSafe, sync::Arc};
 /// * During [cycle recovery](https://https://salsa-rs.github.io/salsa/cycles/fallback.html),
 ///   where it is given to the fallback function.
 /// * As the panic value when an unexpected cycle (i.e., a cycle where one or more participants
-///   lacks cycle recovery information) occurs.
+///   lacks cycle recovery information) occurs, which [`Cycle::catch`] turns into an error.
 ///
 /// You can read more about cycle handling in
 /// the [salsa book](https://https://salsa-rs.github.io/salsa/cycles.html).
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Cycle {
     participants: CycleParticipants,
+    participant_frames: Arc<Vec<QueryFrame>>,
+    query_stack: Arc<Vec<QueryFrame>>,
 }
 
 pub(crate) type CycleParticipants = Arc<Vec<DatabaseKeyIndex>>;
 
 impl Cycle {
-    pub(crate) fn new(participants: CycleParticipants) -> Self {
-        Self { participants }
+    pub(crate) fn new(
+        participants: CycleParticipants,
+        participant_frames: Arc<Vec<QueryFrame>>,
+        query_stack: Arc<Vec<QueryFrame>>,
+    ) -> Self {
+        Self {
+            participants,
+            participant_frames,
+            query_stack,
+        }
     }
 
     /// True if two `Cycle` values represent the same cycle.
@@ -39,7 +49,9 @@ impl Cycle {
         std::panic::resume_unwind(Box::new(self))
     }
 
-    pub(crate) fn catch<T>(execute: impl FnOnce() -> T) -> Result<T, Cycle> {
+    /// Runs `execute`, and catches a cycle that unwinds out of it because
+    /// some of its participants have no cycle recovery.
+    pub fn catch<T>(execute: impl FnOnce() -> T) -> Result<T, Cycle> {
         match std::panic::catch_unwind(AssertUnwindSafe(execute)) {
             Ok(v) => Ok(v),
             Err(err) => match err.downcast::<Cycle>() {
@@ -57,6 +69,18 @@ impl Cycle {
         self.participants.iter().copied()
     }
 
+    /// Returns the queries participating in the cycle, in the same order as
+    /// [`Cycle::participant_keys`], named like the frames of [`Cycle::query_stack`].
+    pub fn participants(&self) -> &[QueryFrame] {
+        &self.participant_frames
+    }
+
+    /// Returns the queries that were executing on the thread that detected
+    /// the cycle, outermost first.
+    pub fn query_stack(&self) -> &[QueryFrame] {
+        &self.query_stack
+    }
+
     /// Returns a vector with the debug information for
     /// all the participants in the cycle.
     pub fn all_participants<DB: ?Sized + Database>(&self, db: &DB) -> Vec<String> {
@@ -103,6 +127,22 @@ impl Cycle {
     }
 }
 
+/// Lists the participants, e.g. `cycle between `a(0)`, `b(0)``.
+impl std::fmt::Display for Cycle {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.write_str("cycle between ")?;
+        for (index, participant) in self.participant_frames.iter().enumerate() {
+            if index > 0 {
+                fmt.write_str(", ")?;
+            }
+            write!(fmt, "`{participant}`")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Cycle {}
+
 /// Cycle recovery strategy: Is this query capable of recovering from
 /// a cycle that results from executing the function? If so, how?
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -114,12 +154,29 @@ pub enum CycleRecoveryStrategy {
     /// strategies.
     ///
     /// In the case of a failure due to a cycle, the panic
-    /// value will be XXX (FIXME).
+    /// value will be the [`Cycle`] itself, which can be recovered
+    /// with `downcast_ref::<Cycle>()` after catching the unwind.
     Panic,
 
     /// Recovers from cycles by storing a sentinel value.
     ///
-    /// This value is computed by the `QueryFunction::cycle_fallback`
-    /// function.
+    /// This value is computed by the function given with the
+    /// `recovery_fn` option of `#[salsa::tracked]`.
     Fallback,
+
+    /// Recovers from cycles by iterating to a fixed point.
+    ///
+    /// When the query is called by a query it called, that call returns
+    /// a provisional value: the one computed by the function given with the
+    /// `cycle_initial` option of `#[salsa::tracked]` at first. The query is then
+    /// executed again with the value it returned as the provisional value, until
+    /// it returns the same value twice (or [`MAX_ITERATIONS`] is reached, which panics).
+    ///
+    /// Only the cycles within one thread are iterated: a cycle that involves
+    /// other threads is handled as with [`CycleRecoveryStrategy::Panic`].
+    FixedPoint,
 }
+
+/// The number of times the head of a cycle with the [`CycleRecoveryStrategy::FixedPoint`]
+/// strategy is executed, at most, before panicking because its value does not converge.
+pub const MAX_ITERATIONS: u32 = 200;
@@ -1,4 +1,19 @@
-use crate::{storage::HasJarsDyn, DebugWithDb, Durability, Event};
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "parallel")]
+use crate::cancelled::CancellationToken;
+#[cfg(feature = "parallel")]
+use crate::sync::Mutex;
+use crate::{
+    history::RevertError,
+    key::{DependencyIndex, StableKey},
+    revision::RevisionToken,
+    storage::HasJarsDyn,
+    subscription::SubscriptionId,
+    Cancelled, DatabaseKeyIndex, DebugWithDb, Durability, Event, Id, Revision,
+};
 
 pub trait Database: HasJarsDyn + AsSalsaDatabase {
     /// This function is invoked at key points in the salsa
@@ -11,6 +26,13 @@ pub trait Database: HasJarsDyn + AsSalsaDatabase {
         log::debug!("salsa_event: {:?}", event.debug(self));
     }
 
+    /// The cache consulted by the tracked functions with the `remote_cache` option
+    /// before they execute, if any. By default, there is none.
+    #[cfg(feature = "remote-cache")]
+    fn remote_cache(&self) -> Option<&dyn crate::remote_cache::RemoteCache> {
+        None
+    }
+
     /// A "synthetic write" causes the system to act *as though* some
     /// input of durability `durability` has changed. This is mostly
     /// useful for profiling scenarios.
@@ -19,10 +41,96 @@ pub trait Database: HasJarsDyn + AsSalsaDatabase {
     /// cancellation. If you invoke it while a snapshot exists, it
     /// will block until that snapshot is dropped -- if that snapshot
     /// is owned by the current thread, this could trigger deadlock.
+    #[track_caller]
     fn synthetic_write(&mut self, durability: Durability) {
+        self.runtime().assert_not_frozen();
         self.runtime_mut().report_tracked_write(durability);
     }
 
+    /// Restores the fields of the inputs declared with `#[salsa::input(history = N)]`
+    /// to the values they had in `revision` (e.g., from [`Database::attach_revision`]),
+    /// as writes in a new revision. This can be used to undo changes, or to find which
+    /// change made an output regress by reverting to successive older revisions.
+    ///
+    /// The history of each such input only covers the last `N` revisions:
+    /// reverting further back fails without changing anything.
+    fn revert_to(&mut self, revision: Revision) -> Result<(), RevertError> {
+        self.revert_inputs(revision)
+    }
+
+    /// Lets writes proceed while snapshots are alive, rather than cancelling them
+    /// and waiting for them to be dropped: the snapshots keep reading the revision
+    /// they started in, from a copy of the database state.
+    /// See [`Storage::set_pipelined_writes`](`crate::storage::Storage::set_pipelined_writes`).
+    fn set_pipelined_writes(&mut self, enabled: bool) {
+        self.pipeline_writes(enabled);
+    }
+
+    /// Limits the number of handles (this one and its snapshots) executing queries
+    /// at the same time, or lifts the limit with `None`.
+    /// See [`Storage::set_max_concurrent_queries`](`crate::storage::Storage::set_max_concurrent_queries`).
+    fn set_max_concurrent_queries(&mut self, max: Option<usize>) {
+        self.runtime().set_max_concurrent_queries(max);
+    }
+
+    /// Runs `op`, which typically sets many inputs, so that all of its writes
+    /// happen in a single new revision: other handles are cancelled once, rather
+    /// than on each write, and tracked functions see either none or all of the writes.
+    ///
+    /// Reading a query from `op` (or taking a snapshot) splits the transaction:
+    /// the writes after it happen in another new revision, so that the values
+    /// just read are not invalidated behind salsa's back. Nested calls
+    /// are part of the outer transaction.
+    fn transaction<R>(&mut self, op: impl FnOnce(&mut Self) -> R) -> R
+    where
+        Self: Sized,
+    {
+        if !self.runtime().begin_transaction() {
+            return op(self);
+        }
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| op(self)));
+        self.runtime().end_transaction();
+        result.unwrap_or_else(|payload| std::panic::resume_unwind(payload))
+    }
+
+    /// Runs `op`, which typically calls a query, and cancels the queries it calls once
+    /// `timeout` has elapsed, returning `Err(Cancelled::TimedOut { .. })` rather than
+    /// letting a pathological input stall the caller. Other cancellations propagate.
+    ///
+    /// The timeout is checked each time a query is called, so a query spending
+    /// a long time without calling other queries runs to completion. When it is nested
+    /// in another `with_timeout`, the earliest deadline of the two applies.
+    ///
+    /// If it is called from a query and times out, that query is executed again
+    /// in the next revision, since its value depends on how long `op` took.
+    fn with_timeout<T>(&self, timeout: Duration, op: impl FnOnce() -> T) -> Result<T, Cancelled>
+    where
+        Self: Sized,
+    {
+        let runtime = self.runtime();
+        let deadline = Instant::now() + timeout;
+        match runtime.with_deadline(deadline, || panic::catch_unwind(AssertUnwindSafe(op))) {
+            Ok(value) => Ok(value),
+            Err(payload) => match payload.downcast::<Cancelled>() {
+                Ok(cancelled) if matches!(*cancelled, Cancelled::TimedOut { .. }) => {
+                    runtime.report_untracked_read();
+                    Err(*cancelled)
+                }
+                Ok(cancelled) => panic::resume_unwind(cancelled),
+                Err(payload) => panic::resume_unwind(payload),
+            },
+        }
+    }
+
+    /// Unwinds with a [`Cancelled`] payload if the queries of this handle are cancelled:
+    /// because of a pending write, a cancelled token or an elapsed timeout.
+    /// Salsa checks this each time a query is called; call it from queries running
+    /// potentially expensive computations, so that they stop promptly too.
+    /// The payload can be told from other panics with [`Cancelled::catch`].
+    fn unwind_if_cancelled(&self) {
+        self.runtime().unwind_if_revision_cancelled(self);
+    }
+
     /// Reports that the query depends on some state unknown to salsa.
     ///
     /// Queries which report untracked reads will be re-executed in the next
@@ -30,12 +138,169 @@ pub trait Database: HasJarsDyn + AsSalsaDatabase {
     fn report_untracked_read(&self) {
         self.runtime().report_untracked_read();
     }
+
+    /// Returns the current revision of the database, which a write replaces by
+    /// the [next](`Revision::next`) one.
+    fn current_revision(&self) -> Revision {
+        self.runtime().current_revision()
+    }
+
+    /// Freezes the database until the returned guard is dropped: writing to it meanwhile,
+    /// through any of its handles, panics at the location of the write, naming where the
+    /// guard was created. So a batch of queries executing on snapshots of the database,
+    /// e.g. on other threads, is never cancelled before the guard is dropped.
+    #[track_caller]
+    fn freeze(&self) -> crate::runtime::FrozenGuard {
+        self.runtime().freeze()
+    }
+
+    /// Captures the current revision, so that a value computed from the database
+    /// outside of the query system can later be checked for staleness with
+    /// [`Database::is_still_valid`].
+    fn attach_revision(&self) -> RevisionToken {
+        RevisionToken {
+            revision: self.runtime().current_revision(),
+            durability: Durability::LOW,
+        }
+    }
+
+    /// Returns true if no input of (at least) the token's durability has changed
+    /// since `token` was captured. This check is conservative: it may report
+    /// values as stale even though the inputs they read are unchanged, but
+    /// never the reverse.
+    fn is_still_valid(&self, token: RevisionToken) -> bool {
+        self.runtime().last_changed_revision(token.durability) <= token.revision
+    }
+
+    /// Returns the memoized queries whose value changed after `revision`,
+    /// e.g. to only regenerate the outputs of the functions that changed.
+    /// Use [`Database::attach_revision`] to capture the revision to compare against.
+    ///
+    /// Each memo is only as recent as the last time its query was called (or
+    /// validated), so call the queries of interest before this.
+    fn changed_since(&self, revision: Revision) -> Vec<DatabaseKeyIndex> {
+        self.memos_changed_since(revision)
+    }
+
+    /// Returns the query that created the tracked struct `entity` (see
+    /// [`TrackedStructInDb::database_key_index`](`crate::tracked_struct::TrackedStructInDb::database_key_index`)),
+    /// or `None` if it is not a tracked struct, or was deleted because that query
+    /// no longer creates it. This is not a tracked read.
+    fn creator_of(&self, entity: DatabaseKeyIndex) -> Option<DatabaseKeyIndex> {
+        self.entity_creator(entity)
+    }
+
+    /// Returns the tracked structs created by the last execution of `query`
+    /// (e.g., as returned by [`Database::creator_of`] or [`Database::changed_since`]),
+    /// in the order in which they were created. This is not a tracked read.
+    fn entities_created_by(&self, query: DatabaseKeyIndex) -> Vec<DatabaseKeyIndex> {
+        self.created_entities(query)
+    }
+
+    /// Returns the (approximate) memory used by the memoized values of each
+    /// tracked function, grouped by jar, e.g. to choose which ones need an `lru` limit.
+    /// The heap memory allocated by the values is only reported for tracked
+    /// functions with the `heap_size` option.
+    fn memory_report(&self) -> crate::memory::MemoryReport {
+        self.ingredient_memory()
+    }
+
+    /// Returns the names of the tracked functions that can be called with
+    /// [`Database::fetch_dyn`], e.g. `my_crate::Jar::my_query`: those whose
+    /// values are cloned (i.e., without the `return_ref` option).
+    fn query_names(&self) -> Vec<String> {
+        self.dyn_query_names()
+    }
+
+    /// Calls the tracked function named `name` (as listed by [`Database::query_names`])
+    /// for `key`, e.g. from a REPL, and returns a clone of its value to be downcast;
+    /// or `None` if there is no such function.
+    ///
+    /// The key is the id of the salsa struct the function takes (see [`AsId`](`crate::AsId`));
+    /// for functions with several arguments, the id of the interned arguments;
+    /// and for functions with no arguments, `Id::from_u32(0)`.
+    fn fetch_dyn(&self, name: &str, key: Id) -> Option<Box<dyn Any>> {
+        self.dyn_fetch(name, key)
+    }
+
+    /// Subscribes to the value of `query(db, key)`, e.g. to update a view when it changes:
+    /// `on_change` is called with the new value whenever the subscriptions are refreshed
+    /// and the value differs from the one seen last time (initially, the value when
+    /// subscribing). Returns an id to [unsubscribe](`Database::unsubscribe`) with.
+    ///
+    /// The subscriptions are refreshed once per revision, when the first tracked function
+    /// called in it (from outside of any query) returns, and by [`Database::refresh_subscriptions`].
+    /// They are shared with the snapshots of the database.
+    ///
+    /// ```ignore
+    /// db.subscribe(|db, file| line_count(db, file), file, move |count| {
+    ///     sender.send(*count).unwrap();
+    /// });
+    /// ```
+    fn subscribe<K, V>(
+        &self,
+        query: impl Fn(&Self, K) -> V + Send + 'static,
+        key: K,
+        mut on_change: impl FnMut(&V) + Send + 'static,
+    ) -> SubscriptionId
+    where
+        Self: Sized,
+        K: Copy + Send + 'static,
+        V: PartialEq + Send + 'static,
+    {
+        let mut last_value = query(self, key);
+        self.add_subscription(Box::new(move |db| {
+            let value = query(db, key);
+            if value != last_value {
+                on_change(&value);
+                last_value = value;
+            }
+        }))
+    }
+
+    /// Removes a subscription made with [`Database::subscribe`].
+    fn unsubscribe(&self, id: SubscriptionId) {
+        self.remove_subscription(id);
+    }
+
+    /// Calls the query of each subscription (see [`Database::subscribe`]) now, and its
+    /// callback if its value changed, rather than waiting for a query to be called in
+    /// the next revision. This must not be called from such a callback.
+    fn refresh_subscriptions(&self) {
+        self.run_subscriptions(false);
+    }
+
+    /// Converts `index` (or a [`DatabaseKeyIndex`], with `.into()`) into a [`StableKey`],
+    /// which names the ingredient rather than numbering it. Unlike the index, it stays
+    /// valid when ingredients are added to the database, so it can be stored on disk or
+    /// sent to another process and converted back with [`Database::resolve_stable_key`].
+    fn stable_key(&self, index: DependencyIndex) -> StableKey {
+        self.dyn_stable_key(index)
+    }
+
+    /// Converts a key made by [`Database::stable_key`], possibly by another process,
+    /// back into a `DependencyIndex`; or returns `None` if this database has no
+    /// ingredient with that name. Use `DatabaseKeyIndex::try_from` to get back
+    /// the key of a query.
+    fn resolve_stable_key(&self, key: &StableKey) -> Option<DependencyIndex> {
+        self.dyn_resolve_stable_key(key)
+    }
+
+    /// Returns how many times each tracked function was executed, how long that took,
+    /// and how many times its memoized values were reused or backdated.
+    #[cfg(feature = "metrics")]
+    fn query_metrics(&self) -> Vec<crate::metrics::QueryMetrics> {
+        self.ingredient_metrics()
+    }
 }
 
 /// Indicates a database that also supports parallel query
 /// evaluation. All of Salsa's base query support is capable of
 /// parallel execution, but for it to work, your query key/value types
 /// must also be `Send`, as must any additional data in your database.
+///
+/// This requires the `parallel` feature, which is enabled by default.
+#[cfg(feature = "parallel")]
 pub trait ParallelDatabase: Database + Send {
     /// Creates a second handle to the database that holds the
     /// database fixed at a particular revision. So long as this
@@ -51,7 +316,7 @@ pub trait ParallelDatabase: Database + Send {
     /// series of queries in parallel and arranging the results. Using
     /// this method for that purpose ensures that those queries will
     /// see a consistent view of the database (it is also advisable
-    /// for those queries to use the [`Runtime::unwind_if_cancelled`]
+    /// for those queries to use the [`Database::unwind_if_cancelled`]
     /// method to check for cancellation).
     ///
     /// # Panics
@@ -59,6 +324,13 @@ pub trait ParallelDatabase: Database + Send {
     /// It is not permitted to create a snapshot from inside of a
     /// query. Attepting to do so will panic.
     ///
+    /// To parallelize the work of a query, call [`crate::par_map`] from
+    /// it instead, which records the reads made on the other threads as
+    /// dependencies of the query. Or fan out over snapshots *before*
+    /// invoking it: have each snapshot execute some of the sub-queries,
+    /// drop the snapshots, and then invoke the query itself, which will
+    /// find those sub-queries memoized.
+    ///
     /// # Deadlock warning
     ///
     /// The intended pattern for snapshots is that, once created, they
@@ -96,6 +368,23 @@ pub trait ParallelDatabase: Database + Send {
     /// }
     /// ```
     fn snapshot(&self) -> Snapshot<Self>;
+
+    /// Creates a snapshot (with [`ParallelDatabase::snapshot`]) whose queries
+    /// can be cancelled on their own with `token`, leaving the other snapshots
+    /// running. Once `token` is cancelled, the queries of the snapshot unwind with
+    /// [`Cancelled::Requested`](`crate::Cancelled::Requested`) the next time they
+    /// call a query; catch it with [`Cancelled::catch`](`crate::Cancelled::catch`).
+    ///
+    /// If another snapshot is blocked on a query that the cancelled snapshot was
+    /// executing, it executes the query itself instead of being cancelled too.
+    fn snapshot_with_token(&self, token: CancellationToken) -> Snapshot<Self>
+    where
+        Self: Sized,
+    {
+        let snapshot = self.snapshot();
+        snapshot.runtime().set_cancellation_token(token);
+        snapshot
+    }
 }
 pub trait AsSalsaDatabase {
     fn as_salsa_database(&self) -> &dyn Database;
@@ -106,6 +395,7 @@ pub trait AsSalsaDatabase {
 /// for more details.
 ///
 /// [fm]: trait.ParallelDatabase.html#method.snapshot
+#[cfg(feature = "parallel")]
 #[derive(Debug)]
 pub struct Snapshot<DB: ?Sized>
 where
@@ -114,6 +404,7 @@ where
     db: DB,
 }
 
+#[cfg(feature = "parallel")]
 impl<DB> Snapshot<DB>
 where
     DB: ParallelDatabase,
@@ -126,6 +417,7 @@ where
     }
 }
 
+#[cfg(feature = "parallel")]
 impl<DB> std::ops::Deref for Snapshot<DB>
 where
     DB: ParallelDatabase,
@@ -136,3 +428,60 @@ where
         &self.db
     }
 }
+
+/// A database handle that is `Sync`: queries are called on it through `&self`
+/// from any number of threads (e.g. in `rayon` closures), and inputs are set
+/// through [`SyncDatabase::get_mut`], with no `Snapshot` to manage.
+///
+/// Each call to [`SyncDatabase::with`] runs on a snapshot of the database, taken
+/// from a pool so that snapshots are only created when more threads than before
+/// call queries at the same time. The pool is emptied on `get_mut`, since a write
+/// waits for all snapshots to be dropped.
+#[cfg(feature = "parallel")]
+pub struct SyncDatabase<DB: ParallelDatabase> {
+    db: Mutex<DB>,
+    snapshots: Mutex<Vec<Snapshot<DB>>>,
+}
+
+#[cfg(feature = "parallel")]
+impl<DB: ParallelDatabase> SyncDatabase<DB> {
+    pub fn new(db: DB) -> Self {
+        SyncDatabase {
+            db: Mutex::new(db),
+            snapshots: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Calls `op` with a snapshot of the database that no other thread is using.
+    ///
+    /// # Panics
+    ///
+    /// As [`ParallelDatabase::snapshot`], if called from inside of a query.
+    pub fn with<R>(&self, op: impl FnOnce(&DB) -> R) -> R {
+        let snapshot = self.snapshots.lock().pop();
+        let snapshot = snapshot.unwrap_or_else(|| self.db.lock().snapshot());
+        let result = op(&snapshot);
+
+        // If `op` unwinds (e.g. because the query was cancelled), the snapshot
+        // is dropped instead.
+        self.snapshots.lock().push(snapshot);
+        result
+    }
+
+    /// Returns the database, to set inputs, after dropping the pooled snapshots.
+    pub fn get_mut(&mut self) -> &mut DB {
+        self.snapshots.get_mut().clear();
+        self.db.get_mut()
+    }
+
+    pub fn into_inner(self) -> DB {
+        self.db.into_inner()
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<DB: ParallelDatabase> From<DB> for SyncDatabase<DB> {
+    fn from(db: DB) -> Self {
+        SyncDatabase::new(db)
+    }
+}
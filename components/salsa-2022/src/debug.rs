@@ -1,5 +1,5 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     fmt,
     rc::Rc,
     sync::Arc,
@@ -148,6 +148,16 @@ where
     }
 }
 
+impl<Db: ?Sized, T> DebugWithDb<Db> for [T]
+where
+    T: DebugWithDb<Db>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>, db: &Db, include_all_fields: bool) -> fmt::Result {
+        let elements = self.iter().map(|e| e.debug_with(db, include_all_fields));
+        f.debug_list().entries(elements).finish()
+    }
+}
+
 impl<Db: ?Sized, T> DebugWithDb<Db> for Option<T>
 where
     T: DebugWithDb<Db>,
@@ -212,6 +222,32 @@ where
     }
 }
 
+impl<Db: ?Sized, K, V> DebugWithDb<Db> for BTreeMap<K, V>
+where
+    K: DebugWithDb<Db>,
+    V: DebugWithDb<Db>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>, db: &Db, include_all_fields: bool) -> fmt::Result {
+        let elements = self.iter().map(|(k, v)| {
+            (
+                k.debug_with(db, include_all_fields),
+                v.debug_with(db, include_all_fields),
+            )
+        });
+        f.debug_map().entries(elements).finish()
+    }
+}
+
+impl<Db: ?Sized, V> DebugWithDb<Db> for BTreeSet<V>
+where
+    V: DebugWithDb<Db>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>, db: &Db, include_all_fields: bool) -> fmt::Result {
+        let elements = self.iter().map(|e| e.debug_with(db, include_all_fields));
+        f.debug_list().entries(elements).finish()
+    }
+}
+
 /// This is used by the macro generated code.
 /// If the field type implements `DebugWithDb`, uses that, otherwise, uses `Debug`.
 /// That's the "has impl" trick (https://github.com/nvzqz/impls#how-it-works)
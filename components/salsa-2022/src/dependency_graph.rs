@@ -0,0 +1,100 @@
+use std::fmt::Write;
+
+use crate::hash::FxIndexMap;
+use crate::key::DependencyIndex;
+use crate::DatabaseKeyIndex;
+
+/// A snapshot of the dependencies recorded for memoized queries,
+/// as returned by [`Storage::dependency_graph`](`crate::storage::Storage::dependency_graph`).
+///
+/// Each edge goes from a query to one of the inputs it read (another query,
+/// an input field, or an interned/tracked struct table) the last time it executed.
+/// The graph can be rendered with [`Self::to_dot`] or [`Self::to_json`].
+#[derive(Debug, Default)]
+pub struct DependencyGraph {
+    /// Maps each node to its human-readable label, in order of discovery.
+    nodes: FxIndexMap<DependencyIndex, String>,
+
+    /// Edges from a query to the inputs it read, in execution order.
+    edges: Vec<(DatabaseKeyIndex, DependencyIndex)>,
+}
+
+impl DependencyGraph {
+    pub(crate) fn add_node(&mut self, node: DependencyIndex, label: impl FnOnce() -> String) {
+        self.nodes.entry(node).or_insert_with(label);
+    }
+
+    pub(crate) fn add_edge(&mut self, from: DatabaseKeyIndex, to: DependencyIndex) {
+        self.edges.push((from, to));
+    }
+
+    /// Returns the nodes of the graph along with their labels.
+    pub fn nodes(&self) -> impl Iterator<Item = (DependencyIndex, &str)> + '_ {
+        self.nodes.iter().map(|(&node, label)| (node, &label[..]))
+    }
+
+    /// Returns the edges of the graph, from a query to one of its inputs.
+    pub fn edges(&self) -> &[(DatabaseKeyIndex, DependencyIndex)] {
+        &self.edges
+    }
+
+    /// Renders the graph in the graphviz DOT format.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph {\n");
+        for (label, index) in self.nodes.values().zip(0..) {
+            writeln!(out, "    n{} [label=\"{}\"];", index, escape(label)).unwrap();
+        }
+        for (from, to) in self.node_edges() {
+            writeln!(out, "    n{} -> n{};", from, to).unwrap();
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Renders the graph as JSON, in the form
+    /// `{"nodes": [{"id": 0, "label": ".."}], "edges": [{"from": 0, "to": 1}]}`.
+    pub fn to_json(&self) -> String {
+        let nodes: Vec<String> = self
+            .nodes
+            .values()
+            .zip(0..)
+            .map(|(label, index)| format!("{{\"id\":{},\"label\":\"{}\"}}", index, escape(label)))
+            .collect();
+        let edges: Vec<String> = self
+            .node_edges()
+            .map(|(from, to)| format!("{{\"from\":{},\"to\":{}}}", from, to))
+            .collect();
+        format!(
+            "{{\"nodes\":[{}],\"edges\":[{}]}}",
+            nodes.join(","),
+            edges.join(",")
+        )
+    }
+
+    /// Returns the edges as pairs of node positions.
+    fn node_edges(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.edges.iter().map(|&(from, to)| {
+            let from = self
+                .nodes
+                .get_index_of(&DependencyIndex::from(from))
+                .unwrap();
+            let to = self.nodes.get_index_of(&to).unwrap();
+            (from, to)
+        })
+    }
+}
+
+/// Escapes `label` so that it can be embedded in a DOT or JSON string literal.
+fn escape(label: &str) -> String {
+    let mut out = String::with_capacity(label.len());
+    for c in label.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,60 @@
+//! The contents of a jar, as returned by the `debug_all` function generated
+//! for jars (e.g. `Jar::debug_all(db)`), to compare two database states in tests.
+
+use std::fmt;
+
+use crate::Id;
+
+/// What the ingredients of a jar store, in the order in which they were created.
+/// Its `Debug` output lists, for each ingredient, its entries, or its values
+/// if it is a field of an input whose type implements `Debug`.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct JarDump {
+    /// The name of the jar, e.g. `my_crate::Jar`.
+    pub jar: &'static str,
+
+    pub ingredients: Vec<IngredientDump>,
+}
+
+/// What one ingredient stores, as returned by
+/// [`Storage::dump_jar`](`crate::storage::Storage::dump_jar`).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IngredientDump {
+    /// The name of the ingredient within its jar, e.g. `my_query` or `MyInput::field`.
+    pub name: String,
+
+    /// How many entries it stores: the instances of a salsa struct, the values
+    /// of a field of an input, the memos of a function, or the queries that
+    /// accumulated values.
+    pub entries: usize,
+
+    /// The values of a field of an input, sorted by id, if the type of the field
+    /// implements `Debug`.
+    pub values: Vec<(Id, String)>,
+}
+
+impl fmt::Debug for JarDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut jar = f.debug_struct(self.jar);
+        for ingredient in &self.ingredients {
+            if ingredient.values.is_empty() {
+                jar.field(&ingredient.name, &ingredient.entries);
+            } else {
+                jar.field(&ingredient.name, &Values(&ingredient.values));
+            }
+        }
+        jar.finish()
+    }
+}
+
+struct Values<'a>(&'a [(Id, String)]);
+
+impl fmt::Debug for Values<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut values = f.debug_map();
+        for (id, value) in self.0 {
+            values.entry(&u32::from(*id), &format_args!("{value}"));
+        }
+        values.finish()
+    }
+}
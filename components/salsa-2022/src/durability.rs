@@ -16,6 +16,8 @@
 /// configuration, the source from library crates, or other things
 /// that are unlikely to be edited.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "u8", into = "u8"))]
 pub struct Durability(u8);
 
 impl Durability {
@@ -47,3 +49,23 @@ impl Durability {
         self.0 as usize
     }
 }
+
+#[cfg(feature = "serde")]
+impl TryFrom<u8> for Durability {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        if (value as usize) < Self::LEN {
+            Ok(Durability(value))
+        } else {
+            Err(format!("invalid durability level `{}`", value))
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Durability> for u8 {
+    fn from(durability: Durability) -> u8 {
+        durability.0
+    }
+}
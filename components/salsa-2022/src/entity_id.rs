@@ -0,0 +1,106 @@
+//! A stable textual form for the ids of entities, e.g. `Function#12@rev4`, so that
+//! the entities named in the logs of a production server can be found again in a
+//! database reproducing the same inputs locally.
+//!
+//! Ids are handed out in order, so an entity has the same id in two databases that
+//! create the entities of its salsa struct in the same order, and the revision tells
+//! which writes the first database had seen.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{AsId, Database, Id, Revision};
+
+/// An entity named by its salsa struct, its id and the revision of the database it
+/// was read from. Displayed as `<struct>#<id>@rev<revision>`, e.g. `Function#12@rev4`,
+/// and parsed back with [`str::parse`].
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct EntityId {
+    /// The name of the salsa struct, without its module path, e.g. `Function`.
+    pub name: String,
+
+    /// The id of the entity.
+    pub id: Id,
+
+    /// The current revision of the database the entity was read from.
+    pub revision: Revision,
+}
+
+impl EntityId {
+    /// Names `entity`, an instance of the salsa struct `T`, in the current revision of `db`.
+    pub fn new<T: AsId>(db: &dyn Database, entity: T) -> Self {
+        EntityId {
+            name: struct_name::<T>().to_string(),
+            id: entity.as_id(),
+            revision: db.current_revision(),
+        }
+    }
+
+    /// Returns the entity, if it is an instance of the salsa struct `T`.
+    ///
+    /// The entity is only the one that was named if the database created the
+    /// entities of `T` in the same order: compare [`Self::revision`] with the
+    /// current revision of the database to tell whether it saw the same writes.
+    pub fn entity<T: AsId>(&self) -> Option<T> {
+        (self.name == struct_name::<T>()).then(|| T::from_id(self.id))
+    }
+}
+
+/// The last segment of the path of `T`, without generic arguments.
+fn struct_name<T>() -> &'static str {
+    let path = std::any::type_name::<T>();
+    let path = path.split('<').next().unwrap();
+    path.rsplit("::").next().unwrap()
+}
+
+impl fmt::Display for EntityId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}#{}@rev{}",
+            self.name,
+            self.id,
+            self.revision.as_usize()
+        )
+    }
+}
+
+impl FromStr for EntityId {
+    type Err = ParseEntityIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseEntityIdError {
+            text: s.to_string(),
+        };
+        let (name, rest) = s.split_once('#').ok_or_else(error)?;
+        let (id, revision) = rest.split_once("@rev").ok_or_else(error)?;
+        if name.is_empty() {
+            return Err(error());
+        }
+        let id = id.parse::<Id>().map_err(|_| error())?;
+        let revision = revision.parse::<usize>().map_err(|_| error())?;
+        Ok(EntityId {
+            name: name.to_string(),
+            id,
+            revision: Revision::from(revision),
+        })
+    }
+}
+
+/// The error returned when parsing a text that is not an [`EntityId`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParseEntityIdError {
+    text: String,
+}
+
+impl fmt::Display for ParseEntityIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` is not an entity id of the form `<struct>#<id>@rev<revision>`",
+            self.text
+        )
+    }
+}
+
+impl std::error::Error for ParseEntityIdError {}
@@ -6,6 +6,7 @@ use std::fmt;
 /// The `Event` struct identifies various notable things that can
 /// occur during salsa execution. Instances of this struct are given
 /// to `salsa_event`.
+#[derive(Clone)]
 pub struct Event {
     /// The id of the snapshot that triggered the event.  Usually
     /// 1-to-1 with a thread, as well.
@@ -15,6 +16,14 @@ pub struct Event {
     pub kind: EventKind,
 }
 
+/// Gives `event` to [`Database::salsa_event`], after recording it in the event
+/// log with the `event-log` feature.
+pub(crate) fn report<DB: ?Sized + Database>(db: &DB, event: Event) {
+    #[cfg(feature = "event-log")]
+    db.runtime().record_event(&event);
+    db.salsa_event(event)
+}
+
 impl fmt::Debug for Event {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt.debug_struct("Event")
@@ -42,6 +51,7 @@ where
 }
 
 /// An enum identifying the various kinds of events that can occur.
+#[derive(Clone)]
 pub enum EventKind {
     /// Occurs when we found that all inputs to a memoized value are
     /// up-to-date and hence the value can be re-used without
@@ -70,6 +80,31 @@ pub enum EventKind {
         database_key: DatabaseKeyIndex,
     },
 
+    /// Indicates that the other thread (with id `other_runtime_id`) is done with
+    /// the given query (`database_key`) that we were blocked on, following a
+    /// [`EventKind::WillBlockOn`] event.
+    ///
+    /// Together, these two events tell for how long a thread waited on another.
+    /// In the meantime, the waiting thread runs the chunks of the calls of
+    /// [`crate::par_map`] made by the other thread (or by the threads it is
+    /// blocked on), if any; it does not change the priority of the other thread.
+    /// An embedder can boost it, identifying it by the `runtime_id` of its own events.
+    DidUnblock {
+        /// The id of the runtime we were blocked on.
+        other_runtime_id: RuntimeId,
+
+        /// The database-key for the affected value. Implements `Debug`.
+        database_key: DatabaseKeyIndex,
+    },
+
+    /// Indicates that we will wait until another handle stops executing queries,
+    /// because as many handles as allowed by
+    /// [`Storage::set_max_concurrent_queries`](`crate::storage::Storage::set_max_concurrent_queries`)
+    /// are executing queries.
+    ///
+    /// Executes before waiting, while no handle can take or release a permit.
+    WillWaitForPermit,
+
     /// Indicates that the function for this query will be executed.
     /// This is either because it has never executed before or because
     /// its inputs may be out of date.
@@ -78,6 +113,13 @@ pub enum EventKind {
         database_key: DatabaseKeyIndex,
     },
 
+    /// Indicates that the value of an input field was set,
+    /// starting a new revision.
+    DidSetInput {
+        /// The database-key for the input field that was set.
+        database_key: DatabaseKeyIndex,
+    },
+
     /// Indicates that `unwind_if_cancelled` was called and salsa will check if
     /// the current revision has been cancelled.
     WillCheckCancellation,
@@ -122,10 +164,23 @@ impl fmt::Debug for EventKind {
                 .field("other_runtime_id", other_runtime_id)
                 .field("database_key", database_key)
                 .finish(),
+            EventKind::DidUnblock {
+                other_runtime_id,
+                database_key,
+            } => fmt
+                .debug_struct("DidUnblock")
+                .field("other_runtime_id", other_runtime_id)
+                .field("database_key", database_key)
+                .finish(),
+            EventKind::WillWaitForPermit => fmt.debug_struct("WillWaitForPermit").finish(),
             EventKind::WillExecute { database_key } => fmt
                 .debug_struct("WillExecute")
                 .field("database_key", database_key)
                 .finish(),
+            EventKind::DidSetInput { database_key } => fmt
+                .debug_struct("DidSetInput")
+                .field("database_key", database_key)
+                .finish(),
             EventKind::WillCheckCancellation => fmt.debug_struct("WillCheckCancellation").finish(),
             EventKind::WillDiscardStaleOutput {
                 execute_key,
@@ -179,6 +234,18 @@ where
                     &database_key.debug_with(db, include_all_fields),
                 )
                 .finish(),
+            EventKind::DidUnblock {
+                other_runtime_id,
+                database_key,
+            } => fmt
+                .debug_struct("DidUnblock")
+                .field("other_runtime_id", other_runtime_id)
+                .field(
+                    "database_key",
+                    &database_key.debug_with(db, include_all_fields),
+                )
+                .finish(),
+            EventKind::WillWaitForPermit => fmt.debug_struct("WillWaitForPermit").finish(),
             EventKind::WillExecute { database_key } => fmt
                 .debug_struct("WillExecute")
                 .field(
@@ -186,6 +253,13 @@ where
                     &database_key.debug_with(db, include_all_fields),
                 )
                 .finish(),
+            EventKind::DidSetInput { database_key } => fmt
+                .debug_struct("DidSetInput")
+                .field(
+                    "database_key",
+                    &database_key.debug_with(db, include_all_fields),
+                )
+                .finish(),
             EventKind::WillCheckCancellation => fmt.debug_struct("WillCheckCancellation").finish(),
             EventKind::WillDiscardStaleOutput {
                 execute_key,
@@ -0,0 +1,106 @@
+//! Inputs whose values live outside of the database (e.g. files or environment
+//! variables), which salsa polls for changes rather than being told about them.
+//!
+//! Add [`Jar`] to your database (e.g. `#[salsa::db(crate::Jar, salsa::external::Jar)]`),
+//! implement [`Db`] by returning an [`ExternalInputs`] stored in the database, create the
+//! inputs with [`ExternalInput::new`] and call [`Db::refresh_external_inputs`] to pick up
+//! the changes.
+
+use std::hash::Hash;
+use std::sync::Arc;
+
+use crate::sync::Mutex;
+
+#[salsa::jar(db = Db)]
+pub struct Jar(ExternalInput);
+
+/// The database of a program with external inputs.
+pub trait Db: salsa::DbWithJar<Jar> {
+    /// The external inputs created so far.
+    fn external_inputs(&self) -> &ExternalInputs;
+
+    /// Polls each external input and sets the stamps of those that changed, so that
+    /// only the queries reading them are executed again. No new revision is created
+    /// if none of them changed.
+    fn refresh_external_inputs(&mut self)
+    where
+        Self: Sized,
+    {
+        // The polls may create more inputs, so the lock is not held while they run.
+        let polls = self.external_inputs().polls.lock().clone();
+        let changes: Vec<_> = polls
+            .into_iter()
+            .filter_map(|(input, poll)| {
+                let stamp = poll(self);
+                (stamp != input.stamp(self)).then_some((input, stamp))
+            })
+            .collect();
+
+        let db: &mut dyn Db = self;
+        for (input, stamp) in changes {
+            input.set_stamp(db).to(stamp);
+        }
+    }
+}
+
+/// Identifies the state of an external input, such as the modification time of a
+/// file: the input is considered changed when its stamp changes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Stamp(u64);
+
+impl Stamp {
+    /// Returns a stamp made from the hash of `value`.
+    pub fn of(value: &impl Hash) -> Self {
+        Stamp(crate::hash::hash(value))
+    }
+}
+
+/// A stamp made from a version number, e.g. one that the external source
+/// increments on each change.
+impl From<u64> for Stamp {
+    fn from(version: u64) -> Self {
+        Stamp(version)
+    }
+}
+
+/// An input polled for changes by [`Db::refresh_external_inputs`]. Queries depend
+/// on it by calling [`ExternalInput::read`], and then read the actual value
+/// from outside of the database.
+#[salsa::input(jar = Jar, constructor = from_stamp)]
+pub struct ExternalInput {
+    stamp: Stamp,
+}
+
+impl ExternalInput {
+    /// Creates an input whose stamp is returned by `poll`, which is called
+    /// right away and then on each [`Db::refresh_external_inputs`].
+    pub fn new(db: &dyn Db, poll: impl Fn(&dyn Db) -> Stamp + Send + Sync + 'static) -> Self {
+        let input = ExternalInput::from_stamp(db, poll(db));
+        db.external_inputs()
+            .polls
+            .lock()
+            .push((input, Arc::new(poll)));
+        input
+    }
+
+    /// Returns the stamp of this input as of the last refresh, making the
+    /// calling query depend on it.
+    pub fn read(self, db: &dyn Db) -> Stamp {
+        self.stamp(db)
+    }
+}
+
+/// Polls the state of an external input.
+type Poll = Arc<dyn Fn(&dyn Db) -> Stamp + Send + Sync>;
+
+/// The [`ExternalInput`]s created so far, with the functions polling them.
+#[derive(Default)]
+pub struct ExternalInputs {
+    polls: Mutex<Vec<(ExternalInput, Poll)>>,
+}
+
+impl ExternalInputs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
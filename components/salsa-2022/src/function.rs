@@ -1,16 +1,25 @@
-use std::{fmt, sync::Arc};
+use std::{
+    any::{Any, TypeId},
+    fmt,
+    ops::RangeBounds,
+    sync::Arc,
+};
 
 use arc_swap::ArcSwap;
 use crossbeam::{atomic::AtomicCell, queue::SegQueue};
 
 use crate::{
     cycle::CycleRecoveryStrategy,
+    dump::IngredientDump,
+    hash::{FxDashMap, FxHashSet},
     ingredient::{fmt_index, IngredientRequiresReset},
-    jar::Jar,
+    jar::{Jar, JarInDb},
     key::{DatabaseKeyIndex, DependencyIndex},
+    memory::IngredientMemory,
+    metrics::{MetricsCounters, QueryMetrics},
     runtime::local_state::QueryOrigin,
     salsa_struct::SalsaStructInDb,
-    Cycle, DbWithJar, Event, EventKind, Id, Revision,
+    Cycle, DbWithJar, Event, EventKind, Id, Revision, Runtime,
 };
 
 use super::{ingredient::Ingredient, routes::IngredientIndex, AsId};
@@ -21,13 +30,21 @@ mod delete;
 mod diff_outputs;
 mod execute;
 mod fetch;
+mod fixpoint;
 mod inputs;
+mod invalidate;
 mod lru;
 mod maybe_changed_after;
 mod memo;
+mod memo_storage;
+#[cfg(feature = "self-check")]
+mod self_check;
 mod specify;
 mod store;
 mod sync;
+mod update;
+
+pub use memo_storage::{DenseMemoStorage, HashMemoStorage, MemoStorage};
 
 /// Function ingredients are the "workhorse" of salsa.
 /// They are used for tracked functions, for the "value" fields of tracked structs, and for the fields of input structs.
@@ -45,7 +62,7 @@ pub struct FunctionIngredient<C: Configuration> {
     index: IngredientIndex,
 
     /// Tracks the keys for which we have memoized values.
-    memo_map: memo::MemoMap<C::Key, C::Value>,
+    memo_map: memo::MemoMap<C>,
 
     /// Tracks the keys that are currently being processed; used to coordinate between
     /// worker threads.
@@ -68,10 +85,21 @@ pub struct FunctionIngredient<C: Configuration> {
     /// everytime and so forth.
     deleted_entries: SegQueue<ArcSwap<memo::Memo<C::Value>>>,
 
+    /// The provisional values of the keys executing as the heads of cycles
+    /// iterated to a fixed point; see [`CycleRecoveryStrategy::FixedPoint`].
+    provisional_values: FxDashMap<Id, Arc<memo::Memo<C::Value>>>,
+
     /// Set to true once we invoke `register_dependent_fn` for `C::SalsaStruct`.
     /// Prevents us from registering more than once.
     registered: AtomicCell<bool>,
 
+    /// Set when registering, if the instances of `C::SalsaStruct` can be deleted:
+    /// the ingredient storing them (see [`SalsaStructInDb::instances_ingredient`]).
+    salsa_struct_index: AtomicCell<Option<IngredientIndex>>,
+
+    /// Counts executions, validations and backdates, if the `metrics` feature is enabled.
+    metrics: MetricsCounters,
+
     debug_name: &'static str,
 }
 
@@ -86,11 +114,14 @@ pub trait Configuration {
     /// What key is used to index the memo. Typically a salsa struct id,
     /// but if this memoized function has multiple arguments it will be a `salsa::Id`
     /// that results from interning those arguments.
-    type Key: AsId;
+    type Key: AsId + 'static;
 
     /// The value computed by the function.
     type Value: fmt::Debug;
 
+    /// Where the memos are stored; set by the `storage` option.
+    type Storage: MemoStorage<Self::Key, Self::Value>;
+
     /// Determines whether this function can recover from being a participant in a cycle
     /// (and, if so, how).
     const CYCLE_STRATEGY: CycleRecoveryStrategy;
@@ -103,6 +134,61 @@ pub trait Configuration {
     /// This invokes user's code in form of the `Eq` impl.
     fn should_backdate_value(old_value: &Self::Value, new_value: &Self::Value) -> bool;
 
+    /// True if new values should be merged into the old ones with [`Self::update_value`]
+    /// rather than replacing them. Set by the `update` option.
+    const UPDATE: bool = false;
+
+    /// If `UPDATE` is true, invoked instead of [`Self::should_backdate_value`] to merge
+    /// the new value into the old one in place. Returns true if the old value changed.
+    ///
+    /// This invokes the user's [`Update`](`crate::update::Update`) impl.
+    fn update_value(_old_value: &mut Self::Value, _new_value: Self::Value) -> bool {
+        panic!("`update_value` invoked on a function without the `update` option")
+    }
+
+    /// True if the heap memory of values is reported with [`Self::heap_size`].
+    /// Set by the `heap_size` option.
+    const HEAP_SIZE: bool = false;
+
+    /// If `HEAP_SIZE` is true, returns the bytes allocated on the heap by `value`,
+    /// for [`Storage::memory_report`](`crate::storage::Storage::memory_report`).
+    ///
+    /// This invokes the user's [`HeapSize`](`crate::memory::HeapSize`) impl.
+    fn heap_size(_value: &Self::Value) -> usize {
+        panic!("`heap_size` invoked on a function without the `heap_size` option")
+    }
+
+    /// True if values can be cloned with [`Self::value_to_any`], so that the function
+    /// can be called by name. This is the case of tracked functions whose values are
+    /// returned by cloning them (i.e., without the `return_ref` or `return_deref` option).
+    const FETCH_DYN: bool = false;
+
+    /// If `FETCH_DYN` is true, returns a clone of `value`,
+    /// for [`Database::fetch_dyn`](`crate::Database::fetch_dyn`).
+    fn value_to_any(_value: &Self::Value) -> Box<dyn Any> {
+        panic!("`value_to_any` invoked on a function whose values are returned by reference")
+    }
+
+    /// Returns a clone of `value`, if the type of the values implements `Clone`, so that
+    /// the memos can be copied by [`Storage::fork`](`crate::storage::Storage::fork`).
+    fn clone_value(_value: &Self::Value) -> Option<Self::Value> {
+        None
+    }
+
+    /// True if the values are dropped by
+    /// [`Storage::release_weak_memos`](`crate::storage::Storage::release_weak_memos`).
+    /// Set by the `weak` option.
+    const WEAK: bool = false;
+
+    /// True if the function must not read untracked state, and its values are
+    /// kept by [`Storage::sweep`](`crate::storage::Storage::sweep`) so that new
+    /// values can always be compared with them. Set by the `firewall` option.
+    const FIREWALL: bool = false;
+
+    /// True if the values cannot be compared, so that they are never backdated.
+    /// Set by the `no_eq` option.
+    const NO_EQ: bool = false;
+
     /// Invoked when we need to compute the value for the given key, either because we've never
     /// computed it before or because the old one relied on inputs that have changed.
     ///
@@ -115,6 +201,14 @@ pub trait Configuration {
     /// This invokes the recovery function given by the user.
     fn recover_from_cycle(db: &DynDb<Self>, cycle: &Cycle, key: Self::Key) -> Self::Value;
 
+    /// If the cycle strategy is `FixedPoint`, invoked when `key` is called by a query it
+    /// called, to find out the provisional value returned before the first iteration.
+    ///
+    /// This invokes the function given by the user with the `cycle_initial` option.
+    fn cycle_initial(_db: &DynDb<Self>, _key: Self::Key) -> Self::Value {
+        panic!("`cycle_initial` invoked on a function without the `cycle_initial` option")
+    }
+
     /// Given a salsa Id, returns the key. Convenience function to avoid
     /// having to type `<C::Key as AsId>::from_id`.
     fn key_from_id(id: Id) -> Self::Key {
@@ -129,6 +223,21 @@ pub fn should_backdate_value<V: Eq>(old_value: &V, new_value: &V) -> bool {
     old_value == new_value
 }
 
+/// The ranges given to the `iter_range` function generated for tracked functions:
+/// any range over keys that have a natural ordering.
+pub trait KeyRange<K> {
+    /// Keeps the entries whose key is in the range, sorted by key.
+    fn select<V>(&self, entries: Vec<(K, V)>) -> Vec<(K, V)>;
+}
+
+impl<K: Ord, R: RangeBounds<K>> KeyRange<K> for R {
+    fn select<V>(&self, mut entries: Vec<(K, V)>) -> Vec<(K, V)> {
+        entries.retain(|(key, _)| self.contains(key));
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        entries
+    }
+}
+
 pub type DynDb<'bound, C> = <<C as Configuration>::Jar as Jar<'bound>>::DynDb;
 
 /// This type is used to make configuration types for the functions in entities;
@@ -146,12 +255,35 @@ where
             lru: Default::default(),
             sync_map: Default::default(),
             deleted_entries: Default::default(),
+            provisional_values: Default::default(),
             registered: Default::default(),
+            salsa_struct_index: Default::default(),
+            metrics: Default::default(),
             debug_name,
         }
     }
 
-    fn database_key_index(&self, k: C::Key) -> DatabaseKeyIndex {
+    /// Returns an ingredient sharing the memos of this one, for the jars of a pipelined
+    /// write (see [`Storage::set_pipelined_writes`](`crate::storage::Storage::set_pipelined_writes`)).
+    /// The queries running in the snapshots keep using `self`.
+    pub fn fork(&self) -> Option<Self> {
+        Some(Self {
+            index: self.index,
+            memo_map: self.memo_map.fork(),
+            sync_map: Default::default(),
+            lru: self.lru.fork(),
+            deleted_entries: Default::default(),
+            provisional_values: Default::default(),
+            registered: AtomicCell::new(self.registered.load()),
+            salsa_struct_index: AtomicCell::new(self.salsa_struct_index.load()),
+            metrics: self.metrics.fork(),
+            debug_name: self.debug_name,
+        })
+    }
+
+    /// Returns the index identifying the call of the function for `k`, as in
+    /// the events of the runtime (e.g., [`EventKind::WillExecute`](`crate::EventKind::WillExecute`)).
+    pub fn database_key_index(&self, k: C::Key) -> DatabaseKeyIndex {
         DatabaseKeyIndex {
             ingredient_index: self.index,
             key_index: k.as_id(),
@@ -159,7 +291,35 @@ where
     }
 
     pub fn set_capacity(&self, capacity: usize) {
-        self.lru.set_capacity(capacity);
+        for evicted in self.lru.set_capacity(capacity) {
+            self.evict(AsId::from_id(evicted));
+        }
+    }
+
+    /// Pins the memo for `key`, so that the LRU never evicts it until it is unpinned.
+    /// Pinned memos do not count towards the LRU capacity.
+    pub fn pin(&self, key: C::Key) {
+        self.lru.pin(key.as_id());
+    }
+
+    /// Unpins the memo for `key`, which becomes the most recently used one.
+    pub fn unpin(&self, key: C::Key) {
+        if let Some(evicted) = self.lru.unpin(key.as_id()) {
+            self.evict(AsId::from_id(evicted));
+        }
+    }
+
+    /// Makes room for the memos of at least `additional` more keys, e.g. before
+    /// calling the function on many keys for the first time. This is what the
+    /// `capacity = N` option of tracked functions does when the ingredient is created.
+    pub fn reserve(&self, additional: usize) {
+        self.memo_map.reserve(additional);
+    }
+
+    /// Returns the revision in which the value for `key` last changed, as of the last
+    /// time it was computed or validated, or `None` if there is no memo for `key`.
+    pub fn last_changed(&self, key: C::Key) -> Option<Revision> {
+        self.memo_map.get(key).map(|m| m.revisions.changed_at)
     }
 
     /// Returns a reference to the memo value that lives as long as self.
@@ -203,7 +363,9 @@ where
     /// so we can remove any data keyed by them.
     fn register(&self, db: &DynDb<'_, C>) {
         if !self.registered.fetch_or(true) {
-            <C::SalsaStruct as SalsaStructInDb<_>>::register_dependent_fn(db, self.index)
+            <C::SalsaStruct as SalsaStructInDb<_>>::register_dependent_fn(db, self.index);
+            self.salsa_struct_index
+                .store(<C::SalsaStruct as SalsaStructInDb<_>>::instances_ingredient(db));
         }
     }
 }
@@ -212,6 +374,7 @@ impl<DB, C> Ingredient<DB> for FunctionIngredient<C>
 where
     DB: ?Sized + DbWithJar<C::Jar>,
     C: Configuration,
+    C::Jar: JarInDb<DB>,
 {
     fn ingredient_index(&self) -> IngredientIndex {
         self.index
@@ -219,7 +382,7 @@ where
 
     fn maybe_changed_after(&self, db: &DB, input: DependencyIndex, revision: Revision) -> bool {
         let key = C::key_from_id(input.key_index.unwrap());
-        let db = db.as_jar_db();
+        let db = <C::Jar as JarInDb<DB>>::as_jar_db(db);
         self.maybe_changed_after(db, key, revision)
     }
 
@@ -239,7 +402,7 @@ where
         output_key: Option<crate::Id>,
     ) {
         let output_key = C::key_from_id(output_key.unwrap());
-        self.validate_specified_value(db.as_jar_db(), executor, output_key);
+        self.validate_specified_value(<C::Jar as JarInDb<DB>>::as_jar_db(db), executor, output_key);
     }
 
     fn remove_stale_output(
@@ -255,6 +418,102 @@ where
 
     fn reset_for_new_revision(&mut self) {
         std::mem::take(&mut self.deleted_entries);
+        self.provisional_values.clear();
+    }
+
+    fn sweep_edges(
+        &self,
+        revision: Revision,
+        op: &mut dyn FnMut(crate::Id, bool, &mut dyn Iterator<Item = DependencyIndex>),
+    ) {
+        let salsa_struct_index = self.salsa_struct_index.load();
+        self.memo_map
+            .sweep_edges(revision, C::FIREWALL, salsa_struct_index, op);
+    }
+
+    fn sweep(&mut self, revision: Revision, reachable: &FxHashSet<DatabaseKeyIndex>) {
+        let index = self.index;
+        self.memo_map.remove_unreachable(|key| {
+            reachable.contains(&DatabaseKeyIndex {
+                ingredient_index: index,
+                key_index: key.as_id(),
+            })
+        });
+        if !C::FIREWALL {
+            self.memo_map.evict_unverified(revision);
+        }
+    }
+
+    fn release_weak_memos(&mut self) {
+        if C::WEAK {
+            self.memo_map.evict_all();
+        }
+    }
+
+    fn unshare(&mut self) -> bool {
+        self.memo_map.unshare()
+    }
+
+    fn memoized_keys(&self) -> Vec<crate::Id> {
+        self.memo_map
+            .keys()
+            .into_iter()
+            .map(|key| key.as_id())
+            .collect()
+    }
+
+    fn query_metrics(&self) -> Option<QueryMetrics> {
+        self.metrics.query_metrics(self.debug_name)
+    }
+
+    fn memory_usage(&self) -> Option<IngredientMemory> {
+        Some(self.memo_map.memory_usage(self.debug_name))
+    }
+
+    fn memo_count(&self) -> Option<usize> {
+        Some(self.memo_map.len())
+    }
+
+    fn dump(&self) -> IngredientDump {
+        IngredientDump {
+            entries: self.memo_map.keys().len(),
+            ..Default::default()
+        }
+    }
+
+    fn can_fetch_dyn(&self) -> bool {
+        C::FETCH_DYN
+    }
+
+    fn history_start(&self) -> Option<Revision> {
+        None
+    }
+
+    fn revert_to(&mut self, _runtime: &mut Runtime, _revision: Revision) {}
+
+    fn fetch_dyn(&self, db: &DB, key: crate::Id) -> Option<Box<dyn Any>> {
+        if !C::FETCH_DYN {
+            return None;
+        }
+        let key = C::key_from_id(key);
+        let db = <C::Jar as JarInDb<DB>>::as_jar_db(db);
+        Some(C::value_to_any(self.fetch(db, key)))
+    }
+
+    fn changed_since(&self, revision: Revision) -> Vec<crate::Id> {
+        self.memo_map
+            .changed_since(revision)
+            .into_iter()
+            .map(|key| key.as_id())
+            .collect()
+    }
+
+    fn key_type(&self) -> Option<TypeId> {
+        Some(TypeId::of::<C::Key>())
+    }
+
+    fn creator(&self, _key_index: crate::Id) -> Option<DatabaseKeyIndex> {
+        None
     }
 
     fn salsa_struct_deleted(&self, db: &DB, id: crate::Id) {
@@ -264,10 +523,13 @@ where
         let id: C::Key = C::key_from_id(id);
         if let Some(origin) = self.delete_memo(id) {
             let key = self.database_key_index(id);
-            db.salsa_event(Event {
-                runtime_id: db.runtime().id(),
-                kind: EventKind::DidDiscard { key },
-            });
+            crate::event::report(
+                db,
+                Event {
+                    runtime_id: db.runtime().id(),
+                    kind: EventKind::DidDiscard { key },
+                },
+            );
 
             // Anything that was output by this memoized execution
             // is now itself stale.
@@ -277,6 +539,10 @@ where
         }
     }
 
+    fn debug_name(&self) -> &'static str {
+        self.debug_name
+    }
+
     fn fmt_index(&self, index: Option<crate::Id>, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt_index(self.debug_name, index, fmt)
     }
@@ -1,4 +1,7 @@
-use crate::runtime::local_state::QueryRevisions;
+use crate::{
+    runtime::local_state::{QueryOrigin, QueryRevisions},
+    Revision,
+};
 
 use super::{memo::Memo, Configuration, FunctionIngredient};
 
@@ -9,28 +12,46 @@ where
     /// If the value/durability of this memo is equal to what is found in `revisions`/`value`,
     /// then updates `revisions.changed_at` to match `self.revisions.changed_at`. This is invoked
     /// on an old memo when a new memo has been produced to check whether there have been changed.
+    /// `is_equal` is given the old value and tells whether the new value is equal to it.
+    ///
+    /// If the value is not backdated and the old value read some untracked state, the inputs
+    /// of the new value cannot tell when it last changed, so `revisions.changed_at` is set to
+    /// `revision_now`.
+    ///
+    /// Returns whether the value was backdated.
     pub(super) fn backdate_if_appropriate(
         &self,
         old_memo: &Memo<C::Value>,
         revisions: &mut QueryRevisions,
-        value: &C::Value,
-    ) {
+        is_equal: impl FnOnce(&C::Value) -> bool,
+        revision_now: Revision,
+    ) -> bool {
         if let Some(old_value) = &old_memo.value {
             // Careful: if the value became less durable than it
             // used to be, that is a "breaking change" that our
             // consumers must be aware of. Becoming *more* durable
             // is not. See the test `constant_to_non_constant`.
-            if revisions.durability >= old_memo.revisions.durability
-                && C::should_backdate_value(old_value, value)
-            {
+            if revisions.durability >= old_memo.revisions.durability && is_equal(old_value) {
                 log::debug!(
                     "value is equal, back-dating to {:?}",
                     old_memo.revisions.changed_at,
                 );
 
-                assert!(old_memo.revisions.changed_at <= revisions.changed_at);
+                // If the old value read untracked state, it changed when it was executed,
+                // which may be after the tracked inputs of the new value changed.
+                assert!(
+                    old_memo.revisions.changed_at <= revisions.changed_at
+                        || matches!(old_memo.revisions.origin, QueryOrigin::DerivedUntracked(_))
+                );
                 revisions.changed_at = old_memo.revisions.changed_at;
+                self.metrics.record_backdate();
+                return true;
             }
         }
+
+        if let QueryOrigin::DerivedUntracked(_) = old_memo.revisions.origin {
+            revisions.changed_at = revision_now;
+        }
+        false
     }
 }
@@ -1,6 +1,6 @@
 use crate::{
     hash::FxHashSet, key::DependencyIndex, runtime::local_state::QueryRevisions,
-    storage::HasJarsDyn, Database, DatabaseKeyIndex, Event, EventKind,
+    storage::HasJarsDyn, DatabaseKeyIndex, Event, EventKind,
 };
 
 use super::{memo::Memo, Configuration, DynDb, FunctionIngredient};
@@ -39,13 +39,16 @@ where
 
     fn report_stale_output(db: &DynDb<'_, C>, key: DatabaseKeyIndex, output: DependencyIndex) {
         let runtime_id = db.runtime().id();
-        db.salsa_event(Event {
-            runtime_id,
-            kind: EventKind::WillDiscardStaleOutput {
-                execute_key: key,
-                output_key: output,
+        crate::event::report(
+            db,
+            Event {
+                runtime_id,
+                kind: EventKind::WillDiscardStaleOutput {
+                    execute_key: key,
+                    output_key: output,
+                },
             },
-        });
+        );
 
         db.remove_stale_output(key, output);
     }
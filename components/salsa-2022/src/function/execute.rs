@@ -1,10 +1,17 @@
 use std::sync::Arc;
 
+use arc_swap::ArcSwap;
+
 use crate::{
+    cycle::CycleRecoveryStrategy,
     debug::DebugWithDb,
-    runtime::{local_state::ActiveQueryGuard, StampedValue},
+    runtime::{
+        local_state::{ActiveQueryGuard, QueryOrigin},
+        StampedValue,
+    },
     storage::HasJarsDyn,
-    Cycle, Database, Event, EventKind,
+    trace::ExecuteSpan,
+    Cycle, Event, EventKind,
 };
 
 use super::{memo::Memo, Configuration, DynDb, FunctionIngredient};
@@ -34,18 +41,48 @@ where
 
         log::info!("{:?}: executing query", database_key_index);
 
-        db.salsa_event(Event {
-            runtime_id: runtime.id(),
-            kind: EventKind::WillExecute {
-                database_key: database_key_index,
+        crate::event::report(
+            db,
+            Event {
+                runtime_id: runtime.id(),
+                kind: EventKind::WillExecute {
+                    database_key: database_key_index,
+                },
             },
-        });
+        );
 
         // Query was not previously executed, or value is potentially
         // stale, or value is absent. Let's execute!
         let database_key_index = active_query.database_key_index;
         let key = C::key_from_id(database_key_index.key_index);
-        let value = match Cycle::catch(|| C::execute(db, key)) {
+        let span = ExecuteSpan::new(db, self.debug_name, database_key_index);
+        let timer = self.metrics.start_execution();
+        let mut iteration = 0;
+        let result = loop {
+            iteration += 1;
+            let result = Cycle::catch(|| {
+                let _entered = span.enter();
+                runtime.execute_query(db, || C::execute(db, key))
+            });
+            match result {
+                // The value was computed from the provisional value of this query:
+                // iterate until it no longer changes.
+                Ok(value)
+                    if C::CYCLE_STRATEGY == CycleRecoveryStrategy::FixedPoint
+                        && active_query.is_cycle_head() =>
+                {
+                    if let Some(value) =
+                        self.reached_fixed_point(db, &active_query, key, value, iteration)
+                    {
+                        break Ok(value);
+                    }
+                }
+                result => break result,
+            }
+        };
+        #[cfg(feature = "self-check")]
+        let recovered_from_cycle = result.is_err();
+        let value = match result {
             Ok(v) => v,
             Err(cycle) => {
                 log::debug!(
@@ -55,10 +92,13 @@ where
                     C::CYCLE_STRATEGY
                 );
                 match C::CYCLE_STRATEGY {
-                    crate::cycle::CycleRecoveryStrategy::Panic => cycle.throw(),
-                    crate::cycle::CycleRecoveryStrategy::Fallback => {
+                    CycleRecoveryStrategy::Panic | CycleRecoveryStrategy::FixedPoint => {
+                        cycle.throw()
+                    }
+                    CycleRecoveryStrategy::Fallback => {
                         if let Some(c) = active_query.take_cycle() {
                             assert!(c.is(&cycle));
+                            crate::trace::cycle_recovered(db, database_key_index);
                             C::recover_from_cycle(db, &cycle, key)
                         } else {
                             // we are not a participant in this cycle
@@ -71,8 +111,39 @@ where
                 }
             }
         };
+        self.metrics.record_execution(timer);
+        let provisional = active_query.is_provisional();
         let mut revisions = active_query.pop(runtime);
 
+        if provisional {
+            // The value depends on the provisional value of a cycle head that is still
+            // iterating: it is not memoized, so that it is computed again by the next
+            // iteration, and the caller depends on its inputs instead.
+            let memo = Arc::new(Memo::new(Some(value), revision_now, revisions.clone()));
+            let value = unsafe {
+                // Unsafety invariant: memo is pushed onto `deleted_entries`.
+                self.extend_memo_lifetime(&memo).unwrap()
+            };
+            self.deleted_entries.push(ArcSwap::new(memo));
+            return revisions.stamped_value(value);
+        }
+
+        // Firewall queries are there to stop changes from propagating, which only
+        // works if they can be verified from their inputs.
+        if C::FIREWALL {
+            if let QueryOrigin::DerivedUntracked(_) = revisions.origin {
+                panic!(
+                    "`{:?}` read untracked state, which firewall queries must not do",
+                    database_key_index.debug(db),
+                );
+            }
+        }
+
+        #[cfg(feature = "self-check")]
+        if !recovered_from_cycle && runtime.self_check_enabled() {
+            self.self_check(db, database_key_index, &value, &revisions);
+        }
+
         // We assume that query is side-effect free -- that is, does
         // not mutate the "inputs" to the query system. Sanity check
         // that assumption here, at least to the best of our ability.
@@ -86,9 +157,22 @@ where
         // really change, even if some of its inputs have. So we can
         // "backdate" its `changed_at` revision to be the same as the
         // old value.
-        if let Some(old_memo) = &opt_old_memo {
-            self.backdate_if_appropriate(old_memo, &mut revisions, &value);
-            self.diff_outputs(db, database_key_index, old_memo, &revisions);
+        let mut value = value;
+        if let Some(old_memo) = opt_old_memo {
+            self.diff_outputs(db, database_key_index, &old_memo, &revisions);
+            let backdated;
+            if C::UPDATE {
+                (value, backdated) =
+                    self.update_in_place(key, old_memo, value, &mut revisions, revision_now);
+            } else {
+                backdated = self.backdate_if_appropriate(
+                    &old_memo,
+                    &mut revisions,
+                    |old_value| C::should_backdate_value(old_value, &value),
+                    revision_now,
+                );
+            }
+            span.record_backdated(backdated);
         }
 
         let value = self
@@ -1,8 +1,11 @@
 use arc_swap::Guard;
 
-use crate::{database::AsSalsaDatabase, runtime::StampedValue, storage::HasJarsDyn, AsId};
+use crate::{
+    cycle::CycleRecoveryStrategy, database::AsSalsaDatabase, runtime::StampedValue,
+    storage::HasJarsDyn, AsId,
+};
 
-use super::{Configuration, DynDb, FunctionIngredient};
+use super::{Configuration, DynDb, FunctionIngredient, KeyRange};
 
 impl<C> FunctionIngredient<C>
 where
@@ -29,9 +32,65 @@ where
             changed_at,
         );
 
+        // The first query called from outside of any query in a new revision
+        // refreshes the subscriptions.
+        if !db.runtime().query_in_progress() {
+            db.run_subscriptions(true);
+        }
+
         value
     }
 
+    /// Returns the value for `key` if it is memoized and verified in the current revision,
+    /// without executing the function nor verifying the memo's dependencies.
+    ///
+    /// The calling query depends on the value if it is returned. Otherwise, the calling
+    /// query is executed again in each new revision, until the value is computed.
+    pub fn peek(&self, db: &DynDb<C>, key: C::Key) -> Option<&C::Value> {
+        let runtime = db.runtime();
+        runtime.unwind_if_revision_cancelled(db);
+
+        let Some(StampedValue {
+            value,
+            durability,
+            changed_at,
+        }) = self.fetch_hot(db, key)
+        else {
+            runtime.report_untracked_read();
+            return None;
+        };
+        runtime.report_tracked_read(self.database_key_index(key).into(), durability, changed_at);
+        Some(value)
+    }
+
+    /// Fetches the value for every key that has a memo and whose `key_of` is in `range`,
+    /// sorted by `key_of`. Keys for which the function was never executed are skipped.
+    ///
+    /// The calling query depends on the values returned, but which keys have a memo is not
+    /// tracked, so the calling query is executed again in each new revision.
+    pub fn fetch_range<K>(
+        &self,
+        db: &DynDb<C>,
+        range: &impl KeyRange<K>,
+        key_of: impl Fn(C::Key) -> K,
+    ) -> Vec<(K, &C::Value)> {
+        let runtime = db.runtime();
+        runtime.unwind_if_revision_cancelled(db);
+        runtime.report_untracked_read();
+
+        let entries = self
+            .memo_map
+            .keys()
+            .into_iter()
+            .map(|key| (key_of(key), key))
+            .collect();
+        range
+            .select(entries)
+            .into_iter()
+            .map(|(k, key)| (k, self.fetch(db, key)))
+            .collect()
+    }
+
     #[inline]
     fn compute_value(&self, db: &DynDb<C>, key: C::Key) -> StampedValue<&C::Value> {
         loop {
@@ -63,6 +122,18 @@ where
         let runtime = db.runtime();
         let database_key_index = self.database_key_index(key);
 
+        // A query iterated to a fixed point that calls itself gets its provisional value.
+        if C::CYCLE_STRATEGY == CycleRecoveryStrategy::FixedPoint {
+            if let Some(value) = self.fetch_provisional(db, key) {
+                return Some(value);
+            }
+        }
+
+        // Outside of any query, the number of handles executing queries may be limited.
+        // The permit is taken before claiming the query, so that the handles blocking on it
+        // do not wait for a handle that is itself waiting for a permit.
+        let _permit = runtime.acquire_execution_permit(db);
+
         // Try to claim this query: if someone else has claimed it already, go back and start again.
         let _claim_guard = self
             .sync_map
@@ -87,7 +158,7 @@ where
         Some(self.execute(db, active_query, opt_old_memo))
     }
 
-    fn evict(&self, key: C::Key) {
+    pub(super) fn evict(&self, key: C::Key) {
         self.memo_map.evict(key);
     }
 }
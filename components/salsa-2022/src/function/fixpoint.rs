@@ -0,0 +1,117 @@
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+
+use crate::{
+    cycle::MAX_ITERATIONS,
+    debug::DebugWithDb,
+    durability::Durability,
+    runtime::{
+        local_state::{ActiveQueryGuard, QueryEdges, QueryOrigin, QueryRevisions},
+        StampedValue,
+    },
+    storage::HasJarsDyn,
+    AsId,
+};
+
+use super::{memo::Memo, Configuration, DynDb, FunctionIngredient};
+
+impl<C> FunctionIngredient<C>
+where
+    C: Configuration,
+{
+    /// If `key` is executing on this thread, i.e. it is called by a query it called,
+    /// returns its provisional value: the value returned by its previous iteration,
+    /// or the value given by `cycle_initial` before the first one.
+    pub(super) fn fetch_provisional(
+        &self,
+        db: &DynDb<C>,
+        key: C::Key,
+    ) -> Option<StampedValue<&C::Value>> {
+        let runtime = db.runtime();
+        if !runtime.report_provisional_read(self.database_key_index(key)) {
+            return None;
+        }
+
+        let memo = match self.provisional_values.get(&key.as_id()) {
+            Some(memo) => memo.clone(),
+            None => {
+                let value = C::cycle_initial(db, key);
+                let memo = Arc::new(self.provisional_memo(db, value));
+                self.provisional_values.insert(key.as_id(), memo.clone());
+                memo
+            }
+        };
+        let value = unsafe {
+            // Unsafety invariant: provisional values are only dropped with `&mut self`,
+            // or once pushed onto `deleted_entries`.
+            self.extend_memo_lifetime(&memo).unwrap()
+        };
+        Some(memo.revisions.stamped_value(value))
+    }
+
+    /// Invoked when `key`, the head of a cycle, returned `value` having read its own
+    /// provisional value. Returns `value` if it is equal to the provisional value, i.e.,
+    /// the iterations reached a fixed point. Otherwise, makes it the provisional value
+    /// for `iteration`, the next iteration, and returns `None`.
+    ///
+    /// # Panics
+    ///
+    /// If the value did not converge after [`MAX_ITERATIONS`] iterations.
+    pub(super) fn reached_fixed_point(
+        &self,
+        db: &DynDb<C>,
+        active_query: &ActiveQueryGuard<'_>,
+        key: C::Key,
+        value: C::Value,
+        iteration: u32,
+    ) -> Option<C::Value> {
+        let database_key_index = active_query.database_key_index;
+        let provisional = self
+            .provisional_values
+            .remove(&key.as_id())
+            .map(|(_, memo)| {
+                let converged = C::should_backdate_value(memo.value.as_ref().unwrap(), &value);
+                self.deleted_entries.push(ArcSwap::new(memo));
+                converged
+            });
+        if provisional == Some(true) {
+            log::debug!(
+                "{:?}: reached a fixed point after {} iterations",
+                database_key_index.debug(db),
+                iteration,
+            );
+            return Some(value);
+        }
+
+        if iteration >= MAX_ITERATIONS {
+            panic!(
+                "`{:?}` did not reach a fixed point after {} iterations of its cycle",
+                database_key_index.debug(db),
+                iteration,
+            );
+        }
+        log::debug!(
+            "{:?}: iterating with provisional value {:?}",
+            database_key_index.debug(db),
+            value,
+        );
+        let memo = Arc::new(self.provisional_memo(db, value));
+        self.provisional_values.insert(key.as_id(), memo);
+        active_query.start_iteration();
+        None
+    }
+
+    /// Returns a memo holding a provisional value, which is not memoized: it only
+    /// keeps the value alive while references to it may be in use.
+    fn provisional_memo(&self, db: &DynDb<C>, value: C::Value) -> Memo<C::Value> {
+        let runtime = db.runtime();
+        let revision_now = runtime.current_revision();
+        let revisions = QueryRevisions {
+            changed_at: revision_now,
+            durability: Durability::MAX,
+            origin: QueryOrigin::Derived(QueryEdges::new(runtime.empty_dependencies())),
+        };
+        Memo::new(Some(value), revision_now, revisions)
+    }
+}
@@ -0,0 +1,41 @@
+use std::sync::Arc;
+
+use crate::{runtime::local_state::QueryOrigin, Runtime};
+
+use super::{memo::Memo, Configuration, FunctionIngredient};
+
+impl<C> FunctionIngredient<C>
+where
+    C: Configuration,
+{
+    /// Forces the function to be executed again for `key` the next time its value is
+    /// needed, as if it had read an untracked input that changed in the current revision,
+    /// so that the queries depending on it are validated again. The old value is kept,
+    /// so that the new one is backdated if it is equal.
+    ///
+    /// Memos whose values were set, or assigned by another query, are left as they are,
+    /// since they cannot be recomputed.
+    pub fn invalidate(&mut self, runtime: &mut Runtime, key: C::Key) {
+        let Some(memo) = self.memo_map.get(key) else {
+            return;
+        };
+        let mut revisions = memo.revisions.clone();
+        let edges = match &revisions.origin {
+            QueryOrigin::Derived(edges) | QueryOrigin::DerivedUntracked(edges) => edges.clone(),
+            QueryOrigin::Assigned(_) | QueryOrigin::BaseInput => return,
+        };
+        drop(memo);
+        revisions.origin = QueryOrigin::DerivedUntracked(edges);
+        runtime.report_tracked_write(revisions.durability);
+
+        let old_memo = self.memo_map.remove(key).unwrap().into_inner();
+        let verified_at = old_memo.verified_at.load();
+        // We have `&mut self`, so the value can only be shared with the jars of a
+        // pipelined write; then, it is dropped, and the new value cannot be backdated.
+        let value = Arc::try_unwrap(old_memo)
+            .ok()
+            .and_then(|old_memo| old_memo.value);
+        let memo = Memo::new(value, verified_at, revisions);
+        let _ = self.memo_map.insert(key, Arc::new(memo));
+    }
+}
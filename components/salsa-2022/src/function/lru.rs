@@ -1,12 +1,18 @@
-use crate::{hash::FxLinkedHashSet, Id};
+use crate::{
+    hash::{FxHashSet, FxLinkedHashSet},
+    sync::Mutex,
+    Id,
+};
 
 use crossbeam_utils::atomic::AtomicCell;
-use parking_lot::Mutex;
 
 #[derive(Default)]
 pub(super) struct Lru {
     capacity: AtomicCell<usize>,
     set: Mutex<FxLinkedHashSet<Id>>,
+
+    /// Entries that are never evicted, and do not count towards the capacity.
+    pinned: Mutex<FxHashSet<Id>>,
 }
 
 impl Lru {
@@ -18,6 +24,10 @@ impl Lru {
             return None;
         }
 
+        if self.pinned.lock().contains(&index) {
+            return None;
+        }
+
         let mut set = self.set.lock();
         set.insert(index);
         if set.len() > capacity {
@@ -27,12 +37,42 @@ impl Lru {
         None
     }
 
-    pub(super) fn set_capacity(&self, capacity: usize) {
+    pub(super) fn fork(&self) -> Self {
+        Self {
+            capacity: AtomicCell::new(self.capacity.load()),
+            set: Mutex::new(self.set.lock().clone()),
+            pinned: Mutex::new(self.pinned.lock().clone()),
+        }
+    }
+
+    /// Pins `index`, so that it is never evicted until it is unpinned.
+    pub(super) fn pin(&self, index: Id) {
+        self.pinned.lock().insert(index);
+        self.set.lock().remove(&index);
+    }
+
+    /// Unpins `index`, which becomes the most recently used entry, returning the
+    /// least recently used entry if it no longer fits and should be evicted.
+    pub(super) fn unpin(&self, index: Id) -> Option<Id> {
+        if self.pinned.lock().remove(&index) {
+            self.record_use(index)
+        } else {
+            None
+        }
+    }
+
+    /// Changes the capacity, returning the least recently used entries
+    /// that no longer fit and should be evicted.
+    pub(super) fn set_capacity(&self, capacity: usize) -> Vec<Id> {
         self.capacity.store(capacity);
 
+        let mut set = self.set.lock();
         if capacity == 0 {
-            let mut set = self.set.lock();
             *set = FxLinkedHashSet::default();
+            return vec![];
         }
+
+        let excess = set.len().saturating_sub(capacity);
+        (0..excess).filter_map(|_| set.pop_front()).collect()
     }
 }
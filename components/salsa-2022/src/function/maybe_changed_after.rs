@@ -1,6 +1,7 @@
 use arc_swap::Guard;
 
 use crate::{
+    cycle::CycleRecoveryStrategy,
     database::AsSalsaDatabase,
     debug::DebugWithDb,
     key::DatabaseKeyIndex,
@@ -64,6 +65,14 @@ where
         let runtime = db.runtime();
         let database_key_index = self.database_key_index(key_index);
 
+        // The value of a query iterated to a fixed point that is executing on this thread
+        // is provisional: the caller has to be executed again, reading it.
+        if C::CYCLE_STRATEGY == CycleRecoveryStrategy::FixedPoint
+            && runtime.is_executing(database_key_index)
+        {
+            return Some(true);
+        }
+
         let _claim_guard = self
             .sync_map
             .claim(db.as_salsa_database(), database_key_index)?;
@@ -218,6 +227,7 @@ where
         }
 
         old_memo.mark_as_verified(db.as_salsa_database(), runtime, database_key_index);
+        self.metrics.record_validation();
         true
     }
 }
@@ -4,80 +4,265 @@ use arc_swap::{ArcSwap, Guard};
 use crossbeam_utils::atomic::AtomicCell;
 
 use crate::{
-    hash::FxDashMap, key::DatabaseKeyIndex, runtime::local_state::QueryRevisions, AsId, Event,
-    EventKind, Revision, Runtime,
+    key::{DatabaseKeyIndex, DependencyIndex},
+    memory::IngredientMemory,
+    runtime::local_state::QueryRevisions,
+    AsId, Event, EventKind, Id, IngredientIndex, Revision, Runtime,
 };
 
-/// The memo map maps from a key of type `K` to the memoized value for that `K`.
-/// The memoized value is a `Memo<V>` which contains, in addition to the value `V`,
-/// dependency information.
-pub(super) struct MemoMap<K: AsId, V> {
-    map: FxDashMap<K, ArcSwap<Memo<V>>>,
+use super::{memo_storage::MemoStorage, Configuration};
+
+/// The memo map maps from a key of type `C::Key` to the memoized value for that key.
+/// The memoized value is a `Memo<C::Value>` which contains, in addition to the value,
+/// dependency information. The memos are stored in the backend `C::Storage`.
+pub(super) struct MemoMap<C: Configuration> {
+    storage: C::Storage,
 }
 
-impl<K: AsId, V> Default for MemoMap<K, V> {
+impl<C: Configuration> Default for MemoMap<C> {
     fn default() -> Self {
         Self {
-            map: Default::default(),
+            storage: Default::default(),
         }
     }
 }
 
-impl<K: AsId, V> MemoMap<K, V> {
+impl<C: Configuration> MemoMap<C> {
     /// Inserts the memo for the given key; (atomically) overwrites any previously existing memo.-
     #[must_use]
-    pub(super) fn insert(&self, key: K, memo: Arc<Memo<V>>) -> Option<ArcSwap<Memo<V>>> {
-        self.map.insert(key, ArcSwap::from(memo))
+    pub(super) fn insert(
+        &self,
+        key: C::Key,
+        memo: Arc<Memo<C::Value>>,
+    ) -> Option<ArcSwap<Memo<C::Value>>> {
+        self.storage.insert(key, memo)
+    }
+
+    /// Returns a map sharing the memos of this one. A memo is only ever replaced
+    /// in the map, not modified, except for the revision in which it was last verified:
+    /// since the forks read later revisions, this is at worst set back to an earlier
+    /// revision in which the memo was also valid, forcing it to be verified again.
+    pub(super) fn fork(&self) -> Self {
+        Self {
+            storage: self.storage.fork(),
+        }
+    }
+
+    /// Replaces each memo by a copy, so that the memos are no longer shared with the map
+    /// this one was forked from, for [`Storage::fork`](`crate::storage::Storage::fork`).
+    /// Unlike the forks of pipelined writes, the copies may move on to revisions that
+    /// the original never sees, so the revisions in which memos are verified must be separate.
+    ///
+    /// Values are cloned if their type implements `Clone` (see [`Configuration::clone_value`]);
+    /// otherwise, the copies are evicted. Returns false if a memo whose value cannot
+    /// be computed again (e.g., a field of a tracked struct) cannot be cloned.
+    pub(super) fn unshare(&mut self) -> bool {
+        use crate::runtime::local_state::QueryOrigin;
+
+        let mut unshared = true;
+        self.storage.for_each(|_, memo_swap| {
+            let memo = memo_swap.load();
+            let value = match &memo.value {
+                Some(value) => match C::clone_value(value) {
+                    Some(value) => Some(value),
+                    None if matches!(memo.revisions.origin, QueryOrigin::Derived(_)) => None,
+                    None => {
+                        unshared = false;
+                        return;
+                    }
+                },
+                None => None,
+            };
+            memo_swap.store(Arc::new(Memo::new(
+                value,
+                memo.verified_at.load(),
+                memo.revisions.clone(),
+            )));
+        });
+        unshared
+    }
+
+    /// Makes room for the memos of at least `additional` more keys.
+    pub(super) fn reserve(&self, additional: usize) {
+        self.storage.reserve(additional)
     }
 
     /// Removes any existing memo for the given key.
     #[must_use]
-    pub(super) fn remove(&self, key: K) -> Option<ArcSwap<Memo<V>>> {
-        self.map.remove(&key).map(|o| o.1)
+    pub(super) fn remove(&self, key: C::Key) -> Option<ArcSwap<Memo<C::Value>>> {
+        self.storage.remove(key)
     }
 
     /// Loads the current memo for `key_index`. This does not hold any sort of
     /// lock on the `memo_map` once it returns, so this memo could immediately
     /// become outdated if other threads store into the `memo_map`.
-    pub(super) fn get(&self, key: K) -> Option<Guard<Arc<Memo<V>>>> {
-        self.map.get(&key).map(|v| v.load())
+    pub(super) fn get(&self, key: C::Key) -> Option<Guard<Arc<Memo<C::Value>>>> {
+        self.storage.get(key)
     }
 
     /// Evicts the existing memo for the given key, replacing it
     /// with an equivalent memo that has no value. If the memo is untracked, BaseInput,
     /// or has values assigned as output of another query, this has no effect.
-    pub(super) fn evict(&self, key: K) {
+    pub(super) fn evict(&self, key: C::Key) {
+        self.storage.with_memo(key, Self::evict_memo);
+    }
+
+    /// Calls `op` with each key, whether its memo must be kept by a sweep, and the
+    /// memos and tracked structs it must keep if it is kept itself (see
+    /// [`Ingredient::sweep_edges`](`crate::ingredient::Ingredient::sweep_edges`)).
+    /// Only the memos verified since `revision`, those of firewalls and those of
+    /// inputs are kept. The others are reached through:
+    ///
+    /// * the dependencies of the memos that are kept;
+    /// * their outputs (the tracked structs they created and the values they assigned),
+    ///   which are deleted along with them;
+    /// * for values assigned by another query, that query;
+    /// * if the keys are tracked structs (`salsa_struct_index`), those structs, so that
+    ///   the memos keyed by a deleted struct are deleted as well.
+    pub(super) fn sweep_edges(
+        &self,
+        revision: Revision,
+        firewall: bool,
+        salsa_struct_index: Option<IngredientIndex>,
+        op: &mut dyn FnMut(Id, bool, &mut dyn Iterator<Item = DependencyIndex>),
+    ) {
         use crate::runtime::local_state::QueryOrigin;
-        use dashmap::mapref::entry::Entry::*;
-
-        if let Occupied(entry) = self.map.entry(key) {
-            let memo = entry.get().load();
-            match memo.revisions.origin {
-                QueryOrigin::Assigned(_)
-                | QueryOrigin::DerivedUntracked(_)
-                | QueryOrigin::BaseInput => {
-                    // Careful: Cannot evict memos whose values were
-                    // assigned as output of another query
-                    // or those with untracked inputs
-                    // as their values cannot be reconstructed.
+
+        self.storage.for_each(|key, memo_swap| {
+            let memo = memo_swap.load();
+            let kept = firewall || memo.verified_at.load() >= revision;
+            let salsa_struct = salsa_struct_index.map(|ingredient_index| DependencyIndex {
+                ingredient_index,
+                key_index: Some(key.as_id()),
+            });
+            match &memo.revisions.origin {
+                QueryOrigin::Derived(edges) | QueryOrigin::DerivedUntracked(edges) => op(
+                    key.as_id(),
+                    kept,
+                    &mut edges.inputs().chain(edges.outputs()).chain(salsa_struct),
+                ),
+                QueryOrigin::Assigned(creator) => op(
+                    key.as_id(),
+                    kept,
+                    &mut std::iter::once((*creator).into()).chain(salsa_struct),
+                ),
+                QueryOrigin::BaseInput => op(key.as_id(), true, &mut salsa_struct.into_iter()),
+            }
+        });
+    }
+
+    /// Deletes the memos of the keys for which `reachable` is false.
+    /// This takes `&mut self` because the deleted memos are freed immediately.
+    pub(super) fn remove_unreachable(&mut self, reachable: impl Fn(C::Key) -> bool) {
+        let mut unreachable = vec![];
+        self.storage.for_each(|key, _| {
+            if !reachable(key) {
+                unreachable.push(key);
+            }
+        });
+        for key in unreachable {
+            let _ = self.storage.remove(key);
+        }
+    }
+
+    /// Evicts (as in [`Self::evict`]) every memo that was last verified before `revision`.
+    /// This takes `&mut self` because the evicted values are freed immediately.
+    pub(super) fn evict_unverified(&mut self, revision: Revision) {
+        self.storage.for_each(|_, memo_swap| {
+            if memo_swap.load().verified_at.load() < revision {
+                Self::evict_memo(memo_swap);
+            }
+        });
+    }
+
+    /// Evicts (as in [`Self::evict`]) every memo.
+    /// This takes `&mut self` because the evicted values are freed immediately.
+    pub(super) fn evict_all(&mut self) {
+        self.storage
+            .for_each(|_, memo_swap| Self::evict_memo(memo_swap));
+    }
+
+    /// Returns the keys that currently have a memo.
+    pub(super) fn keys(&self) -> Vec<C::Key> {
+        let mut keys = vec![];
+        self.storage.for_each(|key, _| keys.push(key));
+        keys
+    }
+
+    /// Returns how many keys have a memo.
+    pub(super) fn len(&self) -> usize {
+        let mut len = 0;
+        self.storage.for_each(|_, _| len += 1);
+        len
+    }
+
+    /// Returns the keys whose memo has a value that last changed after `revision`.
+    pub(super) fn changed_since(&self, revision: Revision) -> Vec<C::Key> {
+        let mut keys = vec![];
+        self.storage.for_each(|key, memo_swap| {
+            if memo_swap.load().revisions.changed_at > revision {
+                keys.push(key);
+            }
+        });
+        keys
+    }
+
+    /// Returns the memory used by the memoized values, see [`IngredientMemory`].
+    pub(super) fn memory_usage(&self, ingredient: &'static str) -> IngredientMemory {
+        let mut usage = IngredientMemory {
+            ingredient,
+            heap_bytes: C::HEAP_SIZE.then_some(0),
+            ..Default::default()
+        };
+        self.storage.for_each(|_, memo_swap| {
+            let memo = memo_swap.load();
+            usage.memos += 1;
+            if let Some(value) = &memo.value {
+                usage.values += 1;
+                usage.value_bytes += std::mem::size_of::<C::Value>();
+                if let Some(heap_bytes) = &mut usage.heap_bytes {
+                    *heap_bytes += C::heap_size(value);
                 }
+            }
+        });
+        usage
+    }
+
+    fn evict_memo(memo_swap: &ArcSwap<Memo<C::Value>>) {
+        use crate::runtime::local_state::QueryOrigin;
 
-                QueryOrigin::Derived(_) => {
-                    let memo_evicted = Arc::new(Memo::new(
-                        None::<V>,
-                        memo.verified_at.load(),
-                        memo.revisions.clone(),
-                    ));
+        let memo = memo_swap.load();
+        match memo.revisions.origin {
+            QueryOrigin::Assigned(_)
+            | QueryOrigin::DerivedUntracked(_)
+            | QueryOrigin::BaseInput => {
+                // Careful: Cannot evict memos whose values were
+                // assigned as output of another query
+                // or those with untracked inputs
+                // as their values cannot be reconstructed.
+            }
 
-                    entry.get().store(memo_evicted);
+            QueryOrigin::Derived(_) => {
+                if memo.value.is_none() {
+                    return;
                 }
+
+                let memo_evicted = Arc::new(Memo::new(
+                    None::<C::Value>,
+                    memo.verified_at.load(),
+                    memo.revisions.clone(),
+                ));
+
+                // Only replace the memo we looked at: if a new one was stored
+                // in the meantime, it is left alone.
+                memo_swap.compare_and_swap(&memo, memo_evicted);
             }
         }
     }
 }
 
 #[derive(Debug)]
-pub(super) struct Memo<V> {
+pub struct Memo<V> {
     /// The result of the query, if we decide to memoize it.
     pub(super) value: Option<V>,
 
@@ -118,12 +303,15 @@ impl<V> Memo<V> {
         runtime: &crate::Runtime,
         database_key_index: DatabaseKeyIndex,
     ) {
-        db.salsa_event(Event {
-            runtime_id: runtime.id(),
-            kind: EventKind::DidValidateMemoizedValue {
-                database_key: database_key_index,
+        crate::event::report(
+            db,
+            Event {
+                runtime_id: runtime.id(),
+                kind: EventKind::DidValidateMemoizedValue {
+                    database_key: database_key_index,
+                },
             },
-        });
+        );
 
         self.verified_at.store(runtime.current_revision());
 
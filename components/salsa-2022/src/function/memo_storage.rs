@@ -0,0 +1,215 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use arc_swap::{ArcSwap, Guard};
+
+use crate::{hash::FxDashMap, interned::GENERATION_SHIFT, sync::RwLock, AsId, Id};
+
+use super::memo::Memo;
+
+/// Stores the memos of a function ingredient, indexed by key. The backend is
+/// chosen with the `storage` option of tracked functions: either
+/// [`HashMemoStorage`] (the default) or [`DenseMemoStorage`].
+///
+/// The memos themselves are private to salsa, so this trait can't be implemented
+/// outside of it.
+pub trait MemoStorage<K: AsId, V>: Default {
+    /// Inserts the memo for the given key; (atomically) overwrites any previously existing memo.
+    fn insert(&self, key: K, memo: Arc<Memo<V>>) -> Option<ArcSwap<Memo<V>>>;
+
+    /// Removes any existing memo for the given key.
+    fn remove(&self, key: K) -> Option<ArcSwap<Memo<V>>>;
+
+    /// Loads the current memo for the given key.
+    fn get(&self, key: K) -> Option<Guard<Arc<Memo<V>>>>;
+
+    /// Invokes `op` with the memo for the given key, if there is one.
+    fn with_memo(&self, key: K, op: impl FnOnce(&ArcSwap<Memo<V>>));
+
+    /// Invokes `op` with each key and its memo.
+    fn for_each(&self, op: impl FnMut(K, &ArcSwap<Memo<V>>));
+
+    /// Returns a storage holding the same memos, which are shared rather than copied.
+    fn fork(&self) -> Self;
+
+    /// Makes room for the memos of at least `additional` more keys.
+    fn reserve(&self, additional: usize);
+}
+
+/// Stores memos in a concurrent hash map. This is the default, and it suits
+/// any kind of key, including those for which only a few memos exist.
+pub struct HashMemoStorage<K: AsId, V> {
+    map: FxDashMap<K, ArcSwap<Memo<V>>>,
+}
+
+impl<K: AsId, V> Default for HashMemoStorage<K, V> {
+    fn default() -> Self {
+        Self {
+            map: Default::default(),
+        }
+    }
+}
+
+impl<K: AsId, V> MemoStorage<K, V> for HashMemoStorage<K, V> {
+    fn insert(&self, key: K, memo: Arc<Memo<V>>) -> Option<ArcSwap<Memo<V>>> {
+        self.map.insert(key, ArcSwap::from(memo))
+    }
+
+    fn remove(&self, key: K) -> Option<ArcSwap<Memo<V>>> {
+        self.map.remove(&key).map(|o| o.1)
+    }
+
+    fn get(&self, key: K) -> Option<Guard<Arc<Memo<V>>>> {
+        self.map.get(&key).map(|v| v.load())
+    }
+
+    fn with_memo(&self, key: K, op: impl FnOnce(&ArcSwap<Memo<V>>)) {
+        if let Some(memo) = self.map.get(&key) {
+            op(memo.value());
+        }
+    }
+
+    fn for_each(&self, mut op: impl FnMut(K, &ArcSwap<Memo<V>>)) {
+        for entry in self.map.iter() {
+            op(*entry.key(), entry.value());
+        }
+    }
+
+    fn fork(&self) -> Self {
+        let map = FxDashMap::default();
+        for entry in self.map.iter() {
+            map.insert(*entry.key(), ArcSwap::new(entry.value().load_full()));
+        }
+        Self { map }
+    }
+
+    fn reserve(&self, additional: usize) {
+        crate::hash::reserve(&self.map, additional);
+    }
+}
+
+/// Stores memos in a vector indexed by the slot of the id of the key (its
+/// first generation), which avoids hashing and is more compact when most keys
+/// have a memo (e.g., for a function called on every instance of a tracked
+/// struct). Since the vector grows up to the largest slot, it is wasteful when
+/// only a few keys have a memo.
+///
+/// Each entry records the id of its key, so a memo is never returned for
+/// another generation of the slot. The memos of keys whose slot holds the memo
+/// of another key are kept in a hash map instead.
+pub struct DenseMemoStorage<K: AsId, V> {
+    memos: RwLock<Vec<Option<DenseEntry<V>>>>,
+    overflow: HashMemoStorage<K, V>,
+    phantom: PhantomData<fn(K) -> K>,
+}
+
+impl<K: AsId, V> Default for DenseMemoStorage<K, V> {
+    fn default() -> Self {
+        Self {
+            memos: Default::default(),
+            overflow: Default::default(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// The id of a key and its memo.
+type DenseEntry<V> = (Id, ArcSwap<Memo<V>>);
+
+/// The index of the memo for `id` in a [`DenseMemoStorage`].
+fn slot(id: Id) -> usize {
+    (id.as_u32() & ((1 << GENERATION_SHIFT) - 1)) as usize
+}
+
+impl<K: AsId, V> MemoStorage<K, V> for DenseMemoStorage<K, V> {
+    fn insert(&self, key: K, memo: Arc<Memo<V>>) -> Option<ArcSwap<Memo<V>>> {
+        let id = key.as_id();
+        let index = slot(id);
+        match self.memos.read().get(index) {
+            Some(Some((memo_id, memo_swap))) if *memo_id == id => {
+                return Some(ArcSwap::new(memo_swap.swap(memo)));
+            }
+            Some(Some(_)) => return self.overflow.insert(key, memo),
+            _ => {}
+        }
+
+        // The slot may have been filled since we released the read lock,
+        // in which case the memo it holds is replaced as well.
+        let mut memos = self.memos.write();
+        if memos.len() <= index {
+            memos.resize_with(index + 1, || None);
+        }
+        match &memos[index] {
+            Some((memo_id, _)) if *memo_id != id => {
+                drop(memos);
+                self.overflow.insert(key, memo)
+            }
+            Some(_) => memos[index]
+                .replace((id, ArcSwap::from(memo)))
+                .map(|(_, memo)| memo),
+            None => {
+                // The memo of `key` was kept in the hash map while its slot was taken.
+                memos[index] = Some((id, ArcSwap::from(memo)));
+                drop(memos);
+                self.overflow.remove(key)
+            }
+        }
+    }
+
+    fn remove(&self, key: K) -> Option<ArcSwap<Memo<V>>> {
+        let id = key.as_id();
+        let mut memos = self.memos.write();
+        if let Some(entry) = memos.get_mut(slot(id)) {
+            if matches!(entry, Some((memo_id, _)) if *memo_id == id) {
+                return entry.take().map(|(_, memo)| memo);
+            }
+        }
+        drop(memos);
+        self.overflow.remove(key)
+    }
+
+    fn get(&self, key: K) -> Option<Guard<Arc<Memo<V>>>> {
+        let id = key.as_id();
+        match self.memos.read().get(slot(id)) {
+            Some(Some((memo_id, memo))) if *memo_id == id => Some(memo.load()),
+            _ => self.overflow.get(key),
+        }
+    }
+
+    fn with_memo(&self, key: K, op: impl FnOnce(&ArcSwap<Memo<V>>)) {
+        let id = key.as_id();
+        match self.memos.read().get(slot(id)) {
+            Some(Some((memo_id, memo))) if *memo_id == id => op(memo),
+            _ => self.overflow.with_memo(key, op),
+        }
+    }
+
+    fn for_each(&self, mut op: impl FnMut(K, &ArcSwap<Memo<V>>)) {
+        for (id, memo) in self.memos.read().iter().flatten() {
+            op(K::from_id(*id), memo);
+        }
+        self.overflow.for_each(op);
+    }
+
+    fn fork(&self) -> Self {
+        let memos = self
+            .memos
+            .read()
+            .iter()
+            .map(|memo| {
+                memo.as_ref()
+                    .map(|(id, memo)| (*id, ArcSwap::new(memo.load_full())))
+            })
+            .collect();
+        Self {
+            memos: RwLock::new(memos),
+            overflow: self.overflow.fork(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// The memos are indexed by slot, so this only avoids reallocations if the
+    /// ids of the keys are allocated densely, as they are for a single salsa struct.
+    fn reserve(&self, additional: usize) {
+        self.memos.write().reserve(additional);
+    }
+}
@@ -0,0 +1,68 @@
+use crate::{
+    debug::DebugWithDb,
+    key::{DatabaseKeyIndex, DependencyIndex},
+    runtime::local_state::{EdgeKind, QueryOrigin, QueryRevisions},
+    storage::HasJarsDyn,
+};
+
+use super::{Configuration, DynDb, FunctionIngredient};
+
+impl<C> FunctionIngredient<C>
+where
+    C: Configuration,
+{
+    /// Executes the query again, right after it returned `value` having read the
+    /// dependencies in `revisions`, and panics if it returns another value or reads
+    /// other dependencies: the query is then nondeterministic, or reads some state
+    /// that salsa does not track, so its memoized values may be stale.
+    ///
+    /// Queries that reported untracked reads are not checked, since they are
+    /// not expected to be deterministic.
+    pub(super) fn self_check(
+        &self,
+        db: &DynDb<C>,
+        database_key_index: DatabaseKeyIndex,
+        value: &C::Value,
+        revisions: &QueryRevisions,
+    ) {
+        let QueryOrigin::Derived(edges) = &revisions.origin else {
+            return;
+        };
+
+        let runtime = db.runtime();
+        let active_query = runtime.push_query(database_key_index);
+        let key = C::key_from_id(database_key_index.key_index);
+        let value_again = C::execute(db, key);
+        let revisions_again = active_query.pop(runtime);
+
+        let QueryOrigin::Derived(edges_again) = &revisions_again.origin else {
+            panic!(
+                "self-check: `{:?}` read untracked state only when executed again",
+                database_key_index.debug(db),
+            );
+        };
+        if edges.input_outputs != edges_again.input_outputs {
+            let dependencies = |edges: &[(EdgeKind, DependencyIndex)]| {
+                edges
+                    .iter()
+                    .map(|(_, dependency)| format!("{:?}", dependency.debug(db)))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            panic!(
+                "self-check: `{:?}` read different dependencies when executed again \
+                 ([{}], then [{}]), so it is nondeterministic or reads state that salsa does not track",
+                database_key_index.debug(db),
+                dependencies(&edges.input_outputs),
+                dependencies(&edges_again.input_outputs),
+            );
+        }
+        if !C::NO_EQ && !C::should_backdate_value(value, &value_again) {
+            panic!(
+                "self-check: `{:?}` returned a different value when executed again, \
+                 so it is nondeterministic or reads state that salsa does not track",
+                database_key_index.debug(db),
+            );
+        }
+    }
+}
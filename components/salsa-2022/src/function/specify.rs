@@ -78,7 +78,12 @@ where
         };
 
         if let Some(old_memo) = self.memo_map.get(key) {
-            self.backdate_if_appropriate(&old_memo, &mut revisions, &value);
+            self.backdate_if_appropriate(
+                &old_memo,
+                &mut revisions,
+                |old_value| C::should_backdate_value(old_value, &value),
+                revision,
+            );
             self.diff_outputs(db, database_key_index, &old_memo, &revisions);
         }
 
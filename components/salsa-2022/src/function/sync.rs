@@ -80,10 +80,12 @@ impl<'me> ClaimGuard<'me> {
 
 impl<'me> Drop for ClaimGuard<'me> {
     fn drop(&mut self) {
-        let wait_result = if std::thread::panicking() {
-            WaitResult::Panicked
-        } else {
+        let wait_result = if !std::thread::panicking() {
             WaitResult::Completed
+        } else if self.runtime.cancellation_requested() {
+            WaitResult::Abandoned
+        } else {
+            WaitResult::Panicked
         };
         self.remove_from_map_and_unblock_queries(wait_result)
     }
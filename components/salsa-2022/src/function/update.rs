@@ -0,0 +1,51 @@
+use std::sync::Arc;
+
+use crate::{runtime::local_state::QueryRevisions, Revision};
+
+use super::{memo::Memo, Configuration, FunctionIngredient};
+
+impl<C> FunctionIngredient<C>
+where
+    C: Configuration,
+{
+    /// For functions with the `update` option: merges `value` into the value of `old_memo`
+    /// with [`Configuration::update_value`], so that the parts of the old value that did not
+    /// change keep their allocations, and backdates `revisions` if nothing changed.
+    /// Returns the merged value, to be stored in the new memo, and whether it was backdated.
+    ///
+    /// This is only possible if nothing can still refer to the old value: it must not have
+    /// been verified in this revision (otherwise `fetch` may have returned a reference to it,
+    /// while references from older revisions have all expired), and no other thread may hold
+    /// the old memo. Otherwise, `value` is returned as is and is not backdated.
+    pub(super) fn update_in_place(
+        &self,
+        key: C::Key,
+        mut old_memo: Arc<Memo<C::Value>>,
+        value: C::Value,
+        revisions: &mut QueryRevisions,
+        revision_now: Revision,
+    ) -> (C::Value, bool) {
+        if old_memo.verified_at.load() == revision_now || old_memo.value.is_none() {
+            let backdated =
+                self.backdate_if_appropriate(&old_memo, revisions, |_| false, revision_now);
+            return (value, backdated);
+        }
+
+        // Release the memo map's reference to the old memo (it is about to be replaced anyway),
+        // so that we can check whether ours is the last one.
+        drop(self.memo_map.remove(key));
+        let old_memo = match Arc::get_mut(&mut old_memo) {
+            Some(old_memo) => old_memo,
+            None => {
+                let backdated =
+                    self.backdate_if_appropriate(&old_memo, revisions, |_| false, revision_now);
+                return (value, backdated);
+            }
+        };
+
+        let changed = C::update_value(old_memo.value.as_mut().unwrap(), value);
+        let backdated =
+            self.backdate_if_appropriate(old_memo, revisions, |_| !changed, revision_now);
+        (old_memo.value.take().unwrap(), backdated)
+    }
+}
@@ -6,9 +6,20 @@ pub(crate) type FxIndexMap<K, V> = indexmap::IndexMap<K, V, FxHasher>;
 pub(crate) type FxDashMap<K, V> = dashmap::DashMap<K, V, FxHasher>;
 pub(crate) type FxLinkedHashSet<K> = hashlink::LinkedHashSet<K, FxHasher>;
 pub(crate) type FxHashSet<K> = std::collections::HashSet<K, FxHasher>;
+pub(crate) type FxHashMap<K, V> = std::collections::HashMap<K, V, FxHasher>;
 
 pub(crate) fn hash<T: Hash>(t: &T) -> u64 {
     let mut hasher = FxHasher::default().build_hasher();
     t.hash(&mut hasher);
     hasher.finish()
 }
+
+/// Makes room for at least `additional` more entries in `map`, spread evenly
+/// between its shards, so that inserting them does not rehash.
+pub(crate) fn reserve<K: Eq + Hash, V>(map: &FxDashMap<K, V>, additional: usize) {
+    let shards = map.shards();
+    let per_shard = additional.div_ceil(shards.len());
+    for shard in shards {
+        shard.write().reserve(per_shard);
+    }
+}
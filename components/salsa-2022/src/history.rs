@@ -0,0 +1,120 @@
+//! The old values of the fields of inputs with the `history` option, kept so that
+//! [`Database::revert_to`](`crate::Database::revert_to`) can restore them.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::hash::Hash;
+
+use crate::hash::FxIndexMap;
+use crate::runtime::StampedValue;
+use crate::Revision;
+
+/// The values replaced by the setter of one input field, oldest first.
+pub(crate) struct InputHistory<K, F> {
+    /// How many revisions the changes are kept for.
+    revisions: usize,
+
+    /// For each change, the revision in which it was made, the input whose field
+    /// was set and the value the field had before.
+    changes: VecDeque<(Revision, K, StampedValue<F>)>,
+
+    /// The oldest revision the field can be reverted to: the changes made
+    /// up to this revision may have been forgotten.
+    start: Revision,
+
+    /// `F::clone`: the setter returns the value it replaces, so the history keeps a clone.
+    clone_value: fn(&F) -> F,
+}
+
+impl<K, F> InputHistory<K, F>
+where
+    K: Copy + Eq + Hash,
+{
+    pub(crate) fn new(revisions: usize, clone_value: fn(&F) -> F) -> Self {
+        Self {
+            revisions,
+            changes: VecDeque::new(),
+            start: Revision::start(),
+            clone_value,
+        }
+    }
+
+    /// Records that the field of `key` was set in `revision`, replacing `old_value`,
+    /// and forgets the changes made before the last `revisions` revisions.
+    pub(crate) fn record(&mut self, revision: Revision, key: K, old_value: &StampedValue<F>) {
+        let old_value = self.clone_stamped(old_value);
+        self.changes.push_back((revision, key, old_value));
+
+        while let Some((changed_at, ..)) = self.changes.front() {
+            if changed_at.as_usize() + self.revisions > revision.as_usize() {
+                break;
+            }
+            self.start = self.start.max(*changed_at);
+            self.changes.pop_front();
+        }
+    }
+
+    /// Copies the history, for the jars of a pipelined write.
+    pub(crate) fn fork(&self) -> Self {
+        Self {
+            revisions: self.revisions,
+            changes: self
+                .changes
+                .iter()
+                .map(|(revision, key, old_value)| (*revision, *key, self.clone_stamped(old_value)))
+                .collect(),
+            start: self.start,
+            clone_value: self.clone_value,
+        }
+    }
+
+    /// The oldest revision the field can be reverted to.
+    pub(crate) fn start(&self) -> Revision {
+        self.start
+    }
+
+    /// Returns the value that the field of each input set after `revision` had in
+    /// `revision`, i.e. the value replaced by the first change after `revision`.
+    pub(crate) fn values_at(&self, revision: Revision) -> Vec<(K, StampedValue<F>)> {
+        let mut values: FxIndexMap<K, StampedValue<F>> = Default::default();
+        for (changed_at, key, old_value) in &self.changes {
+            if *changed_at > revision {
+                values
+                    .entry(*key)
+                    .or_insert_with(|| self.clone_stamped(old_value));
+            }
+        }
+        values.into_iter().collect()
+    }
+
+    fn clone_stamped(&self, stamped_value: &StampedValue<F>) -> StampedValue<F> {
+        StampedValue {
+            value: (self.clone_value)(&stamped_value.value),
+            durability: stamped_value.durability,
+            changed_at: stamped_value.changed_at,
+        }
+    }
+}
+
+/// The error returned by [`Database::revert_to`](`crate::Database::revert_to`)
+/// when the history of some input does not go back to the requested revision.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RevertError {
+    /// The revision to revert to.
+    pub revision: Revision,
+
+    /// The oldest revision that all inputs with the `history` option can be reverted to.
+    pub oldest: Revision,
+}
+
+impl fmt::Display for RevertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "cannot revert to {:?}: the history of the inputs only goes back to {:?}",
+            self.revision, self.oldest
+        )
+    }
+}
+
+impl std::error::Error for RevertError {}
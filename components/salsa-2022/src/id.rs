@@ -11,10 +11,29 @@ use std::num::NonZeroU32;
 /// You are more likely to use types that implement the `AsId` trait,
 /// such as entity keys.
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
 pub struct Id {
     value: NonZeroU32,
 }
 
+// Not derived, so that the ids in the values stored in the remote cache are refused.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Id {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        #[cfg(feature = "remote-cache")]
+        if crate::remote_cache::refuse_id() {
+            return Err(serde::ser::Error::custom(
+                "salsa ids cannot be stored in the remote cache",
+            ));
+        }
+        let mut state = serializer.serialize_struct("Id", 1)?;
+        state.serialize_field("value", &self.value)?;
+        state.end()
+    }
+}
+
 impl Id {
     pub const MAX_U32: u32 = std::u32::MAX - 0xFF;
     pub const MAX_USIZE: usize = Self::MAX_U32 as usize;
@@ -65,6 +84,45 @@ impl From<Id> for usize {
     }
 }
 
+/// Displays the value of the id, e.g. `12`, which [`str::parse`] reads back.
+#[cfg(feature = "debug-ids")]
+impl std::fmt::Display for Id {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_u32())
+    }
+}
+
+#[cfg(feature = "debug-ids")]
+impl std::str::FromStr for Id {
+    type Err = ParseIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse::<u32>() {
+            Ok(value) if value < Id::MAX_U32 => Ok(Id::from_u32(value)),
+            _ => Err(ParseIdError {
+                text: s.to_string(),
+            }),
+        }
+    }
+}
+
+/// The error returned when parsing a text that is not the value of an [`Id`].
+#[cfg(feature = "debug-ids")]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParseIdError {
+    text: String,
+}
+
+#[cfg(feature = "debug-ids")]
+impl std::fmt::Display for ParseIdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}` is not a salsa id", self.text)
+    }
+}
+
+#[cfg(feature = "debug-ids")]
+impl std::error::Error for ParseIdError {}
+
 /// Trait for types that can be interconverted to a salsa Id;
 pub trait AsId: Sized + Copy + Eq + Hash + Debug {
     fn as_id(self) -> Id;
@@ -1,8 +1,10 @@
+use std::any::{Any, TypeId};
 use std::fmt;
 
 use crate::{
-    cycle::CycleRecoveryStrategy, key::DependencyIndex, runtime::local_state::QueryOrigin,
-    DatabaseKeyIndex, Id, IngredientIndex,
+    cycle::CycleRecoveryStrategy, dump::IngredientDump, hash::FxHashSet, key::DependencyIndex,
+    memory::IngredientMemory, metrics::QueryMetrics, runtime::local_state::QueryOrigin,
+    DatabaseKeyIndex, Id, IngredientIndex, Runtime,
 };
 
 use super::Revision;
@@ -65,6 +67,100 @@ pub trait Ingredient<DB: ?Sized> {
     /// [`IngredientRequiresReset::RESET_ON_NEW_REVISION`] to true.
     fn reset_for_new_revision(&mut self);
 
+    /// Invoked by [`Storage::sweep`](`crate::storage::Storage::sweep`) before
+    /// [`Ingredient::sweep`]: calls `op` with the key of each memo (or tracked struct),
+    /// whether it must be kept (e.g., because it was verified since `revision`), and
+    /// what must be kept with it (e.g., the dependencies it read, the tracked structs
+    /// it created, or for a tracked struct, the memo of the query that created it).
+    fn sweep_edges(
+        &self,
+        revision: Revision,
+        op: &mut dyn FnMut(Id, bool, &mut dyn Iterator<Item = DependencyIndex>),
+    );
+
+    /// Invoked by [`Storage::sweep`](`crate::storage::Storage::sweep`) to delete the
+    /// memos (or tracked structs) that are not in `reachable` (those that must be kept, see
+    /// [`Ingredient::sweep_edges`], and their transitive dependencies), and to discard
+    /// the values of the others that were last verified before `revision`.
+    /// For those, the dependency information needed to re-validate (and, if needed,
+    /// re-execute) them must be kept.
+    fn sweep(&mut self, revision: Revision, reachable: &FxHashSet<DatabaseKeyIndex>);
+
+    /// Invoked by [`Storage::release_weak_memos`](`crate::storage::Storage::release_weak_memos`)
+    /// to discard the values of the tracked functions with the `weak` option.
+    /// As with [`Ingredient::sweep`], the dependency information must be kept.
+    fn release_weak_memos(&mut self);
+
+    /// Invoked by [`Storage::fork`](`crate::storage::Storage::fork`) on the ingredients
+    /// of the copy, to copy the state they still share with the ingredients they were
+    /// forked from and that is modified in place (e.g., the revision in which a memo
+    /// was last verified). Returns false if that state cannot be copied.
+    fn unshare(&mut self) -> bool;
+
+    /// Returns the keys for which this ingredient currently stores a memo.
+    /// Used by [`Storage::dependency_graph`](`crate::storage::Storage::dependency_graph`)
+    /// to walk the recorded dependencies.
+    fn memoized_keys(&self) -> Vec<Id>;
+
+    /// Returns the counters recorded for this ingredient, if it executes queries
+    /// and the `metrics` feature is enabled.
+    /// Used by [`Storage::query_metrics`](`crate::storage::Storage::query_metrics`).
+    fn query_metrics(&self) -> Option<QueryMetrics>;
+
+    /// Returns the memory used by the values memoized by this ingredient, if it
+    /// memoizes values. The caller fills in the `jar` field.
+    /// Used by [`Storage::memory_report`](`crate::storage::Storage::memory_report`).
+    fn memory_usage(&self) -> Option<IngredientMemory>;
+
+    /// Returns how many memos (or interned values) this ingredient stores, if it
+    /// stores one for each key it is called with.
+    /// Used by [`Storage::set_leak_check`](`crate::storage::Storage::set_leak_check`).
+    fn memo_count(&self) -> Option<usize>;
+
+    /// Returns what this ingredient stores. The caller fills in the `name` field.
+    /// Used by [`Storage::dump_jar`](`crate::storage::Storage::dump_jar`).
+    fn dump(&self) -> IngredientDump;
+
+    /// True if this ingredient is a function that can be called by name with
+    /// [`Ingredient::fetch_dyn`].
+    /// Used by [`Storage::query_names`](`crate::storage::Storage::query_names`).
+    fn can_fetch_dyn(&self) -> bool;
+
+    /// Returns a clone of the value of this function for `key`, computing it if needed,
+    /// or `None` if this ingredient can't be called by name.
+    /// Used by [`Storage::fetch_dyn`](`crate::storage::Storage::fetch_dyn`).
+    fn fetch_dyn(&self, db: &DB, key: Id) -> Option<Box<dyn Any>>;
+
+    /// Returns the oldest revision whose values this ingredient can restore with
+    /// [`Ingredient::revert_to`], if it keeps a history of its values
+    /// (i.e., it is the field of an input with the `history` option).
+    /// Used by [`Storage::revert_to`](`crate::storage::Storage::revert_to`).
+    fn history_start(&self) -> Option<Revision>;
+
+    /// Restores the values this ingredient had in `revision`, as writes in the
+    /// current revision. Does nothing if it keeps no history.
+    /// Used by [`Storage::revert_to`](`crate::storage::Storage::revert_to`).
+    fn revert_to(&mut self, runtime: &mut Runtime, revision: Revision);
+
+    /// Returns the keys for which this ingredient stores a memo whose value
+    /// last changed after `revision`.
+    /// Used by [`Storage::changed_since`](`crate::storage::Storage::changed_since`).
+    fn changed_since(&self, revision: Revision) -> Vec<Id>;
+
+    /// If this ingredient memoizes a tracked function, returns the type of its keys:
+    /// the salsa struct it takes, or its interned arguments.
+    /// Used by [`Storage::queries_keyed_by`](`crate::storage::Storage::queries_keyed_by`).
+    fn key_type(&self) -> Option<TypeId>;
+
+    /// If this ingredient stores tracked structs, returns the query that created
+    /// the one at `key_index`, unless it was deleted since.
+    /// Used by [`Storage::creator_of`](`crate::storage::Storage::creator_of`).
+    fn creator(&self, key_index: Id) -> Option<DatabaseKeyIndex>;
+
+    /// Returns the name of this ingredient, e.g. `my_query`.
+    /// Used by [`Runtime::current_query_stack`](`crate::Runtime::current_query_stack`).
+    fn debug_name(&self) -> &'static str;
+
     fn fmt_index(&self, index: Option<crate::Id>, fmt: &mut fmt::Formatter<'_>) -> fmt::Result;
 }
 
@@ -22,6 +22,13 @@ impl IngredientList {
         }
     }
 
+    /// Returns a list with the same items, to which items can be added separately.
+    pub(crate) fn fork(&self) -> Self {
+        Self {
+            list: ArcSwapOption::new(self.list.load_full()),
+        }
+    }
+
     /// Returns an iterator over the items in the list.
     /// This is a snapshot of the list as it was when this function is called.
     /// Items could still be added in parallel via `add_ingredient`
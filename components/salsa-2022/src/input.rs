@@ -1,14 +1,24 @@
 use std::{
+    any::{Any, TypeId},
     fmt,
     sync::atomic::{AtomicU32, Ordering},
 };
 
 use crate::{
     cycle::CycleRecoveryStrategy,
+    dump::IngredientDump,
+    durability::Durability,
+    hash::{FxDashMap, FxHashSet},
     ingredient::{fmt_index, Ingredient, IngredientRequiresReset},
+    ingredient_list::IngredientList,
+    interned::{GENERATION_SHIFT, MAX_GENERATION},
     key::{DatabaseKeyIndex, DependencyIndex},
+    memory::IngredientMemory,
+    metrics::QueryMetrics,
+    revision::AtomicRevision,
     runtime::{local_state::QueryOrigin, Runtime},
-    AsId, IngredientIndex, Revision,
+    sync::Mutex,
+    AsId, Database, Event, EventKind, IngredientIndex, Revision,
 };
 
 pub trait InputId: AsId {}
@@ -20,6 +30,26 @@ where
 {
     ingredient_index: IngredientIndex,
     counter: AtomicU32,
+
+    /// The revision in which the last input was created or removed. Queries
+    /// that read the set of all inputs depend on it.
+    changed_at: AtomicRevision,
+
+    /// For each slot whose input was removed, the id of the input that reuses
+    /// it (with the next generation), if any.
+    removed_slots: FxDashMap<u32, Option<crate::Id>>,
+
+    /// Once all the slots were used, the first id of the inputs created since,
+    /// which are above the ids of all generations and never reuse a slot (their
+    /// id is their slot); zero until then.
+    unslotted_start: AtomicU32,
+
+    /// The ids of the removed inputs, whose slots can be reused.
+    free_ids: Mutex<Vec<crate::Id>>,
+
+    /// The tracked functions keyed by this input, whose memos are deleted
+    /// when an input is removed.
+    dependent_fns: IngredientList,
     debug_name: &'static str,
     _phantom: std::marker::PhantomData<Id>,
 }
@@ -32,11 +62,31 @@ where
         Self {
             ingredient_index: index,
             counter: Default::default(),
+            changed_at: AtomicRevision::start(),
+            removed_slots: Default::default(),
+            unslotted_start: Default::default(),
+            free_ids: Default::default(),
+            dependent_fns: IngredientList::new(),
             debug_name,
             _phantom: std::marker::PhantomData,
         }
     }
 
+    /// Copies the ingredient for the jars of a pipelined write.
+    pub fn fork(&self) -> Option<Self> {
+        Some(Self {
+            ingredient_index: self.ingredient_index,
+            counter: AtomicU32::new(self.counter.load(Ordering::Relaxed)),
+            changed_at: AtomicRevision::new(self.changed_at.load()),
+            removed_slots: self.removed_slots.clone(),
+            unslotted_start: AtomicU32::new(self.unslotted_start.load(Ordering::Relaxed)),
+            free_ids: Mutex::new(self.free_ids.lock().clone()),
+            dependent_fns: self.dependent_fns.fork(),
+            debug_name: self.debug_name,
+            _phantom: std::marker::PhantomData,
+        })
+    }
+
     pub fn database_key_index(&self, id: Id) -> DatabaseKeyIndex {
         DatabaseKeyIndex {
             ingredient_index: self.ingredient_index,
@@ -44,11 +94,149 @@ where
         }
     }
 
-    pub fn new_input(&self, _runtime: &Runtime) -> Id {
-        let next_id = self.counter.fetch_add(1, Ordering::Relaxed);
+    /// Returns a new id: the id of a removed input with the next generation,
+    /// if there is one whose slot has generations left.
+    ///
+    /// Once all the slots were used, slots are no longer reused, and new ids
+    /// continue above the ids of all the generations given so far.
+    pub fn new_input(&self, runtime: &Runtime) -> Id {
+        self.changed_at.store(runtime.current_revision());
+
+        let mut free_ids = self.free_ids.lock();
+        let mut next_id = self.counter.load(Ordering::Relaxed);
+        if next_id < 1 << GENERATION_SHIFT {
+            while let Some(id) = free_ids.pop() {
+                let id = id.as_u32();
+                if id >> GENERATION_SHIFT < MAX_GENERATION {
+                    let new_id = crate::Id::from_u32(id + (1 << GENERATION_SHIFT));
+                    let slot = id & ((1 << GENERATION_SHIFT) - 1);
+                    self.removed_slots.insert(slot, Some(new_id));
+                    return Id::from_id(new_id);
+                }
+            }
+        } else {
+            free_ids.clear();
+        }
+
+        if next_id == 1 << GENERATION_SHIFT {
+            let generation = self
+                .removed_slots
+                .iter()
+                .filter_map(|entry| *entry.value())
+                .map(|id| id.as_u32() >> GENERATION_SHIFT)
+                .max()
+                .unwrap_or(0);
+            next_id = (generation + 1) << GENERATION_SHIFT;
+            self.unslotted_start.store(next_id, Ordering::Relaxed);
+        }
+        self.counter.store(next_id + 1, Ordering::Relaxed);
         Id::from_id(crate::Id::from_u32(next_id))
     }
 
+    /// The slot of `id`: its first generation, or `id` itself if it was
+    /// created once all the slots were used.
+    fn slot(&self, id: crate::Id) -> u32 {
+        let unslotted_start = self.unslotted_start.load(Ordering::Relaxed);
+        if unslotted_start != 0 && id.as_u32() >= unslotted_start {
+            id.as_u32()
+        } else {
+            id.as_u32() & ((1 << GENERATION_SHIFT) - 1)
+        }
+    }
+
+    /// The slots of all the inputs created so far, in the order they were first used.
+    fn slots(&self) -> impl Iterator<Item = u32> {
+        let counter = self.counter.load(Ordering::Relaxed);
+        let unslotted = match self.unslotted_start.load(Ordering::Relaxed) {
+            0 => 0..0,
+            unslotted_start => unslotted_start..counter,
+        };
+        (0..counter.min(1 << GENERATION_SHIFT)).chain(unslotted)
+    }
+
+    /// Removes `id`: the memos of the tracked functions keyed by it are deleted, and
+    /// its slot is reused by a new input, with another id. Its fields must have been
+    /// removed in the current revision, so that the queries that read them are
+    /// executed again.
+    ///
+    /// Panics if `id` was already removed.
+    pub fn remove(&self, db: &dyn Database, id: Id) {
+        let id = id.as_id();
+        assert!(
+            self.is_live(id),
+            "`{}({:?})` was already removed",
+            self.debug_name,
+            id,
+        );
+        self.removed_slots.insert(self.slot(id), None);
+        self.free_ids.lock().push(id);
+        self.changed_at.store(db.runtime().current_revision());
+
+        crate::event::report(
+            db,
+            Event {
+                runtime_id: db.runtime().id(),
+                kind: EventKind::DidDiscard {
+                    key: self.database_key_index(Id::from_id(id)),
+                },
+            },
+        );
+        for dependent_fn in self.dependent_fns.iter() {
+            db.salsa_struct_deleted(dependent_fn, id);
+        }
+    }
+
+    /// Whether `id` is an input that was not removed.
+    fn is_live(&self, id: crate::Id) -> bool {
+        let slot = self.slot(id);
+        match self.removed_slots.get(&slot) {
+            Some(current) => *current == Some(id),
+            None => id.as_u32() == slot && slot < self.counter.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Adds a dependent function (one keyed by this input) to our list.
+    /// When inputs are removed, these dependent functions will be notified.
+    pub fn register_dependent_fn(&self, index: IngredientIndex) {
+        self.dependent_fns.push(index);
+    }
+
+    /// Returns how many inputs were created and not removed.
+    ///
+    /// The calling query depends on the set of all inputs, so it is executed
+    /// again after an input is created or removed.
+    pub fn len(&self, runtime: &Runtime) -> usize {
+        self.entries(runtime).count()
+    }
+
+    /// Returns whether there is no input. See [`Self::len`].
+    pub fn is_empty(&self, runtime: &Runtime) -> bool {
+        self.len(runtime) == 0
+    }
+
+    /// Returns all the inputs that were not removed, by slot (i.e., in the order
+    /// they were created, but for those that reuse the slot of a removed one).
+    ///
+    /// The calling query depends on the set of all inputs, so it is executed
+    /// again after an input is created or removed.
+    pub fn entries(&self, runtime: &Runtime) -> impl Iterator<Item = Id> + '_ {
+        self.report_read_of_all(runtime);
+        self.slots()
+            .filter_map(|slot| match self.removed_slots.get(&slot) {
+                Some(current) => *current,
+                None => Some(crate::Id::from_u32(slot)),
+            })
+            .map(Id::from_id)
+    }
+
+    fn report_read_of_all(&self, runtime: &Runtime) {
+        let index = DependencyIndex {
+            ingredient_index: self.ingredient_index,
+            key_index: None,
+        };
+        runtime.report_tracked_read(index, Durability::LOW, self.changed_at.load());
+    }
+
     pub fn new_singleton_input(&self, _runtime: &Runtime) -> Id {
         // when one exists already, panic
         if self.counter.load(Ordering::Relaxed) >= 1 {
@@ -72,10 +260,15 @@ where
         self.ingredient_index
     }
 
-    fn maybe_changed_after(&self, _db: &DB, _input: DependencyIndex, _revision: Revision) -> bool {
+    fn maybe_changed_after(&self, _db: &DB, input: DependencyIndex, revision: Revision) -> bool {
         // Input ingredients are just a counter, they store no data, they are immortal.
         // Their *fields* are stored in function ingredients elsewhere.
-        false
+        match input.key_index {
+            Some(_) => false,
+            // The set of all inputs. Inputs are created without starting a new revision,
+            // so one created in `revision` may have been created after the read.
+            None => self.changed_at.load() >= revision,
+        }
     }
 
     fn cycle_recovery_strategy(&self) -> CycleRecoveryStrategy {
@@ -114,12 +307,80 @@ where
         panic!("unexpected call to `reset_for_new_revision`")
     }
 
+    fn sweep_edges(
+        &self,
+        _revision: Revision,
+        _op: &mut dyn FnMut(crate::Id, bool, &mut dyn Iterator<Item = DependencyIndex>),
+    ) {
+    }
+
+    fn sweep(&mut self, _revision: Revision, _reachable: &FxHashSet<DatabaseKeyIndex>) {}
+
+    fn release_weak_memos(&mut self) {}
+
+    fn unshare(&mut self) -> bool {
+        true
+    }
+
+    fn memoized_keys(&self) -> Vec<crate::Id> {
+        vec![]
+    }
+
+    fn query_metrics(&self) -> Option<QueryMetrics> {
+        None
+    }
+
+    fn memory_usage(&self) -> Option<IngredientMemory> {
+        None
+    }
+
+    fn memo_count(&self) -> Option<usize> {
+        None
+    }
+
+    fn dump(&self) -> IngredientDump {
+        IngredientDump {
+            entries: self.slots().count(),
+            ..Default::default()
+        }
+    }
+
+    fn can_fetch_dyn(&self) -> bool {
+        false
+    }
+
+    fn fetch_dyn(&self, _db: &DB, _key: crate::Id) -> Option<Box<dyn Any>> {
+        None
+    }
+
+    fn history_start(&self) -> Option<Revision> {
+        None
+    }
+
+    fn revert_to(&mut self, _runtime: &mut Runtime, _revision: Revision) {}
+
+    fn changed_since(&self, _revision: Revision) -> Vec<crate::Id> {
+        vec![]
+    }
+
+    fn key_type(&self) -> Option<TypeId> {
+        None
+    }
+
+    fn creator(&self, _key_index: crate::Id) -> Option<DatabaseKeyIndex> {
+        None
+    }
+
     fn salsa_struct_deleted(&self, _db: &DB, _id: crate::Id) {
         panic!(
             "unexpected call: input ingredients do not register for salsa struct deletion events"
         );
     }
 
+    fn debug_name(&self) -> &'static str {
+        self.debug_name
+    }
+
     fn fmt_index(&self, index: Option<crate::Id>, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt_index(self.debug_name, index, fmt)
     }
@@ -1,13 +1,21 @@
 use crate::cycle::CycleRecoveryStrategy;
+use crate::dump::IngredientDump;
+use crate::hash::FxHashSet;
+use crate::history::InputHistory;
 use crate::ingredient::{fmt_index, Ingredient, IngredientRequiresReset};
 use crate::key::DependencyIndex;
+use crate::memory::IngredientMemory;
+use crate::metrics::QueryMetrics;
 use crate::runtime::local_state::QueryOrigin;
 use crate::runtime::StampedValue;
 use crate::{AsId, DatabaseKeyIndex, Durability, Id, IngredientIndex, Revision, Runtime};
 use dashmap::mapref::entry::Entry;
 use dashmap::DashMap;
+use std::any::{Any, TypeId};
 use std::fmt;
 use std::hash::Hash;
+use std::marker::PhantomData;
+use std::sync::Arc;
 
 /// Ingredient used to represent the fields of a `#[salsa::input]`.
 ///
@@ -20,7 +28,15 @@ use std::hash::Hash;
 /// structs.
 pub struct InputFieldIngredient<K, F> {
     index: IngredientIndex,
-    map: DashMap<K, Box<StampedValue<F>>>,
+    map: DashMap<K, Arc<StampedValue<F>>>,
+    /// The values replaced by `store_mut`, if the input has the `history` option.
+    history: Option<InputHistory<K, F>>,
+    /// `F::clone`, if the type of the field implements `Clone`. Needed to fork the ingredient,
+    /// since the setter must then return a copy of the value it replaces.
+    clone_value: Option<fn(&F) -> F>,
+    /// Formats the values with `Debug`, if the type of the field implements it.
+    /// Used to dump the values (see [`Ingredient::dump`]).
+    debug_value: Option<fn(&F) -> String>,
     debug_name: &'static str,
 }
 
@@ -32,27 +48,111 @@ where
         Self {
             index,
             map: Default::default(),
+            history: None,
+            clone_value: None,
+            debug_value: None,
             debug_name,
         }
     }
 
+    /// Records how to clone the values of the field, if its type implements `Clone`
+    /// (see [`helper::CloneValue`]); otherwise, the ingredient cannot be forked.
+    pub fn set_clone_value(&mut self, clone_value: Option<fn(&F) -> F>) {
+        self.clone_value = clone_value;
+    }
+
+    /// Records how to format the values of the field, if its type implements `Debug`
+    /// (see [`helper::DebugValue`]), so that they are included in the dumps of the jar.
+    pub fn set_debug_value(&mut self, debug_value: Option<fn(&F) -> String>) {
+        self.debug_value = debug_value;
+    }
+
+    /// Returns an ingredient sharing the values of this one, for the jars of a pipelined
+    /// write, or `None` if the type of the field does not implement `Clone`.
+    pub fn fork(&self) -> Option<Self> {
+        let clone_value = self.clone_value?;
+        let map = DashMap::default();
+        for entry in self.map.iter() {
+            map.insert(*entry.key(), entry.value().clone());
+        }
+        Some(Self {
+            index: self.index,
+            map,
+            history: self.history.as_ref().map(InputHistory::fork),
+            clone_value: Some(clone_value),
+            debug_value: self.debug_value,
+            debug_name: self.debug_name,
+        })
+    }
+
+    /// Keeps the values replaced by `store_mut` in the last `revisions` revisions,
+    /// so that the field can be reverted to them.
+    pub fn keep_history(&mut self, revisions: usize)
+    where
+        F: Clone,
+    {
+        self.history = Some(InputHistory::new(revisions, F::clone));
+    }
+
     pub fn store_mut(
         &mut self,
-        runtime: &Runtime,
+        runtime: &mut Runtime,
         key: K,
         value: F,
         durability: Durability,
     ) -> Option<F> {
+        if !self.map.contains_key(&key) {
+            self.panic_removed(key);
+        }
         let revision = runtime.current_revision();
-        let stamped_value = Box::new(StampedValue {
+        let stamped_value = Arc::new(StampedValue {
             value,
             durability,
             changed_at: revision,
         });
 
-        self.map
-            .insert(key, stamped_value)
-            .map(|old_value| old_value.value)
+        let old_value = self.map.insert(key, stamped_value)?;
+        if let Some(history) = &mut self.history {
+            history.record(revision, key, &old_value);
+        }
+
+        // Queries that read the old value recorded its durability,
+        // so that is the durability that has now changed.
+        runtime.report_tracked_write(old_value.durability);
+
+        // The old value is shared with the jars this ingredient was forked from, if any.
+        match Arc::try_unwrap(old_value) {
+            Ok(old_value) => Some(old_value.value),
+            Err(old_value) => Some((self.clone_value.unwrap())(&old_value.value)),
+        }
+    }
+
+    /// Returns the durability of the field of `key`, and whether its value is equal
+    /// to `value`, without recording a read. Used by `Setter::to_if_changed`.
+    pub fn compare(&self, key: K, value: &F) -> (Durability, bool)
+    where
+        F: Eq,
+    {
+        let Some(old_value) = self.map.get(&key) else {
+            self.panic_removed(key)
+        };
+        (old_value.durability, old_value.value == *value)
+    }
+
+    /// Removes the field of an input that is removed.
+    /// Queries that read it are executed again.
+    pub fn remove(&mut self, runtime: &mut Runtime, key: K) {
+        if let Some((_, old_value)) = self.map.remove(&key) {
+            runtime.report_tracked_write(old_value.durability);
+        }
+    }
+
+    fn panic_removed(&self, key: K) -> ! {
+        panic!(
+            "cannot access field `{}` of input {:?}: it was removed",
+            self.debug_name,
+            key.as_id(),
+        )
     }
 
     /// Set the field of a new input.
@@ -60,7 +160,7 @@ where
     /// This function panics if the field has ever been set before.
     pub fn store_new(&self, runtime: &Runtime, key: K, value: F, durability: Durability) {
         let revision = runtime.current_revision();
-        let stamped_value = Box::new(StampedValue {
+        let stamped_value = Arc::new(StampedValue {
             value,
             durability,
             changed_at: revision,
@@ -81,7 +181,10 @@ where
             value,
             durability,
             changed_at,
-        } = &**self.map.get(&key).unwrap();
+        } = &**self
+            .map
+            .get(&key)
+            .unwrap_or_else(|| self.panic_removed(key));
 
         runtime.report_tracked_read(
             self.database_key_index(key).into(),
@@ -90,13 +193,13 @@ where
         );
 
         // SAFETY:
-        // The value is stored in a box so internal moves in the dashmap don't
-        // invalidate the reference to the value inside the box.
+        // The value is stored in an `Arc` so internal moves in the dashmap don't
+        // invalidate the reference to the value inside the `Arc`.
         // Values are only removed or altered when we have `&mut self`.
         unsafe { transmute_lifetime(self, value) }
     }
 
-    fn database_key_index(&self, key: K) -> DatabaseKeyIndex {
+    pub(crate) fn database_key_index(&self, key: K) -> DatabaseKeyIndex {
         DatabaseKeyIndex {
             ingredient_index: self.index,
             key_index: key.as_id(),
@@ -126,7 +229,10 @@ where
 
     fn maybe_changed_after(&self, _db: &DB, input: DependencyIndex, revision: Revision) -> bool {
         let key = K::from_id(input.key_index.unwrap());
-        self.map.get(&key).unwrap().changed_at > revision
+        // The field of a removed input has changed.
+        self.map
+            .get(&key)
+            .is_none_or(|value| value.changed_at > revision)
     }
 
     fn origin(&self, _key_index: Id) -> Option<QueryOrigin> {
@@ -157,6 +263,95 @@ where
         panic!("unexpected call: input fields don't register for resets");
     }
 
+    fn sweep_edges(
+        &self,
+        _revision: Revision,
+        _op: &mut dyn FnMut(crate::Id, bool, &mut dyn Iterator<Item = DependencyIndex>),
+    ) {
+    }
+
+    fn sweep(&mut self, _revision: Revision, _reachable: &FxHashSet<DatabaseKeyIndex>) {}
+
+    fn release_weak_memos(&mut self) {}
+
+    fn unshare(&mut self) -> bool {
+        true
+    }
+
+    fn memoized_keys(&self) -> Vec<crate::Id> {
+        vec![]
+    }
+
+    fn query_metrics(&self) -> Option<QueryMetrics> {
+        None
+    }
+
+    fn memory_usage(&self) -> Option<IngredientMemory> {
+        None
+    }
+
+    fn memo_count(&self) -> Option<usize> {
+        None
+    }
+
+    fn dump(&self) -> IngredientDump {
+        let mut values: Vec<_> = match self.debug_value {
+            Some(debug_value) => self
+                .map
+                .iter()
+                .map(|entry| (entry.key().as_id(), debug_value(&entry.value().value)))
+                .collect(),
+            None => vec![],
+        };
+        values.sort_by_key(|(id, _)| *id);
+        IngredientDump {
+            entries: self.map.len(),
+            values,
+            ..Default::default()
+        }
+    }
+
+    fn can_fetch_dyn(&self) -> bool {
+        false
+    }
+
+    fn fetch_dyn(&self, _db: &DB, _key: crate::Id) -> Option<Box<dyn Any>> {
+        None
+    }
+
+    fn history_start(&self) -> Option<Revision> {
+        self.history.as_ref().map(InputHistory::start)
+    }
+
+    fn revert_to(&mut self, runtime: &mut Runtime, revision: Revision) {
+        let Some(history) = &self.history else {
+            return;
+        };
+        for (key, old_value) in history.values_at(revision) {
+            // Removed inputs are not restored.
+            if !self.map.contains_key(&key) {
+                continue;
+            }
+            self.store_mut(runtime, key, old_value.value, old_value.durability);
+        }
+    }
+
+    fn changed_since(&self, _revision: Revision) -> Vec<crate::Id> {
+        vec![]
+    }
+
+    fn key_type(&self) -> Option<TypeId> {
+        None
+    }
+
+    fn creator(&self, _key_index: crate::Id) -> Option<DatabaseKeyIndex> {
+        None
+    }
+
+    fn debug_name(&self) -> &'static str {
+        self.debug_name
+    }
+
     fn fmt_index(&self, index: Option<crate::Id>, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt_index(self.debug_name, index, fmt)
     }
@@ -168,3 +363,47 @@ where
 {
     const RESET_ON_NEW_REVISION: bool = false;
 }
+
+/// Finds `F::clone` for the fields of inputs, if `F` implements `Clone`, and how to
+/// format them, if `F` implements `Debug`.
+///
+/// `CloneValue::<F>::clone_value()` resolves to the inherent method if `F: Clone`,
+/// and to the method of [`NoCloneValue`] otherwise, provided that trait is in scope;
+/// likewise for `DebugValue::<F>::debug_value()` and [`NoDebugValue`].
+pub mod helper {
+    use super::PhantomData;
+
+    pub struct CloneValue<F>(PhantomData<F>);
+
+    impl<F: Clone> CloneValue<F> {
+        #[allow(dead_code)]
+        pub fn clone_value() -> Option<fn(&F) -> F> {
+            Some(F::clone)
+        }
+    }
+
+    pub trait NoCloneValue<F> {
+        fn clone_value() -> Option<fn(&F) -> F> {
+            None
+        }
+    }
+
+    impl<F> NoCloneValue<F> for CloneValue<F> {}
+
+    pub struct DebugValue<F>(PhantomData<F>);
+
+    impl<F: std::fmt::Debug> DebugValue<F> {
+        #[allow(dead_code)]
+        pub fn debug_value() -> Option<fn(&F) -> String> {
+            Some(|value| format!("{value:?}"))
+        }
+    }
+
+    pub trait NoDebugValue<F> {
+        fn debug_value() -> Option<fn(&F) -> String> {
+            None
+        }
+    }
+
+    impl<F> NoDebugValue<F> for DebugValue<F> {}
+}
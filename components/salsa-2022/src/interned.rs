@@ -1,18 +1,23 @@
 use crossbeam::atomic::AtomicCell;
 use crossbeam::queue::SegQueue;
+use std::any::{Any, TypeId};
 use std::fmt;
 use std::hash::Hash;
 use std::marker::PhantomData;
 
+use crate::dump::IngredientDump;
 use crate::durability::Durability;
 use crate::id::AsId;
 use crate::ingredient::{fmt_index, IngredientRequiresReset};
 use crate::key::DependencyIndex;
+use crate::memory::IngredientMemory;
+use crate::metrics::QueryMetrics;
 use crate::runtime::local_state::QueryOrigin;
 use crate::runtime::Runtime;
-use crate::DatabaseKeyIndex;
+use crate::sync::Mutex;
+use crate::{Database, DatabaseKeyIndex};
 
-use super::hash::FxDashMap;
+use super::hash::{FxDashMap, FxHashSet};
 use super::ingredient::Ingredient;
 use super::routes::IngredientIndex;
 use super::Revision;
@@ -23,9 +28,19 @@ impl<T: AsId> InternedId for T {}
 pub trait InternedData: Sized + Eq + Hash + Clone {}
 impl<T: Eq + Hash + Clone> InternedData for T {}
 
+/// With generations (for tracked structs, with garbage collection, or for removed
+/// inputs), the bits of an id above this shift hold the generation of its slot, which
+/// is incremented each time the slot is reused.
+pub(crate) const GENERATION_SHIFT: u32 = 24;
+
+/// The last generation of a slot: its id must stay below [`crate::Id::MAX_U32`].
+pub(crate) const MAX_GENERATION: u32 = 0xFE;
+
 /// The interned ingredient has the job of hashing values of type `Data` to produce an `Id`.
 /// It used to store interned structs but also to store the id fields of a tracked struct.
-/// Interned values endure until they are explicitly removed in some way.
+/// Interned values endure until they are explicitly removed in some way,
+/// or, with garbage collection (see [`InternedIngredient::enable_gc`]),
+/// until they are no longer used.
 pub struct InternedIngredient<Id: InternedId, Data: InternedData> {
     /// Index of this ingredient in the database (used to construct database-ids, etc).
     ingredient_index: IngredientIndex,
@@ -56,6 +71,30 @@ pub struct InternedIngredient<Id: InternedId, Data: InternedData> {
     /// guaranteeing that there are no more references to it.
     deleted_entries: SegQueue<Box<Data>>,
 
+    /// If true, the values that were not used recently are reclaimed by `sweep`.
+    /// See [`InternedIngredient::enable_gc`].
+    gc: bool,
+
+    /// With `gc`, the last revision in which each id was interned, read or
+    /// validated as the dependency of a query.
+    last_used: FxDashMap<Id, AtomicCell<Revision>>,
+
+    /// If true, the slots of the ids of deleted or reclaimed values are reused
+    /// with the next generation. Set with `gc`, and for tracked structs.
+    generations: bool,
+
+    /// With `generations`, the ids deleted by `delete_index` in this revision,
+    /// whose slots are freed in the next one.
+    deleted_ids: SegQueue<Id>,
+
+    /// With `generations`, the ids of the deleted or reclaimed values, whose
+    /// slots can be reused with the next generation.
+    free_ids: Mutex<Vec<Id>>,
+
+    /// With `generations`, the last generation of each reused slot, and the
+    /// revision in which it was given to a new value, to report stale ids.
+    reused_slots: FxDashMap<u32, (u32, Revision)>,
+
     debug_name: &'static str,
 }
 
@@ -72,17 +111,67 @@ where
             counter: AtomicCell::default(),
             reset_at: Revision::start(),
             deleted_entries: Default::default(),
+            gc: false,
+            last_used: Default::default(),
+            generations: false,
+            deleted_ids: Default::default(),
+            free_ids: Default::default(),
+            reused_slots: Default::default(),
             debug_name,
         }
     }
 
+    /// Enables garbage collection: [`Storage::sweep`](`crate::storage::Storage::sweep`)
+    /// then reclaims the values that were not interned or read in the revisions it keeps,
+    /// nor validated as the dependency of a query. Their ids are reused with a new
+    /// generation, so a stale id is never mistaken for the value that reuses its slot.
+    ///
+    /// To make this sound, each query depends on the particular values it uses (rather
+    /// than on the whole table) with low durability, so it is re-validated in every
+    /// new revision. Ids have 24 bits for the slot: beyond that many values, slots
+    /// are no longer reused.
+    pub fn enable_gc(&mut self) {
+        self.gc = true;
+        self.generations = true;
+    }
+
+    /// Reuses the slots of the ids deleted by `delete_index`, with the next generation,
+    /// from the revision after their deletion. Used for tracked structs.
+    pub(crate) fn enable_generations(&mut self) {
+        self.generations = true;
+    }
+
+    /// Copies the interned values for the jars of a pipelined write.
+    /// The ids stay the same, so that they can be used with either copy.
+    pub fn fork(&self) -> Option<Self> {
+        Some(Self {
+            ingredient_index: self.ingredient_index,
+            key_map: self.key_map.clone(),
+            value_map: self.value_map.clone(),
+            counter: AtomicCell::new(self.counter.load()),
+            reset_at: self.reset_at,
+            deleted_entries: Default::default(),
+            gc: self.gc,
+            last_used: self
+                .last_used
+                .iter()
+                .map(|entry| (*entry.key(), AtomicCell::new(entry.value().load())))
+                .collect(),
+            generations: self.generations,
+            deleted_ids: Default::default(),
+            free_ids: Mutex::new(self.free_ids.lock().clone()),
+            reused_slots: self.reused_slots.clone(),
+            debug_name: self.debug_name,
+        })
+    }
+
     pub fn intern(&self, runtime: &Runtime, data: Data) -> Id {
-        runtime.report_tracked_read(
-            DependencyIndex::for_table(self.ingredient_index),
-            Durability::MAX,
-            self.reset_at,
-        );
+        let id = self.intern_id(runtime, data);
+        self.report_read(runtime, Some(id));
+        id
+    }
 
+    fn intern_id(&self, runtime: &Runtime, data: Data) -> Id {
         // Optimisation to only get read lock on the map if the data has already
         // been interned.
         if let Some(id) = self.key_map.get(&data) {
@@ -94,19 +183,100 @@ where
             dashmap::mapref::entry::Entry::Occupied(entry) => *entry.get(),
             // We won any races so should intern the data
             dashmap::mapref::entry::Entry::Vacant(entry) => {
-                let next_id = self.counter.fetch_add(1);
-                let next_id = Id::from_id(crate::id::Id::from_u32(next_id));
+                let next_id = Id::from_id(self.next_id(runtime.current_revision()));
                 let old_value = self.value_map.insert(next_id, Box::new(data));
                 assert!(
                     old_value.is_none(),
                     "next_id is guaranteed to be unique, bar overflow"
                 );
+                if self.gc {
+                    self.last_used
+                        .insert(next_id, AtomicCell::new(runtime.current_revision()));
+                }
                 entry.insert(next_id);
                 next_id
             }
         }
     }
 
+    /// Returns a new id: with `generations`, the id of a deleted or reclaimed value
+    /// with the next generation, if there is one whose slot has generations left.
+    ///
+    /// Once all the slots were used, slots are no longer reused, and new ids
+    /// continue above the ids of all the generations given so far.
+    fn next_id(&self, revision: Revision) -> crate::Id {
+        if !self.generations {
+            return crate::id::Id::from_u32(self.counter.fetch_add(1));
+        }
+
+        let mut free_ids = self.free_ids.lock();
+        let mut next_id = self.counter.load();
+        if next_id < 1 << GENERATION_SHIFT {
+            while let Some(id) = free_ids.pop() {
+                let id = id.as_id().as_u32();
+                let generation = id >> GENERATION_SHIFT;
+                if generation < MAX_GENERATION {
+                    let slot = id & ((1 << GENERATION_SHIFT) - 1);
+                    self.reused_slots.insert(slot, (generation + 1, revision));
+                    return crate::Id::from_u32(id + (1 << GENERATION_SHIFT));
+                }
+            }
+        } else {
+            free_ids.clear();
+        }
+
+        if next_id == 1 << GENERATION_SHIFT {
+            let generation = self
+                .reused_slots
+                .iter()
+                .map(|entry| entry.value().0)
+                .max()
+                .unwrap_or(0);
+            next_id = (generation + 1) << GENERATION_SHIFT;
+        }
+        self.counter.store(next_id + 1);
+        crate::id::Id::from_u32(next_id)
+    }
+
+    /// Reports a read of the table or, with `gc`, of the value of `id`,
+    /// which is marked as used in the current revision.
+    fn report_read(&self, runtime: &Runtime, id: Option<Id>) {
+        match id {
+            Some(id) if self.gc => {
+                if let Some(last_used) = self.last_used.get(&id) {
+                    last_used.store(runtime.current_revision());
+                }
+                runtime.report_tracked_read(
+                    DependencyIndex {
+                        ingredient_index: self.ingredient_index,
+                        key_index: Some(id.as_id()),
+                    },
+                    Durability::LOW,
+                    self.reset_at,
+                );
+            }
+            _ => runtime.report_tracked_read(
+                DependencyIndex::for_table(self.ingredient_index),
+                Durability::MAX,
+                self.reset_at,
+            ),
+        }
+    }
+
+    /// Returns the id that `data` was interned with, if any, without interning it.
+    ///
+    /// `data` may be interned later, even in the current revision, so a `None`
+    /// result is reported as an untracked read: the query that observed it is
+    /// executed again in each new revision.
+    pub fn lookup(&self, runtime: &Runtime, data: &Data) -> Option<Id> {
+        let id = self.key_map.get(data).map(|id| *id);
+        match id {
+            Some(_) => self.report_read(runtime, id),
+            None => runtime.report_untracked_read(),
+        }
+        id
+    }
+
     pub(crate) fn reset_at(&self) -> Revision {
         self.reset_at
     }
@@ -116,21 +286,25 @@ where
         self.reset_at = revision;
         self.key_map.clear();
         self.value_map.clear();
+        self.last_used.clear();
     }
 
     #[track_caller]
     pub fn data<'db>(&'db self, runtime: &'db Runtime, id: Id) -> &'db Data {
-        runtime.report_tracked_read(
-            DependencyIndex::for_table(self.ingredient_index),
-            Durability::MAX,
-            self.reset_at,
-        );
+        self.report_read(runtime, Some(id));
 
         let data = match self.value_map.get(&id) {
             Some(d) => d,
-            None => {
-                panic!("no data found for id `{:?}`", id)
-            }
+            None => match self.recreated_at(id) {
+                Some(revision) => panic!(
+                    "`{}({})` is stale: the entity was recreated in revision {}, \
+                     with the next generation of its id",
+                    self.debug_name,
+                    id.as_id().as_u32(),
+                    revision.as_usize(),
+                ),
+                None => panic!("no data found for id `{:?}`", id),
+            },
         };
 
         // Unsafety clause:
@@ -139,11 +313,31 @@ where
         unsafe { transmute_lifetime(self, &**data) }
     }
 
+    /// With `generations`, if the slot of `id` was given to a new value since `id`
+    /// was deleted or reclaimed, returns the revision in which it was.
+    pub(crate) fn recreated_at(&self, id: Id) -> Option<Revision> {
+        let id = id.as_id().as_u32();
+        let slot = id & ((1 << GENERATION_SHIFT) - 1);
+        let (generation, revision) = *self.reused_slots.get(&slot)?;
+        (id >> GENERATION_SHIFT < generation).then_some(revision)
+    }
+
+    /// Calls `op` with the data of `id`, if it was not deleted, without
+    /// recording a read.
+    pub(crate) fn with_data<R>(&self, id: Id, op: impl FnOnce(&Data) -> R) -> Option<R> {
+        self.value_map.get(&id).map(|data| op(&data))
+    }
+
     /// Get the ingredient index for this table.
     pub(super) fn ingredient_index(&self) -> IngredientIndex {
         self.ingredient_index
     }
 
+    /// Returns the ids of the values currently interned.
+    pub(crate) fn ids(&self) -> Vec<Id> {
+        self.value_map.iter().map(|entry| *entry.key()).collect()
+    }
+
     /// Deletes an index from the interning table, making it available for re-use.
     ///
     /// # Warning
@@ -174,10 +368,36 @@ where
         // the last time an `&mut self` method was called. But that'd take extra storage
         // and doesn't obviously seem worth it.
         self.deleted_entries.push(key);
+        if self.generations {
+            self.deleted_ids.push(id);
+        }
     }
 
+    /// Frees the data deleted in the last revision and, with `generations`,
+    /// the slots of their ids.
     pub(crate) fn clear_deleted_indices(&mut self) {
         std::mem::take(&mut self.deleted_entries);
+        let free_ids = self.free_ids.get_mut();
+        while let Some(id) = self.deleted_ids.pop() {
+            free_ids.push(id);
+        }
+    }
+
+    /// With `gc`, reclaims the values last used before `revision`.
+    /// No references to them remain, since this takes `&mut self`.
+    fn collect_unused(&mut self, revision: Revision) {
+        let unused: Vec<Id> = self
+            .last_used
+            .iter()
+            .filter(|entry| entry.value().load() < revision)
+            .map(|entry| *entry.key())
+            .collect();
+        for id in unused {
+            self.last_used.remove(&id);
+            let (_, data) = self.value_map.remove(&id).unwrap();
+            self.key_map.remove(&*data);
+            self.free_ids.get_mut().push(id);
+        }
     }
 }
 
@@ -193,13 +413,29 @@ impl<DB: ?Sized, Id, Data> Ingredient<DB> for InternedIngredient<Id, Data>
 where
     Id: InternedId,
     Data: InternedData,
+    DB: Database,
 {
     fn ingredient_index(&self) -> IngredientIndex {
         self.ingredient_index
     }
 
-    fn maybe_changed_after(&self, _db: &DB, _input: DependencyIndex, revision: Revision) -> bool {
-        revision < self.reset_at
+    fn maybe_changed_after(&self, db: &DB, input: DependencyIndex, revision: Revision) -> bool {
+        if revision < self.reset_at {
+            return true;
+        }
+
+        // With `gc`, a query that depends on a value is validated only if the value
+        // was not reclaimed; then, the value is used in the current revision.
+        match input.key_index {
+            Some(id) if self.gc => match self.last_used.get(&Id::from_id(id)) {
+                Some(last_used) => {
+                    last_used.store(db.runtime().current_revision());
+                    false
+                }
+                None => true,
+            },
+            _ => false,
+        }
     }
 
     fn cycle_recovery_strategy(&self) -> crate::cycle::CycleRecoveryStrategy {
@@ -241,10 +477,82 @@ where
         panic!("unexpected call to `reset_for_new_revision`")
     }
 
+    fn sweep_edges(
+        &self,
+        _revision: Revision,
+        _op: &mut dyn FnMut(crate::Id, bool, &mut dyn Iterator<Item = DependencyIndex>),
+    ) {
+    }
+
+    fn sweep(&mut self, revision: Revision, _reachable: &FxHashSet<DatabaseKeyIndex>) {
+        if self.gc {
+            self.collect_unused(revision);
+        }
+    }
+
+    fn release_weak_memos(&mut self) {}
+
+    fn unshare(&mut self) -> bool {
+        true
+    }
+
+    fn memoized_keys(&self) -> Vec<crate::Id> {
+        vec![]
+    }
+
+    fn query_metrics(&self) -> Option<QueryMetrics> {
+        None
+    }
+
+    fn memory_usage(&self) -> Option<IngredientMemory> {
+        None
+    }
+
+    fn memo_count(&self) -> Option<usize> {
+        Some(self.value_map.len())
+    }
+
+    fn dump(&self) -> IngredientDump {
+        IngredientDump {
+            entries: self.value_map.len(),
+            ..Default::default()
+        }
+    }
+
+    fn can_fetch_dyn(&self) -> bool {
+        false
+    }
+
+    fn fetch_dyn(&self, _db: &DB, _key: crate::Id) -> Option<Box<dyn Any>> {
+        None
+    }
+
+    fn history_start(&self) -> Option<Revision> {
+        None
+    }
+
+    fn revert_to(&mut self, _runtime: &mut Runtime, _revision: Revision) {}
+
+    fn changed_since(&self, _revision: Revision) -> Vec<crate::Id> {
+        vec![]
+    }
+
+    fn key_type(&self) -> Option<TypeId> {
+        None
+    }
+
+    fn creator(&self, _key_index: crate::Id) -> Option<DatabaseKeyIndex> {
+        None
+    }
+
     fn salsa_struct_deleted(&self, _db: &DB, _id: crate::Id) {
         panic!("unexpected call: interned ingredients do not register for salsa struct deletion events");
     }
 
+    fn debug_name(&self) -> &'static str {
+        self.debug_name
+    }
+
     fn fmt_index(&self, index: Option<crate::Id>, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt_index(self.debug_name, index, fmt)
     }
@@ -268,6 +576,10 @@ impl<Id: AsId> IdentityInterner<Id> {
         IdentityInterner { data: PhantomData }
     }
 
+    pub fn fork(&self) -> Option<Self> {
+        Some(IdentityInterner { data: PhantomData })
+    }
+
     pub fn intern(&self, _runtime: &Runtime, id: Id) -> Id {
         id
     }
@@ -1,24 +1,107 @@
+use std::any::Any;
+
 use crate::{
-    storage::{HasJar, JarFromJars},
-    Database, DbWithJar,
+    storage::{HasJar, HasJars, IngredientsFor},
+    Database,
 };
 
 use super::routes::Routes;
 
 /// Representative trait of a salsa jar
+pub trait Jar<'db>: Sized {
+    type DynDb: ?Sized + HasJar<Self> + Database + 'db;
+}
+
+/// Connects a jar to a database type `DB` that contains it,
+/// either directly or because the jar is included by another jar.
 ///
 /// # Safety
 ///
 /// `init_jar` must fully initialize the jar
-pub unsafe trait Jar<'db>: Sized {
-    type DynDb: ?Sized + HasJar<Self> + Database + 'db;
+pub unsafe trait JarInDb<DB: ?Sized>: Any {
+    /// Upcasts the database to the `DynDb` type of this jar.
+    fn as_jar_db<'db>(db: &'db DB) -> &'db <Self as Jar<'db>>::DynDb
+    where
+        Self: Jar<'db>;
 
-    /// Initializes the jar at `place`
+    /// Initializes the jar at `place`, along with the jars it includes.
+    /// Use [`init_jar`] instead of calling this directly.
     ///
     /// # Safety
     ///
     /// `place` must be a valid pointer to this jar
-    unsafe fn init_jar<DB>(place: *mut Self, routes: &mut Routes<DB>)
+    unsafe fn init_jar(place: *mut Self, routes: &mut Routes<DB>)
     where
-        DB: JarFromJars<Self> + DbWithJar<Self>;
+        DB: HasJars;
+
+    /// Copies the jar, along with the jars it includes, for a pipelined write.
+    /// See [`Storage::set_pipelined_writes`](`crate::storage::Storage::set_pipelined_writes`).
+    fn fork_jar(&self) -> Option<Self>
+    where
+        Self: Sized;
+}
+
+/// Implemented by a jar for the `K`-th jar that it includes.
+/// `#[salsa::db]` uses it to reach the jars included by the jars it lists.
+pub trait IncludedJar<const K: usize> {
+    type Jar;
+
+    fn included(&self) -> &Self::Jar;
+
+    fn included_mut(&mut self) -> &mut Self::Jar;
+}
+
+/// Returns `db` as the database type of the jar `J`, to call the functions of `J`
+/// from code that is generic over the database. The `J: JarInDb<DB>` bound can
+/// be added with `#[salsa::requires(J)]`.
+pub fn db_for_jar<'db, J, DB>(db: &'db DB) -> &'db <J as Jar<'db>>::DynDb
+where
+    DB: ?Sized,
+    J: JarInDb<DB> + Jar<'db>,
+{
+    J::as_jar_db(db)
+}
+
+/// Implemented by a jar for itself: a salsa item can only be listed in the jar
+/// `J` if the jar it is declared for, `<I as IngredientsFor>::Jar`, is `J`.
+#[diagnostic::on_unimplemented(
+    message = "a salsa item declared for the jar `{Self}` is listed in the jar `{J}`",
+    label = "declared for `{Self}`",
+    note = "salsa items belong to `crate::Jar` unless they have a `jar = ...` option"
+)]
+pub trait SameJar<J> {}
+
+impl<J> SameJar<J> for J {}
+
+/// Called by `#[salsa::jar]` for each item `I` of the jar `J`, with the span of the item,
+/// so that listing something other than a salsa item of `J` is reported there rather
+/// than in the generated code.
+pub fn assert_in_jar<I, J>()
+where
+    I: IngredientsFor,
+    I::Jar: SameJar<J>,
+{
+}
+
+/// Initializes the jar `J` at `place`.
+///
+/// A jar may be part of the database several times, e.g. when it is included by
+/// two different jars. Only the first copy, depth-first, is registered in `routes`,
+/// and returned by `HasJar::jar`; the others are initialized but never used.
+///
+/// # Safety
+///
+/// `place` must be a valid pointer to the jar
+pub unsafe fn init_jar<DB, J>(place: *mut J, routes: &mut Routes<DB>)
+where
+    DB: HasJars,
+    J: JarInDb<DB>,
+{
+    if routes.add_jar::<J>() {
+        let outer_jar = routes.set_current_jar(std::any::type_name::<J>());
+        J::init_jar(place, routes);
+        routes.set_current_jar(outer_jar);
+    } else {
+        J::init_jar(place, &mut Routes::new());
+    }
 }
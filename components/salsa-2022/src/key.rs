@@ -7,6 +7,7 @@ use crate::{Database, DebugWithDb, Id, IngredientIndex};
 /// equatable but those orderings are arbitrary, and meant to be used only for
 /// inserting into maps and the like.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DependencyIndex {
     pub(crate) ingredient_index: IngredientIndex,
     pub(crate) key_index: Option<Id>,
@@ -34,6 +35,21 @@ impl DependencyIndex {
     }
 }
 
+/// A [`DependencyIndex`] that names its ingredient rather than numbering it, so that
+/// it can be stored or sent to another process, which may create the ingredients in
+/// a different order. See [`Database::stable_key`](`crate::Database::stable_key`).
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StableKey {
+    /// The stable name of the ingredient, e.g. `my_crate::Jar::my_query`.
+    /// See [`Storage::stable_name`](`crate::storage::Storage::stable_name`).
+    pub ingredient: String,
+
+    /// The id of the key. Ids are handed out in order, so those of salsa structs
+    /// only match across processes that create them in the same order.
+    pub key: Option<Id>,
+}
+
 impl<Db> crate::debug::DebugWithDb<Db> for DependencyIndex
 where
     Db: ?Sized + Database,
@@ -53,6 +69,7 @@ where
 /// that is actively executing. In that case, the `key_index` cannot be
 /// None.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DatabaseKeyIndex {
     pub(crate) ingredient_index: IngredientIndex,
     pub(crate) key_index: Id,
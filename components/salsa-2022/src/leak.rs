@@ -0,0 +1,137 @@
+//! Detects the ingredients whose memos pile up, which usually means that their keys
+//! leak: e.g., a tracked function keyed by source positions, which change with each
+//! edit, keeps a memo (and an interned key) for every position it was ever called with.
+//!
+//! Enable the check with [`Storage::set_leak_check`](`crate::storage::Storage::set_leak_check`).
+//! The leaks of a database are returned by [`Storage::leak_report`](`crate::storage::Storage::leak_report`),
+//! so that a test can assert that there are none, and logged as warnings when it is dropped.
+//! Only available in debug builds.
+
+use std::fmt;
+
+/// An ingredient whose number of memos (or of interned values, for an interned
+/// ingredient such as the keys of a tracked function taking several arguments)
+/// grew in many revisions and never shrank, as returned by
+/// [`Storage::leak_report`](`crate::storage::Storage::leak_report`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Leak {
+    /// The name of the jar containing the ingredient, e.g. `my_crate::Jar`.
+    pub jar: &'static str,
+
+    /// The name of the ingredient, e.g. `my_query`.
+    pub ingredient: &'static str,
+
+    /// True if the ingredient stores interned values rather than memos, e.g. the keys
+    /// of a tracked function taking several arguments (which has the same name).
+    pub interned: bool,
+
+    /// In how many revisions the number of memos grew.
+    pub revisions: usize,
+
+    /// The number of memos when it started growing.
+    pub from: usize,
+
+    /// The number of memos when the report was made.
+    pub to: usize,
+}
+
+impl fmt::Display for Leak {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries = if self.interned {
+            "interned values"
+        } else {
+            "memos"
+        };
+        write!(
+            f,
+            "{}::{}: grew from {} to {} {} over {} revisions",
+            self.jar, self.ingredient, self.from, self.to, entries, self.revisions,
+        )
+    }
+}
+
+/// The number of memos of each ingredient, sampled at the start of each revision.
+#[derive(Clone, Debug)]
+pub(crate) struct LeakCheck {
+    /// The number of revisions in which the memos of an ingredient must grow
+    /// for it to be reported.
+    revisions: usize,
+
+    /// Indexed by ingredient; `None` for the ingredients that have no memos.
+    growths: Vec<Option<Growth>>,
+}
+
+#[derive(Clone, Debug)]
+struct Growth {
+    /// The number of memos when they last shrank (or were first counted).
+    from: usize,
+
+    /// The number of memos when last counted.
+    last: usize,
+
+    /// In how many revisions the number of memos grew since `from`.
+    revisions: usize,
+}
+
+impl LeakCheck {
+    pub(crate) fn new(revisions: usize) -> Self {
+        LeakCheck {
+            revisions,
+            growths: vec![],
+        }
+    }
+
+    /// Records the number of memos of each ingredient, in the order of their indices.
+    pub(crate) fn record(&mut self, counts: impl Iterator<Item = Option<usize>>) {
+        for (index, count) in counts.enumerate() {
+            if index == self.growths.len() {
+                self.growths.push(None);
+            }
+            let Some(count) = count else {
+                continue;
+            };
+            match &mut self.growths[index] {
+                Some(growth) if count > growth.last => {
+                    growth.revisions += 1;
+                    growth.last = count;
+                }
+                Some(growth) if count == growth.last => {}
+                growth => {
+                    *growth = Some(Growth {
+                        from: count,
+                        last: count,
+                        revisions: 0,
+                    })
+                }
+            }
+        }
+    }
+
+    /// Returns the ingredients whose memos grew in at least as many revisions as
+    /// required, given a function returning, for the index of an ingredient, the
+    /// name of its jar, its own name, and whether it is interned.
+    pub(crate) fn report(
+        &self,
+        describe: impl Fn(usize) -> (&'static str, &'static str, bool),
+    ) -> Vec<Leak> {
+        self.growths
+            .iter()
+            .enumerate()
+            .filter_map(|(index, growth)| {
+                let growth = growth.as_ref()?;
+                if growth.revisions < self.revisions {
+                    return None;
+                }
+                let (jar, ingredient, interned) = describe(index);
+                Some(Leak {
+                    jar,
+                    ingredient,
+                    interned,
+                    revisions: growth.revisions,
+                    from: growth.from,
+                    to: growth.last,
+                })
+            })
+            .collect()
+    }
+}
@@ -1,12 +1,24 @@
+// Lets the macros, whose expansion refers to `salsa::`, be used in this crate (see `string`).
+extern crate self as salsa;
+
 pub mod accumulator;
+#[cfg(feature = "parallel")]
+pub mod background;
 pub mod cancelled;
+pub mod component;
 pub mod cycle;
 pub mod database;
 pub mod debug;
+pub mod dependency_graph;
+pub mod dump;
 pub mod durability;
+#[cfg(feature = "debug-ids")]
+pub mod entity_id;
 pub mod event;
+pub mod external;
 pub mod function;
 pub mod hash;
+pub mod history;
 pub mod id;
 pub mod ingredient;
 pub mod ingredient_list;
@@ -15,39 +27,80 @@ pub mod input_field;
 pub mod interned;
 pub mod jar;
 pub mod key;
+#[cfg(debug_assertions)]
+pub mod leak;
+pub mod list;
+pub mod memory;
+pub mod metrics;
+#[cfg(feature = "parallel")]
+pub mod par_map;
 pub mod plumbing;
+pub mod progress;
+#[cfg(feature = "remote-cache")]
+pub mod remote_cache;
+#[cfg(feature = "replay")]
+pub mod replay;
 pub mod revision;
 pub mod routes;
 pub mod runtime;
 pub mod salsa_struct;
 pub mod setter;
+pub mod shared;
 pub mod storage;
+pub mod string;
+pub mod subscription;
+mod sync;
+pub mod testing;
+mod trace;
 #[doc(hidden)]
 pub mod tracked_struct;
+pub mod update;
 
+pub use self::cancelled::CancellationToken;
 pub use self::cancelled::Cancelled;
 pub use self::cycle::Cycle;
 pub use self::database::Database;
+#[cfg(feature = "parallel")]
 pub use self::database::ParallelDatabase;
+#[cfg(feature = "parallel")]
 pub use self::database::Snapshot;
+#[cfg(feature = "parallel")]
+pub use self::database::SyncDatabase;
 pub use self::debug::DebugWith;
 pub use self::debug::DebugWithDb;
 pub use self::durability::Durability;
+#[cfg(feature = "debug-ids")]
+pub use self::entity_id::EntityId;
 pub use self::event::Event;
 pub use self::event::EventKind;
 pub use self::id::AsId;
 pub use self::id::Id;
 pub use self::key::DatabaseKeyIndex;
+pub use self::list::List;
+pub use self::memory::HeapSize;
+#[cfg(feature = "parallel")]
+pub use self::par_map::par_map;
 pub use self::revision::Revision;
+pub use self::revision::RevisionToken;
 pub use self::routes::IngredientIndex;
 pub use self::runtime::Runtime;
+pub use self::shared::shared;
+pub use self::shared::Shared;
 pub use self::storage::DbWithJar;
 pub use self::storage::Storage;
+pub use self::string::Str;
 pub use self::tracked_struct::TrackedStructData;
 pub use self::tracked_struct::TrackedStructId;
+pub use self::update::Update;
 pub use salsa_2022_macros::accumulator;
+pub use salsa_2022_macros::component;
 pub use salsa_2022_macros::db;
+pub use salsa_2022_macros::delegate_db;
 pub use salsa_2022_macros::input;
 pub use salsa_2022_macros::interned;
 pub use salsa_2022_macros::jar;
+pub use salsa_2022_macros::progress;
+pub use salsa_2022_macros::requires;
 pub use salsa_2022_macros::tracked;
+pub use salsa_2022_macros::HeapSize;
+pub use salsa_2022_macros::Update;
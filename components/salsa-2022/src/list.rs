@@ -0,0 +1,112 @@
+//! A list returned by a tracked function, whose elements can each be read
+//! by a query of their own.
+//!
+//! A function reading one element of a large list that it gets from another
+//! function is executed again whenever any element changes, since it depends on
+//! the whole list. The `elements = <name>` option of a tracked function returning
+//! a [`List`] generates a tracked function `<name>`, taking the same arguments
+//! followed by an index, which returns the element at that index. Its value is
+//! backdated when the element is unchanged, so the functions calling it are only
+//! executed again for the elements that changed: appending an element to a list
+//! of thousands only executes again the functions reading the new one.
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::Arc;
+
+use crate::update::Update;
+use crate::HeapSize;
+
+/// An immutable list, which is cloned in O(1).
+///
+/// Two lists are compared element-wise, unless they share their elements.
+/// With the `update` option, the elements of the old value that are equal
+/// to those of the new one are kept.
+pub struct List<T>(Arc<Vec<T>>);
+
+impl<T> List<T> {
+    pub fn new() -> Self {
+        List(Arc::new(Vec::new()))
+    }
+}
+
+impl<T> Default for List<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for List<T> {
+    fn clone(&self) -> Self {
+        List(self.0.clone())
+    }
+}
+
+impl<T> Deref for List<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.0
+    }
+}
+
+impl<T> From<Vec<T>> for List<T> {
+    fn from(elements: Vec<T>) -> Self {
+        List(Arc::new(elements))
+    }
+}
+
+impl<T> FromIterator<T> for List<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        List(Arc::new(iter.into_iter().collect()))
+    }
+}
+
+impl<'a, T> IntoIterator for &'a List<T> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<T: PartialEq> PartialEq for List<T> {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0) || self.0 == other.0
+    }
+}
+
+impl<T: Eq> Eq for List<T> {}
+
+impl<T: Hash> Hash for List<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for List<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.0.iter()).finish()
+    }
+}
+
+/// The elements are copied first if the old list shares them,
+/// e.g. with a clone returned by the tracked function.
+impl<T: Update + Clone> Update for List<T> {
+    fn maybe_update(old_value: &mut Self, new_value: Self) -> bool {
+        if Arc::ptr_eq(&old_value.0, &new_value.0) {
+            return false;
+        }
+        let new_elements = Arc::try_unwrap(new_value.0).unwrap_or_else(|arc| (*arc).clone());
+        Vec::maybe_update(Arc::make_mut(&mut old_value.0), new_elements)
+    }
+}
+
+/// Like for `Arc`, the elements are counted in full by each clone.
+impl<T: HeapSize> HeapSize for List<T> {
+    fn heap_size(&self) -> usize {
+        self.0.heap_size()
+    }
+}
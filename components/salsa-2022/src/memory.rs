@@ -0,0 +1,262 @@
+//! Approximate memory usage of the memoized values, as returned by
+//! [`Database::memory_report`](`crate::Database::memory_report`).
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::mem::{size_of, size_of_val};
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::Id;
+
+/// Types that can tell how much heap memory they own.
+///
+/// This is used to report the memory used by the values of tracked functions
+/// with the `heap_size` option. It can be derived with `#[derive(salsa::HeapSize)]`,
+/// and is implemented for all salsa structs (whose data is owned by the database,
+/// not by the value).
+pub trait HeapSize {
+    /// Returns the number of bytes allocated on the heap by `self`,
+    /// not counting the `size_of_val(self)` bytes of `self` itself.
+    fn heap_size(&self) -> usize;
+}
+
+macro_rules! no_heap_impls {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl HeapSize for $ty {
+                fn heap_size(&self) -> usize {
+                    0
+                }
+            }
+        )*
+    };
+}
+
+no_heap_impls! {
+    (), bool, char, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64,
+    str, Id,
+}
+
+/// References point to memory owned by someone else.
+impl<T: ?Sized> HeapSize for &T {
+    fn heap_size(&self) -> usize {
+        0
+    }
+}
+
+impl HeapSize for String {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl HeapSize for PathBuf {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Option<T> {
+    fn heap_size(&self) -> usize {
+        self.as_ref().map_or(0, T::heap_size)
+    }
+}
+
+impl<T: HeapSize, E: HeapSize> HeapSize for Result<T, E> {
+    fn heap_size(&self) -> usize {
+        match self {
+            Ok(value) => value.heap_size(),
+            Err(error) => error.heap_size(),
+        }
+    }
+}
+
+impl<T: HeapSize> HeapSize for [T] {
+    fn heap_size(&self) -> usize {
+        self.iter().map(T::heap_size).sum()
+    }
+}
+
+impl<T: HeapSize, const N: usize> HeapSize for [T; N] {
+    fn heap_size(&self) -> usize {
+        self.iter().map(T::heap_size).sum()
+    }
+}
+
+impl<T: ?Sized + HeapSize> HeapSize for Box<T> {
+    fn heap_size(&self) -> usize {
+        size_of_val(&**self) + (**self).heap_size()
+    }
+}
+
+/// The shared value is counted in full by each `Arc`, so values sharing
+/// data are over-estimated.
+impl<T: ?Sized + HeapSize> HeapSize for Arc<T> {
+    fn heap_size(&self) -> usize {
+        size_of_val(&**self) + (**self).heap_size()
+    }
+}
+
+/// Like for `Arc`, the shared value is counted in full by each `Rc`.
+impl<T: ?Sized + HeapSize> HeapSize for Rc<T> {
+    fn heap_size(&self) -> usize {
+        size_of_val(&**self) + (**self).heap_size()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Vec<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<T>() + self[..].heap_size()
+    }
+}
+
+/// The hash tables are assumed to store their entries inline, ignoring the
+/// few control bytes per entry.
+impl<K: HeapSize, V: HeapSize, S> HeapSize for HashMap<K, V, S> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<(K, V)>()
+            + self
+                .iter()
+                .map(|(key, value)| key.heap_size() + value.heap_size())
+                .sum::<usize>()
+    }
+}
+
+impl<T: HeapSize, S> HeapSize for HashSet<T, S> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<T>() + self.iter().map(T::heap_size).sum::<usize>()
+    }
+}
+
+/// The B-trees are counted as if their nodes were full.
+impl<K: HeapSize, V: HeapSize> HeapSize for BTreeMap<K, V> {
+    fn heap_size(&self) -> usize {
+        self.len() * size_of::<(K, V)>()
+            + self
+                .iter()
+                .map(|(key, value)| key.heap_size() + value.heap_size())
+                .sum::<usize>()
+    }
+}
+
+impl<T: HeapSize> HeapSize for BTreeSet<T> {
+    fn heap_size(&self) -> usize {
+        self.len() * size_of::<T>() + self.iter().map(T::heap_size).sum::<usize>()
+    }
+}
+
+macro_rules! tuple_impls {
+    ($(($($name:ident $index:tt),*))*) => {
+        $(
+            impl<$($name: HeapSize),*> HeapSize for ($($name,)*) {
+                fn heap_size(&self) -> usize {
+                    0 $(+ self.$index.heap_size())*
+                }
+            }
+        )*
+    };
+}
+
+tuple_impls! {
+    (A 0)
+    (A 0, B 1)
+    (A 0, B 1, C 2)
+    (A 0, B 1, C 2, D 3)
+    (A 0, B 1, C 2, D 3, E 4)
+    (A 0, B 1, C 2, D 3, E 4, F 5)
+}
+
+/// The memory used by the memos of one tracked function (or of one field of a
+/// tracked struct), as returned by
+/// [`Storage::memory_report`](`crate::storage::Storage::memory_report`).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IngredientMemory {
+    /// The name of the jar containing the function, e.g. `my_crate::Jar`.
+    pub jar: &'static str,
+
+    /// The name of the function, e.g. `my_query`.
+    pub ingredient: &'static str,
+
+    /// How many memos are stored, including those whose value was evicted
+    /// (by the `lru` option or by [`Storage::sweep`](`crate::storage::Storage::sweep`)),
+    /// which only keep the dependencies of the value.
+    pub memos: usize,
+
+    /// How many memos still have their value.
+    pub values: usize,
+
+    /// The bytes used by the values themselves, i.e. `values * size_of::<Value>()`.
+    pub value_bytes: usize,
+
+    /// The bytes allocated on the heap by the values, as reported by their
+    /// [`HeapSize`] impl. `None` unless the function has the `heap_size` option.
+    pub heap_bytes: Option<usize>,
+}
+
+impl IngredientMemory {
+    /// The bytes used by the values, including those they allocated if known.
+    pub fn total_bytes(&self) -> usize {
+        self.value_bytes + self.heap_bytes.unwrap_or(0)
+    }
+}
+
+/// The memory used by the memoized values of each tracked function in the
+/// database, as returned by [`Database::memory_report`](`crate::Database::memory_report`).
+///
+/// The sizes are approximate: they leave out the bookkeeping of salsa itself
+/// (e.g., the dependencies of each memo), and the heap allocations of values
+/// are only known for functions with the `heap_size` option.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryReport {
+    /// One entry per function ingredient, in the order in which they were created.
+    pub ingredients: Vec<IngredientMemory>,
+}
+
+impl MemoryReport {
+    /// The bytes used by all values.
+    pub fn total_bytes(&self) -> usize {
+        self.ingredients
+            .iter()
+            .map(IngredientMemory::total_bytes)
+            .sum()
+    }
+
+    /// The bytes used by the values of each jar, in the order in which the jars were created.
+    pub fn jars(&self) -> Vec<(&'static str, usize)> {
+        let mut jars: Vec<(&'static str, usize)> = vec![];
+        for ingredient in &self.ingredients {
+            match jars.iter_mut().find(|(jar, _)| *jar == ingredient.jar) {
+                Some((_, bytes)) => *bytes += ingredient.total_bytes(),
+                None => jars.push((ingredient.jar, ingredient.total_bytes())),
+            }
+        }
+        jars
+    }
+}
+
+/// Prints one line per jar followed by one line per ingredient in that jar,
+/// with the number of values and the bytes they use.
+impl fmt::Display for MemoryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (jar, bytes) in self.jars() {
+            writeln!(f, "{jar}: {bytes} bytes")?;
+            for ingredient in self.ingredients.iter().filter(|i| i.jar == jar) {
+                write!(
+                    f,
+                    "    {}: {} values ({} memos), {} bytes",
+                    ingredient.ingredient,
+                    ingredient.values,
+                    ingredient.memos,
+                    ingredient.total_bytes(),
+                )?;
+                if ingredient.heap_bytes.is_none() {
+                    write!(f, " + unknown heap")?;
+                }
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
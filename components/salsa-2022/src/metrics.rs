@@ -0,0 +1,123 @@
+//! Per-query counters, only recorded when the `metrics` feature is enabled.
+
+use std::time::Duration;
+
+#[cfg(feature = "metrics")]
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Instant,
+};
+
+/// The counters recorded for one tracked function, as returned by
+/// [`Storage::query_metrics`](`crate::storage::Storage::query_metrics`).
+///
+/// The counters are summed over all the keys of the function and
+/// over all the revisions since the database was created.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct QueryMetrics {
+    /// The name of the function, e.g. `my_query`.
+    pub name: &'static str,
+
+    /// How many times the function was executed.
+    pub executions: u64,
+
+    /// The total time spent executing the function. This includes the time
+    /// spent in the queries it called, if they had to be executed too.
+    pub execution_time: Duration,
+
+    /// How many times a memoized value from an older revision was reused
+    /// after checking that its inputs had not changed, without executing the function.
+    pub validations: u64,
+
+    /// How many times the function was executed again and produced a value
+    /// equal to the old one, so that the value was backdated.
+    pub backdates: u64,
+}
+
+/// Records the [`QueryMetrics`] of a function ingredient.
+/// Without the `metrics` feature, this is empty and recording does nothing.
+#[derive(Default)]
+pub(crate) struct MetricsCounters {
+    #[cfg(feature = "metrics")]
+    executions: AtomicU64,
+
+    #[cfg(feature = "metrics")]
+    execution_nanos: AtomicU64,
+
+    #[cfg(feature = "metrics")]
+    validations: AtomicU64,
+
+    #[cfg(feature = "metrics")]
+    backdates: AtomicU64,
+}
+
+/// Returned by [`MetricsCounters::start_execution`], to be passed
+/// back to [`MetricsCounters::record_execution`].
+pub(crate) struct ExecutionTimer {
+    #[cfg(feature = "metrics")]
+    start: Instant,
+}
+
+#[cfg(feature = "metrics")]
+impl MetricsCounters {
+    pub(crate) fn start_execution(&self) -> ExecutionTimer {
+        ExecutionTimer {
+            start: Instant::now(),
+        }
+    }
+
+    pub(crate) fn record_execution(&self, timer: ExecutionTimer) {
+        let nanos = u64::try_from(timer.start.elapsed().as_nanos()).unwrap_or(u64::MAX);
+        self.executions.fetch_add(1, Ordering::Relaxed);
+        self.execution_nanos.fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_validation(&self) {
+        self.validations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_backdate(&self) {
+        self.backdates.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Copies the counters, for the jars of a pipelined write.
+    pub(crate) fn fork(&self) -> Self {
+        Self {
+            executions: AtomicU64::new(self.executions.load(Ordering::Relaxed)),
+            execution_nanos: AtomicU64::new(self.execution_nanos.load(Ordering::Relaxed)),
+            validations: AtomicU64::new(self.validations.load(Ordering::Relaxed)),
+            backdates: AtomicU64::new(self.backdates.load(Ordering::Relaxed)),
+        }
+    }
+
+    pub(crate) fn query_metrics(&self, name: &'static str) -> Option<QueryMetrics> {
+        Some(QueryMetrics {
+            name,
+            executions: self.executions.load(Ordering::Relaxed),
+            execution_time: Duration::from_nanos(self.execution_nanos.load(Ordering::Relaxed)),
+            validations: self.validations.load(Ordering::Relaxed),
+            backdates: self.backdates.load(Ordering::Relaxed),
+        })
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+impl MetricsCounters {
+    pub(crate) fn start_execution(&self) -> ExecutionTimer {
+        ExecutionTimer {}
+    }
+
+    pub(crate) fn record_execution(&self, _timer: ExecutionTimer) {}
+
+    pub(crate) fn record_validation(&self) {}
+
+    pub(crate) fn record_backdate(&self) {}
+
+    pub(crate) fn fork(&self) -> Self {
+        Self {}
+    }
+
+    pub(crate) fn query_metrics(&self, _name: &'static str) -> Option<QueryMetrics> {
+        None
+    }
+}
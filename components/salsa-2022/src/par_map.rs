@@ -0,0 +1,387 @@
+//! Fans the work of a query out over threads, see [`par_map`].
+//!
+//! A query cannot call [`ParallelDatabase::snapshot`] to run its sub-queries in
+//! parallel: the reads made on the snapshots would not be dependencies of the query,
+//! which would then not be executed again when they change. `par_map` takes the
+//! snapshots itself and adds the reads made on them to the query that called it.
+//!
+//! This requires the `parallel` feature, which is enabled by default.
+
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, OnceLock};
+use std::{fmt, mem, thread};
+
+use crate::jar::{Jar, JarInDb};
+use crate::key::DatabaseKeyIndex;
+use crate::runtime::{ForkedDependencies, PendingWork};
+use crate::storage::HasJarsDyn;
+use crate::sync::{Condvar, Mutex, MutexGuard};
+use crate::{Database, ParallelDatabase, Runtime, Snapshot};
+
+/// Lets the functions of the jar `J`, which take the `&dyn Db` of the jar, call
+/// [`par_map`]: list `salsa::par_map::ParallelDb<Jar>` among the supertraits of `Db`.
+///
+/// Implemented by the databases that implement [`ParallelDatabase`]. Implement `Db`
+/// for the database type itself (`impl Db for MyDatabase {}`): a blanket impl of `Db`
+/// for the types implementing `ParallelDb<Jar>` is recursive, since the database
+/// must implement `Db` to implement `ParallelDb<Jar>`.
+pub trait ParallelDb<J: for<'db> Jar<'db>> {
+    /// Returns a snapshot of the database, even from inside a query.
+    /// Only called by [`par_map`].
+    fn fork_db(&self) -> Box<dyn ForkedDb<J>>;
+}
+
+/// A snapshot of the database returned by [`ParallelDb::fork_db`].
+pub trait ForkedDb<J: for<'db> Jar<'db>>: Send {
+    fn db(&self) -> &<J as Jar<'_>>::DynDb;
+}
+
+impl<DB, J> ParallelDb<J> for DB
+where
+    DB: ParallelDatabase + 'static,
+    J: for<'db> Jar<'db> + JarInDb<DB>,
+{
+    fn fork_db(&self) -> Box<dyn ForkedDb<J>> {
+        Box::new(self.snapshot())
+    }
+}
+
+impl<DB, J> ForkedDb<J> for Snapshot<DB>
+where
+    DB: ParallelDatabase,
+    J: for<'db> Jar<'db> + JarInDb<DB>,
+{
+    fn db(&self) -> &<J as Jar<'_>>::DynDb {
+        J::as_jar_db(&**self)
+    }
+}
+
+/// Calls `op` for each of `items` on other threads and returns the results, in the
+/// order of `items`. Called from a tracked function, the reads made by `op` are
+/// dependencies of the function, as if it had called `op` itself: it is executed
+/// again in a new revision if one of the inputs that `op` read changed.
+///
+/// The items are split into chunks, run by the calling thread, by the threads of a
+/// pool shared by all the calls of `par_map` (one fewer than the machine has cores,
+/// started on first use), and by the threads blocked on a query that the calling
+/// thread executes (or on a query blocked on it, and so on): rather than waiting
+/// idly for the query, they help finishing it.
+///
+/// The chunks run on snapshots of `db`, which share its cancellation token and
+/// timeout and, from inside a query, its permit to execute queries (see
+/// [`Storage::set_max_concurrent_queries`](`crate::storage::Storage::set_max_concurrent_queries`)).
+/// If `op` unwinds on one of them, e.g. because the revision was cancelled, the
+/// chunks that did not start are dropped and `par_map` unwinds with the same
+/// payload once the others are done.
+///
+/// # Panics
+///
+/// If `op` creates tracked structs, specifies the value of tracked functions or
+/// pushes accumulated values.
+///
+/// # Deadlocks
+///
+/// The queries executing on the thread that called `par_map` are not on the query
+/// stacks of the threads running `op`: if `op` calls one of them, directly or not,
+/// the cycle may not be detected, and the threads then wait for each other forever.
+pub fn par_map<J, Db, T, R>(
+    db: &Db,
+    items: Vec<T>,
+    op: impl for<'db> Fn(&'db <J as Jar<'db>>::DynDb, T) -> R + Sync,
+) -> Vec<R>
+where
+    J: for<'db> Jar<'db>,
+    Db: ?Sized + Database + ParallelDb<J>,
+    T: Send,
+    R: Send,
+{
+    if items.is_empty() {
+        return Vec::new();
+    }
+    let runtime = db.runtime();
+    let parent = runtime
+        .active_query()
+        .map(|(database_key_index, _)| database_key_index);
+
+    // Make more chunks than threads, so that blocked threads find some left to run,
+    // and a snapshot for each thread of the pool that helps, plus a spare one for them.
+    let pool = Pool::get();
+    let chunk_len = items.len().div_ceil((pool.threads + 1) * 4);
+    let mut items = items.into_iter().peekable();
+    let mut chunks = VecDeque::new();
+    while items.peek().is_some() {
+        chunks.push_back((
+            chunks.len(),
+            items.by_ref().take(chunk_len).collect::<Vec<_>>(),
+        ));
+    }
+    let helpers = pool.threads.min(chunks.len() - 1);
+    let forks = chunks.len().min(helpers + 2);
+    let forks: Vec<_> = runtime.fork_for_par_map(|| (0..forks).map(|_| db.fork_db()).collect());
+
+    let work = Arc::new(Work {
+        parent,
+        op: &op,
+        state: Mutex::new(WorkState {
+            results: chunks.iter().map(|_| None).collect(),
+            pending: chunks.len(),
+            chunks,
+            forks,
+        }),
+        chunk_done: Condvar::new(),
+    });
+    let registered = Registered::new(runtime, &work, helpers);
+    work.run();
+    drop(registered);
+
+    // Add the dependencies in the order of `items`, as if `op` had been called
+    // on each of them in turn.
+    let results = mem::take(&mut work.state.lock().results);
+    let mut values = Vec::new();
+    for result in results {
+        let (chunk_values, dependencies) = result
+            .unwrap()
+            .unwrap_or_else(|payload| panic::resume_unwind(payload));
+        if let Some(dependencies) = dependencies {
+            runtime.add_forked_dependencies(dependencies);
+        }
+        values.extend(chunk_values);
+    }
+    values
+}
+
+/// The values returned by `op` for the items of a chunk and the reads it made,
+/// or the payload it unwound with.
+type ChunkResult<R> = thread::Result<(Vec<R>, Option<ForkedDependencies>)>;
+
+/// A call of [`par_map`].
+struct Work<'a, J: for<'db> Jar<'db>, T, R, F> {
+    /// The query that called `par_map`, if any.
+    parent: Option<DatabaseKeyIndex>,
+    op: &'a F,
+    state: Mutex<WorkState<J, T, R>>,
+
+    /// Notified whenever a chunk is done.
+    chunk_done: Condvar,
+}
+
+struct WorkState<J: for<'db> Jar<'db>, T, R> {
+    /// The chunks that no thread runs yet, with their index.
+    chunks: VecDeque<(usize, Vec<T>)>,
+
+    /// The snapshots that no thread runs a chunk on.
+    forks: Vec<Box<dyn ForkedDb<J>>>,
+
+    /// The result of each chunk, once done.
+    results: Vec<Option<ChunkResult<R>>>,
+
+    /// The number of chunks not done yet, running or not.
+    pending: usize,
+}
+
+impl<J, T, R, F> Work<'_, J, T, R, F>
+where
+    J: for<'db> Jar<'db>,
+    T: Send,
+    R: Send,
+    F: for<'db> Fn(&'db <J as Jar<'db>>::DynDb, T) -> R + Sync,
+{
+    /// Runs the chunks until all of them are done, by this thread or others.
+    fn run(&self) {
+        let mut state = self.state.lock();
+        while state.pending > 0 {
+            if !state.chunks.is_empty() && !state.forks.is_empty() {
+                MutexGuard::unlocked(&mut state, || self.help());
+            } else {
+                self.chunk_done.wait(&mut state);
+            }
+        }
+    }
+}
+
+impl<J: for<'db> Jar<'db>, T, R, F> Work<'_, J, T, R, F> {
+    /// Drops the chunks that no thread runs yet and waits for the others.
+    /// Once this returns, no thread touches the items or `op` anymore.
+    fn abandon(&self) {
+        let mut state = self.state.lock();
+        let abandoned = state.chunks.len();
+        state.chunks.clear();
+        state.pending -= abandoned;
+        while state.pending > 0 {
+            self.chunk_done.wait(&mut state);
+        }
+        let forks = mem::take(&mut state.forks);
+        drop(state);
+        drop(forks);
+    }
+}
+
+impl<J, T, R, F> PendingWork for Work<'_, J, T, R, F>
+where
+    J: for<'db> Jar<'db>,
+    T: Send,
+    R: Send,
+    F: for<'db> Fn(&'db <J as Jar<'db>>::DynDb, T) -> R + Sync,
+{
+    fn has_work(&self) -> bool {
+        let state = self.state.lock();
+        !state.chunks.is_empty() && !state.forks.is_empty()
+    }
+
+    fn help(&self) -> bool {
+        let (index, chunk, fork) = {
+            let mut state = self.state.lock();
+            if state.chunks.is_empty() || state.forks.is_empty() {
+                return false;
+            }
+            let (index, chunk) = state.chunks.pop_front().unwrap();
+            (index, chunk, state.forks.pop().unwrap())
+        };
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let db = fork.db();
+            db.runtime().run_forked(self.parent, || {
+                chunk
+                    .into_iter()
+                    .map(|item| (self.op)(db, item))
+                    .collect::<Vec<_>>()
+            })
+        }));
+
+        let mut state = self.state.lock();
+        if result.is_err() {
+            // `par_map` unwinds anyway: don't run the chunks left.
+            let abandoned = state.chunks.len();
+            state.chunks.clear();
+            state.pending -= abandoned;
+        }
+        state.results[index] = Some(result);
+        state.forks.push(fork);
+        state.pending -= 1;
+        self.chunk_done.notify_all();
+        true
+    }
+}
+
+impl<J: for<'db> Jar<'db>, T, R, F> fmt::Debug for Work<'_, J, T, R, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Work")
+            .field("parent", &self.parent)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Lets the threads of the pool and those blocked on the runtime that called
+/// [`par_map`] run the chunks of `work` until dropped. Dropping it waits until
+/// these threads no longer hold `work`, which borrows `op` and the items.
+struct Registered<'a, 'w, J: for<'db> Jar<'db>, T, R, F> {
+    runtime: &'a Runtime,
+    work: &'a Arc<Work<'w, J, T, R, F>>,
+    erased: Arc<dyn PendingWork>,
+}
+
+impl<'a, 'w, J, T, R, F> Registered<'a, 'w, J, T, R, F>
+where
+    J: for<'db> Jar<'db> + 'w,
+    T: Send + 'w,
+    R: Send + 'w,
+    F: for<'db> Fn(&'db <J as Jar<'db>>::DynDb, T) -> R + Sync,
+{
+    fn new(runtime: &'a Runtime, work: &'a Arc<Work<'w, J, T, R, F>>, helpers: usize) -> Self {
+        let erased: Arc<dyn PendingWork + 'w> = work.clone();
+        // SAFETY: `Registered::drop` withdraws `erased` from the pool and the
+        // dependency graph, then waits until the threads that took a clone of it
+        // dropped theirs, before `'w` ends.
+        let erased =
+            unsafe { mem::transmute::<Arc<dyn PendingWork + 'w>, Arc<dyn PendingWork>>(erased) };
+        runtime.add_pending_work(erased.clone());
+        Pool::get().submit(&erased, helpers);
+        Registered {
+            runtime,
+            work,
+            erased,
+        }
+    }
+}
+
+impl<J: for<'db> Jar<'db>, T, R, F> Drop for Registered<'_, '_, J, T, R, F> {
+    fn drop(&mut self) {
+        Pool::get().withdraw(&self.erased);
+        self.runtime.remove_pending_work(&self.erased);
+        self.work.abandon();
+
+        // No chunk is left, so the threads still holding a clone of `erased` are
+        // about to find none and drop it. The other two references are `erased`
+        // itself and the `work` of `par_map`.
+        while Arc::strong_count(self.work) > 2 {
+            thread::yield_now();
+        }
+    }
+}
+
+/// The threads running the chunks of the calls of [`par_map`], shared by all of
+/// them, so that calls made from many queries (or from `op`) don't start threads
+/// of their own. The calling thread runs chunks too, so there is one fewer thread
+/// than the machine has cores.
+struct Pool {
+    threads: usize,
+
+    /// The calls of `par_map` to help with, once for each thread that should.
+    queue: Mutex<VecDeque<Arc<dyn PendingWork>>>,
+
+    /// Notified when calls are queued.
+    work_queued: Condvar,
+}
+
+impl Pool {
+    /// Returns the pool, starting its threads on first use.
+    fn get() -> &'static Pool {
+        static POOL: OnceLock<Pool> = OnceLock::new();
+        POOL.get_or_init(|| {
+            let threads = thread::available_parallelism().map_or(1, |n| n.get()) - 1;
+            for _ in 0..threads {
+                // The threads wait for the pool to be initialized.
+                thread::spawn(|| Pool::get().work());
+            }
+            Pool {
+                threads,
+                queue: Mutex::new(VecDeque::new()),
+                work_queued: Condvar::new(),
+            }
+        })
+    }
+
+    /// Lets `helpers` threads of the pool run the chunks of `work`.
+    fn submit(&self, work: &Arc<dyn PendingWork>, helpers: usize) {
+        if helpers == 0 {
+            return;
+        }
+        let mut queue = self.queue.lock();
+        queue.extend((0..helpers).map(|_| work.clone()));
+        self.work_queued.notify_all();
+    }
+
+    /// Removes `work` from the queue, for the threads that did not pick it up yet.
+    fn withdraw(&self, work: &Arc<dyn PendingWork>) {
+        self.queue
+            .lock()
+            .retain(|w| !std::ptr::addr_eq(Arc::as_ptr(w), Arc::as_ptr(work)));
+    }
+
+    /// The loop of each thread: runs the chunks of the queued calls.
+    fn work(&self) {
+        loop {
+            let work = {
+                let mut queue = self.queue.lock();
+                loop {
+                    if let Some(work) = queue.pop_front() {
+                        break work;
+                    }
+                    self.work_queued.wait(&mut queue);
+                }
+            };
+            while work.help() {}
+        }
+    }
+}
@@ -2,6 +2,8 @@ use std::{alloc, ptr};
 
 use crate::storage::HasJars;
 
+pub use salsa_2022_macros::jar_chain;
+
 /// Initializes the `DB`'s jars in-place
 ///
 /// # Safety:
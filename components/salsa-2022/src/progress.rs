@@ -0,0 +1,215 @@
+//! Partial results reported by a query while it is still executing.
+
+use std::any::{Any, TypeId};
+use std::fmt;
+
+use crossbeam::channel::{self, Sender};
+
+pub use crossbeam::channel::Receiver;
+
+use crate::{
+    cycle::CycleRecoveryStrategy,
+    dump::IngredientDump,
+    hash::FxHashSet,
+    ingredient::{fmt_index, Ingredient, IngredientRequiresReset},
+    key::DependencyIndex,
+    memory::IngredientMemory,
+    metrics::QueryMetrics,
+    runtime::local_state::QueryOrigin,
+    sync::Mutex,
+    DatabaseKeyIndex, IngredientIndex, Revision, Runtime,
+};
+
+/// A partial result, as received from [`ProgressIngredient::subscribe`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Progress<Data> {
+    /// The query that reported the value.
+    pub query: DatabaseKeyIndex,
+
+    /// The reported value.
+    pub value: Data,
+}
+
+/// Stores the subscribers for a `#[salsa::progress]` struct.
+///
+/// Unlike accumulated values, reported values are not part of the query's result:
+/// they are not remembered, and they are not reported again if the query is reused
+/// from a previous revision. They are only meant to give consumers outside of the
+/// query system something to show while a long computation runs.
+pub struct ProgressIngredient<Data: Clone> {
+    index: IngredientIndex,
+    subscribers: Mutex<Vec<Sender<Progress<Data>>>>,
+    debug_name: &'static str,
+}
+
+impl<Data: Clone> ProgressIngredient<Data> {
+    pub fn new(index: IngredientIndex, debug_name: &'static str) -> Self {
+        Self {
+            index,
+            subscribers: Mutex::new(vec![]),
+            debug_name,
+        }
+    }
+
+    /// Copies the ingredient for the jars of a pipelined write. The subscribers
+    /// get the values reported by the queries of both copies.
+    pub fn fork(&self) -> Option<Self> {
+        Some(Self {
+            index: self.index,
+            subscribers: Mutex::new(self.subscribers.lock().clone()),
+            debug_name: self.debug_name,
+        })
+    }
+
+    /// Returns a receiver that gets every value reported from now on.
+    /// Dropping the receiver unsubscribes.
+    pub fn subscribe(&self) -> Receiver<Progress<Data>> {
+        let (sender, receiver) = channel::unbounded();
+        self.subscribers.lock().push(sender);
+        receiver
+    }
+
+    /// Sends `value` to the subscribers, on behalf of the active query.
+    pub fn report(&self, runtime: &Runtime, value: Data) {
+        let (query, _) = match runtime.active_query() {
+            Some(pair) => pair,
+            None => {
+                panic!("cannot report progress outside of an active query")
+            }
+        };
+
+        let mut subscribers = self.subscribers.lock();
+        subscribers.retain(|subscriber| {
+            subscriber
+                .send(Progress {
+                    query,
+                    value: value.clone(),
+                })
+                .is_ok()
+        });
+    }
+}
+
+impl<DB: ?Sized, Data> Ingredient<DB> for ProgressIngredient<Data>
+where
+    DB: crate::Database,
+    Data: Clone,
+{
+    fn ingredient_index(&self) -> IngredientIndex {
+        self.index
+    }
+
+    fn maybe_changed_after(&self, _db: &DB, _input: DependencyIndex, _revision: Revision) -> bool {
+        panic!("nothing should ever depend on a progress struct")
+    }
+
+    fn cycle_recovery_strategy(&self) -> CycleRecoveryStrategy {
+        CycleRecoveryStrategy::Panic
+    }
+
+    fn origin(&self, _key_index: crate::Id) -> Option<QueryOrigin> {
+        None
+    }
+
+    fn mark_validated_output(
+        &self,
+        _db: &DB,
+        _executor: DatabaseKeyIndex,
+        _output_key: Option<crate::Id>,
+    ) {
+        panic!("unexpected call: progress structs are never outputs")
+    }
+
+    fn remove_stale_output(
+        &self,
+        _db: &DB,
+        _executor: DatabaseKeyIndex,
+        _stale_output_key: Option<crate::Id>,
+    ) {
+        panic!("unexpected call: progress structs are never outputs")
+    }
+
+    fn reset_for_new_revision(&mut self) {
+        panic!("unexpected reset on progress struct")
+    }
+
+    fn sweep_edges(
+        &self,
+        _revision: Revision,
+        _op: &mut dyn FnMut(crate::Id, bool, &mut dyn Iterator<Item = DependencyIndex>),
+    ) {
+    }
+
+    fn sweep(&mut self, _revision: Revision, _reachable: &FxHashSet<DatabaseKeyIndex>) {}
+
+    fn release_weak_memos(&mut self) {}
+
+    fn unshare(&mut self) -> bool {
+        true
+    }
+
+    fn memoized_keys(&self) -> Vec<crate::Id> {
+        vec![]
+    }
+
+    fn query_metrics(&self) -> Option<QueryMetrics> {
+        None
+    }
+
+    fn memory_usage(&self) -> Option<IngredientMemory> {
+        None
+    }
+
+    fn memo_count(&self) -> Option<usize> {
+        None
+    }
+
+    fn dump(&self) -> IngredientDump {
+        IngredientDump::default()
+    }
+
+    fn can_fetch_dyn(&self) -> bool {
+        false
+    }
+
+    fn fetch_dyn(&self, _db: &DB, _key: crate::Id) -> Option<Box<dyn Any>> {
+        None
+    }
+
+    fn history_start(&self) -> Option<Revision> {
+        None
+    }
+
+    fn revert_to(&mut self, _runtime: &mut Runtime, _revision: Revision) {}
+
+    fn changed_since(&self, _revision: Revision) -> Vec<crate::Id> {
+        vec![]
+    }
+
+    fn key_type(&self) -> Option<TypeId> {
+        None
+    }
+
+    fn creator(&self, _key_index: crate::Id) -> Option<DatabaseKeyIndex> {
+        None
+    }
+
+    fn salsa_struct_deleted(&self, _db: &DB, _id: crate::Id) {
+        panic!("unexpected call: progress struct is not registered as a dependent fn");
+    }
+
+    fn debug_name(&self) -> &'static str {
+        self.debug_name
+    }
+
+    fn fmt_index(&self, index: Option<crate::Id>, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_index(self.debug_name, index, fmt)
+    }
+}
+
+impl<Data> IngredientRequiresReset for ProgressIngredient<Data>
+where
+    Data: Clone,
+{
+    const RESET_ON_NEW_REVISION: bool = false;
+}
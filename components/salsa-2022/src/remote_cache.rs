@@ -0,0 +1,144 @@
+//! A cache of the values of tracked functions shared between processes or machines
+//! (e.g., a cache server used by all the CI machines of a project), which the tracked
+//! functions declared with the `remote_cache` option consult before executing.
+//! Only available with the `remote-cache` feature.
+//!
+//! The ids of salsa structs depend on the order in which they are created, so the
+//! values are not looked up by their key. Rather, `remote_cache = <path>` names a
+//! function, taking the same arguments as the tracked function, that returns a
+//! checksum of everything the value depends on (e.g., the contents of a file, or the
+//! [`checksum`] of the values of other tracked functions). The reads it makes are the
+//! dependencies of the value found in the cache, so it must read every input that
+//! the tracked function would read, directly or not.
+//!
+//! The value found in the cache replaces the execution of the function, so the
+//! function must not create tracked structs, specify tracked functions or push
+//! accumulated values, and its value must not contain ids, which differ from one
+//! database to the next. Salsa structs do not implement `Serialize`; the other
+//! cases panic when the value is stored in the cache.
+
+use std::cell::Cell;
+use std::hash::{Hash, Hasher};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::Database;
+
+/// Identifies a value in a [`RemoteCache`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CacheKey {
+    /// The path of the tracked function, e.g. `my_crate::parse`, made of the
+    /// `module_path!()` where it is declared and its name.
+    pub function: String,
+
+    /// The checksum of what the value depends on, as returned by the function
+    /// given to the `remote_cache` option.
+    pub checksum: u64,
+}
+
+/// Stores the serialized values of tracked functions with the `remote_cache` option.
+/// Return it from [`Database::remote_cache`].
+pub trait RemoteCache: Send + Sync {
+    /// Returns the value stored for `key`, if any.
+    fn get(&self, key: &CacheKey) -> Option<Vec<u8>>;
+
+    /// Stores the value of `key`, once the tracked function computed it.
+    fn put(&self, key: &CacheKey, value: Vec<u8>);
+}
+
+/// Returns a hash of `value` that does not change from one process to the next,
+/// on machines with the same pointer width. The values that contain the ids of
+/// salsa structs should not be hashed with it: the ids depend on the order in
+/// which the structs were created.
+pub fn checksum<T: ?Sized + Hash>(value: &T) -> u64 {
+    let mut hasher = rustc_hash::FxHasher::default();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Returns a hash of the serialization of `value` that, like [`checksum`], does not
+/// change from one process to the next. Called by the `fetch_checksum` function
+/// generated for the tracked functions with the `remote_cache` option, whose value
+/// is then a good input to the checksum of the values that depend on it.
+pub fn value_checksum<T: ?Sized + Serialize>(value: &T) -> u64 {
+    match serde_json::to_vec(value) {
+        Ok(serialized) => checksum(&serialized),
+        Err(error) => panic!("cannot serialize the value to compute its checksum: {error}"),
+    }
+}
+
+thread_local! {
+    /// Set while a value is serialized to be stored in the cache,
+    /// to `Some(true)` once an id was found in it.
+    static STORING: Cell<Option<bool>> = const { Cell::new(None) };
+}
+
+/// Called when an id is serialized: returns `true` if it is part of a value
+/// being stored in the cache, which is then refused.
+pub(crate) fn refuse_id() -> bool {
+    STORING.with(|storing| {
+        let refused = storing.get().is_some();
+        if refused {
+            storing.set(Some(true));
+        }
+        refused
+    })
+}
+
+/// Returns the value stored in the remote cache of `db` for `function` and `checksum`,
+/// or the value returned by `execute`, which is then stored in the cache.
+/// Invoked by the `execute` function generated for tracked functions with the
+/// `remote_cache` option.
+///
+/// # Panics
+///
+/// If `execute` creates tracked structs, specifies tracked functions or pushes
+/// accumulated values, or if the value contains ids, when there is a cache.
+#[doc(hidden)]
+pub fn fetch_or_execute<DB, V>(
+    db: &DB,
+    function: &str,
+    checksum: u64,
+    execute: impl FnOnce() -> V,
+) -> V
+where
+    DB: ?Sized + Database,
+    V: Serialize + DeserializeOwned,
+{
+    let Some(cache) = db.remote_cache() else {
+        return execute();
+    };
+
+    let key = CacheKey {
+        function: function.to_string(),
+        checksum,
+    };
+    if let Some(value) = cache.get(&key) {
+        match serde_json::from_slice(&value) {
+            Ok(value) => return value,
+            Err(error) => log::warn!("{key:?}: cannot deserialize the cached value: {error}"),
+        }
+    }
+
+    let value = execute();
+    if db.runtime().active_query_has_outputs() {
+        panic!(
+            "`{function}` has the `remote_cache` option, so it cannot create tracked structs, \
+             specify tracked functions or push accumulated values"
+        );
+    }
+    STORING.with(|storing| storing.set(Some(false)));
+    let serialized = serde_json::to_vec(&value);
+    if STORING.with(|storing| storing.take()) == Some(true) {
+        panic!(
+            "`{function}` has the `remote_cache` option, so its value cannot contain ids: \
+             they differ from one database to the next"
+        );
+    }
+    match serialized {
+        Ok(serialized) => cache.put(&key, serialized),
+        Err(error) => log::warn!("{key:?}: cannot serialize the value: {error}"),
+    }
+    value
+}
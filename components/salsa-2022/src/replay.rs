@@ -0,0 +1,240 @@
+//! Recording the writes and reads of a database to a file, and replaying them
+//! to check that incremental results match results computed from scratch.
+//! Only available with the `replay` feature.
+//!
+//! Salsa can't serialize the values of inputs or the keys of queries by itself,
+//! so the database describes its writes (e.g., "set the text of this file") and
+//! reads (e.g., "type-check this file") with serializable types, by implementing
+//! [`Replay`]. Since a fresh database creates its inputs with the same ids when the
+//! same writes are applied in the same order, writes and reads may refer to inputs
+//! by id (see [`AsId`](`crate::AsId`)).
+
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::{Database, Revision};
+
+/// A database whose writes and reads can be recorded with a [`Recorder`]
+/// and replayed with [`replay`].
+pub trait Replay: Database + Default {
+    /// A write to the inputs, like setting an input field or creating an input.
+    type Write: fmt::Debug + Serialize + DeserializeOwned;
+
+    /// A read, like calling a tracked function.
+    type Read: fmt::Debug + Serialize + DeserializeOwned;
+
+    /// The result of a read.
+    type Output: fmt::Debug + PartialEq + Serialize + DeserializeOwned;
+
+    /// Applies `write` to the database.
+    fn write(&mut self, write: &Self::Write);
+
+    /// Performs `read` against the database.
+    fn read(&self, read: &Self::Read) -> Self::Output;
+}
+
+/// One line of a log written by a [`Recorder`].
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+enum Step<DB: Replay> {
+    Write {
+        write: DB::Write,
+        /// The current revision after the write.
+        revision: Revision,
+    },
+    Read {
+        read: DB::Read,
+        output: DB::Output,
+    },
+}
+
+/// Wraps a database to log each write and read made through it to `out`,
+/// one JSON value per line, so that they can be replayed with [`replay`].
+///
+/// All writes must be made through [`Recorder::write`], starting from a fresh
+/// database; reads made directly on the database are simply not recorded.
+pub struct Recorder<DB: Replay, W: Write> {
+    db: DB,
+    out: W,
+}
+
+impl<DB: Replay, W: Write> Recorder<DB, W> {
+    /// Records the writes and reads of a fresh database to `out`.
+    pub fn new(out: W) -> Self {
+        Recorder {
+            db: DB::default(),
+            out,
+        }
+    }
+
+    /// Returns the database, e.g. to make reads that are not recorded.
+    pub fn db(&self) -> &DB {
+        &self.db
+    }
+
+    /// Applies `write` to the database and records it.
+    pub fn write(&mut self, write: DB::Write) -> io::Result<()> {
+        self.db.write(&write);
+        let revision = self.db.runtime().current_revision();
+        self.log(&Step::Write { write, revision })
+    }
+
+    /// Performs `read` against the database and records it, along with its output.
+    pub fn read(&mut self, read: DB::Read) -> io::Result<DB::Output>
+    where
+        DB::Output: Clone,
+    {
+        let output = self.db.read(&read);
+        self.log(&Step::Read {
+            read,
+            output: output.clone(),
+        })?;
+        Ok(output)
+    }
+
+    /// Returns the database and the writer, which is flushed.
+    pub fn into_inner(mut self) -> io::Result<(DB, W)> {
+        self.out.flush()?;
+        Ok((self.db, self.out))
+    }
+
+    fn log(&mut self, step: &Step<DB>) -> io::Result<()> {
+        serde_json::to_writer(&mut self.out, step)?;
+        self.out.write_all(b"\n")
+    }
+}
+
+/// Replays a log written by a [`Recorder`] against a fresh database.
+///
+/// Each write is applied again, checking that it leads to the same revision as when it
+/// was recorded. At each read, the output of the database (which has been updated
+/// incrementally, as when the log was recorded) is compared with the output of another
+/// fresh database to which all the writes so far are applied at once. The first read
+/// whose outputs differ is returned as a [`ReplayError::Diverged`].
+pub fn replay<DB: Replay>(log: impl BufRead) -> Result<(), ReplayError> {
+    let mut db = DB::default();
+    let mut writes: Vec<DB::Write> = vec![];
+    for (index, line) in log.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let step: Step<DB> = serde_json::from_str(&line).map_err(|error| ReplayError::Parse {
+            line: index + 1,
+            error,
+        })?;
+        match step {
+            Step::Write { write, revision } => {
+                db.write(&write);
+                let replayed = db.runtime().current_revision();
+                if replayed != revision {
+                    return Err(ReplayError::Revision {
+                        line: index + 1,
+                        recorded: revision,
+                        replayed,
+                    });
+                }
+                writes.push(write);
+            }
+            Step::Read { read, output } => {
+                let incremental = db.read(&read);
+                let mut fresh_db = DB::default();
+                for write in &writes {
+                    fresh_db.write(write);
+                }
+                let from_scratch = fresh_db.read(&read);
+                if incremental != from_scratch {
+                    return Err(ReplayError::Diverged {
+                        line: index + 1,
+                        read: format!("{read:?}"),
+                        recorded: format!("{output:?}"),
+                        incremental: format!("{incremental:?}"),
+                        from_scratch: format!("{from_scratch:?}"),
+                    });
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The error returned by [`replay`]. Lines are numbered from 1.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ReplayError {
+    /// The log could not be read.
+    Io(io::Error),
+
+    /// A line of the log could not be parsed.
+    Parse {
+        line: usize,
+        error: serde_json::Error,
+    },
+
+    /// A write led to a different revision than when it was recorded,
+    /// so the writes have side effects that are not recorded.
+    Revision {
+        line: usize,
+        recorded: Revision,
+        replayed: Revision,
+    },
+
+    /// A read returned a different output incrementally than from scratch.
+    /// The outputs are formatted with `Debug`, as is the read.
+    Diverged {
+        line: usize,
+        read: String,
+        recorded: String,
+        incremental: String,
+        from_scratch: String,
+    },
+}
+
+impl From<io::Error> for ReplayError {
+    fn from(error: io::Error) -> Self {
+        ReplayError::Io(error)
+    }
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(error) => write!(f, "failed to read the log: {error}"),
+            ReplayError::Parse { line, error } => {
+                write!(f, "line {line}: failed to parse the step: {error}")
+            }
+            ReplayError::Revision {
+                line,
+                recorded,
+                replayed,
+            } => write!(
+                f,
+                "line {line}: the write led to {replayed:?} rather than {recorded:?}"
+            ),
+            ReplayError::Diverged {
+                line,
+                read,
+                recorded,
+                incremental,
+                from_scratch,
+            } => write!(
+                f,
+                "line {line}: `{read}` returned `{incremental}` incrementally \
+                 but `{from_scratch}` from scratch (recorded: `{recorded}`)"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReplayError::Io(error) => Some(error),
+            ReplayError::Parse { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
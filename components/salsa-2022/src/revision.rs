@@ -1,37 +1,48 @@
-use std::num::NonZeroUsize;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
-/// Value of the initial revision, as a u64. We don't use 0
-/// because we want to use a `NonZeroUsize`.
+use crate::Durability;
+
+/// Value of the initial revision, as a usize. We don't use 0,
+/// which is [`Revision::ZERO`], older than every revision.
 const START: usize = 1;
 
 /// A unique identifier for the current version of the database; each
 /// time an input is changed, the revision number is incremented.
-/// `Revision` is used internally to track which values may need to be
-/// recomputed, but is not something you should have to interact with
-/// directly as a user of salsa.
+///
+/// Revisions are ordered: a value that changed in a revision changed after
+/// every earlier one. Get the current revision with
+/// [`Database::current_revision`](`crate::Database::current_revision`), and
+/// the revision in which the value of a tracked function last changed with the
+/// `last_changed` function generated for it (e.g. `my_query::last_changed(db, input)`),
+/// to tell whether something computed outside of salsa is stale.
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Revision {
-    generation: NonZeroUsize,
+    generation: usize,
 }
 
 impl Revision {
+    /// A revision older than every revision of a database, so that every value
+    /// is considered to have changed after it.
+    pub const ZERO: Revision = Revision { generation: 0 };
+
     pub(crate) fn start() -> Self {
         Self::from(START)
     }
 
     pub(crate) fn from(g: usize) -> Self {
-        Self {
-            generation: NonZeroUsize::new(g).unwrap(),
-        }
+        Self { generation: g }
     }
 
-    pub(crate) fn next(self) -> Revision {
-        Self::from(self.generation.get() + 1)
+    /// Returns the revision after this one.
+    pub fn next(self) -> Revision {
+        Self::from(self.generation + 1)
     }
 
-    fn as_usize(self) -> usize {
-        self.generation.get()
+    /// Returns the number of this revision: the first revision of a database
+    /// is 1, and each new revision gets the next number.
+    pub fn as_usize(self) -> usize {
+        self.generation
     }
 }
 
@@ -41,6 +52,33 @@ impl std::fmt::Debug for Revision {
     }
 }
 
+/// A token recording the revision in which something was computed from
+/// the database, obtained from [`Database::attach_revision`](`crate::Database::attach_revision`).
+///
+/// Code outside of the query system can keep such a token next to a value it
+/// derived from the database (e.g., rendered HTML) and later ask
+/// [`Database::is_still_valid`](`crate::Database::is_still_valid`) whether
+/// the inputs it may depend on have changed since.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RevisionToken {
+    pub(crate) revision: Revision,
+    pub(crate) durability: Durability,
+}
+
+impl RevisionToken {
+    /// Only consider changes to inputs with at least the given durability
+    /// as invalidating this token. Use this when the value derived from
+    /// the database only depends on inputs of that durability.
+    pub fn with_durability(self, durability: Durability) -> Self {
+        Self { durability, ..self }
+    }
+
+    /// The revision this token was captured in.
+    pub fn revision(self) -> Revision {
+        self.revision
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct AtomicRevision {
     data: AtomicUsize,
@@ -53,6 +91,12 @@ impl AtomicRevision {
         }
     }
 
+    pub(crate) fn new(r: Revision) -> Self {
+        Self {
+            data: AtomicUsize::new(r.as_usize()),
+        }
+    }
+
     pub(crate) fn load(&self) -> Revision {
         Revision::from(self.data.load(Ordering::SeqCst))
     }
@@ -1,3 +1,6 @@
+use std::any::{Any, TypeId};
+
+use crate::hash::FxHashSet;
 use crate::ingredient::IngredientRequiresReset;
 
 use super::{ingredient::Ingredient, storage::HasJars};
@@ -6,11 +9,12 @@ use super::{ingredient::Ingredient, storage::HasJars};
 /// The database contains a number of jars, and each jar contains a number of ingredients.
 /// Each ingredient is given a unique index as the database is being created.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IngredientIndex(u32);
 
 impl IngredientIndex {
     /// Create an ingredient index from a usize.
-    fn from(v: usize) -> Self {
+    pub(crate) fn from(v: usize) -> Self {
         assert!(v < (std::u32::MAX as usize));
         Self(v as u32)
     }
@@ -56,6 +60,20 @@ pub struct Routes<DB: HasJars> {
 
     /// Indices of routes which need a 'reset' call.
     needs_reset: Vec<IngredientIndex>,
+
+    /// Type ids of the jars whose ingredients have been added so far.
+    jars: FxHashSet<TypeId>,
+
+    /// Vector indexed by ingredient index. Yields the name of the jar
+    /// which created the ingredient.
+    jar_names: Vec<&'static str>,
+
+    /// Vector indexed by ingredient index. Yields the name of the salsa
+    /// struct whose field the ingredient stores, if it stores one.
+    struct_names: Vec<Option<&'static str>>,
+
+    /// The name of the jar whose ingredients are being added.
+    current_jar: &'static str,
 }
 
 impl<DB: HasJars> Routes<DB> {
@@ -64,9 +82,25 @@ impl<DB: HasJars> Routes<DB> {
         Routes {
             routes: vec![],
             needs_reset: vec![],
+            jars: FxHashSet::default(),
+            jar_names: vec![],
+            struct_names: vec![],
+            current_jar: "",
         }
     }
 
+    /// Records that the ingredients of the jar `J` are being added.
+    /// Returns false if they have already been added.
+    pub(crate) fn add_jar<J: Any>(&mut self) -> bool {
+        self.jars.insert(TypeId::of::<J>())
+    }
+
+    /// Sets the name of the jar whose ingredients are being added,
+    /// returning the previous one (jars add the jars they include).
+    pub(crate) fn set_current_jar(&mut self, jar_name: &'static str) -> &'static str {
+        std::mem::replace(&mut self.current_jar, jar_name)
+    }
+
     /// Adds a new ingredient into the ingredients table, returning
     /// the `IngredientIndex` that can be used in a `DatabaseKeyIndex`.
     /// This index can then be used to fetch the "route" so that we can
@@ -93,6 +127,8 @@ impl<DB: HasJars> Routes<DB> {
             Box::new(move |jars| route(jars)),
             Box::new(move |jars| mut_route(jars)),
         ));
+        self.jar_names.push(self.current_jar);
+        self.struct_names.push(None);
         let index = IngredientIndex::from(len);
 
         if I::RESET_ON_NEW_REVISION {
@@ -102,6 +138,23 @@ impl<DB: HasJars> Routes<DB> {
         index
     }
 
+    /// Like [`Routes::push`], for an ingredient that stores a field of the salsa
+    /// struct named `struct_name`: the name of the struct is part of the
+    /// [stable name](`crate::storage::Storage::stable_name`) of the ingredient.
+    pub fn push_field<I>(
+        &mut self,
+        struct_name: &'static str,
+        route: impl (Fn(&DB::Jars) -> &I) + Send + Sync + 'static,
+        mut_route: impl (Fn(&mut DB::Jars) -> &mut I) + Send + Sync + 'static,
+    ) -> IngredientIndex
+    where
+        I: Ingredient<DB> + IngredientRequiresReset + 'static,
+    {
+        let index = self.push(route, mut_route);
+        self.struct_names[index.as_usize()] = Some(struct_name);
+        index
+    }
+
     /// Given an ingredient index, return the "route"
     /// (a function that, given a `&Jars`, returns the ingredient).
     pub fn route(&self, index: IngredientIndex) -> &dyn Fn(&DB::Jars) -> &dyn Ingredient<DB> {
@@ -117,6 +170,31 @@ impl<DB: HasJars> Routes<DB> {
         &self.routes[index.as_usize()].1
     }
 
+    /// Returns the name of the jar which created the ingredient `index`.
+    pub fn jar_name(&self, index: IngredientIndex) -> &'static str {
+        self.jar_names[index.as_usize()]
+    }
+
+    /// Returns the name of the salsa struct whose field is stored by the ingredient
+    /// `index`, if it was added with [`Routes::push_field`].
+    pub fn struct_name(&self, index: IngredientIndex) -> Option<&'static str> {
+        self.struct_names[index.as_usize()]
+    }
+
+    /// Returns the routes for all ingredients.
+    pub fn all_routes(
+        &self,
+    ) -> impl Iterator<Item = &dyn Fn(&DB::Jars) -> &dyn Ingredient<DB>> + '_ {
+        (0..self.routes.len()).map(|index| self.route(IngredientIndex::from(index)))
+    }
+
+    /// Returns the mut routes for all ingredients.
+    pub fn all_routes_mut(
+        &self,
+    ) -> impl Iterator<Item = &dyn Fn(&mut DB::Jars) -> &mut dyn Ingredient<DB>> + '_ {
+        (0..self.routes.len()).map(|index| self.route_mut(IngredientIndex::from(index)))
+    }
+
     /// Returns the mut routes for ingredients that need to be reset at the start of each revision.
     pub fn reset_routes(
         &self,
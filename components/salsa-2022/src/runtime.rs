@@ -1,6 +1,10 @@
+#[cfg(feature = "parallel")]
+use std::sync::atomic::Ordering;
 use std::{
+    cell::{Cell, OnceCell},
     panic::panic_any,
-    sync::{atomic::Ordering, Arc},
+    sync::Arc,
+    time::Instant,
 };
 
 use crate::{
@@ -9,11 +13,13 @@ use crate::{
     durability::Durability,
     key::{DatabaseKeyIndex, DependencyIndex},
     runtime::active_query::ActiveQuery,
-    Cancelled, Cycle, Database, Event, EventKind, Revision,
+    shared::SharedValues,
+    CancellationToken, Cancelled, Cycle, Database, Event, EventKind, Revision,
 };
 
 use self::{
     dependency_graph::DependencyGraph,
+    execution_limit::ExecutionPermit,
     local_state::{ActiveQueryGuard, EdgeKind},
 };
 
@@ -21,9 +27,17 @@ use super::{tracked_struct::Disambiguator, IngredientIndex};
 
 mod active_query;
 mod dependency_graph;
+#[cfg(feature = "event-log")]
+mod event_log;
+mod execution_limit;
+mod frozen;
 pub mod local_state;
 mod shared_state;
 
+#[cfg(feature = "parallel")]
+pub(crate) use self::dependency_graph::PendingWork;
+pub use self::frozen::FrozenGuard;
+
 pub struct Runtime {
     /// Our unique runtime id.
     id: RuntimeId,
@@ -33,6 +47,36 @@ pub struct Runtime {
 
     /// Shared state that is accessible via all runtimes.
     shared_state: Arc<shared_state::SharedState>,
+
+    /// The state of the [`Database::transaction`] running on this handle, if any.
+    transaction: Cell<Transaction>,
+
+    /// The token cancelling the queries of this handle, for snapshots created
+    /// with [`ParallelDatabase::snapshot_with_token`](`crate::ParallelDatabase::snapshot_with_token`).
+    cancellation_token: OnceCell<CancellationToken>,
+
+    /// When the [`Database::with_timeout`] running on this handle, if any, times out.
+    deadline: Cell<Option<Instant>>,
+
+    /// True while [`crate::par_map`] snapshots this handle, maybe from inside a query.
+    #[cfg(feature = "parallel")]
+    forking: Cell<bool>,
+
+    /// True for the handles that [`crate::par_map`] runs its closure on when called
+    /// from a query, which run with the execution permit of the handle executing it.
+    forked_in_query: bool,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Transaction {
+    /// No transaction is running.
+    None,
+
+    /// A transaction is running, and its next write starts a new revision.
+    Open,
+
+    /// A transaction is running and started a revision, which its next writes reuse.
+    Started,
 }
 
 #[derive(Clone, Debug)]
@@ -40,6 +84,9 @@ pub(crate) enum WaitResult {
     Completed,
     Panicked,
     Cycle(Cycle),
+
+    /// The other thread was cancelled by its [`CancellationToken`] before completing.
+    Abandoned,
 }
 
 /// A unique identifier for a particular runtime. Each time you create
@@ -50,6 +97,38 @@ pub struct RuntimeId {
     counter: usize,
 }
 
+/// A query executing on the current thread, as returned by [`Runtime::current_query_stack`].
+/// The stack is also attached to the [`Cycle`]s and [`Cancelled`] values that salsa unwinds with.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct QueryFrame {
+    /// The name of the query, e.g. `my_query`.
+    pub query: &'static str,
+
+    /// The id of the key the query is executing for.
+    pub key: crate::Id,
+}
+
+impl QueryFrame {
+    fn new<DB: ?Sized + Database>(db: &DB, database_key_index: DatabaseKeyIndex) -> Self {
+        QueryFrame {
+            query: db.ingredient_debug_name(database_key_index.ingredient_index),
+            key: database_key_index.key_index,
+        }
+    }
+}
+
+/// Formats the frame like a database key, e.g. `my_query(0)`.
+impl std::fmt::Display for QueryFrame {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(fmt, "{}({})", self.query, u32::from(self.key))
+    }
+}
+
+/// The dependencies recorded by the closure of [`crate::par_map`] on another thread,
+/// which are added to the query that called it.
+#[cfg(feature = "parallel")]
+pub(crate) struct ForkedDependencies(ActiveQuery);
+
 #[derive(Clone, Debug)]
 pub(crate) struct StampedValue<V> {
     pub(crate) value: V,
@@ -72,6 +151,12 @@ impl Default for Runtime {
             id: RuntimeId { counter: 0 },
             shared_state: Default::default(),
             local_state: Default::default(),
+            transaction: Cell::new(Transaction::None),
+            cancellation_token: OnceCell::new(),
+            deadline: Cell::new(None),
+            #[cfg(feature = "parallel")]
+            forking: Cell::new(false),
+            forked_in_query: false,
         }
     }
 }
@@ -100,12 +185,84 @@ impl Runtime {
         self.local_state.active_query()
     }
 
+    /// Returns the number of dependencies (inputs and outputs) recorded so far
+    /// by the active query, or 0 if there is none.
+    pub(crate) fn active_query_edge_count(&self) -> usize {
+        self.local_state.active_query_edge_count()
+    }
+
+    /// Returns true if a query is executing on this thread.
+    pub(crate) fn query_in_progress(&self) -> bool {
+        self.local_state.query_in_progress()
+    }
+
     pub(crate) fn empty_dependencies(&self) -> Arc<[(EdgeKind, DependencyIndex)]> {
         self.shared_state.empty_dependencies.clone()
     }
 
+    pub(crate) fn shared_values(&self) -> &SharedValues {
+        &self.shared_state.shared_values
+    }
+
+    /// Records `event` in the event log, see [`Runtime::recent_events`].
+    #[cfg(feature = "event-log")]
+    pub(crate) fn record_event(&self, event: &Event) {
+        self.shared_state.event_log.record(event);
+    }
+
+    /// Returns the last events of the database (all its handles), oldest first.
+    /// Their number is set with
+    /// [`Storage::set_event_log_capacity`](`crate::storage::Storage::set_event_log_capacity`).
+    #[cfg(feature = "event-log")]
+    pub fn recent_events(&self) -> Vec<Event> {
+        self.shared_state.event_log.events()
+    }
+
+    #[cfg(feature = "event-log")]
+    pub(crate) fn set_event_log_capacity(&self, capacity: usize) {
+        self.shared_state.event_log.set_capacity(capacity);
+    }
+
+    /// Runs `execute`, the body of the query on top of the query stack. With the
+    /// `event-log` feature, if a panic escapes it (other than a cancellation or a
+    /// cycle), the query stack and the last events are written to stderr.
+    pub(crate) fn execute_query<DB: ?Sized + Database, R>(
+        &self,
+        db: &DB,
+        execute: impl FnOnce() -> R,
+    ) -> R {
+        #[cfg(feature = "event-log")]
+        {
+            match std::panic::catch_unwind(std::panic::AssertUnwindSafe(execute)) {
+                Ok(value) => value,
+                Err(payload) => {
+                    event_log::dump_on_panic(self, db, &*payload);
+                    std::panic::resume_unwind(payload)
+                }
+            }
+        }
+        #[cfg(not(feature = "event-log"))]
+        {
+            let _ = db;
+            execute()
+        }
+    }
+
+    #[cfg(feature = "self-check")]
+    pub(crate) fn set_self_check(&self, enabled: bool) {
+        self.shared_state.self_check.store(enabled);
+    }
+
+    #[cfg(feature = "self-check")]
+    pub(crate) fn self_check_enabled(&self) -> bool {
+        self.shared_state.self_check.load()
+    }
+
+    #[cfg(feature = "parallel")]
     pub fn snapshot(&self) -> Self {
-        if self.local_state.query_in_progress() {
+        let forking = self.forking.get();
+        let query_in_progress = self.local_state.query_in_progress();
+        if query_in_progress && !forking {
             panic!("it is not legal to `snapshot` during a query (see salsa-rs/salsa#80)");
         }
 
@@ -117,9 +274,150 @@ impl Runtime {
             id,
             shared_state: self.shared_state.clone(),
             local_state: Default::default(),
+            transaction: Cell::new(Transaction::None),
+            cancellation_token: if forking {
+                self.cancellation_token.clone()
+            } else {
+                OnceCell::new()
+            },
+            deadline: Cell::new(if forking { self.deadline.get() } else { None }),
+            forking: Cell::new(false),
+            forked_in_query: forking && query_in_progress,
+        }
+    }
+
+    /// Calls `fork`, which snapshots the database for [`crate::par_map`], even from
+    /// inside a query. The snapshots share the execution permit, cancellation token
+    /// and timeout of this handle.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn fork_for_par_map<R>(&self, fork: impl FnOnce() -> R) -> R {
+        struct Restore<'a>(&'a Cell<bool>);
+
+        impl Drop for Restore<'_> {
+            fn drop(&mut self) {
+                self.0.set(false);
+            }
+        }
+
+        assert!(!self.forking.replace(true));
+        let _restore = Restore(&self.forking);
+        fork()
+    }
+
+    /// Runs `op` on this handle, forked by [`crate::par_map`] from a handle executing
+    /// the query `parent`, and returns the dependencies that `op` recorded for it.
+    ///
+    /// # Panics
+    ///
+    /// If `op` creates tracked structs, specifies the value of tracked functions or
+    /// pushes accumulated values.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn run_forked<R>(
+        &self,
+        parent: Option<DatabaseKeyIndex>,
+        op: impl FnOnce() -> R,
+    ) -> (R, Option<ForkedDependencies>) {
+        let Some(parent) = parent else {
+            return (op(), None);
+        };
+        let active_query = self.push_query(parent);
+        let value = op();
+        let dependencies = active_query.complete();
+        assert!(
+            dependencies
+                .input_outputs
+                .iter()
+                .all(|&(edge_kind, _)| edge_kind == EdgeKind::Input),
+            "the closure of `par_map` cannot create tracked structs, \
+             specify tracked functions or push accumulated values"
+        );
+        (value, Some(ForkedDependencies(dependencies)))
+    }
+
+    /// Lets the runtimes blocked on this one run the chunks of `work`, a call of
+    /// [`crate::par_map`] made by this handle, until [`Runtime::remove_pending_work`].
+    #[cfg(feature = "parallel")]
+    pub(crate) fn add_pending_work(&self, work: Arc<dyn PendingWork>) {
+        self.shared_state
+            .dependency_graph
+            .lock()
+            .add_pending_work(self.id(), work);
+    }
+
+    #[cfg(feature = "parallel")]
+    pub(crate) fn remove_pending_work(&self, work: &Arc<dyn PendingWork>) {
+        self.shared_state
+            .dependency_graph
+            .lock()
+            .remove_pending_work(self.id(), work);
+    }
+
+    /// Adds `dependencies`, recorded by [`Runtime::run_forked`], to the active query.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn add_forked_dependencies(&self, dependencies: ForkedDependencies) {
+        self.local_state.add_dependencies_from(&dependencies.0);
+    }
+
+    /// Returns a runtime for an independent copy of the database, made by
+    /// [`Storage::fork`](`crate::storage::Storage::fork`). It starts at the
+    /// current revision, which the two runtimes then advance separately.
+    pub(crate) fn fork(&self) -> Self {
+        if self.local_state.query_in_progress() {
+            panic!("it is not legal to `fork` during a query");
+        }
+
+        Runtime {
+            shared_state: Arc::new(self.shared_state.fork()),
+            ..Default::default()
         }
     }
 
+    /// Sets the token cancelling the queries of this handle.
+    /// Panics if it already has one.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn set_cancellation_token(&self, token: CancellationToken) {
+        if self.cancellation_token.set(token).is_err() {
+            panic!("this database handle already has a cancellation token");
+        }
+    }
+
+    /// True if the queries of this handle were cancelled by its [`CancellationToken`],
+    /// or because the [`Database::with_timeout`] they run in timed out.
+    pub(crate) fn cancellation_requested(&self) -> bool {
+        self.token_cancelled() || self.timed_out()
+    }
+
+    fn token_cancelled(&self) -> bool {
+        self.cancellation_token
+            .get()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    fn timed_out(&self) -> bool {
+        self.deadline
+            .get()
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Runs `op`, cancelling the queries it calls once `deadline` has passed.
+    /// If `op` runs within another call with an earlier deadline, that one applies.
+    pub(crate) fn with_deadline<T>(&self, deadline: Instant, op: impl FnOnce() -> T) -> T {
+        struct RestoreDeadline<'me>(&'me Cell<Option<Instant>>, Option<Instant>);
+
+        impl Drop for RestoreDeadline<'_> {
+            fn drop(&mut self) {
+                self.0.set(self.1);
+            }
+        }
+
+        let outer_deadline = self.deadline.get();
+        let _restore = RestoreDeadline(&self.deadline, outer_deadline);
+        self.deadline.set(Some(
+            outer_deadline.map_or(deadline, |outer| outer.min(deadline)),
+        ));
+        op()
+    }
+
     pub(crate) fn report_tracked_read(
         &self,
         key_index: DependencyIndex,
@@ -155,6 +453,13 @@ impl Runtime {
         self.local_state.add_output(key);
     }
 
+    /// Check whether the current query created tracked structs, specified the value
+    /// of tracked functions or pushed accumulated values.
+    #[cfg(feature = "remote-cache")]
+    pub(crate) fn active_query_has_outputs(&self) -> bool {
+        self.local_state.has_outputs()
+    }
+
     /// Check whether `entity` is contained the list of outputs written by the current query.
     pub(super) fn is_output_of_active_query(&self, entity: DependencyIndex) -> bool {
         self.local_state.is_output(entity)
@@ -206,29 +511,108 @@ impl Runtime {
     /// `salsa_event` is emitted when this method is called, so that should be
     /// used instead.
     pub(crate) fn unwind_if_revision_cancelled<DB: ?Sized + Database>(&self, db: &DB) {
-        db.salsa_event(Event {
-            runtime_id: self.id(),
-            kind: EventKind::WillCheckCancellation,
-        });
-        if self.shared_state.revision_canceled.load() {
-            db.salsa_event(Event {
+        crate::event::report(
+            db,
+            Event {
                 runtime_id: self.id(),
                 kind: EventKind::WillCheckCancellation,
-            });
-            self.unwind_cancelled();
+            },
+        );
+        if self.shared_state.revision_canceled.load() {
+            crate::event::report(
+                db,
+                Event {
+                    runtime_id: self.id(),
+                    kind: EventKind::WillCheckCancellation,
+                },
+            );
+            self.unwind_cancelled(db);
+        }
+        if self.cancellation_requested() {
+            self.unwind_requested(db);
+        }
+
+        // The values read from now on are verified in the current revision,
+        // so the next write of a transaction must not reuse it.
+        if self.transaction.get() == Transaction::Started {
+            self.transaction.set(Transaction::Open);
         }
     }
 
     #[cold]
-    pub(crate) fn unwind_cancelled(&self) {
+    pub(crate) fn unwind_cancelled<DB: ?Sized + Database>(&self, db: &DB) {
         self.report_untracked_read();
-        Cancelled::PendingWrite.throw();
+        Cancelled::PendingWrite {
+            query_stack: self.current_query_stack(db),
+        }
+        .throw();
+    }
+
+    #[cold]
+    fn unwind_requested<DB: ?Sized + Database>(&self, db: &DB) {
+        self.report_untracked_read();
+        let query_stack = self.current_query_stack(db);
+        if self.token_cancelled() {
+            Cancelled::Requested { query_stack }.throw();
+        } else {
+            Cancelled::TimedOut { query_stack }.throw();
+        }
+    }
+
+    /// Returns the queries executing on the current thread, outermost first,
+    /// e.g. to report which query led to a panic.
+    pub fn current_query_stack<DB: ?Sized + Database>(&self, db: &DB) -> Vec<QueryFrame> {
+        self.local_state
+            .query_stack_keys()
+            .into_iter()
+            .map(|database_key_index| QueryFrame::new(db, database_key_index))
+            .collect()
+    }
+
+    /// Starts a transaction, see [`Database::transaction`].
+    /// Returns false if one is already running.
+    pub(crate) fn begin_transaction(&self) -> bool {
+        if self.transaction.get() != Transaction::None {
+            return false;
+        }
+        self.transaction.set(Transaction::Open);
+        true
+    }
+
+    pub(crate) fn end_transaction(&self) {
+        self.transaction.set(Transaction::None);
+    }
+
+    /// True if a transaction is running and already started the current revision,
+    /// so that a write can reuse it rather than starting a new one.
+    pub(crate) fn in_transaction_revision(&self) -> bool {
+        self.transaction.get() == Transaction::Started
+    }
+
+    /// Freezes the database until the returned guard is dropped
+    /// (see [`Database::freeze`](`crate::Database::freeze`)).
+    #[track_caller]
+    pub(crate) fn freeze(&self) -> FrozenGuard {
+        FrozenGuard::new(self.shared_state.clone(), std::panic::Location::caller())
+    }
+
+    /// Panics, at the location of the caller, if the database is frozen.
+    #[track_caller]
+    pub(crate) fn assert_not_frozen(&self) {
+        self.shared_state.freezes.assert_not_frozen();
     }
 
     pub(crate) fn set_cancellation_flag(&self) {
         self.shared_state.revision_canceled.store(true);
     }
 
+    /// Detaches this handle from the state shared with its snapshots, which keep
+    /// the current revision while this handle moves on to new ones. Used when the
+    /// storage forks the jars rather than waiting for the snapshots to be dropped.
+    pub(crate) fn fork_shared_state(&mut self) {
+        self.shared_state = Arc::new(self.shared_state.fork());
+    }
+
     /// Increments the "current revision" counter and clears
     /// the cancellation flag.
     ///
@@ -238,14 +622,74 @@ impl Runtime {
         let r_new = r_old.next();
         self.shared_state.revisions[0].store(r_new);
         self.shared_state.revision_canceled.store(false);
+        if self.transaction.get() == Transaction::Open {
+            self.transaction.set(Transaction::Started);
+        }
         r_new
     }
 
+    /// Limits the number of handles executing queries at the same time
+    /// (see [`Storage::set_max_concurrent_queries`](`crate::storage::Storage::set_max_concurrent_queries`)).
+    pub(crate) fn set_max_concurrent_queries(&self, max: Option<usize>) {
+        self.shared_state.execution_limit.set_max(max);
+    }
+
+    /// Outside of any query, waits until this handle may execute queries, if the number
+    /// of handles executing queries at the same time is limited, and returns the permit
+    /// to do so. The queries called from then on run with that permit.
+    ///
+    /// Unwinds if the handle is cancelled while waiting.
+    pub(crate) fn acquire_execution_permit<DB: ?Sized + Database>(
+        &self,
+        db: &DB,
+    ) -> Option<ExecutionPermit<'_>> {
+        if self.query_in_progress() || self.forked_in_query {
+            return None;
+        }
+        let cancelled =
+            || self.shared_state.revision_canceled.load() || self.cancellation_requested();
+        let will_wait = || {
+            crate::event::report(
+                db,
+                Event {
+                    runtime_id: self.id(),
+                    kind: EventKind::WillWaitForPermit,
+                },
+            )
+        };
+        match self
+            .shared_state
+            .execution_limit
+            .acquire(cancelled, will_wait)
+        {
+            Ok(permit) => permit,
+            Err(()) => {
+                self.unwind_if_revision_cancelled(db);
+                unreachable!("the handle was cancelled while waiting for a permit")
+            }
+        }
+    }
+
     #[inline]
     pub(crate) fn push_query(&self, database_key_index: DatabaseKeyIndex) -> ActiveQueryGuard<'_> {
         self.local_state.push_query(database_key_index)
     }
 
+    /// True if `database_key_index` is on the query stack of this thread.
+    pub(crate) fn is_executing(&self, database_key_index: DatabaseKeyIndex) -> bool {
+        self.local_state
+            .query_stack_keys()
+            .contains(&database_key_index)
+    }
+
+    /// Invoked when `database_key_index`, a query with the
+    /// [`CycleRecoveryStrategy::FixedPoint`] strategy, is called. If it is executing on
+    /// this thread, marks it as the head of a cycle and the queries it called since as
+    /// depending on its provisional value, and returns true.
+    pub(crate) fn report_provisional_read(&self, database_key_index: DatabaseKeyIndex) -> bool {
+        self.local_state.report_provisional_read(database_key_index)
+    }
+
     /// Block until `other_id` completes executing `database_key`;
     /// panic or unwind in the case of a cycle.
     ///
@@ -285,13 +729,16 @@ impl Runtime {
             assert!(!dg.depends_on(other_id, self.id()));
         }
 
-        db.salsa_event(Event {
-            runtime_id: self.id(),
-            kind: EventKind::WillBlockOn {
-                other_runtime_id: other_id,
-                database_key,
+        crate::event::report(
+            db,
+            Event {
+                runtime_id: self.id(),
+                kind: EventKind::WillBlockOn {
+                    other_runtime_id: other_id,
+                    database_key,
+                },
             },
-        });
+        );
 
         let stack = self.local_state.take_query_stack();
 
@@ -306,15 +753,33 @@ impl Runtime {
 
         self.local_state.restore_query_stack(stack);
 
+        crate::event::report(
+            db,
+            Event {
+                runtime_id: self.id(),
+                kind: EventKind::DidUnblock {
+                    other_runtime_id: other_id,
+                    database_key,
+                },
+            },
+        );
+
         match result {
             WaitResult::Completed => (),
 
             // If the other thread panicked, then we consider this thread
             // cancelled. The assumption is that the panic will be detected
             // by the other thread and responded to appropriately.
-            WaitResult::Panicked => Cancelled::PropagatedPanic.throw(),
+            WaitResult::Panicked => Cancelled::PropagatedPanic {
+                query_stack: self.current_query_stack(db),
+            }
+            .throw(),
 
             WaitResult::Cycle(c) => c.throw(),
+
+            // The other thread was cancelled by its token, but this one was not:
+            // return, so that the caller claims the query and executes it itself.
+            WaitResult::Abandoned => (),
         }
     }
 
@@ -340,6 +805,10 @@ impl Runtime {
 
         let mut from_stack = self.local_state.take_query_stack();
         let from_id = self.id();
+        let query_stack = from_stack
+            .iter()
+            .map(|active_query| QueryFrame::new(db, active_query.database_key_index))
+            .collect();
 
         // Make a "dummy stack frame". As we iterate through the cycle, we will collect the
         // inputs from each participant. Then, if we are participating in cycle recovery, we
@@ -373,13 +842,15 @@ impl Runtime {
             // No need to store extra memory.
             v.shrink_to_fit();
 
-            Cycle::new(Arc::new(v))
+            let frames = v.iter().map(|&key| QueryFrame::new(db, key)).collect();
+            Cycle::new(Arc::new(v), Arc::new(frames), Arc::new(query_stack))
         };
         log::debug!(
             "cycle {:?}, cycle_query {:#?}",
             cycle.debug(db),
             cycle_query,
         );
+        crate::trace::cycle_detected(db, &cycle);
 
         // We can remove the cycle participants from the list of dependencies;
         // they are a strongly connected component (SCC) and we only care about
@@ -394,7 +865,7 @@ impl Runtime {
             aqs.iter_mut()
                 .skip_while(|aq| {
                     match db.cycle_recovery_strategy(aq.database_key_index.ingredient_index) {
-                        CycleRecoveryStrategy::Panic => true,
+                        CycleRecoveryStrategy::Panic | CycleRecoveryStrategy::FixedPoint => true,
                         CycleRecoveryStrategy::Fallback => false,
                     }
                 })
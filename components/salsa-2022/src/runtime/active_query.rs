@@ -38,6 +38,19 @@ pub(super) struct ActiveQuery {
     /// hash is added to this map. If it is not present, then the disambiguator is 0.
     /// Otherwise it is 1 more than the current value (which is incremented).
     pub(super) disambiguator_map: FxIndexMap<u64, Disambiguator>,
+
+    /// True if the provisional value of this query was read by a query it called,
+    /// making it the head of a cycle iterated to a fixed point.
+    pub(super) provisional_read: bool,
+
+    /// The heads of cycles, lower on the stack, whose provisional values this query
+    /// read (directly or not). If there are any, its result is provisional too.
+    pub(super) cycle_heads: FxIndexSet<DatabaseKeyIndex>,
+
+    /// The queries that this query read while their results were provisional.
+    /// Their inputs are merged into those of this query, which no longer depends
+    /// on them once it is no longer provisional itself.
+    pub(super) cycle_participants: FxIndexSet<DatabaseKeyIndex>,
 }
 
 impl ActiveQuery {
@@ -50,6 +63,9 @@ impl ActiveQuery {
             untracked_read: false,
             cycle: None,
             disambiguator_map: Default::default(),
+            provisional_read: false,
+            cycle_heads: Default::default(),
+            cycle_participants: Default::default(),
         }
     }
 
@@ -127,6 +143,34 @@ impl ActiveQuery {
         }
     }
 
+    /// Merges `provisional`, a query whose result depends on the provisional value
+    /// of a cycle head and was therefore not memoized, into `self`, the query that
+    /// called it: `self` now depends on its inputs rather than on it.
+    /// Used for cycles iterated to a fixed point.
+    pub(super) fn add_provisional(&mut self, provisional: &ActiveQuery) {
+        self.add_from(provisional);
+        self.cycle_heads.extend(
+            provisional
+                .cycle_heads
+                .iter()
+                .copied()
+                .filter(|&head| head != self.database_key_index),
+        );
+        self.cycle_participants
+            .extend(provisional.cycle_participants.iter().copied());
+        self.cycle_participants
+            .insert(provisional.database_key_index);
+    }
+
+    /// Removes the queries read while provisional from my dependencies, once my
+    /// own result is final: their inputs were merged into mine.
+    pub(super) fn remove_provisional_participants(&mut self) {
+        for &p in &self.cycle_participants {
+            let p: DependencyIndex = p.into();
+            self.input_outputs.shift_remove(&(EdgeKind::Input, p));
+        }
+    }
+
     /// Copy the changed-at, durability, and dependencies from `cycle_query`.
     /// Used during cycle recovery, see [`Runtime::create_cycle_error`].
     pub(crate) fn take_inputs_from(&mut self, cycle_query: &ActiveQuery) {
@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use crate::key::DatabaseKeyIndex;
-use parking_lot::{Condvar, MutexGuard};
+use crate::sync::{Condvar, MutexGuard};
 use rustc_hash::FxHashMap;
 use smallvec::SmallVec;
 
@@ -9,6 +9,17 @@ use super::{active_query::ActiveQuery, RuntimeId, WaitResult};
 
 type QueryStack = Vec<ActiveQuery>;
 
+/// The chunks of a call of [`crate::par_map`] that no thread runs yet.
+#[cfg(feature = "parallel")]
+pub(crate) trait PendingWork: std::fmt::Debug + Send + Sync {
+    /// True if a chunk is waiting for a thread to run it.
+    fn has_work(&self) -> bool;
+
+    /// Runs one of the chunks waiting for a thread, if any, and
+    /// returns false if there was none.
+    fn help(&self) -> bool;
+}
+
 #[derive(Debug, Default)]
 pub(super) struct DependencyGraph {
     /// A `(K -> V)` pair in this map indicates that the the runtime
@@ -25,6 +36,11 @@ pub(super) struct DependencyGraph {
     /// it stores its `WaitResult` here. As they wake up, each query Q in Qs will
     /// come here to fetch their results.
     wait_results: FxHashMap<RuntimeId, (QueryStack, WaitResult)>,
+
+    /// The calls of `par_map` made by each runtime that are not done yet. The
+    /// runtimes blocked on it help running their chunks instead of waiting idly.
+    #[cfg(feature = "parallel")]
+    pending_work: FxHashMap<RuntimeId, Vec<Arc<dyn PendingWork>>>,
 }
 
 #[derive(Debug)]
@@ -35,7 +51,7 @@ struct Edge {
 
     /// Signalled whenever a query with dependents completes.
     /// Allows those dependents to check if they are ready to unblock.
-    condvar: Arc<parking_lot::Condvar>,
+    condvar: Arc<Condvar>,
 }
 
 impl DependencyGraph {
@@ -211,10 +227,59 @@ impl DependencyGraph {
                 debug_assert!(!me.edges.contains_key(&from_id));
                 return stack_and_result;
             }
+
+            // Rather than waiting idly, run the chunks of a `par_map` called by
+            // `to_id`, or by the runtime `to_id` is blocked on, and so on: it is
+            // then done sooner, and so is the query we are waiting for.
+            #[cfg(feature = "parallel")]
+            if let Some(work) = me.pending_work_for(to_id) {
+                MutexGuard::unlocked(&mut me, || work.help());
+                continue;
+            }
+
             condvar.wait(&mut me);
         }
     }
 
+    /// Returns a call of `par_map` with chunks waiting for a thread, made by
+    /// `id` or by the runtimes that `id` is transitively blocked on.
+    #[cfg(feature = "parallel")]
+    fn pending_work_for(&self, id: RuntimeId) -> Option<Arc<dyn PendingWork>> {
+        let mut p = Some(id);
+        while let Some(q) = p {
+            let work = self
+                .pending_work
+                .get(&q)
+                .and_then(|works| works.iter().find(|work| work.has_work()));
+            if let Some(work) = work {
+                return Some(work.clone());
+            }
+            p = self.edges.get(&q).map(|edge| edge.blocked_on_id);
+        }
+        None
+    }
+
+    /// Lets the runtimes blocked on `id` help with `work`, a call of `par_map`
+    /// made by `id`, until [`Self::remove_pending_work`].
+    #[cfg(feature = "parallel")]
+    pub(super) fn add_pending_work(&mut self, id: RuntimeId, work: Arc<dyn PendingWork>) {
+        self.pending_work.entry(id).or_default().push(work);
+
+        // Wake up the blocked runtimes, so that they check for work.
+        for edge in self.edges.values() {
+            edge.condvar.notify_one();
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    pub(super) fn remove_pending_work(&mut self, id: RuntimeId, work: &Arc<dyn PendingWork>) {
+        let works = self.pending_work.get_mut(&id).unwrap();
+        works.retain(|w| !std::ptr::addr_eq(Arc::as_ptr(w), Arc::as_ptr(work)));
+        if works.is_empty() {
+            self.pending_work.remove(&id);
+        }
+    }
+
     /// Helper for `block_on`: performs actual graph modification
     /// to add a dependency edge from `from_id` to `to_id`, which is
     /// computing `database_key`.
@@ -224,7 +289,7 @@ impl DependencyGraph {
         database_key: DatabaseKeyIndex,
         to_id: RuntimeId,
         from_stack: QueryStack,
-    ) -> Arc<parking_lot::Condvar> {
+    ) -> Arc<Condvar> {
         assert_ne!(from_id, to_id);
         debug_assert!(!self.edges.contains_key(&from_id));
         debug_assert!(!self.depends_on(to_id, from_id));
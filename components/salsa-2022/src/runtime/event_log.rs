@@ -0,0 +1,130 @@
+use std::any::Any;
+use std::cell::Cell;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use arc_swap::ArcSwapOption;
+
+use crate::sync::Mutex;
+use crate::{debug::DebugWithDb, Cancelled, Cycle, Database, Event, Runtime};
+
+/// How many events are kept, unless another capacity is set with
+/// [`Storage::set_event_log_capacity`](`crate::storage::Storage::set_event_log_capacity`).
+pub(super) const DEFAULT_CAPACITY: usize = 256;
+
+/// The last events of a database, shared by its handles. When the log is full,
+/// recording an event overwrites the oldest one.
+#[derive(Debug)]
+pub(super) struct EventLog {
+    /// `None` if the capacity is 0, which disables the log.
+    ring: ArcSwapOption<Ring>,
+}
+
+/// A ring buffer of events, which is read without removing them.
+#[derive(Debug)]
+struct Ring {
+    /// The sequence number of the next event to record. The event with the
+    /// sequence number `n` is stored in the slot `n % slots.len()`.
+    next: AtomicU64,
+
+    /// The sequence number and the event stored in each slot, if any. The lock of a
+    /// slot is only contended by threads recording or reading that slot at once.
+    slots: Box<[Mutex<Option<(u64, Event)>>]>,
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        let log = EventLog {
+            ring: ArcSwapOption::empty(),
+        };
+        log.set_capacity(DEFAULT_CAPACITY);
+        log
+    }
+}
+
+impl EventLog {
+    /// Replaces the log by an empty one with room for `capacity` events.
+    pub(super) fn set_capacity(&self, capacity: usize) {
+        self.ring.store((capacity > 0).then(|| {
+            Arc::new(Ring {
+                next: AtomicU64::new(0),
+                slots: (0..capacity).map(|_| Mutex::new(None)).collect(),
+            })
+        }));
+    }
+
+    pub(super) fn record(&self, event: &Event) {
+        if let Some(ring) = &*self.ring.load() {
+            let sequence = ring.next.fetch_add(1, Ordering::Relaxed);
+            let mut slot = ring.slot(sequence).lock();
+            // A thread that took a later sequence number for the slot, a whole
+            // round of the ring after this one, may have stored its event first.
+            if !matches!(&*slot, Some((stored, _)) if *stored > sequence) {
+                *slot = Some((sequence, event.clone()));
+            }
+        }
+    }
+
+    /// Returns a copy of the events in the log, oldest first, in the order in
+    /// which their recording started. The log is left as is. An event whose
+    /// recording has not finished yet, on another thread, is left out.
+    pub(super) fn events(&self) -> Vec<Event> {
+        let Some(ring) = self.ring.load_full() else {
+            return vec![];
+        };
+        let end = ring.next.load(Ordering::Relaxed);
+        let start = end.saturating_sub(ring.slots.len() as u64);
+        (start..end)
+            .filter_map(|sequence| match &*ring.slot(sequence).lock() {
+                Some((stored, event)) if *stored == sequence => Some(event.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl Ring {
+    fn slot(&self, sequence: u64) -> &Mutex<Option<(u64, Event)>> {
+        &self.slots[(sequence % self.slots.len() as u64) as usize]
+    }
+}
+
+thread_local! {
+    /// The address of the payload of the last panic dumped by this thread, so that
+    /// it is dumped once, by the innermost query it escapes.
+    static DUMPED_PANIC: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// Writes the query stack and the last events to stderr if `payload`, a panic that
+/// escapes the query on top of the query stack, is neither a cancellation nor a cycle.
+pub(super) fn dump_on_panic<DB: ?Sized + Database>(
+    runtime: &Runtime,
+    db: &DB,
+    payload: &(dyn Any + Send),
+) {
+    if payload.is::<Cancelled>() || payload.is::<Cycle>() {
+        return;
+    }
+    let query_stack = runtime.current_query_stack(db);
+    let address = payload as *const (dyn Any + Send) as *const () as usize;
+    let dumped = DUMPED_PANIC.with(|dumped| {
+        // The outermost query forgets the panic, whose payload may be freed.
+        let next = (query_stack.len() > 1).then_some(address);
+        dumped.replace(next) == Some(address)
+    });
+    if dumped {
+        return;
+    }
+
+    let mut dump = String::from("salsa: a panic escaped a query\nquery stack (innermost last):\n");
+    for frame in &query_stack {
+        let _ = writeln!(dump, "  {frame}");
+    }
+    let events = runtime.shared_state.event_log.events();
+    let _ = writeln!(dump, "last {} events (oldest first):", events.len());
+    for event in &events {
+        let _ = writeln!(dump, "  {:?}", event.debug(db));
+    }
+    eprint!("{dump}");
+}
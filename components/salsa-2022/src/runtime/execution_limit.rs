@@ -0,0 +1,87 @@
+use crossbeam::atomic::AtomicCell;
+
+use crate::sync::{Condvar, Mutex};
+
+/// Limits how many handles of a database execute queries at the same time
+/// (see [`Storage::set_max_concurrent_queries`](`crate::storage::Storage::set_max_concurrent_queries`)).
+///
+/// A handle takes a permit when it executes a query from outside of any query,
+/// and the queries that this one calls run with the same permit. So a handle
+/// holding a permit never waits for one: it may only block on the queries of
+/// other handles, which hold a permit too, or are called by a handle that does.
+#[derive(Debug, Default)]
+pub(super) struct ExecutionLimit {
+    /// The number of permits, or 0 if the number of handles is not limited.
+    max: AtomicCell<usize>,
+
+    /// The number of permits taken.
+    taken: Mutex<usize>,
+
+    /// Notified when a permit is released, or the limit is changed.
+    released: Condvar,
+}
+
+impl ExecutionLimit {
+    /// Returns a limit with the same number of permits, none of which is taken.
+    pub(super) fn fork(&self) -> Self {
+        ExecutionLimit {
+            max: AtomicCell::new(self.max.load()),
+            ..Default::default()
+        }
+    }
+
+    pub(super) fn set_max(&self, max: Option<usize>) {
+        assert_ne!(
+            max,
+            Some(0),
+            "at least one query must be allowed to execute"
+        );
+        let _taken = self.taken.lock();
+        self.max.store(max.unwrap_or(0));
+        self.released.notify_all();
+    }
+
+    /// Takes a permit, waiting until one is released if they are all taken.
+    /// Returns `None` if the number of handles is not limited. If `cancelled`
+    /// returns true while waiting, gives up and returns `Err`: since the handles
+    /// holding the permits are cancelled too, they release them promptly.
+    /// `will_wait` is invoked, with the permits locked, before waiting for the first time.
+    pub(super) fn acquire(
+        &self,
+        cancelled: impl Fn() -> bool,
+        will_wait: impl FnOnce(),
+    ) -> Result<Option<ExecutionPermit<'_>>, ()> {
+        if self.max.load() == 0 {
+            return Ok(None);
+        }
+
+        let mut will_wait = Some(will_wait);
+        let mut taken = self.taken.lock();
+        loop {
+            if cancelled() {
+                return Err(());
+            }
+            let max = self.max.load();
+            if max == 0 || *taken < max {
+                *taken += 1;
+                return Ok(Some(ExecutionPermit { limit: self }));
+            }
+            if let Some(will_wait) = will_wait.take() {
+                will_wait();
+            }
+            self.released.wait(&mut taken);
+        }
+    }
+}
+
+/// A permit to execute queries, released when dropped.
+pub(crate) struct ExecutionPermit<'a> {
+    limit: &'a ExecutionLimit,
+}
+
+impl Drop for ExecutionPermit<'_> {
+    fn drop(&mut self) {
+        *self.limit.taken.lock() -= 1;
+        self.limit.released.notify_all();
+    }
+}
@@ -0,0 +1,73 @@
+use std::panic::Location;
+use std::sync::Arc;
+
+use crate::sync::Mutex;
+
+use super::shared_state::SharedState;
+
+/// The guards freezing a database (see [`Database::freeze`](`crate::Database::freeze`)),
+/// shared by its handles.
+#[derive(Debug, Default)]
+pub(super) struct Freezes {
+    /// Where each guard that was not dropped yet was created.
+    locations: Mutex<Vec<&'static Location<'static>>>,
+}
+
+impl Freezes {
+    pub(super) fn freeze(&self, location: &'static Location<'static>) {
+        self.locations.lock().push(location);
+    }
+
+    fn unfreeze(&self, location: &'static Location<'static>) {
+        let mut locations = self.locations.lock();
+        let index = locations.iter().position(|l| *l == location).unwrap();
+        locations.swap_remove(index);
+    }
+
+    /// Panics, at the location of the caller, if the database is frozen.
+    #[track_caller]
+    pub(super) fn assert_not_frozen(&self) {
+        if let Some(location) = self.locations.lock().first() {
+            panic!(
+                "cannot write to the database while it is frozen: \
+                 the `FrozenGuard` created at {location} was not dropped yet"
+            );
+        }
+    }
+}
+
+/// Returned by [`Database::freeze`](`crate::Database::freeze`): until it is dropped,
+/// writing to the database through any of its handles panics, so the queries executing
+/// on the handles of the database are never cancelled.
+#[must_use = "the database is only frozen until the guard is dropped"]
+pub struct FrozenGuard {
+    shared_state: Arc<SharedState>,
+    location: &'static Location<'static>,
+}
+
+impl FrozenGuard {
+    pub(super) fn new(
+        shared_state: Arc<SharedState>,
+        location: &'static Location<'static>,
+    ) -> Self {
+        shared_state.freezes.freeze(location);
+        FrozenGuard {
+            shared_state,
+            location,
+        }
+    }
+}
+
+impl std::fmt::Debug for FrozenGuard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FrozenGuard")
+            .field("location", &self.location)
+            .finish()
+    }
+}
+
+impl Drop for FrozenGuard {
+    fn drop(&mut self) {
+        self.shared_state.freezes.unfreeze(self.location);
+    }
+}
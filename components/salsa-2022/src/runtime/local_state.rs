@@ -170,6 +170,16 @@ impl LocalState {
             .expect("query stack taken"))
     }
 
+    /// Returns the queries on the stack, outermost first.
+    pub(super) fn query_stack_keys(&self) -> Vec<DatabaseKeyIndex> {
+        self.with_query_stack(|stack| {
+            stack
+                .iter()
+                .map(|active_query| active_query.database_key_index)
+                .collect()
+        })
+    }
+
     pub(super) fn query_in_progress(&self) -> bool {
         self.with_query_stack(|stack| !stack.is_empty())
     }
@@ -191,6 +201,14 @@ impl LocalState {
         })
     }
 
+    pub(super) fn active_query_edge_count(&self) -> usize {
+        self.with_query_stack(|stack| {
+            stack
+                .last()
+                .map_or(0, |active_query| active_query.input_outputs.len())
+        })
+    }
+
     pub(super) fn add_output(&self, entity: DependencyIndex) {
         self.with_query_stack(|stack| {
             if let Some(top_query) = stack.last_mut() {
@@ -199,6 +217,18 @@ impl LocalState {
         })
     }
 
+    #[cfg(feature = "remote-cache")]
+    pub(super) fn has_outputs(&self) -> bool {
+        self.with_query_stack(|stack| {
+            stack.last().is_some_and(|top_query| {
+                top_query
+                    .input_outputs
+                    .iter()
+                    .any(|(edge_kind, _)| *edge_kind == EdgeKind::Output)
+            })
+        })
+    }
+
     pub(super) fn is_output(&self, entity: DependencyIndex) -> bool {
         self.with_query_stack(|stack| {
             if let Some(top_query) = stack.last_mut() {
@@ -251,6 +281,34 @@ impl LocalState {
         })
     }
 
+    pub(super) fn report_provisional_read(&self, head: DatabaseKeyIndex) -> bool {
+        self.with_query_stack(|stack| {
+            let Some(position) = stack
+                .iter()
+                .position(|active_query| active_query.database_key_index == head)
+            else {
+                return false;
+            };
+            stack[position].provisional_read = true;
+            for active_query in &mut stack[position + 1..] {
+                active_query.cycle_heads.insert(head);
+            }
+            stack.last_mut().unwrap().cycle_participants.insert(head);
+            true
+        })
+    }
+
+    /// Adds the dependencies of `other`, a query run on another handle by
+    /// [`crate::par_map`], to the active query.
+    #[cfg(feature = "parallel")]
+    pub(super) fn add_dependencies_from(&self, other: &ActiveQuery) {
+        self.with_query_stack(|stack| {
+            if let Some(top_query) = stack.last_mut() {
+                top_query.add_from(other);
+            }
+        })
+    }
+
     pub(super) fn report_untracked_read(&self, current_revision: Revision) {
         self.with_query_stack(|stack| {
             if let Some(top_query) = stack.last_mut() {
@@ -341,14 +399,48 @@ impl ActiveQueryGuard<'_> {
     #[inline]
     pub(crate) fn pop(self, runtime: &Runtime) -> QueryRevisions {
         // Extract accumulated inputs.
-        let popped_query = self.complete();
+        let local_state = self.local_state;
+        let mut popped_query = self.complete();
 
         // If this frame were a cycle participant, it would have unwound.
         assert!(popped_query.cycle.is_none());
 
+        if popped_query.cycle_heads.is_empty() {
+            popped_query.remove_provisional_participants();
+        } else {
+            // The caller depends on the inputs of this provisional result,
+            // which is not memoized.
+            local_state.with_query_stack(|stack| {
+                if let Some(top_query) = stack.last_mut() {
+                    top_query.add_provisional(&popped_query);
+                }
+            });
+        }
+
         popped_query.revisions(runtime)
     }
 
+    /// True if the provisional value of the active query was read by a query it called.
+    pub(crate) fn is_cycle_head(&self) -> bool {
+        self.local_state
+            .with_query_stack(|stack| stack.last().unwrap().provisional_read)
+    }
+
+    /// True if the result of the active query depends on the provisional value
+    /// of a cycle head that is still executing, in which case it is not memoized.
+    pub(crate) fn is_provisional(&self) -> bool {
+        self.local_state
+            .with_query_stack(|stack| !stack.last().unwrap().cycle_heads.is_empty())
+    }
+
+    /// Forgets what the active query read and created, before it is executed again
+    /// with a new provisional value.
+    pub(crate) fn start_iteration(&self) {
+        self.local_state.with_query_stack(|stack| {
+            *stack.last_mut().unwrap() = ActiveQuery::new(self.database_key_index);
+        })
+    }
+
     /// If the active query is registered as a cycle participant, remove and
     /// return that cycle.
     pub(crate) fn take_cycle(&self) -> Option<Cycle> {
@@ -1,11 +1,21 @@
-use std::sync::{atomic::AtomicUsize, Arc};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
 
 use crossbeam::atomic::AtomicCell;
-use parking_lot::Mutex;
 
-use crate::{durability::Durability, key::DependencyIndex, revision::AtomicRevision};
+use crate::{
+    durability::Durability, key::DependencyIndex, revision::AtomicRevision, shared::SharedValues,
+    sync::Mutex,
+};
 
-use super::{dependency_graph::DependencyGraph, local_state::EdgeKind};
+#[cfg(feature = "event-log")]
+use super::event_log::EventLog;
+use super::{
+    dependency_graph::DependencyGraph, execution_limit::ExecutionLimit, frozen::Freezes,
+    local_state::EdgeKind,
+};
 
 /// State that will be common to all threads (when we support multiple threads)
 #[derive(Debug)]
@@ -35,6 +45,27 @@ pub(super) struct SharedState {
     /// The dependency graph tracks which runtimes are blocked on one
     /// another, waiting for queries to terminate.
     pub(super) dependency_graph: Mutex<DependencyGraph>,
+
+    /// The values created with [`Shared::new`](`crate::shared::Shared::new`),
+    /// which are also shared with the forks of this state.
+    pub(super) shared_values: Arc<SharedValues>,
+
+    /// Limits how many handles execute queries at the same time.
+    pub(super) execution_limit: ExecutionLimit,
+
+    /// The guards freezing the database: while there are some, writes panic.
+    /// A handle forking this state is not writing, so the forks are not frozen.
+    pub(super) freezes: Freezes,
+
+    /// True if tracked functions are executed twice to check that they are deterministic
+    /// (see [`Storage::set_self_check`](`crate::storage::Storage::set_self_check`)).
+    #[cfg(feature = "self-check")]
+    pub(super) self_check: AtomicCell<bool>,
+
+    /// The last events, which are dumped when a panic escapes a query. Shared
+    /// with the forks of this state.
+    #[cfg(feature = "event-log")]
+    pub(super) event_log: Arc<EventLog>,
 }
 
 impl Default for SharedState {
@@ -44,6 +75,29 @@ impl Default for SharedState {
 }
 
 impl SharedState {
+    /// Returns a copy of the revision counters, for a handle that forks the jars
+    /// (see [`Runtime::fork_shared_state`](`super::Runtime::fork_shared_state`)).
+    pub(super) fn fork(&self) -> Self {
+        SharedState {
+            next_id: AtomicUsize::new(self.next_id.load(Ordering::SeqCst)),
+            empty_dependencies: self.empty_dependencies.clone(),
+            revision_canceled: Default::default(),
+            revisions: self
+                .revisions
+                .iter()
+                .map(|revision| AtomicRevision::new(revision.load()))
+                .collect(),
+            dependency_graph: Default::default(),
+            shared_values: self.shared_values.clone(),
+            execution_limit: self.execution_limit.fork(),
+            freezes: Default::default(),
+            #[cfg(feature = "self-check")]
+            self_check: AtomicCell::new(self.self_check.load()),
+            #[cfg(feature = "event-log")]
+            event_log: self.event_log.clone(),
+        }
+    }
+
     fn with_durabilities(durabilities: usize) -> Self {
         SharedState {
             next_id: AtomicUsize::new(1),
@@ -51,6 +105,13 @@ impl SharedState {
             revision_canceled: Default::default(),
             revisions: (0..durabilities).map(|_| AtomicRevision::start()).collect(),
             dependency_graph: Default::default(),
+            shared_values: Default::default(),
+            execution_limit: Default::default(),
+            freezes: Default::default(),
+            #[cfg(feature = "self-check")]
+            self_check: AtomicCell::new(false),
+            #[cfg(feature = "event-log")]
+            event_log: Default::default(),
         }
     }
 }
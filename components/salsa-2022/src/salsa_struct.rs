@@ -2,6 +2,13 @@ use crate::{Database, IngredientIndex};
 
 pub trait SalsaStructInDb<DB: ?Sized + Database> {
     fn register_dependent_fn(db: &DB, index: IngredientIndex);
+
+    /// Returns the ingredient storing the instances of this struct, if they can be
+    /// deleted (those of tracked structs). [`Storage::sweep`](`crate::storage::Storage::sweep`)
+    /// keeps the memos keyed by an instance only as long as it keeps the instance.
+    fn instances_ingredient(_db: &DB) -> Option<IngredientIndex> {
+        None
+    }
 }
 
 /// A ZST that implements [`SalsaStructInDb`]
@@ -1,39 +1,90 @@
 use crate::input_field::InputFieldIngredient;
-use crate::{AsId, Durability, Runtime};
+use crate::{AsId, Database, Durability, Event, EventKind, Runtime};
 use std::hash::Hash;
 
 #[must_use]
-pub struct Setter<'setter, K, F> {
-    runtime: &'setter mut Runtime,
+pub struct Setter<'setter, DB: ?Sized, K, F> {
+    db: &'setter mut DB,
     key: K,
-    ingredient: &'setter mut InputFieldIngredient<K, F>,
-    durability: Durability,
+    ingredient: fn(&DB) -> &InputFieldIngredient<K, F>,
+    ingredient_mut: fn(&mut DB) -> (&mut InputFieldIngredient<K, F>, &mut Runtime),
+    /// The durability given with `with_durability`, if any.
+    durability: Option<Durability>,
 }
 
-impl<'setter, K, F> Setter<'setter, K, F>
+impl<'setter, DB, K, F> Setter<'setter, DB, K, F>
 where
+    DB: ?Sized + Database,
     K: Eq + Hash + AsId,
 {
     pub fn new(
-        runtime: &'setter mut Runtime,
+        db: &'setter mut DB,
         key: K,
-        ingredient: &'setter mut InputFieldIngredient<K, F>,
+        ingredient: fn(&DB) -> &InputFieldIngredient<K, F>,
+        ingredient_mut: fn(&mut DB) -> (&mut InputFieldIngredient<K, F>, &mut Runtime),
     ) -> Self {
         Setter {
-            runtime,
+            db,
             key,
             ingredient,
-            durability: Durability::LOW,
+            ingredient_mut,
+            durability: None,
         }
     }
 
     pub fn with_durability(self, durability: Durability) -> Self {
-        Setter { durability, ..self }
+        Setter {
+            durability: Some(durability),
+            ..self
+        }
     }
 
+    /// Panics if the database is frozen (see [`Database::freeze`]).
+    #[track_caller]
     pub fn to(self, value: F) -> F {
-        self.ingredient
-            .store_mut(self.runtime, self.key, value, self.durability)
-            .unwrap()
+        self.db.runtime().assert_not_frozen();
+        let durability = self.durability.unwrap_or(Durability::LOW);
+        self.store(value, durability)
+    }
+
+    /// Sets the field to `value` unless it is equal to the current value, e.g. when
+    /// a file watcher reports a change but the contents of the file are the same.
+    /// In that case, no new revision is started, so other handles are not cancelled
+    /// and no query is invalidated. The field keeps its durability, unless another
+    /// one is given with [`Setter::with_durability`].
+    ///
+    /// Returns the old value if it was replaced, or `None` if it was unchanged.
+    /// Panics if the database is frozen, even if the value is unchanged.
+    #[track_caller]
+    pub fn to_if_changed(self, value: F) -> Option<F>
+    where
+        F: Eq,
+    {
+        self.db.runtime().assert_not_frozen();
+        let (old_durability, unchanged) = (self.ingredient)(self.db).compare(self.key, &value);
+        let durability = self.durability.unwrap_or(old_durability);
+        if unchanged && durability == old_durability {
+            return None;
+        }
+        Some(self.store(value, durability))
+    }
+
+    fn store(self, value: F, durability: Durability) -> F {
+        let (ingredient, runtime) = (self.ingredient_mut)(self.db);
+        let old_value = ingredient
+            .store_mut(runtime, self.key, value, durability)
+            .unwrap();
+        let runtime_id = runtime.id();
+        let database_key = ingredient.database_key_index(self.key);
+
+        crate::event::report(
+            &*self.db,
+            Event {
+                runtime_id,
+                kind: EventKind::DidSetInput { database_key },
+            },
+        );
+
+        old_value
     }
 }
@@ -0,0 +1,176 @@
+//! Values shared between the results of queries, created with [`Shared::new`] or [`shared`].
+//!
+//! Equal values given to the same database share one allocation, so that large values
+//! returned by many queries (e.g. the representations of types) are only stored once,
+//! and comparing them, as done to backdate the values of tracked functions, is O(1).
+
+use std::any::{Any, TypeId};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::Arc;
+
+use dashmap::mapref::entry::Entry;
+
+use crate::hash::FxDashMap;
+use crate::Database;
+
+/// A value stored once per database: creating a `Shared` equal to an existing one
+/// returns a handle to the same allocation.
+///
+/// Cloning, comparing and hashing a `Shared` are O(1), since two `Shared`s are equal
+/// if and only if they point to the same value. Therefore, `Shared`s created with
+/// different databases, which are not snapshots of one another, are never equal.
+pub struct Shared<T: ?Sized>(Arc<T>);
+
+impl<T> Shared<T>
+where
+    T: Eq + Hash + Send + Sync + 'static,
+{
+    /// Returns the value of `db` equal to `value`, storing `value` if there is none.
+    pub fn new<DB: ?Sized + Database>(db: &DB, value: T) -> Self {
+        db.runtime().shared_values().store::<T>().share(value)
+    }
+}
+
+/// Shorthand for [`Shared::new`].
+pub fn shared<DB, T>(db: &DB, value: T) -> Shared<T>
+where
+    DB: ?Sized + Database,
+    T: Eq + Hash + Send + Sync + 'static,
+{
+    Shared::new(db, value)
+}
+
+impl<T: ?Sized> Clone for Shared<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: ?Sized> Deref for Shared<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: ?Sized> AsRef<T> for Shared<T> {
+    fn as_ref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: ?Sized> PartialEq for Shared<T> {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<T: ?Sized> Eq for Shared<T> {}
+
+impl<T: ?Sized> Hash for Shared<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::ptr::hash(Arc::as_ptr(&self.0), state)
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for Shared<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T: ?Sized + fmt::Display> fmt::Display for Shared<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// The shared values of a database and its snapshots, with a store for each type.
+#[derive(Default)]
+pub(crate) struct SharedValues {
+    stores: FxDashMap<TypeId, Arc<dyn ErasedStore>>,
+}
+
+impl SharedValues {
+    fn store<T>(&self) -> Arc<Store<T>>
+    where
+        T: Eq + Hash + Send + Sync + 'static,
+    {
+        let store = self
+            .stores
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Arc::new(Store::<T>::default()))
+            .clone();
+        store.into_any().downcast().unwrap()
+    }
+
+    /// Drops the values that are no longer used outside of their store.
+    pub(crate) fn release_unused(&self) {
+        for store in self.stores.iter() {
+            store.release_unused();
+        }
+    }
+}
+
+impl fmt::Debug for SharedValues {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedValues")
+            .field("stores", &self.stores.len())
+            .finish()
+    }
+}
+
+trait ErasedStore: Send + Sync {
+    fn release_unused(&self);
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync>;
+}
+
+struct Store<T> {
+    values: FxDashMap<Arc<T>, ()>,
+}
+
+impl<T: Eq + Hash> Default for Store<T> {
+    fn default() -> Self {
+        Self {
+            values: Default::default(),
+        }
+    }
+}
+
+impl<T> Store<T>
+where
+    T: Eq + Hash + Send + Sync + 'static,
+{
+    fn share(&self, value: T) -> Shared<T> {
+        if let Some(entry) = self.values.get(&value) {
+            return Shared(entry.key().clone());
+        }
+
+        // Another thread may have stored an equal value since.
+        match self.values.entry(Arc::new(value)) {
+            Entry::Occupied(entry) => Shared(entry.key().clone()),
+            Entry::Vacant(entry) => {
+                let value = entry.key().clone();
+                entry.insert(());
+                Shared(value)
+            }
+        }
+    }
+}
+
+impl<T> ErasedStore for Store<T>
+where
+    T: Eq + Hash + Send + Sync + 'static,
+{
+    fn release_unused(&self) {
+        self.values.retain(|value, _| Arc::strong_count(value) > 1);
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+        self
+    }
+}
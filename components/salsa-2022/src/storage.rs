@@ -1,17 +1,31 @@
-use std::{fmt, sync::Arc};
-
-use parking_lot::Condvar;
+use std::{
+    any::{Any, TypeId},
+    fmt,
+    sync::Arc,
+};
 
 use crate::cycle::CycleRecoveryStrategy;
+use crate::dependency_graph::DependencyGraph;
+use crate::dump::{IngredientDump, JarDump};
+use crate::hash::{FxHashMap, FxHashSet};
+use crate::history::RevertError;
 use crate::ingredient::Ingredient;
-use crate::jar::Jar;
-use crate::key::DependencyIndex;
+use crate::jar::JarInDb;
+use crate::key::{DependencyIndex, StableKey};
+#[cfg(debug_assertions)]
+use crate::leak::{Leak, LeakCheck};
+use crate::memory::MemoryReport;
+use crate::metrics::QueryMetrics;
 use crate::runtime::local_state::QueryOrigin;
 use crate::runtime::Runtime;
+use crate::subscription::{SubscriptionId, Subscriptions};
+use crate::sync::{Condvar, Mutex};
 use crate::{Database, DatabaseKeyIndex, Id, IngredientIndex};
 
 use super::routes::Routes;
-use super::{ParallelDatabase, Revision};
+#[cfg(feature = "parallel")]
+use super::ParallelDatabase;
+use super::Revision;
 
 /// The "storage" struct stores all the data for the jars.
 /// It is shared between the main database and any active snapshots.
@@ -31,6 +45,19 @@ pub struct Storage<DB: HasJars> {
     /// The runtime for this particular salsa database handle.
     /// Each handle gets its own runtime, but the runtimes have shared state between them.
     runtime: Runtime,
+
+    /// If true, a write that finds snapshots still alive forks the jars
+    /// rather than waiting for them. See [`Storage::set_pipelined_writes`].
+    pipelined_writes: bool,
+
+    /// The subscriptions to the values of queries, shared with the snapshots.
+    /// See [`Storage::add_subscription`].
+    subscriptions: Arc<Subscriptions<DB>>,
+
+    /// The number of memos of each ingredient, if leaks are checked.
+    /// See [`Storage::set_leak_check`].
+    #[cfg(debug_assertions)]
+    leak_check: Option<LeakCheck>,
 }
 
 /// Data shared between all threads.
@@ -48,6 +75,11 @@ struct Shared<DB: HasJars> {
     /// Conditional variable that is used to coordinate cancellation.
     /// When the main thread writes to the database, it blocks until each of the snapshots can be cancelled.
     cvar: Arc<Condvar>,
+
+    /// The mutex paired with `cvar`. The main thread holds it from checking whether the
+    /// snapshots are gone until it waits, and the snapshots take it before notifying `cvar`,
+    /// so that a snapshot dropped in between cannot be missed.
+    cvar_mutex: Arc<Mutex<()>>,
 }
 
 // ANCHOR: default
@@ -62,9 +94,14 @@ where
             shared: Shared {
                 jars: Some(Arc::from(jars)),
                 cvar: Arc::new(Default::default()),
+                cvar_mutex: Arc::new(Default::default()),
             },
             routes: Arc::new(routes),
             runtime: Runtime::default(),
+            pipelined_writes: false,
+            subscriptions: Default::default(),
+            #[cfg(debug_assertions)]
+            leak_check: None,
         }
     }
 }
@@ -74,6 +111,7 @@ impl<DB> Storage<DB>
 where
     DB: HasJars,
 {
+    #[cfg(feature = "parallel")]
     pub fn snapshot(&self) -> Storage<DB>
     where
         DB: ParallelDatabase,
@@ -82,9 +120,48 @@ where
             shared: self.shared.clone(),
             routes: self.routes.clone(),
             runtime: self.runtime.snapshot(),
+            pipelined_writes: false,
+            subscriptions: self.subscriptions.clone(),
+            #[cfg(debug_assertions)]
+            leak_check: None,
         }
     }
 
+    /// Returns an independent copy of the database, e.g. to make speculative edits
+    /// to a warmed-up database without modifying it. The copy starts with the inputs,
+    /// structs and memoized values of this database, and each of them then moves on
+    /// to its own revisions: writes to one are not seen by the other.
+    ///
+    /// The ingredients are copied as for pipelined writes (see
+    /// [`Storage::set_pipelined_writes`]), except that the memos are copied too.
+    /// Memoized values whose type does not implement `Clone` are not copied, and are
+    /// computed again if the copy needs them. Returns `None` if the fields of an input
+    /// or of a tracked struct have a type that does not implement `Clone`.
+    ///
+    /// The subscriptions (see [`Storage::add_subscription`]) are not copied.
+    pub fn fork(&self) -> Option<Storage<DB>> {
+        let mut jars = DB::fork_jars(self.shared.jars.as_ref().unwrap())?;
+        for route in self.routes.all_routes_mut() {
+            if !route(&mut jars).unshare() {
+                return None;
+            }
+        }
+
+        Some(Self {
+            shared: Shared {
+                jars: Some(Arc::from(jars)),
+                cvar: Arc::new(Default::default()),
+                cvar_mutex: Arc::new(Default::default()),
+            },
+            routes: self.routes.clone(),
+            runtime: self.runtime.fork(),
+            pipelined_writes: self.pipelined_writes,
+            subscriptions: Default::default(),
+            #[cfg(debug_assertions)]
+            leak_check: None,
+        })
+    }
+
     pub fn jars(&self) -> (&DB::Jars, &Runtime) {
         (self.shared.jars.as_ref().unwrap(), &self.runtime)
     }
@@ -93,6 +170,7 @@ where
         &self.runtime
     }
 
+    #[track_caller]
     pub fn runtime_mut(&mut self) -> &mut Runtime {
         self.jars_mut().1
     }
@@ -102,9 +180,25 @@ where
     /// and it will also cancel any ongoing work in the current revision.
     /// Any actual writes that occur to data in a jar should use
     /// [`Runtime::report_tracked_write`].
+    ///
+    /// Panics if the database is frozen (see [`Database::freeze`](`crate::Database::freeze`)).
+    #[track_caller]
     pub fn jars_mut(&mut self) -> (&mut DB::Jars, &mut Runtime) {
-        // Wait for all snapshots to be dropped.
-        self.cancel_other_workers();
+        self.runtime.assert_not_frozen();
+
+        // Within a transaction, the writes after the first one reuse its revision,
+        // unless a snapshot was taken since (see `Database::transaction`).
+        if self.runtime.in_transaction_revision()
+            && Arc::get_mut(self.shared.jars.as_mut().unwrap()).is_some()
+        {
+            let jars = Arc::get_mut(self.shared.jars.as_mut().unwrap()).unwrap();
+            return (jars, &mut self.runtime);
+        }
+
+        // Wait for all snapshots to be dropped, unless they can keep the current jars.
+        if !(self.pipelined_writes && self.fork_jars()) {
+            self.cancel_other_workers();
+        }
 
         // Increment revision counter.
         self.runtime.new_revision();
@@ -113,9 +207,14 @@ where
         // the snapshots have all been dropped, so we hold the only handle to the `Arc`.
         let jars = Arc::get_mut(self.shared.jars.as_mut().unwrap()).unwrap();
 
+        let routes = self.routes.clone();
+        #[cfg(debug_assertions)]
+        if let Some(leak_check) = &mut self.leak_check {
+            leak_check.record(routes.all_routes().map(|route| route(jars).memo_count()));
+        }
+
         // Inform other ingredients that a new revision has begun.
         // This gives them a chance to free resources that were being held until the next revision.
-        let routes = self.routes.clone();
         for route in routes.reset_routes() {
             route(jars).reset_for_new_revision();
         }
@@ -125,6 +224,263 @@ where
     }
     // ANCHOR_END: jars_mut
 
+    /// With pipelined writes, a write made while snapshots are alive does not cancel
+    /// them and wait for them to be dropped. Instead, this handle gets a copy of the jars
+    /// (sharing the memoized values and the values of the inputs), to which the write
+    /// is made, while the snapshots keep reading the current revision from the jars
+    /// they hold. This way, a long-running computation in a snapshot does not stall
+    /// the writes; its result is simply computed against the inputs it started with.
+    ///
+    /// Forking the jars takes time and memory in proportion to the number of
+    /// memoized values and inputs, and the work done by the snapshots from then on
+    /// is not shared with this handle. The fields of inputs whose type does not
+    /// implement `Clone` cannot be copied: if there are any, writes still wait for
+    /// the snapshots as usual.
+    pub fn set_pipelined_writes(&mut self, enabled: bool) {
+        self.pipelined_writes = enabled;
+    }
+
+    /// Limits the number of handles (this one and its snapshots) executing queries
+    /// at the same time to `max`, or lifts the limit with `None` (the default).
+    /// A query called from outside of any query first waits until fewer than `max`
+    /// handles are executing queries; the queries it calls then run without waiting.
+    /// This keeps the memory used by the queries under control when many threads
+    /// start executing expensive queries at once.
+    ///
+    /// Queries whose memoized values are still valid are returned without waiting.
+    /// A handle waiting for its turn is still cancelled by writes.
+    ///
+    /// Panics if `max` is `Some(0)`.
+    pub fn set_max_concurrent_queries(&mut self, max: Option<usize>) {
+        self.runtime.set_max_concurrent_queries(max);
+    }
+
+    /// With self-check, each tracked function is executed a second time right after
+    /// it returns, and salsa panics, naming the function, if the second execution
+    /// returns a different value or reads different dependencies. This finds the
+    /// functions that are nondeterministic or read state that salsa does not track
+    /// (without reporting an untracked read), whose memoized values may be stale.
+    ///
+    /// This doubles the work done to execute functions, so it is meant for tests
+    /// and debugging.
+    #[cfg(feature = "self-check")]
+    pub fn set_self_check(&mut self, enabled: bool) {
+        self.runtime.set_self_check(enabled);
+    }
+
+    /// Sets how many of the last events of the database are kept (256 by default),
+    /// discarding those kept so far; 0 disables the event log. They are returned by
+    /// [`Runtime::recent_events`], and written to stderr, with the query stack, when
+    /// a panic other than a cancellation or a cycle escapes a tracked function.
+    #[cfg(feature = "event-log")]
+    pub fn set_event_log_capacity(&mut self, capacity: usize) {
+        self.runtime.set_event_log_capacity(capacity);
+    }
+
+    /// Counts the memos of each tracked function (and the values of each interned
+    /// ingredient) at the start of each revision, and reports those whose count grew
+    /// in at least `revisions` revisions without ever shrinking when this handle is
+    /// dropped, as likely leaks of keys; see [`Storage::leak_report`].
+    /// `None` disables the check, which is the default.
+    ///
+    /// Counting takes time in proportion to the number of memos, so this is meant
+    /// for tests. Only available in debug builds.
+    #[cfg(debug_assertions)]
+    pub fn set_leak_check(&mut self, revisions: Option<usize>) {
+        self.leak_check = revisions.map(LeakCheck::new);
+    }
+
+    /// Returns the ingredients whose count grew in at least as many revisions as given
+    /// to [`Storage::set_leak_check`] without ever shrinking, counting the memos once
+    /// more now, so that a test can assert that there are none. The leaks that remain
+    /// when this handle is dropped are logged as warnings.
+    ///
+    /// Returns no leaks if the check is disabled. Only available in debug builds.
+    #[cfg(debug_assertions)]
+    pub fn leak_report(&self) -> Vec<Leak> {
+        let Some(leak_check) = &self.leak_check else {
+            return vec![];
+        };
+        let jars = self.shared.jars.as_ref().unwrap();
+        let mut leak_check = leak_check.clone();
+        leak_check.record(
+            self.routes
+                .all_routes()
+                .map(|route| route(jars).memo_count()),
+        );
+        leak_check.report(|index| {
+            let index = IngredientIndex::from(index);
+            let ingredient = self.routes.route(index)(jars);
+            // Only the functions report the memory used by their memos.
+            let interned = ingredient.memory_usage().is_none();
+            (
+                self.routes.jar_name(index),
+                ingredient.debug_name(),
+                interned,
+            )
+        })
+    }
+
+    /// Replaces the jars shared with the snapshots by a copy, if there are any
+    /// snapshots and all the ingredients can be copied. Returns true if the jars
+    /// are no longer shared.
+    fn fork_jars(&mut self) -> bool {
+        let jars = self.shared.jars.as_mut().unwrap();
+        if Arc::get_mut(jars).is_some() {
+            return false;
+        }
+        let Some(forked_jars) = DB::fork_jars(jars) else {
+            return false;
+        };
+
+        // The snapshots notify the condition variable of the jars they hold when dropped.
+        self.shared = Shared {
+            jars: Some(Arc::from(forked_jars)),
+            cvar: Arc::new(Default::default()),
+            cvar_mutex: Arc::new(Default::default()),
+        };
+        self.runtime.fork_shared_state();
+        true
+    }
+
+    /// Discards the memoized values that were not verified in any of the
+    /// last `max_age` revisions (`0` meaning only the current revision).
+    /// If no memo verified in those revisions depends on them, directly or not,
+    /// their memos are deleted altogether, and their functions are executed again
+    /// the next time they are called; otherwise, the dependency information of
+    /// those values is kept, so they are re-validated and, if need be, re-computed
+    /// the next time they are requested.
+    ///
+    /// The tracked structs created by the deleted memos are deleted with them,
+    /// along with the values of their fields and the memos keyed by them.
+    ///
+    /// The [shared values](`crate::shared::Shared`) that are no longer used are dropped as well.
+    ///
+    /// Like a write, this will cancel any ongoing work and start a new revision.
+    pub fn sweep(&mut self, max_age: usize) {
+        let current_revision = self.runtime.current_revision();
+        let oldest_kept =
+            Revision::from(current_revision.as_usize().saturating_sub(max_age).max(1));
+
+        let routes = self.routes.clone();
+        let (jars, _) = self.jars_mut();
+
+        // The memos that must be kept, and those they depend on, transitively
+        // (including the tracked structs they created), are reachable; the others
+        // are deleted.
+        let mut dependencies: FxHashMap<DatabaseKeyIndex, Vec<DatabaseKeyIndex>> =
+            FxHashMap::default();
+        let mut reachable = FxHashSet::default();
+        let mut stack = vec![];
+        for route in routes.all_routes() {
+            let ingredient = route(jars);
+            let ingredient_index = ingredient.ingredient_index();
+            ingredient.sweep_edges(oldest_kept, &mut |key_index, kept, inputs| {
+                let key = DatabaseKeyIndex {
+                    ingredient_index,
+                    key_index,
+                };
+                let inputs = inputs
+                    .filter_map(|input| DatabaseKeyIndex::try_from(input).ok())
+                    .collect();
+                dependencies.insert(key, inputs);
+                if kept && reachable.insert(key) {
+                    stack.push(key);
+                }
+            });
+        }
+        while let Some(key) = stack.pop() {
+            for &input in &dependencies[&key] {
+                if dependencies.contains_key(&input) && reachable.insert(input) {
+                    stack.push(input);
+                }
+            }
+        }
+
+        for route in routes.all_routes_mut() {
+            route(jars).sweep(oldest_kept, &reachable);
+        }
+        self.runtime.shared_values().release_unused();
+    }
+
+    /// Discards the values of the tracked functions with the `weak` option, whose values
+    /// are expensive but can be recomputed: call this when memory runs low. As with
+    /// [`Storage::sweep`], the dependency information of those values is kept, so they
+    /// are re-validated and re-computed only if they are requested again; the functions
+    /// that depend on them are not executed again unless their values change.
+    ///
+    /// Like a write, this will cancel any ongoing work and start a new revision.
+    pub fn release_weak_memos(&mut self) {
+        let routes = self.routes.clone();
+        let (jars, _) = self.jars_mut();
+        for route in routes.all_routes_mut() {
+            route(jars).release_weak_memos();
+        }
+    }
+
+    /// Restores the fields of the inputs with the `history` option to the values
+    /// they had in `revision`, as writes in a new revision. The fields of other inputs
+    /// are left as they are. Inputs created after `revision` are not deleted: their
+    /// fields get the values they were created with.
+    ///
+    /// Fails, without changing anything, if the history of some input does not go
+    /// back to `revision`. Panics if `revision` is after the current revision.
+    ///
+    /// Like a write, this will cancel any ongoing work and start a new revision.
+    pub fn revert_to(&mut self, revision: Revision) -> Result<(), RevertError> {
+        let current_revision = self.runtime.current_revision();
+        assert!(
+            revision <= current_revision,
+            "cannot revert to {revision:?}, which is after the current revision {current_revision:?}"
+        );
+
+        let jars = self.shared.jars.as_ref().unwrap();
+        let oldest = self
+            .routes
+            .all_routes()
+            .filter_map(|route| route(jars).history_start())
+            .max();
+        if let Some(oldest) = oldest {
+            if revision < oldest {
+                return Err(RevertError { revision, oldest });
+            }
+        }
+
+        let routes = self.routes.clone();
+        let (jars, runtime) = self.jars_mut();
+        for route in routes.all_routes_mut() {
+            route(jars).revert_to(runtime, revision);
+        }
+        Ok(())
+    }
+
+    /// Registers `refresh`, which calls a query again and reports its value if it changed
+    /// (see [`Database::subscribe`](`crate::Database::subscribe`)). The subscriptions are
+    /// refreshed when the first tracked function called in a revision returns (see
+    /// [`Storage::refresh_stale_subscriptions`]), or with [`Storage::refresh_subscriptions`].
+    pub fn add_subscription(&self, refresh: Box<dyn FnMut(&DB) + Send>) -> SubscriptionId {
+        self.subscriptions.add(refresh)
+    }
+
+    /// Removes a subscription. Does nothing if it was already removed.
+    pub fn remove_subscription(&self, id: SubscriptionId) {
+        self.subscriptions.remove(id);
+    }
+
+    /// Refreshes each subscription, in the order in which they were added.
+    /// This must not be called from the callback of a subscription.
+    pub fn refresh_subscriptions(&self, db: &DB) {
+        self.subscriptions
+            .refresh(db, self.runtime.current_revision());
+    }
+
+    /// Refreshes the subscriptions, unless they were already refreshed in the current
+    /// revision. Called when a tracked function called from outside of any query returns.
+    pub fn refresh_stale_subscriptions(&self, db: &DB) {
+        self.subscriptions
+            .refresh_if_stale(db, self.runtime.current_revision());
+    }
+
     // ANCHOR: cancel_other_workers
     /// Sets cancellation flag and blocks until all other workers with access
     /// to this storage have completed.
@@ -132,33 +488,281 @@ where
     /// This could deadlock if there is a single worker with two handles to the
     /// same database!
     fn cancel_other_workers(&mut self) {
-        loop {
-            self.runtime.set_cancellation_flag();
+        self.runtime.set_cancellation_flag();
 
+        let cvar_mutex = self.shared.cvar_mutex.clone();
+        let mut guard = cvar_mutex.lock();
+        loop {
             // If we have unique access to the jars, we are done.
             if Arc::get_mut(self.shared.jars.as_mut().unwrap()).is_some() {
                 return;
             }
 
             // Otherwise, wait until some other storage entities have dropped.
-            // We create a mutex here because the cvar api requires it, but we
-            // don't really need one as the data being protected is actually
-            // the jars above.
-            //
-            // The cvar `self.shared.cvar` is notified by the `Drop` impl.
-            let mutex = parking_lot::Mutex::new(());
-            let mut guard = mutex.lock();
+            // The cvar `self.shared.cvar` is notified by the `Drop` impl,
+            // which cannot happen between the check above and the wait
+            // since it takes `cvar_mutex` first.
             self.shared.cvar.wait(&mut guard);
         }
     }
     // ANCHOR_END: cancel_other_workers
 
+    /// Walks the dependencies recorded for the memoized queries whose label
+    /// (e.g., `my_query(0)`) is accepted by `query_filter`, along with
+    /// the dependencies of the queries they read, transitively.
+    ///
+    /// The dependencies are the ones recorded the last time each query executed,
+    /// so this can be used to find out why a particular memo got invalidated.
+    pub fn dependency_graph(&self, query_filter: impl Fn(&str) -> bool) -> DependencyGraph {
+        let mut graph = DependencyGraph::default();
+        let mut stack = vec![];
+        for (index, route) in self.routes.all_routes().enumerate() {
+            let ingredient = route(self.shared.jars.as_ref().unwrap());
+            for key_index in ingredient.memoized_keys() {
+                let label = format!("{:?}", FmtIndex(ingredient, Some(key_index)));
+                if query_filter(&label) {
+                    let query = DatabaseKeyIndex {
+                        ingredient_index: IngredientIndex::from(index),
+                        key_index,
+                    };
+                    graph.add_node(query.into(), || label);
+                    stack.push(query);
+                }
+            }
+        }
+
+        let mut visited = FxHashSet::default();
+        while let Some(query) = stack.pop() {
+            if !visited.insert(query) {
+                continue;
+            }
+            let edges = match self
+                .ingredient(query.ingredient_index)
+                .origin(query.key_index)
+            {
+                Some(QueryOrigin::Derived(edges)) | Some(QueryOrigin::DerivedUntracked(edges)) => {
+                    edges
+                }
+                _ => continue,
+            };
+            for input in edges.inputs() {
+                let ingredient = self.ingredient(input.ingredient_index());
+                graph.add_node(input, || {
+                    format!("{:?}", FmtIndex(ingredient, input.key_index()))
+                });
+                graph.add_edge(query, input);
+                if let Ok(input) = DatabaseKeyIndex::try_from(input) {
+                    stack.push(input);
+                }
+            }
+        }
+        graph
+    }
+
+    /// Returns the counters recorded for each tracked function, in the order
+    /// in which their ingredients were created. Empty unless the `metrics`
+    /// feature is enabled.
+    pub fn query_metrics(&self) -> Vec<QueryMetrics> {
+        self.routes
+            .all_routes()
+            .filter_map(|route| route(self.shared.jars.as_ref().unwrap()).query_metrics())
+            .collect()
+    }
+
+    /// Returns the memory used by the memoized values of each function ingredient,
+    /// in the order in which the ingredients were created.
+    pub fn memory_report(&self) -> MemoryReport {
+        let jars = self.shared.jars.as_ref().unwrap();
+        let ingredients = self
+            .routes
+            .all_routes()
+            .enumerate()
+            .filter_map(|(index, route)| {
+                let mut memory = route(jars).memory_usage()?;
+                memory.jar = self.routes.jar_name(IngredientIndex::from(index));
+                Some(memory)
+            })
+            .collect();
+        MemoryReport { ingredients }
+    }
+
+    /// Returns what the ingredients of the jar named `jar` (e.g. `my_crate::Jar`)
+    /// store, in the order in which they were created. The ingredients of the jars
+    /// it includes are not listed.
+    pub fn dump_jar(&self, jar: &'static str) -> JarDump {
+        let jars = self.shared.jars.as_ref().unwrap();
+        let ingredients = self
+            .routes
+            .all_routes()
+            .enumerate()
+            .map(|(index, route)| (IngredientIndex::from(index), route(jars)))
+            .filter(|&(index, _)| self.routes.jar_name(index) == jar)
+            .map(|(index, ingredient)| {
+                let debug_name = ingredient.debug_name();
+                let name = match self.routes.struct_name(index) {
+                    Some(struct_name) => format!("{struct_name}::{debug_name}"),
+                    None => debug_name.to_string(),
+                };
+                IngredientDump {
+                    name,
+                    ..ingredient.dump()
+                }
+            })
+            .collect();
+        JarDump { jar, ingredients }
+    }
+
+    /// Returns the names under which the tracked functions can be called with
+    /// [`Storage::fetch_dyn`], in the order in which their ingredients were created.
+    /// Each name is the name of the jar followed by the name of the function,
+    /// e.g. `my_crate::Jar::my_query`.
+    pub fn query_names(&self) -> Vec<String> {
+        let jars = self.shared.jars.as_ref().unwrap();
+        self.routes
+            .all_routes()
+            .enumerate()
+            .filter(|(_, route)| route(jars).can_fetch_dyn())
+            .map(|(index, _)| self.stable_name(IngredientIndex::from(index)))
+            .collect()
+    }
+
+    /// Calls the tracked function named `name` (see [`Storage::query_names`]) for `key`
+    /// and returns a clone of its value, or `None` if there is no such function.
+    ///
+    /// The key is the id of the salsa struct that the function takes; for a function
+    /// with more arguments, the id of its interned arguments; and for a function
+    /// with no arguments, `Id::from_u32(0)`. Like a call to the function itself,
+    /// this panics if the key is not valid.
+    pub fn fetch_dyn(&self, db: &DB, name: &str, key: Id) -> Option<Box<dyn Any>> {
+        let jars = self.shared.jars.as_ref().unwrap();
+        let (_, route) = self
+            .routes
+            .all_routes()
+            .enumerate()
+            .filter(|(_, route)| route(jars).can_fetch_dyn())
+            .find(|(index, _)| self.stable_name(IngredientIndex::from(*index)) == name)?;
+        route(jars).fetch_dyn(db, key)
+    }
+
+    /// Returns a name for the ingredient `index` that does not depend on the order in
+    /// which the ingredients were created: the name of its jar, then the name of the
+    /// salsa struct if it stores one of its fields, then its own name,
+    /// e.g. `my_crate::Jar::my_query` or `my_crate::Jar::MyInput::field`.
+    pub fn stable_name(&self, index: IngredientIndex) -> String {
+        let jar_name = self.routes.jar_name(index);
+        let debug_name = self.ingredient(index).debug_name();
+        match self.routes.struct_name(index) {
+            Some(struct_name) => format!("{jar_name}::{struct_name}::{debug_name}"),
+            None => format!("{jar_name}::{debug_name}"),
+        }
+    }
+
+    /// Returns the ingredient whose [stable name](`Storage::stable_name`) is `name`, if any.
+    pub fn ingredient_by_stable_name(&self, name: &str) -> Option<IngredientIndex> {
+        (0..self.routes.all_routes().count())
+            .map(IngredientIndex::from)
+            .find(|&index| self.stable_name(index) == name)
+    }
+
+    /// Converts `index` into a [`StableKey`], which names its ingredient.
+    pub fn stable_key(&self, index: DependencyIndex) -> StableKey {
+        StableKey {
+            ingredient: self.stable_name(index.ingredient_index),
+            key: index.key_index,
+        }
+    }
+
+    /// Converts a [`StableKey`] back into a `DependencyIndex`, or returns `None`
+    /// if this database has no ingredient with that name.
+    pub fn resolve_stable_key(&self, key: &StableKey) -> Option<DependencyIndex> {
+        Some(DependencyIndex {
+            ingredient_index: self.ingredient_by_stable_name(&key.ingredient)?,
+            key_index: key.key,
+        })
+    }
+
+    /// Returns the memoized queries whose value changed after `revision`, in order.
+    ///
+    /// Each memo is only as recent as the last time its query was called (or
+    /// validated), so the queries of interest should be called again first:
+    /// a query that would produce a new value but has not been called yet
+    /// is not reported.
+    pub fn changed_since(&self, revision: Revision) -> Vec<DatabaseKeyIndex> {
+        let mut changed = vec![];
+        for (index, route) in self.routes.all_routes().enumerate() {
+            let ingredient = route(self.shared.jars.as_ref().unwrap());
+            changed.extend(
+                ingredient
+                    .changed_since(revision)
+                    .into_iter()
+                    .map(|key_index| DatabaseKeyIndex {
+                        ingredient_index: IngredientIndex::from(index),
+                        key_index,
+                    }),
+            );
+        }
+        changed.sort();
+        changed
+    }
+
+    /// Returns the queries of the tracked functions whose key has type `key_type`
+    /// (e.g., the functions taking a given salsa struct) that have a memo for `key`,
+    /// in the order in which their ingredients were created.
+    pub fn queries_keyed_by(&self, key_type: TypeId, key: Id) -> Vec<DatabaseKeyIndex> {
+        let jars = self.shared.jars.as_ref().unwrap();
+        self.routes
+            .all_routes()
+            .enumerate()
+            .filter(|(_, route)| {
+                let ingredient = route(jars);
+                ingredient.key_type() == Some(key_type) && ingredient.origin(key).is_some()
+            })
+            .map(|(index, _)| DatabaseKeyIndex {
+                ingredient_index: IngredientIndex::from(index),
+                key_index: key,
+            })
+            .collect()
+    }
+
+    /// Returns the query that created the tracked struct `entity`, or `None` if
+    /// `entity` is not a tracked struct, or was deleted because that query no longer
+    /// creates it.
+    pub fn creator_of(&self, entity: DatabaseKeyIndex) -> Option<DatabaseKeyIndex> {
+        self.ingredient(entity.ingredient_index)
+            .creator(entity.key_index)
+    }
+
+    /// Returns the tracked structs created by the last execution of `query`,
+    /// in the order in which they were created; none if `query` has no memo.
+    pub fn entities_created_by(&self, query: DatabaseKeyIndex) -> Vec<DatabaseKeyIndex> {
+        let Some(origin) = self
+            .ingredient(query.ingredient_index)
+            .origin(query.key_index)
+        else {
+            return vec![];
+        };
+        origin
+            .outputs()
+            .filter_map(|output| DatabaseKeyIndex::try_from(output).ok())
+            .filter(|&output| self.creator_of(output) == Some(query))
+            .collect()
+    }
+
     pub fn ingredient(&self, ingredient_index: IngredientIndex) -> &dyn Ingredient<DB> {
         let route = self.routes.route(ingredient_index);
         route(self.shared.jars.as_ref().unwrap())
     }
 }
 
+/// Formats an index the way the ingredient owning it does.
+struct FmtIndex<'i, DB>(&'i dyn Ingredient<DB>, Option<Id>);
+
+impl<DB> fmt::Debug for FmtIndex<'_, DB> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt_index(self.1, fmt)
+    }
+}
+
 impl<DB> Clone for Shared<DB>
 where
     DB: HasJars,
@@ -167,6 +771,7 @@ where
         Self {
             jars: self.jars.clone(),
             cvar: self.cvar.clone(),
+            cvar_mutex: self.cvar_mutex.clone(),
         }
     }
 }
@@ -176,9 +781,15 @@ where
     DB: HasJars,
 {
     fn drop(&mut self) {
+        #[cfg(debug_assertions)]
+        for leak in self.leak_report() {
+            log::warn!("likely leak of keys: {leak}");
+        }
+
         // Drop the Arc reference before the cvar is notified,
         // since other threads are sleeping, waiting for it to reach 1.
         drop(self.shared.jars.take());
+        let _guard = self.shared.cvar_mutex.lock();
         self.shared.cvar.notify_all();
     }
 }
@@ -193,14 +804,14 @@ pub trait HasJars: HasJarsDyn + Sized {
     fn jars_mut(&mut self) -> (&mut Self::Jars, &mut Runtime);
 
     fn create_jars(routes: &mut Routes<Self>) -> Box<Self::Jars>;
-}
 
-pub trait DbWithJar<J>: HasJar<J> + Database {
-    fn as_jar_db<'db>(&'db self) -> &<J as Jar<'db>>::DynDb
-    where
-        J: Jar<'db>;
+    /// Copies the jars for a pipelined write, or returns `None` if some ingredient
+    /// cannot be copied. See [`Storage::set_pipelined_writes`].
+    fn fork_jars(jars: &Self::Jars) -> Option<Box<Self::Jars>>;
 }
 
+pub trait DbWithJar<J>: HasJar<J> + Database {}
+
 pub trait JarFromJars<J>: HasJars {
     fn jar_from_jars(jars: &Self::Jars) -> &J;
 
@@ -213,6 +824,10 @@ pub trait HasJar<J> {
     fn jar_mut(&mut self) -> (&mut J, &mut Runtime);
 }
 
+/// Does nothing, but only compiles if the database `DB` has the jar `J`.
+/// Emitted by `#[salsa::requires(J)]` for functions taking a `&dyn Db`.
+pub fn require_jar<J, DB: ?Sized + HasJar<J>>(_db: &DB) {}
+
 // ANCHOR: HasJarsDyn
 /// Dyn friendly subset of HasJars
 pub trait HasJarsDyn {
@@ -243,6 +858,76 @@ pub trait HasJarsDyn {
     fn salsa_struct_deleted(&self, ingredient: IngredientIndex, id: Id);
 
     fn fmt_index(&self, index: DependencyIndex, fmt: &mut fmt::Formatter<'_>) -> fmt::Result;
+
+    /// Returns the name of the ingredient `ingredient`.
+    /// See [`Ingredient::debug_name`](`crate::ingredient::Ingredient::debug_name`).
+    fn ingredient_debug_name(&self, ingredient: IngredientIndex) -> &'static str;
+
+    /// Returns the counters recorded for each tracked function.
+    /// See [`Storage::query_metrics`].
+    fn ingredient_metrics(&self) -> Vec<QueryMetrics>;
+
+    /// Returns the memory used by the memoized values of each function ingredient.
+    /// See [`Storage::memory_report`].
+    fn ingredient_memory(&self) -> MemoryReport;
+
+    /// Returns what the ingredients of the jar named `jar` store.
+    /// See [`Storage::dump_jar`].
+    fn dump_jar(&self, jar: &'static str) -> JarDump;
+
+    /// Returns the names under which the tracked functions can be called.
+    /// See [`Storage::query_names`].
+    fn dyn_query_names(&self) -> Vec<String>;
+
+    /// Calls the tracked function named `name` for `key`.
+    /// See [`Storage::fetch_dyn`].
+    fn dyn_fetch(&self, name: &str, key: Id) -> Option<Box<dyn Any>>;
+
+    /// Converts `index` into a key that names its ingredient.
+    /// See [`Storage::stable_key`].
+    fn dyn_stable_key(&self, index: DependencyIndex) -> StableKey;
+
+    /// Converts a key that names its ingredient back into a `DependencyIndex`.
+    /// See [`Storage::resolve_stable_key`].
+    fn dyn_resolve_stable_key(&self, key: &StableKey) -> Option<DependencyIndex>;
+
+    /// Restores the inputs with the `history` option to their values in `revision`.
+    /// See [`Storage::revert_to`].
+    fn revert_inputs(&mut self, revision: Revision) -> Result<(), RevertError>;
+
+    /// Returns the memoized queries whose value changed after `revision`.
+    /// See [`Storage::changed_since`].
+    fn memos_changed_since(&self, revision: Revision) -> Vec<DatabaseKeyIndex>;
+
+    /// Returns the memoized queries of the tracked functions whose key has type `key_type`.
+    /// See [`Storage::queries_keyed_by`].
+    fn queries_keyed_by(&self, key_type: TypeId, key: Id) -> Vec<DatabaseKeyIndex>;
+
+    /// Returns the query that created a tracked struct.
+    /// See [`Storage::creator_of`].
+    fn entity_creator(&self, entity: DatabaseKeyIndex) -> Option<DatabaseKeyIndex>;
+
+    /// Returns the tracked structs created by a query.
+    /// See [`Storage::entities_created_by`].
+    fn created_entities(&self, query: DatabaseKeyIndex) -> Vec<DatabaseKeyIndex>;
+
+    /// Enables or disables pipelined writes.
+    /// See [`Storage::set_pipelined_writes`].
+    fn pipeline_writes(&mut self, enabled: bool);
+
+    /// Registers a subscription to the value of a query.
+    /// See [`Storage::add_subscription`].
+    fn add_subscription(&self, refresh: Box<dyn FnMut(&Self) + Send>) -> SubscriptionId
+    where
+        Self: Sized;
+
+    /// Removes a subscription.
+    /// See [`Storage::remove_subscription`].
+    fn remove_subscription(&self, id: SubscriptionId);
+
+    /// Refreshes the subscriptions, or only those not refreshed in the current revision.
+    /// See [`Storage::refresh_subscriptions`] and [`Storage::refresh_stale_subscriptions`].
+    fn run_subscriptions(&self, only_stale: bool);
 }
 // ANCHOR_END: HasJarsDyn
 
@@ -254,11 +939,21 @@ where
     fn ingredient_mut(&mut self) -> &mut I::Ingredients;
 }
 
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be listed in a jar",
+    label = "not a salsa item",
+    note = "jars list the salsa structs, tracked functions and accumulators they contain"
+)]
 pub trait IngredientsFor {
     type Jar;
     type Ingredients;
 
     fn create_ingredients<DB>(routes: &mut Routes<DB>) -> Self::Ingredients
     where
-        DB: DbWithJar<Self::Jar> + JarFromJars<Self::Jar>;
+        DB: DbWithJar<Self::Jar> + JarFromJars<Self::Jar>,
+        Self::Jar: JarInDb<DB>;
+
+    /// Copies the ingredients for a pipelined write, or returns `None` if they
+    /// cannot be copied. See [`Storage::set_pipelined_writes`].
+    fn fork_ingredients(ingredients: &Self::Ingredients) -> Option<Self::Ingredients>;
 }
@@ -0,0 +1,69 @@
+//! Interned strings, as needed for the identifiers and symbols of most compilers.
+//!
+//! Add [`Jar`] to your database (e.g. `#[salsa::db(crate::Jar, salsa::string::Jar)]`)
+//! and make your database trait extend [`Db`] to use [`Str`] and [`StaticStr`].
+
+use std::fmt;
+
+#[salsa::jar(db = Db)]
+pub struct Jar(Str, StaticStr);
+
+/// The database trait of [`Jar`], implemented by every database that includes it.
+pub trait Db: salsa::DbWithJar<Jar> {}
+
+impl<DB: ?Sized + salsa::DbWithJar<Jar>> Db for DB {}
+
+/// An interned string, created with `Str::new(db, text)`.
+///
+/// Copying, comparing and hashing a `Str` are O(1). `Str`s are ordered
+/// by the order in which their text was first interned, not alphabetically.
+#[salsa::interned(jar = Jar)]
+pub struct Str {
+    #[return_ref]
+    text: String,
+}
+
+impl Str {
+    /// Returns the text of this string.
+    pub fn resolve(self, db: &dyn Db) -> &str {
+        self.text(db)
+    }
+
+    /// Returns a value displaying the text of this string.
+    pub fn display(self, db: &dyn Db) -> impl fmt::Display + '_ {
+        self.resolve(db)
+    }
+}
+
+/// An interned string whose text lives for the rest of the program, so that it can be
+/// used without the database: either a literal, given to `StaticStr::new(db, text)`,
+/// or any text leaked with [`StaticStr::leak`].
+///
+/// Like [`Str`], copying, comparing and hashing a `StaticStr` are O(1), and
+/// `StaticStr`s are ordered by the order in which their text was first interned.
+#[salsa::interned(jar = Jar)]
+pub struct StaticStr {
+    text: &'static str,
+}
+
+impl StaticStr {
+    /// Interns `text`, leaking a copy of it unless the same text was already interned.
+    pub fn leak(db: &dyn Db, text: &str) -> Self {
+        // SAFETY: `lookup` only compares `text` with the interned texts, without keeping it.
+        let borrowed: &'static str = unsafe { &*(text as *const str) };
+        if let Some(interned) = StaticStr::lookup(db, borrowed) {
+            return interned;
+        }
+        StaticStr::new(db, Box::leak(text.into()))
+    }
+
+    /// Returns the text of this string.
+    pub fn resolve(self, db: &dyn Db) -> &'static str {
+        self.text(db)
+    }
+
+    /// Returns a value displaying the text of this string.
+    pub fn display(self, db: &dyn Db) -> impl fmt::Display {
+        self.resolve(db)
+    }
+}
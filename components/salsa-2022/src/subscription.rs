@@ -0,0 +1,88 @@
+//! Subscriptions to the values of queries, registered with
+//! [`Database::subscribe`](`crate::Database::subscribe`).
+
+use std::sync::Arc;
+
+use crossbeam::atomic::AtomicCell;
+
+use crate::sync::Mutex;
+use crate::Revision;
+
+/// Identifies a subscription, to remove it with
+/// [`Database::unsubscribe`](`crate::Database::unsubscribe`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SubscriptionId(u32);
+
+/// Calls the query of a subscription again, and its callback if the value changed.
+type Refresh<DB> = Arc<Mutex<Box<dyn FnMut(&DB) + Send>>>;
+
+/// The subscriptions of a database, shared between the database and its snapshots.
+pub(crate) struct Subscriptions<DB> {
+    entries: Mutex<Vec<(SubscriptionId, Refresh<DB>)>>,
+
+    next_id: AtomicCell<u32>,
+
+    /// The last revision in which the subscriptions were refreshed.
+    refreshed_at: AtomicCell<Option<Revision>>,
+}
+
+impl<DB> Default for Subscriptions<DB> {
+    fn default() -> Self {
+        Self {
+            entries: Default::default(),
+            next_id: AtomicCell::new(0),
+            refreshed_at: AtomicCell::new(None),
+        }
+    }
+}
+
+impl<DB> Subscriptions<DB> {
+    pub(crate) fn add(&self, refresh: Box<dyn FnMut(&DB) + Send>) -> SubscriptionId {
+        let id = SubscriptionId(self.next_id.fetch_add(1));
+        self.entries
+            .lock()
+            .push((id, Arc::new(Mutex::new(refresh))));
+        id
+    }
+
+    pub(crate) fn remove(&self, id: SubscriptionId) {
+        self.entries.lock().retain(|(entry_id, _)| *entry_id != id);
+    }
+
+    /// Refreshes the subscriptions, unless they were already refreshed in `revision`.
+    pub(crate) fn refresh_if_stale(&self, db: &DB, revision: Revision) {
+        let refreshed_at = self.refreshed_at.load();
+        if refreshed_at == Some(revision) {
+            return;
+        }
+
+        // If another thread just claimed this revision, it refreshes them.
+        if self
+            .refreshed_at
+            .compare_exchange(refreshed_at, Some(revision))
+            .is_ok()
+        {
+            self.refresh_entries(db);
+        }
+    }
+
+    /// Refreshes the subscriptions for `revision`, even if they were already refreshed in it.
+    pub(crate) fn refresh(&self, db: &DB, revision: Revision) {
+        self.refreshed_at.store(Some(revision));
+        self.refresh_entries(db);
+    }
+
+    /// Refreshes each subscription, in the order in which they were added.
+    ///
+    /// The lock is not held while the queries execute, since those may
+    /// add or remove subscriptions, or trigger [`Subscriptions::refresh_if_stale`].
+    fn refresh_entries(&self, db: &DB) {
+        let entries = self.entries.lock().clone();
+        for (id, refresh) in entries {
+            let still_subscribed = self.entries.lock().iter().any(|(other, _)| *other == id);
+            if still_subscribed {
+                (refresh.lock())(db);
+            }
+        }
+    }
+}
@@ -0,0 +1,146 @@
+//! The locks and condition variables used by the runtime, with the API of `parking_lot`.
+//!
+//! They come from `parking_lot` by default. With the `std-sync` feature (or without the
+//! default `parking_lot` feature), they wrap those of the standard library instead; and
+//! when building with `--cfg loom`, those of `loom`, so that `loom` explores the orders in
+//! which threads take these locks and wait on these condition variables.
+//!
+//! Only these primitives go through `loom`: the atomics of the runtime (e.g., the flag
+//! cancelling the current revision) and those used inside `dashmap`, `arc-swap` and
+//! `crossbeam` are not instrumented, so their interleavings are not explored.
+
+#[cfg(all(feature = "parking_lot", not(feature = "std-sync"), not(loom)))]
+pub(crate) use parking_lot::{Condvar, Mutex, MutexGuard, RwLock};
+
+#[cfg(any(not(feature = "parking_lot"), feature = "std-sync", loom))]
+pub(crate) use self::wrappers::{Condvar, Mutex, MutexGuard, RwLock};
+
+#[cfg(any(not(feature = "parking_lot"), feature = "std-sync", loom))]
+mod wrappers {
+    use std::ops::{Deref, DerefMut};
+    use std::sync::PoisonError;
+
+    #[cfg(loom)]
+    use loom::sync as imp;
+    #[cfg(not(loom))]
+    use std::sync as imp;
+
+    /// A mutual exclusion lock. As with `parking_lot`, locking ignores poisoning:
+    /// salsa unwinds through queries on cancellation, while holding no invariant
+    /// that a panic could break.
+    #[derive(Debug)]
+    pub(crate) struct Mutex<T>(imp::Mutex<T>);
+
+    impl<T: Default> Default for Mutex<T> {
+        fn default() -> Self {
+            Mutex::new(T::default())
+        }
+    }
+
+    impl<T> Mutex<T> {
+        pub(crate) fn new(value: T) -> Self {
+            Mutex(imp::Mutex::new(value))
+        }
+
+        pub(crate) fn lock(&self) -> MutexGuard<'_, T> {
+            MutexGuard {
+                mutex: &self.0,
+                guard: Some(self.0.lock().unwrap_or_else(PoisonError::into_inner)),
+            }
+        }
+
+        pub(crate) fn get_mut(&mut self) -> &mut T {
+            self.0.get_mut().unwrap_or_else(PoisonError::into_inner)
+        }
+
+        #[cfg(feature = "parallel")]
+        pub(crate) fn into_inner(self) -> T {
+            self.0.into_inner().unwrap_or_else(PoisonError::into_inner)
+        }
+    }
+
+    /// The guard of a [`Mutex`]. The inner guard is only taken out
+    /// while [`Condvar::wait`] is waiting or [`MutexGuard::unlocked`] runs.
+    pub(crate) struct MutexGuard<'a, T> {
+        #[cfg_attr(not(feature = "parallel"), allow(dead_code))]
+        mutex: &'a imp::Mutex<T>,
+        guard: Option<imp::MutexGuard<'a, T>>,
+    }
+
+    impl<T> MutexGuard<'_, T> {
+        /// Releases the lock while `f` runs, like `parking_lot::MutexGuard::unlocked`.
+        #[cfg(feature = "parallel")]
+        pub(crate) fn unlocked<R>(guard: &mut Self, f: impl FnOnce() -> R) -> R {
+            drop(guard.guard.take());
+            let result = f();
+            guard.guard = Some(guard.mutex.lock().unwrap_or_else(PoisonError::into_inner));
+            result
+        }
+    }
+
+    impl<T> Deref for MutexGuard<'_, T> {
+        type Target = T;
+
+        fn deref(&self) -> &T {
+            self.guard.as_ref().unwrap()
+        }
+    }
+
+    impl<T> DerefMut for MutexGuard<'_, T> {
+        fn deref_mut(&mut self) -> &mut T {
+            self.guard.as_mut().unwrap()
+        }
+    }
+
+    #[derive(Debug)]
+    pub(crate) struct Condvar(imp::Condvar);
+
+    impl Default for Condvar {
+        fn default() -> Self {
+            Condvar::new()
+        }
+    }
+
+    impl Condvar {
+        pub(crate) fn new() -> Self {
+            Condvar(imp::Condvar::new())
+        }
+
+        /// Releases the lock of `guard` until notified, like `parking_lot::Condvar::wait`.
+        pub(crate) fn wait<T>(&self, guard: &mut MutexGuard<'_, T>) {
+            let inner = guard.guard.take().unwrap();
+            guard.guard = Some(self.0.wait(inner).unwrap_or_else(PoisonError::into_inner));
+        }
+
+        pub(crate) fn notify_one(&self) {
+            self.0.notify_one();
+        }
+
+        pub(crate) fn notify_all(&self) {
+            self.0.notify_all();
+        }
+    }
+
+    #[derive(Debug)]
+    pub(crate) struct RwLock<T>(imp::RwLock<T>);
+
+    impl<T: Default> Default for RwLock<T> {
+        fn default() -> Self {
+            RwLock::new(T::default())
+        }
+    }
+
+    impl<T> RwLock<T> {
+        pub(crate) fn new(value: T) -> Self {
+            RwLock(imp::RwLock::new(value))
+        }
+
+        pub(crate) fn read(&self) -> imp::RwLockReadGuard<'_, T> {
+            self.0.read().unwrap_or_else(PoisonError::into_inner)
+        }
+
+        pub(crate) fn write(&self) -> imp::RwLockWriteGuard<'_, T> {
+            self.0.write().unwrap_or_else(PoisonError::into_inner)
+        }
+    }
+}
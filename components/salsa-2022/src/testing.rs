@@ -0,0 +1,228 @@
+//! Helpers for testing databases: recording the events of a database to check
+//! which tracked functions were executed (see [`assert_executes!`](`crate::assert_executes`)
+//! and [`assert_memoized!`](`crate::assert_memoized`)), stubbing the tracked functions
+//! called by the one under test with a [`MockDatabase`], and coordinating threads
+//! with a [`Signal`].
+
+use crate::debug::DebugWithDb;
+#[cfg(feature = "parallel")]
+use crate::sync::Condvar;
+use crate::sync::Mutex;
+use crate::{Database, DatabaseKeyIndex, Event, EventKind};
+
+/// The events of a database, recorded by its `salsa_event` method:
+///
+/// ```ignore
+/// impl salsa::Database for Database {
+///     fn salsa_event(&self, event: salsa::Event) {
+///         self.execution_log.record(event);
+///     }
+/// }
+/// ```
+#[derive(Default)]
+pub struct ExecutionLog {
+    events: Mutex<Vec<Event>>,
+}
+
+impl ExecutionLog {
+    /// Records `event`.
+    pub fn record(&self, event: Event) {
+        self.events.lock().push(event);
+    }
+
+    /// Returns the events recorded so far, clearing the log.
+    pub fn take(&self) -> Vec<Event> {
+        std::mem::take(&mut *self.events.lock())
+    }
+
+    /// Clears the log.
+    pub fn clear(&self) {
+        self.events.lock().clear();
+    }
+
+    /// Returns the queries executed since the log was last cleared, in order.
+    pub fn executed(&self) -> Vec<DatabaseKeyIndex> {
+        self.events
+            .lock()
+            .iter()
+            .filter_map(|event| match event.kind {
+                EventKind::WillExecute { database_key } => Some(database_key),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// A database that records its events in an [`ExecutionLog`],
+/// as required by [`assert_executes!`](`crate::assert_executes`)
+/// and [`assert_memoized!`](`crate::assert_memoized`).
+pub trait LoggingDatabase: Database {
+    fn execution_log(&self) -> &ExecutionLog;
+}
+
+/// Asserts that calling `query(db, args...)` executes the tracked function `query`,
+/// rather than reusing its memoized value, and returns the result of the call.
+///
+/// The database must implement [`LoggingDatabase`](`crate::testing::LoggingDatabase`).
+/// Executing `query` for other arguments does not count. The arguments are evaluated
+/// twice: they are cloned first to find the call in the log.
+#[macro_export]
+macro_rules! assert_executes {
+    ($db:expr, $query:path $(, $arg:expr)* $(,)?) => {{
+        let db = $db;
+        let database_key = <$query>::database_key_index(db $(, ::std::clone::Clone::clone(&$arg))*);
+        $crate::testing::LoggingDatabase::execution_log(db).clear();
+        let value = $query(db $(, $arg)*);
+        $crate::testing::check_executed(db, database_key, true);
+        value
+    }};
+}
+
+/// Asserts that calling `query(db, args...)` reuses the memoized value of the
+/// tracked function `query`, without executing it, and returns the result of the call.
+///
+/// The database must implement [`LoggingDatabase`](`crate::testing::LoggingDatabase`).
+/// As with [`assert_executes!`](`crate::assert_executes`), only executing `query`
+/// for these arguments fails the assertion, and the arguments are evaluated twice.
+#[macro_export]
+macro_rules! assert_memoized {
+    ($db:expr, $query:path $(, $arg:expr)* $(,)?) => {{
+        let db = $db;
+        let database_key = <$query>::database_key_index(db $(, ::std::clone::Clone::clone(&$arg))*);
+        $crate::testing::LoggingDatabase::execution_log(db).clear();
+        let value = $query(db $(, $arg)*);
+        $crate::testing::check_executed(db, database_key, false);
+        value
+    }};
+}
+
+#[doc(hidden)]
+pub fn check_executed<DB: ?Sized + LoggingDatabase>(
+    db: &DB,
+    database_key: DatabaseKeyIndex,
+    expected: bool,
+) {
+    let executed = db.execution_log().executed();
+    if executed.contains(&database_key) != expected {
+        let executed: Vec<_> = executed.iter().map(|key| key.debug(db)).collect();
+        panic!(
+            "expected `{:?}` {}to be executed, but the executed queries were {executed:?}",
+            database_key.debug(db),
+            if expected { "" } else { "not " },
+        );
+    }
+}
+
+/// Builds a database for the unit tests of a tracked function, in which the tracked
+/// functions it calls are stubbed: they return the given values rather than being
+/// executed, so that the test does not have to create all the inputs they read.
+///
+/// ```ignore
+/// let mut mock = MockDatabase::<Database>::default();
+/// let file = File::new(&*mock, path);
+/// mock.stub::<parse>(file, ast).stub::<resolve>((file, name), item);
+/// let db = mock.build();
+/// assert_eq!(check(&db, file), diagnostics);
+/// ```
+///
+/// The mock dereferences to the database, e.g. to create the salsa structs
+/// the stubbed functions are called with.
+pub struct MockDatabase<DB> {
+    db: DB,
+}
+
+impl<DB: Default> Default for MockDatabase<DB> {
+    fn default() -> Self {
+        Self::new(DB::default())
+    }
+}
+
+impl<DB> MockDatabase<DB> {
+    /// Builds on `db`, e.g. a database with a few inputs already set.
+    pub fn new(db: DB) -> Self {
+        MockDatabase { db }
+    }
+
+    /// Stubs the tracked function `Q` (named by the struct with the same name as
+    /// the function), so that it returns `value` when called with `key`: its
+    /// arguments but the database, as a tuple if there are several.
+    ///
+    /// This sets the value as the function's `set` associated function does:
+    /// stubbing the function again for the same key starts a new revision.
+    pub fn stub<Q: StubQuery<DB>>(&mut self, key: Q::Key, value: Q::Value) -> &mut Self {
+        Q::stub(&mut self.db, key, value);
+        self
+    }
+
+    /// Returns the database, on which the function under test is called.
+    pub fn build(self) -> DB {
+        self.db
+    }
+}
+
+impl<DB> std::ops::Deref for MockDatabase<DB> {
+    type Target = DB;
+
+    fn deref(&self) -> &DB {
+        &self.db
+    }
+}
+
+impl<DB> std::ops::DerefMut for MockDatabase<DB> {
+    fn deref_mut(&mut self) -> &mut DB {
+        &mut self.db
+    }
+}
+
+/// Implemented for tracked functions, by the struct with the same name as the function,
+/// to stub them with [`MockDatabase::stub`]. Only implemented for the functions whose
+/// database argument is a trait object (e.g., `&dyn Db`) and that have no generic parameters.
+pub trait StubQuery<DB: ?Sized> {
+    /// The arguments of the function but the database, as a tuple if there are several.
+    type Key;
+
+    /// The value returned by the function.
+    type Value;
+
+    /// Sets the value of the function for `key`, as its `set` associated function does.
+    fn stub(db: &mut DB, key: Self::Key, value: Self::Value);
+}
+
+/// A kind of flexible barrier, to force threads to reach some states in a given order:
+/// [`Signal::wait_for`] blocks until [`Signal::signal`] was called with the same stage
+/// or a later one.
+#[cfg(feature = "parallel")]
+#[derive(Default)]
+pub struct Signal {
+    value: Mutex<usize>,
+    cond_var: Condvar,
+}
+
+#[cfg(feature = "parallel")]
+impl Signal {
+    /// Moves to `stage`, unblocking the threads waiting for it or an earlier stage.
+    /// Does nothing if a later stage was already signaled.
+    pub fn signal(&self, stage: usize) {
+        // This check avoids acquiring the lock for things that will
+        // clearly be a no-op. Not *necessary* but helps to ensure we
+        // are more likely to encounter weird race conditions.
+        if stage > 0 {
+            let mut v = self.value.lock();
+            if stage > *v {
+                *v = stage;
+                self.cond_var.notify_all();
+            }
+        }
+    }
+
+    /// Waits until `stage`, or a later one, is signaled.
+    pub fn wait_for(&self, stage: usize) {
+        // As above, avoid lock if clearly a no-op.
+        if stage > 0 {
+            let mut v = self.value.lock();
+            while *v < stage {
+                self.cond_var.wait(&mut v);
+            }
+        }
+    }
+}
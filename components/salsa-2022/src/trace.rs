@@ -0,0 +1,78 @@
+//! Spans and events for the `tracing` crate, only emitted when the `tracing` feature is enabled.
+
+use crate::{key::DatabaseKeyIndex, Cancelled, Cycle, Database};
+
+#[cfg(feature = "tracing")]
+use crate::debug::DebugWithDb;
+
+/// The span covering one execution of a tracked function, with the name of
+/// the function (`query`), the key it was executed for (`key`) and, once the
+/// new value has been compared with the old one, whether it was backdated
+/// (`backdated`). Without the `tracing` feature, this is empty.
+pub(crate) struct ExecuteSpan {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+#[cfg(feature = "tracing")]
+impl ExecuteSpan {
+    pub(crate) fn new<Db: ?Sized + Database>(
+        db: &Db,
+        query: &'static str,
+        database_key_index: DatabaseKeyIndex,
+    ) -> Self {
+        ExecuteSpan {
+            span: tracing::info_span!(
+                "execute",
+                query,
+                key = ?database_key_index.debug(db),
+                backdated = tracing::field::Empty,
+            ),
+        }
+    }
+
+    /// Enters the span until the returned guard is dropped.
+    pub(crate) fn enter(&self) -> tracing::span::Entered<'_> {
+        self.span.enter()
+    }
+
+    pub(crate) fn record_backdated(&self, backdated: bool) {
+        self.span.record("backdated", backdated);
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+impl ExecuteSpan {
+    pub(crate) fn new<Db: ?Sized + Database>(
+        _db: &Db,
+        _query: &'static str,
+        _database_key_index: DatabaseKeyIndex,
+    ) -> Self {
+        ExecuteSpan {}
+    }
+
+    pub(crate) fn enter(&self) {}
+
+    pub(crate) fn record_backdated(&self, _backdated: bool) {}
+}
+
+/// Emits an event when the current query is cancelled.
+pub(crate) fn cancelled(_cancelled: &Cancelled) {
+    #[cfg(feature = "tracing")]
+    tracing::info!(reason = %_cancelled, "query cancelled");
+}
+
+/// Emits an event when a cycle is detected, with its participants.
+pub(crate) fn cycle_detected(_db: &dyn Database, _cycle: &Cycle) {
+    #[cfg(feature = "tracing")]
+    tracing::info!(participants = ?_cycle.all_participants(_db), "cycle detected");
+}
+
+/// Emits an event when `database_key_index` recovers from a cycle with its fallback.
+pub(crate) fn cycle_recovered<Db: ?Sized + Database>(
+    _db: &Db,
+    _database_key_index: DatabaseKeyIndex,
+) {
+    #[cfg(feature = "tracing")]
+    tracing::info!(key = ?_database_key_index.debug(_db), "recovering from cycle");
+}
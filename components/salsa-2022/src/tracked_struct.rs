@@ -1,11 +1,17 @@
+use std::any::{Any, TypeId};
 use std::fmt;
 
 use crate::{
     cycle::CycleRecoveryStrategy,
+    debug::DebugWithDb,
+    dump::IngredientDump,
+    hash::{FxDashMap, FxHashSet},
     ingredient::{fmt_index, Ingredient, IngredientRequiresReset},
     ingredient_list::IngredientList,
     interned::{InternedData, InternedId, InternedIngredient},
     key::{DatabaseKeyIndex, DependencyIndex},
+    memory::IngredientMemory,
+    metrics::QueryMetrics,
     runtime::{local_state::QueryOrigin, Runtime},
     salsa_struct::SalsaStructInDb,
     Database, Event, IngredientIndex, Revision,
@@ -30,6 +36,8 @@ pub trait TrackedStructInDb<DB: ?Sized + Database>: SalsaStructInDb<DB> {
 /// Unlike normal interners, tracked struct indices can be deleted and reused aggressively:
 /// when a tracked function re-executes,
 /// any tracked structs that it created before but did not create this time can be deleted.
+/// Their slots are reused from the next revision, with the next generation of their ids,
+/// so that a stale id is never mistaken for the struct that reuses its slot.
 pub struct TrackedStructIngredient<Id, Data>
 where
     Id: TrackedStructId,
@@ -45,6 +53,13 @@ where
     /// so they can remove any data tied to that instance.
     dependent_fns: IngredientList,
 
+    /// The instances that were deleted, each with the reason why.
+    ///
+    /// Ids are never reused (only their slots are, with another generation), so
+    /// this is only used to report uses of a deleted instance clearly, and entries
+    /// are kept across revisions.
+    deleted: FxDashMap<Id, Deletion>,
+
     debug_name: &'static str,
 }
 
@@ -58,19 +73,46 @@ struct TrackedStructKey<Data> {
 #[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Copy, Clone)]
 pub struct Disambiguator(pub u32);
 
+/// Why an instance of a tracked struct was deleted; holds the query that created it.
+#[derive(Debug, Copy, Clone)]
+enum Deletion {
+    /// The query no longer created it when it was executed again.
+    NotCreated(DatabaseKeyIndex),
+
+    /// [`Storage::sweep`](`crate::storage::Storage::sweep`) deleted the memo of the query.
+    Swept(DatabaseKeyIndex),
+}
+
 impl<Id, Data> TrackedStructIngredient<Id, Data>
 where
     Id: TrackedStructId,
     Data: TrackedStructData,
 {
     pub fn new(index: IngredientIndex, debug_name: &'static str) -> Self {
+        let mut interned = InternedIngredient::new(index, debug_name);
+        interned.enable_generations();
         Self {
-            interned: InternedIngredient::new(index, debug_name),
+            interned,
             dependent_fns: IngredientList::new(),
+            deleted: FxDashMap::default(),
             debug_name,
         }
     }
 
+    /// Copies the ingredient for the jars of a pipelined write.
+    pub fn fork(&self) -> Option<Self> {
+        Some(Self {
+            interned: self.interned.fork()?,
+            dependent_fns: self.dependent_fns.fork(),
+            deleted: self.deleted.clone(),
+            debug_name: self.debug_name,
+        })
+    }
+
+    pub fn ingredient_index(&self) -> IngredientIndex {
+        self.interned.ingredient_index()
+    }
+
     pub fn database_key_index(&self, id: Id) -> DatabaseKeyIndex {
         DatabaseKeyIndex {
             ingredient_index: self.interned.ingredient_index(),
@@ -99,6 +141,42 @@ where
         &self.interned.data(runtime, id).data
     }
 
+    /// Panics if `id` was deleted, naming the query that created it, rather than
+    /// letting the getters of its fields fail to find its data.
+    /// Called by those getters before reading the field.
+    pub fn assert_not_deleted<DB: ?Sized + Database>(&self, db: &DB, id: Id) {
+        if let Some(deletion) = self.deleted.get(&id) {
+            let (deleted_by, why) = match *deletion {
+                Deletion::NotCreated(creator) => (
+                    format!("`{:?}` deleted it when it was executed again", creator.debug(db)),
+                    format!(
+                        "`{:?}`, which created it, no longer creates it since it was executed again",
+                        creator.debug(db)
+                    ),
+                ),
+                Deletion::Swept(creator) => (
+                    format!("a sweep deleted it with the memo of `{:?}`", creator.debug(db)),
+                    format!(
+                        "a sweep deleted the memo of `{:?}`, which created it",
+                        creator.debug(db)
+                    ),
+                ),
+            };
+            if let Some(revision) = self.interned.recreated_at(id) {
+                panic!(
+                    "`{:?}` is stale: the entity was recreated in revision {}, with the \
+                     next generation of its id ({deleted_by})",
+                    self.database_key_index(id).debug(db),
+                    revision.as_usize(),
+                );
+            }
+            panic!(
+                "`{:?}` was deleted: {why}, so its fields cannot be read anymore",
+                self.database_key_index(id).debug(db),
+            );
+        }
+    }
+
     /// Deletes the given entities. This is used after a query `Q` executes and we can compare
     /// the entities `E_now` that it produced in this revision vs the entities
     /// `E_prev` it produced in the last revision. Any missing entities `E_prev - E_new` can be
@@ -109,20 +187,41 @@ where
     /// Using this method on an entity id that MAY be used in the current revision will lead to
     /// unspecified results (but not UB). See [`InternedIngredient::delete_index`] for more
     /// discussion and important considerations.
-    pub(crate) fn delete_entity(&self, db: &dyn crate::Database, id: Id) {
-        db.salsa_event(Event {
-            runtime_id: db.runtime().id(),
-            kind: crate::EventKind::DidDiscard {
-                key: self.database_key_index(id),
+    pub(crate) fn delete_entity(
+        &self,
+        db: &dyn crate::Database,
+        executor: DatabaseKeyIndex,
+        id: Id,
+    ) {
+        crate::event::report(
+            db,
+            Event {
+                runtime_id: db.runtime().id(),
+                kind: crate::EventKind::DidDiscard {
+                    key: self.database_key_index(id),
+                },
             },
-        });
+        );
 
         self.interned.delete_index(id);
+        self.deleted.insert(id, Deletion::NotCreated(executor));
         for dependent_fn in self.dependent_fns.iter() {
             db.salsa_struct_deleted(dependent_fn, id.as_id());
         }
     }
 
+    /// Returns the instances that currently exist, each with the query that created it.
+    fn creators(&self) -> Vec<(Id, DatabaseKeyIndex)> {
+        self.interned
+            .ids()
+            .into_iter()
+            .filter_map(|id| {
+                let creator = self.interned.with_data(id, |key| key.query_key)??;
+                Some((id, creator))
+            })
+            .collect()
+    }
+
     /// Adds a dependent function (one keyed by this tracked struct) to our list.
     /// When instances of this struct are deleted, these dependent functions
     /// will be notified.
@@ -165,7 +264,7 @@ where
     fn remove_stale_output(
         &self,
         db: &DB,
-        _executor: DatabaseKeyIndex,
+        executor: DatabaseKeyIndex,
         stale_output_key: Option<crate::Id>,
     ) {
         // This method is called when, in prior revisions,
@@ -173,17 +272,98 @@ where
         // but it did not in the current revision.
         // In that case, we can delete `stale_output_key` and any data associated with it.
         let stale_output_key: Id = Id::from_id(stale_output_key.unwrap());
-        self.delete_entity(db.as_salsa_database(), stale_output_key);
+        self.delete_entity(db.as_salsa_database(), executor, stale_output_key);
     }
 
     fn reset_for_new_revision(&mut self) {
         self.interned.clear_deleted_indices();
     }
 
+    fn sweep_edges(
+        &self,
+        _revision: Revision,
+        op: &mut dyn FnMut(crate::Id, bool, &mut dyn Iterator<Item = DependencyIndex>),
+    ) {
+        // An instance is kept only if the memo of the query that created it is kept,
+        // which keeps its instances in turn (they are outputs of the memo).
+        for (id, creator) in self.creators() {
+            op(id.as_id(), false, &mut std::iter::once(creator.into()));
+        }
+    }
+
+    fn sweep(&mut self, _revision: Revision, reachable: &FxHashSet<DatabaseKeyIndex>) {
+        // The memos keyed by the deleted instances, including the values of their
+        // fields, are not reachable either, so their own ingredients delete them.
+        for (id, creator) in self.creators() {
+            if !reachable.contains(&self.database_key_index(id)) {
+                self.interned.delete_index(id);
+                self.deleted.insert(id, Deletion::Swept(creator));
+            }
+        }
+    }
+
+    fn release_weak_memos(&mut self) {}
+
+    fn unshare(&mut self) -> bool {
+        true
+    }
+
+    fn memoized_keys(&self) -> Vec<crate::Id> {
+        vec![]
+    }
+
+    fn query_metrics(&self) -> Option<QueryMetrics> {
+        None
+    }
+
+    fn memory_usage(&self) -> Option<IngredientMemory> {
+        None
+    }
+
+    fn memo_count(&self) -> Option<usize> {
+        None
+    }
+
+    fn dump(&self) -> IngredientDump {
+        <InternedIngredient<_, _> as Ingredient<DB>>::dump(&self.interned)
+    }
+
+    fn can_fetch_dyn(&self) -> bool {
+        false
+    }
+
+    fn fetch_dyn(&self, _db: &DB, _key: crate::Id) -> Option<Box<dyn Any>> {
+        None
+    }
+
+    fn history_start(&self) -> Option<Revision> {
+        None
+    }
+
+    fn revert_to(&mut self, _runtime: &mut Runtime, _revision: Revision) {}
+
+    fn changed_since(&self, _revision: Revision) -> Vec<crate::Id> {
+        vec![]
+    }
+
+    fn key_type(&self) -> Option<TypeId> {
+        None
+    }
+
+    fn creator(&self, key_index: crate::Id) -> Option<DatabaseKeyIndex> {
+        self.interned
+            .with_data(Id::from_id(key_index), |key| key.query_key)
+            .flatten()
+    }
+
     fn salsa_struct_deleted(&self, _db: &DB, _id: crate::Id) {
         panic!("unexpected call: interned ingredients do not register for salsa struct deletion events");
     }
 
+    fn debug_name(&self) -> &'static str {
+        self.debug_name
+    }
+
     fn fmt_index(&self, index: Option<crate::Id>, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt_index(self.debug_name, index, fmt)
     }
@@ -0,0 +1,162 @@
+//! Merging the new value of a tracked function into the old one, for
+//! functions with the `update` option.
+
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasher, Hash};
+use std::path::PathBuf;
+
+use crate::Id;
+
+/// Types whose values can be updated in place to become equal to a new value.
+///
+/// Unlike replacing the old value, this keeps the allocations of the parts that
+/// did not change (e.g., the unchanged elements of a `Vec`), and the result tells
+/// whether *anything* changed, so that the value can be backdated even for nested
+/// structures like ASTs. It can be derived with `#[derive(salsa::Update)]`, and is
+/// implemented for all salsa structs.
+pub trait Update {
+    /// Updates `old_value` to be equal to `new_value`, reusing the parts of
+    /// `old_value` that are equal already. Returns true if `old_value` changed.
+    fn maybe_update(old_value: &mut Self, new_value: Self) -> bool;
+}
+
+/// Updates `old_value` by replacing it, unless it is equal to `new_value`.
+/// This is the [`Update`] implementation for types without parts to reuse.
+pub fn update_fallback<T: PartialEq>(old_value: &mut T, new_value: T) -> bool {
+    if *old_value == new_value {
+        false
+    } else {
+        *old_value = new_value;
+        true
+    }
+}
+
+macro_rules! update_fallback_impls {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl Update for $ty {
+                fn maybe_update(old_value: &mut Self, new_value: Self) -> bool {
+                    update_fallback(old_value, new_value)
+                }
+            }
+        )*
+    };
+}
+
+update_fallback_impls! {
+    (), bool, char, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64,
+    &'static str, String, PathBuf, Id,
+}
+
+impl<T: Update> Update for Option<T> {
+    fn maybe_update(old_value: &mut Self, new_value: Self) -> bool {
+        match (old_value, new_value) {
+            (Some(old_value), Some(new_value)) => T::maybe_update(old_value, new_value),
+            (None, None) => false,
+            (old_value, new_value) => {
+                *old_value = new_value;
+                true
+            }
+        }
+    }
+}
+
+impl<T: Update, E: Update> Update for Result<T, E> {
+    fn maybe_update(old_value: &mut Self, new_value: Self) -> bool {
+        match (old_value, new_value) {
+            (Ok(old_value), Ok(new_value)) => T::maybe_update(old_value, new_value),
+            (Err(old_value), Err(new_value)) => E::maybe_update(old_value, new_value),
+            (old_value, new_value) => {
+                *old_value = new_value;
+                true
+            }
+        }
+    }
+}
+
+impl<T: Update> Update for Box<T> {
+    fn maybe_update(old_value: &mut Self, new_value: Self) -> bool {
+        T::maybe_update(old_value, *new_value)
+    }
+}
+
+impl<T: Update> Update for Vec<T> {
+    fn maybe_update(old_value: &mut Self, new_value: Self) -> bool {
+        let mut changed = old_value.len() != new_value.len();
+        old_value.truncate(new_value.len());
+        let mut new_values = new_value.into_iter();
+        for (old_element, new_element) in old_value.iter_mut().zip(&mut new_values) {
+            changed |= T::maybe_update(old_element, new_element);
+        }
+        old_value.extend(new_values);
+        changed
+    }
+}
+
+impl<K, V, S> Update for HashMap<K, V, S>
+where
+    K: Eq + Hash,
+    V: Update,
+    S: BuildHasher,
+{
+    fn maybe_update(old_value: &mut Self, mut new_value: Self) -> bool {
+        let old_len = old_value.len();
+        old_value.retain(|key, _| new_value.contains_key(key));
+        let mut changed = old_value.len() != old_len;
+        for (key, new_element) in new_value.drain() {
+            match old_value.get_mut(&key) {
+                Some(old_element) => changed |= V::maybe_update(old_element, new_element),
+                None => {
+                    old_value.insert(key, new_element);
+                    changed = true;
+                }
+            }
+        }
+        changed
+    }
+}
+
+impl<K, V> Update for BTreeMap<K, V>
+where
+    K: Ord,
+    V: Update,
+{
+    fn maybe_update(old_value: &mut Self, new_value: Self) -> bool {
+        let old_len = old_value.len();
+        old_value.retain(|key, _| new_value.contains_key(key));
+        let mut changed = old_value.len() != old_len;
+        for (key, new_element) in new_value {
+            match old_value.get_mut(&key) {
+                Some(old_element) => changed |= V::maybe_update(old_element, new_element),
+                None => {
+                    old_value.insert(key, new_element);
+                    changed = true;
+                }
+            }
+        }
+        changed
+    }
+}
+
+macro_rules! tuple_impls {
+    ($(($($name:ident $index:tt),*))*) => {
+        $(
+            impl<$($name: Update),*> Update for ($($name,)*) {
+                fn maybe_update(old_value: &mut Self, new_value: Self) -> bool {
+                    let mut changed = false;
+                    $(changed |= $name::maybe_update(&mut old_value.$index, new_value.$index);)*
+                    changed
+                }
+            }
+        )*
+    };
+}
+
+tuple_impls! {
+    (A 0)
+    (A 0, B 1)
+    (A 0, B 1, C 2)
+    (A 0, B 1, C 2, D 3)
+    (A 0, B 1, C 2, D 3, E 4)
+    (A 0, B 1, C 2, D 3, E 4, F 5)
+}
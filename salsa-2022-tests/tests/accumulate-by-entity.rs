@@ -0,0 +1,90 @@
+//! Test `accumulated_by`:
+//!
+//! * it collects the values accumulated by all the functions called for one input,
+//!   but not those accumulated for another input;
+//! * a query reachable from several of those functions contributes its values once;
+//! * the functions are brought up to date first.
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(File, Diagnostics, parse, check, unused);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input]
+struct File {
+    text: String,
+}
+
+#[salsa::accumulator]
+struct Diagnostics(String);
+
+#[salsa::tracked]
+fn parse(db: &dyn Db, file: File) -> usize {
+    let text = file.text(db);
+    Diagnostics::push(db, format!("parsed `{text}`"));
+    text.len()
+}
+
+#[salsa::tracked]
+fn check(db: &dyn Db, file: File) -> bool {
+    let len = parse(db, file);
+    Diagnostics::push(db, format!("checked {len} bytes"));
+    len > 0
+}
+
+#[salsa::tracked]
+fn unused(db: &dyn Db, file: File) {
+    Diagnostics::push(db, format!("never called for `{}`", file.text(db)));
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+#[test]
+fn accumulated_by_file() {
+    let mut db = Database::default();
+    let a = File::new(&db, "a".to_string());
+    let b = File::new(&db, "bb".to_string());
+
+    // `parse(a)` is called both directly and by `check(a)`.
+    parse(&db, a);
+    check(&db, a);
+    check(&db, b);
+
+    expect![[r#"
+        [
+            "parsed `a`",
+            "checked 1 bytes",
+        ]
+    "#]]
+    .assert_debug_eq(&Diagnostics::accumulated_by(&db, a));
+    expect![[r#"
+        [
+            "parsed `bb`",
+            "checked 2 bytes",
+        ]
+    "#]]
+    .assert_debug_eq(&Diagnostics::accumulated_by(&db, b));
+
+    // The functions are executed again, but not `unused`, which was never called.
+    a.set_text(&mut db).to("abc".to_string());
+    expect![[r#"
+        [
+            "parsed `abc`",
+            "checked 3 bytes",
+        ]
+    "#]]
+    .assert_debug_eq(&Diagnostics::accumulated_by(&db, a));
+
+    unused(&db, a);
+}
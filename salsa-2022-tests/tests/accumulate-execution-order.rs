@@ -0,0 +1,105 @@
+//! Test that accumulated values are returned in the order in which they
+//! were pushed, even when a query pushes values both before and after
+//! calling other queries, and that `accumulated_with_queries` reports
+//! which query pushed each value.
+
+use salsa::DebugWithDb;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, Logs, parent, child);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::accumulator]
+struct Logs(String);
+
+#[salsa::tracked]
+fn parent(db: &dyn Db, input: MyInput) {
+    Logs::push(db, "parent: before".to_string());
+    child(db, input);
+    Logs::push(db, format!("parent: field = {}", input.field(db)));
+    child(db, input);
+    Logs::push(db, "parent: after".to_string());
+}
+
+#[salsa::tracked]
+fn child(db: &dyn Db, input: MyInput) {
+    for i in 0..input.field(db) {
+        Logs::push(db, format!("child: {}", i));
+    }
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+#[test]
+fn execution_order() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 2);
+    parent(&db, input);
+
+    let logs = parent::accumulated::<Logs>(&db, input);
+    assert_eq!(
+        logs,
+        [
+            "parent: before",
+            "child: 0",
+            "child: 1",
+            "parent: field = 2",
+            "parent: after",
+        ]
+    );
+
+    // The order is preserved when the values are re-accumulated.
+    input.set_field(&mut db).to(1);
+    let logs = parent::accumulated::<Logs>(&db, input);
+    assert_eq!(
+        logs,
+        [
+            "parent: before",
+            "child: 0",
+            "parent: field = 1",
+            "parent: after",
+        ]
+    );
+}
+
+#[test]
+fn with_queries() {
+    let db = Database::default();
+    let input = MyInput::new(&db, 1);
+
+    let logs: Vec<_> = parent::accumulated_with_queries::<Logs>(&db, input)
+        .into_iter()
+        .map(|accumulated| {
+            format!(
+                "{:?} @ {}: {}",
+                accumulated.query.debug(&db),
+                accumulated.position,
+                accumulated.value
+            )
+        })
+        .collect();
+    assert_eq!(
+        logs,
+        [
+            "parent(0) @ 0: parent: before",
+            "child(0) @ 1: child: 0",
+            "parent(0) @ 3: parent: field = 1",
+            "parent(0) @ 3: parent: after",
+        ]
+    );
+}
@@ -0,0 +1,118 @@
+//! Test the `extends` option of accumulators: the values pushed to an accumulator
+//! are also accumulated by its parent (converted with `Into`), and by the parent
+//! of its parent, in the order in which they were pushed.
+
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(
+    MyInput,
+    AllDiagnostics,
+    Errors,
+    Warnings,
+    Lints,
+    Notes,
+    check,
+    lint,
+);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input]
+struct MyInput {
+    count: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Diagnostic(String);
+
+impl From<String> for Diagnostic {
+    fn from(message: String) -> Self {
+        Diagnostic(message)
+    }
+}
+
+#[salsa::accumulator]
+struct AllDiagnostics(Diagnostic);
+
+#[salsa::accumulator(extends = AllDiagnostics)]
+struct Errors(Diagnostic);
+
+#[salsa::accumulator(extends = AllDiagnostics)]
+struct Warnings(Diagnostic);
+
+#[salsa::accumulator(extends = Warnings)]
+struct Lints(Diagnostic);
+
+#[salsa::accumulator(extends = AllDiagnostics)]
+struct Notes(String);
+
+#[salsa::tracked]
+#[allow(dead_code)]
+fn check(db: &dyn Db, input: MyInput) {
+    for i in 0..input.count(db) {
+        Errors::push(db, Diagnostic(format!("error {i}")));
+        Warnings::push(db, Diagnostic(format!("warning {i}")));
+    }
+    lint(db, input);
+    Notes::push(db, "note".to_string());
+}
+
+#[salsa::tracked]
+fn lint(db: &dyn Db, input: MyInput) {
+    Lints::push(db, Diagnostic(format!("lint {}", input.count(db))));
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+fn messages(diagnostics: Vec<Diagnostic>) -> Vec<String> {
+    diagnostics
+        .into_iter()
+        .map(|Diagnostic(message)| message)
+        .collect()
+}
+
+#[test]
+fn parents_accumulate_children() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 2);
+
+    assert_eq!(
+        messages(check::accumulated::<AllDiagnostics>(&db, input)),
+        [
+            "error 0",
+            "warning 0",
+            "error 1",
+            "warning 1",
+            "lint 2",
+            "note"
+        ]
+    );
+    assert_eq!(
+        messages(check::accumulated::<Warnings>(&db, input)),
+        ["warning 0", "warning 1", "lint 2"]
+    );
+    assert_eq!(
+        messages(check::accumulated::<Errors>(&db, input)),
+        ["error 0", "error 1"]
+    );
+    assert_eq!(
+        messages(check::accumulated::<Lints>(&db, input)),
+        ["lint 2"]
+    );
+    assert_eq!(check::accumulated::<Notes>(&db, input), ["note"]);
+
+    input.set_count(&mut db).to(1);
+    assert_eq!(
+        messages(check::accumulated::<AllDiagnostics>(&db, input)),
+        ["error 0", "warning 0", "lint 1", "note"]
+    );
+}
@@ -71,8 +71,8 @@ fn test1() {
     compute(&db, l1);
     expect![[r#"
         [
-            11,
             1,
+            11,
         ]
     "#]]
     .assert_debug_eq(&compute::accumulated::<Integers>(&db, l1));
@@ -81,8 +81,8 @@ fn test1() {
     compute(&db, l1);
     expect![[r#"
         [
-            12,
             2,
+            12,
         ]
     "#]]
     .assert_debug_eq(&compute::accumulated::<Integers>(&db, l1));
@@ -89,11 +89,11 @@ fn accumulate_once() {
     let logs = push_logs::accumulated::<Logs>(&db, input);
     expect![[r#"
         [
+            "log_a(0 of 2)",
+            "log_a(1 of 2)",
             "log_b(0 of 3)",
             "log_b(1 of 3)",
             "log_b(2 of 3)",
-            "log_a(0 of 2)",
-            "log_a(1 of 2)",
         ]"#]]
     .assert_eq(&format!("{:#?}", logs));
     db.assert_logs(expect![[r#"
@@ -113,11 +113,11 @@ fn change_a_and_reaccumulate() {
     let logs = push_logs::accumulated::<Logs>(&db, input);
     expect![[r#"
         [
+            "log_a(0 of 2)",
+            "log_a(1 of 2)",
             "log_b(0 of 3)",
             "log_b(1 of 3)",
             "log_b(2 of 3)",
-            "log_a(0 of 2)",
-            "log_a(1 of 2)",
         ]"#]]
     .assert_eq(&format!("{:#?}", logs));
     db.assert_logs(expect![[r#"
@@ -174,3 +174,41 @@ fn get_a_logs_after_changing_b() {
     .assert_debug_eq(&logs);
     db.assert_logs(expect!["[]"]);
 }
+
+#[test]
+fn get_a_logs_after_changing_a() {
+    let mut db = Database::default();
+
+    // Accumulate only the values pushed by `push_a_logs` (and its callees),
+    // ignoring those pushed elsewhere in `push_logs`.
+    let input = MyInput::new(&db, 3, 3);
+    let logs = push_a_logs::accumulated::<Logs>(&db, input);
+    expect![[r#"
+        [
+            "log_a(0 of 3)",
+            "log_a(1 of 3)",
+            "log_a(2 of 3)",
+        ]
+    "#]]
+    .assert_debug_eq(&logs);
+    db.assert_logs(expect![[r#"
+        [
+            "push_a_logs(3)",
+        ]"#]]);
+
+    // Re-executing `push_a_logs` replaces the values it accumulated
+    // in the previous revision rather than adding to them.
+    input.set_field_a(&mut db).to(2);
+    let logs = push_a_logs::accumulated::<Logs>(&db, input);
+    expect![[r#"
+        [
+            "log_a(0 of 2)",
+            "log_a(1 of 2)",
+        ]
+    "#]]
+    .assert_debug_eq(&logs);
+    db.assert_logs(expect![[r#"
+        [
+            "push_a_logs(2)",
+        ]"#]]);
+}
@@ -0,0 +1,106 @@
+//! Test `assert_executes!` and `assert_memoized!`, which check whether calling
+//! a tracked function executes it, using the events recorded by the database.
+
+use salsa::testing::{ExecutionLog, LoggingDatabase};
+use salsa::{assert_executes, assert_memoized};
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, length, is_long, Node, depth);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input]
+struct MyInput {
+    text: String,
+}
+
+#[salsa::tracked]
+fn length(db: &dyn Db, input: MyInput) -> usize {
+    input.text(db).len()
+}
+
+#[salsa::tracked]
+fn is_long(db: &dyn Db, input: MyInput) -> bool {
+    length(db, input) > 3
+}
+
+#[salsa::input]
+struct Node {
+    parent: Option<Node>,
+}
+
+#[salsa::tracked]
+fn depth(db: &dyn Db, node: Node) -> usize {
+    node.parent(db).map_or(0, |parent| depth(db, parent) + 1)
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    execution_log: ExecutionLog,
+}
+
+impl salsa::Database for Database {
+    fn salsa_event(&self, event: salsa::Event) {
+        self.execution_log.record(event);
+    }
+}
+
+impl LoggingDatabase for Database {
+    fn execution_log(&self) -> &ExecutionLog {
+        &self.execution_log
+    }
+}
+
+impl Db for Database {}
+
+#[test]
+fn executes_then_memoized() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, "abc".to_string());
+
+    assert!(!assert_executes!(&db, is_long, input));
+    assert!(!assert_memoized!(&db, is_long, input));
+
+    // `length` is executed again, but its value did not change.
+    input.set_text(&mut db).to("xyz".to_string());
+    assert_memoized!(&db, is_long, input);
+    assert_eq!(db.execution_log().executed().len(), 1);
+
+    input.set_text(&mut db).to("abcd".to_string());
+    assert!(assert_executes!(&db, is_long, input));
+}
+
+#[test]
+#[should_panic(
+    expected = "expected `is_long(0)` not to be executed, but the executed queries were [is_long(0), length(0)]"
+)]
+fn executed_but_expected_memoized() {
+    let db = Database::default();
+    let input = MyInput::new(&db, "abc".to_string());
+    assert_memoized!(&db, is_long, input);
+}
+
+#[test]
+#[should_panic(expected = "expected `length(0)` to be executed, but the executed queries were []")]
+fn memoized_but_expected_executed() {
+    let db = Database::default();
+    let input = MyInput::new(&db, "abc".to_string());
+    is_long(&db, input);
+    assert_executes!(&db, crate::length, input);
+}
+
+#[test]
+fn memoized_while_other_key_executes() {
+    let mut db = Database::default();
+    let root = Node::new(&db, None);
+    let child = Node::new(&db, Some(root));
+    assert_eq!(depth(&db, child), 1);
+
+    // Verifying `depth(child)` executes `depth(root)` again, which returns the same value.
+    root.set_parent(&mut db).to(None);
+    assert_eq!(assert_memoized!(&db, depth, child), 1);
+    assert_eq!(db.execution_log().executed().len(), 1);
+}
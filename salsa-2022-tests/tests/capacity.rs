@@ -0,0 +1,110 @@
+//! Test the `capacity` option and the `reserve` function of tracked functions:
+//! reserving room for memos, with either memo storage, does not change
+//! which values are memoized.
+
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, hashed, dense, multiple);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::input]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::tracked(capacity = 1000)]
+fn hashed(db: &dyn Db, input: MyInput) -> u32 {
+    db.push_log(format!("hashed({})", input.field(db)));
+    input.field(db) * 2
+}
+
+#[salsa::tracked(storage = dense, capacity = 1000)]
+fn dense(db: &dyn Db, input: MyInput) -> u32 {
+    db.push_log(format!("dense({})", input.field(db)));
+    input.field(db) * 3
+}
+
+#[salsa::tracked(capacity = 16)]
+fn multiple(db: &dyn Db, input: MyInput, factor: u32) -> u32 {
+    input.field(db) * factor
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn memoized_with_capacity() {
+    let mut db = Database::default();
+    let inputs: Vec<_> = (0..3).map(|i| MyInput::new(&db, i)).collect();
+    for &input in &inputs {
+        assert_eq!(hashed(&db, input), input.field(&db) * 2);
+        assert_eq!(dense(&db, input), input.field(&db) * 3);
+        assert_eq!(multiple(&db, input, 5), input.field(&db) * 5);
+    }
+    db.assert_logs(expect![[r#"
+        [
+            "hashed(0)",
+            "dense(0)",
+            "hashed(1)",
+            "dense(1)",
+            "hashed(2)",
+            "dense(2)",
+        ]"#]]);
+
+    for &input in &inputs {
+        hashed(&db, input);
+        dense(&db, input);
+    }
+    db.assert_logs(expect!["[]"]);
+
+    inputs[1].set_field(&mut db).to(10);
+    for &input in &inputs {
+        hashed(&db, input);
+        dense(&db, input);
+    }
+    db.assert_logs(expect![[r#"
+        [
+            "hashed(10)",
+            "dense(10)",
+        ]"#]]);
+}
+
+#[test]
+fn reserve_after_memoizing() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 1);
+    assert_eq!(hashed(&db, input), 2);
+    assert_eq!(dense(&db, input), 3);
+    db.assert_logs(expect![[r#"
+        [
+            "hashed(1)",
+            "dense(1)",
+        ]"#]]);
+
+    // The memos are kept when the maps grow.
+    hashed::reserve(&db, 100_000);
+    dense::reserve(&db, 100_000);
+    multiple::reserve(&db, 100_000);
+    assert_eq!(hashed(&db, input), 2);
+    assert_eq!(dense(&db, input), 3);
+    db.assert_logs(expect!["[]"]);
+}
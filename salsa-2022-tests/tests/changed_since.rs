@@ -0,0 +1,89 @@
+//! Test that `changed_since` reports the memoized queries whose value
+//! changed after a given revision, and not those that were re-executed
+//! but backdated.
+
+use salsa::{Database as _, DebugWithDb};
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, length, is_long, compile);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input]
+struct MyInput {
+    #[return_ref]
+    text: String,
+}
+
+#[salsa::tracked]
+fn length(db: &dyn Db, input: MyInput) -> usize {
+    input.text(db).len()
+}
+
+#[salsa::tracked]
+fn is_long(db: &dyn Db, input: MyInput) -> bool {
+    length(db, input) > 3
+}
+
+#[salsa::tracked]
+fn compile(db: &dyn Db, input: MyInput) -> String {
+    input.text(db).to_uppercase()
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+fn changed_since(db: &Database, revision: salsa::Revision) -> Vec<String> {
+    db.changed_since(revision)
+        .into_iter()
+        .map(|query| format!("{:?}", query.debug(db)))
+        .collect()
+}
+
+#[test]
+fn execute() {
+    let mut db = Database::default();
+    let a = MyInput::new(&db, "main".to_string());
+    let b = MyInput::new(&db, "lib".to_string());
+    let run = |db: &Database| {
+        for input in [a, b] {
+            is_long(db, input);
+            compile(db, input);
+        }
+    };
+    run(&db);
+
+    let before = db.attach_revision().revision();
+    assert!(changed_since(&db, before).is_empty());
+
+    // `length(0)` changes, but `is_long(0)` is backdated.
+    a.set_text(&mut db).to("other".to_string());
+    run(&db);
+    assert_eq!(changed_since(&db, before), ["length(0)", "compile(0)"]);
+
+    let middle = db.attach_revision().revision();
+    b.set_text(&mut db).to("library".to_string());
+    run(&db);
+    assert_eq!(
+        changed_since(&db, before),
+        [
+            "length(0)",
+            "length(1)",
+            "is_long(1)",
+            "compile(0)",
+            "compile(1)"
+        ]
+    );
+    assert_eq!(
+        changed_since(&db, middle),
+        ["length(1)", "is_long(1)", "compile(1)"]
+    );
+}
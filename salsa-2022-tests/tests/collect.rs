@@ -0,0 +1,91 @@
+//! Test the `collect` option of tracked functions: the iterator they
+//! return is collected into the given container, which is memoized
+//! (and backdated) like any other value.
+
+use std::collections::BTreeSet;
+
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, MyInput_even_words, words, lengths, total_length);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::input]
+struct MyInput {
+    #[return_ref]
+    text: String,
+}
+
+#[salsa::tracked(return_ref, collect = Vec<String>)]
+fn words(db: &dyn Db, input: MyInput) -> impl Iterator<Item = String> + '_ {
+    db.push_log(format!("words({:?})", input.text(db)));
+    input.text(db).split_whitespace().map(str::to_string)
+}
+
+#[salsa::tracked(collect = BTreeSet<usize>)]
+fn lengths(db: &dyn Db, input: MyInput) -> impl Iterator<Item = usize> + '_ {
+    db.push_log("lengths".to_string());
+    words(db, input).iter().map(String::len)
+}
+
+#[salsa::tracked]
+fn total_length(db: &dyn Db, input: MyInput) -> usize {
+    db.push_log("total_length".to_string());
+    lengths(db, input).iter().sum()
+}
+
+#[salsa::tracked]
+impl MyInput {
+    #[salsa::tracked(collect = Vec<String>)]
+    fn even_words(self, db: &dyn Db) -> impl Iterator<Item = String> + '_ {
+        words(db, self).iter().step_by(2).cloned()
+    }
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn execute() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, "a bb cc ddd".to_string());
+
+    assert_eq!(words(&db, input), &["a", "bb", "cc", "ddd"]);
+    assert_eq!(lengths(&db, input), BTreeSet::from([1, 2, 3]));
+    assert_eq!(total_length(&db, input), 6);
+    assert_eq!(input.even_words(&db), ["a", "cc"]);
+    db.assert_logs(expect![[r#"
+        [
+            "words(\"a bb cc ddd\")",
+            "lengths",
+            "total_length",
+        ]"#]]);
+
+    // The words change but not their lengths, so the set is backdated.
+    input.set_text(&mut db).to("b aa dd eee".to_string());
+    assert_eq!(total_length(&db, input), 6);
+    assert_eq!(input.even_words(&db), ["b", "dd"]);
+    db.assert_logs(expect![[r#"
+        [
+            "words(\"b aa dd eee\")",
+            "lengths",
+        ]"#]]);
+}
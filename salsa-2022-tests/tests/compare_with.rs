@@ -0,0 +1,137 @@
+//! Test that a tracked fn with `compare_with` is backdated according
+//! to the supplied comparison function rather than `Eq`, so that
+//! downstream queries are not re-executed when only "irrelevant"
+//! parts of the value change.
+
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, parse, parse_no_eq, len, len_no_eq);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::input(jar = Jar)]
+struct MyInput {
+    #[return_ref]
+    text: String,
+}
+
+/// A parsed value together with the "span" it was found at.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Spanned {
+    value: String,
+    start: usize,
+}
+
+fn same_value(a: &Spanned, b: &Spanned) -> bool {
+    a.value == b.value
+}
+
+#[salsa::tracked(jar = Jar, compare_with = same_value)]
+fn parse(db: &dyn Db, input: MyInput) -> Spanned {
+    db.push_log("parse".to_string());
+    let text = input.text(db);
+    let start = text.len() - text.trim_start().len();
+    Spanned {
+        value: text.trim().to_string(),
+        start,
+    }
+}
+
+#[salsa::tracked(jar = Jar)]
+fn len(db: &dyn Db, input: MyInput) -> usize {
+    db.push_log("len".to_string());
+    parse(db, input).value.len()
+}
+
+/// A value that cannot implement `Eq`.
+#[derive(Clone, Debug)]
+struct Float(f64);
+
+fn same_float(a: &Float, b: &Float) -> bool {
+    a.0.to_bits() == b.0.to_bits()
+}
+
+#[salsa::tracked(jar = Jar, no_eq, compare_with = same_float)]
+fn parse_no_eq(db: &dyn Db, input: MyInput) -> Float {
+    db.push_log("parse_no_eq".to_string());
+    Float(input.text(db).trim().parse().unwrap())
+}
+
+#[salsa::tracked(jar = Jar)]
+fn len_no_eq(db: &dyn Db, input: MyInput) -> u64 {
+    db.push_log("len_no_eq".to_string());
+    parse_no_eq(db, input).0 as u64
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn backdate_with_custom_comparison() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, "abc".to_string());
+
+    assert_eq!(len(&db, input), 3);
+    db.assert_logs(expect![[r#"
+        [
+            "len",
+            "parse",
+        ]"#]]);
+
+    // Only the span changes: `same_value` considers the values equal,
+    // so `parse` is backdated and `len` is not re-executed.
+    input.set_text(&mut db).to("  abc".to_string());
+    assert_eq!(len(&db, input), 3);
+    assert_eq!(parse(&db, input).start, 2);
+    db.assert_logs(expect![[r#"
+        [
+            "parse",
+        ]"#]]);
+
+    // The value itself changes.
+    input.set_text(&mut db).to("abcd".to_string());
+    assert_eq!(len(&db, input), 4);
+    db.assert_logs(expect![[r#"
+        [
+            "parse",
+            "len",
+        ]"#]]);
+}
+
+#[test]
+fn backdate_with_no_eq() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, "1.5".to_string());
+
+    assert_eq!(len_no_eq(&db, input), 1);
+    db.assert_logs(expect![[r#"
+        [
+            "len_no_eq",
+            "parse_no_eq",
+        ]"#]]);
+
+    input.set_text(&mut db).to(" 1.5 ".to_string());
+    assert_eq!(len_no_eq(&db, input), 1);
+    db.assert_logs(expect![[r#"
+        [
+            "parse_no_eq",
+        ]"#]]);
+}
@@ -0,0 +1,32 @@
+#[salsa::jar(db = Db)]
+pub struct Jar(a::MyInput);
+
+mod a {
+    use crate::Jar;
+
+    #[salsa::input(jar = Jar)]
+    pub struct MyInput {
+        pub name: String,
+
+        #[default]
+        field: u32,
+    }
+}
+
+pub trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+fn main() {
+    let db = Database::default();
+    let input = a::MyInput::builder("a".to_string()).field(22).new(&db);
+    input.name(&db);
+}
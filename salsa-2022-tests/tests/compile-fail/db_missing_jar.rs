@@ -0,0 +1,36 @@
+mod listed {
+    #[salsa::jar(db = Db)]
+    pub struct Jar(MyInput);
+
+    pub trait Db: salsa::DbWithJar<Jar> {}
+
+    #[salsa::input(jar = Jar)]
+    pub struct MyInput {
+        field: u32,
+    }
+}
+
+mod unlisted {
+    #[salsa::jar(db = Db)]
+    pub struct Jar(MyInput);
+
+    pub trait Db: salsa::DbWithJar<Jar> {}
+
+    #[salsa::input(jar = Jar)]
+    pub struct MyInput {
+        field: u32,
+    }
+}
+
+#[salsa::db(listed::Jar)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl listed::Db for Database {}
+
+impl unlisted::Db for Database {}
+
+fn main() {}
@@ -0,0 +1,23 @@
+#[salsa::jar(db = Db)]
+struct Jar(MyInput);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input]
+struct MyInput {
+    field: u32,
+}
+
+struct NotClone;
+
+#[salsa::db(Jar, parallel, default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    other: NotClone,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+fn main() {}
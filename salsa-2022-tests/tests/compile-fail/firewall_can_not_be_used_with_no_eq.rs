@@ -0,0 +1,16 @@
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, firewall_can_not_be_used_with_no_eq);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input(jar = Jar)]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::tracked(jar = Jar, firewall, no_eq)]
+fn firewall_can_not_be_used_with_no_eq(db: &dyn Db, input: MyInput) -> u32 {
+    input.field(db)
+}
+
+fn main() {}
@@ -1,5 +1,5 @@
 #[salsa::jar(db = Db)]
-struct Jar(InputWithRetRef, InputWithSpecify, InputNoWithEq, InputWithDb, InputWithRecover, InputWithLru);
+struct Jar(InputWithRetRef, InputWithSpecify, InputNoWithEq, InputWithRecover, InputWithLru);
 
 trait Db: salsa::DbWithJar<Jar> {}
 
@@ -12,9 +12,6 @@ struct InputWithSpecify (u32);
 #[salsa::input(jar = Jar, no_eq)]
 struct InputNoWithEq (u32);
 
-#[salsa::input(jar = Jar, db = Db)]
-struct InputWithDb (u32);
-
 #[salsa::input(jar = Jar, recover_fn = recover)]
 struct InputWithRecover (u32);
 
@@ -0,0 +1,26 @@
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, NotSalsa, OtherInput);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input]
+struct MyInput {
+    field: u32,
+}
+
+struct NotSalsa;
+
+#[salsa::jar(db = OtherDb)]
+struct OtherJar(OtherInput);
+
+trait OtherDb: salsa::DbWithJar<OtherJar> {}
+
+#[salsa::input(jar = OtherJar)]
+struct OtherInput {
+    field: u32,
+}
+
+#[salsa::jar(db = Db)]
+struct RefJar(&'static str);
+
+fn main() {}
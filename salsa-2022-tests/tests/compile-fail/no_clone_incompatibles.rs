@@ -0,0 +1,21 @@
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, no_clone_with_return_ref, no_clone_with_elements);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input(jar = Jar)]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::tracked(jar = Jar, no_clone, return_ref)]
+fn no_clone_with_return_ref(db: &dyn Db, input: MyInput) -> u32 {
+    input.field(db)
+}
+
+#[salsa::tracked(jar = Jar, no_clone, elements = element)]
+fn no_clone_with_elements(db: &dyn Db, input: MyInput) -> salsa::List<u32> {
+    salsa::List::from(vec![input.field(db)])
+}
+
+fn main() {}
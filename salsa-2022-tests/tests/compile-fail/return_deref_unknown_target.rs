@@ -0,0 +1,26 @@
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, names);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input]
+struct MyInput {
+    field: u32,
+}
+
+struct Names(Vec<String>);
+
+impl std::ops::Deref for Names {
+    type Target = [String];
+
+    fn deref(&self) -> &[String] {
+        &self.0
+    }
+}
+
+#[salsa::tracked(return_deref)]
+fn names(db: &dyn Db, input: MyInput) -> Names {
+    Names(vec![input.field(db).to_string()])
+}
+
+fn main() {}
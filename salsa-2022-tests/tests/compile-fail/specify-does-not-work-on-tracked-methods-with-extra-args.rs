@@ -0,0 +1,24 @@
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, MyTracked, MyTracked_tracked_method);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input(jar = Jar)]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::tracked(jar = Jar)]
+struct MyTracked {
+    field: u32,
+}
+
+#[salsa::tracked(jar = Jar)]
+impl MyTracked {
+    #[salsa::tracked(specify)]
+    fn tracked_method(self, db: &dyn Db, extra: u32) -> u32 {
+        self.field(db) + extra
+    }
+}
+
+fn main() {}
@@ -0,0 +1,21 @@
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, misspelled, unknown);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::tracked(retrun_ref)]
+fn misspelled(db: &dyn Db, input: MyInput) -> u32 {
+    input.field(db)
+}
+
+#[salsa::tracked(cache)]
+fn unknown(db: &dyn Db, input: MyInput) -> u32 {
+    input.field(db)
+}
+
+fn main() {}
@@ -0,0 +1,26 @@
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, not_eq, not_clone);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input]
+struct MyInput {
+    field: u32,
+}
+
+struct NotEq(u32);
+
+#[derive(Debug, PartialEq, Eq)]
+struct NotClone(u32);
+
+#[salsa::tracked]
+fn not_eq(db: &dyn Db, input: MyInput) -> NotEq {
+    NotEq(input.field(db))
+}
+
+#[salsa::tracked]
+fn not_clone(db: &dyn Db, input: MyInput) -> NotClone {
+    NotClone(input.field(db))
+}
+
+fn main() {}
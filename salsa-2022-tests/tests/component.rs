@@ -0,0 +1,155 @@
+//! Test `#[salsa::component]`: the tracked methods of a trait are memoized
+//! separately for each type implementing it, and re-executed when the inputs
+//! they read change. With `return_ref`, they return references into the
+//! database, e.g. `Option<&T>` for an `Option<T>` value.
+
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(
+    Word,
+    Number,
+    Analysis_size,
+    Analysis_scaled,
+    Analysis_label,
+    Analysis_digits,
+);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::component(jar = Jar)]
+trait Analysis {
+    fn name(&self) -> &'static str;
+
+    fn measure(self, db: &dyn Db) -> u32;
+
+    #[salsa::tracked]
+    fn size(self, db: &dyn Db) -> u32 {
+        db.push_log(format!("size({})", self.name()));
+        self.measure(db)
+    }
+
+    #[salsa::tracked]
+    fn scaled(self, db: &dyn Db, factor: u32) -> u32 {
+        db.push_log(format!("scaled({}, {factor})", self.name()));
+        self.size(db) * factor
+    }
+
+    #[salsa::tracked(return_ref)]
+    fn label(self, db: &dyn Db) -> String {
+        format!("{} of size {}", self.name(), self.size(db))
+    }
+
+    #[salsa::tracked(return_ref)]
+    fn digits(self, db: &dyn Db) -> Option<Vec<u32>> {
+        db.push_log(format!("digits({})", self.name()));
+        let size = self.size(db);
+        (size >= 10).then(|| {
+            size.to_string()
+                .chars()
+                .map(|c| c as u32 - '0' as u32)
+                .collect()
+        })
+    }
+}
+
+#[salsa::input]
+struct Word {
+    text: String,
+}
+
+impl Analysis for Word {
+    fn name(&self) -> &'static str {
+        "word"
+    }
+
+    fn measure(self, db: &dyn Db) -> u32 {
+        self.text(db).len() as u32
+    }
+}
+
+#[salsa::input]
+struct Number {
+    value: u32,
+}
+
+impl Analysis for Number {
+    fn name(&self) -> &'static str {
+        "number"
+    }
+
+    fn measure(self, db: &dyn Db) -> u32 {
+        self.value(db)
+    }
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn execute() {
+    let mut db = Database::default();
+
+    // Both inputs have the id 0, but they are different types.
+    let word = Word::new(&db, "hello".to_string());
+    let number = Number::new(&db, 7);
+    assert_eq!(word.size(&db), 5);
+    assert_eq!(number.size(&db), 7);
+    assert_eq!(word.scaled(&db, 2), 10);
+    assert_eq!(word.size(&db), 5);
+    db.assert_logs(expect![[r#"
+        [
+            "size(word)",
+            "size(number)",
+            "scaled(word, 2)",
+        ]"#]]);
+
+    word.set_text(&mut db).to("hi".to_string());
+    assert_eq!(word.scaled(&db, 2), 4);
+    assert_eq!(number.size(&db), 7);
+    db.assert_logs(expect![[r#"
+        [
+            "size(word)",
+            "scaled(word, 2)",
+        ]"#]]);
+}
+
+#[test]
+fn return_ref() {
+    let mut db = Database::default();
+    let word = Word::new(&db, "hello".to_string());
+    let number = Number::new(&db, 42);
+
+    let label: &String = word.label(&db);
+    assert_eq!(label, "word of size 5");
+    let digits: Option<&Vec<u32>> = number.digits(&db);
+    assert_eq!(digits, Some(&vec![4, 2]));
+    assert_eq!(word.digits(&db), None);
+    db.assert_logs(expect![[r#"
+        [
+            "size(word)",
+            "digits(number)",
+            "size(number)",
+            "digits(word)",
+        ]"#]]);
+
+    number.set_value(&mut db).to(7);
+    assert_eq!(number.digits(&db), None);
+}
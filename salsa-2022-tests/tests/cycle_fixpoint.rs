@@ -0,0 +1,139 @@
+//! Test the `cycle_initial` option: the cycles of tracked functions are
+//! iterated to a fixed point, starting from the initial value.
+
+use std::collections::BTreeSet;
+
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(Graph, reachable, diverging);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+/// The successors of each node.
+#[salsa::input]
+struct Graph {
+    edges: Vec<Vec<u32>>,
+}
+
+/// The nodes reachable from `node`, which cycles whenever the graph does.
+#[salsa::tracked(cycle_initial = reachable_initial)]
+fn reachable(db: &dyn Db, graph: Graph, node: u32) -> BTreeSet<u32> {
+    db.push_log(format!("reachable({node})"));
+    let mut nodes = BTreeSet::new();
+    for &next in &graph.edges(db)[node as usize] {
+        nodes.insert(next);
+        nodes.extend(reachable(db, graph, next));
+    }
+    nodes
+}
+
+fn reachable_initial(_db: &dyn Db, _graph: Graph, _node: u32) -> BTreeSet<u32> {
+    BTreeSet::new()
+}
+
+#[salsa::tracked(cycle_initial = diverging_initial)]
+fn diverging(db: &dyn Db, graph: Graph) -> u32 {
+    diverging(db, graph) + 1
+}
+
+fn diverging_initial(_db: &dyn Db, _graph: Graph) -> u32 {
+    0
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+fn nodes(nodes: &[u32]) -> BTreeSet<u32> {
+    nodes.iter().copied().collect()
+}
+
+#[test]
+fn iterate() {
+    let mut db = Database::default();
+    let graph = Graph::new(&db, vec![vec![1], vec![0, 2], vec![]]);
+
+    // `reachable(0)` is executed again until its value no longer changes;
+    // `reachable(1)` depends on its provisional value, so it is executed by
+    // each iteration, while `reachable(2)` is memoized.
+    assert_eq!(reachable(&db, graph, 0), nodes(&[0, 1, 2]));
+    db.assert_logs(expect![[r#"
+        [
+            "reachable(0)",
+            "reachable(1)",
+            "reachable(2)",
+            "reachable(0)",
+            "reachable(1)",
+        ]"#]]);
+
+    // Once the cycle head has its final value, the other participants are computed from it.
+    assert_eq!(reachable(&db, graph, 1), nodes(&[0, 1, 2]));
+    db.assert_logs(expect![[r#"
+        [
+            "reachable(1)",
+        ]"#]]);
+    assert_eq!(reachable(&db, graph, 0), nodes(&[0, 1, 2]));
+    assert_eq!(reachable(&db, graph, 1), nodes(&[0, 1, 2]));
+    db.assert_logs(expect!["[]"]);
+}
+
+#[test]
+fn self_loop() {
+    let db = Database::default();
+    let graph = Graph::new(&db, vec![vec![0, 1], vec![]]);
+    assert_eq!(reachable(&db, graph, 0), nodes(&[0, 1]));
+}
+
+#[test]
+fn incremental() {
+    let mut db = Database::default();
+    let graph = Graph::new(&db, vec![vec![1], vec![0], vec![]]);
+    assert_eq!(reachable(&db, graph, 0), nodes(&[0, 1]));
+    assert_eq!(reachable(&db, graph, 1), nodes(&[0, 1]));
+    assert_eq!(reachable(&db, graph, 2), nodes(&[]));
+
+    graph
+        .set_edges(&mut db)
+        .to(vec![vec![1], vec![0, 2], vec![]]);
+    assert_eq!(reachable(&db, graph, 0), nodes(&[0, 1, 2]));
+    assert_eq!(reachable(&db, graph, 1), nodes(&[0, 1, 2]));
+    assert_eq!(reachable(&db, graph, 2), nodes(&[]));
+
+    // The cycle is entered from the other participant this time.
+    graph
+        .set_edges(&mut db)
+        .to(vec![vec![], vec![0, 2], vec![1]]);
+    assert_eq!(reachable(&db, graph, 1), nodes(&[0, 1, 2]));
+    assert_eq!(reachable(&db, graph, 2), nodes(&[0, 1, 2]));
+    assert_eq!(reachable(&db, graph, 0), nodes(&[]));
+}
+
+#[test]
+fn diverge() {
+    let db = Database::default();
+    let graph = Graph::new(&db, vec![]);
+    let panic = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| diverging(&db, graph)))
+        .unwrap_err();
+    let message = panic.downcast_ref::<String>().unwrap();
+    assert!(
+        message.contains("did not reach a fixed point after 200 iterations"),
+        "{message}"
+    );
+}
@@ -0,0 +1,70 @@
+//! Test that `recovery_fn` can be used on tracked functions that take
+//! more than one argument and on tracked methods, producing a fallback
+//! value instead of panicking when a cycle is detected.
+
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, infer, MyInput_depth);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input(jar = Jar)]
+struct MyInput {
+    field: u32,
+}
+
+/// Infers a "type" for `input` at `depth`; the type of every
+/// depth refers back to depth zero, so this always cycles.
+#[salsa::tracked(jar = Jar, recovery_fn = recover_infer)]
+fn infer(db: &dyn Db, input: MyInput, depth: u32) -> u32 {
+    if depth == 0 {
+        infer(db, input, input.field(db)) + 1
+    } else {
+        infer(db, input, 0)
+    }
+}
+
+fn recover_infer(_db: &dyn Db, _cycle: &salsa::Cycle, _input: MyInput, depth: u32) -> u32 {
+    depth * 100
+}
+
+#[salsa::tracked(jar = Jar)]
+impl MyInput {
+    #[salsa::tracked(recovery_fn = recover_depth)]
+    fn depth(self, db: &dyn Db) -> u32 {
+        self.depth(db) + 1
+    }
+}
+
+fn recover_depth(db: &dyn Db, _cycle: &salsa::Cycle, input: MyInput) -> u32 {
+    input.field(db) * 10
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+#[test]
+fn multiple_arguments() {
+    let db = Database::default();
+    let input = MyInput::new(&db, 3);
+
+    // Every participant in the cycle takes its fallback value, and each
+    // recovery function is handed the arguments of its own query.
+    assert_eq!(infer(&db, input, 0), 0);
+    assert_eq!(infer(&db, input, 3), 300);
+}
+
+#[test]
+fn tracked_method() {
+    let db = Database::default();
+    let input = MyInput::new(&db, 3);
+    assert_eq!(input.depth(&db), 30);
+}
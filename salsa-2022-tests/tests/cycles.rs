@@ -34,6 +34,7 @@ use salsa::Durability;
 // | Thread | Recovery | Old, New | Dep style | Layers   | Test Name      |
 // | ------ | -------- | -------- | --------- | ------   | ---------      |
 // | Intra  | Panic    | N/A      | Tracked   | direct   | cycle_memoized |
+// | Intra  | Panic    | N/A      | Tracked   | direct   | cycle_catch    |
 // | Intra  | Panic    | N/A      | Untracked | direct   | cycle_volatile |
 // | Intra  | Fallback | N/A      | Tracked   | direct   | cycle_cycle  |
 // | Intra  | Fallback | N/A      | Tracked   | indirect | inner_cycle |
@@ -194,6 +195,19 @@ fn cycle_memoized() {
     expected.assert_debug_eq(&cycle.all_participants(&db));
 }
 
+#[test]
+fn cycle_catch() {
+    let db = Database::default();
+    let input = MyInput::new(&db);
+    let cycle = salsa::Cycle::catch(|| memoized_a(&db, input)).unwrap_err();
+    let participants: Vec<String> = cycle.participants().iter().map(|p| p.to_string()).collect();
+    assert_eq!(participants, cycle.all_participants(&db));
+    assert_eq!(
+        cycle.to_string(),
+        "cycle between `memoized_a(0)`, `memoized_b(0)`"
+    );
+}
+
 #[test]
 fn cycle_volatile() {
     let mut db = Database::default();
@@ -0,0 +1,56 @@
+//! Test the `parallel` and `default` flags of `#[salsa::db]`, which generate the
+//! `ParallelDatabase` impl (cloning the other fields into the snapshots)
+//! and the `Default` impl.
+
+use std::sync::{Arc, Mutex};
+
+use salsa::ParallelDatabase;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, double);
+
+trait Db: salsa::DbWithJar<Jar> {
+    fn push_log(&self, message: String);
+}
+
+#[salsa::input]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::tracked]
+fn double(db: &dyn Db, input: MyInput) -> u32 {
+    db.push_log(format!("double({})", input.field(db)));
+    input.field(db) * 2
+}
+
+#[salsa::db(Jar, parallel, default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logs: Arc<Mutex<Vec<String>>>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {
+    fn push_log(&self, message: String) {
+        self.logs.lock().unwrap().push(message);
+    }
+}
+
+#[test]
+fn snapshot_shares_memos_and_fields() {
+    let db = Database::default();
+    let input = MyInput::new(&db, 21);
+
+    let snapshot = db.snapshot();
+    let value = std::thread::spawn(move || double(&*snapshot, input))
+        .join()
+        .unwrap();
+    assert_eq!(value, 42);
+    assert_eq!(double(&db, input), 42);
+
+    // The snapshot logged to the same `Arc`, and its memo is reused.
+    assert_eq!(*db.logs.lock().unwrap(), ["double(21)"]);
+}
@@ -0,0 +1,58 @@
+//! Test the `db = <path>` option on salsa structs, which names the database
+//! trait used in the signatures of their generated methods.
+
+use salsa::DebugWithDb;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, MyTracked, MyInterned, tracked_fn);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input(db = dyn Db)]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::tracked(db = Db)]
+struct MyTracked {
+    field: u32,
+}
+
+#[salsa::interned(db = dyn Db)]
+struct MyInterned {
+    #[return_ref]
+    name: String,
+}
+
+#[salsa::tracked]
+fn tracked_fn(db: &dyn Db, input: MyInput) -> MyTracked {
+    MyTracked::new(db, input.field(db) * 2)
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+#[test]
+fn execute() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 22);
+    assert_eq!(tracked_fn(&db, input).field(&db), 44);
+
+    input.set_field(&mut db).to(23);
+    assert_eq!(tracked_fn(&db, input).field(&db), 46);
+
+    let interned = MyInterned::new(&db, "foo".to_string());
+    assert_eq!(interned.name(&db), "foo");
+    assert_eq!(
+        format!("{:?}", interned.debug(&db)),
+        r#"MyInterned { [salsa id]: 0, name: "foo" }"#
+    );
+}
@@ -0,0 +1,117 @@
+//! Test `Jar::debug_all`: it lists the values of the fields of inputs and
+//! the number of instances of the other salsa structs, of memos and of
+//! queries that accumulated values, so that two states can be compared.
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(
+    MyInput,
+    MyTracked,
+    MyInterned,
+    Warnings,
+    make_tracked,
+    intern_text,
+);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input]
+struct MyInput {
+    field: u32,
+    text: String,
+}
+
+#[salsa::tracked]
+struct MyTracked {
+    value: u32,
+}
+
+#[salsa::interned]
+struct MyInterned {
+    text: String,
+}
+
+#[salsa::accumulator]
+struct Warnings(String);
+
+#[salsa::tracked]
+fn make_tracked(db: &dyn Db, input: MyInput) -> MyTracked {
+    if input.field(db) == 0 {
+        Warnings::push(db, "zero".to_string());
+    }
+    MyTracked::new(db, input.field(db))
+}
+
+#[salsa::tracked]
+fn intern_text(db: &dyn Db, input: MyInput) -> MyInterned {
+    MyInterned::new(db, input.text(db))
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+#[test]
+fn execute() {
+    let mut db = Database::default();
+    let input1 = MyInput::new(&db, 0, "a".to_string());
+    let input2 = MyInput::new(&db, 22, "a".to_string());
+
+    make_tracked(&db, input1);
+    make_tracked(&db, input2);
+    intern_text(&db, input1);
+    intern_text(&db, input2);
+    expect![[r#"
+        debug_all::Jar {
+            MyInput::field: {
+                0: 0,
+                1: 22,
+            },
+            MyInput::text: {
+                0: "a",
+                1: "a",
+            },
+            MyInput: 2,
+            MyTracked::value: 2,
+            MyTracked: 2,
+            MyInterned: 1,
+            Warnings: 1,
+            make_tracked: 2,
+            intern_text: 2,
+        }"#]]
+    .assert_eq(&format!("{:#?}", Jar::debug_all(&db)));
+
+    // The dump of a `&dyn Db` is the same.
+    let dyn_db: &dyn Db = &db;
+    assert_eq!(Jar::debug_all(dyn_db), Jar::debug_all(&db));
+
+    input1.set_text(&mut db).to("b".to_string());
+    intern_text(&db, input1);
+    expect![[r#"
+        debug_all::Jar {
+            MyInput::field: {
+                0: 0,
+                1: 22,
+            },
+            MyInput::text: {
+                0: "b",
+                1: "a",
+            },
+            MyInput: 2,
+            MyTracked::value: 2,
+            MyTracked: 2,
+            MyInterned: 2,
+            Warnings: 1,
+            make_tracked: 2,
+            intern_text: 2,
+        }"#]]
+    .assert_eq(&format!("{:#?}", Jar::debug_all(&db)));
+}
@@ -4,7 +4,7 @@ use expect_test::expect;
 use salsa::DebugWithDb;
 
 #[salsa::jar(db = Db)]
-struct Jar(MyInput, ComplexStruct);
+struct Jar(MyInput, ComplexStruct, MyTracked, MyInterned, make_tracked);
 
 trait Db: salsa::DbWithJar<Jar> {}
 
@@ -24,6 +24,23 @@ struct ComplexStruct {
     not_salsa: NotSalsa,
 }
 
+#[salsa::tracked]
+struct MyTracked {
+    #[id]
+    name: String,
+    inputs: Vec<MyInput>,
+}
+
+#[salsa::interned]
+struct MyInterned {
+    text: String,
+}
+
+#[salsa::tracked]
+fn make_tracked(db: &dyn Db, input: MyInput) -> MyTracked {
+    MyTracked::new(db, format!("tracked{}", input.field(db)), vec![input])
+}
+
 #[salsa::db(Jar)]
 #[derive(Default)]
 struct Database {
@@ -56,3 +73,49 @@ fn input() {
     ]];
     expected.assert_eq(&actual);
 }
+
+#[test]
+fn tracked_and_interned() {
+    let db = Database::default();
+
+    let input = MyInput::new(&db, 22);
+    let tracked = make_tracked(&db, input);
+
+    // default debug only includes identity fields
+    let actual = format!("{:?}", tracked.debug(&db));
+    let expected = expect![[r#"MyTracked { [salsa id]: 0, name: "tracked22" }"#]];
+    expected.assert_eq(&actual);
+
+    // all fields, resolving the salsa structs they contain
+    let actual = format!("{:?}", tracked.debug_all(&db));
+    let expected = expect![[
+        r#"MyTracked { [salsa id]: 0, name: "tracked22", inputs: [MyInput { [salsa id]: 0, field: 22 }] }"#
+    ]];
+    expected.assert_eq(&actual);
+
+    // interned fields are always included
+    let interned = MyInterned::new(&db, "hello".to_string());
+    let actual = format!("{:?}", interned.debug(&db));
+    let expected = expect![[r#"MyInterned { [salsa id]: 0, text: "hello" }"#]];
+    expected.assert_eq(&actual);
+}
+
+#[test]
+fn collections() {
+    let db = Database::default();
+
+    let a = MyInput::new(&db, 1);
+    let b = MyInput::new(&db, 2);
+
+    let slice: &[MyInput] = &[a, b];
+    let actual = format!("{:?}", DebugWithDb::debug_all(&slice, &db));
+    let expected =
+        expect!["[MyInput { [salsa id]: 0, field: 1 }, MyInput { [salsa id]: 1, field: 2 }]"];
+    expected.assert_eq(&actual);
+
+    let map: std::collections::BTreeMap<MyInput, MyInput> = [(a, b)].into_iter().collect();
+    let actual = format!("{:?}", map.debug_all(&db));
+    let expected =
+        expect!["{MyInput { [salsa id]: 0, field: 1 }: MyInput { [salsa id]: 1, field: 2 }}"];
+    expected.assert_eq(&actual);
+}
@@ -0,0 +1,138 @@
+//! Test `#[salsa::delegate_db]`: wrappers around a database, holding it,
+//! a reference to it, or a snapshot of it, share its memos.
+
+use salsa::ParallelDatabase;
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, doubled);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::input]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::tracked]
+fn doubled(db: &dyn Db, input: MyInput) -> u32 {
+    db.push_log(format!("doubled({})", input.field(db)));
+    input.field(db) * 2
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl salsa::Database for Database {}
+
+impl salsa::ParallelDatabase for Database {
+    fn snapshot(&self) -> salsa::Snapshot<Self> {
+        salsa::Snapshot::new(Database {
+            storage: self.storage.snapshot(),
+            logger: Logger::default(),
+        })
+    }
+}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+/// A database used to answer one request.
+#[salsa::delegate_db(Db)]
+struct RequestDb<'a> {
+    db: &'a Database,
+    request: u32,
+}
+
+impl salsa::Database for RequestDb<'_> {}
+
+impl HasLogger for RequestDb<'_> {
+    fn logger(&self) -> &Logger {
+        self.db.logger()
+    }
+}
+
+#[salsa::delegate_db(Db)]
+struct OwnedDb(Database);
+
+impl salsa::Database for OwnedDb {}
+
+impl HasLogger for OwnedDb {
+    fn logger(&self) -> &Logger {
+        self.0.logger()
+    }
+}
+
+#[salsa::delegate_db(Db)]
+struct SnapshotDb(salsa::Snapshot<Database>);
+
+impl salsa::Database for SnapshotDb {}
+
+impl HasLogger for SnapshotDb {
+    fn logger(&self) -> &Logger {
+        self.0.logger()
+    }
+}
+
+#[test]
+fn shared_memos() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 1);
+
+    let request = RequestDb {
+        db: &db,
+        request: 1,
+    };
+    assert_eq!(request.request, 1);
+    assert_eq!(doubled(&request, input), 2);
+    assert_eq!(doubled(&db, input), 2);
+    db.assert_logs(expect![[r#"
+        [
+            "doubled(1)",
+        ]"#]]);
+
+    let snapshot = SnapshotDb(db.snapshot());
+    assert_eq!(doubled(&snapshot, input), 2);
+    drop(snapshot);
+    db.assert_logs(expect!["[]"]);
+
+    input.set_field(&mut db).to(2);
+    let request = RequestDb {
+        db: &db,
+        request: 2,
+    };
+    assert_eq!(doubled(&request, input), 4);
+    assert_eq!(doubled(&db, input), 4);
+    db.assert_logs(expect![[r#"
+        [
+            "doubled(2)",
+        ]"#]]);
+}
+
+#[test]
+fn write_through_owned() {
+    let mut db = OwnedDb(Database::default());
+    let input = MyInput::new(&db, 1);
+    assert_eq!(doubled(&db, input), 2);
+
+    input.set_field(&mut db).to(3);
+    assert_eq!(doubled(&db, input), 6);
+    assert_eq!(doubled(&db.0, input), 6);
+    db.0.assert_logs(expect![[r#"
+        [
+            "doubled(1)",
+            "doubled(3)",
+        ]"#]]);
+}
@@ -0,0 +1,101 @@
+//! Test that reading the fields of a tracked struct that was deleted, because
+//! the query that created it no longer does, panics with a clear message,
+//! also once its slot was reused by another struct.
+
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, MyTracked, create_tracked_structs);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::tracked]
+struct MyTracked {
+    #[id]
+    index: u32,
+    value: u32,
+}
+
+#[salsa::tracked]
+fn create_tracked_structs(db: &dyn Db, input: MyInput) -> Vec<MyTracked> {
+    (0..input.field(db))
+        .map(|i| MyTracked::new(db, i, i * 10))
+        .collect()
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+/// Creates three tracked structs, then re-executes the query so that
+/// it only creates the first one, and returns the last one.
+fn deleted_struct(db: &mut Database) -> MyTracked {
+    let input = MyInput::new(db, 3);
+    let structs = create_tracked_structs(db, input);
+    assert_eq!(structs[2].value(db), 20);
+
+    input.set_field(db).to(1);
+    let kept = create_tracked_structs(db, input);
+    assert_eq!(kept.len(), 1);
+    assert_eq!(kept[0].value(db), 0);
+    structs[2]
+}
+
+#[test]
+#[should_panic(expected = "`MyTracked(2)` was deleted: `create_tracked_structs(0)`, \
+                           which created it, no longer creates it")]
+fn read_id_field() {
+    let mut db = Database::default();
+    let tracked = deleted_struct(&mut db);
+    tracked.index(&db);
+}
+
+#[test]
+#[should_panic(expected = "`MyTracked(2)` was deleted: `create_tracked_structs(0)`, \
+                           which created it, no longer creates it")]
+fn read_value_field() {
+    let mut db = Database::default();
+    let tracked = deleted_struct(&mut db);
+    tracked.value(&db);
+}
+
+#[test]
+fn kept_struct_is_still_readable() {
+    let mut db = Database::default();
+    deleted_struct(&mut db);
+    let input = MyInput::new(&db, 2);
+    let structs = create_tracked_structs(&db, input);
+    assert_eq!(structs[1].index(&db), 1);
+    assert_eq!(structs[1].value(&db), 10);
+}
+
+#[test]
+#[should_panic(
+    expected = "`MyTracked(2)` is stale: the entity was recreated in revision 3, \
+                           with the next generation of its id"
+)]
+fn read_recreated_struct() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 3);
+    let structs = create_tracked_structs(&db, input);
+    input.set_field(&mut db).to(1);
+    assert_eq!(create_tracked_structs(&db, input).len(), 1);
+
+    // The slots of the deleted structs are reused in the next revision.
+    input.set_field(&mut db).to(3);
+    let recreated = create_tracked_structs(&db, input);
+    assert_ne!(recreated[2], structs[2]);
+    assert_eq!(recreated[2].value(&db), 20);
+    structs[2].value(&db);
+}
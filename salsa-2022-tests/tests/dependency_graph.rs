@@ -0,0 +1,83 @@
+//! Test that `Storage::dependency_graph` reports the dependencies
+//! recorded for memoized queries, and renders them as DOT and JSON.
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, double, sum, unrelated);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input(jar = Jar)]
+struct MyInput {
+    field: u32,
+    other: u32,
+}
+
+#[salsa::tracked(jar = Jar)]
+fn double(db: &dyn Db, input: MyInput) -> u32 {
+    input.field(db) * 2
+}
+
+#[salsa::tracked(jar = Jar)]
+fn sum(db: &dyn Db, input: MyInput) -> u32 {
+    double(db, input) + input.other(db)
+}
+
+#[salsa::tracked(jar = Jar)]
+fn unrelated(db: &dyn Db, input: MyInput) -> u32 {
+    input.other(db)
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl Database {
+    fn dump_dependency_graph(
+        &self,
+        query_filter: impl Fn(&str) -> bool,
+    ) -> salsa::dependency_graph::DependencyGraph {
+        self.storage.dependency_graph(query_filter)
+    }
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+#[test]
+fn dot() {
+    let db = Database::default();
+    let input = MyInput::new(&db, 1, 2);
+    assert_eq!(sum(&db, input), 4);
+    assert_eq!(unrelated(&db, input), 2);
+
+    let graph = db.dump_dependency_graph(|label| label.starts_with("sum"));
+    expect![[r#"
+        digraph {
+            n0 [label="sum(0)"];
+            n1 [label="double(0)"];
+            n2 [label="other(0)"];
+            n3 [label="field(0)"];
+            n0 -> n1;
+            n0 -> n2;
+            n1 -> n3;
+        }
+    "#]]
+    .assert_eq(&graph.to_dot());
+}
+
+#[test]
+fn json() {
+    let db = Database::default();
+    let input = MyInput::new(&db, 1, 2);
+    assert_eq!(double(&db, input), 2);
+
+    let graph = db.dump_dependency_graph(|_| true);
+    expect![[r#"{"nodes":[{"id":0,"label":"double(0)"},{"id":1,"label":"field(0)"}],"edges":[{"from":0,"to":1}]}"#]]
+    .assert_eq(&graph.to_json());
+}
@@ -0,0 +1,71 @@
+//! Test `Database::creator_of` and `Database::entities_created_by`: finding
+//! the query that created a tracked struct, and the tracked structs that a
+//! query created, in its last execution.
+
+use salsa::tracked_struct::TrackedStructInDb;
+use salsa::{Database as _, DebugWithDb};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, MyTracked, create_tracked_structs, total);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::tracked]
+struct MyTracked {
+    #[id]
+    index: u32,
+}
+
+#[salsa::tracked]
+fn create_tracked_structs(db: &dyn Db, input: MyInput) -> Vec<MyTracked> {
+    (0..input.field(db))
+        .map(|i| MyTracked::new(db, i))
+        .collect()
+}
+
+#[salsa::tracked]
+fn total(db: &dyn Db, input: MyInput) -> u32 {
+    create_tracked_structs(db, input)
+        .iter()
+        .map(|tracked| tracked.index(db))
+        .sum()
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+#[test]
+fn execute() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 3);
+    assert_eq!(total(&db, input), 3);
+
+    let structs = create_tracked_structs(&db, input);
+    let creator = db.creator_of(structs[1].database_key_index(&db)).unwrap();
+    expect!["create_tracked_structs(0)"].assert_eq(&format!("{:?}", creator.debug(&db)));
+    let created = db.entities_created_by(creator);
+    expect!["[MyTracked(0), MyTracked(1), MyTracked(2)]"]
+        .assert_eq(&format!("{:?}", created.debug(&db)));
+
+    // Deleted tracked structs have no creator.
+    input.set_field(&mut db).to(1);
+    assert_eq!(total(&db, input), 0);
+    assert_eq!(db.creator_of(structs[2].database_key_index(&db)), None);
+    expect!["[MyTracked(0)]"]
+        .assert_eq(&format!("{:?}", db.entities_created_by(creator).debug(&db)));
+}
@@ -0,0 +1,81 @@
+//! Test `salsa::EntityId`: entities are displayed as `<struct>#<id>@rev<revision>`,
+//! and parsed back to the same entity.
+
+use salsa::{AsId, Database as _, EntityId, Id};
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, Function, functions);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input]
+struct MyInput {
+    count: u32,
+}
+
+#[salsa::tracked]
+struct Function {
+    index: u32,
+}
+
+#[salsa::tracked]
+fn functions(db: &dyn Db, input: MyInput) -> Vec<Function> {
+    (0..input.count(db)).map(|i| Function::new(db, i)).collect()
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+#[test]
+fn display_and_parse() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 2);
+    input.set_count(&mut db).to(3);
+    let function = functions(&db, input)[2];
+
+    let entity_id = EntityId::new(&db, function);
+    let text = entity_id.to_string();
+    assert_eq!(text, format!("Function#{}@rev2", function.as_id()));
+
+    let parsed: EntityId = text.parse().unwrap();
+    assert_eq!(parsed, entity_id);
+    assert_eq!(parsed.revision, db.current_revision());
+    assert_eq!(parsed.entity::<Function>(), Some(function));
+    assert_eq!(parsed.entity::<MyInput>(), None);
+
+    // Another database creating the same entities finds the same one.
+    let mut other = Database::default();
+    let input = MyInput::new(&other, 1);
+    input.set_count(&mut other).to(3);
+    let function = parsed.entity::<Function>().unwrap();
+    assert_eq!(functions(&other, input)[2], function);
+    assert_eq!(function.index(&other), 2);
+}
+
+#[test]
+fn parse_errors() {
+    assert_eq!("12".parse::<Id>(), Ok(Id::from_u32(12)));
+    assert!("-1".parse::<Id>().is_err());
+    assert!(u32::MAX.to_string().parse::<Id>().is_err());
+
+    for text in [
+        "Function",
+        "Function#12",
+        "#12@rev1",
+        "Function#x@rev1",
+        "Function#12@1",
+    ] {
+        let err = text.parse::<EntityId>().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("`{text}` is not an entity id of the form `<struct>#<id>@rev<revision>`")
+        );
+    }
+}
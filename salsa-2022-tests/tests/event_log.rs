@@ -0,0 +1,99 @@
+//! Test the event log of the `event-log` feature: the last events of a
+//! database are kept, and still there after a panic escapes a query.
+
+use std::panic::{self, AssertUnwindSafe};
+
+use expect_test::expect;
+use salsa::storage::HasJarsDyn;
+use salsa::DebugWithDb;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, outer, inner);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::tracked]
+fn outer(db: &dyn Db, input: MyInput) -> u32 {
+    inner(db, input) + 1
+}
+
+#[salsa::tracked]
+fn inner(db: &dyn Db, input: MyInput) -> u32 {
+    let field = input.field(db);
+    assert!(field < 100, "field too large: {field}");
+    field
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+fn recent_events(db: &Database) -> Vec<String> {
+    db.runtime()
+        .recent_events()
+        .iter()
+        .map(|event| format!("{:?}", event.kind.debug(db)))
+        .collect()
+}
+
+#[test]
+fn keeps_the_last_events() {
+    let mut db = Database::default();
+    db.storage.set_event_log_capacity(3);
+    let input = MyInput::new(&db, 22);
+    assert_eq!(outer(&db, input), 23);
+    expect![[r#"
+        [
+            "WillExecute { database_key: outer(0) }",
+            "WillCheckCancellation",
+            "WillExecute { database_key: inner(0) }",
+        ]
+    "#]]
+    .assert_debug_eq(&recent_events(&db));
+
+    // Reading the events does not remove them.
+    assert_eq!(recent_events(&db).len(), 3);
+}
+
+#[test]
+fn events_before_a_panic() {
+    let mut db = Database::default();
+    db.storage.set_event_log_capacity(4);
+    let input = MyInput::new(&db, 22);
+    input.set_field(&mut db).to(122);
+    let err = panic::catch_unwind(AssertUnwindSafe(|| outer(&db, input))).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<String>().unwrap(),
+        "field too large: 122"
+    );
+    expect![[r#"
+        [
+            "WillCheckCancellation",
+            "WillExecute { database_key: outer(0) }",
+            "WillCheckCancellation",
+            "WillExecute { database_key: inner(0) }",
+        ]
+    "#]]
+    .assert_debug_eq(&recent_events(&db));
+}
+
+#[test]
+fn disabled() {
+    let mut db = Database::default();
+    db.storage.set_event_log_capacity(0);
+    let input = MyInput::new(&db, 22);
+    assert_eq!(outer(&db, input), 23);
+    assert!(db.runtime().recent_events().is_empty());
+}
@@ -0,0 +1,88 @@
+//! Test that if a value field of a tracked struct changes,
+//! functions that only read one of its `#[id]` fields are not re-executed:
+//! dependencies are recorded per field rather than on the struct as a whole.
+
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, Item, make_item, item_name_len, item_body_len);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::input(jar = Jar)]
+struct MyInput {
+    #[return_ref]
+    text: String,
+}
+
+#[salsa::tracked(jar = Jar)]
+struct Item {
+    #[id]
+    #[return_ref]
+    name: String,
+
+    #[return_ref]
+    body: String,
+}
+
+#[salsa::tracked(jar = Jar)]
+fn make_item(db: &dyn Db, input: MyInput) -> Item {
+    let (name, body) = input.text(db).split_once('=').unwrap();
+    Item::new(db, name.to_string(), body.to_string())
+}
+
+#[salsa::tracked(jar = Jar)]
+fn item_name_len(db: &dyn Db, input: MyInput) -> usize {
+    db.push_log("item_name_len".to_string());
+    make_item(db, input).name(db).len()
+}
+
+#[salsa::tracked(jar = Jar)]
+fn item_body_len(db: &dyn Db, input: MyInput) -> usize {
+    db.push_log("item_body_len".to_string());
+    make_item(db, input).body(db).len()
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn execute() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, "a=1".to_string());
+
+    assert_eq!(item_name_len(&db, input), 1);
+    assert_eq!(item_body_len(&db, input), 1);
+    db.assert_logs(expect![[r#"
+        [
+            "item_name_len",
+            "item_body_len",
+        ]"#]]);
+
+    // Changing the body re-creates the same `Item` (its `#[id]` field is
+    // unchanged) with a new body: only the query reading `body` re-executes.
+    input.set_text(&mut db).to("a=22".to_string());
+    assert_eq!(item_name_len(&db, input), 1);
+    assert_eq!(item_body_len(&db, input), 2);
+    db.assert_logs(expect![[r#"
+        [
+            "item_body_len",
+        ]"#]]);
+}
@@ -0,0 +1,104 @@
+//! Test `salsa::external`: `refresh_external_inputs` polls the external inputs
+//! and only the queries reading those whose stamp changed are executed again.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use salsa::external::{Db as _, ExternalInput, ExternalInputs, Stamp};
+use salsa::Database as _;
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(version_of);
+
+trait Db: salsa::DbWithJar<Jar> + salsa::external::Db + HasLogger {}
+
+#[salsa::tracked]
+fn version_of(db: &dyn Db, input: ExternalInput) -> Stamp {
+    let stamp = input.read(db);
+    db.push_log(format!("version_of({:?})", stamp));
+    stamp
+}
+
+#[salsa::db(Jar, salsa::external::Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    external_inputs: ExternalInputs,
+    logger: Logger,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl salsa::external::Db for Database {
+    fn external_inputs(&self) -> &ExternalInputs {
+        &self.external_inputs
+    }
+}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+/// An external value, e.g. the version of a document in an editor.
+fn external_value(value: u64) -> (Arc<AtomicU64>, ExternalInput, Database) {
+    let db = Database::default();
+    let value = Arc::new(AtomicU64::new(value));
+    let polled = value.clone();
+    let input = ExternalInput::new(&db, move |_| Stamp::from(polled.load(Ordering::SeqCst)));
+    (value, input, db)
+}
+
+#[test]
+fn refresh_changed_input() {
+    let (value, input, mut db) = external_value(1);
+    assert_eq!(version_of(&db, input), Stamp::from(1));
+    db.assert_logs(expect![[r#"
+        [
+            "version_of(Stamp(1))",
+        ]"#]]);
+
+    value.store(2, Ordering::SeqCst);
+    db.refresh_external_inputs();
+    assert_eq!(version_of(&db, input), Stamp::from(2));
+    db.assert_logs(expect![[r#"
+        [
+            "version_of(Stamp(2))",
+        ]"#]]);
+}
+
+#[test]
+fn no_new_revision_if_unchanged() {
+    let (_value, input, mut db) = external_value(1);
+    let other_value = Arc::new(AtomicU64::new(10));
+    let polled = other_value.clone();
+    let other = ExternalInput::new(&db, move |_| Stamp::from(polled.load(Ordering::SeqCst)));
+    version_of(&db, input);
+    version_of(&db, other);
+    db.assert_logs(expect![[r#"
+        [
+            "version_of(Stamp(1))",
+            "version_of(Stamp(10))",
+        ]"#]]);
+
+    let token = db.attach_revision();
+    db.refresh_external_inputs();
+    assert!(db.is_still_valid(token));
+
+    // Only the query reading the changed input is executed again.
+    other_value.store(11, Ordering::SeqCst);
+    db.refresh_external_inputs();
+    version_of(&db, input);
+    version_of(&db, other);
+    db.assert_logs(expect![[r#"
+        [
+            "version_of(Stamp(11))",
+        ]"#]]);
+}
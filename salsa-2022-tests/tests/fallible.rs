@@ -0,0 +1,137 @@
+//! Test tracked functions returning a `Result`, with the `cycle_err`
+//! option (cycles are returned as errors) and the `retry_err` option
+//! (errors are not backdated and are recomputed in the next revision).
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, fetch, length, depth);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {
+    /// Whether the network is available to `fetch`; not tracked by salsa.
+    fn online(&self) -> bool;
+}
+
+#[salsa::input]
+struct MyInput {
+    url: String,
+    parent: Option<MyInput>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Error {
+    Offline,
+    Cycle,
+}
+
+impl From<salsa::Cycle> for Error {
+    fn from(_: salsa::Cycle) -> Self {
+        Error::Cycle
+    }
+}
+
+#[salsa::tracked(retry_err)]
+fn fetch(db: &dyn Db, input: MyInput) -> Result<String, Error> {
+    db.push_log(format!("fetch({})", input.url(db)));
+    if db.online() {
+        Ok(format!("contents of {}", input.url(db)))
+    } else {
+        Err(Error::Offline)
+    }
+}
+
+#[salsa::tracked]
+fn length(db: &dyn Db, input: MyInput) -> Result<usize, Error> {
+    db.push_log(format!("length({})", input.url(db)));
+    Ok(fetch(db, input)?.len())
+}
+
+#[salsa::tracked(cycle_err)]
+fn depth(db: &dyn Db, input: MyInput) -> Result<u32, Error> {
+    match input.parent(db) {
+        Some(parent) => Ok(depth(db, parent)? + 1),
+        None => Ok(0),
+    }
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+    offline: AtomicBool,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {
+    fn online(&self) -> bool {
+        !self.offline.load(Ordering::SeqCst)
+    }
+}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn retry_err() {
+    let mut db = Database::default();
+    db.offline.store(true, Ordering::SeqCst);
+    let a = MyInput::new(&db, "a".to_string(), None);
+    let b = MyInput::new(&db, "b".to_string(), None);
+
+    assert_eq!(length(&db, a), Err(Error::Offline));
+    assert_eq!(length(&db, a), Err(Error::Offline));
+    db.assert_logs(expect![[r#"
+        [
+            "length(a)",
+            "fetch(a)",
+        ]"#]]);
+
+    // The error is recomputed in the next revision, even though
+    // nothing `fetch` read has changed, and it is not backdated.
+    b.set_url(&mut db).to("c".to_string());
+    assert_eq!(length(&db, a), Err(Error::Offline));
+    db.assert_logs(expect![[r#"
+        [
+            "fetch(a)",
+            "length(a)",
+        ]"#]]);
+
+    b.set_url(&mut db).to("d".to_string());
+    db.offline.store(false, Ordering::SeqCst);
+    assert_eq!(length(&db, a), Ok(13));
+    db.assert_logs(expect![[r#"
+        [
+            "fetch(a)",
+            "length(a)",
+        ]"#]]);
+
+    // Successful values are memoized as usual.
+    b.set_url(&mut db).to("e".to_string());
+    assert_eq!(length(&db, a), Ok(13));
+    db.assert_logs(expect!["[]"]);
+}
+
+#[test]
+fn cycle_err() {
+    let mut db = Database::default();
+    let a = MyInput::new(&db, "a".to_string(), None);
+    let b = MyInput::new(&db, "b".to_string(), Some(a));
+    assert_eq!(depth(&db, b), Ok(1));
+
+    a.set_parent(&mut db).to(Some(b));
+    assert_eq!(depth(&db, a), Err(Error::Cycle));
+    assert_eq!(depth(&db, b), Err(Error::Cycle));
+
+    a.set_parent(&mut db).to(None);
+    assert_eq!(depth(&db, b), Ok(1));
+}
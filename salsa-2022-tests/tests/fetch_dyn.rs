@@ -0,0 +1,112 @@
+//! Test calling tracked functions by name with `Database::fetch_dyn`,
+//! as a REPL or a debugger would.
+
+use salsa::{AsId, Database as _, Id};
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, length, words, sum, answer);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input]
+struct MyInput {
+    text: String,
+}
+
+#[salsa::tracked]
+fn length(db: &dyn Db, input: MyInput) -> usize {
+    input.text(db).len()
+}
+
+#[salsa::tracked(return_ref)]
+fn words(db: &dyn Db, input: MyInput) -> Vec<String> {
+    input.text(db).split(' ').map(str::to_string).collect()
+}
+
+#[salsa::tracked]
+fn sum(db: &dyn Db, a: MyInput, b: MyInput) -> usize {
+    length(db, a) + length(db, b)
+}
+
+#[salsa::tracked]
+fn answer(_db: &dyn Db) -> u32 {
+    42
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+#[test]
+fn query_names() {
+    let db = Database::default();
+    // `words` returns its value by reference, so it can't be cloned.
+    assert_eq!(
+        db.query_names(),
+        vec![
+            "fetch_dyn::Jar::length",
+            "fetch_dyn::Jar::sum",
+            "fetch_dyn::Jar::answer",
+        ]
+    );
+}
+
+#[test]
+fn fetch() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, "hello world".to_string());
+
+    let value = db
+        .fetch_dyn("fetch_dyn::Jar::length", input.as_id())
+        .unwrap();
+    assert_eq!(*value.downcast::<usize>().unwrap(), 11);
+
+    // The value is memoized and tracked as if the function were called directly.
+    input.set_text(&mut db).to("hi".to_string());
+    let value = db
+        .fetch_dyn("fetch_dyn::Jar::length", input.as_id())
+        .unwrap();
+    assert_eq!(*value.downcast::<usize>().unwrap(), 2);
+    assert_eq!(length(&db, input), 2);
+
+    let value = db
+        .fetch_dyn("fetch_dyn::Jar::answer", Id::from_u32(0))
+        .unwrap();
+    assert_eq!(*value.downcast::<u32>().unwrap(), 42);
+    assert_eq!(answer(&db), 42);
+}
+
+#[test]
+fn fetch_with_interned_arguments() {
+    let db = Database::default();
+    let a = MyInput::new(&db, "abc".to_string());
+    let b = MyInput::new(&db, "de".to_string());
+    assert_eq!(sum(&db, a, b), 5);
+
+    // The arguments `(a, b)` were interned as the first key of `sum`.
+    let value = db
+        .fetch_dyn("fetch_dyn::Jar::sum", Id::from_u32(0))
+        .unwrap();
+    assert_eq!(*value.downcast::<usize>().unwrap(), 5);
+}
+
+#[test]
+fn unknown_query() {
+    let db = Database::default();
+    let input = MyInput::new(&db, "hello world".to_string());
+    assert_eq!(words(&db, input).len(), 2);
+    assert!(db
+        .fetch_dyn("fetch_dyn::Jar::words", input.as_id())
+        .is_none());
+    assert!(db
+        .fetch_dyn("fetch_dyn::Jar::MyInput", input.as_id())
+        .is_none());
+    assert!(db.fetch_dyn("length", input.as_id()).is_none());
+}
@@ -0,0 +1,108 @@
+//! Test the `firewall` option of tracked functions:
+//!
+//! * the functions that depend on a firewall function are not executed again
+//!   when its value stays the same;
+//! * `sweep` keeps its values, so that new values can still be compared with them;
+//! * reading untracked state from a firewall function panics.
+
+use expect_test::expect;
+use salsa_2022_tests::{HasLogger, Logger};
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(File, line_count, report, untracked);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::input]
+struct File {
+    #[return_ref]
+    text: String,
+}
+
+#[salsa::tracked(firewall)]
+fn line_count(db: &dyn Db, file: File) -> usize {
+    db.push_log(format!("line_count({:?})", file.text(db)));
+    file.text(db).lines().count()
+}
+
+#[salsa::tracked]
+fn report(db: &dyn Db, file: File) -> String {
+    let count = line_count(db, file);
+    db.push_log(format!("report({count})"));
+    format!("{count} lines")
+}
+
+#[salsa::tracked(firewall)]
+fn untracked(db: &dyn Db, file: File) -> usize {
+    db.report_untracked_read();
+    file.text(db).len()
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl Database {
+    fn sweep(&mut self, max_age: usize) {
+        self.storage.sweep(max_age);
+    }
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn same_projection_stops_changes() {
+    let mut db = Database::default();
+    let file = File::new(&db, "a\nb".to_string());
+    assert_eq!(report(&db, file), "2 lines");
+    db.assert_logs(expect![[r#"
+        [
+            "line_count(\"a\\nb\")",
+            "report(2)",
+        ]"#]]);
+
+    // Same number of lines: `report` is not executed again.
+    file.set_text(&mut db).to("c\nd".to_string());
+    assert_eq!(report(&db, file), "2 lines");
+    db.assert_logs(expect![[r#"
+        [
+            "line_count(\"c\\nd\")",
+        ]"#]]);
+
+    // The value of `line_count` survives a sweep, so this still holds afterwards.
+    db.sweep(0);
+    file.set_text(&mut db).to("e\nf".to_string());
+    assert_eq!(report(&db, file), "2 lines");
+    db.assert_logs(expect![[r#"
+        [
+            "line_count(\"e\\nf\")",
+        ]"#]]);
+
+    file.set_text(&mut db).to("e\nf\ng".to_string());
+    assert_eq!(report(&db, file), "3 lines");
+    db.assert_logs(expect![[r#"
+        [
+            "line_count(\"e\\nf\\ng\")",
+            "report(3)",
+        ]"#]]);
+}
+
+#[test]
+#[should_panic(expected = "read untracked state, which firewall queries must not do")]
+fn untracked_read_panics() {
+    let db = Database::default();
+    let file = File::new(&db, "a".to_string());
+    untracked(&db, file);
+}
@@ -0,0 +1,125 @@
+//! Test `Storage::fork`: the copy of a database reuses its memoized values,
+//! and the two databases can then be edited separately, even when they
+//! move on to the same revisions.
+
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, MyTracked, double, tracked, text);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::input]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::tracked]
+struct MyTracked {
+    field: u32,
+}
+
+#[salsa::tracked]
+fn double(db: &dyn Db, input: MyInput) -> u32 {
+    db.push_log(format!("double({})", input.field(db)));
+    tracked(db, input).field(db) * 2
+}
+
+#[salsa::tracked]
+fn tracked(db: &dyn Db, input: MyInput) -> MyTracked {
+    MyTracked::new(db, input.field(db))
+}
+
+#[salsa::tracked(return_ref)]
+fn text(db: &dyn Db, input: MyInput) -> NotClone {
+    db.push_log(format!("text({})", input.field(db)));
+    NotClone(input.field(db).to_string())
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct NotClone(String);
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl Database {
+    fn fork(&self) -> Self {
+        Database {
+            storage: self.storage.fork().unwrap(),
+            logger: Default::default(),
+        }
+    }
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn reuse_memos() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 1);
+    assert_eq!(double(&db, input), 2);
+    assert_eq!(text(&db, input).0, "1");
+    db.assert_logs(expect![[r#"
+        [
+            "double(1)",
+            "text(1)",
+        ]"#]]);
+
+    // Values that cannot be cloned are computed again.
+    let mut fork = db.fork();
+    assert_eq!(double(&fork, input), 2);
+    assert_eq!(text(&fork, input).0, "1");
+    fork.assert_logs(expect![[r#"
+        [
+            "text(1)",
+        ]"#]]);
+
+    // Writes to the fork are not seen by the original.
+    input.set_field(&mut fork).to(3);
+    assert_eq!(double(&fork, input), 6);
+    assert_eq!(double(&db, input), 2);
+    fork.assert_logs(expect![[r#"
+        [
+            "double(3)",
+        ]"#]]);
+    db.assert_logs(expect!["[]"]);
+}
+
+#[test]
+fn separate_revisions() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 1);
+    let other = MyInput::new(&db, 10);
+    assert_eq!(double(&db, input), 2);
+    let mut fork = db.fork();
+
+    // Both databases move on to the same revision: verifying the memo
+    // in the original does not verify it in the fork.
+    other.set_field(&mut db).to(20);
+    input.set_field(&mut fork).to(5);
+    assert_eq!(double(&db, input), 2);
+    assert_eq!(double(&fork, input), 10);
+    db.assert_logs(expect![[r#"
+        [
+            "double(1)",
+        ]"#]]);
+    fork.assert_logs(expect![[r#"
+        [
+            "double(5)",
+        ]"#]]);
+}
@@ -0,0 +1,102 @@
+//! Test the `salsa-fs` jar: files are read into `SourceFile` inputs, and
+//! `apply_fs_changes` updates them from watcher events.
+
+use std::path::{Path, PathBuf};
+
+use salsa_2022_tests::{HasLogger, Logger};
+use salsa_fs::notify::event::{Event, EventKind, ModifyKind, RemoveKind};
+use salsa_fs::{source_file, Db as _, Files, SourceFile};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(line_count);
+
+trait Db: salsa::DbWithJar<Jar> + salsa_fs::Db + HasLogger {}
+
+#[salsa::tracked]
+fn line_count(db: &dyn Db, file: SourceFile) -> Option<usize> {
+    db.push_log(format!("line_count({})", file_name(db, file)));
+    Some(file.contents(db).as_ref()?.lines().count())
+}
+
+fn file_name(db: &dyn Db, file: SourceFile) -> String {
+    file.path(db)
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[salsa::db(Jar, salsa_fs::Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+    files: Files,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl salsa_fs::Db for Database {
+    fn files(&self) -> &Files {
+        &self.files
+    }
+}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+fn modified(path: &Path) -> Event {
+    Event::new(EventKind::Modify(ModifyKind::Any)).add_path(path.to_path_buf())
+}
+
+fn temp_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("salsa-fs-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn execute() {
+    let dir = temp_dir();
+    let a = dir.join("a.txt");
+    let b = dir.join("b.txt");
+    std::fs::write(&a, "1\n2\n").unwrap();
+    std::fs::write(&b, "1\n").unwrap();
+
+    let mut db = Database::default();
+    let file_a = source_file(&db, &a);
+    let file_b = source_file(&db, &b);
+    assert_eq!(source_file(&db, &a), file_a);
+    assert_eq!(line_count(&db, file_a), Some(2));
+    assert_eq!(line_count(&db, file_b), Some(1));
+    db.assert_logs(expect![[r#"
+        [
+            "line_count(a.txt)",
+            "line_count(b.txt)",
+        ]"#]]);
+
+    // Only the changed file is re-read, and events for unknown files are ignored.
+    std::fs::write(&a, "1\n2\n3\n").unwrap();
+    db.apply_fs_changes([modified(&a), modified(&b), modified(&dir.join("c.txt"))]);
+    assert_eq!(line_count(&db, file_a), Some(3));
+    assert_eq!(line_count(&db, file_b), Some(1));
+    db.assert_logs(expect![[r#"
+        [
+            "line_count(a.txt)",
+        ]"#]]);
+
+    // Removed files have no contents.
+    std::fs::remove_file(&b).unwrap();
+    db.apply_fs_changes([Event::new(EventKind::Remove(RemoveKind::File)).add_path(b.clone())]);
+    assert_eq!(line_count(&db, file_b), None);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,110 @@
+//! Test the `all` and `len` functions generated for inputs: a query
+//! reading them depends on the set of inputs.
+
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(File, Config, symbol_index, file_count);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::input]
+struct File {
+    name: String,
+}
+
+#[salsa::input]
+struct Config {
+    verbose: bool,
+}
+
+#[salsa::tracked]
+fn symbol_index(db: &dyn Db) -> Vec<String> {
+    db.push_log("symbol_index".to_string());
+    File::all(db)
+        .into_iter()
+        .map(|file| file.name(db))
+        .collect()
+}
+
+#[salsa::tracked]
+fn file_count(db: &dyn Db) -> usize {
+    db.push_log("file_count".to_string());
+    File::len(db)
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn depends_on_the_set_of_inputs() {
+    let mut db = Database::default();
+    let config = Config::new(&db, false);
+    File::new(&db, "a".to_string());
+    File::new(&db, "b".to_string());
+    config.set_verbose(&mut db).to(true);
+    assert_eq!(symbol_index(&db), vec!["a", "b"]);
+    assert_eq!(file_count(&db), 2);
+    db.assert_logs(expect![[r#"
+        [
+            "symbol_index",
+            "file_count",
+        ]"#]]);
+
+    // No input was created in the new revision.
+    config.set_verbose(&mut db).to(false);
+    assert_eq!(symbol_index(&db), vec!["a", "b"]);
+    assert_eq!(file_count(&db), 2);
+    db.assert_logs(expect!["[]"]);
+
+    // The new input is seen in the next revision.
+    File::new(&db, "c".to_string());
+    config.set_verbose(&mut db).to(true);
+    assert_eq!(symbol_index(&db), vec!["a", "b", "c"]);
+    assert_eq!(file_count(&db), 3);
+    db.assert_logs(expect![[r#"
+        [
+            "symbol_index",
+            "file_count",
+        ]"#]]);
+}
+
+#[test]
+fn depends_on_the_inputs_read() {
+    let mut db = Database::default();
+    let a = File::new(&db, "a".to_string());
+    File::new(&db, "b".to_string());
+    Config::new(&db, false).set_verbose(&mut db).to(true);
+    assert_eq!(symbol_index(&db), vec!["a", "b"]);
+    assert_eq!(file_count(&db), 2);
+    db.assert_logs(expect![[r#"
+        [
+            "symbol_index",
+            "file_count",
+        ]"#]]);
+
+    a.set_name(&mut db).to("d".to_string());
+    assert_eq!(symbol_index(&db), vec!["d", "b"]);
+    assert_eq!(file_count(&db), 2);
+    db.assert_logs(expect![[r#"
+        [
+            "symbol_index",
+        ]"#]]);
+}
@@ -0,0 +1,77 @@
+//! Test that inputs can be created with a builder, where fields
+//! marked `#[default]` are optional and all fields share a durability.
+
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(FileInput, Config, text_len);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input(jar = Jar)]
+struct FileInput {
+    #[return_ref]
+    path: String,
+
+    #[default]
+    #[return_ref]
+    text: String,
+
+    #[default]
+    version: u32,
+}
+
+#[salsa::input(jar = Jar, singleton)]
+struct Config {
+    #[default]
+    verbose: bool,
+}
+
+#[salsa::tracked(jar = Jar)]
+fn text_len(db: &dyn Db, input: FileInput) -> usize {
+    input.text(db).len()
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+#[test]
+fn defaults() {
+    let db = Database::default();
+    let file = FileInput::builder("a.txt".to_string()).new(&db);
+    assert_eq!(file.path(&db), "a.txt");
+    assert_eq!(file.text(&db), "");
+    assert_eq!(file.version(&db), 0);
+}
+
+#[test]
+fn fields_and_durability() {
+    let mut db = Database::default();
+    let file = FileInput::builder("a.txt".to_string())
+        .text("hello".to_string())
+        .durability(salsa::Durability::HIGH)
+        .new(&db);
+    assert_eq!(file.path(&db), "a.txt");
+    assert_eq!(file.text(&db), "hello");
+    assert_eq!(file.version(&db), 0);
+    assert_eq!(text_len(&db, file), 5);
+
+    // Changes to fields created with a high durability are still observed.
+    file.set_text(&mut db).to("hello world".to_string());
+    assert_eq!(text_len(&db, file), 11);
+}
+
+#[test]
+fn singleton() {
+    let db = Database::default();
+    let config = Config::builder().verbose(true).new(&db);
+    assert_eq!(Config::get(&db), config);
+    assert!(config.verbose(&db));
+}
@@ -0,0 +1,98 @@
+//! Test that setting inputs with a durability invalidates
+//! the memoized values that depend on them, while values that
+//! only depend on higher-durability inputs skip deep verification.
+
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, tracked_fn);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::input(jar = Jar)]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::tracked(jar = Jar)]
+fn tracked_fn(db: &dyn Db, input: MyInput) -> u32 {
+    db.push_log(format!("tracked_fn({})", input.field(db)));
+    input.field(db) * 2
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn set_high_durability_input() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 22);
+    input
+        .set_field(&mut db)
+        .with_durability(salsa::Durability::HIGH)
+        .to(22);
+    assert_eq!(tracked_fn(&db, input), 44);
+    db.assert_logs(expect![[r#"
+        [
+            "tracked_fn(22)",
+        ]"#]]);
+
+    input
+        .set_field(&mut db)
+        .with_durability(salsa::Durability::HIGH)
+        .to(23);
+    assert_eq!(tracked_fn(&db, input), 46);
+    db.assert_logs(expect![[r#"
+        [
+            "tracked_fn(23)",
+        ]"#]]);
+}
+
+#[test]
+fn lower_durability_of_input() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 22);
+    input
+        .set_field(&mut db)
+        .with_durability(salsa::Durability::HIGH)
+        .to(22);
+    assert_eq!(tracked_fn(&db, input), 44);
+    db.assert_logs(expect![[r#"
+        [
+            "tracked_fn(22)",
+        ]"#]]);
+
+    // Lowering the durability must still invalidate queries
+    // that observed the high-durability value...
+    input.set_field(&mut db).to(23);
+    assert_eq!(tracked_fn(&db, input), 46);
+    db.assert_logs(expect![[r#"
+        [
+            "tracked_fn(23)",
+        ]"#]]);
+
+    // ...and later low-durability writes are seen as usual.
+    input.set_field(&mut db).to(24);
+    assert_eq!(tracked_fn(&db, input), 48);
+    db.assert_logs(expect![[r#"
+        [
+            "tracked_fn(24)",
+        ]"#]]);
+}
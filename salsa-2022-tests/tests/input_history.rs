@@ -0,0 +1,104 @@
+//! Test reverting inputs with the `history` option to older revisions
+//! with `Database::revert_to`.
+
+use salsa::Database as _;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(File, Settings, length);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input(history = 3)]
+struct File {
+    text: String,
+    version: u32,
+}
+
+/// An input without the `history` option, which is never reverted.
+#[salsa::input]
+struct Settings {
+    tab_width: u32,
+}
+
+#[salsa::tracked]
+fn length(db: &dyn Db, file: File) -> usize {
+    file.text(db).len()
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+#[test]
+fn revert() {
+    let mut db = Database::default();
+    let file = File::new(&db, "a".to_string(), 1);
+    let settings = Settings::new(&db, 4);
+    let start = db.attach_revision().revision();
+
+    file.set_text(&mut db).to("abc".to_string());
+    file.set_version(&mut db).to(2);
+    settings.set_tab_width(&mut db).to(8);
+    assert_eq!(length(&db, file), 3);
+    let before_revert = db.attach_revision().revision();
+
+    db.revert_to(start).unwrap();
+    assert!(db.attach_revision().revision() > before_revert);
+    assert_eq!(file.text(&db), "a");
+    assert_eq!(file.version(&db), 1);
+    assert_eq!(length(&db, file), 1);
+    assert_eq!(settings.tab_width(&db), 8);
+
+    // The revert is itself a change, which can be reverted.
+    db.revert_to(before_revert).unwrap();
+    assert_eq!(file.text(&db), "abc");
+    assert_eq!(file.version(&db), 2);
+    assert_eq!(length(&db, file), 3);
+}
+
+#[test]
+fn inputs_created_later() {
+    let mut db = Database::default();
+    let start = db.attach_revision().revision();
+    db.synthetic_write(salsa::Durability::LOW);
+
+    let file = File::new(&db, "a".to_string(), 1);
+    file.set_text(&mut db).to("ab".to_string());
+    // The input can't be deleted: it gets the values it was created with.
+    db.revert_to(start).unwrap();
+    assert_eq!(file.text(&db), "a");
+}
+
+#[test]
+fn history_is_bounded() {
+    let mut db = Database::default();
+    let file = File::new(&db, "".to_string(), 0);
+    let mut revisions = vec![db.attach_revision().revision()];
+    for version in 1..=5 {
+        file.set_version(&mut db).to(version);
+        revisions.push(db.attach_revision().revision());
+    }
+
+    // Only the changes of the last 3 revisions are kept.
+    let error = db.revert_to(revisions[1]).unwrap_err();
+    assert_eq!(error.revision, revisions[1]);
+    assert_eq!(error.oldest, revisions[2]);
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "cannot revert to {:?}: the history of the inputs only goes back to {:?}",
+            revisions[1], revisions[2]
+        )
+    );
+    assert_eq!(file.version(&db), 5);
+
+    db.revert_to(revisions[2]).unwrap();
+    assert_eq!(file.version(&db), 2);
+}
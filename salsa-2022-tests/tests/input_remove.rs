@@ -0,0 +1,179 @@
+//! Test removing inputs with the `remove` and `retain` functions generated
+//! for them.
+
+use salsa::DebugWithDb;
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(File, length, total_length, dense_length);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::input]
+struct File {
+    text: String,
+}
+
+#[salsa::tracked]
+fn length(db: &dyn Db, file: File) -> usize {
+    db.push_log(format!("length({})", file.text(db)));
+    file.text(db).len()
+}
+
+#[salsa::tracked]
+fn total_length(db: &dyn Db) -> usize {
+    db.push_log("total_length".to_string());
+    File::all(db).into_iter().map(|file| length(db, file)).sum()
+}
+
+#[salsa::tracked(storage = dense)]
+fn dense_length(db: &dyn Db, file: File) -> usize {
+    db.push_log(format!("dense_length({})", file.text(db)));
+    file.text(db).len()
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl salsa::Database for Database {
+    fn salsa_event(&self, event: salsa::Event) {
+        if let salsa::EventKind::DidDiscard { .. } = event.kind {
+            self.push_log(format!("salsa_event({:?})", event.kind.debug(self)));
+        }
+    }
+}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn remove_input() {
+    let mut db = Database::default();
+    let a = File::new(&db, "a".to_string());
+    let b = File::new(&db, "bb".to_string());
+    let c = File::new(&db, "ccc".to_string());
+    a.set_text(&mut db).to("a".to_string());
+    assert_eq!(total_length(&db), 6);
+    db.assert_logs(expect![[r#"
+        [
+            "total_length",
+            "length(a)",
+            "length(bb)",
+            "length(ccc)",
+        ]"#]]);
+
+    b.remove(&mut db);
+    assert_eq!(File::all(&db), vec![a, c]);
+    assert_eq!(total_length(&db), 4);
+    db.assert_logs(expect![[r#"
+        [
+            "salsa_event(DidDiscard { key: File(1) })",
+            "salsa_event(DidDiscard { key: length(1) })",
+            "total_length",
+        ]"#]]);
+
+    // The slot of the removed input is reused, with a new id.
+    let d = File::new(&db, "dddd".to_string());
+    assert_ne!(d, b);
+    assert_eq!(File::all(&db), vec![a, d, c]);
+    c.set_text(&mut db).to("cc".to_string());
+    assert_eq!(total_length(&db), 7);
+    db.assert_logs(expect![[r#"
+        [
+            "total_length",
+            "length(dddd)",
+            "length(cc)",
+        ]"#]]);
+}
+
+#[test]
+fn reuse_slot_with_dense_storage() {
+    let mut db = Database::default();
+    let a = File::new(&db, "a".to_string());
+    let b = File::new(&db, "bb".to_string());
+    assert_eq!(dense_length(&db, a), 1);
+    assert_eq!(dense_length(&db, b), 2);
+
+    // `c` reuses the slot of `a`, with the next generation of its id.
+    a.remove(&mut db);
+    let c = File::new(&db, "ccc".to_string());
+    assert_eq!(
+        salsa::AsId::as_id(c).as_u32(),
+        salsa::AsId::as_id(a).as_u32() + (1 << 24)
+    );
+    assert_eq!(dense_length(&db, c), 3);
+    assert_eq!(dense_length(&db, b), 2);
+    db.assert_logs(expect![[r#"
+        [
+            "dense_length(a)",
+            "dense_length(bb)",
+            "salsa_event(DidDiscard { key: File(0) })",
+            "salsa_event(DidDiscard { key: dense_length(0) })",
+            "dense_length(ccc)",
+        ]"#]]);
+}
+
+#[test]
+fn retain_inputs() {
+    let mut db = Database::default();
+    let files: Vec<File> = ["a", "bb", "ccc", "dddd"]
+        .into_iter()
+        .map(|text| File::new(&db, text.to_string()))
+        .collect();
+    files[0].set_text(&mut db).to("a".to_string());
+    assert_eq!(total_length(&db), 10);
+    db.assert_logs_len(5);
+
+    File::retain(&mut db, |db, file| file.text(db).len() % 2 == 0);
+    assert_eq!(File::len(&db), 2);
+    assert_eq!(total_length(&db), 6);
+    db.assert_logs(expect![[r#"
+        [
+            "salsa_event(DidDiscard { key: File(0) })",
+            "salsa_event(DidDiscard { key: length(0) })",
+            "salsa_event(DidDiscard { key: File(2) })",
+            "salsa_event(DidDiscard { key: length(2) })",
+            "total_length",
+        ]"#]]);
+}
+
+#[test]
+#[should_panic(expected = "cannot access field `text` of input Id { value: 1 }: it was removed")]
+fn read_removed_input() {
+    let mut db = Database::default();
+    let a = File::new(&db, "a".to_string());
+    a.remove(&mut db);
+    a.text(&db);
+}
+
+#[test]
+#[should_panic(expected = "cannot access field `text` of input")]
+fn read_stale_id() {
+    let mut db = Database::default();
+    let a = File::new(&db, "a".to_string());
+    a.remove(&mut db);
+    let b = File::new(&db, "b".to_string());
+    assert_eq!(b.text(&db), "b");
+    a.text(&db);
+}
+
+#[test]
+#[should_panic(expected = "`File(Id { value: 1 })` was already removed")]
+fn remove_twice() {
+    let mut db = Database::default();
+    let a = File::new(&db, "a".to_string());
+    a.remove(&mut db);
+    a.remove(&mut db);
+}
@@ -0,0 +1,111 @@
+//! Test the `gc` option of interned structs:
+//!
+//! * `Storage::sweep` reclaims the values that were not used in the revisions it keeps;
+//! * their ids are reused with a new generation, and reading a stale id panics;
+//! * a query that returned a reclaimed value is executed again.
+
+use salsa::AsId;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(File, Name, name_of, name_len);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input]
+struct File {
+    text: String,
+}
+
+#[salsa::interned(gc)]
+struct Name {
+    #[return_ref]
+    text: String,
+}
+
+#[salsa::tracked]
+fn name_of(db: &dyn Db, file: File) -> Name {
+    Name::new(db, file.text(db))
+}
+
+#[salsa::tracked]
+fn name_len(db: &dyn Db, file: File) -> usize {
+    Name::new(db, format!("tmp-{}", file.text(db)))
+        .text(db)
+        .len()
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl Database {
+    fn sweep(&mut self, max_age: usize) {
+        self.storage.sweep(max_age);
+    }
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+#[test]
+fn reclaim_unused_values() {
+    let mut db = Database::default();
+    let file = File::new(&db, "a".to_string());
+
+    assert_eq!(name_len(&db, file), 5);
+    let tmp_a = Name::lookup(&db, "tmp-a".to_string()).unwrap();
+
+    // `tmp-a` is no longer used once `name_len` is executed again.
+    file.set_text(&mut db).to("bb".to_string());
+    assert_eq!(name_len(&db, file), 6);
+    db.sweep(0);
+    assert_eq!(Name::lookup(&db, "tmp-a".to_string()), None);
+    assert!(Name::lookup(&db, "tmp-bb".to_string()).is_some());
+
+    // The slot of `tmp-a` is reused with the next generation.
+    let tmp_c = Name::new(&db, "tmp-c".to_string());
+    assert_ne!(tmp_c, tmp_a);
+    assert_eq!(tmp_c.as_id().as_u32(), tmp_a.as_id().as_u32() + (1 << 24));
+}
+
+#[test]
+fn execute_again_after_reclaiming_result() {
+    let mut db = Database::default();
+    let file = File::new(&db, "a".to_string());
+    let other = File::new(&db, "x".to_string());
+
+    let name = name_of(&db, file);
+    assert_eq!(name.text(&db), "a");
+
+    // `name_of(file)` is not used for two revisions, so its result is reclaimed.
+    other.set_text(&mut db).to("y".to_string());
+    other.set_text(&mut db).to("z".to_string());
+    db.sweep(0);
+    assert_eq!(Name::lookup(&db, "a".to_string()), None);
+
+    // Its memo depends on the reclaimed value, so it is executed again.
+    let name = name_of(&db, file);
+    assert_eq!(name.text(&db), "a");
+}
+
+#[test]
+#[should_panic(
+    expected = "`Name(0)` is stale: the entity was recreated in revision 3, \
+                           with the next generation of its id"
+)]
+fn read_stale_id() {
+    let mut db = Database::default();
+    let file = File::new(&db, "a".to_string());
+    assert_eq!(name_len(&db, file), 5);
+    let tmp_a = Name::lookup(&db, "tmp-a".to_string()).unwrap();
+
+    file.set_text(&mut db).to("bb".to_string());
+    assert_eq!(name_len(&db, file), 6);
+    db.sweep(0);
+    Name::new(&db, "tmp-c".to_string());
+    tmp_a.text(&db);
+}
@@ -0,0 +1,66 @@
+//! Test interned enums: the constructors of the variants intern equal
+//! values once, so the values sharing a part share its id, and `data`
+//! and `debug` give back the structure of a value.
+
+use expect_test::expect;
+use salsa::DebugWithDb;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(Ty, size_of);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+/// The types of a small language.
+#[salsa::interned(jar = Jar)]
+enum Ty {
+    Int,
+    Named(String),
+    Tuple(Vec<Ty>),
+    Fn { param: Ty, ret: Ty },
+}
+
+#[salsa::tracked(jar = Jar)]
+fn size_of(db: &dyn Db, ty: Ty) -> usize {
+    match ty.data(db) {
+        TyData::Int | TyData::Named(_) | TyData::Fn { .. } => 8,
+        TyData::Tuple(tys) => tys.into_iter().map(|ty| size_of(db, ty)).sum(),
+    }
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+#[test]
+fn structural_sharing() {
+    let db = Database::default();
+    let int = Ty::int(&db);
+    let pair = Ty::tuple(&db, vec![int, Ty::named(&db, "T".to_string())]);
+    let func = Ty::r#fn(&db, pair, int);
+
+    // Equal values are interned once.
+    assert_eq!(Ty::int(&db), int);
+    assert_eq!(
+        Ty::new(
+            &db,
+            TyData::Fn {
+                param: pair,
+                ret: int
+            }
+        ),
+        func
+    );
+    assert_eq!(Ty::lookup(&db, TyData::Tuple(vec![int, int])), None);
+
+    assert!(matches!(func.data(&db), TyData::Fn { param, ret } if param == pair && ret == int));
+    assert_eq!(size_of(&db, pair), 16);
+    expect![[r#"Fn { param: Tuple([Int, Named("T")]), ret: Int }"#]]
+        .assert_eq(&format!("{:?}", func.debug(&db)));
+}
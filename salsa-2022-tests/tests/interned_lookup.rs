@@ -0,0 +1,76 @@
+//! Test that `lookup` on an interned struct finds values that were
+//! already interned without interning new ones, and that a query that
+//! found nothing is executed again once the value is interned.
+
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(Symbol, Query, Unrelated, find_symbol);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::interned(jar = Jar)]
+struct Symbol {
+    #[return_ref]
+    text: String,
+    namespace: u32,
+}
+
+#[salsa::input(jar = Jar)]
+struct Query {
+    text: String,
+}
+
+#[salsa::input(jar = Jar)]
+struct Unrelated {
+    value: u32,
+}
+
+#[salsa::tracked(jar = Jar)]
+fn find_symbol(db: &dyn Db, query: Query) -> Option<Symbol> {
+    Symbol::lookup(db, query.text(db), 0)
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+#[test]
+fn lookup() {
+    let db = Database::default();
+
+    assert_eq!(Symbol::lookup(&db, "foo".to_string(), 0), None);
+
+    let foo = Symbol::new(&db, "foo".to_string(), 0);
+    assert_eq!(Symbol::lookup(&db, "foo".to_string(), 0), Some(foo));
+    assert_eq!(Symbol::lookup(&db, "foo".to_string(), 1), None);
+    assert_eq!(Symbol::lookup(&db, "bar".to_string(), 0), None);
+
+    // Looking a value up does not intern it: the next id handed out
+    // is the one right after `foo`.
+    let bar = Symbol::new(&db, "bar".to_string(), 0);
+    assert_eq!(
+        salsa::AsId::as_id(bar).as_u32(),
+        salsa::AsId::as_id(foo).as_u32() + 1
+    );
+    assert_eq!(bar.text(&db), "bar");
+}
+
+#[test]
+fn lookup_in_query() {
+    let mut db = Database::default();
+    let query = Query::new(&db, "foo".to_string());
+    let unrelated = Unrelated::new(&db, 0);
+
+    assert_eq!(find_symbol(&db, query), None);
+
+    let foo = Symbol::new(&db, "foo".to_string(), 0);
+    unrelated.set_value(&mut db).to(1);
+    assert_eq!(find_symbol(&db, query), Some(foo));
+}
@@ -0,0 +1,93 @@
+//! Test that `invalidate` forces a tracked function to be executed again
+//! without changing any input, and that the functions depending on it are
+//! executed again only if its value changed.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use expect_test::expect;
+use salsa_2022_tests::{HasLogger, Logger};
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, external, double);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger + HasExternal {}
+
+trait HasExternal {
+    /// State outside of salsa, like the outputs of a build tool.
+    fn external_state(&self) -> u32;
+}
+
+#[salsa::input]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::tracked]
+fn external(db: &dyn Db, input: MyInput) -> u32 {
+    db.push_log(format!("external({})", input.field(db)));
+    input.field(db) + db.external_state()
+}
+
+#[salsa::tracked]
+fn double(db: &dyn Db, input: MyInput) -> u32 {
+    db.push_log(format!("double({})", input.field(db)));
+    external(db, input) * 2
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+    external_state: AtomicU32,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+impl HasExternal for Database {
+    fn external_state(&self) -> u32 {
+        self.external_state.load(Ordering::SeqCst)
+    }
+}
+
+#[test]
+fn invalidate() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 1);
+    assert_eq!(double(&db, input), 2);
+    db.assert_logs(expect![[r#"
+        [
+            "double(1)",
+            "external(1)",
+        ]"#]]);
+
+    // The change is not seen until `external` is invalidated.
+    db.external_state.store(10, Ordering::SeqCst);
+    assert_eq!(double(&db, input), 2);
+    db.assert_logs(expect!["[]"]);
+
+    external::invalidate(&mut db, input);
+    assert_eq!(double(&db, input), 22);
+    db.assert_logs(expect![[r#"
+        [
+            "external(1)",
+            "double(1)",
+        ]"#]]);
+
+    // `external` returns the same value, so `double` is not executed again.
+    external::invalidate(&mut db, input);
+    assert_eq!(double(&db, input), 22);
+    db.assert_logs(expect![[r#"
+        [
+            "external(1)",
+        ]"#]]);
+}
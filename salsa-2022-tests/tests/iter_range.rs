@@ -0,0 +1,111 @@
+//! Test the `iter_range` function generated for tracked functions: it returns
+//! the memoized values whose arguments are in a range, sorted by arguments.
+
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(File, diagnostic, line_count);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::input]
+struct File {
+    text: String,
+}
+
+#[salsa::tracked]
+fn diagnostic(db: &dyn Db, file: File, offset: u32) -> Option<String> {
+    db.push_log(format!("diagnostic({offset})"));
+    let byte = file.text(db).as_bytes()[offset as usize];
+    (byte == b'!').then(|| format!("unexpected `!` at {offset}"))
+}
+
+#[salsa::tracked]
+fn line_count(db: &dyn Db, file: File) -> usize {
+    file.text(db).lines().count()
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn iterate_over_region() {
+    let mut db = Database::default();
+    let file = File::new(&db, "a!b!c!".to_string());
+    let other = File::new(&db, "!!!!!!".to_string());
+    for offset in [5, 0, 3, 1] {
+        diagnostic(&db, file, offset);
+    }
+    diagnostic(&db, other, 2);
+    db.assert_logs(expect![[r#"
+        [
+            "diagnostic(5)",
+            "diagnostic(0)",
+            "diagnostic(3)",
+            "diagnostic(1)",
+            "diagnostic(2)",
+        ]"#]]);
+
+    // Only the memoized offsets are returned, and no function is executed.
+    let region: Vec<_> = diagnostic::iter_range(&db, (file, 1)..(file, 5))
+        .into_iter()
+        .map(|((_, offset), value)| (offset, value.clone()))
+        .collect();
+    assert_eq!(
+        region,
+        vec![
+            (1, Some("unexpected `!` at 1".to_string())),
+            (3, Some("unexpected `!` at 3".to_string())),
+        ]
+    );
+    assert_eq!(diagnostic::iter_range(&db, (file, 0)..=(file, 5)).len(), 4);
+    assert_eq!(diagnostic::iter_range(&db, (other, 0)..).len(), 1);
+    db.assert_logs(expect!["[]"]);
+
+    // Stale values are verified again when iterated.
+    file.set_text(&mut db).to("a!b?c!".to_string());
+    let region: Vec<_> = diagnostic::iter_range(&db, (file, 3)..=(file, 3))
+        .into_iter()
+        .map(|(_, value)| value.clone())
+        .collect();
+    assert_eq!(region, vec![None]);
+    db.assert_logs(expect![[r#"
+        [
+            "diagnostic(3)",
+        ]"#]]);
+}
+
+#[test]
+fn iterate_over_salsa_structs() {
+    let db = Database::default();
+    let files: Vec<File> = ["a", "a\nb", "a\nb\nc"]
+        .into_iter()
+        .map(|text| File::new(&db, text.to_string()))
+        .collect();
+    line_count(&db, files[2]);
+    line_count(&db, files[0]);
+
+    let counts: Vec<usize> = line_count::iter_range(&db, ..)
+        .into_iter()
+        .map(|(_, count)| *count)
+        .collect();
+    assert_eq!(counts, vec![1, 3]);
+    assert!(line_count::iter_range(&db, files[1]..files[2]).is_empty());
+}
@@ -0,0 +1,137 @@
+//! Test that a jar can include other jars, so that a database only
+//! has to list the outermost jar. `base` is included by both `left`
+//! and `right`, which are in turn included by `app`.
+
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+mod base {
+    #[salsa::jar(db = Db)]
+    pub struct Jar(Number, double);
+
+    pub trait Db: salsa::DbWithJar<Jar> + salsa_2022_tests::HasLogger {}
+
+    #[salsa::input(jar = Jar)]
+    pub struct Number {
+        pub value: u32,
+    }
+
+    #[salsa::tracked(jar = Jar)]
+    pub fn double(db: &dyn Db, number: Number) -> u32 {
+        db.push_log(format!("double({})", number.value(db)));
+        number.value(db) * 2
+    }
+}
+
+mod left {
+    #[salsa::jar(db = Db, includes(crate::base::Jar))]
+    pub struct Jar(plus_one);
+
+    pub trait Db: salsa::DbWithJar<Jar> + crate::base::Db {}
+
+    #[salsa::tracked(jar = Jar)]
+    pub fn plus_one(db: &dyn Db, number: crate::base::Number) -> u32 {
+        crate::base::double(db, number) + 1
+    }
+}
+
+mod right {
+    #[salsa::jar(db = Db, includes(crate::base::Jar))]
+    pub struct Jar(plus_two);
+
+    pub trait Db: salsa::DbWithJar<Jar> + crate::base::Db {}
+
+    #[salsa::tracked(jar = Jar)]
+    pub fn plus_two(db: &dyn Db, number: crate::base::Number) -> u32 {
+        crate::base::double(db, number) + 2
+    }
+}
+
+mod app {
+    #[salsa::jar(db = Db, includes(crate::left::Jar, crate::right::Jar))]
+    pub struct Jar(sum);
+
+    pub trait Db: salsa::DbWithJar<Jar> + crate::left::Db + crate::right::Db {}
+
+    #[salsa::tracked(jar = Jar)]
+    pub fn sum(db: &dyn Db, number: crate::base::Number) -> u32 {
+        crate::left::plus_one(db, number) + crate::right::plus_two(db, number)
+    }
+}
+
+#[salsa::db(app::Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl salsa::Database for Database {}
+
+impl base::Db for Database {}
+
+impl left::Db for Database {}
+
+impl right::Db for Database {}
+
+impl app::Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn execute() {
+    let mut db = Database::default();
+    let number = base::Number::new(&db, 10);
+
+    assert_eq!(app::sum(&db, number), 21 + 22);
+
+    // `double` is shared by `left` and `right` and only executed once.
+    db.assert_logs(expect![[r#"
+        [
+            "double(10)",
+        ]"#]]);
+
+    number.set_value(&mut db).to(20);
+    assert_eq!(app::sum(&db, number), 41 + 42);
+    db.assert_logs(expect![[r#"
+        [
+            "double(20)",
+        ]"#]]);
+}
+
+#[test]
+fn included_jar_listed_again() {
+    #[salsa::db(base::Jar, left::Jar)]
+    #[derive(Default)]
+    struct Database {
+        storage: salsa::Storage<Self>,
+        logger: Logger,
+    }
+
+    impl salsa::Database for Database {}
+
+    impl base::Db for Database {}
+
+    impl left::Db for Database {}
+
+    impl HasLogger for Database {
+        fn logger(&self) -> &Logger {
+            &self.logger
+        }
+    }
+
+    let mut db = Database::default();
+    let number = base::Number::new(&db, 10);
+    assert_eq!(left::plus_one(&db, number), 21);
+    assert_eq!(base::double(&db, number), 20);
+    db.assert_logs(expect![[r#"
+        [
+            "double(10)",
+        ]"#]]);
+}
@@ -0,0 +1,62 @@
+//! Test `Database::current_revision` and the `last_changed` function of
+//! tracked functions: the revision in which a value last changed stays the
+//! same when the value is backdated, and is later than `Revision::ZERO`.
+
+use salsa::{Database as _, Revision};
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, is_small, scaled);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input(jar = Jar)]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::tracked(jar = Jar)]
+fn is_small(db: &dyn Db, input: MyInput) -> bool {
+    input.field(db) < 10
+}
+
+#[salsa::tracked(jar = Jar)]
+fn scaled(db: &dyn Db, input: MyInput, factor: u32) -> u32 {
+    input.field(db) * factor
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+#[test]
+fn execute() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 2);
+    let first = db.current_revision();
+    assert!(Revision::ZERO < first);
+    assert_eq!(first.as_usize(), 1);
+
+    // Nothing is memoized yet.
+    assert_eq!(is_small::last_changed(&db, input), None);
+    assert!(is_small(&db, input));
+    assert_eq!(scaled(&db, input, 3), 6);
+    assert_eq!(is_small::last_changed(&db, input), Some(first));
+    assert_eq!(scaled::last_changed(&db, input, 3), Some(first));
+    assert_eq!(scaled::last_changed(&db, input, 4), None);
+
+    // `is_small` is backdated, `scaled` is not.
+    input.set_field(&mut db).to(4);
+    let second = db.current_revision();
+    assert_eq!(second, first.next());
+    assert!(is_small(&db, input));
+    assert_eq!(scaled(&db, input, 3), 12);
+    assert_eq!(is_small::last_changed(&db, input), Some(first));
+    assert_eq!(scaled::last_changed(&db, input, 3), Some(second));
+}
@@ -0,0 +1,72 @@
+//! Test `Storage::set_leak_check`: a tracked function called with new keys
+//! in every revision is reported by `Storage::leak_report`, while one called
+//! with the same keys is not. Only built in
+//! debug builds, like the check.
+#![cfg(debug_assertions)]
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, length, char_at);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input]
+struct MyInput {
+    text: String,
+}
+
+#[salsa::tracked]
+fn length(db: &dyn Db, input: MyInput) -> usize {
+    input.text(db).len()
+}
+
+#[salsa::tracked]
+fn char_at(db: &dyn Db, input: MyInput, position: usize) -> Option<char> {
+    input.text(db).chars().nth(position)
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+#[test]
+fn execute() {
+    let mut db = Database::default();
+    db.storage.set_leak_check(Some(3));
+    let input = MyInput::new(&db, String::new());
+
+    // Each edit appends a character, which is looked up at its own position.
+    for (position, c) in "salsa".chars().enumerate() {
+        let mut text = input.text(&db);
+        text.push(c);
+        input.set_text(&mut db).to(text);
+        assert_eq!(length(&db, input), position + 1);
+        assert_eq!(char_at(&db, input, position), Some(c));
+        if position == 1 {
+            // The memos only grew in two revisions so far.
+            assert!(db.storage.leak_report().is_empty());
+        }
+    }
+
+    let leaks: Vec<String> = db
+        .storage
+        .leak_report()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    expect![[r#"
+        [
+            "leak_check::Jar::char_at: grew from 0 to 5 interned values over 5 revisions",
+            "leak_check::Jar::char_at: grew from 0 to 5 memos over 5 revisions",
+        ]
+    "#]]
+    .assert_debug_eq(&leaks);
+}
@@ -0,0 +1,93 @@
+//! Test `salsa::List` and the `elements` option of tracked functions: when an
+//! element is appended to a list, only the functions reading the new element
+//! through the generated function are executed again.
+
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(File, lines, line, line_length, total_length);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::input]
+struct File {
+    text: String,
+}
+
+#[salsa::tracked(return_ref, update, elements = line)]
+fn lines(db: &dyn Db, file: File) -> salsa::List<String> {
+    file.text(db).lines().map(str::to_string).collect()
+}
+
+#[salsa::tracked]
+fn line_length(db: &dyn Db, file: File, index: usize) -> usize {
+    db.push_log(format!("line_length({index})"));
+    line(db, file, index).map_or(0, |line| line.len())
+}
+
+#[salsa::tracked]
+fn total_length(db: &dyn Db, file: File) -> usize {
+    (0..lines(db, file).len())
+        .map(|index| line_length(db, file, index))
+        .sum()
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn execute() {
+    let mut db = Database::default();
+    let file = File::new(&db, "a\nbb".to_string());
+
+    assert_eq!(total_length(&db, file), 3);
+    db.assert_logs(expect![[r#"
+        [
+            "line_length(0)",
+            "line_length(1)",
+        ]"#]]);
+
+    file.set_text(&mut db).to("a\nbb\nccc".to_string());
+    assert_eq!(total_length(&db, file), 6);
+    db.assert_logs(expect![[r#"
+        [
+            "line_length(2)",
+        ]"#]]);
+
+    file.set_text(&mut db).to("a\nBB\nccc".to_string());
+    assert_eq!(total_length(&db, file), 6);
+    db.assert_logs(expect![[r#"
+        [
+            "line_length(1)",
+        ]"#]]);
+
+    assert_eq!(line(&db, file, 1).as_deref(), Some("BB"));
+    assert_eq!(line(&db, file, 3), None);
+}
+
+#[test]
+fn list() {
+    let list: salsa::List<u32> = (1..=3).collect();
+    assert_eq!(list.len(), 3);
+    assert_eq!(list, salsa::List::from(vec![1, 2, 3]));
+    assert_eq!(list.clone(), list);
+    assert_eq!(format!("{list:?}"), "[1, 2, 3]");
+    assert_eq!((&list).into_iter().sum::<u32>(), 6);
+}
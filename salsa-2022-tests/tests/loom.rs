@@ -0,0 +1,67 @@
+//! Explore, with `loom`, the orders in which a write and a snapshot take the
+//! runtime's locks and wait on its condition variables, as a write cancels the
+//! snapshot and waits for it to be dropped. The atomics and concurrent maps of
+//! the runtime are not instrumented by `loom`, so this does not model-check
+//! their interleavings. Only built with `--cfg loom`:
+//!
+//! ```text
+//! RUSTFLAGS="--cfg loom" cargo test --release --test loom
+//! ```
+#![cfg(loom)]
+
+use std::panic::AssertUnwindSafe;
+
+use salsa::{Cancelled, ParallelDatabase};
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, double);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::tracked]
+fn double(db: &dyn Db, input: MyInput) -> u32 {
+    input.field(db) * 2
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl ParallelDatabase for Database {
+    fn snapshot(&self) -> salsa::Snapshot<Self> {
+        salsa::Snapshot::new(Database {
+            storage: self.storage.snapshot(),
+        })
+    }
+}
+
+#[test]
+fn write_waits_for_snapshot() {
+    loom::model(|| {
+        let mut db = Database::default();
+        let input = MyInput::new(&db, 1);
+
+        let snapshot = db.snapshot();
+        let reader = loom::thread::spawn(move || {
+            // The read either completes in the old revision or is cancelled by the write.
+            if let Ok(value) = Cancelled::catch(AssertUnwindSafe(|| double(&*snapshot, input))) {
+                assert_eq!(value, 2);
+            }
+        });
+
+        input.set_field(&mut db).to(2);
+        assert_eq!(double(&db, input), 4);
+        reader.join().unwrap();
+    });
+}
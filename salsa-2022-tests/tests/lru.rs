@@ -185,3 +185,62 @@ fn lru_keeps_dependency_info() {
     assert_eq!(p, 0);
     db.assert_logs_len(0);
 }
+
+#[test]
+fn lru_can_be_shrunk_at_runtime() {
+    let db = DatabaseImpl::default();
+    assert_eq!(load_n_potatoes(), 0);
+
+    let inputs: Vec<(u32, MyInput)> = (0..32).map(|i| (i, MyInput::new(&db, i))).collect();
+
+    for &(i, input) in inputs.iter() {
+        let p = get_hot_potato(&db, input);
+        assert_eq!(p.0, i)
+    }
+    assert_eq!(load_n_potatoes(), 32);
+
+    // Shrinking the capacity evicts the least recently used values right away
+    get_hot_potato::set_lru_capacity(&db, 8);
+
+    // Create a new input to change the revision, and trigger the GC
+    MyInput::new(&db, 0);
+    assert_eq!(load_n_potatoes(), 8);
+
+    // Further uses stay within the new capacity
+    for &(i, input) in inputs.iter() {
+        let p = get_hot_potato(&db, input);
+        assert_eq!(p.0, i)
+    }
+
+    MyInput::new(&db, 0);
+    assert_eq!(load_n_potatoes(), 8);
+}
+
+#[test]
+fn pinned_values_are_not_evicted() {
+    let mut db = DatabaseImpl::default();
+    assert_eq!(load_n_potatoes(), 0);
+
+    let inputs: Vec<(u32, MyInput)> = (0..128).map(|i| (i, MyInput::new(&db, i))).collect();
+    let (_, open_file) = inputs[0];
+    get_hot_potato::pin(&db, open_file);
+
+    for &(i, input) in inputs.iter() {
+        let p = get_hot_potato(&db, input);
+        assert_eq!(p.0, i)
+    }
+
+    // The pinned value does not count towards the capacity
+    MyInput::new(&db, 0);
+    assert_eq!(load_n_potatoes(), 33);
+    db.assert_logs_len(128);
+    get_hot_potato(&db, open_file);
+    db.assert_logs_len(0);
+
+    // Once unpinned, it is the most recently used value, and the least recently used one is evicted
+    get_hot_potato::unpin(&db, open_file);
+    MyInput::new(&db, 0);
+    assert_eq!(load_n_potatoes(), 32);
+    get_hot_potato(&db, open_file);
+    db.assert_logs_len(0);
+}
@@ -0,0 +1,174 @@
+//! Test the `storage` option: tracked functions storing their memos
+//! densely, by slot, are memoized and re-executed like the others,
+//! also when the slot of a deleted struct is reused.
+
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, Word, words, word_len, word_len_hash, total_len);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::input]
+struct MyInput {
+    #[return_ref]
+    text: String,
+}
+
+#[salsa::tracked]
+struct Word {
+    #[id]
+    #[return_ref]
+    text: String,
+}
+
+#[salsa::tracked]
+fn words(db: &dyn Db, input: MyInput) -> Vec<Word> {
+    input
+        .text(db)
+        .split_whitespace()
+        .map(|word| Word::new(db, word.to_string()))
+        .collect()
+}
+
+#[salsa::tracked(storage = dense, lru = 2)]
+fn word_len(db: &dyn Db, word: Word) -> usize {
+    db.push_log(format!("word_len({})", word.text(db)));
+    word.text(db).len()
+}
+
+#[salsa::tracked(storage = hash)]
+fn word_len_hash(db: &dyn Db, word: Word) -> usize {
+    word.text(db).len()
+}
+
+#[salsa::tracked(storage = dense)]
+fn total_len(db: &dyn Db, input: MyInput) -> usize {
+    db.push_log("total_len".to_string());
+    words(db, input)
+        .into_iter()
+        .map(|word| {
+            assert_eq!(word_len_hash(db, word), word_len(db, word));
+            word_len(db, word)
+        })
+        .sum()
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn execute() {
+    let mut db = Database::default();
+    let a = MyInput::new(&db, "a bb".to_string());
+    let b = MyInput::new(&db, "ccc".to_string());
+
+    assert_eq!(total_len(&db, a), 3);
+    assert_eq!(total_len(&db, b), 3);
+    assert_eq!(total_len(&db, a), 3);
+    db.assert_logs(expect![[r#"
+        [
+            "total_len",
+            "word_len(a)",
+            "word_len(bb)",
+            "total_len",
+            "word_len(ccc)",
+        ]"#]]);
+
+    // Removing a word deletes its memos, and the others are reused.
+    a.set_text(&mut db).to("bb".to_string());
+    assert_eq!(total_len(&db, a), 2);
+    assert_eq!(total_len(&db, b), 3);
+    db.assert_logs(expect![[r#"
+        [
+            "total_len",
+        ]"#]]);
+
+    a.set_text(&mut db).to("a bb dddd".to_string());
+    assert_eq!(total_len(&db, a), 7);
+    db.assert_logs(expect![[r#"
+        [
+            "total_len",
+            "word_len(a)",
+            "word_len(dddd)",
+        ]"#]]);
+}
+
+#[test]
+fn reuse_slot() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, "a bb".to_string());
+    assert_eq!(total_len(&db, input), 3);
+    let old_words = words(&db, input);
+    db.assert_logs(expect![[r#"
+        [
+            "total_len",
+            "word_len(a)",
+            "word_len(bb)",
+        ]"#]]);
+
+    // `a` is deleted, and its slot is reused by `cccc` in the next revision.
+    input.set_text(&mut db).to("bb".to_string());
+    assert_eq!(total_len(&db, input), 2);
+    input.set_text(&mut db).to("bb cccc".to_string());
+    assert_eq!(total_len(&db, input), 6);
+    let new_words = words(&db, input);
+    assert_ne!(new_words[1], old_words[0]);
+    assert_eq!(
+        salsa::AsId::as_id(new_words[1]).as_u32() & 0xFF_FFFF,
+        salsa::AsId::as_id(old_words[0]).as_u32(),
+    );
+    db.assert_logs(expect![[r#"
+        [
+            "total_len",
+            "total_len",
+            "word_len(cccc)",
+        ]"#]]);
+
+    // The memos of `bb` and `cccc` are both kept.
+    assert_eq!(word_len(&db, new_words[0]), 2);
+    assert_eq!(word_len(&db, new_words[1]), 4);
+    db.assert_logs(expect!["[]"]);
+}
+
+#[test]
+fn lru() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, "a bb ccc".to_string());
+    let all_words = words(&db, input);
+    for &word in &all_words {
+        word_len(&db, word);
+    }
+    db.assert_logs(expect![[r#"
+        [
+            "word_len(a)",
+            "word_len(bb)",
+            "word_len(ccc)",
+        ]"#]]);
+
+    // The least recently used value was evicted, and is computed again.
+    for &word in all_words.iter().rev() {
+        word_len(&db, word);
+    }
+    db.assert_logs(expect![[r#"
+        [
+            "word_len(a)",
+        ]"#]]);
+}
@@ -0,0 +1,130 @@
+//! Test `Database::memory_report`, which reports the memory used by the
+//! memoized values of each tracked function, and `#[derive(salsa::HeapSize)]`.
+
+use salsa::memory::{HeapSize, IngredientMemory};
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, words, word_count, summary);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input]
+struct MyInput {
+    #[return_ref]
+    text: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, salsa::HeapSize)]
+enum Summary {
+    Empty,
+    Words { first: String, count: usize },
+}
+
+#[salsa::tracked(return_ref, heap_size)]
+fn words(db: &dyn Db, input: MyInput) -> Vec<String> {
+    input
+        .text(db)
+        .split_whitespace()
+        .map(str::to_string)
+        .collect()
+}
+
+#[salsa::tracked(lru = 1)]
+fn word_count(db: &dyn Db, input: MyInput) -> usize {
+    words(db, input).len()
+}
+
+#[salsa::tracked(heap_size)]
+fn summary(db: &dyn Db, input: MyInput) -> Summary {
+    match words(db, input).first() {
+        Some(first) => Summary::Words {
+            first: first.clone(),
+            count: word_count(db, input),
+        },
+        None => Summary::Empty,
+    }
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+#[test]
+fn derive() {
+    assert_eq!(Summary::Empty.heap_size(), 0);
+    let first = String::with_capacity(16);
+    assert_eq!(Summary::Words { first, count: 0 }.heap_size(), 16);
+}
+
+#[test]
+fn report() {
+    let db = Database::default();
+    let input1 = MyInput::new(&db, "a bb ccc".to_string());
+    let input2 = MyInput::new(&db, "".to_string());
+    summary(&db, input1);
+    summary(&db, input2);
+    word_count(&db, input2);
+
+    let report = salsa::Database::memory_report(&db);
+    let words1 = words(&db, input1);
+    let words_heap_bytes = words1.capacity() * std::mem::size_of::<String>()
+        + words1.iter().map(String::capacity).sum::<usize>();
+    assert_eq!(
+        report.ingredients[0],
+        IngredientMemory {
+            jar: "memory_report::Jar",
+            ingredient: "words",
+            memos: 2,
+            values: 2,
+            value_bytes: 2 * std::mem::size_of::<Vec<String>>(),
+            heap_bytes: Some(words_heap_bytes),
+        }
+    );
+
+    // The value of `word_count` for `input1` was evicted by `lru`.
+    assert_eq!(
+        report.ingredients[1],
+        IngredientMemory {
+            jar: "memory_report::Jar",
+            ingredient: "word_count",
+            memos: 2,
+            values: 1,
+            value_bytes: std::mem::size_of::<usize>(),
+            heap_bytes: None,
+        }
+    );
+
+    assert_eq!(report.ingredients[2].heap_bytes, Some("a".len()));
+    assert_eq!(report.ingredients.len(), 3);
+    assert_eq!(
+        report.jars(),
+        vec![("memory_report::Jar", report.total_bytes())]
+    );
+}
+
+#[test]
+fn display() {
+    let db = Database::default();
+    let input = MyInput::new(&db, "".to_string());
+    summary(&db, input);
+
+    let report = salsa::Database::memory_report(&db);
+    let expected = format!(
+        "memory_report::Jar: {total} bytes
+    words: 1 values (1 memos), {words} bytes
+    word_count: 0 values (0 memos), 0 bytes + unknown heap
+    summary: 1 values (1 memos), {summary} bytes
+",
+        total = report.total_bytes(),
+        words = std::mem::size_of::<Vec<String>>(),
+        summary = std::mem::size_of::<Summary>(),
+    );
+    assert_eq!(report.to_string(), expected);
+}
@@ -0,0 +1,99 @@
+//! Test `salsa::testing::MockDatabase`: the tracked functions called by
+//! the one under test return their stubbed values without being executed.
+
+use salsa::testing::MockDatabase;
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, parse, resolve, check);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::input]
+struct MyInput {
+    text: String,
+}
+
+#[salsa::tracked]
+fn parse(db: &dyn Db, input: MyInput) -> Vec<String> {
+    db.push_log(format!("parse({})", input.text(db)));
+    input.text(db).split(' ').map(str::to_string).collect()
+}
+
+#[salsa::tracked]
+fn resolve(db: &dyn Db, input: MyInput, name: String) -> Option<usize> {
+    db.push_log(format!("resolve({name})"));
+    parse(db, input).iter().position(|word| *word == name)
+}
+
+/// The function under test.
+#[salsa::tracked]
+fn check(db: &dyn Db, input: MyInput) -> Vec<Option<usize>> {
+    db.push_log("check".to_string());
+    parse(db, input)
+        .into_iter()
+        .map(|word| resolve(db, input, word))
+        .collect()
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn stubbed() {
+    let mut mock = MockDatabase::<Database>::default();
+    let input = MyInput::new(&*mock, String::new());
+    mock.stub::<parse>(input, vec!["a".to_string(), "b".to_string()])
+        .stub::<resolve>((input, "a".to_string()), Some(1))
+        .stub::<resolve>((input, "b".to_string()), None);
+    let mut db = mock.build();
+
+    assert_eq!(check(&db, input), vec![Some(1), None]);
+    db.assert_logs(expect![[r#"
+        [
+            "check",
+        ]"#]]);
+
+    // Stubbing a function again executes the function under test again.
+    let mut mock = MockDatabase::new(db);
+    mock.stub::<resolve>((input, "b".to_string()), Some(0));
+    let mut db = mock.build();
+    assert_eq!(check(&db, input), vec![Some(1), Some(0)]);
+    db.assert_logs(expect![[r#"
+        [
+            "check",
+        ]"#]]);
+}
+
+#[test]
+fn partially_stubbed() {
+    let mut mock = MockDatabase::<Database>::default();
+    let input = MyInput::new(&*mock, "a b".to_string());
+    mock.stub::<parse>(input, vec!["b".to_string()]);
+    let mut db = mock.build();
+
+    // `resolve` is executed, and reads the stubbed value of `parse`.
+    assert_eq!(check(&db, input), vec![Some(0)]);
+    db.assert_logs(expect![[r#"
+        [
+            "check",
+            "resolve(b)",
+        ]"#]]);
+}
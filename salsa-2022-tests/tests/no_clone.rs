@@ -0,0 +1,80 @@
+//! Test the `no_clone` option of tracked functions: values that are not `Clone`
+//! are read by reference with `<fn>::get`, and still backdated when unchanged.
+
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, parse, len);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::input]
+struct MyInput {
+    text: String,
+}
+
+/// Intentionally not `Clone`.
+#[derive(PartialEq, Eq, Debug)]
+struct Ast {
+    words: Vec<String>,
+}
+
+#[salsa::tracked(no_clone)]
+fn parse(db: &dyn Db, input: MyInput) -> Ast {
+    db.push_log(format!("parse({})", input.text(db)));
+    Ast {
+        words: input
+            .text(db)
+            .split_whitespace()
+            .map(str::to_string)
+            .collect(),
+    }
+}
+
+#[salsa::tracked]
+fn len(db: &dyn Db, input: MyInput) -> usize {
+    db.push_log("len".to_string());
+    parse::get(db, input).words.len()
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn read_by_reference() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, "a b".to_string());
+    let ast: &Ast = parse::get(&db, input);
+    assert_eq!(ast.words, ["a", "b"]);
+    assert_eq!(len(&db, input), 2);
+    db.assert_logs(expect![[r#"
+        [
+            "parse(a b)",
+            "len",
+        ]"#]]);
+
+    // The new value is equal to the old one, so `len` is not executed again.
+    input.set_text(&mut db).to("a  b".to_string());
+    assert_eq!(len(&db, input), 2);
+    db.assert_logs(expect![[r#"
+        [
+            "parse(a  b)",
+        ]"#]]);
+}
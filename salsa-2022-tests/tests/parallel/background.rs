@@ -0,0 +1,75 @@
+//! Test `salsa::background`: the queries computed in the background are
+//! memoized for the database, and those cancelled by a write are computed
+//! again on the new revision once resumed.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::setup::Database;
+use crate::setup::Knobs;
+use salsa::background::Background;
+
+pub(crate) trait Db: salsa::DbWithJar<Jar> + Knobs {}
+
+impl<T: salsa::DbWithJar<Jar> + Knobs> Db for T {}
+
+#[salsa::jar(db = Db)]
+pub(crate) struct Jar(MyInput, double, slow);
+
+#[salsa::input(jar = Jar)]
+pub(crate) struct MyInput {
+    field: i32,
+}
+
+static DOUBLE_EXECUTIONS: AtomicUsize = AtomicUsize::new(0);
+
+#[salsa::tracked(jar = Jar)]
+pub(crate) fn double(db: &dyn Db, input: MyInput) -> i32 {
+    DOUBLE_EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    input.field(db) * 2
+}
+
+static SLOW_EXECUTIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// The first execution calls `double` until it is cancelled.
+#[salsa::tracked(jar = Jar)]
+pub(crate) fn slow(db: &dyn Db, input: MyInput) -> i32 {
+    if SLOW_EXECUTIONS.fetch_add(1, Ordering::SeqCst) == 0 {
+        db.signal(1);
+        loop {
+            double(db, input);
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+    }
+    double(db, input) + 1
+}
+
+#[test]
+fn execute() {
+    let mut db = Database::default();
+    let inputs: Vec<MyInput> = (0..4).map(|i| MyInput::new(&db, i)).collect();
+
+    let background = Background::new(2);
+    for &input in inputs.iter().chain(&inputs) {
+        background.enqueue(&db, |db, input| double(db, input), input);
+    }
+    background.wait();
+    assert_eq!(background.pending(), 0);
+    assert_eq!(DOUBLE_EXECUTIONS.load(Ordering::SeqCst), 4);
+    let sum: i32 = inputs.iter().map(|&input| double(&db, input)).sum();
+    assert_eq!(sum, 12);
+    assert_eq!(DOUBLE_EXECUTIONS.load(Ordering::SeqCst), 4);
+
+    // The write cancels `slow`, which is queued again.
+    background.enqueue(&db, |db, input| slow(db, input), inputs[1]);
+    db.wait_for(1);
+    inputs[1].set_field(&mut db).to(10);
+    background.wait();
+    assert_eq!(background.pending(), 1);
+
+    background.resume(&db);
+    background.wait();
+    assert_eq!(background.pending(), 0);
+    assert_eq!(SLOW_EXECUTIONS.load(Ordering::SeqCst), 2);
+    assert_eq!(slow(&db, inputs[1]), 21);
+    assert_eq!(SLOW_EXECUTIONS.load(Ordering::SeqCst), 2);
+}
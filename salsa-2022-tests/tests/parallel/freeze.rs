@@ -0,0 +1,82 @@
+//! Test `Database::freeze`: while the guard is alive, setting an input panics,
+//! naming where the guard was created, rather than cancelling the snapshots.
+
+use crate::setup::Database;
+use crate::setup::Knobs;
+use salsa::{Database as _, ParallelDatabase};
+
+pub(crate) trait Db: salsa::DbWithJar<Jar> + Knobs {}
+
+impl<T: salsa::DbWithJar<Jar> + Knobs> Db for T {}
+
+#[salsa::jar(db = Db)]
+pub(crate) struct Jar(MyInput, slow);
+
+#[salsa::input(jar = Jar)]
+pub(crate) struct MyInput {
+    field: i32,
+}
+
+#[salsa::tracked(jar = Jar)]
+pub(crate) fn slow(db: &dyn Db, input: MyInput) -> i32 {
+    // Wait until the main thread tried to set the input.
+    db.signal(1);
+    db.wait_for(2);
+
+    input.field(db) + 1
+}
+
+// Main thread                  Thread A
+// -----------                  --------
+// freeze
+// wait for stage 1 (blocks)    slow
+// |                            signal stage 1
+// (unblocked)                  wait for stage 2 (blocks)
+// set the input (panics)       |
+// signal stage 2               |
+//                              (unblocked)
+//                              slow completes, not cancelled
+// drop the guard
+// set the input
+
+#[test]
+fn execute() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 10);
+
+    let line = line!() + 1;
+    let guard = db.freeze();
+    let thread_a = std::thread::spawn({
+        let db = db.snapshot();
+        move || slow(&*db, input)
+    });
+
+    db.wait_for(1);
+    let panic = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        input.set_field(&mut db).to(20);
+    }))
+    .unwrap_err();
+    let message = panic.downcast_ref::<String>().unwrap();
+    assert!(
+        message.contains(&format!(
+            "the `FrozenGuard` created at {}:{line}:20 was not dropped yet",
+            file!()
+        )),
+        "{message}"
+    );
+    db.signal(2);
+
+    assert_eq!(thread_a.join().unwrap(), 11);
+    drop(guard);
+
+    input.set_field(&mut db).to(20);
+    assert_eq!(slow(&db, input), 21);
+}
+
+#[test]
+#[should_panic(expected = "cannot write to the database while it is frozen")]
+fn synthetic_write() {
+    let mut db = Database::default();
+    let _guard = db.freeze();
+    db.synthetic_write(salsa::Durability::LOW);
+}
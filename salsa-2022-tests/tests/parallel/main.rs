@@ -1,7 +1,15 @@
 mod setup;
 
+mod background;
+mod freeze;
+mod max_concurrent_queries;
+mod parallel_cancellation_token;
 mod parallel_cycle_all_recover;
 mod parallel_cycle_mid_recover;
 mod parallel_cycle_none_recover;
 mod parallel_cycle_one_recover;
-mod signal;
+mod parallel_fan_out;
+mod parallel_unblock;
+mod pipelined_writes;
+mod sync_database;
+mod unwind_if_cancelled;
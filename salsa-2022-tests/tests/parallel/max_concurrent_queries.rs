@@ -0,0 +1,88 @@
+//! Test `Database::set_max_concurrent_queries`: with a limit of one, a
+//! snapshot waits for the other to finish its query before executing its own,
+//! while the queries called from those run without waiting.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::setup::Database;
+use crate::setup::Knobs;
+use salsa::{Database as _, ParallelDatabase};
+
+pub(crate) trait Db: salsa::DbWithJar<Jar> + Knobs {}
+
+impl<T: salsa::DbWithJar<Jar> + Knobs> Db for T {}
+
+#[salsa::jar(db = Db)]
+pub(crate) struct Jar(MyInput, outer, inner);
+
+#[salsa::input(jar = Jar)]
+pub(crate) struct MyInput {
+    field: i32,
+}
+
+static EXECUTING: AtomicUsize = AtomicUsize::new(0);
+static MAX_EXECUTING: AtomicUsize = AtomicUsize::new(0);
+
+#[salsa::tracked(jar = Jar)]
+pub(crate) fn outer(db: &dyn Db, input: MyInput) -> i32 {
+    let executing = EXECUTING.fetch_add(1, Ordering::SeqCst) + 1;
+    MAX_EXECUTING.fetch_max(executing, Ordering::SeqCst);
+    if input.field(db) == 0 {
+        // Thread A: hold the permit until thread B waits for it.
+        db.signal(1);
+        db.wait_for(2);
+    } else {
+        // Thread B: only reached once thread A released the permit (if thread B
+        // executed right away, this would release thread A, and the test would
+        // see both executing).
+        db.signal(2);
+    }
+    let result = inner(db, input) + 1;
+    EXECUTING.fetch_sub(1, Ordering::SeqCst);
+    result
+}
+
+#[salsa::tracked(jar = Jar)]
+pub(crate) fn inner(db: &dyn Db, input: MyInput) -> i32 {
+    input.field(db) * 2
+}
+
+// Thread A                      Thread B
+// --------                      --------
+// outer (takes the permit)
+// signal stage 1
+// wait for stage 2 (blocks)     (spawned)
+// |                             outer: waits for the permit -> stage 2
+// (unblocked)                   |
+// outer completes               |
+// (releases the permit)         (takes the permit)
+//                               outer completes
+
+#[test]
+fn execute() {
+    let mut db = Database::default();
+    db.set_max_concurrent_queries(Some(1));
+    let a = MyInput::new(&db, 0);
+    let b = MyInput::new(&db, 1);
+
+    let thread_a = std::thread::spawn({
+        let db = db.snapshot();
+        move || outer(&*db, a)
+    });
+
+    db.wait_for(1);
+    let thread_b = std::thread::spawn({
+        let db = db.snapshot();
+        db.knobs().signal_on_will_wait_for_permit.set(2);
+        move || outer(&*db, b)
+    });
+
+    assert_eq!(thread_a.join().unwrap(), 1);
+    assert_eq!(thread_b.join().unwrap(), 3);
+    assert_eq!(MAX_EXECUTING.load(Ordering::SeqCst), 1);
+
+    // Without a limit, the handles no longer wait for each other.
+    db.set_max_concurrent_queries(None);
+    a.set_field(&mut db).to(10);
+    assert_eq!(outer(&db, a), 21);
+}
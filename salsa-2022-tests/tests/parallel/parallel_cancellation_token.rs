@@ -0,0 +1,99 @@
+//! Test that cancelling the token of a snapshot only cancels the
+//! queries of that snapshot, and that a snapshot blocked on one of
+//! its queries executes that query itself.
+
+use std::panic::AssertUnwindSafe;
+
+use crate::setup::Database;
+use crate::setup::Knobs;
+use salsa::{CancellationToken, Cancelled, ParallelDatabase};
+
+pub(crate) trait Db: salsa::DbWithJar<Jar> + Knobs {}
+
+impl<T: salsa::DbWithJar<Jar> + Knobs> Db for T {}
+
+#[salsa::jar(db = Db)]
+pub(crate) struct Jar(MyInput, slow, double);
+
+#[salsa::input(jar = Jar)]
+pub(crate) struct MyInput {
+    field: i32,
+}
+
+#[salsa::tracked(jar = Jar)]
+pub(crate) fn slow(db: &dyn Db, input: MyInput) -> i32 {
+    // Wait until thread B blocks on this query and the token of thread A is cancelled.
+    db.signal(1);
+    db.wait_for(3);
+
+    double(db, input) + 1
+}
+
+#[salsa::tracked(jar = Jar)]
+pub(crate) fn double(db: &dyn Db, input: MyInput) -> i32 {
+    input.field(db) * 2
+}
+
+// Thread A (token_a)          Thread B (token_b)          Main
+// --------                    --------                    ----
+// slow                        wait for stage 1 (blocks)   wait for stage 2 (blocks)
+// signal stage 1              |                           |
+// wait for stage 3 (blocks)   (unblocked)                 |
+// |                           slow (blocks -> stage 2)    |
+// |                           |                           (unblocked)
+// |                           |                           cancel token_a
+// |                           |                           signal stage 3
+// (unblocked)                 |                           |
+// double: cancelled           |                           |
+// |                           (unblocked)                 |
+// |                           slow: executed by B         |
+
+#[test]
+fn execute() {
+    let db = Database::default();
+    let input = MyInput::new(&db, 10);
+    let token_a = CancellationToken::new();
+
+    let thread_a = std::thread::spawn({
+        let db = db.snapshot_with_token(token_a.clone());
+        move || Cancelled::catch(AssertUnwindSafe(|| slow(&*db, input)))
+    });
+
+    let thread_b = std::thread::spawn({
+        let db = db.snapshot_with_token(CancellationToken::new());
+        db.knobs().signal_on_will_block.set(2);
+        move || {
+            db.wait_for(1);
+            Cancelled::catch(AssertUnwindSafe(|| slow(&*db, input)))
+        }
+    });
+
+    db.wait_for(2);
+    token_a.cancel();
+    db.signal(3);
+
+    let cancelled = thread_a.join().unwrap().unwrap_err();
+    assert!(matches!(cancelled, Cancelled::Requested { .. }));
+    assert_eq!(
+        cancelled.to_string(),
+        "cancelled because of cancellation request"
+    );
+    assert_eq!(cancelled.query_stack()[0].to_string(), "slow(0)");
+
+    assert_eq!(thread_b.join().unwrap().unwrap(), 21);
+}
+
+#[test]
+fn cancelled_before_query() {
+    let db = Database::default();
+    let input = MyInput::new(&db, 10);
+    let token = CancellationToken::new();
+    token.cancel();
+
+    let cancelled_db = db.snapshot_with_token(token);
+    let result = Cancelled::catch(AssertUnwindSafe(|| double(&*cancelled_db, input)));
+    assert!(matches!(result, Err(Cancelled::Requested { .. })));
+
+    // Other snapshots are unaffected.
+    assert_eq!(double(&*db.snapshot(), input), 20);
+}
@@ -69,15 +69,22 @@ fn execute() {
             ]
         "#]];
         expected.assert_debug_eq(&c.all_participants(&db));
+
+        // The cycle was detected by thread B, when `b` tried to block on `a`.
+        let query_stack: Vec<String> = c.query_stack().iter().map(|f| f.to_string()).collect();
+        assert_eq!(query_stack, ["b(0)"]);
     } else {
         panic!("b failed in an unexpected way: {:?}", err_b);
     }
 
     // We expect A to propagate a panic, which causes us to use the sentinel
     // type `Canceled`.
-    assert!(thread_a
-        .join()
-        .unwrap_err()
-        .downcast_ref::<salsa::Cancelled>()
-        .is_some());
+    let err_a = thread_a.join().unwrap_err();
+    let cancelled = err_a.downcast_ref::<salsa::Cancelled>().unwrap();
+    let query_stack: Vec<String> = cancelled
+        .query_stack()
+        .iter()
+        .map(|f| f.to_string())
+        .collect();
+    assert_eq!(query_stack, ["a(0)"]);
 }
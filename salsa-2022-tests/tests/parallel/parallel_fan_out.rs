@@ -0,0 +1,180 @@
+//! Test the ways to parallelize work that a tracked fn would otherwise
+//! do sequentially: fan out over snapshots *before* invoking it, so that
+//! it finds its sub-queries already memoized, or call `salsa::par_map`
+//! from it, which records the reads of the other threads, and which the
+//! threads blocked on it help with.
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::setup::Database;
+use crate::setup::Knobs;
+use salsa::ParallelDatabase;
+
+pub(crate) trait Db:
+    salsa::DbWithJar<Jar> + salsa::par_map::ParallelDb<Jar> + Knobs
+{
+}
+
+impl Db for Database {}
+
+#[salsa::jar(db = Db)]
+pub(crate) struct Jar(
+    MyInput,
+    square,
+    sum_of_squares,
+    par_sum_of_squares,
+    par_sum_waiting_for_help,
+    par_square_on_calling_thread,
+);
+
+#[salsa::input(jar = Jar)]
+pub(crate) struct MyInput {
+    #[return_ref]
+    values: Vec<MyInput>,
+    field: i32,
+}
+
+static SQUARE_EXECUTIONS: AtomicUsize = AtomicUsize::new(0);
+
+#[salsa::tracked(jar = Jar)]
+pub(crate) fn square(db: &dyn Db, input: MyInput) -> i32 {
+    SQUARE_EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    let field = input.field(db);
+    field * field
+}
+
+#[salsa::tracked(jar = Jar)]
+pub(crate) fn sum_of_squares(db: &dyn Db, input: MyInput) -> i32 {
+    input.values(db).iter().map(|&v| square(db, v)).sum()
+}
+
+static PAR_SUM_EXECUTIONS: AtomicUsize = AtomicUsize::new(0);
+
+#[salsa::tracked(jar = Jar)]
+pub(crate) fn par_sum_of_squares(db: &dyn Db, input: MyInput) -> i32 {
+    PAR_SUM_EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    let values = input.values(db).clone();
+    salsa::par_map(db, values, |db, v| square(db, v))
+        .into_iter()
+        .sum()
+}
+
+/// Calls `par_map` with a single item, which the calling thread runs itself.
+#[salsa::tracked(jar = Jar)]
+pub(crate) fn par_square_on_calling_thread(db: &dyn Db, input: MyInput) -> i32 {
+    let caller = std::thread::current().id();
+    salsa::par_map(db, vec![input], |db, v| {
+        assert_eq!(std::thread::current().id(), caller);
+        square(db, v)
+    })[0]
+}
+
+thread_local! {
+    static BLOCKED_THREAD: Cell<bool> = const { Cell::new(false) };
+}
+
+static HELPED: AtomicBool = AtomicBool::new(false);
+static GAVE_UP: AtomicBool = AtomicBool::new(false);
+
+/// Like `par_sum_of_squares`, but the items only run once the thread blocked
+/// on this query ran one of them (or after a timeout).
+#[salsa::tracked(jar = Jar)]
+pub(crate) fn par_sum_waiting_for_help(db: &dyn Db, input: MyInput) -> i32 {
+    db.signal(1);
+    let values = input.values(db).clone();
+    salsa::par_map(db, values, |db, v| {
+        if BLOCKED_THREAD.with(Cell::get) {
+            HELPED.store(true, Ordering::SeqCst);
+        } else {
+            let start = Instant::now();
+            while !HELPED.load(Ordering::SeqCst) && !GAVE_UP.load(Ordering::SeqCst) {
+                if start.elapsed() > Duration::from_secs(10) {
+                    GAVE_UP.store(true, Ordering::SeqCst);
+                }
+                std::thread::yield_now();
+            }
+        }
+        v.field(db) * v.field(db)
+    })
+    .into_iter()
+    .sum()
+}
+
+#[test]
+fn execute() {
+    let db = Database::default();
+
+    let values: Vec<MyInput> = (0..8).map(|i| MyInput::new(&db, vec![], i)).collect();
+    let root = MyInput::new(&db, values.clone(), 0);
+
+    // Warm up the sub-queries on one snapshot per thread.
+    std::thread::scope(|scope| {
+        for chunk in values.chunks(2) {
+            let db = db.snapshot();
+            scope.spawn(move || {
+                for &v in chunk {
+                    square(&*db, v);
+                }
+            });
+        }
+    });
+    assert_eq!(SQUARE_EXECUTIONS.load(Ordering::SeqCst), 8);
+
+    // The snapshots are gone, so the main handle can use the memoized
+    // results (and would be free to set inputs again).
+    assert_eq!(sum_of_squares(&db, root), 140);
+    assert_eq!(SQUARE_EXECUTIONS.load(Ordering::SeqCst), 8);
+}
+
+#[test]
+fn par_map() {
+    let mut db = Database::default();
+
+    let values: Vec<MyInput> = (0..8).map(|i| MyInput::new(&db, vec![], i)).collect();
+    let root = MyInput::new(&db, values.clone(), 0);
+    let unrelated = MyInput::new(&db, vec![], 0);
+    assert_eq!(par_sum_of_squares(&db, root), 140);
+    assert_eq!(PAR_SUM_EXECUTIONS.load(Ordering::SeqCst), 1);
+
+    // The input was read by another thread, on behalf of `par_sum_of_squares`.
+    values[3].set_field(&mut db).to(10);
+    assert_eq!(par_sum_of_squares(&db, root), 231);
+    assert_eq!(PAR_SUM_EXECUTIONS.load(Ordering::SeqCst), 2);
+
+    unrelated.set_field(&mut db).to(1);
+    assert_eq!(par_sum_of_squares(&db, root), 231);
+    assert_eq!(PAR_SUM_EXECUTIONS.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn single_chunk() {
+    let db = Database::default();
+    let input = MyInput::new(&db, vec![], 7);
+    assert_eq!(par_square_on_calling_thread(&db, input), 49);
+}
+
+#[test]
+fn blocked_thread_helps() {
+    let db = Database::default();
+
+    let values: Vec<MyInput> = (0..200).map(|i| MyInput::new(&db, vec![], i)).collect();
+    let root = MyInput::new(&db, values.clone(), 0);
+    let expected = (0..200).map(|i| i * i).sum::<i32>();
+
+    let thread_b = std::thread::spawn({
+        let db = db.snapshot();
+        move || par_sum_waiting_for_help(&*db, root)
+    });
+
+    // Block on the query while thread B runs `par_map`: meanwhile, this thread
+    // runs some of the items, which the other threads wait for.
+    db.wait_for(1);
+    BLOCKED_THREAD.with(|blocked| blocked.set(true));
+    assert_eq!(par_sum_waiting_for_help(&db, root), expected);
+    BLOCKED_THREAD.with(|blocked| blocked.set(false));
+
+    assert_eq!(thread_b.join().unwrap(), expected);
+    assert!(HELPED.load(Ordering::SeqCst));
+}
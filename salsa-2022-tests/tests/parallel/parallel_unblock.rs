@@ -0,0 +1,64 @@
+//! Test that a thread that blocked on a query executing in another
+//! thread is notified with a `DidUnblock` event once it completes.
+
+use crate::setup::Database;
+use crate::setup::Knobs;
+use salsa::ParallelDatabase;
+
+pub(crate) trait Db: salsa::DbWithJar<Jar> + Knobs {}
+
+impl<T: salsa::DbWithJar<Jar> + Knobs> Db for T {}
+
+#[salsa::jar(db = Db)]
+pub(crate) struct Jar(MyInput, slow);
+
+#[salsa::input(jar = Jar)]
+pub(crate) struct MyInput {
+    field: i32,
+}
+
+#[salsa::tracked(jar = Jar)]
+pub(crate) fn slow(db: &dyn Db, input: MyInput) -> i32 {
+    // Wait until thread A blocks on this query
+    db.signal(1);
+    db.wait_for(2);
+
+    input.field(db) * 2
+}
+
+// Thread A                   Thread B
+// --------                   --------
+// wait for stage 1 (blocks)  slow
+// |                          signal stage 1
+// (unblocked)                wait for stage 2 (blocks)
+// slow (blocks -> stage 2)   |
+// |                          (unblocked)
+// |                          slow completes
+// (unblocked -> stage 3)
+
+#[test]
+fn execute() {
+    let db = Database::default();
+    let input = MyInput::new(&db, 11);
+
+    let thread_a = std::thread::spawn({
+        let db = db.snapshot();
+        db.knobs().signal_on_will_block.set(2);
+        db.knobs().signal_on_did_unblock.set(3);
+        move || {
+            db.wait_for(1);
+            slow(&*db, input)
+        }
+    });
+
+    let thread_b = std::thread::spawn({
+        let db = db.snapshot();
+        move || slow(&*db, input)
+    });
+
+    // Only reached once thread A got the `DidUnblock` event.
+    db.wait_for(3);
+
+    assert_eq!(thread_a.join().unwrap(), 22);
+    assert_eq!(thread_b.join().unwrap(), 22);
+}
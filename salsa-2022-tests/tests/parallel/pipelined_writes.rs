@@ -0,0 +1,65 @@
+//! Test that, with pipelined writes, setting an input while a snapshot is alive
+//! does not wait for the snapshot, which keeps reading the revision it started in.
+
+use crate::setup::Database;
+use crate::setup::Knobs;
+use salsa::{Database as _, ParallelDatabase};
+
+pub(crate) trait Db: salsa::DbWithJar<Jar> + Knobs {}
+
+impl<T: salsa::DbWithJar<Jar> + Knobs> Db for T {}
+
+#[salsa::jar(db = Db)]
+pub(crate) struct Jar(MyInput, slow, double);
+
+#[salsa::input(jar = Jar)]
+pub(crate) struct MyInput {
+    field: i32,
+}
+
+#[salsa::tracked(jar = Jar)]
+pub(crate) fn slow(db: &dyn Db, input: MyInput) -> i32 {
+    // Wait until the input was set by the main thread.
+    db.signal(1);
+    db.wait_for(2);
+
+    double(db, input) + 1
+}
+
+#[salsa::tracked(jar = Jar)]
+pub(crate) fn double(db: &dyn Db, input: MyInput) -> i32 {
+    input.field(db) * 2
+}
+
+// Main thread                  Thread A
+// -----------                  --------
+// double (memoized)
+// wait for stage 1 (blocks)    slow
+// |                            signal stage 1
+// (unblocked)                  wait for stage 2 (blocks)
+// set the input (no blocking)  |
+// double (new value)           |
+// signal stage 2               |
+//                              (unblocked)
+//                              slow completes with the old value
+
+#[test]
+fn execute() {
+    let mut db = Database::default();
+    db.set_pipelined_writes(true);
+    let input = MyInput::new(&db, 10);
+    assert_eq!(double(&db, input), 20);
+
+    let thread_a = std::thread::spawn({
+        let db = db.snapshot();
+        move || slow(&*db, input)
+    });
+
+    db.wait_for(1);
+    input.set_field(&mut db).to(20);
+    assert_eq!(double(&db, input), 40);
+    db.signal(2);
+
+    assert_eq!(thread_a.join().unwrap(), 21);
+    assert_eq!(slow(&db, input), 41);
+}
@@ -1,6 +1,6 @@
 use std::{cell::Cell, sync::Arc};
 
-use crate::signal::Signal;
+use salsa::testing::Signal;
 
 /// Various "knobs" and utilities used by tests to force
 /// a certain behavior.
@@ -23,15 +23,32 @@ pub(crate) struct KnobsStruct {
 
     /// When this database is about to block, send a signal.
     pub(crate) signal_on_will_block: Cell<usize>,
+
+    /// When this database is done blocking, send a signal.
+    pub(crate) signal_on_did_unblock: Cell<usize>,
+
+    /// When this database is about to wait for a permit to execute queries,
+    /// send a signal.
+    pub(crate) signal_on_will_wait_for_permit: Cell<usize>,
 }
 
 #[salsa::db(
+    crate::background::Jar,
+    crate::freeze::Jar,
+    crate::max_concurrent_queries::Jar,
+    crate::parallel_cancellation_token::Jar,
     crate::parallel_cycle_one_recover::Jar,
     crate::parallel_cycle_none_recover::Jar,
     crate::parallel_cycle_mid_recover::Jar,
-    crate::parallel_cycle_all_recover::Jar
+    crate::parallel_cycle_all_recover::Jar,
+    crate::parallel_fan_out::Jar,
+    crate::parallel_unblock::Jar,
+    crate::pipelined_writes::Jar,
+    crate::sync_database::Jar,
+    crate::unwind_if_cancelled::Jar,
+    parallel,
+    default
 )]
-#[derive(Default)]
 pub(crate) struct Database {
     storage: salsa::Storage<Self>,
     knobs: KnobsStruct,
@@ -39,21 +56,21 @@ pub(crate) struct Database {
 
 impl salsa::Database for Database {
     fn salsa_event(&self, event: salsa::Event) {
-        if let salsa::EventKind::WillBlockOn { .. } = event.kind {
-            self.signal(self.knobs().signal_on_will_block.get());
+        match event.kind {
+            salsa::EventKind::WillBlockOn { .. } => {
+                self.signal(self.knobs().signal_on_will_block.get());
+            }
+            salsa::EventKind::DidUnblock { .. } => {
+                self.signal(self.knobs().signal_on_did_unblock.get());
+            }
+            salsa::EventKind::WillWaitForPermit => {
+                self.signal(self.knobs().signal_on_will_wait_for_permit.get());
+            }
+            _ => {}
         }
     }
 }
 
-impl salsa::ParallelDatabase for Database {
-    fn snapshot(&self) -> salsa::Snapshot<Self> {
-        salsa::Snapshot::new(Database {
-            storage: self.storage.snapshot(),
-            knobs: self.knobs.clone(),
-        })
-    }
-}
-
 impl Knobs for Database {
     fn knobs(&self) -> &KnobsStruct {
         &self.knobs
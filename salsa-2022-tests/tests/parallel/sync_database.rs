@@ -0,0 +1,67 @@
+//! Test `SyncDatabase`: queries are called through `&self` from several
+//! threads at once, reusing each other's memos, and inputs are set
+//! through `get_mut` once the threads are done.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::setup::Database;
+use crate::setup::Knobs;
+use salsa::SyncDatabase;
+
+pub(crate) trait Db: salsa::DbWithJar<Jar> + Knobs {}
+
+impl<T: salsa::DbWithJar<Jar> + Knobs> Db for T {}
+
+#[salsa::jar(db = Db)]
+pub(crate) struct Jar(MyInput, triple);
+
+#[salsa::input(jar = Jar)]
+pub(crate) struct MyInput {
+    field: i32,
+}
+
+static TRIPLE_EXECUTIONS: AtomicUsize = AtomicUsize::new(0);
+
+#[salsa::tracked(jar = Jar)]
+pub(crate) fn triple(db: &dyn Db, input: MyInput) -> i32 {
+    TRIPLE_EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    input.field(db) * 3
+}
+
+fn sum_on_threads(db: &SyncDatabase<Database>, inputs: &[MyInput]) -> i32 {
+    std::thread::scope(|scope| {
+        let threads: Vec<_> = inputs
+            .chunks(2)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|&input| db.with(|db| triple(db, input)))
+                        .sum::<i32>()
+                })
+            })
+            .collect();
+        threads.into_iter().map(|t| t.join().unwrap()).sum()
+    })
+}
+
+#[test]
+fn execute() {
+    let mut db = SyncDatabase::new(Database::default());
+    let inputs: Vec<MyInput> = (0..8).map(|i| MyInput::new(db.get_mut(), i)).collect();
+
+    assert_eq!(sum_on_threads(&db, &inputs), 84);
+    assert_eq!(TRIPLE_EXECUTIONS.load(Ordering::SeqCst), 8);
+
+    // The memos are shared by all the threads.
+    assert_eq!(sum_on_threads(&db, &inputs), 84);
+    assert_eq!(TRIPLE_EXECUTIONS.load(Ordering::SeqCst), 8);
+
+    // Writing does not wait for the pooled snapshots.
+    inputs[0].set_field(db.get_mut()).to(10);
+    assert_eq!(sum_on_threads(&db, &inputs), 114);
+    assert_eq!(TRIPLE_EXECUTIONS.load(Ordering::SeqCst), 9);
+
+    let db = db.into_inner();
+    assert_eq!(triple(&db, inputs[0]), 30);
+}
@@ -0,0 +1,68 @@
+//! Test `Database::unwind_if_cancelled`: a query checking it while it loops
+//! is cancelled by a pending write, and `Cancelled::catch` tells that
+//! cancellation from the panics of queries.
+
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::setup::Database;
+use crate::setup::Knobs;
+use salsa::{Cancelled, ParallelDatabase};
+
+pub(crate) trait Db: salsa::DbWithJar<Jar> + Knobs {}
+
+impl<T: salsa::DbWithJar<Jar> + Knobs> Db for T {}
+
+#[salsa::jar(db = Db)]
+pub(crate) struct Jar(MyInput, busy, buggy);
+
+#[salsa::input(jar = Jar)]
+pub(crate) struct MyInput {
+    field: i32,
+}
+
+#[salsa::tracked(jar = Jar)]
+pub(crate) fn busy(db: &dyn Db, input: MyInput) -> i32 {
+    input.field(db);
+    db.signal(1);
+    loop {
+        db.unwind_if_cancelled();
+        std::thread::yield_now();
+    }
+}
+
+#[salsa::tracked(jar = Jar)]
+pub(crate) fn buggy(db: &dyn Db, input: MyInput) -> i32 {
+    db.unwind_if_cancelled();
+    panic!("buggy({})", input.field(db))
+}
+
+#[test]
+fn execute() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 10);
+
+    let thread = std::thread::spawn({
+        let db = db.snapshot();
+        move || Cancelled::catch(AssertUnwindSafe(|| busy(&*db, input)))
+    });
+
+    // Waits for the snapshot to be dropped once its query is cancelled.
+    db.wait_for(1);
+    input.set_field(&mut db).to(20);
+
+    let cancelled = thread.join().unwrap().unwrap_err();
+    assert!(matches!(cancelled, Cancelled::PendingWrite { .. }));
+    assert_eq!(cancelled.query_stack()[0].to_string(), "busy(0)");
+}
+
+#[test]
+fn other_panics_are_not_caught() {
+    let db = Database::default();
+    let input = MyInput::new(&db, 10);
+
+    let payload = panic::catch_unwind(AssertUnwindSafe(|| {
+        Cancelled::catch(AssertUnwindSafe(|| buggy(&db, input)))
+    }))
+    .unwrap_err();
+    assert_eq!(payload.downcast_ref::<String>().unwrap(), "buggy(10)");
+}
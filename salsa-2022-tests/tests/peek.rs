@@ -0,0 +1,104 @@
+//! Test the `peek` function generated for tracked functions: it returns the
+//! value only if it is memoized and verified in the current revision, without
+//! executing the function.
+
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, expensive, heuristic);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::input]
+struct MyInput {
+    field: u32,
+    other: u32,
+}
+
+#[salsa::tracked]
+fn expensive(db: &dyn Db, input: MyInput) -> u32 {
+    db.push_log(format!("expensive({})", input.field(db)));
+    input.field(db) * 100
+}
+
+/// Uses the value of `expensive` if it is available, or a guess otherwise.
+#[salsa::tracked]
+fn heuristic(db: &dyn Db, input: MyInput) -> u32 {
+    db.push_log("heuristic".to_string());
+    match expensive::peek(db, input) {
+        Some(value) => *value,
+        None => input.other(db),
+    }
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn peek_does_not_execute() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 1, 7);
+    assert_eq!(expensive::peek(&db, input), None);
+    assert_eq!(heuristic(&db, input), 7);
+    db.assert_logs(expect![[r#"
+        [
+            "heuristic",
+        ]"#]]);
+
+    assert_eq!(expensive(&db, input), 100);
+    assert_eq!(expensive::peek(&db, input), Some(&100));
+    db.assert_logs(expect![[r#"
+        [
+            "expensive(1)",
+        ]"#]]);
+
+    // The memo of `expensive` is not verified in the new revision.
+    input.set_field(&mut db).to(2);
+    assert_eq!(expensive::peek(&db, input), None);
+    db.assert_logs(expect!["[]"]);
+}
+
+#[test]
+fn caller_sees_computed_value() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 1, 7);
+    assert_eq!(heuristic(&db, input), 7);
+    assert_eq!(expensive(&db, input), 100);
+    db.assert_logs(expect![[r#"
+        [
+            "heuristic",
+            "expensive(1)",
+        ]"#]]);
+
+    // `heuristic` read no value, so it is executed again in the next revision.
+    input.set_other(&mut db).to(8);
+    assert_eq!(expensive(&db, input), 100);
+    assert_eq!(heuristic(&db, input), 100);
+    db.assert_logs(expect![[r#"
+        [
+            "heuristic",
+        ]"#]]);
+
+    // It now depends on the value of `expensive`, which did not change.
+    input.set_other(&mut db).to(9);
+    assert_eq!(expensive(&db, input), 100);
+    assert_eq!(heuristic(&db, input), 100);
+    db.assert_logs(expect!["[]"]);
+}
@@ -0,0 +1,115 @@
+//! Test that a tracked function can report partial results through a
+//! `#[salsa::progress]` struct, and that they can be observed while the
+//! function is still executing.
+
+use std::sync::{Arc, Barrier};
+
+use salsa::{DebugWithDb, ParallelDatabase};
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, PartialHighlights, highlight);
+
+trait Db: salsa::DbWithJar<Jar> {
+    /// Blocks until the test has observed the partial results.
+    fn wait_for_test(&self);
+}
+
+#[salsa::input]
+struct MyInput {
+    #[return_ref]
+    text: String,
+}
+
+#[salsa::progress]
+struct PartialHighlights(String);
+
+#[salsa::tracked]
+fn highlight(db: &dyn Db, input: MyInput) -> Vec<String> {
+    let mut highlighted = vec![];
+    for word in input.text(db).split_whitespace() {
+        highlighted.push(format!("<b>{}</b>", word));
+        PartialHighlights::report(db, highlighted.join(" "));
+        db.wait_for_test();
+    }
+    highlighted
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    barrier: Option<Arc<Barrier>>,
+}
+
+impl salsa::Database for Database {}
+
+impl ParallelDatabase for Database {
+    fn snapshot(&self) -> salsa::Snapshot<Self> {
+        salsa::Snapshot::new(Database {
+            storage: self.storage.snapshot(),
+            barrier: self.barrier.clone(),
+        })
+    }
+}
+
+impl Db for Database {
+    fn wait_for_test(&self) {
+        if let Some(barrier) = &self.barrier {
+            barrier.wait();
+        }
+    }
+}
+
+#[test]
+fn report() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, "fn main".to_string());
+
+    let progress = PartialHighlights::subscribe(&db);
+    assert_eq!(highlight(&db, input), ["<b>fn</b>", "<b>main</b>"]);
+
+    let reported: Vec<_> = progress
+        .try_iter()
+        .map(|progress| format!("{:?}: {}", progress.query.debug(&db), progress.value))
+        .collect();
+    assert_eq!(
+        reported,
+        [
+            "highlight(0): <b>fn</b>",
+            "highlight(0): <b>fn</b> <b>main</b>"
+        ]
+    );
+
+    // Nothing is reported when the memoized value is reused...
+    assert_eq!(highlight(&db, input), ["<b>fn</b>", "<b>main</b>"]);
+    assert!(progress.try_recv().is_err());
+
+    // ...but it is when the function is re-executed.
+    input.set_text(&mut db).to("main".to_string());
+    assert_eq!(highlight(&db, input), ["<b>main</b>"]);
+    let reported: Vec<_> = progress.try_iter().map(|progress| progress.value).collect();
+    assert_eq!(reported, ["<b>main</b>"]);
+}
+
+#[test]
+fn observe_while_executing() {
+    let db = Database {
+        barrier: Some(Arc::new(Barrier::new(2))),
+        ..Default::default()
+    };
+    let input = MyInput::new(&db, "fn main".to_string());
+    let progress = PartialHighlights::subscribe(&db);
+
+    let thread = std::thread::spawn({
+        let db = db.snapshot();
+        move || highlight(&*db, input)
+    });
+
+    assert_eq!(progress.recv().unwrap().value, "<b>fn</b>");
+    db.wait_for_test();
+    assert_eq!(progress.recv().unwrap().value, "<b>fn</b> <b>main</b>");
+    db.wait_for_test();
+
+    assert_eq!(thread.join().unwrap(), ["<b>fn</b>", "<b>main</b>"]);
+}
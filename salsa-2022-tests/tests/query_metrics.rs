@@ -0,0 +1,94 @@
+//! Test that, with the `metrics` feature, the database counts how many
+//! times each tracked function was executed, validated and backdated.
+
+use salsa::metrics::QueryMetrics;
+use salsa::Database as _;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, is_large, describe);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input]
+struct MyInput {
+    field: u32,
+    other: u32,
+}
+
+#[salsa::tracked]
+fn is_large(db: &dyn Db, input: MyInput) -> bool {
+    input.field(db) >= 10
+}
+
+#[salsa::tracked]
+fn describe(db: &dyn Db, input: MyInput) -> &'static str {
+    if is_large(db, input) {
+        "large"
+    } else {
+        "small"
+    }
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+/// Returns the counters as `(name, executions, validations, backdates)`,
+/// leaving out the execution time, which we can't predict.
+fn counters(db: &Database) -> Vec<(&'static str, u64, u64, u64)> {
+    db.query_metrics()
+        .into_iter()
+        .map(|metrics: QueryMetrics| {
+            (
+                metrics.name,
+                metrics.executions,
+                metrics.validations,
+                metrics.backdates,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn execute() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 2, 0);
+
+    assert_eq!(describe(&db, input), "small");
+    assert_eq!(describe(&db, input), "small");
+    assert_eq!(
+        counters(&db),
+        [("is_large", 1, 0, 0), ("describe", 1, 0, 0)]
+    );
+
+    // `is_large` is executed again but its value is backdated,
+    // so `describe` is validated without being executed.
+    input.set_field(&mut db).to(4);
+    assert_eq!(describe(&db, input), "small");
+    assert_eq!(
+        counters(&db),
+        [("is_large", 2, 0, 1), ("describe", 1, 1, 0)]
+    );
+
+    // Changing a field that neither function reads lets both be validated.
+    input.set_other(&mut db).to(1);
+    assert_eq!(describe(&db, input), "small");
+    assert_eq!(
+        counters(&db),
+        [("is_large", 2, 1, 1), ("describe", 1, 2, 0)]
+    );
+
+    input.set_field(&mut db).to(20);
+    assert_eq!(describe(&db, input), "large");
+    assert_eq!(
+        counters(&db),
+        [("is_large", 3, 1, 1), ("describe", 2, 2, 0)]
+    );
+}
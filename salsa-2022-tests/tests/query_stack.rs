@@ -0,0 +1,73 @@
+//! Test `Runtime::current_query_stack`, and that the query stack is
+//! attached to the cycles salsa panics with.
+
+use std::panic::{self, AssertUnwindSafe};
+
+use salsa::storage::HasJarsDyn;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, outer, inner, cycle_a, cycle_b);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::tracked]
+fn outer(db: &dyn Db, input: MyInput) -> Vec<String> {
+    inner(db, input)
+}
+
+#[salsa::tracked]
+fn inner(db: &dyn Db, _input: MyInput) -> Vec<String> {
+    db.runtime()
+        .current_query_stack(db)
+        .iter()
+        .map(|frame| frame.to_string())
+        .collect()
+}
+
+#[salsa::tracked]
+fn cycle_a(db: &dyn Db, input: MyInput) -> u32 {
+    cycle_b(db, input)
+}
+
+#[salsa::tracked]
+fn cycle_b(db: &dyn Db, input: MyInput) -> u32 {
+    cycle_a(db, input)
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+#[test]
+fn current_query_stack() {
+    let db = Database::default();
+    let input = MyInput::new(&db, 22);
+    assert_eq!(outer(&db, input), ["outer(0)", "inner(0)"]);
+    assert!(db.runtime().current_query_stack(&db).is_empty());
+}
+
+#[test]
+fn cycle_panic() {
+    let db = Database::default();
+    let input = MyInput::new(&db, 22);
+    let err = panic::catch_unwind(AssertUnwindSafe(|| cycle_a(&db, input))).unwrap_err();
+    let cycle = err.downcast_ref::<salsa::Cycle>().unwrap();
+    let query_stack: Vec<String> = cycle
+        .query_stack()
+        .iter()
+        .map(|frame| frame.to_string())
+        .collect();
+    assert_eq!(query_stack, ["cycle_a(0)", "cycle_b(0)"]);
+}
@@ -0,0 +1,205 @@
+//! Test the `remote_cache` option of tracked functions: a database finds
+//! the values computed by another one in the cache they share, as long as
+//! the checksum of what the values depend on is the same. The functions that
+//! create entities or return ids cannot use the cache.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use salsa::remote_cache::{CacheKey, RemoteCache};
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(
+    SourceFile,
+    word_count,
+    line_count,
+    Word,
+    first_word,
+    file_id,
+);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::input]
+struct SourceFile {
+    #[return_ref]
+    text: String,
+}
+
+#[salsa::tracked(remote_cache = text_checksum)]
+fn word_count(db: &dyn Db, file: SourceFile) -> usize {
+    db.push_log(format!("word_count({:?})", file.text(db)));
+    file.text(db).split_whitespace().count()
+}
+
+fn text_checksum(db: &dyn Db, file: SourceFile) -> u64 {
+    salsa::remote_cache::checksum(file.text(db))
+}
+
+#[salsa::tracked(remote_cache = text_checksum)]
+fn line_count(db: &dyn Db, file: SourceFile) -> usize {
+    file.text(db).lines().count()
+}
+
+#[salsa::tracked]
+struct Word {
+    text: String,
+}
+
+#[salsa::tracked(remote_cache = text_checksum)]
+fn first_word(db: &dyn Db, file: SourceFile) -> String {
+    let text = file.text(db).split_whitespace().next().unwrap_or_default();
+    Word::new(db, text.to_string()).text(db)
+}
+
+#[salsa::tracked(remote_cache = text_checksum)]
+fn file_id(_db: &dyn Db, file: SourceFile) -> salsa::Id {
+    salsa::AsId::as_id(file)
+}
+
+#[derive(Default)]
+struct MemoryCache {
+    values: Mutex<HashMap<CacheKey, Vec<u8>>>,
+}
+
+impl RemoteCache for MemoryCache {
+    fn get(&self, key: &CacheKey) -> Option<Vec<u8>> {
+        self.values.lock().unwrap().get(key).cloned()
+    }
+
+    fn put(&self, key: &CacheKey, value: Vec<u8>) {
+        self.values.lock().unwrap().insert(key.clone(), value);
+    }
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+    cache: Option<Arc<MemoryCache>>,
+}
+
+impl Database {
+    fn with_cache(cache: &Arc<MemoryCache>) -> Self {
+        Database {
+            cache: Some(cache.clone()),
+            ..Default::default()
+        }
+    }
+}
+
+impl salsa::Database for Database {
+    fn remote_cache(&self) -> Option<&dyn RemoteCache> {
+        self.cache.as_deref().map(|cache| cache as &dyn RemoteCache)
+    }
+}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn shared_cache() {
+    let cache = Arc::new(MemoryCache::default());
+
+    let mut db1 = Database::with_cache(&cache);
+    let file = SourceFile::new(&db1, "a b c".to_string());
+    assert_eq!(word_count(&db1, file), 3);
+    db1.assert_logs(expect![[r#"
+        [
+            "word_count(\"a b c\")",
+        ]"#]]);
+
+    // Another database with the same text gets the value from the cache.
+    let mut db2 = Database::with_cache(&cache);
+    let file = SourceFile::new(&db2, "a b c".to_string());
+    assert_eq!(word_count(&db2, file), 3);
+    db2.assert_logs(expect!["[]"]);
+
+    // The value depends on the reads of the checksum function.
+    file.set_text(&mut db2).to("a b".to_string());
+    assert_eq!(word_count(&db2, file), 2);
+    db2.assert_logs(expect![[r#"
+        [
+            "word_count(\"a b\")",
+        ]"#]]);
+    file.set_text(&mut db2).to("a b c".to_string());
+    assert_eq!(word_count(&db2, file), 3);
+    db2.assert_logs(expect!["[]"]);
+}
+
+#[test]
+fn no_cache() {
+    let mut db = Database::default();
+    let file = SourceFile::new(&db, "a b c".to_string());
+    assert_eq!(word_count(&db, file), 3);
+    db.assert_logs(expect![[r#"
+        [
+            "word_count(\"a b c\")",
+        ]"#]]);
+}
+
+#[test]
+fn cache_keys() {
+    let cache = Arc::new(MemoryCache::default());
+    let db = Database::with_cache(&cache);
+    let file = SourceFile::new(&db, "a b c".to_string());
+    word_count(&db, file);
+    line_count(&db, file);
+
+    let mut functions: Vec<_> = cache
+        .values
+        .lock()
+        .unwrap()
+        .keys()
+        .map(|key| key.function.clone())
+        .collect();
+    functions.sort();
+    assert_eq!(
+        functions,
+        ["remote_cache::line_count", "remote_cache::word_count"]
+    );
+}
+
+#[test]
+fn fetch_checksum() {
+    let mut db = Database::default();
+    let file = SourceFile::new(&db, "a b c".to_string());
+    assert_eq!(
+        word_count::fetch_checksum(&db, file),
+        salsa::remote_cache::value_checksum(&3_usize)
+    );
+
+    file.set_text(&mut db).to("a b".to_string());
+    assert_eq!(
+        word_count::fetch_checksum(&db, file),
+        salsa::remote_cache::value_checksum(&2_usize)
+    );
+}
+
+#[test]
+#[should_panic(expected = "cannot create tracked structs")]
+fn creates_entities() {
+    let cache = Arc::new(MemoryCache::default());
+    let db = Database::with_cache(&cache);
+    let file = SourceFile::new(&db, "a b c".to_string());
+    first_word(&db, file);
+}
+
+#[test]
+#[should_panic(expected = "its value cannot contain ids")]
+fn returns_ids() {
+    let cache = Arc::new(MemoryCache::default());
+    let db = Database::with_cache(&cache);
+    let file = SourceFile::new(&db, "a b c".to_string());
+    file_id(&db, file);
+}
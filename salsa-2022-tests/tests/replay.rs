@@ -0,0 +1,136 @@
+//! Test recording the writes and reads of a database with a `Recorder`
+//! and replaying them with `salsa::replay::replay`, which finds the reads
+//! whose incremental result differs from the result from scratch.
+
+use expect_test::expect;
+use salsa::replay::{replay, Recorder, Replay, ReplayError};
+use salsa::{AsId, Id};
+use serde::{Deserialize, Serialize};
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(File, word_count);
+
+trait Db: salsa::DbWithJar<Jar> {
+    /// Some state that salsa does not know about, which is a bug.
+    fn ignore_short_words(&self) -> bool;
+}
+
+#[salsa::input]
+struct File {
+    #[return_ref]
+    text: String,
+}
+
+#[salsa::tracked]
+fn word_count(db: &dyn Db, file: File) -> usize {
+    file.text(db)
+        .split_whitespace()
+        .filter(|word| !db.ignore_short_words() || word.len() > 2)
+        .count()
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    ignore_short_words: bool,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {
+    fn ignore_short_words(&self) -> bool {
+        self.ignore_short_words
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum Write {
+    NewFile(String),
+    SetText(Id, String),
+    IgnoreShortWords,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum Read {
+    WordCount(Id),
+}
+
+impl Replay for Database {
+    type Write = Write;
+    type Read = Read;
+    type Output = usize;
+
+    fn write(&mut self, write: &Write) {
+        match write {
+            Write::NewFile(text) => {
+                File::new(self, text.clone());
+            }
+            Write::SetText(file, text) => {
+                File::from_id(*file).set_text(self).to(text.clone());
+            }
+            Write::IgnoreShortWords => self.ignore_short_words = true,
+        }
+    }
+
+    fn read(&self, read: &Read) -> usize {
+        match read {
+            Read::WordCount(file) => word_count(self, File::from_id(*file)),
+        }
+    }
+}
+
+fn file_id(index: u32) -> Id {
+    Id::from_u32(index)
+}
+
+#[test]
+fn consistent() {
+    let mut recorder = Recorder::<Database, _>::new(vec![]);
+    recorder.write(Write::NewFile("a b c".to_string())).unwrap();
+    recorder.write(Write::NewFile("d".to_string())).unwrap();
+    assert_eq!(recorder.read(Read::WordCount(file_id(0))).unwrap(), 3);
+    recorder
+        .write(Write::SetText(file_id(0), "a b".to_string()))
+        .unwrap();
+    assert_eq!(recorder.read(Read::WordCount(file_id(0))).unwrap(), 2);
+    assert_eq!(recorder.read(Read::WordCount(file_id(1))).unwrap(), 1);
+
+    let (_, log) = recorder.into_inner().unwrap();
+    let log = String::from_utf8(log).unwrap();
+    expect![[r#"
+        {"Write":{"write":{"NewFile":"a b c"},"revision":{"generation":1}}}
+        {"Write":{"write":{"NewFile":"d"},"revision":{"generation":1}}}
+        {"Read":{"read":{"WordCount":{"value":1}},"output":3}}
+        {"Write":{"write":{"SetText":[{"value":1},"a b"]},"revision":{"generation":2}}}
+        {"Read":{"read":{"WordCount":{"value":1}},"output":2}}
+        {"Read":{"read":{"WordCount":{"value":2}},"output":1}}
+    "#]]
+    .assert_eq(&log);
+
+    replay::<Database>(log.as_bytes()).unwrap();
+}
+
+#[test]
+fn stale_result() {
+    let mut recorder = Recorder::<Database, _>::new(vec![]);
+    recorder
+        .write(Write::NewFile("a bb ccc".to_string()))
+        .unwrap();
+    assert_eq!(recorder.read(Read::WordCount(file_id(0))).unwrap(), 3);
+    recorder.write(Write::IgnoreShortWords).unwrap();
+    assert_eq!(recorder.read(Read::WordCount(file_id(0))).unwrap(), 3);
+
+    let (_, log) = recorder.into_inner().unwrap();
+    let error = replay::<Database>(&log[..]).unwrap_err();
+    assert!(matches!(error, ReplayError::Diverged { line: 4, .. }));
+    expect![[r#"line 4: `WordCount(Id { value: 1 })` returned `3` incrementally but `1` from scratch (recorded: `3`)"#]]
+        .assert_eq(&error.to_string());
+}
+
+#[test]
+fn parse_error() {
+    let error = replay::<Database>("{\"Write\":{}}\n".as_bytes()).unwrap_err();
+    assert!(matches!(error, ReplayError::Parse { line: 1, .. }));
+}
@@ -0,0 +1,71 @@
+//! Test `#[salsa::requires]`, which adds the bounds needed to call the
+//! functions of another jar, both from tracked functions and from code
+//! that is generic over the database.
+
+use test_log::test;
+
+mod numbers {
+    #[salsa::jar(db = Db)]
+    pub struct Jar(Number, double);
+
+    pub trait Db: salsa::DbWithJar<Jar> {}
+
+    #[salsa::input(jar = Jar)]
+    pub struct Number {
+        pub value: u32,
+    }
+
+    #[salsa::tracked(jar = Jar)]
+    pub fn double(db: &dyn Db, number: Number) -> u32 {
+        number.value(db) * 2
+    }
+}
+
+mod report {
+    use crate::numbers::{self, Number};
+
+    #[salsa::jar(db = Db)]
+    pub struct Jar(describe);
+
+    pub trait Db: salsa::DbWithJar<Jar> + numbers::Db {}
+
+    #[salsa::tracked(jar = Jar)]
+    #[salsa::requires(numbers::Jar)]
+    pub fn describe(db: &dyn Db, number: Number) -> String {
+        format!(
+            "{} doubled is {}",
+            number.value(db),
+            numbers::double(db, number)
+        )
+    }
+
+    /// Works with any database that has the `numbers` jar.
+    #[salsa::requires(numbers::Jar)]
+    pub fn double_plus_one<DB: ?Sized>(db: &DB, number: Number) -> u32 {
+        numbers::double(salsa::jar::db_for_jar::<numbers::Jar, _>(db), number) + 1
+    }
+}
+
+#[salsa::db(numbers::Jar, report::Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl numbers::Db for Database {}
+
+impl report::Db for Database {}
+
+#[test]
+fn execute() {
+    let mut db = Database::default();
+    let number = numbers::Number::new(&db, 10);
+    assert_eq!(report::describe(&db, number), "10 doubled is 20");
+    assert_eq!(report::double_plus_one(&db, number), 21);
+
+    number.set_value(&mut db).to(20);
+    assert_eq!(report::describe(&db, number), "20 doubled is 40");
+    assert_eq!(report::double_plus_one(&db, number), 41);
+}
@@ -0,0 +1,75 @@
+//! Test that a `RevisionToken` captured with `attach_revision` can be
+//! used to check whether a value derived from the database is stale.
+
+use salsa::{Database as _, Durability};
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, render);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input(jar = Jar)]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::tracked(jar = Jar)]
+fn render(db: &dyn Db, input: MyInput) -> String {
+    format!("<p>{}</p>", input.field(db))
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+#[test]
+fn invalidated_by_writes() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 1);
+
+    let html = render(&db, input);
+    let token = db.attach_revision();
+    assert_eq!(html, "<p>1</p>");
+    assert!(db.is_still_valid(token));
+
+    // Reading does not invalidate the token.
+    assert_eq!(render(&db, input), html);
+    assert!(db.is_still_valid(token));
+
+    input.set_field(&mut db).to(2);
+    assert!(!db.is_still_valid(token));
+
+    let token = db.attach_revision();
+    assert!(db.is_still_valid(token));
+}
+
+#[test]
+fn with_durability() {
+    let mut db = Database::default();
+    let config = MyInput::new(&db, 1);
+    config
+        .set_field(&mut db)
+        .with_durability(Durability::HIGH)
+        .to(1);
+    let file = MyInput::new(&db, 2);
+
+    let token = db.attach_revision().with_durability(Durability::HIGH);
+
+    // Low durability changes do not invalidate the token...
+    file.set_field(&mut db).to(3);
+    assert!(db.is_still_valid(token));
+
+    // ...but high durability ones do.
+    config
+        .set_field(&mut db)
+        .with_durability(Durability::HIGH)
+        .to(2);
+    assert!(!db.is_still_valid(token));
+}
@@ -0,0 +1,131 @@
+//! Test `Storage::set_self_check`: tracked functions are executed twice, and
+//! those returning different values or reading different dependencies the
+//! second time are reported, unless they read untracked state.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, double, counter, flaky_dependency, volatile_counter);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::input]
+struct MyInput {
+    field_a: u32,
+    field_b: u32,
+}
+
+#[salsa::tracked]
+fn double(db: &dyn Db, input: MyInput) -> u32 {
+    db.push_log(format!("double({})", input.field_a(db)));
+    input.field_a(db) * 2
+}
+
+static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+#[salsa::tracked]
+fn counter(db: &dyn Db, input: MyInput) -> u32 {
+    input.field_a(db) + COUNTER.fetch_add(1, Ordering::SeqCst)
+}
+
+static READ_B: AtomicU32 = AtomicU32::new(0);
+
+#[salsa::tracked]
+fn flaky_dependency(db: &dyn Db, input: MyInput) -> u32 {
+    if READ_B.fetch_add(1, Ordering::SeqCst) % 2 == 1 {
+        input.field_b(db);
+    }
+    input.field_a(db)
+}
+
+static VOLATILE_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+#[salsa::tracked]
+fn volatile_counter(db: &dyn Db, input: MyInput) -> u32 {
+    db.report_untracked_read();
+    input.field_a(db) + VOLATILE_COUNTER.fetch_add(1, Ordering::SeqCst)
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+fn self_checked_database() -> Database {
+    let mut db = Database::default();
+    db.storage.set_self_check(true);
+    db
+}
+
+#[test]
+fn deterministic() {
+    let mut db = self_checked_database();
+    let input = MyInput::new(&db, 1, 2);
+    assert_eq!(double(&db, input), 2);
+    db.assert_logs(expect![[r#"
+        [
+            "double(1)",
+            "double(1)",
+        ]"#]]);
+
+    input.set_field_a(&mut db).to(2);
+    assert_eq!(double(&db, input), 4);
+    db.assert_logs(expect![[r#"
+        [
+            "double(2)",
+            "double(2)",
+        ]"#]]);
+
+    // Untracked reads are not checked.
+    volatile_counter(&db, input);
+}
+
+#[test]
+#[should_panic(
+    expected = "self-check: `counter(0)` returned a different value when executed again"
+)]
+fn different_value() {
+    let db = self_checked_database();
+    let input = MyInput::new(&db, 1, 2);
+    counter(&db, input);
+}
+
+#[test]
+#[should_panic(
+    expected = "self-check: `flaky_dependency(0)` read different dependencies \
+                           when executed again ([field_a(0)], \
+                           then [field_b(0), field_a(0)])"
+)]
+fn different_dependencies() {
+    let db = self_checked_database();
+    let input = MyInput::new(&db, 1, 2);
+    flaky_dependency(&db, input);
+}
+
+#[test]
+fn disabled() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 1, 2);
+    assert_eq!(double(&db, input), 2);
+    db.assert_logs(expect![[r#"
+        [
+            "double(1)",
+        ]"#]]);
+}
@@ -0,0 +1,57 @@
+//! Test that identifiers and durabilities can be serialized
+//! and deserialized with the `serde` feature.
+
+use salsa::{AsId, Durability, Id};
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input(jar = Jar)]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+#[test]
+fn round_trip_id() {
+    let db = Database::default();
+    let _ = MyInput::new(&db, 0);
+    let input = MyInput::new(&db, 22);
+
+    let json = serde_json::to_string(&input.as_id()).unwrap();
+    let id: Id = serde_json::from_str(&json).unwrap();
+    let restored = MyInput::from_id(id);
+    assert_eq!(restored, input);
+    assert_eq!(restored.field(&db), 22);
+}
+
+#[test]
+fn round_trip_durability() {
+    for durability in [Durability::LOW, Durability::MEDIUM, Durability::HIGH] {
+        let json = serde_json::to_string(&durability).unwrap();
+        let restored: Durability = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, durability);
+    }
+}
+
+#[test]
+fn reject_invalid_id() {
+    // `Id`s are never zero internally, so a zero must not deserialize.
+    assert!(serde_json::from_str::<Id>(r#"{"value":0}"#).is_err());
+}
+
+#[test]
+fn reject_invalid_durability() {
+    assert!(serde_json::from_str::<Durability>("3").is_err());
+}
@@ -0,0 +1,87 @@
+//! Test `Setter::to_if_changed`: setting an input to an equal value does not
+//! start a new revision, and setting it to another value keeps its durability.
+
+use salsa::{Database as _, Durability};
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, tracked_fn);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::input(jar = Jar)]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::tracked(jar = Jar)]
+fn tracked_fn(db: &dyn Db, input: MyInput) -> u32 {
+    db.push_log(format!("tracked_fn({})", input.field(db)));
+    input.field(db) * 2
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn unchanged() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 22);
+    assert_eq!(tracked_fn(&db, input), 44);
+    db.assert_logs(expect![[r#"
+        [
+            "tracked_fn(22)",
+        ]"#]]);
+
+    let revision = db.current_revision();
+    assert_eq!(input.set_field(&mut db).to_if_changed(22), None);
+    assert_eq!(db.current_revision(), revision);
+
+    assert_eq!(input.set_field(&mut db).to_if_changed(23), Some(22));
+    assert_eq!(db.current_revision(), revision.next());
+    assert_eq!(tracked_fn(&db, input), 46);
+    db.assert_logs(expect![[r#"
+        [
+            "tracked_fn(23)",
+        ]"#]]);
+}
+
+#[test]
+fn keep_durability() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 22);
+    input
+        .set_field(&mut db)
+        .with_durability(Durability::HIGH)
+        .to(22);
+    assert_eq!(input.set_field(&mut db).to_if_changed(23), Some(22));
+
+    // The field is still high durability, so writing it again
+    // invalidates the values that only depend on such inputs.
+    let token = db.attach_revision().with_durability(Durability::HIGH);
+    assert_eq!(input.set_field(&mut db).to_if_changed(24), Some(23));
+    assert!(!db.is_still_valid(token));
+
+    // Another durability can still be given explicitly, even for an equal value.
+    let revision = db.current_revision();
+    let setter = input.set_field(&mut db).with_durability(Durability::LOW);
+    assert_eq!(setter.to_if_changed(24), Some(24));
+    assert_eq!(db.current_revision(), revision.next());
+}
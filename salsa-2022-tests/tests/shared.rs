@@ -0,0 +1,105 @@
+//! Test `salsa::Shared`: equal values created by different queries share one
+//! allocation, and a query whose shared value is recreated equal is backdated.
+
+use salsa::Shared;
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, sorted, deduplicated, total);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::input]
+struct MyInput {
+    #[return_ref]
+    numbers: Vec<u32>,
+}
+
+#[salsa::tracked]
+fn sorted(db: &dyn Db, input: MyInput) -> Shared<Vec<u32>> {
+    let mut numbers = input.numbers(db).clone();
+    numbers.sort();
+    salsa::shared(db, numbers)
+}
+
+#[salsa::tracked]
+fn deduplicated(db: &dyn Db, input: MyInput) -> Shared<Vec<u32>> {
+    let mut numbers = input.numbers(db).clone();
+    numbers.sort();
+    numbers.dedup();
+    Shared::new(db, numbers)
+}
+
+#[salsa::tracked]
+fn total(db: &dyn Db, input: MyInput) -> u32 {
+    let sorted = sorted(db, input);
+    db.push_log(format!("total({:?})", sorted));
+    sorted.iter().sum()
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn equal_values_are_shared() {
+    let db = Database::default();
+    let input = MyInput::new(&db, vec![3, 1, 2]);
+    let sorted = sorted(&db, input);
+    let deduplicated = deduplicated(&db, input);
+    assert_eq!(*sorted, vec![1, 2, 3]);
+    assert_eq!(sorted, deduplicated);
+    assert!(std::ptr::eq(&*sorted, &*deduplicated));
+    assert_eq!(Shared::new(&db, vec![1, 2, 3]), sorted);
+    assert_ne!(Shared::new(&db, vec![1, 2]), sorted);
+}
+
+#[test]
+fn backdated_when_recreated_equal() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, vec![3, 1, 2]);
+    assert_eq!(total(&db, input), 6);
+    db.assert_logs(expect![[r#"
+        [
+            "total([1, 2, 3])",
+        ]"#]]);
+
+    // `sorted` creates a new vector, equal to the old one, so `total` is not executed again.
+    input.set_numbers(&mut db).to(vec![2, 3, 1]);
+    assert_eq!(total(&db, input), 6);
+    db.assert_logs(expect!["[]"]);
+
+    input.set_numbers(&mut db).to(vec![2, 3, 4]);
+    assert_eq!(total(&db, input), 9);
+    db.assert_logs(expect![[r#"
+        [
+            "total([2, 3, 4])",
+        ]"#]]);
+}
+
+#[test]
+fn sweep_keeps_used_values() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, vec![2, 1]);
+    let kept = sorted(&db, input);
+    Shared::new(&db, vec![5, 6]);
+
+    db.storage.sweep(0);
+    assert_eq!(Shared::new(&db, vec![1, 2]), kept);
+}
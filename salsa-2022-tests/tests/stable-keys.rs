@@ -0,0 +1,120 @@
+//! Test `Database::stable_key` and `Database::resolve_stable_key`:
+//! keys made with a database resolve to the same queries in a database
+//! that creates the ingredients in another order, and the fields of
+//! different structs with the same name get different names.
+
+use salsa::key::StableKey;
+use salsa::{Database as _, DatabaseKeyIndex, DebugWithDb};
+use test_log::test;
+
+#[salsa::jar(db = Db1)]
+struct Jar1(Source, Target, length);
+
+trait Db1: salsa::DbWithJar<Jar1> {}
+
+#[salsa::input(jar = Jar1)]
+struct Source {
+    text: String,
+}
+
+#[salsa::input(jar = Jar1)]
+struct Target {
+    text: String,
+}
+
+#[salsa::tracked(jar = Jar1)]
+fn length(db: &dyn Db1, source: Source) -> usize {
+    source.text(db).len()
+}
+
+#[salsa::jar(db = Db2)]
+struct Jar2(double);
+
+trait Db2: salsa::DbWithJar<Jar2> {}
+
+#[salsa::tracked(jar = Jar2)]
+fn double(_db: &dyn Db2) -> u32 {
+    2
+}
+
+#[salsa::db(Jar1, Jar2)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db1 for Database {}
+
+impl Db2 for Database {}
+
+#[salsa::db(Jar2, Jar1)]
+#[derive(Default)]
+struct ReorderedDatabase {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for ReorderedDatabase {}
+
+impl Db1 for ReorderedDatabase {}
+
+impl Db2 for ReorderedDatabase {}
+
+#[test]
+fn resolve_in_reordered_database() {
+    let db = Database::default();
+    let reordered = ReorderedDatabase::default();
+    let source = Source::new(&db, "abc".to_string());
+    assert_eq!(Source::new(&reordered, "abc".to_string()), source);
+    assert_eq!(length(&db, source), 3);
+    assert_eq!(double(&reordered), 2);
+
+    let key = StableKey {
+        ingredient: format!("{}::length", std::any::type_name::<Jar1>()),
+        key: Some(salsa::AsId::as_id(source)),
+    };
+    let index = db.resolve_stable_key(&key).unwrap();
+    let reordered_index = reordered.resolve_stable_key(&key).unwrap();
+    assert_ne!(index.ingredient_index(), reordered_index.ingredient_index());
+    assert_eq!(format!("{:?}", index.debug(&db)), "length(0)");
+    assert_eq!(
+        format!("{:?}", reordered_index.debug(&reordered)),
+        "length(0)"
+    );
+
+    // The keys of queries convert both ways.
+    let query = DatabaseKeyIndex::try_from(reordered_index).unwrap();
+    assert_eq!(reordered.stable_key(query.into()), key);
+}
+
+#[test]
+fn field_names_include_struct() {
+    let db = Database::default();
+    let source = Source::new(&db, "abc".to_string());
+    let target = Target::new(&db, "def".to_string());
+
+    let jar1 = std::any::type_name::<Jar1>();
+    let source_text = StableKey {
+        ingredient: format!("{jar1}::Source::text"),
+        key: Some(salsa::AsId::as_id(source)),
+    };
+    let target_text = StableKey {
+        ingredient: format!("{jar1}::Target::text"),
+        key: Some(salsa::AsId::as_id(target)),
+    };
+    let source_index = db.resolve_stable_key(&source_text).unwrap();
+    let target_index = db.resolve_stable_key(&target_text).unwrap();
+    assert_ne!(
+        source_index.ingredient_index(),
+        target_index.ingredient_index()
+    );
+    assert_eq!(db.stable_key(source_index), source_text);
+    assert_eq!(db.stable_key(target_index), target_text);
+
+    let missing = StableKey {
+        ingredient: format!("{jar1}::missing"),
+        key: None,
+    };
+    assert_eq!(db.resolve_stable_key(&missing), None);
+}
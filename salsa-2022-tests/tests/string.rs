@@ -0,0 +1,69 @@
+//! Test the interned strings of `salsa::string`, used from
+//! the tracked functions of another jar.
+
+use salsa::string::{StaticStr, Str};
+use salsa::DebugWithDb;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, identifiers);
+
+trait Db: salsa::DbWithJar<Jar> + salsa::string::Db {}
+
+#[salsa::input]
+struct MyInput {
+    #[return_ref]
+    text: String,
+}
+
+#[salsa::tracked]
+fn identifiers(db: &dyn Db, input: MyInput) -> Vec<Str> {
+    input
+        .text(db)
+        .split_whitespace()
+        .map(|word| Str::new(db, word.to_string()))
+        .collect()
+}
+
+#[salsa::db(Jar, salsa::string::Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+#[test]
+fn str() {
+    let db = Database::default();
+    let input = MyInput::new(&db, "foo bar foo".to_string());
+    let ids = identifiers(&db, input);
+    assert_eq!(ids[0], ids[2]);
+    assert_ne!(ids[0], ids[1]);
+    assert_eq!(ids[1].resolve(&db), "bar");
+    assert_eq!(ids[1].display(&db).to_string(), "bar");
+    assert_eq!(Str::lookup(&db, "foo".to_string()), Some(ids[0]));
+
+    // Ordered by intern order, not alphabetically.
+    assert!(ids[0] < ids[1]);
+
+    assert_eq!(
+        format!("{:?}", ids[1].debug(&db)),
+        r#"Str { [salsa id]: 1, text: "bar" }"#
+    );
+}
+
+#[test]
+fn static_str() {
+    let db = Database::default();
+    let literal = StaticStr::new(&db, "foo");
+    let text = String::from("foo");
+    assert_eq!(StaticStr::leak(&db, &text), literal);
+
+    let leaked = StaticStr::leak(&db, &String::from("bar"));
+    let resolved: &'static str = leaked.resolve(&db);
+    drop(db);
+    assert_eq!(resolved, "bar");
+}
@@ -0,0 +1,101 @@
+//! Test `Database::subscribe`:
+//!
+//! * the callback is called when the first tracked function called in a new
+//!   revision returns, only if the value of the subscribed query changed;
+//! * `refresh_subscriptions` refreshes them explicitly;
+//! * `unsubscribe` removes a subscription.
+
+use std::sync::mpsc::{channel, Receiver};
+
+use salsa::Database as _;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, line_count, first_line);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input]
+struct MyInput {
+    #[return_ref]
+    text: String,
+}
+
+#[salsa::tracked]
+fn line_count(db: &dyn Db, input: MyInput) -> usize {
+    input.text(db).lines().count()
+}
+
+#[salsa::tracked]
+fn first_line(db: &dyn Db, input: MyInput) -> String {
+    input
+        .text(db)
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+fn received<T>(receiver: &Receiver<T>) -> Vec<T> {
+    receiver.try_iter().collect()
+}
+
+#[test]
+fn callback_on_change() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, "a\nb".to_string());
+
+    let (sender, receiver) = channel();
+    db.subscribe(
+        |db, input| line_count(db, input),
+        input,
+        move |count| sender.send(*count).unwrap(),
+    );
+    assert_eq!(received(&receiver), Vec::<usize>::new());
+
+    // Calling another function in the new revision refreshes the subscription.
+    input.set_text(&mut db).to("a\nb\nc".to_string());
+    assert_eq!(first_line(&db, input), "a");
+    assert_eq!(received(&receiver), vec![3]);
+
+    // Only once per revision.
+    line_count(&db, input);
+    assert_eq!(received(&receiver), Vec::<usize>::new());
+
+    // The line count did not change.
+    input.set_text(&mut db).to("d\ne\nf".to_string());
+    assert_eq!(first_line(&db, input), "d");
+    assert_eq!(received(&receiver), Vec::<usize>::new());
+}
+
+#[test]
+fn refresh_and_unsubscribe() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, "a".to_string());
+
+    let (sender, receiver) = channel();
+    let id = db.subscribe(
+        |db, input| first_line(db, input),
+        input,
+        move |line: &String| sender.send(line.clone()).unwrap(),
+    );
+
+    input.set_text(&mut db).to("b".to_string());
+    db.refresh_subscriptions();
+    assert_eq!(received(&receiver), vec!["b".to_string()]);
+
+    db.unsubscribe(id);
+    input.set_text(&mut db).to("c".to_string());
+    db.refresh_subscriptions();
+    assert_eq!(received(&receiver), Vec::<String>::new());
+}
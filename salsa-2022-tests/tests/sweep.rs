@@ -0,0 +1,278 @@
+//! Test that `Storage::sweep` discards the values of memos that
+//! were not verified recently, while keeping the others, and deletes
+//! the memos that no recently verified memo depends on, with the
+//! tracked structs they created.
+
+use salsa::tracked_struct::TrackedStructInDb;
+use salsa::Durability;
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(
+    MyInput,
+    tracked_fn,
+    outer_fn,
+    MyTracked,
+    create_tracked_structs,
+    tracked_double,
+);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::input(jar = Jar)]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::tracked(jar = Jar)]
+fn tracked_fn(db: &dyn Db, input: MyInput) -> u32 {
+    db.push_log(format!("tracked_fn({})", input.field(db)));
+    input.field(db) * 2
+}
+
+#[salsa::tracked(jar = Jar)]
+fn outer_fn(db: &dyn Db, input: MyInput) -> u32 {
+    db.push_log(format!("outer_fn({})", input.field(db)));
+    tracked_fn(db, input) + 1
+}
+
+#[salsa::tracked(jar = Jar)]
+struct MyTracked {
+    value: u32,
+}
+
+#[salsa::tracked(jar = Jar)]
+fn create_tracked_structs(db: &dyn Db, input: MyInput) -> Vec<MyTracked> {
+    (0..input.field(db))
+        .map(|i| MyTracked::new(db, i))
+        .collect()
+}
+
+#[salsa::tracked(jar = Jar)]
+fn tracked_double(db: &dyn Db, tracked: MyTracked) -> u32 {
+    tracked.value(db) * 2
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl Database {
+    fn sweep(&mut self, max_age: usize) {
+        self.storage.sweep(max_age);
+    }
+
+    /// The number of memos of `tracked_fn`, and how many have a value.
+    fn tracked_fn_memos(&self) -> (usize, usize) {
+        let report = salsa::Database::memory_report(self);
+        let memory = &report.ingredients[0];
+        assert_eq!(memory.ingredient, "tracked_fn");
+        (memory.memos, memory.values)
+    }
+
+    /// The number of memos of `tracked_double`.
+    fn tracked_double_memos(&self) -> usize {
+        let report = salsa::Database::memory_report(self);
+        report
+            .ingredients
+            .iter()
+            .find(|memory| memory.ingredient == "tracked_double")
+            .unwrap()
+            .memos
+    }
+
+    /// Whether `tracked` still exists.
+    fn exists(&self, tracked: MyTracked) -> bool {
+        let key = tracked.database_key_index(self);
+        self.storage.creator_of(key).is_some()
+    }
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn sweep_unverified_values() {
+    let mut db = Database::default();
+    let a = MyInput::new(&db, 1);
+    let b = MyInput::new(&db, 2);
+    let c = MyInput::new(&db, 3);
+
+    assert_eq!(tracked_fn(&db, a), 2);
+    assert_eq!(tracked_fn(&db, b), 4);
+    db.assert_logs(expect![[r#"
+        [
+            "tracked_fn(1)",
+            "tracked_fn(2)",
+        ]"#]]);
+
+    // Start a new revision in which only `tracked_fn(a)` is verified.
+    c.set_field(&mut db).to(4);
+    assert_eq!(tracked_fn(&db, a), 2);
+    db.assert_logs(expect!["[]"]);
+
+    // Sweeping discards the value of `tracked_fn(b)`, so it has to be recomputed,
+    // while `tracked_fn(a)` is reused.
+    db.sweep(0);
+    assert_eq!(tracked_fn(&db, a), 2);
+    assert_eq!(tracked_fn(&db, b), 4);
+    db.assert_logs(expect![[r#"
+        [
+            "tracked_fn(2)",
+        ]"#]]);
+}
+
+#[test]
+fn sweep_keeps_values_within_max_age() {
+    let mut db = Database::default();
+    let a = MyInput::new(&db, 1);
+    let b = MyInput::new(&db, 2);
+
+    assert_eq!(tracked_fn(&db, a), 2);
+    db.assert_logs(expect![[r#"
+        [
+            "tracked_fn(1)",
+        ]"#]]);
+
+    b.set_field(&mut db).to(3);
+    db.sweep(1);
+    assert_eq!(tracked_fn(&db, a), 2);
+    db.assert_logs(expect!["[]"]);
+}
+
+#[test]
+fn sweep_deletes_unreachable_memos() {
+    let mut db = Database::default();
+    let a = MyInput::new(&db, 1);
+    let b = MyInput::new(&db, 2);
+
+    assert_eq!(tracked_fn(&db, a), 2);
+    assert_eq!(tracked_fn(&db, b), 4);
+    assert_eq!(db.tracked_fn_memos(), (2, 2));
+
+    // Only `tracked_fn(a)` is verified in the new revision, and nothing
+    // depends on `tracked_fn(b)`, whose memo is deleted.
+    b.set_field(&mut db).to(3);
+    assert_eq!(tracked_fn(&db, a), 2);
+    db.sweep(0);
+    assert_eq!(db.tracked_fn_memos(), (1, 1));
+
+    assert_eq!(tracked_fn(&db, b), 6);
+    assert_eq!(db.tracked_fn_memos(), (2, 2));
+    db.assert_logs(expect![[r#"
+        [
+            "tracked_fn(1)",
+            "tracked_fn(2)",
+            "tracked_fn(3)",
+        ]"#]]);
+}
+
+#[test]
+fn sweep_keeps_memos_of_dependencies() {
+    let mut db = Database::default();
+    let a = MyInput::new(&db, 1);
+    a.set_field(&mut db).with_durability(Durability::HIGH).to(1);
+    let b = MyInput::new(&db, 2);
+
+    assert_eq!(outer_fn(&db, a), 3);
+    db.assert_logs(expect![[r#"
+        [
+            "outer_fn(1)",
+            "tracked_fn(1)",
+        ]"#]]);
+
+    // `outer_fn(a)` only depends on high durability inputs, so it is verified
+    // in the new revision without verifying `tracked_fn(a)`: the value of
+    // `tracked_fn(a)` is discarded, but its memo is kept, since `outer_fn(a)`
+    // depends on it.
+    b.set_field(&mut db).to(3);
+    assert_eq!(outer_fn(&db, a), 3);
+    db.sweep(0);
+    assert_eq!(db.tracked_fn_memos(), (1, 0));
+
+    // After a change to another high durability input, `outer_fn(a)` is
+    // verified with the memo of `tracked_fn(a)`, without executing either.
+    let c = MyInput::new(&db, 4);
+    c.set_field(&mut db).with_durability(Durability::HIGH).to(5);
+    assert_eq!(outer_fn(&db, a), 3);
+    db.assert_logs(expect!["[]"]);
+    assert_eq!(tracked_fn(&db, a), 2);
+    db.assert_logs(expect![[r#"
+        [
+            "tracked_fn(1)",
+        ]"#]]);
+}
+
+/// Creates two tracked structs, with a memo keyed by each, then starts a
+/// new revision in which the query creating them is not called.
+fn structs_no_longer_created(db: &mut Database) -> (MyInput, Vec<MyTracked>) {
+    let a = MyInput::new(db, 2);
+    let b = MyInput::new(db, 0);
+    let structs = create_tracked_structs(db, a);
+    assert_eq!(tracked_double(db, structs[1]), 2);
+    assert_eq!(db.tracked_double_memos(), 1);
+
+    b.set_field(db).to(1);
+    (a, structs)
+}
+
+#[test]
+fn sweep_deletes_tracked_structs() {
+    let mut db = Database::default();
+    let (a, structs) = structs_no_longer_created(&mut db);
+    assert!(structs.iter().all(|&tracked| db.exists(tracked)));
+
+    // Nothing verified in the new revision depends on `create_tracked_structs(a)`:
+    // its memo is deleted, with the structs it created and the memos keyed by them.
+    db.sweep(0);
+    assert!(structs.iter().all(|&tracked| !db.exists(tracked)));
+    assert_eq!(db.tracked_double_memos(), 0);
+
+    // Calling the query again creates new structs.
+    let recreated = create_tracked_structs(&db, a);
+    assert_ne!(recreated, structs);
+    assert_eq!(tracked_double(&db, recreated[1]), 2);
+}
+
+#[test]
+#[should_panic(expected = "`MyTracked(1)` was deleted: a sweep deleted the memo of \
+                           `create_tracked_structs(0)`, which created it")]
+fn read_swept_tracked_struct() {
+    let mut db = Database::default();
+    let (_, structs) = structs_no_longer_created(&mut db);
+    db.sweep(0);
+    structs[1].value(&db);
+}
+
+#[test]
+fn sweep_keeps_tracked_structs_of_kept_memos() {
+    let mut db = Database::default();
+    let (a, structs) = structs_no_longer_created(&mut db);
+
+    // `create_tracked_structs(a)` is verified in the new revision, so the structs
+    // it created are kept, with the memos keyed by them that are verified as well.
+    assert_eq!(create_tracked_structs(&db, a), structs);
+    assert_eq!(tracked_double(&db, structs[1]), 2);
+    db.sweep(0);
+    assert!(structs.iter().all(|&tracked| db.exists(tracked)));
+    assert_eq!(db.tracked_double_memos(), 1);
+
+    // Like a write, the sweep started a new revision, in which the structs are
+    // obtained again from the query that created them.
+    assert_eq!(create_tracked_structs(&db, a), structs);
+    assert_eq!(structs[1].value(&db), 1);
+    assert_eq!(tracked_double(&db, structs[1]), 2);
+}
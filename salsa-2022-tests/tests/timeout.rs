@@ -0,0 +1,83 @@
+//! Test `Database::with_timeout`: a query that runs for too long is cancelled,
+//! while queries that complete in time return their value, and the timeout
+//! no longer applies once `with_timeout` returns.
+
+use std::time::Duration;
+
+use salsa::{Cancelled, Database as _};
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, tick, endless, double);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::tracked]
+fn tick(db: &dyn Db, input: MyInput) -> u32 {
+    input.field(db)
+}
+
+/// Never returns, but calls a query regularly.
+#[salsa::tracked]
+fn endless(db: &dyn Db, input: MyInput) -> u32 {
+    loop {
+        std::thread::sleep(Duration::from_millis(1));
+        tick(db, input);
+    }
+}
+
+#[salsa::tracked]
+fn double(db: &dyn Db, input: MyInput) -> u32 {
+    tick(db, input) * 2
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+#[test]
+fn endless_query_times_out() {
+    let db = Database::default();
+    let input = MyInput::new(&db, 21);
+
+    let result = db.with_timeout(Duration::from_millis(20), || endless(&db, input));
+    let Err(Cancelled::TimedOut { query_stack, .. }) = result else {
+        panic!("expected a timeout, got {result:?}");
+    };
+    assert_eq!(query_stack[0].to_string(), "endless(0)");
+
+    // Without the timeout, the other queries still work.
+    assert_eq!(double(&db, input), 42);
+}
+
+#[test]
+fn query_completes_in_time() {
+    let db = Database::default();
+    let input = MyInput::new(&db, 21);
+
+    let result = db.with_timeout(Duration::from_secs(60), || double(&db, input));
+    assert_eq!(result.unwrap(), 42);
+}
+
+#[test]
+fn nested_timeouts() {
+    let db = Database::default();
+    let input = MyInput::new(&db, 21);
+
+    // The outer deadline is earlier, so it applies to the inner call too.
+    let result = db.with_timeout(Duration::from_millis(20), || {
+        db.with_timeout(Duration::from_secs(60), || endless(&db, input))
+    });
+    assert!(matches!(result, Ok(Err(Cancelled::TimedOut { .. }))));
+}
@@ -0,0 +1,162 @@
+//! Test that, with the `tracing` feature, each execution of a tracked
+//! function is wrapped in a span recording whether it was backdated,
+//! and that cycles are reported as events.
+
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+
+use expect_test::expect;
+use test_log::test;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::Layer;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, is_large, describe, cyclic);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::tracked]
+fn is_large(db: &dyn Db, input: MyInput) -> bool {
+    input.field(db) >= 10
+}
+
+#[salsa::tracked]
+fn describe(db: &dyn Db, input: MyInput) -> &'static str {
+    if is_large(db, input) {
+        "large"
+    } else {
+        "small"
+    }
+}
+
+#[salsa::tracked(recovery_fn = recover_cyclic)]
+fn cyclic(db: &dyn Db, input: MyInput) -> u32 {
+    cyclic(db, input)
+}
+
+fn recover_cyclic(_db: &dyn Db, _cycle: &salsa::Cycle, _input: MyInput) -> u32 {
+    0
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+/// Records the new spans, the values recorded on them later and the events, one line each.
+#[derive(Clone, Default)]
+struct Recorder {
+    lines: Arc<Mutex<Vec<String>>>,
+}
+
+impl Recorder {
+    fn take(&self) -> Vec<String> {
+        std::mem::take(&mut *self.lines.lock().unwrap())
+    }
+}
+
+struct FieldsVisitor(String);
+
+impl Visit for FieldsVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        write!(self.0, " {}={:?}", field.name(), value).unwrap();
+    }
+}
+
+impl<S: Subscriber> Layer<S> for Recorder {
+    fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: Context<'_, S>) {
+        let mut visitor = FieldsVisitor(format!("span {}:", attrs.metadata().name()));
+        attrs.record(&mut visitor);
+        self.lines.lock().unwrap().push(visitor.0);
+    }
+
+    fn on_record(&self, _id: &Id, values: &Record<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = FieldsVisitor("record:".to_string());
+        values.record(&mut visitor);
+        self.lines.lock().unwrap().push(visitor.0);
+    }
+
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = FieldsVisitor("event:".to_string());
+        event.record(&mut visitor);
+        self.lines.lock().unwrap().push(visitor.0);
+    }
+}
+
+fn with_recorder(op: impl FnOnce(&Recorder)) {
+    let recorder = Recorder::default();
+    let subscriber = tracing_subscriber::registry().with(recorder.clone());
+    tracing::subscriber::with_default(subscriber, || op(&recorder));
+}
+
+#[test]
+fn execute() {
+    with_recorder(|recorder| {
+        let mut db = Database::default();
+        let input = MyInput::new(&db, 2);
+
+        assert_eq!(describe(&db, input), "small");
+        expect![[r#"
+            [
+                "span execute: query=\"describe\" key=describe(0)",
+                "span execute: query=\"is_large\" key=is_large(0)",
+            ]
+        "#]]
+        .assert_debug_eq(&recorder.take());
+
+        // `is_large` is executed again but its value is backdated,
+        // so `describe` is not executed.
+        input.set_field(&mut db).to(4);
+        assert_eq!(describe(&db, input), "small");
+        expect![[r#"
+            [
+                "span execute: query=\"is_large\" key=is_large(0)",
+                "record: backdated=true",
+            ]
+        "#]]
+        .assert_debug_eq(&recorder.take());
+
+        input.set_field(&mut db).to(20);
+        assert_eq!(describe(&db, input), "large");
+        expect![[r#"
+            [
+                "span execute: query=\"is_large\" key=is_large(0)",
+                "record: backdated=false",
+                "span execute: query=\"describe\" key=describe(0)",
+                "record: backdated=false",
+            ]
+        "#]]
+        .assert_debug_eq(&recorder.take());
+    });
+}
+
+#[test]
+fn cycle() {
+    with_recorder(|recorder| {
+        let db = Database::default();
+        let input = MyInput::new(&db, 2);
+
+        assert_eq!(cyclic(&db, input), 0);
+        expect![[r#"
+            [
+                "span execute: query=\"cyclic\" key=cyclic(0)",
+                "event: message=cycle detected participants=[\"cyclic(0)\"]",
+                "event: message=recovering from cycle key=cyclic(0)",
+            ]
+        "#]]
+        .assert_debug_eq(&recorder.take());
+    });
+}
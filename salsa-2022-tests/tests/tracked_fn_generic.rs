@@ -0,0 +1,148 @@
+//! Test a generic tracked function instantiated for a set of types with
+//! the `for` option: each instance is a separate ingredient.
+
+use std::marker::PhantomData;
+
+use expect_test::expect;
+use salsa_2022_tests::{HasLogger, Logger};
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(Function, lower_Wasm, lower_X86, lines_Wasm);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+trait Target: Clone + std::fmt::Debug + Eq {
+    const NAME: &'static str;
+
+    fn lower(op: u32) -> String;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Wasm;
+
+impl Target for Wasm {
+    const NAME: &'static str = "wasm";
+
+    fn lower(op: u32) -> String {
+        format!("i32.const {op}")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct X86;
+
+impl Target for X86 {
+    const NAME: &'static str = "x86";
+
+    fn lower(op: u32) -> String {
+        format!("mov eax, {op}")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Arm;
+
+impl Target for Arm {
+    const NAME: &'static str = "arm";
+
+    fn lower(op: u32) -> String {
+        format!("mov r0, #{op}")
+    }
+}
+
+#[salsa::input]
+struct Function {
+    ops: Vec<u32>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Ir<T: Target> {
+    lines: Vec<String>,
+    target: PhantomData<T>,
+}
+
+#[salsa::tracked(for(Wasm, X86))]
+fn lower<T: Target>(db: &dyn Db, function: Function) -> Ir<T> {
+    db.push_log(format!("lower::<{}>({:?})", T::NAME, function));
+    Ir {
+        lines: function.ops(db).iter().map(|&op| T::lower(op)).collect(),
+        target: PhantomData::<T>,
+    }
+}
+
+#[salsa::tracked(return_ref, for(Wasm))]
+fn lines<T: Target>(db: &dyn Db, function: Function) -> Vec<String> {
+    lower::<T>(db, function).lines
+}
+
+/// Generic code calls the generic function, which dispatches to its instances.
+fn line_count<T: Target + 'static>(db: &dyn Db, function: Function) -> usize {
+    lower::<T>(db, function).lines.len()
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn execute() {
+    let mut db = Database::default();
+    let function = Function::new(&db, vec![1, 2]);
+
+    assert_eq!(
+        lower::<Wasm>(&db, function).lines,
+        ["i32.const 1", "i32.const 2"]
+    );
+    assert_eq!(
+        lower::<X86>(&db, function).lines,
+        ["mov eax, 1", "mov eax, 2"]
+    );
+    assert_eq!(
+        lines::<Wasm>(&db, function),
+        &["i32.const 1", "i32.const 2"]
+    );
+    assert_eq!(line_count::<X86>(&db, function), 2);
+    db.assert_logs(expect![[r#"
+        [
+            "lower::<wasm>(Function(Id { value: 1 }))",
+            "lower::<x86>(Function(Id { value: 1 }))",
+        ]"#]]);
+
+    // Each instance is memoized separately.
+    function.set_ops(&mut db).to(vec![3]);
+    assert_eq!(line_count::<Wasm>(&db, function), 1);
+    db.assert_logs(expect![[r#"
+        [
+            "lower::<wasm>(Function(Id { value: 1 }))",
+        ]"#]]);
+    assert_eq!(lines::<Wasm>(&db, function), &["i32.const 3"]);
+    assert_eq!(lower::<X86>(&db, function).lines, ["mov eax, 3"]);
+    db.assert_logs(expect![[r#"
+        [
+            "lower::<x86>(Function(Id { value: 1 }))",
+        ]"#]]);
+}
+
+#[test]
+#[should_panic(expected = "`lower` is not instantiated for `tracked_fn_generic::Arm`")]
+fn not_instantiated() {
+    let db = Database::default();
+    let function = Function::new(&db, vec![1]);
+    assert_eq!(Arm::NAME, "arm");
+    assert_eq!(Arm::lower(1), "mov r0, #1");
+    line_count::<Arm>(&db, function);
+}
@@ -0,0 +1,98 @@
+//! Test that a `tracked` fn taking extra arguments besides
+//! the salsa struct memoizes on all of its arguments.
+
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, tracked_fn, MyInput_tracked_method);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::input(jar = Jar)]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::tracked(jar = Jar)]
+fn tracked_fn(db: &dyn Db, input: MyInput, extra: u32, flag: bool) -> u32 {
+    db.push_log(format!("tracked_fn({:?}, {}, {})", input, extra, flag));
+    if flag {
+        input.field(db) + extra
+    } else {
+        input.field(db) * extra
+    }
+}
+
+#[salsa::tracked(jar = Jar)]
+impl MyInput {
+    #[salsa::tracked]
+    fn tracked_method(self, db: &dyn Db, extra: u32) -> u32 {
+        let field = self.field(db);
+        db.push_log(format!("tracked_method({}, {})", field, extra));
+        field - extra
+    }
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn execute() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 22);
+
+    assert_eq!(tracked_fn(&db, input, 2, true), 24);
+    assert_eq!(tracked_fn(&db, input, 2, false), 44);
+    assert_eq!(tracked_fn(&db, input, 3, false), 66);
+    db.assert_logs(expect![[r#"
+        [
+            "tracked_fn(MyInput(Id { value: 1 }), 2, true)",
+            "tracked_fn(MyInput(Id { value: 1 }), 2, false)",
+            "tracked_fn(MyInput(Id { value: 1 }), 3, false)",
+        ]"#]]);
+
+    // Same arguments: memoized values are reused.
+    assert_eq!(tracked_fn(&db, input, 2, true), 24);
+    assert_eq!(tracked_fn(&db, input, 3, false), 66);
+    db.assert_logs(expect!["[]"]);
+
+    // Changing the input re-executes only the keys that are demanded.
+    input.set_field(&mut db).to(10);
+    assert_eq!(tracked_fn(&db, input, 2, true), 12);
+    db.assert_logs(expect![[r#"
+        [
+            "tracked_fn(MyInput(Id { value: 1 }), 2, true)",
+        ]"#]]);
+}
+
+#[test]
+fn tracked_method() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 22);
+
+    assert_eq!(input.tracked_method(&db, 2), 20);
+    assert_eq!(input.tracked_method(&db, 2), 20);
+    assert_eq!(input.tracked_method(&db, 5), 17);
+    db.assert_logs(expect![[r#"
+        [
+            "tracked_method(22, 2)",
+            "tracked_method(22, 5)",
+        ]"#]]);
+}
@@ -0,0 +1,88 @@
+//! Test that a `tracked` fn can take several salsa structs as arguments
+//! without having to define an interned struct to use as its key.
+
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, MyInterned, MyTracked, create_tracked, combine);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::input(jar = Jar)]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::interned(jar = Jar)]
+struct MyInterned {
+    field: u32,
+}
+
+#[salsa::tracked(jar = Jar)]
+struct MyTracked {
+    field: u32,
+}
+
+#[salsa::tracked(jar = Jar)]
+fn create_tracked(db: &dyn Db, input: MyInput) -> MyTracked {
+    MyTracked::new(db, input.field(db) * 10)
+}
+
+#[salsa::tracked(jar = Jar)]
+fn combine(db: &dyn Db, a: MyInput, b: MyInterned, c: MyTracked) -> u32 {
+    db.push_log(format!(
+        "combine({}, {}, {})",
+        a.field(db),
+        b.field(db),
+        c.field(db)
+    ));
+    a.field(db) + b.field(db) + c.field(db)
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn execute() {
+    let mut db = Database::default();
+    let a1 = MyInput::new(&db, 1);
+    let a2 = MyInput::new(&db, 2);
+    let b = MyInterned::new(&db, 100);
+    let c = create_tracked(&db, a1);
+
+    assert_eq!(combine(&db, a1, b, c), 111);
+    assert_eq!(combine(&db, a2, b, c), 112);
+    assert_eq!(combine(&db, a1, b, c), 111);
+    db.assert_logs(expect![[r#"
+        [
+            "combine(1, 100, 10)",
+            "combine(2, 100, 10)",
+        ]"#]]);
+
+    // Only the combination reading `a2` is affected by changing it.
+    a2.set_field(&mut db).to(3);
+    let c = create_tracked(&db, a1);
+    assert_eq!(combine(&db, a1, b, c), 111);
+    assert_eq!(combine(&db, a2, b, c), 113);
+    db.assert_logs(expect![[r#"
+        [
+            "combine(3, 100, 10)",
+        ]"#]]);
+}
@@ -0,0 +1,86 @@
+//! Test `tracked` fns whose only key is plain data rather than a salsa struct:
+//! the key is interned into an id, and each key memoizes its own value.
+
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(Prefix, greeting, repeated);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::input(singleton)]
+struct Prefix {
+    text: String,
+}
+
+#[salsa::tracked]
+fn greeting(db: &dyn Db, name: String) -> String {
+    db.push_log(format!("greeting({name:?})"));
+    format!("{} {name}", Prefix::get(db).text(db))
+}
+
+#[salsa::tracked]
+fn repeated(db: &dyn Db, key: (u32, String)) -> String {
+    db.push_log(format!("repeated({key:?})"));
+    let (count, name) = key;
+    vec![greeting(db, name); count as usize].join(", ")
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn execute() {
+    let mut db = Database::default();
+    Prefix::new(&db, "hello".to_string());
+
+    assert_eq!(greeting(&db, "world".to_string()), "hello world");
+    assert_eq!(
+        repeated(&db, (2, "world".to_string())),
+        "hello world, hello world"
+    );
+    assert_eq!(repeated(&db, (1, "salsa".to_string())), "hello salsa");
+    db.assert_logs(expect![[r#"
+        [
+            "greeting(\"world\")",
+            "repeated((2, \"world\"))",
+            "repeated((1, \"salsa\"))",
+            "greeting(\"salsa\")",
+        ]"#]]);
+
+    // Equal keys are interned into the same id: the memoized values are reused.
+    assert_eq!(
+        repeated(&db, (2, "world".to_string())),
+        "hello world, hello world"
+    );
+    assert_eq!(greeting(&db, "salsa".to_string()), "hello salsa");
+    db.assert_logs(expect!["[]"]);
+
+    Prefix::get(&db).set_text(&mut db).to("goodbye".to_string());
+    assert_eq!(
+        repeated(&db, (2, "world".to_string())),
+        "goodbye world, goodbye world"
+    );
+    db.assert_logs(expect![[r#"
+        [
+            "greeting(\"world\")",
+            "repeated((2, \"world\"))",
+        ]"#]]);
+}
@@ -0,0 +1,103 @@
+//! Test that a `tracked` fn with `return_deref` returns a reference
+//! to the `Deref::Target` of its value (e.g. `&[T]` for a `Vec<T>`),
+//! named in its signature rather than projected from the value type.
+
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, digits, text, shape, boxed_text, MyInput_doubled);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input(jar = Jar)]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::tracked(jar = Jar, return_deref)]
+fn digits(db: &dyn Db, input: MyInput) -> Vec<u32> {
+    input
+        .field(db)
+        .to_string()
+        .chars()
+        .map(|c| c.to_digit(10).unwrap())
+        .collect()
+}
+
+#[salsa::tracked(jar = Jar, return_deref)]
+fn text(db: &dyn Db, input: MyInput) -> String {
+    format!("field = {}", input.field(db))
+}
+
+trait Shape: std::fmt::Debug {
+    fn area(&self) -> u32;
+}
+
+#[derive(Debug)]
+struct Square(u32);
+
+impl Shape for Square {
+    fn area(&self) -> u32 {
+        self.0 * self.0
+    }
+}
+
+#[salsa::tracked(jar = Jar, return_deref, no_eq)]
+fn shape(db: &dyn Db, input: MyInput) -> Box<dyn Shape + Send + Sync> {
+    Box::new(Square(input.field(db)))
+}
+
+#[salsa::tracked(jar = Jar, return_deref = str)]
+fn boxed_text(db: &dyn Db, input: MyInput) -> Box<String> {
+    Box::new(text(db, input).to_uppercase())
+}
+
+#[salsa::tracked(jar = Jar)]
+impl MyInput {
+    #[salsa::tracked(return_deref)]
+    fn doubled(self, db: &dyn Db) -> Vec<u32> {
+        digits(db, self).iter().map(|d| d * 2).collect()
+    }
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+#[test]
+fn execute() {
+    let db = Database::default();
+    let input = MyInput::new(&db, 123);
+
+    let d: &[u32] = digits(&db, input);
+    assert_eq!(d, [1, 2, 3]);
+
+    let t: &str = text(&db, input);
+    assert_eq!(t, "field = 123");
+
+    let s: &dyn Shape = shape(&db, input);
+    assert_eq!(s.area(), 15129);
+
+    let b: &str = boxed_text(&db, input);
+    assert_eq!(b, "FIELD = 123");
+
+    let doubled: &[u32] = input.doubled(&db);
+    assert_eq!(doubled, [2, 4, 6]);
+}
+
+#[test]
+fn signatures() {
+    // The functions are generic over the lifetime of the database, which
+    // these function pointers name.
+    let _: for<'db> fn(&'db dyn Db, MyInput) -> &'db [u32] = digits;
+    let _: for<'db> fn(&'db dyn Db, MyInput) -> &'db str = text;
+    let _: for<'db> fn(&'db dyn Db, MyInput) -> &'db (dyn Shape + Send + Sync) = shape;
+    let _: for<'db> fn(&'db dyn Db, MyInput) -> &'db str = boxed_text;
+    let _: for<'db> fn(MyInput, &'db dyn Db) -> &'db [u32] = MyInput::doubled;
+}
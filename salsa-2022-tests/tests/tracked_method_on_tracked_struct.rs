@@ -0,0 +1,115 @@
+//! Test that tracked methods can be defined on tracked structs, so that
+//! the entity is the `self` argument, and that they are memoized like
+//! tracked functions.
+
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(
+    MyInput,
+    Function,
+    Function_signature,
+    Function_parameter,
+    functions,
+);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::input]
+struct MyInput {
+    #[return_ref]
+    source: String,
+}
+
+#[salsa::tracked]
+struct Function {
+    #[id]
+    name: String,
+    #[return_ref]
+    parameters: Vec<String>,
+}
+
+#[salsa::tracked]
+impl Function {
+    #[salsa::tracked(return_ref)]
+    fn signature(self, db: &dyn Db) -> String {
+        db.push_log(format!("signature({})", self.name(db)));
+        format!("fn {}({})", self.name(db), self.parameters(db).join(", "))
+    }
+
+    #[salsa::tracked]
+    fn parameter(self, db: &dyn Db, index: usize) -> Option<String> {
+        db.push_log(format!("parameter({}, {})", self.name(db), index));
+        self.parameters(db).get(index).cloned()
+    }
+}
+
+/// Parses lines like `name a b c` into functions.
+#[salsa::tracked]
+fn functions(db: &dyn Db, input: MyInput) -> Vec<Function> {
+    input
+        .source(db)
+        .lines()
+        .map(|line| {
+            let mut words = line.split_whitespace().map(String::from);
+            let name = words.next().unwrap();
+            Function::new(db, name, words.collect())
+        })
+        .collect()
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn execute() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, "f a b\ng".to_string());
+
+    let signatures: Vec<_> = functions(&db, input)
+        .into_iter()
+        .map(|function| function.signature(&db).clone())
+        .collect();
+    assert_eq!(signatures, ["fn f(a, b)", "fn g()"]);
+
+    let f = functions(&db, input)[0];
+    assert_eq!(f.parameter(&db, 1), Some("b".to_string()));
+    assert_eq!(f.parameter(&db, 1), Some("b".to_string()));
+    assert_eq!(f.parameter(&db, 2), None);
+    db.assert_logs(expect![[r#"
+        [
+            "signature(f)",
+            "signature(g)",
+            "parameter(f, 1)",
+            "parameter(f, 2)",
+        ]"#]]);
+
+    // Only the methods of the entity whose fields changed are executed again.
+    input.set_source(&mut db).to("f a c\ng".to_string());
+    let f = functions(&db, input)[0];
+    assert_eq!(f.signature(&db), "fn f(a, c)");
+    assert_eq!(functions(&db, input)[1].signature(&db), "fn g()");
+    assert_eq!(f.parameter(&db, 1), Some("c".to_string()));
+    db.assert_logs(expect![[r#"
+        [
+            "signature(f)",
+            "parameter(f, 1)",
+        ]"#]]);
+}
@@ -0,0 +1,61 @@
+//! Test that `specify` works on a `tracked` method
+//! whose receiver is a tracked struct.
+#![allow(warnings)]
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, MyTracked, tracked_fn, MyTracked_tracked_method);
+
+trait Db: salsa::DbWithJar<Jar> {}
+
+#[salsa::input(jar = Jar)]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::tracked(jar = Jar)]
+struct MyTracked {
+    field: u32,
+}
+
+#[salsa::tracked(jar = Jar)]
+fn tracked_fn(db: &dyn Db, input: MyInput) -> MyTracked {
+    let t = MyTracked::new(db, input.field(db) * 2);
+    if input.field(db) != 0 {
+        MyTracked_tracked_method::specify(db, t, 2222);
+    }
+    t
+}
+
+#[salsa::tracked(jar = Jar)]
+impl MyTracked {
+    #[salsa::tracked(specify)]
+    fn tracked_method(self, db: &dyn Db) -> u32 {
+        self.field(db)
+    }
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+#[test]
+fn execute_when_specified() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 22);
+    let tracked = tracked_fn(&db, input);
+    assert_eq!(tracked.tracked_method(&db), 2222);
+}
+
+#[test]
+fn execute_when_not_specified() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 0);
+    let tracked = tracked_fn(&db, input);
+    assert_eq!(tracked.tracked_method(&db), 0);
+}
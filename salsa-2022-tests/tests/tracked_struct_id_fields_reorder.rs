@@ -0,0 +1,82 @@
+//! Test that tracked structs are matched up by their `#[id]` fields
+//! across re-executions of the query that creates them, so that they
+//! keep their ids when they are created in a different order and the
+//! queries reading them are not re-executed.
+
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, Item, items, item_len);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::input]
+struct MyInput {
+    #[return_ref]
+    text: String,
+}
+
+#[salsa::tracked]
+struct Item {
+    #[id]
+    #[return_ref]
+    name: String,
+}
+
+#[salsa::tracked]
+fn items(db: &dyn Db, input: MyInput) -> Vec<Item> {
+    input
+        .text(db)
+        .split_whitespace()
+        .map(|name| Item::new(db, name.to_string()))
+        .collect()
+}
+
+#[salsa::tracked]
+fn item_len(db: &dyn Db, item: Item) -> usize {
+    db.push_log(format!("item_len({})", item.name(db)));
+    item.name(db).len()
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn reorder() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, "foo bar".to_string());
+
+    let old_items = items(&db, input);
+    let lens: Vec<_> = old_items.iter().map(|&item| item_len(&db, item)).collect();
+    assert_eq!(lens, [3, 3]);
+    db.assert_logs(expect![[r#"
+        [
+            "item_len(foo)",
+            "item_len(bar)",
+        ]"#]]);
+
+    input.set_text(&mut db).to("bar foo".to_string());
+    let new_items = items(&db, input);
+    assert_eq!(new_items, [old_items[1], old_items[0]]);
+
+    let lens: Vec<_> = new_items.iter().map(|&item| item_len(&db, item)).collect();
+    assert_eq!(lens, [3, 3]);
+    db.assert_logs(expect!["[]"]);
+}
@@ -0,0 +1,109 @@
+//! Test `Database::transaction`, which makes several writes
+//! happen in a single new revision.
+
+use salsa::Database as _;
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, sum);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::input]
+struct MyInput {
+    a: u32,
+    b: u32,
+}
+
+#[salsa::tracked]
+fn sum(db: &dyn Db, input: MyInput) -> u32 {
+    db.push_log(format!("sum({}, {})", input.a(db), input.b(db)));
+    input.a(db) + input.b(db)
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+fn revision(db: &Database) -> String {
+    format!("{:?}", db.attach_revision().revision())
+}
+
+#[test]
+fn single_revision() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 1, 2);
+    assert_eq!(sum(&db, input), 3);
+    assert_eq!(revision(&db), "R1");
+
+    db.transaction(|db| {
+        input.set_a(db).to(10);
+        input.set_b(db).to(20);
+    });
+    assert_eq!(revision(&db), "R2");
+    assert_eq!(sum(&db, input), 30);
+
+    // Without a transaction, each write starts a new revision.
+    input.set_a(&mut db).to(100);
+    input.set_b(&mut db).to(200);
+    assert_eq!(revision(&db), "R4");
+    assert_eq!(sum(&db, input), 300);
+
+    db.assert_logs(expect![[r#"
+        [
+            "sum(1, 2)",
+            "sum(10, 20)",
+            "sum(100, 200)",
+        ]"#]]);
+}
+
+#[test]
+fn read_splits_transaction() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 1, 2);
+
+    let read_in_between = db.transaction(|db| {
+        input.set_a(db).to(10);
+        let read = sum(db, input);
+        input.set_b(db).to(20);
+        read
+    });
+    assert_eq!(read_in_between, 12);
+
+    // The write after the read started another revision,
+    // so the value read in between is not reused.
+    assert_eq!(revision(&db), "R3");
+    assert_eq!(sum(&db, input), 30);
+}
+
+#[test]
+fn nested() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 1, 2);
+
+    db.transaction(|db| {
+        input.set_a(db).to(10);
+        db.transaction(|db| {
+            input.set_b(db).to(20);
+        });
+        input.set_a(db).to(30);
+    });
+    assert_eq!(revision(&db), "R2");
+    assert_eq!(sum(&db, input), 50);
+}
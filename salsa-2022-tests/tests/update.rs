@@ -0,0 +1,138 @@
+//! Test the `update` option: the new value of a tracked function is
+//! merged into the old one with `#[derive(salsa::Update)]`, so that the
+//! unchanged parts keep their allocation and equal values are backdated.
+
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, parse, item_count);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::input]
+struct MyInput {
+    #[return_ref]
+    text: String,
+}
+
+#[derive(Debug, PartialEq, Eq, salsa::Update)]
+struct Ast {
+    items: Vec<Item>,
+}
+
+#[derive(Debug, PartialEq, Eq, salsa::Update)]
+enum Item {
+    Fn { name: String, params: Vec<String> },
+    Comment(String),
+}
+
+#[salsa::tracked(return_ref, update)]
+fn parse(db: &dyn Db, input: MyInput) -> Ast {
+    db.push_log("parse".to_string());
+    let items = input
+        .text(db)
+        .lines()
+        .map(|line| match line.strip_prefix("//") {
+            Some(comment) => Item::Comment(comment.trim().to_string()),
+            None => {
+                let mut words = line.split_whitespace().map(String::from);
+                Item::Fn {
+                    name: words.next().unwrap(),
+                    params: words.collect(),
+                }
+            }
+        })
+        .collect();
+    Ast { items }
+}
+
+#[salsa::tracked]
+fn item_count(db: &dyn Db, input: MyInput) -> usize {
+    db.push_log("item_count".to_string());
+    parse(db, input).items.len()
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+/// Returns the addresses of the allocations of the items and of the first item's name.
+fn addresses(db: &Database, input: MyInput) -> (usize, usize) {
+    let ast = parse(db, input);
+    let name = match &ast.items[0] {
+        Item::Fn { name, .. } => name.as_ptr() as usize,
+        Item::Comment(_) => panic!("expected a fn"),
+    };
+    (ast.items.as_ptr() as usize, name)
+}
+
+#[test]
+fn execute() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, "main\n// helper\nhelper x y".to_string());
+
+    assert_eq!(item_count(&db, input), 3);
+    let old_addresses = addresses(&db, input);
+    db.assert_logs(expect![[r#"
+        [
+            "item_count",
+            "parse",
+        ]"#]]);
+
+    // The value is equal, so it is backdated and the allocations are kept.
+    input
+        .set_text(&mut db)
+        .to("main\n//   helper\nhelper   x y".to_string());
+    assert_eq!(item_count(&db, input), 3);
+    assert_eq!(addresses(&db, input), old_addresses);
+    db.assert_logs(expect![[r#"
+        [
+            "parse",
+        ]"#]]);
+
+    // A nested part changed: the other parts keep their allocation.
+    input
+        .set_text(&mut db)
+        .to("main\n// helper\nhelper z".to_string());
+    assert_eq!(item_count(&db, input), 3);
+    assert_eq!(addresses(&db, input), old_addresses);
+    assert_eq!(
+        parse(&db, input).items[2],
+        Item::Fn {
+            name: "helper".to_string(),
+            params: vec!["z".to_string()],
+        }
+    );
+    db.assert_logs(expect![[r#"
+        [
+            "parse",
+            "item_count",
+        ]"#]]);
+
+    // A variant changed.
+    input.set_text(&mut db).to("// main\n// helper".to_string());
+    assert_eq!(item_count(&db, input), 2);
+    assert_eq!(
+        parse(&db, input).items,
+        [
+            Item::Comment("main".to_string()),
+            Item::Comment("helper".to_string())
+        ]
+    );
+}
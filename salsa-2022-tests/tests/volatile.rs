@@ -0,0 +1,92 @@
+//! Test the `volatile` option: tracked functions reading state unknown
+//! to salsa are executed again in every new revision, and their callers
+//! only when the value changed.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use salsa_2022_tests::{HasLogger, Logger};
+
+use expect_test::expect;
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, minutes, greeting);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {
+    /// The current time in seconds; not tracked by salsa.
+    fn clock(&self) -> u64;
+}
+
+#[salsa::input]
+struct MyInput {
+    name: String,
+    unrelated: u32,
+}
+
+#[salsa::tracked(volatile)]
+fn minutes(db: &dyn Db, input: MyInput) -> u64 {
+    db.push_log(format!("minutes({})", input.name(db)));
+    db.clock() / 60
+}
+
+#[salsa::tracked]
+fn greeting(db: &dyn Db, input: MyInput) -> String {
+    db.push_log(format!("greeting({})", input.name(db)));
+    format!("hello {} at minute {}", input.name(db), minutes(db, input))
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+    clock: AtomicU64,
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {
+    fn clock(&self) -> u64 {
+        self.clock.load(Ordering::SeqCst)
+    }
+}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn execute() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, "world".to_string(), 0);
+
+    assert_eq!(greeting(&db, input), "hello world at minute 0");
+    db.clock.store(30, Ordering::SeqCst);
+    assert_eq!(greeting(&db, input), "hello world at minute 0");
+    db.assert_logs(expect![[r#"
+        [
+            "greeting(world)",
+            "minutes(world)",
+        ]"#]]);
+
+    // In a new revision, `minutes` is executed again even though its inputs
+    // did not change; its value did not change either, so it is backdated.
+    input.set_unrelated(&mut db).to(1);
+    assert_eq!(greeting(&db, input), "hello world at minute 0");
+    db.assert_logs(expect![[r#"
+        [
+            "minutes(world)",
+        ]"#]]);
+
+    // Once the value changes, its callers are executed again.
+    db.clock.store(90, Ordering::SeqCst);
+    input.set_unrelated(&mut db).to(2);
+    assert_eq!(greeting(&db, input), "hello world at minute 1");
+    db.assert_logs(expect![[r#"
+        [
+            "minutes(world)",
+            "greeting(world)",
+        ]"#]]);
+}
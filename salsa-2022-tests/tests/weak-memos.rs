@@ -0,0 +1,85 @@
+//! Test that `Storage::release_weak_memos` drops the values of the functions
+//! with the `weak` option, which are computed again only when requested,
+//! while the functions that depend on them are not executed again.
+
+use expect_test::expect;
+use salsa_2022_tests::{HasLogger, Logger};
+use test_log::test;
+
+#[salsa::jar(db = Db)]
+struct Jar(MyInput, body, body_len, other);
+
+trait Db: salsa::DbWithJar<Jar> + HasLogger {}
+
+#[salsa::input]
+struct MyInput {
+    field: u32,
+}
+
+#[salsa::tracked(weak, return_ref)]
+fn body(db: &dyn Db, input: MyInput) -> Vec<u32> {
+    db.push_log(format!("body({})", input.field(db)));
+    vec![input.field(db); 1024]
+}
+
+#[salsa::tracked]
+fn body_len(db: &dyn Db, input: MyInput) -> usize {
+    db.push_log(format!("body_len({})", input.field(db)));
+    body(db, input).len()
+}
+
+#[salsa::tracked]
+fn other(db: &dyn Db, input: MyInput) -> u32 {
+    db.push_log(format!("other({})", input.field(db)));
+    input.field(db) + 1
+}
+
+#[salsa::db(Jar)]
+#[derive(Default)]
+struct Database {
+    storage: salsa::Storage<Self>,
+    logger: Logger,
+}
+
+impl Database {
+    fn release_weak_memos(&mut self) {
+        self.storage.release_weak_memos();
+    }
+}
+
+impl salsa::Database for Database {}
+
+impl Db for Database {}
+
+impl HasLogger for Database {
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+#[test]
+fn release_weak_memos() {
+    let mut db = Database::default();
+    let input = MyInput::new(&db, 1);
+    assert_eq!(body_len(&db, input), 1024);
+    assert_eq!(other(&db, input), 2);
+    db.assert_logs(expect![[r#"
+        [
+            "body_len(1)",
+            "body(1)",
+            "other(1)",
+        ]"#]]);
+
+    // Only the value of `body` is dropped, and `body_len` can still be validated.
+    db.release_weak_memos();
+    assert_eq!(body_len(&db, input), 1024);
+    assert_eq!(other(&db, input), 2);
+    db.assert_logs(expect!["[]"]);
+
+    // `body` is executed again when requested.
+    assert_eq!(body(&db, input).len(), 1024);
+    db.assert_logs(expect![[r#"
+        [
+            "body(1)",
+        ]"#]]);
+}
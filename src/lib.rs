@@ -66,11 +66,31 @@ pub trait Database: plumbing::DatabaseOps {
     /// Cancellation will automatically be triggered by salsa on any query
     /// invocation.
     ///
+    /// Inside [`QueryTable::fetch_checked`] this method does not unwind;
+    /// cancellation is reported by the `Err` result of `fetch_checked` instead.
+    ///
     /// This method should not be overridden by `Database` implementors. A
     /// `salsa_event` is emitted when this method is called, so that should be
     /// used instead.
     #[inline]
     fn unwind_if_cancelled(&self) {
+        if self.is_cancelled() && self.salsa_runtime().unwinds_on_cancellation() {
+            self.salsa_runtime().unwind_cancelled();
+        }
+    }
+
+    /// Returns true if the current revision is cancelled, without unwinding.
+    ///
+    /// This is the cooperative counterpart of [`Database::unwind_if_cancelled`],
+    /// for use in builds where unwinding is not available (e.g. `panic = "abort"`)
+    /// or across FFI boundaries. A query that observes cancellation should return
+    /// as quickly as possible; its result will not be reused in later revisions.
+    ///
+    /// This method should not be overridden by `Database` implementors. A
+    /// `salsa_event` is emitted when this method is called, so that should be
+    /// used instead.
+    #[inline]
+    fn is_cancelled(&self) -> bool {
         let runtime = self.salsa_runtime();
         self.salsa_event(Event {
             runtime_id: runtime.id(),
@@ -80,13 +100,17 @@ pub trait Database: plumbing::DatabaseOps {
         let current_revision = runtime.current_revision();
         let pending_revision = runtime.pending_revision();
         log::debug!(
-            "unwind_if_cancelled: current_revision={:?}, pending_revision={:?}",
+            "is_cancelled: current_revision={:?}, pending_revision={:?}",
             current_revision,
             pending_revision
         );
         if pending_revision > current_revision {
-            runtime.unwind_cancelled();
+            // Whatever the active query returns now is not to be trusted,
+            // so make sure it is re-executed in the next revision.
+            runtime.report_untracked_read();
+            return true;
         }
+        false
     }
 
     /// Gives access to the underlying salsa runtime.
@@ -504,6 +528,30 @@ where
         self.storage.fetch(self.db, &key)
     }
 
+    /// Like [`QueryTable::get`], but returns `Err(Cancelled)` instead of
+    /// unwinding if the current revision is cancelled.
+    ///
+    /// Cancellation is checked before the query starts. If the revision is
+    /// cancelled while the query is executing, the query and the queries it
+    /// calls run to completion without unwinding (so this also works with
+    /// `panic = "abort"`), and `Err(Cancelled::PendingWrite)` is returned
+    /// once they are done. Queries that observed the cancellation are
+    /// re-executed in the next revision.
+    ///
+    /// Blocking on a query that panicked in another thread still unwinds with
+    /// [`Cancelled::PropagatedPanic`].
+    pub fn fetch_checked(&self, key: Q::Key) -> Result<Q::Value, Cancelled> {
+        if self.db.is_cancelled() {
+            return Err(Cancelled::PendingWrite);
+        }
+        let runtime = self.db.salsa_runtime();
+        let value = runtime.without_unwinding_on_cancellation(|| self.get(key));
+        if runtime.pending_revision() > runtime.current_revision() {
+            return Err(Cancelled::PendingWrite);
+        }
+        Ok(value)
+    }
+
     /// Completely clears the storage for this query.
     ///
     /// This method breaks internal invariants of salsa, so any further queries
@@ -146,6 +146,35 @@ impl Runtime {
         self.shared_state.pending_revision.load()
     }
 
+    /// False while a [`QueryTable::fetch_checked`](`crate::QueryTable::fetch_checked`)
+    /// is executing on this handle.
+    pub(crate) fn unwinds_on_cancellation(&self) -> bool {
+        self.local_state.unwind_on_cancellation()
+    }
+
+    /// Invokes `op` such that [`Database::unwind_if_cancelled`](`crate::Database::unwind_if_cancelled`)
+    /// does not unwind when the current revision is cancelled.
+    ///
+    /// This is sound because the pending write waits for this handle to
+    /// finish, so values computed by `op` are still valid for the current
+    /// revision; queries that observed the cancellation record an untracked
+    /// read and are re-executed in the next revision.
+    pub(crate) fn without_unwinding_on_cancellation<R>(&self, op: impl FnOnce() -> R) -> R {
+        struct Restore<'me>(&'me LocalState, bool);
+
+        impl Drop for Restore<'_> {
+            fn drop(&mut self) {
+                self.0.set_unwind_on_cancellation(self.1);
+            }
+        }
+
+        let _restore = Restore(
+            &self.local_state,
+            self.local_state.set_unwind_on_cancellation(false),
+        );
+        op()
+    }
+
     #[cold]
     pub(crate) fn unwind_cancelled(&self) {
         self.report_untracked_read();
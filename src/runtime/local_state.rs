@@ -5,7 +5,7 @@ use crate::runtime::ActiveQuery;
 use crate::runtime::Revision;
 use crate::Cycle;
 use crate::DatabaseKeyIndex;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::sync::Arc;
 
 use super::StampedValue;
@@ -25,6 +25,10 @@ pub(super) struct LocalState {
     /// Unwinding note: pushes onto this vector must be popped -- even
     /// during unwinding.
     query_stack: RefCell<Option<Vec<ActiveQuery>>>,
+
+    /// False while a [`QueryTable::fetch_checked`](`crate::QueryTable::fetch_checked`)
+    /// is executing on this handle, in which case cancellation does not unwind.
+    unwind_on_cancellation: Cell<bool>,
 }
 
 /// Summarizes "all the inputs that a query used"
@@ -67,11 +71,21 @@ impl Default for LocalState {
     fn default() -> Self {
         LocalState {
             query_stack: RefCell::new(Some(Vec::new())),
+            unwind_on_cancellation: Cell::new(true),
         }
     }
 }
 
 impl LocalState {
+    pub(super) fn unwind_on_cancellation(&self) -> bool {
+        self.unwind_on_cancellation.get()
+    }
+
+    /// Sets whether cancellation unwinds, returning the previous setting.
+    pub(super) fn set_unwind_on_cancellation(&self, unwind: bool) -> bool {
+        self.unwind_on_cancellation.replace(unwind)
+    }
+
     #[inline]
     pub(super) fn push_query(&self, database_key_index: DatabaseKeyIndex) -> ActiveQueryGuard<'_> {
         let mut query_stack = self.query_stack.borrow_mut();
@@ -1,6 +1,6 @@
-use crate::setup::{ParDatabase, ParDatabaseImpl};
+use crate::setup::{CancellationFlag, Knobs, ParDatabase, ParDatabaseImpl, Sum3Query, SumQuery};
 use crate::signal::Signal;
-use salsa::{Database, ParallelDatabase};
+use salsa::{Cancelled, Database, ParallelDatabase};
 use std::{
     panic::{catch_unwind, AssertUnwindSafe},
     sync::Arc,
@@ -56,3 +56,89 @@ fn in_par_get_set_cancellation() {
     let c = thread2.join().unwrap();
     assert_eq!(c, 2);
 }
+
+/// Like `in_par_get_set_cancellation`, but observes cancellation
+/// cooperatively through `is_cancelled` and `fetch_checked`
+/// rather than by unwinding.
+#[test]
+fn in_par_get_set_cancellation_without_unwinding() {
+    let mut db = ParDatabaseImpl::default();
+
+    db.set_input('a', 1);
+
+    let signal = Arc::new(Signal::default());
+
+    let thread1 = std::thread::spawn({
+        let db = db.snapshot();
+        let signal = signal.clone();
+        move || {
+            // Not yet cancelled: queries can be fetched normally.
+            assert!(!db.is_cancelled());
+            assert_eq!(SumQuery.in_db(&*db).fetch_checked("a").unwrap(), 1);
+
+            // Signal other thread to proceed.
+            signal.signal(1);
+
+            // Wait for other thread to signal cancellation
+            while !db.is_cancelled() {
+                std::thread::yield_now();
+            }
+
+            let result = SumQuery.in_db(&*db).fetch_checked("aa");
+            assert!(matches!(result, Err(Cancelled::PendingWrite { .. })));
+        }
+    });
+
+    let thread2 = std::thread::spawn({
+        move || {
+            // Wait until thread 1 has asserted that they are not cancelled
+            // before we invoke `set.`
+            signal.wait_for(1);
+
+            // This will block until thread1 drops the revision lock.
+            let value = db.remove_input('a') + 1;
+            db.set_input('a', value);
+
+            db.sum("aa")
+        }
+    });
+
+    thread1.join().unwrap();
+
+    let c = thread2.join().unwrap();
+    assert_eq!(c, 4);
+}
+
+/// Like `in_par_get_set_cancellation_without_unwinding`, but the revision
+/// is cancelled while `fetch_checked` executes: the queries run to
+/// completion instead of unwinding, and the result is an error.
+#[test]
+fn in_par_get_set_cancellation_during_fetch_checked() {
+    let mut db = ParDatabaseImpl::default();
+
+    db.set_input('a', 1);
+
+    let thread1 = std::thread::spawn({
+        let db = db.snapshot();
+        move || {
+            // `sum` signals stage 1 and then waits for cancellation.
+            db.knobs().sum_signal_on_entry.set(1);
+            db.knobs()
+                .sum_wait_for_cancellation
+                .set(CancellationFlag::Continue);
+            Sum3Query.in_db(&*db).fetch_checked("a")
+        }
+    });
+
+    // Wait until `sum` is executing before cancelling it.
+    db.wait_for(1);
+
+    // This will block until thread1 drops the revision lock.
+    db.set_input('a', 2);
+
+    let result = thread1.join().unwrap();
+    assert!(matches!(result, Err(Cancelled::PendingWrite { .. })));
+
+    // The values computed after the cancellation are not reused.
+    assert_eq!(db.sum3("a"), 2);
+}
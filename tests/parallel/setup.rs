@@ -61,6 +61,9 @@ impl<T> WithValue<T> for Cell<T> {
 pub(crate) enum CancellationFlag {
     Down,
     Panic,
+    /// Wait for cancellation, then carry on with `unwind_if_cancelled`
+    /// (which only returns when called from `fetch_checked`).
+    Continue,
 }
 
 impl Default for CancellationFlag {
@@ -128,6 +131,13 @@ fn sum(db: &dyn ParDatabase, key: &'static str) -> usize {
                 std::thread::yield_now();
             }
         }
+        CancellationFlag::Continue => {
+            log::debug!("waiting for cancellation");
+            while !db.is_cancelled() {
+                std::thread::yield_now();
+            }
+            db.unwind_if_cancelled();
+        }
     }
 
     db.wait_for(db.knobs().sum_wait_for_on_exit.get());